@@ -1,7 +1,7 @@
 extern crate wasmparser;
 
 use std::env;
-use wasmparser::parallelize;
+use wasmparser::pfc::prelude::*;
 
 fn main() {
     let args = env::args().collect::<Vec<_>>();
@@ -10,12 +10,23 @@ fn main() {
         return;
     }
 
-    let mut mapper = parallelize::new_mapper();
+    let mut mapper = new_mapper();
+
+    // with the terminal interface, every function is expanded and the nodes to lower are chosen afterwards
+    #[cfg(feature = "tui")]
+    mapper.set_observer(Box::new(SilentObserver));
 
     println!("Analyzing {}...", args[1]);
 
     let buf: Vec<u8> = mapper.read_wasm(&args[1]).unwrap();
     let nodes = mapper.map(buf);
 
+    #[cfg(feature = "tui")]
+    {
+        let constraints = wasmparser::tui::explore(nodes, &mut ConsoleObserver).unwrap();
+        println!("Lowered {} nodes", constraints.len());
+    }
+
+    #[cfg(not(feature = "tui"))]
     println!("{:#x?}", nodes);
 }
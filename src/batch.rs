@@ -0,0 +1,202 @@
+//! # Batch
+//! Reports on a corpus of modules mapped one after another, as `Mapper::map_directory` does for the files of a
+//! directory whose names match a glob pattern. Each module gets a report of its own, holding how much of it
+//! was mapped, what mapping warned about, the speedup its parallelization bounds and its functions ranked by
+//! annealability; a file that can't be read or mapped is reported along with why. The aggregate ranks the
+//! modules by their most annealable function and picks the best candidates for annealing across all of them
+
+use std::cmp::Ordering;
+use std::fmt;
+use parallelize::{Annealability, SpeedupEstimate};
+
+// the qubits of the device functions are scored against, an Advantage system's working graph rounded down
+pub const DEVICE_QUBITS:usize = 5000;
+
+
+/// A module report summarizes how one module of a batch was mapped
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleReport {
+    pub path: String, // the file the module was read from
+    pub functions: usize, // how many functions were mapped
+    pub unsupported: usize, // how many unsupported operators mapping encountered
+    pub warnings: usize, // how many warnings mapping gave
+    pub speedup: SpeedupEstimate, // the bound on the speedup from parallelizing the module
+    pub annealability: Vec<Annealability>, // the module's functions, most annealable first
+    pub error: Option<String> // why the module couldn't be read or mapped, or was only mapped in part, if it was
+}
+
+
+impl ModuleReport {
+    // reports a module that couldn't be mapped at all, with the reason
+    pub fn failed(path:&str, error:String) -> ModuleReport {
+        ModuleReport {
+            path: path.to_string(),
+            functions: 0,
+            unsupported: 0,
+            warnings: 0,
+            speedup: SpeedupEstimate { work: 0, critical_path: 0 },
+            annealability: Vec::new(),
+            error: Some(error)
+        }
+    }
+
+    // returns the module's most annealable function, unless it has none
+    pub fn best(&self) -> Option<&Annealability> {
+        self.annealability.first()
+    }
+
+    // returns the score of the module's most annealable function, which is zero without functions
+    pub fn best_score(&self) -> f64 {
+        self.best().map_or(0.0, |function| function.score)
+    }
+}
+
+
+// summarizes the module on a line, e.g. `kernels/add.wasm: 3 functions, best function 0 with score 0.74,
+// speedup 1.50, 2 warnings`
+impl fmt::Display for ModuleReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} functions", self.path, self.functions)?;
+        if let Some(best) = self.best() {
+            write!(f, ", best function {} with score {:.2}", best.id, best.score)?;
+        }
+        write!(f, ", speedup {:.2}, {} warnings", self.speedup.speedup(), self.warnings)?;
+        if self.unsupported > 0 {
+            write!(f, ", {} unsupported operators", self.unsupported)?;
+        }
+        if let Some(ref error) = self.error {
+            write!(f, ", error: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// A batch report holds the reports of the modules mapped together,
+/// which can be compared by the functions worth annealing in each
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchReport {
+    pub device_qubits: usize, // the qubits of the device the functions were scored against
+    pub modules: Vec<ModuleReport> // the modules' reports, in the order of their file names
+}
+
+
+impl BatchReport {
+    // returns the modules with their most annealable functions first, the modules without functions last
+    pub fn ranking(&self) -> Vec<&ModuleReport> {
+        let mut ranking:Vec<&ModuleReport> = self.modules.iter().collect();
+        ranking.sort_by(|a, b| b.best_score().partial_cmp(&a.best_score()).unwrap_or(Ordering::Equal));
+        ranking
+    }
+
+    // returns up to the given number of the most annealable functions across every module, best first, each
+    // with the file it is in
+    pub fn candidates(&self, count:usize) -> Vec<(&str, &Annealability)> {
+        let mut candidates:Vec<(&str, &Annealability)> = self.modules.iter()
+            .flat_map(|module| module.annealability.iter().map(move |function| (&module.path[..], function)))
+            .collect();
+        candidates.sort_by(|a, b| b.1.score.partial_cmp(&a.1.score).unwrap_or(Ordering::Equal));
+        candidates.truncate(count);
+        candidates
+    }
+
+    // returns the modules that couldn't be read or mapped in whole
+    pub fn failures(&self) -> Vec<&ModuleReport> {
+        self.modules.iter().filter(|module| module.error.is_some()).collect()
+    }
+
+    // returns how many functions were mapped across every module
+    pub fn functions(&self) -> usize {
+        self.modules.iter().map(|module| module.functions).sum()
+    }
+}
+
+
+// summarizes the batch with a line per module, best first, e.g. `3 modules, 7 functions, 1 failed` followed by
+// `kernels/add.wasm: 3 functions, best function 0 with score 0.74, speedup 1.50, 2 warnings`
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} modules, {} functions, {} failed", self.modules.len(), self.functions(), self.failures().len())?;
+        for module in self.ranking() {
+            write!(f, "\n{}", module)?;
+        }
+        Ok(())
+    }
+}
+
+
+// checks whether a file name matches a glob pattern, in which `*` stands for any run of characters and `?`
+// for any single one
+pub fn glob_match(pattern:&str, name:&str) -> bool {
+    let pattern:Vec<char> = pattern.chars().collect();
+    let name:Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+
+    // the last star seen, with where in the name its run started, is widened by a character whenever the rest
+    // of the pattern fails to match
+    let mut star:Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            star = Some((star_p, star_n + 1));
+            p = star_p + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+
+#[cfg(all(test, feature = "std-io"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use parallelize::{new_mapper, SilentObserver};
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("*.wasm", "add.wasm") && glob_match("*", "") && glob_match("a?d.*", "add.wat"));
+        assert!(glob_match("*_kernel_*.wasm", "conv_kernel_3x3.wasm"));
+        assert!(!glob_match("*.wasm", "add.wat") && !glob_match("a?d", "ad") && !glob_match("", "add"));
+    }
+
+    #[test]
+    fn map_directory() {
+        let directory = std::env::temp_dir().join("wasm-pfc-batch");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("inline.wasm"), &include_bytes!("../tests/parallelization/inline.wasm")[..]).unwrap();
+        fs::write(directory.join("add.wasm"), &include_bytes!("../tests/parallelization/add.wasm")[..]).unwrap();
+        fs::write(directory.join("broken.wasm"), b"\0asm\x01\0\0\0\x01\xff").unwrap();
+        fs::write(directory.join("notes.txt"), "not a module").unwrap();
+
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let report = mapper.map_directory(directory.to_str().unwrap(), "*.wasm").unwrap();
+        let names:Vec<&str> = report.modules.iter().map(|module| module.path.rsplit('/').next().unwrap()).collect();
+        assert_eq!(names, vec!["add.wasm", "broken.wasm", "inline.wasm"]);
+        assert_eq!(report.device_qubits, DEVICE_QUBITS);
+
+        // the broken module is reported as failed, and ranked below the modules with functions
+        let failures = report.failures();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].path.ends_with("broken.wasm") && failures[0].functions == 0);
+        assert!(report.ranking().last().unwrap().path.ends_with("broken.wasm"));
+
+        // the candidates are ranked across modules, and each is the best of its own module or worse
+        let candidates = report.candidates(3);
+        assert_eq!(candidates.len(), 3);
+        assert!(candidates.windows(2).all(|pair| pair[0].1.score >= pair[1].1.score));
+        let best = report.ranking()[0];
+        assert_eq!(candidates[0], (&best.path[..], best.best().unwrap()));
+        assert!(report.to_string().starts_with(&format!("3 modules, {} functions, 1 failed\n{}", report.functions(), best)));
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
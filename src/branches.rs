@@ -0,0 +1,171 @@
+//! # Branches
+//! Execution profiles of which way a module's branches went, so that the paths through the tree that run
+//! most can be told apart from those that hardly run. An instrumented run of the module, outside of the
+//! mapper, counts how often each if, conditional branch and select was taken and writes the counts as JSON:
+//! `{"branches":[{"function":1,"offset":42,"taken":90,"not_taken":10}]}`, with the function's index and
+//! the offset of the instruction in the WASM file, the way program points name instructions. Mapping with a
+//! profile weighs each recorded condition with its counts, which nodes keep through every pass
+
+use std::collections::BTreeMap;
+#[cfg(feature = "std-io")]
+use std::fs;
+#[cfg(feature = "std-io")]
+use std::io;
+use points::ProgramPoint;
+
+
+/// Branch counts are how often a branch went each way in a profiled
+/// run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BranchCounts {
+    pub taken: u64, // the times the condition was true, entering the if, branching or selecting the first value
+    pub not_taken: u64 // the times it was false
+}
+
+
+impl BranchCounts {
+    pub fn new(taken:u64, not_taken:u64) -> BranchCounts {
+        BranchCounts { taken, not_taken }
+    }
+
+    // returns how many times the branch ran
+    pub fn total(&self) -> u64 {
+        self.taken.saturating_add(self.not_taken)
+    }
+
+    // returns the probability of the branch being taken, unless it never ran
+    pub fn probability(&self) -> Option<f64> {
+        if self.total() == 0 {
+            None
+        } else {
+            Some(self.taken as f64 / self.total() as f64)
+        }
+    }
+}
+
+
+/// A branch profile holds the counts of a module's branches, by the
+/// program points of the instructions
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BranchProfile {
+    branches: BTreeMap<ProgramPoint, BranchCounts> // the counts of each profiled branch
+}
+
+
+impl BranchProfile {
+    pub fn new() -> BranchProfile {
+        BranchProfile::default()
+    }
+
+    // sets the counts of the branch at a program point, replacing any it had
+    pub fn set(&mut self, point:ProgramPoint, counts:BranchCounts) {
+        self.branches.insert(point, counts);
+    }
+
+    // returns the counts of the branch at a program point, if it was profiled
+    pub fn get(&self, point:ProgramPoint) -> Option<BranchCounts> {
+        self.branches.get(&point).cloned()
+    }
+
+    // returns the program points of the profiled branches, in order
+    pub fn points(&self) -> Vec<ProgramPoint> {
+        self.branches.keys().cloned().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.branches.is_empty()
+    }
+
+    // reads a profile from its JSON, unless it is malformed; branches counted more than once add up, saturating
+    // rather than overflowing
+    pub fn from_json(json:&str) -> Option<BranchProfile> {
+        let start = json_value(json, "branches", 0)?;
+        let mut position = start + json[start..].find('[')? + 1;
+        let mut profile = BranchProfile::new();
+        loop {
+            let rest = json[position..].trim_start();
+            position = json.len() - rest.len();
+            if rest.starts_with(']') {
+                return Some(profile);
+            }
+            if rest.starts_with(',') {
+                position += 1;
+                continue;
+            }
+            if !rest.starts_with('{') {
+                return None;
+            }
+            let end = position + rest.find('}')?;
+            let object = &json[position..=end];
+            let point = ProgramPoint::new(json_number(object, "function")? as usize, json_number(object, "offset")? as usize);
+            let (taken, not_taken) = (json_number(object, "taken")?, json_number(object, "not_taken")?);
+            let counts = profile.branches.entry(point).or_default();
+            counts.taken = counts.taken.saturating_add(taken);
+            counts.not_taken = counts.not_taken.saturating_add(not_taken);
+            position = end + 1;
+        }
+    }
+
+    // describes the profile as the JSON from_json reads
+    pub fn to_json(&self) -> String {
+        let branches:Vec<String> = self.branches.iter().map(|(point, counts)| {
+            format!("{{\"function\":{},\"offset\":{},\"taken\":{},\"not_taken\":{}}}", point.function, point.offset, counts.taken, counts.not_taken)
+        }).collect();
+        format!("{{\"branches\":[{}]}}", branches.join(","))
+    }
+
+    // reads a profile written by an instrumented run
+    #[cfg(feature = "std-io")]
+    pub fn load(path:&str) -> io::Result<BranchProfile> {
+        let json = fs::read_to_string(path)?;
+        match BranchProfile::from_json(&json) {
+            Some(profile) => Ok(profile),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a branch profile", path)))
+        }
+    }
+}
+
+
+// returns the position of the value of a key in a JSON object, after its colon, searching from a position
+fn json_value(json:&str, key:&str, from:usize) -> Option<usize> {
+    let quoted = format!("\"{}\"", key);
+    let start = from + json[from..].find(&quoted)? + quoted.len();
+    let colon = json[start..].find(':')?;
+    Some(start + colon + 1)
+}
+
+
+// reads the unsigned JSON number given for a key in a flat JSON object
+fn json_number(object:&str, key:&str) -> Option<u64> {
+    let start = json_value(object, key, 0)?;
+    let rest = object[start..].trim_start();
+    let digits = rest.find(|c:char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    rest[..digits].parse().ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_json() {
+        let json = "{\"version\": 1, \"branches\": [\n  {\"function\": 1, \"offset\": 42, \"taken\": 90, \"not_taken\": 10},\n  {\"not_taken\": 3, \"taken\": 0, \"offset\": 7, \"function\": 0},\n  {\"function\": 1, \"offset\": 42, \"taken\": 10, \"not_taken\": 0}\n]}";
+        let profile = BranchProfile::from_json(json).unwrap();
+        assert_eq!(profile.points(), vec![ProgramPoint::new(0, 7), ProgramPoint::new(1, 42)]);
+        assert_eq!(profile.get(ProgramPoint::new(1, 42)), Some(BranchCounts::new(100, 10)));
+        assert_eq!(profile.get(ProgramPoint::new(0, 7)).unwrap().probability(), Some(0.0));
+        assert_eq!(BranchCounts::default().probability(), None);
+        assert_eq!(BranchProfile::from_json(&profile.to_json()), Some(profile));
+
+        // counts have to be there and be unsigned
+        assert_eq!(BranchProfile::from_json("{\"branches\":[{\"function\":1,\"offset\":42,\"taken\":-1,\"not_taken\":0}]}"), None);
+        assert_eq!(BranchProfile::from_json("{\"branches\":[{\"function\":1,\"offset\":42}]}"), None);
+        assert_eq!(BranchProfile::from_json("{\"branches\":[]}"), Some(BranchProfile::new()));
+
+        // counts of a branch listed more than once saturate
+        let max = u64::MAX;
+        let json = format!("{{\"branches\":[{{\"function\":0,\"offset\":1,\"taken\":{},\"not_taken\":1}},{{\"function\":0,\"offset\":1,\"taken\":2,\"not_taken\":3}}]}}", max);
+        assert_eq!(BranchProfile::from_json(&json).unwrap().get(ProgramPoint::new(0, 1)), Some(BranchCounts::new(max, 4)));
+    }
+}
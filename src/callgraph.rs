@@ -0,0 +1,288 @@
+//! # Call graph
+//! The calls between functions as discovered when a module is first mapped, before unreachable functions
+//! are pruned, helpers inlined and callees copied into their callers' node trees. Each function appears
+//! once however often it is called, and the edges count the call sites between two functions, including
+//! those in nested blocks. Indirect calls can't be resolved to a callee when mapping, so they are marked
+//! with the type of the functions they can call instead. The graph can be exported to DOT and JSON
+//!
+//! The call paths from a function, the chains of calls it can make down to functions calling nothing
+//! else, can be enumerated. Their number grows exponentially with the branching of the graph, so the
+//! enumeration stops at a number of paths and cuts paths off at a length, or samples random paths from
+//! a seed instead, and says whether the paths it found are all there are. Paths end before a call back
+//! into a function already on them, so recursion doesn't make them endless
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use validation::next_random;
+
+
+// the number of paths sampled when the limits don't bound it
+const DEFAULT_SAMPLES:usize = 1024;
+
+
+/// A call edge counts the direct call sites from one function to another
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallEdge {
+    pub caller: usize, // the index of the calling function
+    pub callee: usize, // the index of the called function
+    pub calls: usize // the number of call sites
+}
+
+
+/// An indirect call marker counts the indirect call sites in a function
+/// through functions of the same type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndirectCall {
+    pub caller: usize, // the index of the calling function
+    pub type_index: usize, // the type of the functions that can be called
+    pub calls: usize // the number of call sites
+}
+
+
+/// Path limits bound the enumeration of call paths
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathLimits {
+    pub max_paths: Option<usize>, // how many paths are found, if limited
+    pub max_length: Option<usize>, // how many functions a path holds before it is cut off, if limited
+    pub seed: Option<u64> // the seed of random paths sampled instead of enumerating them in order, if sampling
+}
+
+
+impl Default for PathLimits {
+    fn default() -> PathLimits {
+        PathLimits { max_paths: Some(DEFAULT_SAMPLES), max_length: Some(64), seed: None }
+    }
+}
+
+
+impl PathLimits {
+    // returns limits enumerating every path, however many there are
+    pub fn unlimited() -> PathLimits {
+        PathLimits { max_paths: None, max_length: None, seed: None }
+    }
+}
+
+
+/// A path enumeration holds the call paths found from a function
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathEnumeration {
+    pub paths: Vec<Vec<usize>>, // the paths, each starting with the function and following its calls, in the order found
+    pub exhaustive: bool // whether the paths are all there are, uncut, which sampled paths are never taken to be
+}
+
+
+/// A call graph holds the functions of a module and the calls between them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CallGraph {
+    pub functions: Vec<usize>, // the indices of the mapped functions and the functions they call, in order
+    pub edges: Vec<CallEdge>, // the direct calls, ordered by caller and callee
+    pub indirect: Vec<IndirectCall> // the indirect calls, ordered by caller and type
+}
+
+
+impl CallGraph {
+    // creates a graph from the call sites of each function, given as callee indices and as the type indices
+    // of indirect calls, with one entry per site
+    pub fn new(sites:HashMap<usize, (Vec<usize>, Vec<usize>)>) -> CallGraph {
+        let mut graph = CallGraph::default();
+        for (caller, (callees, types)) in sites.iter() {
+            graph.functions.push(*caller);
+            graph.functions.extend(callees.iter().cloned());
+
+            let mut counts:HashMap<usize, usize> = HashMap::new();
+            for callee in callees {
+                *counts.entry(*callee).or_default() += 1;
+            }
+            graph.edges.extend(counts.into_iter().map(|(callee, calls)| CallEdge { caller: *caller, callee, calls }));
+
+            let mut counts:HashMap<usize, usize> = HashMap::new();
+            for type_index in types {
+                *counts.entry(*type_index).or_default() += 1;
+            }
+            graph.indirect.extend(counts.into_iter().map(|(type_index, calls)| IndirectCall { caller: *caller, type_index, calls }));
+        }
+        graph.functions.sort();
+        graph.functions.dedup();
+        graph.edges.sort_by_key(|edge| (edge.caller, edge.callee));
+        graph.indirect.sort_by_key(|call| (call.caller, call.type_index));
+        graph
+    }
+
+    // returns the functions called directly from a function, in order
+    pub fn callees(&self, caller:usize) -> Vec<usize> {
+        self.edges.iter().filter(|edge| edge.caller == caller).map(|edge| edge.callee).collect()
+    }
+
+    // returns the number of direct call sites from one function to another
+    pub fn multiplicity(&self, caller:usize, callee:usize) -> usize {
+        self.edges.iter()
+            .find(|edge| edge.caller == caller && edge.callee == callee)
+            .map_or(0, |edge| edge.calls)
+    }
+
+    // checks whether a function makes indirect calls, whose callees aren't known
+    pub fn calls_indirectly(&self, caller:usize) -> bool {
+        self.indirect.iter().any(|call| call.caller == caller)
+    }
+
+    // finds the call paths from a function within the limits, enumerating them in the order of the callees
+    // unless the limits ask for a sample
+    pub fn paths(&self, root:usize, limits:&PathLimits) -> PathEnumeration {
+        let mut found = PathEnumeration { paths: Vec::new(), exhaustive: limits.seed.is_none() };
+        match limits.seed {
+            Some(seed) => self.sample_paths(root, limits, seed, &mut found),
+            None => self.extend_path(&mut vec![root], limits, &mut found)
+        }
+        found
+    }
+
+    // enumerates the paths continuing the given one, noting whether the limits left any out
+    fn extend_path(&self, path:&mut Vec<usize>, limits:&PathLimits, found:&mut PathEnumeration) {
+        if limits.max_paths.is_some_and(|max| found.paths.len() >= max) {
+            found.exhaustive = false;
+            return;
+        }
+        let callees:Vec<usize> = self.callees(*path.last().unwrap()).into_iter().filter(|callee| !path.contains(callee)).collect();
+        if !callees.is_empty() && limits.max_length.is_some_and(|max| path.len() >= max) {
+            found.exhaustive = false;
+            found.paths.push(path.clone());
+            return;
+        }
+        if callees.is_empty() {
+            found.paths.push(path.clone());
+            return;
+        }
+        for callee in callees {
+            path.push(callee);
+            self.extend_path(path, limits, found);
+            path.pop();
+        }
+    }
+
+    // samples paths by following a random callee at each call, keeping each path found once
+    fn sample_paths(&self, root:usize, limits:&PathLimits, seed:u64, found:&mut PathEnumeration) {
+        let mut random = seed | 1;
+        let mut seen:HashSet<Vec<usize>> = HashSet::new();
+        for _ in 0..limits.max_paths.unwrap_or(DEFAULT_SAMPLES) {
+            let mut path = vec![root];
+            while limits.max_length.is_none_or(|max| path.len() < max) {
+                let callees:Vec<usize> = self.callees(*path.last().unwrap()).into_iter().filter(|callee| !path.contains(callee)).collect();
+                if callees.is_empty() {
+                    break;
+                }
+                path.push(callees[(next_random(&mut random) % callees.len() as u64) as usize]);
+            }
+            if seen.insert(path.clone()) {
+                found.paths.push(path);
+            }
+        }
+    }
+
+    // describes the graph in the DOT language; edges are labeled with their number of call sites when
+    // there is more than one, and indirect calls lead to a dashed node per type
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n");
+        for index in self.functions.iter() {
+            writeln!(dot, "    f{} [label=\"{}\"];", index, index).unwrap();
+        }
+        for edge in self.edges.iter() {
+            match edge.calls {
+                1 => writeln!(dot, "    f{} -> f{};", edge.caller, edge.callee).unwrap(),
+                calls => writeln!(dot, "    f{} -> f{} [label=\"{}\"];", edge.caller, edge.callee, calls).unwrap()
+            }
+        }
+        let mut types:Vec<usize> = self.indirect.iter().map(|call| call.type_index).collect();
+        types.sort();
+        types.dedup();
+        for type_index in types {
+            writeln!(dot, "    t{} [label=\"type {}\", shape=box, style=dashed];", type_index, type_index).unwrap();
+        }
+        for call in self.indirect.iter() {
+            match call.calls {
+                1 => writeln!(dot, "    f{} -> t{} [style=dashed];", call.caller, call.type_index).unwrap(),
+                calls => writeln!(dot, "    f{} -> t{} [style=dashed, label=\"{}\"];", call.caller, call.type_index, calls).unwrap()
+            }
+        }
+        dot.push('}');
+        dot
+    }
+
+    // describes the graph as a JSON object with its functions, direct edges and indirect calls
+    pub fn to_json(&self) -> String {
+        let functions:Vec<String> = self.functions.iter().map(|index| index.to_string()).collect();
+        let edges:Vec<String> = self.edges.iter()
+            .map(|edge| format!("{{\"caller\":{},\"callee\":{},\"calls\":{}}}", edge.caller, edge.callee, edge.calls))
+            .collect();
+        let indirect:Vec<String> = self.indirect.iter()
+            .map(|call| format!("{{\"caller\":{},\"type\":{},\"calls\":{}}}", call.caller, call.type_index, call.calls))
+            .collect();
+        format!("{{\"functions\":[{}],\"edges\":[{}],\"indirect\":[{}]}}", functions.join(","), edges.join(","), indirect.join(","))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_graph() {
+        let mut sites = HashMap::new();
+        sites.insert(0, (vec![1, 2, 1], vec![]));
+        sites.insert(1, (vec![], vec![3]));
+        sites.insert(2, (vec![], vec![]));
+        let graph = CallGraph::new(sites);
+
+        assert_eq!(graph.functions, vec![0, 1, 2]);
+        assert_eq!(graph.callees(0), vec![1, 2]);
+        assert_eq!(graph.multiplicity(0, 1), 2);
+        assert_eq!(graph.multiplicity(1, 0), 0);
+        assert!(graph.calls_indirectly(1) && !graph.calls_indirectly(0));
+
+        assert_eq!(graph.to_json(), "{\"functions\":[0,1,2],\"edges\":[{\"caller\":0,\"callee\":1,\"calls\":2},{\"caller\":0,\"callee\":2,\"calls\":1}],\"indirect\":[{\"caller\":1,\"type\":3,\"calls\":1}]}");
+        let dot = graph.to_dot();
+        assert!(dot.contains("f0 -> f1 [label=\"2\"];"));
+        assert!(dot.contains("f0 -> f2;"));
+        assert!(dot.contains("f1 -> t3 [style=dashed];"));
+    }
+
+    #[test]
+    fn paths() {
+
+        // a ladder of three diamonds, 0 -> {1, 2} -> 3 -> {4, 5} -> 6 -> {7, 8} -> 9, which 9 closes by calling 0
+        let mut sites = HashMap::new();
+        for step in 0..3 {
+            let base = step * 3;
+            sites.insert(base, (vec![base + 1, base + 2], vec![]));
+            sites.insert(base + 1, (vec![base + 3], vec![]));
+            sites.insert(base + 2, (vec![base + 3], vec![]));
+        }
+        sites.insert(9, (vec![0], vec![]));
+        let graph = CallGraph::new(sites);
+
+        // every path is found when nothing limits them, and ends before calling back into the root
+        let all = graph.paths(0, &PathLimits::unlimited());
+        assert!(all.exhaustive);
+        assert_eq!(all.paths.len(), 8);
+        assert_eq!(all.paths[0], vec![0, 1, 3, 4, 6, 7, 9]);
+        let around = graph.paths(7, &PathLimits::default());
+        assert_eq!((around.paths.len(), around.exhaustive), (4, true));
+        assert_eq!(around.paths[0], vec![7, 9, 0, 1, 3, 4, 6, 8]);
+
+        // limits leave paths out or cut them off, which the enumeration says
+        let few = graph.paths(0, &PathLimits { max_paths: Some(3), ..PathLimits::unlimited() });
+        assert_eq!((few.paths.len(), few.exhaustive), (3, false));
+        assert_eq!(few.paths[..], all.paths[..3]);
+        let short = graph.paths(0, &PathLimits { max_length: Some(3), ..PathLimits::unlimited() });
+        assert_eq!(short.paths, vec![vec![0, 1, 3], vec![0, 2, 3]]);
+        assert!(!short.exhaustive);
+        assert!(graph.paths(0, &PathLimits { max_paths: Some(8), ..PathLimits::unlimited() }).exhaustive);
+
+        // samples are the same for the same seed, and only hold paths there are
+        let limits = PathLimits { max_paths: Some(16), max_length: None, seed: Some(7) };
+        let sampled = graph.paths(0, &limits);
+        assert_eq!(sampled, graph.paths(0, &limits));
+        assert!(!sampled.exhaustive && !sampled.paths.is_empty());
+        assert!(sampled.paths.iter().all(|path| all.paths.contains(path)));
+    }
+}
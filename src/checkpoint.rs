@@ -0,0 +1,292 @@
+//! # Checkpoint
+//! Checkpoints of long, interactive mapping sessions, so that they can be resumed after an interruption.
+//! The blocks and nodes a mapping produces only depend on the module, the mapper's configuration and the
+//! decisions answered along the way, so a checkpoint holds the module, every decision answered so far and
+//! the top-level functions whose expansion has started, which mark how far the session got. Resuming maps
+//! the module again with the same configuration, replaying the recorded answers instead of asking them, so
+//! only the decisions the interrupted session didn't reach come up again
+
+#[cfg(feature = "std-io")]
+use std::fs;
+#[cfg(feature = "std-io")]
+use std::io;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use parallelize::{Decision, MapperObserver};
+
+
+/// A checkpoint holds what is needed to resume a mapping session
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Checkpoint {
+    pub module: Vec<u8>, // the module being mapped
+    pub decisions: Vec<(Decision, bool)>, // the decisions answered so far and their answers, in order
+    pub functions: Vec<usize> // the top-level functions whose expansion has started, in order
+}
+
+
+impl Checkpoint {
+    // describes the checkpoint as text, with the module in hexadecimal and a line per function and decision
+    pub fn encode(&self) -> String {
+        let mut text = String::from("wasm-pfc checkpoint\nmodule ");
+        for byte in self.module.iter() {
+            write!(text, "{:02x}", byte).unwrap();
+        }
+        text.push('\n');
+        for function in self.functions.iter() {
+            writeln!(text, "function {}", function).unwrap();
+        }
+        for (decision, answer) in self.decisions.iter() {
+            let answer = if *answer { "yes" } else { "no" };
+            match *decision {
+                Decision::Parallelize { function } => writeln!(text, "parallelize {} {}", function, answer).unwrap(),
+                Decision::Lower { node, input_variables, internal_variables, constants } => {
+                    writeln!(text, "lower {} {} {} {} {}", node, input_variables, internal_variables, constants, answer).unwrap()
+                }
+                Decision::LowerNested { node, child } => writeln!(text, "lower-nested {} {} {}", node, child, answer).unwrap()
+            }
+        }
+        text
+    }
+
+    // reads a checkpoint described by encode, unless the text is malformed
+    pub fn decode(text:&str) -> Option<Checkpoint> {
+        let mut lines = text.lines();
+        if lines.next()? != "wasm-pfc checkpoint" {
+            return None;
+        }
+        let mut checkpoint = Checkpoint::default();
+        for line in lines {
+            let words:Vec<&str> = line.split_whitespace().collect();
+            let (&kind, rest) = match words.split_first() {
+                Some(split) => split,
+                None => continue
+            };
+            if kind == "module" {
+                let hex = rest.first().cloned().unwrap_or("");
+                if hex.len() % 2 != 0 {
+                    return None;
+                }
+                for i in (0..hex.len()).step_by(2) {
+                    checkpoint.module.push(u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()?);
+                }
+                continue;
+            }
+
+            // the remaining lines are numbers, ending with the answer for decisions
+            let (answer, numbers) = match rest.split_last() {
+                Some((&"yes", numbers)) if kind != "function" => (true, numbers),
+                Some((&"no", numbers)) if kind != "function" => (false, numbers),
+                _ => (false, rest)
+            };
+            let numbers = numbers.iter().map(|word| word.parse().ok()).collect::<Option<Vec<usize>>>()?;
+            match (kind, numbers.as_slice()) {
+                ("function", &[function]) => checkpoint.functions.push(function),
+                ("parallelize", &[function]) => checkpoint.decisions.push((Decision::Parallelize { function }, answer)),
+                ("lower", &[node, input_variables, internal_variables, constants]) => {
+                    checkpoint.decisions.push((Decision::Lower { node, input_variables, internal_variables, constants }, answer))
+                }
+                ("lower-nested", &[node, child]) => checkpoint.decisions.push((Decision::LowerNested { node, child }, answer)),
+                _ => return None
+            }
+        }
+        Some(checkpoint)
+    }
+
+    // writes the checkpoint to a file, going through a temporary file so that an interruption while writing
+    // leaves the previous checkpoint in place
+    #[cfg(feature = "std-io")]
+    pub fn save(&self, path:&str) -> io::Result<()> {
+        let temporary = format!("{}.tmp", path);
+        fs::write(&temporary, self.encode())?;
+        fs::rename(&temporary, path)
+    }
+
+    // reads a checkpoint written by save
+    #[cfg(feature = "std-io")]
+    pub fn load(path:&str) -> io::Result<Checkpoint> {
+        let text = fs::read_to_string(path)?;
+        match Checkpoint::decode(&text) {
+            Some(checkpoint) => Ok(checkpoint),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a mapping checkpoint", path)))
+        }
+    }
+}
+
+
+/// The checkpoint observer records a session's checkpoint as it passes
+/// events on to another observer, answering the decisions recorded by an
+/// earlier session itself and only asking the other observer the rest
+pub struct CheckpointObserver {
+    inner: Box<dyn MapperObserver>, // the observer events are passed on to
+    session: Arc<Mutex<Checkpoint>>, // the checkpoint of this session, shared with whoever reads it
+    replay: Vec<(Decision, bool)>, // the earlier session's answers that haven't been replayed yet
+    path: Option<String>, // the file the checkpoint is written to, if any
+    interval: usize, // how many decisions are asked between writes, or zero to only write when a module starts
+    asked: usize // how many decisions have been asked since the last write
+}
+
+
+impl CheckpointObserver {
+    // wraps an observer, replaying the answers recorded by an earlier session before asking it anything;
+    // a new session starts from the default checkpoint
+    pub fn new(inner:Box<dyn MapperObserver>, earlier:Checkpoint) -> CheckpointObserver {
+        CheckpointObserver {
+            inner,
+            session: Arc::new(Mutex::new(Checkpoint::default())),
+            replay: earlier.decisions,
+            path: None,
+            interval: 0,
+            asked: 0
+        }
+    }
+
+    // returns the session's checkpoint, which the observer keeps updating
+    pub fn session(&self) -> Arc<Mutex<Checkpoint>> {
+        self.session.clone()
+    }
+
+    // has the checkpoint written to a file when a module starts being mapped, and then whenever the given
+    // number of decisions have been asked since it last was
+    pub fn set_file(&mut self, path:&str, interval:usize) {
+        self.path = Some(path.to_string());
+        self.interval = interval;
+    }
+
+    // writes the checkpoint to its file, if it has one, warning instead of failing the session
+    fn save(&mut self) {
+        self.asked = 0;
+        if let Some(ref path) = self.path {
+            #[cfg(feature = "std-io")]
+            {
+                let result = self.session.lock().unwrap().save(path);
+                if let Err(err) = result {
+                    self.inner.on_warning(&format!("Couldn't write checkpoint to {}: {}", path, err));
+                }
+            }
+            #[cfg(not(feature = "std-io"))]
+            self.inner.on_warning(&format!("Couldn't write checkpoint to {} without file access", path));
+        }
+    }
+}
+
+
+impl MapperObserver for CheckpointObserver {
+    fn on_module(&mut self, module:&[u8]) {
+        {
+            let mut session = self.session.lock().unwrap();
+            session.module = module.to_vec();
+            session.functions.clear();
+            session.decisions.clear();
+        }
+        self.save();
+        self.inner.on_module(module);
+    }
+
+    fn on_function_start(&mut self, function:usize) {
+        self.session.lock().unwrap().functions.push(function);
+        self.inner.on_function_start(function);
+    }
+
+    fn on_block_found(&mut self, parent:usize, block:usize) {
+        self.inner.on_block_found(parent, block);
+    }
+
+    // decisions are matched with recorded ones by value, since the order of the questions can vary between runs
+    fn on_decision_needed(&mut self, decision:&Decision) -> bool {
+        let answer = match self.replay.iter().position(|(recorded, _)| recorded == decision) {
+            Some(position) => self.replay.remove(position).1,
+            None => {
+                let answer = self.inner.on_decision_needed(decision);
+                self.asked += 1;
+                answer
+            }
+        };
+        self.session.lock().unwrap().decisions.push((decision.clone(), answer));
+        if self.interval > 0 && self.asked >= self.interval {
+            self.save();
+        }
+        answer
+    }
+
+    fn on_warning(&mut self, message:&str) {
+        self.inner.on_warning(message);
+    }
+
+    fn on_progress(&mut self, message:&str) {
+        self.inner.on_progress(message);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallelize::{new_mapper, SilentObserver};
+
+    #[derive(Clone, Default)]
+    struct CountingObserver {
+        asked: Arc<Mutex<usize>>
+    }
+
+    impl MapperObserver for CountingObserver {
+        fn on_decision_needed(&mut self, _decision:&Decision) -> bool {
+            *self.asked.lock().unwrap() += 1;
+            true
+        }
+    }
+
+    #[test]
+    fn checkpoint() {
+        let module = include_bytes!("../tests/parallelization/inline.wasm").to_vec();
+
+        // the first session is interrupted once it has answered a single decision
+        let observer = CheckpointObserver::new(Box::new(SilentObserver), Checkpoint::default());
+        let session = observer.session();
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(observer));
+        mapper.map(module.clone());
+        let mut checkpoint = session.lock().unwrap().clone();
+        assert_eq!(checkpoint.module, module);
+        assert_eq!(checkpoint.decisions.len(), 3);
+        assert_eq!(checkpoint.functions.len(), 3);
+        checkpoint.decisions.truncate(1);
+        assert_eq!(Checkpoint::decode(&checkpoint.encode()), Some(checkpoint.clone()));
+        assert_eq!(Checkpoint::decode("wasm-pfc checkpoint\nmodule 0"), None);
+
+        // resuming only asks the decisions the first session didn't reach
+        let counting = CountingObserver::default();
+        let observer = CheckpointObserver::new(Box::new(counting.clone()), checkpoint.clone());
+        let session = observer.session();
+        mapper.set_observer(Box::new(observer));
+        mapper.map(module.clone());
+        assert_eq!(*counting.asked.lock().unwrap(), 2);
+        let resumed = session.lock().unwrap().clone();
+        assert_eq!(resumed.decisions.len(), 3);
+        assert!(resumed.decisions.contains(&checkpoint.decisions[0]));
+    }
+
+    #[cfg(feature = "std-io")]
+    #[test]
+    fn resume() {
+        let module = include_bytes!("../tests/parallelization/inline.wasm").to_vec();
+        let path = std::env::temp_dir().join(format!("wasm-pfc-checkpoint-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        // the checkpoint is written as soon as mapping starts, and resuming maps the same module
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.set_checkpointing(path, 1);
+        let nodes = mapper.map(module.clone());
+        let checkpoint = Checkpoint::load(path).unwrap();
+        assert_eq!(checkpoint.module, module);
+        assert_eq!(checkpoint.decisions, mapper.get_checkpoint().unwrap().decisions);
+
+        let mut resumed = new_mapper();
+        resumed.set_observer(Box::new(SilentObserver));
+        let resumed_nodes = resumed.resume(path, 1).unwrap();
+        assert_eq!(resumed_nodes.len(), nodes.len());
+        assert_eq!(resumed.get_checkpoint().unwrap().decisions.len(), checkpoint.decisions.len());
+        std::fs::remove_file(path).unwrap();
+        assert!(resumed.resume(path, 1).is_err());
+    }
+}
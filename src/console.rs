@@ -0,0 +1,301 @@
+//! # Console
+//! Colored output of the mapper's first pass, which prints each operator it reads in a color given by the
+//! role it plays in the mapping. The colors come from a theme, and the output goes to any sink that can
+//! be colored, so that it can be redirected to a file or captured without escape codes. Standard output
+//! is colored unless the NO_COLOR or CLICOLOR environment variables say otherwise
+
+#[cfg(feature = "std-io")]
+extern crate termcolor;
+
+use std::env;
+use std::collections::HashMap;
+#[cfg(feature = "std-io")]
+pub use self::termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+#[cfg(not(feature = "std-io"))]
+pub use self::plain::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+
+// without the std-io feature there is no terminal to color, so output goes through these stand-ins
+// for the parts of termcolor the printer uses, which leave the text uncolored
+#[cfg(not(feature = "std-io"))]
+mod plain {
+    use std::io;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Color { Black, Blue, Green, Red, Cyan, Magenta, Yellow, White }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ColorChoice { Always, AlwaysAnsi, Auto, Never }
+
+    #[derive(Clone, Debug, Default)]
+    pub struct ColorSpec;
+
+    impl ColorSpec {
+        pub fn new() -> ColorSpec {
+            ColorSpec
+        }
+
+        pub fn set_fg(&mut self, _color:Option<Color>) -> &mut ColorSpec {
+            self
+        }
+    }
+
+    pub trait WriteColor: io::Write {
+        fn set_color(&mut self, _spec:&ColorSpec) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    pub struct StandardStream;
+
+    impl StandardStream {
+        pub fn stdout(_choice:ColorChoice) -> StandardStream {
+            StandardStream
+        }
+
+        pub fn lock(&self) -> StandardStreamLock {
+            StandardStreamLock
+        }
+    }
+
+    pub struct StandardStreamLock;
+
+    impl io::Write for StandardStreamLock {
+        fn write(&mut self, buf:&[u8]) -> io::Result<usize> {
+            io::stdout().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            io::stdout().flush()
+        }
+    }
+
+    impl WriteColor for StandardStreamLock {}
+}
+
+
+/// The highlight enum represents the roles printed operators play in
+/// the mapping, which the theme gives colors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Highlight {
+    Plain, // non-critical code and everything that isn't an operator
+    Simulatable, // simulatable operations
+    Control, // control dependencies
+    Data, // data dependencies
+    Call, // function calls
+    Error, // code that can't be read
+    Critical // functions on the critical path when the tree is printed
+}
+
+
+/// A theme maps each highlight to the color it is printed in, or to
+/// none for the terminal's default
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    colors: HashMap<Highlight, Color> // the colors of the highlights that have one
+}
+
+
+impl Default for Theme {
+    fn default() -> Theme {
+        let mut theme = Theme::plain();
+        theme.set(Highlight::Plain, Some(Color::White));
+        theme.set(Highlight::Simulatable, Some(Color::Green));
+        theme.set(Highlight::Control, Some(Color::Yellow));
+        theme.set(Highlight::Data, Some(Color::Blue));
+        theme.set(Highlight::Call, Some(Color::Magenta));
+        theme.set(Highlight::Error, Some(Color::Red));
+        theme.set(Highlight::Critical, Some(Color::Cyan));
+        theme
+    }
+}
+
+
+impl Theme {
+    // creates a theme printing everything in the terminal's default color
+    pub fn plain() -> Theme {
+        Theme { colors: HashMap::new() }
+    }
+
+    // sets the color a highlight is printed in, or none for the terminal's default
+    pub fn set(&mut self, highlight:Highlight, color:Option<Color>) {
+        match color {
+            Some(color) => { self.colors.insert(highlight, color); }
+            None => { self.colors.remove(&highlight); }
+        }
+    }
+
+    // returns the color a highlight is printed in, if it has one
+    pub fn color(&self, highlight:Highlight) -> Option<Color> {
+        self.colors.get(&highlight).cloned()
+    }
+}
+
+
+/// The console printer writes text to a sink in the colors of a theme
+pub struct ConsolePrinter<'a> {
+    sink: &'a mut dyn WriteColor, // where the text goes
+    theme: Theme // the colors of the highlights
+}
+
+
+impl<'a> ConsolePrinter<'a> {
+    pub fn new(sink:&'a mut dyn WriteColor, theme:Theme) -> ConsolePrinter<'a> {
+        ConsolePrinter { sink, theme }
+    }
+
+    // prints the following text in the color of the given highlight; output is best effort, so a sink that
+    // can't be colored or written to doesn't interrupt mapping
+    pub fn highlight(&mut self, highlight:Highlight) {
+        let mut spec = ColorSpec::new();
+        spec.set_fg(self.theme.color(highlight));
+        let _ = self.sink.set_color(&spec);
+    }
+
+    // prints text as it is
+    pub fn print(&mut self, text:&str) {
+        let _ = self.sink.write_all(text.as_bytes());
+    }
+
+    // prints text followed by a new line
+    pub fn println(&mut self, text:&str) {
+        self.print(text);
+        self.print("\n");
+    }
+}
+
+
+// decides whether standard output is colored from the NO_COLOR, CLICOLOR_FORCE and CLICOLOR environment
+// variables; it is by default
+pub fn color_choice() -> ColorChoice {
+    choose_color(env::var("NO_COLOR").ok(), env::var("CLICOLOR_FORCE").ok(), env::var("CLICOLOR").ok())
+}
+
+// decides whether to color output from the values of the environment variables: a non-empty NO_COLOR turns
+// colors off, a CLICOLOR_FORCE other than 0 turns them on and a CLICOLOR of 0 turns them off
+fn choose_color(no_color:Option<String>, force:Option<String>, clicolor:Option<String>) -> ColorChoice {
+    if matches!(no_color, Some(ref value) if !value.is_empty()) {
+        return ColorChoice::Never;
+    }
+    if matches!(force, Some(ref value) if value != "0") {
+        return ColorChoice::Always;
+    }
+    match clicolor {
+        Some(ref value) if value == "0" => ColorChoice::Never,
+        _ => ColorChoice::Always
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_choices() {
+        let value = |text:&str| Some(text.to_string());
+        assert_eq!(choose_color(None, None, None), ColorChoice::Always);
+        assert_eq!(choose_color(value("1"), value("1"), None), ColorChoice::Never);
+        assert_eq!(choose_color(value(""), None, value("0")), ColorChoice::Never);
+        assert_eq!(choose_color(None, value("1"), value("0")), ColorChoice::Always);
+        assert_eq!(choose_color(None, value("0"), value("1")), ColorChoice::Always);
+    }
+
+    #[cfg(feature = "std-io")]
+    #[test]
+    fn console_printer() {
+        let mut theme = Theme::default();
+        theme.set(Highlight::Data, None);
+        assert_eq!(theme.color(Highlight::Call), Some(Color::Magenta));
+
+        // colors are written as escape codes, unless the highlight has none
+        let mut buffer = termcolor::Buffer::ansi();
+        {
+            let mut printer = ConsolePrinter::new(&mut buffer, theme.clone());
+            printer.highlight(Highlight::Call);
+            printer.println("call");
+            printer.highlight(Highlight::Data);
+            printer.print("load");
+        }
+        assert_eq!(String::from_utf8(buffer.into_inner()).unwrap(), "\u{1b}[0m\u{1b}[35mcall\n\u{1b}[0mload");
+
+        // and left out of sinks that can't be colored
+        let mut buffer = termcolor::Buffer::no_color();
+        {
+            let mut printer = ConsolePrinter::new(&mut buffer, theme);
+            printer.highlight(Highlight::Call);
+            printer.println("call");
+        }
+        assert_eq!(String::from_utf8(buffer.into_inner()).unwrap(), "call\n");
+    }
+
+    #[cfg(feature = "std-io")]
+    #[derive(Clone, Default)]
+    struct SharedBuffer {
+        bytes: std::sync::Arc<std::sync::Mutex<Vec<u8>>>
+    }
+
+    #[cfg(feature = "std-io")]
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf:&[u8]) -> std::io::Result<usize> {
+            self.bytes.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "std-io")]
+    #[test]
+    fn mapper_output() {
+        use parallelize::{new_mapper, SilentObserver};
+
+        // the first pass can be captured without escape codes
+        let buffer = SharedBuffer::default();
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.set_output(Box::new(termcolor::NoColor::new(buffer.clone())));
+        mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        let output = String::from_utf8(buffer.bytes.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("I32Add"));
+        assert!(!output.contains('\u{1b}'));
+
+        // a mapper with an output of its own doesn't wait for standard output while another holds it
+        let stdout = std::io::stdout();
+        let _locked = stdout.lock();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+            sender.send(()).unwrap();
+        });
+        assert!(receiver.recv_timeout(std::time::Duration::from_secs(30)).is_ok());
+    }
+
+    #[cfg(feature = "std-io")]
+    #[test]
+    fn critical_path_tree() {
+        use parallelize::{new_mapper, SilentObserver};
+
+        // the functions on the critical path are printed in its color, followed by its length and functions
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        mapper.set_critical_path_highlighting(true);
+        let mut buffer = termcolor::Buffer::ansi();
+        {
+            let mut theme = Theme::plain();
+            theme.set(Highlight::Critical, Some(Color::Cyan));
+            mapper.write_tree(&nodes, &mut ConsolePrinter::new(&mut buffer, theme));
+        }
+        let printed = String::from_utf8(buffer.into_inner()).unwrap();
+        let critical = mapper.critical_path();
+        assert!(!critical.is_empty());
+        for index in 0..3 {
+            assert_eq!(printed.contains(&format!("\u{1b}[36m{}\u{1b}[0m", index)), critical.contains(&index));
+        }
+        let length = mapper.speedup_estimate().critical_path;
+        assert!(printed.ends_with(&format!("Critical path of length {} through functions:\n{:?}\n", length, critical)));
+    }
+}
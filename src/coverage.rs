@@ -0,0 +1,132 @@
+//! # Coverage
+//! How much of what the mapper recorded a real run of the module exercised, so that users can check that
+//! the functions, blocks and branches the analysis works with are those the module really runs. A trace
+//! lists the instructions a run reached, as the program points naming them, e.g. taken from a runtime's
+//! log; a function is covered once the trace enters it, a block once the trace reaches an instruction
+//! within its span but outside the spans of the blocks nested in it, and a branch once the trace reaches
+//! its if, conditional branch or select. Traces are read from text with a point per line, the function's
+//! index and the instruction's offset in the WASM file, in decimal or hexadecimal, e.g. `3 0x1a2`
+
+use std::collections::{BTreeMap, HashSet};
+use ids::BlockId;
+use parallelize::Node;
+use points::ProgramPoint;
+
+
+/// A coverage report tells which functions, blocks and branches a
+/// trace exercised
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverageReport {
+    pub functions: BTreeMap<usize, bool>, // each function's index mapped to whether the trace entered it
+    pub blocks: BTreeMap<BlockId, bool>, // each block's id mapped to whether the trace ran code in it
+    pub branches: BTreeMap<ProgramPoint, bool> // each branch's program point mapped to whether the trace reached it
+}
+
+
+impl CoverageReport {
+    // returns the share of the functions the trace entered, which is complete if there are none
+    pub fn function_coverage(&self) -> f64 {
+        share(&self.functions)
+    }
+
+    // returns the share of the blocks the trace ran code in, which is complete if there are none
+    pub fn block_coverage(&self) -> f64 {
+        share(&self.blocks)
+    }
+
+    // returns the share of the branches the trace reached, which is complete if there are none
+    pub fn branch_coverage(&self) -> f64 {
+        share(&self.branches)
+    }
+
+    // returns the program points of the branches the trace didn't reach, in order
+    pub fn missed_branches(&self) -> Vec<ProgramPoint> {
+        self.branches.iter().filter(|(_, covered)| !**covered).map(|(point, _)| *point).collect()
+    }
+}
+
+
+// returns the share of the entries that are covered
+fn share<K>(covered:&BTreeMap<K, bool>) -> f64 {
+    if covered.is_empty() {
+        return 1.0;
+    }
+    covered.values().filter(|covered| **covered).count() as f64 / covered.len() as f64
+}
+
+
+// measures which of the given functions, by index, and blocks, by id, the trace exercised, along with the
+// branches of both
+pub(crate) fn measure<'a>(functions:impl Iterator<Item = (usize, &'a Node)>, blocks:impl Iterator<Item = (BlockId, &'a Node)>, trace:&[ProgramPoint]) -> CoverageReport {
+    let reached:HashSet<ProgramPoint> = trace.iter().cloned().collect();
+    let entered:HashSet<usize> = trace.iter().map(|point| point.function).collect();
+    let mut report = CoverageReport::default();
+    let mut record_branches = |node:&Node| {
+        for point in node.branch_sites().values() {
+            report.branches.insert(*point, reached.contains(point));
+        }
+    };
+    let mut functions_covered = BTreeMap::new();
+    for (index, node) in functions {
+        functions_covered.insert(index, entered.contains(&index));
+        record_branches(node);
+    }
+
+    // a point is reached in the innermost block spanning it, so the blocks enclosing that one aren't covered by it
+    let blocks:Vec<(BlockId, &Node)> = blocks.collect();
+    let mut blocks_covered:BTreeMap<BlockId, bool> = blocks.iter().map(|(id, _)| (*id, false)).collect();
+    for point in reached.iter() {
+        let innermost = blocks.iter()
+            .filter(|(_, node)| node.get_function_index().0 == point.function && node.spans(point.offset))
+            .min_by_key(|(_, node)| node.get_end() - node.get_start());
+        if let Some((id, _)) = innermost {
+            blocks_covered.insert(*id, true);
+        }
+    }
+    for (_, node) in blocks.iter() {
+        record_branches(node);
+    }
+    report.functions = functions_covered;
+    report.blocks = blocks_covered;
+    report
+}
+
+
+// reads a trace from its text, a program point per line, skipping blank lines and those starting with #;
+// fails on lines that don't name a point
+pub fn parse_trace(text:&str) -> Option<Vec<ProgramPoint>> {
+    let mut trace = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let mut fields = line.split(|c:char| c.is_whitespace() || c == ',').filter(|field| !field.is_empty());
+        let function = parse_number(fields.next()?)?;
+        let offset = parse_number(fields.next()?)?;
+        if fields.next().is_some() {
+            return None;
+        }
+        trace.push(ProgramPoint::new(function, offset));
+    }
+    Some(trace)
+}
+
+
+// reads a decimal number, or a hexadecimal one starting with 0x
+fn parse_number(text:&str) -> Option<usize> {
+    match text.strip_prefix("0x") {
+        Some(digits) => usize::from_str_radix(digits, 16).ok(),
+        None => text.parse().ok()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_trace() {
+        let trace = super::parse_trace("# function offset\n0 0x2a\n\n  3, 17  \n").unwrap();
+        assert_eq!(trace, vec![ProgramPoint::new(0, 42), ProgramPoint::new(3, 17)]);
+        assert_eq!(super::parse_trace("0 1 2"), None);
+        assert_eq!(super::parse_trace("0x 1"), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! # Decode
+//! Reading solver output back as WASM values. A solver returns a sample, an assignment of the binary
+//! variables of a constraint's QUBO, which says nothing by itself about the program it was lowered from;
+//! the binarizations a constraint records name the variables holding the bits of each node's free inputs
+//! and result, lowest first, along with their types, so a sample can be decoded into the integer and float
+//! values of every node whose constraint went into it. Words narrower than their types, as hinted widths
+//! and inferred ranges make them, are read as the low bits of the value, the others being zero
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use gadgets::Qubo;
+use parallelize::{Binarization, Constraint};
+use primitives::Type;
+
+
+/// A decoded value is a WASM value read back from the bits a sample
+/// assigns to a word
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DecodedValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128)
+}
+
+
+impl fmt::Display for DecodedValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodedValue::I32(value) => write!(f, "i32 {}", value),
+            DecodedValue::I64(value) => write!(f, "i64 {}", value),
+            DecodedValue::F32(value) => write!(f, "f32 {}", value),
+            DecodedValue::F64(value) => write!(f, "f64 {}", value),
+            DecodedValue::V128(value) => write!(f, "v128 {:#x}", value)
+        }
+    }
+}
+
+
+/// A node sample holds the values a sample gives a node's free inputs
+/// and result
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NodeSample {
+    pub inputs: BTreeMap<usize, DecodedValue>, // the values of the node's free input variables, by variable id
+    pub result: Option<DecodedValue> // the value the node leaves on the stack, if it leaves one
+}
+
+
+/// A decoded sample is a sample read back node by node, along with
+/// its energy
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DecodedSample {
+    pub energy: i64, // the value of the constraint's QUBO for the sample, which is zero when every gadget holds
+    pub nodes: BTreeMap<usize, NodeSample> // the values of each node whose constraint went into the one decoded, by node id
+}
+
+
+// returns the variables a solver's assignment sets, given as each variable's value; both binary values and spins
+// are read, a variable being set when its value is positive
+pub fn from_assignment<I:IntoIterator<Item = (usize, i64)>>(assignment:I) -> BTreeSet<usize> {
+    assignment.into_iter().filter(|&(_, value)| value > 0).map(|(variable, _)| variable).collect()
+}
+
+
+// reads the value of the given type held by a word, lowest bit first, in a sample given as the set variables
+pub fn decode_value(ty:Type, word:&[usize], sample:&BTreeSet<usize>) -> DecodedValue {
+    let bits = word.iter().enumerate()
+        .filter(|&(bit, variable)| bit < 128 && sample.contains(variable))
+        .fold(0u128, |bits, (bit, _)| bits | 1 << bit);
+    match ty {
+        Type::I64 => DecodedValue::I64(bits as u64 as i64),
+        Type::F32 => DecodedValue::F32(f32::from_bits(bits as u32)),
+        Type::F64 => DecodedValue::F64(f64::from_bits(bits as u64)),
+        Type::V128 => DecodedValue::V128(bits),
+        _ => DecodedValue::I32(bits as u32 as i32)
+    }
+}
+
+
+// reads the values a sample gives the free inputs and result of a node binarized as given
+pub fn decode_node(binarization:&Binarization, sample:&BTreeSet<usize>) -> NodeSample {
+    NodeSample {
+        inputs: binarization.inputs.iter()
+            .map(|(var_id, word)| (*var_id, decode_value(binarization.input_types.get(var_id).cloned().unwrap_or(Type::I32), word, sample)))
+            .collect(),
+        result: binarization.result_type.filter(|_| !binarization.result.is_empty()).map(|ty| decode_value(ty, &binarization.result, sample))
+    }
+}
+
+
+// reads a sample of a constraint's QUBO back as the values of every node whose constraint went into it
+pub fn decode(constraint:&Constraint, sample:&BTreeSet<usize>) -> DecodedSample {
+    DecodedSample {
+        energy: constraint.qubo().energy(sample),
+        nodes: constraint.binarizations().iter().map(|(node, binarization)| (*node, decode_node(binarization, sample))).collect()
+    }
+}
+
+
+// reads a batch of samples of a constraint's QUBO back, in order
+pub fn decode_all(constraint:&Constraint, samples:&[BTreeSet<usize>]) -> Vec<DecodedSample> {
+    samples.iter().map(|sample| decode(constraint, sample)).collect()
+}
+
+
+// returns the sample with the lowest energy, the first of them if several have it, or none if there are no samples
+pub fn best_sample<'a>(qubo:&Qubo, samples:&'a [BTreeSet<usize>]) -> Option<&'a BTreeSet<usize>> {
+    samples.iter().fold(None, |best:Option<(i64, &BTreeSet<usize>)>, sample| {
+        let energy = qubo.energy(sample);
+        match best {
+            Some((lowest, _)) if lowest <= energy => best,
+            _ => Some((energy, sample))
+        }
+    }).map(|(_, sample)| sample)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallelize::{new_mapper, NodeOutput, SilentObserver};
+    use ids::NodeId;
+
+    #[test]
+    fn decode() {
+        assert_eq!(decode_value(Type::I32, &[0, 1, 2], &from_assignment(vec![(0, 1), (1, -1), (2, 1)])), DecodedValue::I32(5));
+        assert_eq!(decode_value(Type::F32, &(0..32).collect::<Vec<usize>>(), &(23..30).collect()), DecodedValue::F32(1.0));
+        assert_eq!(DecodedValue::I64(-1).to_string(), "i64 -1");
+
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        let mut scale = nodes[&NodeId(1)].clone();
+        scale.set_bit_width(Some(2));
+        scale.constrain_output(NodeOutput::Result, 2);
+        let mut double = nodes[&NodeId(0)].clone();
+        double.set_bit_width(Some(2));
+
+        // every ground state of scale decodes to parameters whose result is the pinned one
+        let constraint = scale.lower();
+        let (_, states) = constraint.qubo().ground_states();
+        let samples = decode_all(&constraint, &states);
+        assert_eq!(samples.len(), 4);
+        for sample in samples.iter() {
+            let node = &sample.nodes[&1];
+            let (a, b) = match (node.inputs[&0], node.inputs[&1]) {
+                (DecodedValue::I32(a), DecodedValue::I32(b)) => (a, b),
+                inputs => panic!("unexpected inputs {:?}", inputs)
+            };
+            assert_eq!((sample.energy, node.result), (0, Some(DecodedValue::I32(2))));
+            assert_eq!(a * (b + 3) % 4, 2);
+        }
+
+        // composed constraints decode per node, and the best sample is the first of the lowest energy
+        let composed = constraint.clone() + double.lower();
+        assert_eq!(super::decode(&composed, &states[0]).nodes.keys().cloned().collect::<Vec<usize>>(), vec![0, 1]);
+        let mut candidates = vec![BTreeSet::new()];
+        candidates.extend(states.iter().cloned());
+        assert_eq!(best_sample(constraint.qubo(), &candidates), Some(&states[0]));
+        assert_eq!(best_sample(constraint.qubo(), &[]), None);
+    }
+}
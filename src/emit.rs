@@ -0,0 +1,364 @@
+//! # Emit
+//! Regenerates a runnable WASM module from the split form of a module, in which the blocks and loops
+//! separated out of their functions by expansion are functions of their own, so that the parallelized
+//! module can be cross-checked on a conventional runtime. An outlined block takes the enclosing
+//! function's whole local index space as parameters, which keeps its local indices and its branches'
+//! relative depths unchanged, and is replaced by a call passing the locals on. Outlined functions are
+//! appended after the module's own, so existing call indices, exports and names stay valid
+
+use crate::{BinaryReader, BinaryReaderError, FuncType, ImportSectionEntryType, Operator, Result, Type};
+use crate::{CodeSectionReader, FunctionSectionReader, ImportSectionReader, TypeSectionReader};
+
+
+// the ids of the sections that are rewritten; every other section is copied as is
+const TYPE_SECTION:u32 = 1;
+const IMPORT_SECTION:u32 = 2;
+const FUNCTION_SECTION:u32 = 3;
+const CODE_SECTION:u32 = 10;
+
+
+/// An outlined block records where a block was split out of its function
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutlinedBlock {
+    pub function: usize, // the index of the function the block was split out of
+    pub offset: usize, // where the block starts in the original WASM file
+    pub index: usize // the index of the function the block became in the emitted module
+}
+
+
+/// A split module holds the emitted WASM together with the blocks
+/// that were outlined into functions
+#[derive(Clone, Debug)]
+pub struct SplitModule {
+    pub bytes: Vec<u8>, // the emitted module
+    pub outlined: Vec<OutlinedBlock> // the outlined blocks in the order their functions were emitted
+}
+
+
+/// A split function is an outlined block's signature and code
+struct SplitFunction {
+    params: Vec<Type>, // the enclosing function's local index space
+    result: Option<Type>, // the block's result
+    body: Vec<u8> // the function's instructions, without locals declarations
+}
+
+
+/// The outliner holds the bookkeeping of a module being split
+struct Outliner<'a> {
+    buf: &'a [u8], // the original module
+    first_index: usize, // the index the first outlined function will have
+    functions: Vec<SplitFunction>, // the functions outlined so far
+    outlined: Vec<OutlinedBlock> // where each of them came from
+}
+
+
+impl<'a> Outliner<'a> {
+    // rewrites the instructions in the given range of the original module, replacing each block or loop
+    // that can run on its own with a call to an outlined function
+    fn rewrite(&mut self, start:usize, end:usize, function:usize, locals:&[Type]) -> Result<Vec<u8>> {
+        let operators = read_operators(self.buf, start, end)?;
+        let mut code:Vec<u8> = Vec::new();
+        let mut i = 0;
+        while i < operators.len() {
+            let (op_start, op_end) = (operators[i].0, operators[i].1);
+            let ty = match operators[i].2 {
+                Operator::Block { ty } | Operator::Loop { ty } => Some(ty),
+                _ => None
+            };
+            if let Some(ty) = ty {
+                let closing = matching_end(&operators, i)?;
+                if is_outlinable(&operators[i..=closing], ty) {
+
+                    // the block keeps its own label inside the outlined function, so its branches are unchanged
+                    let mut body = self.buf[op_start..op_end].to_vec();
+                    body.extend(self.rewrite(op_end, operators[closing].0, function, locals)?);
+                    body.push(0x0b);
+                    body.push(0x0b);
+
+                    let index = self.first_index + self.functions.len();
+                    let result = if ty == Type::EmptyBlockType { None } else { Some(ty) };
+                    self.functions.push(SplitFunction { params: locals.to_vec(), result, body });
+                    self.outlined.push(OutlinedBlock { function, offset: op_start, index });
+
+                    for local_index in 0..locals.len() {
+                        code.push(0x20);
+                        write_var_u32(&mut code, local_index as u32);
+                    }
+                    code.push(0x10);
+                    write_var_u32(&mut code, index as u32);
+                    i = closing + 1;
+                    continue;
+                }
+            }
+            code.extend_from_slice(&self.buf[op_start..op_end]);
+            i += 1;
+        }
+        Ok(code)
+    }
+}
+
+
+// splits every block and loop that can run on its own out of the functions of the given module
+pub fn split_module(buf:&[u8]) -> Result<SplitModule> {
+    let mut reader = BinaryReader::new(buf);
+    reader.read_file_header()?;
+
+    // sections are kept as their ids and payload ranges
+    let mut sections:Vec<(u32, usize, usize)> = Vec::new();
+    while !reader.eof() {
+        let id = reader.read_var_u7()?;
+        let len = reader.read_var_u32()? as usize;
+        let start = reader.original_position();
+        reader.read_bytes(len)?;
+        sections.push((id, start, start + len));
+    }
+
+    let mut types:Vec<FuncType> = Vec::new();
+    let mut imported_functions = 0;
+    let mut func_types:Vec<u32> = Vec::new();
+    let mut bodies:Vec<(usize, usize)> = Vec::new();
+    for &(id, start, end) in sections.iter() {
+        match id {
+            TYPE_SECTION => {
+                let mut section = TypeSectionReader::new(&buf[start..end], start)?;
+                for _ in 0..section.get_count() {
+                    types.push(section.read()?);
+                }
+            }
+            IMPORT_SECTION => {
+                let mut section = ImportSectionReader::new(&buf[start..end], start)?;
+                for _ in 0..section.get_count() {
+                    if let ImportSectionEntryType::Function(_) = section.read()?.ty {
+                        imported_functions += 1;
+                    }
+                }
+            }
+            FUNCTION_SECTION => {
+                let mut section = FunctionSectionReader::new(&buf[start..end], start)?;
+                for _ in 0..section.get_count() {
+                    func_types.push(section.read()?);
+                }
+            }
+            CODE_SECTION => {
+                let mut section = CodeSectionReader::new(&buf[start..end], start)?;
+                for _ in 0..section.get_count() {
+                    let range = section.read()?.range();
+                    bodies.push((range.start, range.end));
+                }
+            }
+            _ => ()
+        }
+    }
+    if bodies.len() != func_types.len() {
+        return Err(BinaryReaderError { message: "Function and code section have inconsistent lengths", offset: buf.len() });
+    }
+
+    // each function body is rewritten after its locals declarations
+    let mut outliner = Outliner { buf, first_index: imported_functions + bodies.len(), functions: Vec::new(), outlined: Vec::new() };
+    let mut rewritten:Vec<Vec<u8>> = Vec::new();
+    for (i, &(start, end)) in bodies.iter().enumerate() {
+        let signature = match types.get(func_types[i] as usize) {
+            Some(signature) => signature,
+            None => return Err(BinaryReaderError { message: "Function type index out of bounds", offset: start })
+        };
+        let mut locals:Vec<Type> = signature.params.to_vec();
+        let mut body = BinaryReader::new_with_offset(&buf[start..end], start);
+        for _ in 0..body.read_var_u32()? {
+            let count = body.read_var_u32()?;
+            let ty = body.read_type()?;
+            locals.extend((0..count).map(|_| ty));
+        }
+        let operators_start = body.original_position();
+        let mut code = buf[start..operators_start].to_vec();
+        code.extend(outliner.rewrite(operators_start, end, imported_functions + i, &locals)?);
+        rewritten.push(code);
+    }
+
+    // outlined functions reuse the module's types where they can
+    let mut new_func_types:Vec<u32> = Vec::new();
+    for function in outliner.functions.iter() {
+        let returns:Vec<Type> = function.result.into_iter().collect();
+        let position = types.iter().position(|ty| *ty.params == function.params[..] && *ty.returns == returns[..]);
+        match position {
+            Some(index) => new_func_types.push(index as u32),
+            None => {
+                new_func_types.push(types.len() as u32);
+                types.push(FuncType { form: Type::Func, params: function.params.clone().into_boxed_slice(), returns: returns.into_boxed_slice() });
+            }
+        }
+    }
+    for function in outliner.functions.iter() {
+        let mut code = vec![0x00];
+        code.extend_from_slice(&function.body);
+        rewritten.push(code);
+    }
+    func_types.extend(new_func_types);
+
+    let mut bytes = buf[..8].to_vec();
+    for &(id, start, end) in sections.iter() {
+        let mut payload:Vec<u8> = Vec::new();
+        match id {
+            TYPE_SECTION => {
+                write_var_u32(&mut payload, types.len() as u32);
+                for ty in types.iter() {
+                    payload.push(0x60);
+                    write_types(&mut payload, &ty.params)?;
+                    write_types(&mut payload, &ty.returns)?;
+                }
+            }
+            FUNCTION_SECTION => {
+                write_var_u32(&mut payload, func_types.len() as u32);
+                for ty in func_types.iter() {
+                    write_var_u32(&mut payload, *ty);
+                }
+            }
+            CODE_SECTION => {
+                write_var_u32(&mut payload, rewritten.len() as u32);
+                for code in rewritten.iter() {
+                    write_var_u32(&mut payload, code.len() as u32);
+                    payload.extend_from_slice(code);
+                }
+            }
+            _ => payload.extend_from_slice(&buf[start..end])
+        }
+        write_var_u32(&mut bytes, id);
+        write_var_u32(&mut bytes, payload.len() as u32);
+        bytes.extend(payload);
+    }
+
+    Ok(SplitModule { bytes, outlined: outliner.outlined })
+}
+
+
+// reads the operators in the given range of a module along with where each of them starts and ends
+fn read_operators(buf:&[u8], start:usize, end:usize) -> Result<Vec<(usize, usize, Operator<'_>)>> {
+    let mut reader = BinaryReader::new_with_offset(&buf[start..end], start);
+    let mut operators = Vec::new();
+    while !reader.eof() {
+        let op_start = reader.original_position();
+        let op = reader.read_operator()?;
+        operators.push((op_start, reader.original_position(), op));
+    }
+    Ok(operators)
+}
+
+
+// returns the index of the end closing the block, loop or if opened at the given index
+fn matching_end(operators:&[(usize, usize, Operator)], opening:usize) -> Result<usize> {
+    let mut depth = 0;
+    for (i, operator) in operators.iter().enumerate().skip(opening) {
+        match operator.2 {
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => depth += 1,
+            Operator::End => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => ()
+        }
+    }
+    Err(BinaryReaderError { message: "Block is never closed", offset: operators[opening].0 })
+}
+
+
+// checks whether a block, given from its opening to its closing operator, can run as a function of its
+// own; it mustn't leave through anything but its own end or write locals the caller would still see
+fn is_outlinable(operators:&[(usize, usize, Operator)], ty:Type) -> bool {
+    if ty == Type::V128 {
+        return false;
+    }
+    let mut depth = 0;
+    for operator in operators.iter() {
+        match operator.2 {
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => depth += 1,
+            Operator::End => depth -= 1,
+            Operator::Br { relative_depth } | Operator::BrIf { relative_depth } if relative_depth >= depth => return false,
+            Operator::BrTable { ref table } => {
+                let (targets, default) = match table.read_table() {
+                    Ok(table) => table,
+                    Err(_) => return false
+                };
+                if targets.iter().chain(Some(&default)).any(|relative_depth| *relative_depth >= depth) {
+                    return false;
+                }
+            }
+            Operator::Return | Operator::SetLocal { .. } | Operator::TeeLocal { .. } => return false,
+            _ => ()
+        }
+    }
+    true
+}
+
+
+// writes an unsigned LEB128 integer
+pub(crate) fn write_var_u32(bytes:&mut Vec<u8>, mut value:u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+
+// writes a vector of value types
+pub(crate) fn write_types(bytes:&mut Vec<u8>, types:&[Type]) -> Result<()> {
+    write_var_u32(bytes, types.len() as u32);
+    for ty in types.iter() {
+        bytes.push(match *ty {
+            Type::I32 => 0x7f,
+            Type::I64 => 0x7e,
+            Type::F32 => 0x7d,
+            Type::F64 => 0x7c,
+            Type::V128 => 0x7b,
+            Type::AnyFunc => 0x70,
+            Type::AnyRef => 0x6f,
+            _ => return Err(BinaryReaderError { message: "Invalid value type", offset: 0 })
+        });
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate;
+
+    #[test]
+    fn split_blocks() {
+        let buf = include_bytes!("../tests/parallelization/blocks.wasm");
+        let split = split_module(buf).unwrap();
+        assert!(validate(&split.bytes, None));
+
+        // the block computing the result and the block nested in the loop are outlined, but the loop
+        // branching out of itself and the block writing a local stay where they are
+        let indices:Vec<(usize, usize)> = split.outlined.iter().map(|block| (block.function, block.index)).collect();
+        assert_eq!(indices, vec![(0, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn fixtures_stay_valid() {
+        let fixtures:[&[u8]; 4] = [
+            include_bytes!("../tests/parallelization/math.wasm"),
+            include_bytes!("../tests/parallelization/test.wasm"),
+            include_bytes!("../tests/parallelization/locals.wasm"),
+            include_bytes!("../tests/parallelization/game_of_life.wasm")
+        ];
+        for buf in fixtures.iter() {
+            let split = split_module(buf).unwrap();
+            assert!(validate(&split.bytes, None));
+        }
+    }
+
+    #[test]
+    fn write_var_u32() {
+        let mut bytes = Vec::new();
+        super::write_var_u32(&mut bytes, 624485);
+        assert_eq!(bytes, vec![0xe5, 0x8e, 0x26]);
+    }
+}
@@ -0,0 +1,263 @@
+//! # FFI
+//! A C interface to the mapping pipeline, so that Python and Julia quantum computing workflows can
+//! load the analyzer as a shared library instead of spawning it as a subprocess. Mapped modules are
+//! handed out as opaque handles and everything else is returned as JSON strings, which the caller
+//! must release with the matching `wasm_pfc_free_*` function. Nothing is asked on standard input;
+//! every function is expanded and every node lowered.
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic;
+use std::ptr;
+use std::slice;
+use parallelize::{new_mapper, Constraint, Mapper, Node, SilentObserver};
+use ids::NodeId;
+use snapshot::{json_string, nodes_json};
+
+
+/// A mapped module holds the mapper that analyzed a WASM module together
+/// with the resulting node tree, behind the handle given to C callers
+pub struct MappedModule {
+    mapper: Mapper, // the mapper that produced the nodes
+    nodes: HashMap<NodeId, Node> // the mapped top-level nodes
+}
+
+
+/// Maps the `len` bytes of WASM at `bytes`, returning a handle to the mapped module,
+/// or null if the module could not be mapped
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes. The returned handle must be released
+/// with `wasm_pfc_free_module`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_pfc_map(bytes:*const u8, len:usize) -> *mut MappedModule {
+    if bytes.is_null() {
+        return ptr::null_mut();
+    }
+    let buf = slice::from_raw_parts(bytes, len).to_vec();
+
+    // panics must not unwind into the caller
+    let mapped = panic::catch_unwind(move || {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(buf);
+        MappedModule { mapper, nodes }
+    });
+    match mapped {
+        Ok(module) => Box::into_raw(Box::new(module)),
+        Err(_) => ptr::null_mut()
+    }
+}
+
+
+/// Describes the top-level nodes of a mapped module as a JSON array, or returns
+/// null if the handle is null
+///
+/// # Safety
+///
+/// `module` must be null or a handle returned by `wasm_pfc_map` that has not been freed.
+/// The returned string must be released with `wasm_pfc_free_string`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_pfc_nodes(module:*const MappedModule) -> *mut c_char {
+    match module.as_ref() {
+        Some(module) => into_c_string(nodes_json(&module.nodes)),
+        None => ptr::null_mut()
+    }
+}
+
+
+/// Lowers the top-level node with the given id, returning the resulting constraint as
+/// a JSON object, or null if the handle is null, there is no such node or lowering failed
+///
+/// # Safety
+///
+/// `module` must be null or a handle returned by `wasm_pfc_map` that has not been freed.
+/// The returned string must be released with `wasm_pfc_free_string`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_pfc_lower(module:*mut MappedModule, node_id:usize) -> *mut c_char {
+    let module = match module.as_mut() {
+        Some(module) => module,
+        None => return ptr::null_mut()
+    };
+    let mut node = match module.nodes.get(&NodeId(node_id)) {
+        Some(node) => node.clone(),
+        None => return ptr::null_mut()
+    };
+
+    // panics must not unwind into the caller; the mapper's observer is silent, and it records how long lowering took
+    let mapper = &mut module.mapper;
+    match panic::catch_unwind(panic::AssertUnwindSafe(move || mapper.lower(&mut node))) {
+        Ok(mut constraint) => {
+            constraint.simplify();
+            into_c_string(constraint_json(&constraint))
+        }
+        Err(_) => ptr::null_mut()
+    }
+}
+
+
+/// Describes the lowering plan of every top-level node of a mapped module as a JSON
+/// array, or returns null if the handle is null
+///
+/// # Safety
+///
+/// `module` must be null or a handle returned by `wasm_pfc_map` that has not been freed.
+/// The returned string must be released with `wasm_pfc_free_string`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_pfc_plan(module:*mut MappedModule) -> *mut c_char {
+    let module = match module.as_mut() {
+        Some(module) => module,
+        None => return ptr::null_mut()
+    };
+    let plans:Vec<String> = module.mapper.plan_lowering().iter().map(|plan| {
+        format!("{{\"id\":{},\"operations\":{},\"auxiliary_qubits\":{},\"shared_operations\":{},\"qubit_savings\":{},\"adder_trees\":{},\"adder_savings\":{},\"multiplier\":\"{}\",\"multiplier_qubits\":{},\"multiplier_couplers\":{}}}",
+            plan.id, plan.operations, plan.auxiliary_qubits, plan.shared_operations, plan.qubit_savings, plan.adder_trees, plan.adder_savings,
+            plan.multipliers.strategy, plan.multipliers.qubits, plan.multipliers.couplers)
+    }).collect();
+    into_c_string(format!("[{}]", plans.join(",")))
+}
+
+
+/// Describes the call graph of a mapped module as a JSON object, or returns null if
+/// the handle is null
+///
+/// # Safety
+///
+/// `module` must be null or a handle returned by `wasm_pfc_map` that has not been freed.
+/// The returned string must be released with `wasm_pfc_free_string`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_pfc_call_graph(module:*const MappedModule) -> *mut c_char {
+    match module.as_ref() {
+        Some(module) => into_c_string(module.mapper.call_graph().to_json()),
+        None => ptr::null_mut()
+    }
+}
+
+
+/// Describes the time spent mapping, expanding and lowering each node of a mapped module
+/// as a JSON object, or returns null if the handle is null
+///
+/// # Safety
+///
+/// `module` must be null or a handle returned by `wasm_pfc_map` that has not been freed.
+/// The returned string must be released with `wasm_pfc_free_string`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_pfc_profile(module:*const MappedModule) -> *mut c_char {
+    match module.as_ref() {
+        Some(module) => into_c_string(module.mapper.profile().to_json()),
+        None => ptr::null_mut()
+    }
+}
+
+
+/// Releases a mapped module
+///
+/// # Safety
+///
+/// `module` must be null or a handle returned by `wasm_pfc_map` that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_pfc_free_module(module:*mut MappedModule) {
+    if !module.is_null() {
+        drop(Box::from_raw(module));
+    }
+}
+
+
+/// Releases a string returned by any of the other functions
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by this interface that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_pfc_free_string(string:*mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+
+// hands a string over to the caller; JSON never contains interior nul bytes, since they are escaped
+fn into_c_string(string:String) -> *mut c_char {
+    match CString::new(string) {
+        Ok(string) => string.into_raw(),
+        Err(_) => ptr::null_mut()
+    }
+}
+
+
+// describes a constraint as a JSON object, with its penalties as lists of variables and coefficients
+fn constraint_json(constraint:&Constraint) -> String {
+    let expression = match constraint.get_expression() {
+        Some(expression) => json_string(&format!("{:?}", expression)),
+        None => "null".to_string()
+    };
+    let qubo = constraint.qubo();
+    let linear:Vec<String> = qubo.linear().iter().map(|(variable, coefficient)| format!("[{},{}]", variable, coefficient)).collect();
+    let quadratic:Vec<String> = qubo.quadratic().iter().map(|(&(u, v), coefficient)| format!("[{},{},{}]", u, v, coefficient)).collect();
+    format!("{{\"id\":{},\"expression\":{},\"qubo\":{{\"offset\":{},\"linear\":[{}],\"quadratic\":[{}]}}}}",
+        constraint.get_id(), expression, qubo.get_offset(), linear.join(","), quadratic.join(","))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    unsafe fn take_string(string:*mut c_char) -> String {
+        let owned = CStr::from_ptr(string).to_string_lossy().into_owned();
+        wasm_pfc_free_string(string);
+        owned
+    }
+
+    #[test]
+    fn null_handles() {
+        unsafe {
+            assert!(wasm_pfc_map(ptr::null(), 0).is_null());
+            assert!(wasm_pfc_nodes(ptr::null()).is_null());
+            assert!(wasm_pfc_lower(ptr::null_mut(), 0).is_null());
+            assert!(wasm_pfc_plan(ptr::null_mut()).is_null());
+            assert!(wasm_pfc_call_graph(ptr::null()).is_null());
+            assert!(wasm_pfc_profile(ptr::null()).is_null());
+            wasm_pfc_free_module(ptr::null_mut());
+            wasm_pfc_free_string(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn empty_module() {
+        let module = Box::into_raw(Box::new(MappedModule { mapper: new_mapper(), nodes: HashMap::new() }));
+        unsafe {
+            assert_eq!(take_string(wasm_pfc_nodes(module)), "[]");
+            assert_eq!(take_string(wasm_pfc_plan(module)), "[]");
+            assert_eq!(take_string(wasm_pfc_call_graph(module)), "{\"functions\":[],\"edges\":[],\"indirect\":[]}");
+            assert_eq!(take_string(wasm_pfc_profile(module)), "{\"reads\":0,\"nodes\":[],\"passes\":[]}");
+
+            // there is no node to lower
+            assert!(wasm_pfc_lower(module, 0).is_null());
+            wasm_pfc_free_module(module);
+        }
+    }
+
+    #[test]
+    fn profile() {
+        let buf = include_bytes!("../tests/parallelization/inline.wasm");
+        unsafe {
+            let module = wasm_pfc_map(buf.as_ptr(), buf.len());
+            assert!(!wasm_pfc_lower(module, 2).is_null());
+            let profile = take_string(wasm_pfc_profile(module));
+            assert!(profile.starts_with("{\"reads\":"));
+            assert!(profile.contains("{\"id\":2,"));
+            assert!((*module).mapper.profile().get(2).lowering > ::std::time::Duration::from_secs(0));
+            wasm_pfc_free_module(module);
+        }
+    }
+
+    #[test]
+    fn json_string() {
+        assert_eq!(super::json_string("Num { val: 1 }"), "\"Num { val: 1 }\"");
+        assert_eq!(super::json_string("\"a\\b\"\n\u{1}"), "\"\\\"a\\\\b\\\"\\n\\u0001\"");
+    }
+}
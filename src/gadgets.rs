@@ -0,0 +1,1328 @@
+//! # Gadgets
+//! QUBO encodings of primitive gates, which lowering builds the penalties of a node's operations from. A
+//! gadget is a quadratic function of binary variables that is zero exactly for the assignments where the
+//! gate's output agrees with its inputs and at least one for every other assignment, so adding gadgets up
+//! keeps the ground states of a circuit those where every gate holds. Most gadgets are the square of a
+//! linear equation over their variables, e.g. a full adder's `a + b + carry_in = sum + 2 * carry_out`, and
+//! gates that aren't pairwise on their own, like XOR, take an auxiliary variable. Gadgets only refer to
+//! their variables by index; a circuit allocates the variables and combines the gadgets of word-level
+//! operations, from constants to ripple-carry additions and shift-and-add multiplications. Additions of
+//! many operands can instead be counted a column at a time, saving each column's carries into the columns
+//! above, which takes fewer variables than a chain of adders. Multiplications can likewise be split
+//! Karatsuba style, which trades some of the variables of shift-and-add for the couplers of wider counts
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::mem;
+use std::ops;
+
+
+/// A QUBO is a quadratic function of binary variables, identified by
+/// index, with integer coefficients
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Qubo {
+    offset: i64, // the constant term
+    linear: BTreeMap<usize, i64>, // the coefficients of single variables, by variable
+    quadratic: BTreeMap<(usize, usize), i64> // the coefficients of pairs of variables, by pair with the lower index first
+}
+
+
+impl Qubo {
+    pub fn new() -> Qubo {
+        Qubo::default()
+    }
+
+    // adds to the constant term
+    pub fn add_offset(&mut self, coefficient:i64) {
+        self.offset += coefficient;
+    }
+
+    // adds to the coefficient of a variable, dropping it once it comes to zero
+    pub fn add_linear(&mut self, variable:usize, coefficient:i64) {
+        let sum = self.linear.get(&variable).cloned().unwrap_or(0) + coefficient;
+        if sum == 0 {
+            self.linear.remove(&variable);
+        } else {
+            self.linear.insert(variable, sum);
+        }
+    }
+
+    // adds to the coefficient of a pair of variables, dropping it once it comes to zero; a variable times
+    // itself is the variable, since it is binary
+    pub fn add_quadratic(&mut self, u:usize, v:usize, coefficient:i64) {
+        if u == v {
+            return self.add_linear(u, coefficient);
+        }
+        let pair = (u.min(v), u.max(v));
+        let sum = self.quadratic.get(&pair).cloned().unwrap_or(0) + coefficient;
+        if sum == 0 {
+            self.quadratic.remove(&pair);
+        } else {
+            self.quadratic.insert(pair, sum);
+        }
+    }
+
+    // returns the constant term
+    pub fn get_offset(&self) -> i64 {
+        self.offset
+    }
+
+    // borrows the coefficients of single variables, by variable
+    pub fn linear(&self) -> &BTreeMap<usize, i64> {
+        &self.linear
+    }
+
+    // borrows the coefficients of pairs of variables, by pair with the lower index first
+    pub fn quadratic(&self) -> &BTreeMap<(usize, usize), i64> {
+        &self.quadratic
+    }
+
+    // returns the variables with a coefficient, in order
+    pub fn variables(&self) -> Vec<usize> {
+        let mut variables:BTreeSet<usize> = self.linear.keys().cloned().collect();
+        for &(u, v) in self.quadratic.keys() {
+            variables.insert(u);
+            variables.insert(v);
+        }
+        variables.into_iter().collect()
+    }
+
+    // returns the function with every variable's index raised by the given amount, e.g. to keep one function's
+    // variables apart from another's before adding them up
+    pub fn shifted(&self, by:usize) -> Qubo {
+        Qubo {
+            offset: self.offset,
+            linear: self.linear.iter().map(|(variable, coefficient)| (variable + by, *coefficient)).collect(),
+            quadratic: self.quadratic.iter().map(|(&(u, v), coefficient)| ((u + by, v + by), *coefficient)).collect()
+        }
+    }
+
+    // checks whether the function is zero everywhere
+    pub fn is_empty(&self) -> bool {
+        self.offset == 0 && self.linear.is_empty() && self.quadratic.is_empty()
+    }
+
+    // pins a variable to a value, adding the penalty of the gadget holding it there
+    pub fn constrain_output(&mut self, variable:usize, value:bool) {
+        *self += fix(variable, value);
+    }
+
+    // substitutes values for some of the variables, which leaves the function over the others; the couplings of
+    // a set variable become coefficients of its partners, and its own coefficient joins the constant term
+    pub fn substitute(&mut self, values:&BTreeMap<usize, bool>) {
+        let linear = mem::take(&mut self.linear);
+        let quadratic = mem::take(&mut self.quadratic);
+        for (variable, coefficient) in linear {
+            match values.get(&variable) {
+                Some(true) => self.offset += coefficient,
+                Some(false) => (),
+                None => self.add_linear(variable, coefficient)
+            }
+        }
+        for ((u, v), coefficient) in quadratic {
+            match (values.get(&u), values.get(&v)) {
+                (Some(false), _) | (_, Some(false)) => (),
+                (Some(true), Some(true)) => self.offset += coefficient,
+                (Some(true), None) => self.add_linear(v, coefficient),
+                (None, Some(true)) => self.add_linear(u, coefficient),
+                (None, None) => self.add_quadratic(u, v, coefficient)
+            }
+        }
+    }
+
+    // returns the value of the function for an assignment of the variables, given as the set ones
+    pub fn energy(&self, set:&BTreeSet<usize>) -> i64 {
+        let linear:i64 = self.linear.iter()
+            .filter(|&(variable, _)| set.contains(variable))
+            .map(|(_, coefficient)| coefficient)
+            .sum();
+        let quadratic:i64 = self.quadratic.iter()
+            .filter(|&(&(u, v), _)| set.contains(&u) && set.contains(&v))
+            .map(|(_, coefficient)| coefficient)
+            .sum();
+        self.offset + linear + quadratic
+    }
+
+    // returns the lowest value of the function along with every assignment reaching it, as the set variables,
+    // by trying every assignment; this takes exponential time, so it is only meant for gadgets and small circuits
+    pub fn ground_states(&self) -> (i64, Vec<BTreeSet<usize>>) {
+        let variables = self.variables();
+        let mut lowest = i64::MAX;
+        let mut states = Vec::new();
+        for assignment in 0..1u64 << variables.len() {
+            let set:BTreeSet<usize> = variables.iter().enumerate()
+                .filter(|&(bit, _)| assignment >> bit & 1 == 1)
+                .map(|(_, variable)| *variable)
+                .collect();
+            let energy = self.energy(&set);
+            if energy < lowest {
+                lowest = energy;
+                states.clear();
+            }
+            if energy == lowest {
+                states.push(set);
+            }
+        }
+        (lowest, states)
+    }
+}
+
+
+// adds up two functions over the same variables
+impl ops::Add for Qubo {
+    type Output = Qubo;
+
+    fn add(mut self, other:Qubo) -> Qubo {
+        self += other;
+        self
+    }
+}
+
+
+impl ops::AddAssign for Qubo {
+    fn add_assign(&mut self, other:Qubo) {
+        self.offset += other.offset;
+        for (variable, coefficient) in other.linear {
+            self.add_linear(variable, coefficient);
+        }
+        for ((u, v), coefficient) in other.quadratic {
+            self.add_quadratic(u, v, coefficient);
+        }
+    }
+}
+
+
+// scales every coefficient, e.g. to weigh a gadget's penalty against others; scaling by zero empties it
+impl ops::Mul<i64> for Qubo {
+    type Output = Qubo;
+
+    fn mul(self, scalar:i64) -> Qubo {
+        let mut scaled = Qubo::new();
+        scaled.add_offset(self.offset * scalar);
+        for (variable, coefficient) in self.linear {
+            scaled.add_linear(variable, coefficient * scalar);
+        }
+        for ((u, v), coefficient) in self.quadratic {
+            scaled.add_quadratic(u, v, coefficient * scalar);
+        }
+        scaled
+    }
+}
+
+
+// writes the function in infix notation, the constant term first, then the single variables and the pairs in
+// order, e.g. `1 - x0 - x1 + 2 * x0 * x1`
+impl fmt::Display for Qubo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut terms:Vec<(i64, String)> = Vec::new();
+        if self.offset != 0 {
+            terms.push((self.offset, String::new()));
+        }
+        terms.extend(self.linear.iter().map(|(variable, coefficient)| (*coefficient, format!("x{}", variable))));
+        terms.extend(self.quadratic.iter().map(|(&(u, v), coefficient)| (*coefficient, format!("x{} * x{}", u, v))));
+        if terms.is_empty() {
+            return write!(f, "0");
+        }
+        for (index, (coefficient, variables)) in terms.iter().enumerate() {
+            let sign = if *coefficient < 0 { "-" } else { "+" };
+            match index {
+                0 if *coefficient < 0 => write!(f, "-")?,
+                0 => (),
+                _ => write!(f, " {} ", sign)?
+            }
+            match (coefficient.abs(), variables.is_empty()) {
+                (magnitude, true) => write!(f, "{}", magnitude)?,
+                (1, false) => write!(f, "{}", variables)?,
+                (magnitude, false) => write!(f, "{} * {}", magnitude, variables)?
+            }
+        }
+        Ok(())
+    }
+}
+
+
+// returns the square of a linear function of binary variables, given as weighted variables and a constant,
+// which is zero exactly where the function is and at least one elsewhere
+pub fn square(terms:&[(usize, i64)], constant:i64) -> Qubo {
+    let mut qubo = Qubo::new();
+    qubo.add_offset(constant * constant);
+    for (index, &(u, a)) in terms.iter().enumerate() {
+        qubo.add_linear(u, a * a + 2 * a * constant);
+        for &(v, b) in terms[index + 1..].iter() {
+            qubo.add_quadratic(u, v, 2 * a * b);
+        }
+    }
+    qubo
+}
+
+
+// holds a variable at a value
+pub fn fix(variable:usize, value:bool) -> Qubo {
+    square(&[(variable, 1)], -(value as i64))
+}
+
+
+// sets the output to the negation of the input
+pub fn not(a:usize, out:usize) -> Qubo {
+    square(&[(a, 1), (out, 1)], -1)
+}
+
+
+// sets the output to whether both inputs are set
+pub fn and(a:usize, b:usize, out:usize) -> Qubo {
+    let mut qubo = Qubo::new();
+    qubo.add_quadratic(a, b, 1);
+    qubo.add_quadratic(a, out, -2);
+    qubo.add_quadratic(b, out, -2);
+    qubo.add_linear(out, 3);
+    qubo
+}
+
+
+// sets the output to whether either input is set
+pub fn or(a:usize, b:usize, out:usize) -> Qubo {
+    let mut qubo = Qubo::new();
+    qubo.add_linear(a, 1);
+    qubo.add_linear(b, 1);
+    qubo.add_linear(out, 1);
+    qubo.add_quadratic(a, b, 1);
+    qubo.add_quadratic(a, out, -2);
+    qubo.add_quadratic(b, out, -2);
+    qubo
+}
+
+
+// sets the output to whether exactly one input is set; the auxiliary variable ends up set when both are
+pub fn xor(a:usize, b:usize, out:usize, aux:usize) -> Qubo {
+    square(&[(a, 1), (b, 1), (out, -1), (aux, -2)], 0)
+}
+
+
+// sets the output to whether the inputs are equal; the auxiliary variable ends up set when both are
+pub fn equality(a:usize, b:usize, out:usize, aux:usize) -> Qubo {
+    square(&[(a, 1), (b, 1), (out, 1), (aux, -2)], -1)
+}
+
+
+// sets the sum and carry to the two bits of the sum of two inputs
+pub fn half_adder(a:usize, b:usize, sum:usize, carry:usize) -> Qubo {
+    square(&[(a, 1), (b, 1), (sum, -1), (carry, -2)], 0)
+}
+
+
+// sets the sum and carry out to the two bits of the sum of two inputs and a carry in
+pub fn full_adder(a:usize, b:usize, carry_in:usize, sum:usize, carry_out:usize) -> Qubo {
+    square(&[(a, 1), (b, 1), (carry_in, 1), (sum, -1), (carry_out, -2)], 0)
+}
+
+
+// sets the output to the first input when the selector isn't set and to the second when it is, by way of the
+// negated selector and the two selected inputs, which the auxiliary variables end up holding
+pub fn multiplexer(select:usize, a:usize, b:usize, out:usize, aux:[usize; 3]) -> Qubo {
+    let [negated, first, second] = aux;
+    not(select, negated) + and(negated, a, first) + and(select, b, second) + or(first, second, out)
+}
+
+
+/// A circuit allocates binary variables and adds up the gadgets
+/// relating them; words are lists of variables, lowest bit first
+#[derive(Clone, Debug, Default)]
+pub struct Circuit {
+    qubo: Qubo, // the summed penalties of the gadgets added so far
+    variables: usize // how many variables have been allocated
+}
+
+
+impl Circuit {
+    pub fn new() -> Circuit {
+        Circuit::default()
+    }
+
+    // allocates a variable
+    pub fn variable(&mut self) -> usize {
+        self.variables += 1;
+        self.variables - 1
+    }
+
+    // allocates a word of unconstrained variables
+    pub fn word(&mut self, width:usize) -> Vec<usize> {
+        (0..width).map(|_| self.variable()).collect()
+    }
+
+    // adds a gadget's penalty to the circuit
+    pub fn add(&mut self, gadget:Qubo) {
+        self.qubo += gadget;
+    }
+
+    // allocates a word held at a constant, wrapped to the word's width
+    pub fn constant(&mut self, value:u64, width:usize) -> Vec<usize> {
+        let word = self.word(width);
+        for (bit, variable) in word.iter().enumerate() {
+            self.add(fix(*variable, bit < 64 && value >> bit & 1 == 1));
+        }
+        word
+    }
+
+    // returns a variable set to whether both inputs are
+    pub fn and(&mut self, a:usize, b:usize) -> usize {
+        let out = self.variable();
+        self.add(and(a, b, out));
+        out
+    }
+
+    // returns a variable set to whether either input is
+    pub fn or(&mut self, a:usize, b:usize) -> usize {
+        let out = self.variable();
+        self.add(or(a, b, out));
+        out
+    }
+
+    // returns a variable set to the negation of the input
+    pub fn not(&mut self, a:usize) -> usize {
+        let out = self.variable();
+        self.add(not(a, out));
+        out
+    }
+
+    // returns a variable set to whether exactly one input is
+    pub fn xor(&mut self, a:usize, b:usize) -> usize {
+        let (out, aux) = (self.variable(), self.variable());
+        self.add(xor(a, b, out, aux));
+        out
+    }
+
+    // returns a variable holding the first input when the selector isn't set and the second when it is
+    pub fn select(&mut self, select:usize, a:usize, b:usize) -> usize {
+        let out = self.variable();
+        let aux = [self.variable(), self.variable(), self.variable()];
+        self.add(multiplexer(select, a, b, out, aux));
+        out
+    }
+
+    // returns a variable set to whether any of the variables is, unless there are none
+    fn any(&mut self, variables:&[usize]) -> Option<usize> {
+        let mut variables = variables.iter();
+        let first = *variables.next()?;
+        Some(variables.fold(first, |any, variable| self.or(any, *variable)))
+    }
+
+    // returns a variable set to whether all of the variables are, unless there are none
+    fn all(&mut self, variables:&[usize]) -> Option<usize> {
+        let mut variables = variables.iter();
+        let first = *variables.next()?;
+        Some(variables.fold(first, |all, variable| self.and(all, *variable)))
+    }
+
+    // returns a variable set to whether none of the word's bits are, negating the OR of its bits
+    pub fn is_zero(&mut self, a:&[usize]) -> usize {
+        match self.any(a) {
+            Some(any) => self.not(any),
+            None => self.constant(1, 1)[0]
+        }
+    }
+
+    // returns the wrapping sum of two words of the same width, adding their bits with a ripple of carries
+    pub fn sum(&mut self, a:&[usize], b:&[usize]) -> Vec<usize> {
+        let width = a.len().min(b.len());
+        self.sum_to(a, b, width)
+    }
+
+    // returns the sum of two words of any widths to the given width, adding their bits with a ripple of carries;
+    // the sum is only as wide as the wider word and the carry out of it, if that is narrower
+    pub fn sum_to(&mut self, a:&[usize], b:&[usize], width:usize) -> Vec<usize> {
+        let mut word = Vec::new();
+        let mut carry:Option<usize> = None;
+        for bit in 0..width {
+            let inputs:Vec<usize> = a.get(bit).into_iter().chain(b.get(bit)).chain(carry.as_ref()).cloned().collect();
+            carry = match inputs[..] {
+                [] => break,
+                [input] => {
+                    word.push(input);
+                    None
+                }
+                _ => {
+                    let (sum, carry_out) = (self.variable(), self.variable());
+                    match inputs[..] {
+                        [a, b] => self.add(half_adder(a, b, sum, carry_out)),
+                        _ => self.add(full_adder(inputs[0], inputs[1], inputs[2], sum, carry_out))
+                    }
+                    word.push(sum);
+                    Some(carry_out)
+                }
+            };
+        }
+        word
+    }
+
+    // returns the wrapping sum of any number of words of the same width in carry-save form: each column's bits,
+    // along with the carries saved into it by the columns below, are counted by a single gadget, and the count's
+    // higher bits are saved into the columns above rather than rippling through a chain of adders
+    pub fn carry_save_sum(&mut self, words:&[Vec<usize>]) -> Vec<usize> {
+        let width = words.iter().map(|word| word.len()).min().unwrap_or(0);
+        let terms:Vec<(&[usize], usize, bool)> = words.iter().map(|word| (&word[..], 0, false)).collect();
+        self.carry_save_combination(&terms, width)
+    }
+
+    // returns the wrapping sum, to the given width, of words shifted left by the given numbers of bits and
+    // negated when marked so, in carry-save form; a negated word is added as its complement plus one, the
+    // complement's bits being counted as one less the word's, so a column's count is never negative
+    pub fn carry_save_combination(&mut self, terms:&[(&[usize], usize, bool)], width:usize) -> Vec<usize> {
+        let mut columns:Vec<Vec<(usize, i64)>> = vec![Vec::new(); width];
+        let mut constants:Vec<i64> = vec![0; width];
+        for &(word, shift, negated) in terms {
+            for (bit, variable) in word.iter().enumerate().filter(|&(bit, _)| shift + bit < width) {
+                columns[shift + bit].push((*variable, if negated { -1 } else { 1 }));
+            }
+            if negated && shift < width {
+                constants[shift] += 1;
+                for constant in constants[shift..].iter_mut() {
+                    *constant += 1;
+                }
+            }
+        }
+
+        let mut word = Vec::new();
+        for bit in 0..width {
+            let inputs = mem::take(&mut columns[bit]);
+            if constants[bit] == 0 && inputs.len() == 1 && inputs[0].1 == 1 {
+                word.push(inputs[0].0);
+                continue;
+            }
+
+            // the count's bits past the width still have to balance the column, but are carried nowhere
+            let most = inputs.iter().filter(|&&(_, weight)| weight > 0).count() + constants[bit] as usize;
+            let count = self.word(count_width(most).max(1));
+            let mut terms = inputs;
+            terms.extend(count.iter().enumerate().map(|(weight, variable)| (*variable, -(1 << weight))));
+            self.add(square(&terms, constants[bit]));
+            word.push(count[0]);
+            for (weight, carry) in count.iter().enumerate().skip(1) {
+                if bit + weight < width {
+                    columns[bit + weight].push((*carry, 1));
+                }
+            }
+        }
+        word
+    }
+
+    // returns the wrapping product of two words of the same width, adding up the first word's bits times the
+    // second word, shifted by the bits' positions; the bits shifted past the width are left out
+    pub fn product(&mut self, a:&[usize], b:&[usize]) -> Vec<usize> {
+        let width = a.len().min(b.len());
+        self.product_to(a, b, width)
+    }
+
+    // returns the product of two words of any widths to the given width, shifting and adding the wider word once
+    // per bit of the narrower one; the product is only as wide as its operands together, if that is narrower
+    pub fn product_to(&mut self, a:&[usize], b:&[usize], width:usize) -> Vec<usize> {
+        let (a, b) = (&a[..a.len().min(width)], &b[..b.len().min(width)]);
+        let (a, b) = if a.len() > b.len() { (b, a) } else { (a, b) };
+        let mut word:Vec<usize> = Vec::new();
+        for (shift, bit) in a.iter().enumerate() {
+            let row:Vec<usize> = b.iter().take(width - shift).map(|other| self.and(*bit, *other)).collect();
+            if shift == 0 {
+                word = row;
+                continue;
+            }
+            let sum = self.sum_to(&word[shift..], &row, width - shift);
+            word.truncate(shift);
+            word.extend(sum);
+        }
+        word
+    }
+
+    // returns the wrapping product of two words of the same width, Karatsuba style: words wider than the cutoff
+    // are split into halves, and the halves' cross terms are the product of the halves' sums less the products
+    // of the low halves and of the high halves, which takes three half-width products rather than four
+    pub fn karatsuba_product(&mut self, a:&[usize], b:&[usize], cutoff:usize) -> Vec<usize> {
+        let width = a.len().min(b.len());
+        self.karatsuba_product_to(a, b, width, cutoff)
+    }
+
+    // returns the product of two words of any widths to the given width, Karatsuba style
+    pub fn karatsuba_product_to(&mut self, a:&[usize], b:&[usize], width:usize, cutoff:usize) -> Vec<usize> {
+        self.karatsuba(a, b, width, cutoff.max(1))
+    }
+
+    // returns the product of two words to the given width; the products of the halves only need as many bits as
+    // are left below the width once they are shifted into place
+    fn karatsuba(&mut self, a:&[usize], b:&[usize], width:usize, cutoff:usize) -> Vec<usize> {
+        let (a, b) = (&a[..a.len().min(width)], &b[..b.len().min(width)]);
+        let bits = a.len().max(b.len());
+        if bits <= cutoff {
+            return self.column_product(a, b, width);
+        }
+
+        let half = bits / 2;
+        let (a_low, a_high) = a.split_at(half.min(a.len()));
+        let (b_low, b_high) = b.split_at(half.min(b.len()));
+        let upper = width - half;
+        let low = self.karatsuba(a_low, b_low, width.min(2 * half), cutoff);
+        let a_sum = self.carry_save_combination(&[(a_low, 0, false), (a_high, 0, false)], (bits - half + 1).min(upper));
+        let b_sum = self.carry_save_combination(&[(b_low, 0, false), (b_high, 0, false)], (bits - half + 1).min(upper));
+        let middle = self.karatsuba(&a_sum, &b_sum, upper, cutoff);
+        let high = self.karatsuba(a_high, b_high, upper, cutoff);
+        self.carry_save_combination(&[
+            (&low, 0, false),
+            (&middle, half, false),
+            (&low, half, true),
+            (&high, half, true),
+            (&high, 2 * half, false)
+        ], width)
+    }
+
+    // returns the product of two words to the given width, counting the columns of the first word's bits times
+    // the second word
+    fn column_product(&mut self, a:&[usize], b:&[usize], width:usize) -> Vec<usize> {
+        let mut rows = Vec::new();
+        for (shift, bit) in a.iter().enumerate().take(width) {
+            let row:Vec<usize> = b.iter().take(width - shift).map(|other| self.and(*bit, *other)).collect();
+            rows.push((row, shift));
+        }
+        let terms:Vec<(&[usize], usize, bool)> = rows.iter().map(|(row, shift)| (&row[..], *shift, false)).collect();
+        self.carry_save_combination(&terms, width)
+    }
+
+    // returns the product of a word and a constant to the given width, adding up copies of the word shifted to
+    // each of the constant's set bits rather than multiplying by the constant's bits
+    pub fn scaled(&mut self, a:&[usize], multiplier:u64, width:usize) -> Vec<usize> {
+        let terms:Vec<(&[usize], usize, bool)> = (0..64).filter(|shift| multiplier >> shift & 1 == 1).map(|shift| (a, shift, false)).collect();
+        match terms[..] {
+            [] => self.constant(0, width),
+            [(a, shift, _)] if shift < width => {
+                let mut word = self.constant(0, shift);
+                word.extend(a.iter().take(width - shift));
+                self.extend(&word, word.len(), false, width)
+            }
+            _ => self.carry_save_combination(&terms, width)
+        }
+    }
+
+    // returns the unsigned quotient and remainder of two words to the given width as words of their own, held to
+    // them by the dividend being the quotient times the divisor plus the remainder, and by the remainder being
+    // less than the divisor; this takes a multiplier's worth of variables, and as dividing by zero traps, no
+    // assignment escapes the penalties then
+    pub fn divide(&mut self, a:&[usize], b:&[usize], width:usize) -> (Vec<usize>, Vec<usize>) {
+        let (quotient, remainder) = (self.word(width), self.word(width));
+
+        // the product's rows, the remainder and the negated dividend balance out at a width none of them reach
+        let mut rows = Vec::new();
+        for (shift, bit) in quotient.iter().enumerate() {
+            let row:Vec<usize> = b.iter().map(|other| self.and(*bit, *other)).collect();
+            rows.push((row, shift));
+        }
+        let mut terms:Vec<(&[usize], usize, bool)> = rows.iter().map(|(row, shift)| (&row[..], *shift, false)).collect();
+        terms.push((&remainder, 0, false));
+        terms.push((a, 0, true));
+        let balance = self.carry_save_combination(&terms, width.max(a.len()) + b.len() + 1);
+        self.constrain(&balance, 0);
+
+        // the remainder is less than the divisor
+        let less = self.less_than(&remainder, b);
+        self.qubo.constrain_output(less, true);
+        (quotient, remainder)
+    }
+
+    // returns a variable set to whether the first word is less than the second, unsigned, which is when the
+    // first less the second is negative, so the sign bit of their difference a bit wider than either is set
+    pub fn less_than(&mut self, a:&[usize], b:&[usize]) -> usize {
+        let sign = a.len().max(b.len());
+        let difference = self.carry_save_combination(&[(a, 0, false), (b, 0, true)], sign + 1);
+        difference[sign]
+    }
+
+    // returns a variable set to whether the first of two floats of the same width, given by their bits, is less
+    // than the second, with the negative zero less than the positive one: floats of different signs are
+    // ordered by their signs, and floats of the same sign by their magnitudes, the other way around when they
+    // are negative; NaNs are ordered as if they were larger than infinity
+    pub fn float_less_than(&mut self, a:&[usize], b:&[usize]) -> usize {
+        let (sign_a, sign_b) = (a[a.len() - 1], b[b.len() - 1]);
+        let (magnitude_a, magnitude_b) = (&a[..a.len() - 1], &b[..b.len() - 1]);
+        let smaller = self.less_than(magnitude_a, magnitude_b);
+        let larger = self.less_than(magnitude_b, magnitude_a);
+        let ordered = self.select(sign_a, smaller, larger);
+        let different = self.xor(sign_a, sign_b);
+        self.select(different, ordered, sign_a)
+    }
+
+    // returns a variable set to whether a float, given by its bits, is a NaN, which is when its exponent, of the
+    // given number of bits below the sign, is all ones and its mantissa isn't zero
+    pub fn is_nan(&mut self, a:&[usize], exponent:usize) -> usize {
+        let mantissa = a.len() - 1 - exponent;
+        let saturated = self.all(&a[mantissa..a.len() - 1]);
+        match (saturated, self.any(&a[..mantissa])) {
+            (Some(saturated), Some(fraction)) => self.and(saturated, fraction),
+            _ => self.constant(0, 1)[0]
+        }
+    }
+
+    // returns the lesser of two floats of the same width, given by their bits, or the greater if the maximum is
+    // asked for, with the negative zero the lesser zero; if the width of the floats' exponent is given, a NaN
+    // operand makes the result the canonical NaN, whose bits are set by or-ing and cleared by and-ing with
+    // whether there is one, and otherwise the operands are assumed never to be NaN
+    pub fn float_extremum(&mut self, a:&[usize], b:&[usize], maximum:bool, exponent:Option<usize>) -> Vec<usize> {
+        let less = self.float_less_than(a, b);
+        let (unset, set) = if maximum { (a, b) } else { (b, a) };
+        let word:Vec<usize> = unset.iter().zip(set.iter()).map(|(unset, set)| self.select(less, *unset, *set)).collect();
+        let exponent = match exponent {
+            Some(exponent) => exponent,
+            None => return word
+        };
+        let (nan_a, nan_b) = (self.is_nan(a, exponent), self.is_nan(b, exponent));
+        let nan = self.or(nan_a, nan_b);
+        let number = self.not(nan);
+        let quiet = word.len() - 2 - exponent;
+        word.iter().enumerate().map(|(bit, variable)| match bit {
+            _ if bit == word.len() - 1 => self.and(*variable, number),
+            _ if bit >= quiet => self.or(*variable, nan),
+            _ => self.and(*variable, number)
+        }).collect()
+    }
+
+    // returns the number of set bits of a word to the given width, adding up the bits pairwise in a tree of
+    // adders whose sums widen by a bit at each level, so the word's bits are counted in logarithmic depth
+    pub fn popcount(&mut self, a:&[usize], width:usize) -> Vec<usize> {
+        let mut counts:Vec<Vec<usize>> = a.iter().map(|bit| vec![*bit]).collect();
+        while counts.len() > 1 {
+            counts = counts.chunks(2).map(|pair| match pair {
+                [lhs, rhs] => self.sum_to(lhs, rhs, lhs.len().max(rhs.len()) + 1),
+                _ => pair[0].clone()
+            }).collect();
+        }
+        let count = counts.pop().unwrap_or_default();
+        self.extend(&count, count.len(), false, width)
+    }
+
+    // returns the number of zero bits below a word's lowest set bit to the given width, or the given number of
+    // bits when none is set, which is the width of the value the word holds the lowest bits of
+    pub fn trailing_zeros(&mut self, a:&[usize], bits:usize, width:usize) -> Vec<usize> {
+        let (first, none) = self.priority(a);
+        let mut choices:Vec<(usize, u64)> = first.into_iter().zip(0..).collect();
+        choices.push((none, bits as u64));
+        self.encode(&choices, width)
+    }
+
+    // returns the number of zero bits above a word's highest set bit to the given width, counting from the
+    // given number of bits, of which the word holds the lowest while the rest are known to be clear
+    pub fn leading_zeros(&mut self, a:&[usize], bits:usize, width:usize) -> Vec<usize> {
+        let reversed:Vec<usize> = a.iter().rev().cloned().collect();
+        let (first, none) = self.priority(&reversed);
+        let clear = bits.saturating_sub(a.len()) as u64;
+        let mut choices:Vec<(usize, u64)> = first.into_iter().zip(clear..).collect();
+        choices.push((none, bits as u64));
+        self.encode(&choices, width)
+    }
+
+    // returns a variable per given variable, set only for the first of them that is set, along with a variable
+    // set when none of them are: each variable but the first is only the first set one if all before it are
+    // clear, and they still are after it exactly when they were and it isn't the first set one
+    fn priority(&mut self, variables:&[usize]) -> (Vec<usize>, usize) {
+        let mut first = Vec::new();
+        let mut clear:Option<usize> = None;
+        for variable in variables {
+            clear = Some(match clear {
+                None => {
+                    first.push(*variable);
+                    self.not(*variable)
+                }
+                Some(clear) => {
+                    let set = self.and(*variable, clear);
+                    let still_clear = self.variable();
+                    self.add(square(&[(clear, 1), (set, -1), (still_clear, -1)], 0));
+                    first.push(set);
+                    still_clear
+                }
+            });
+        }
+        let none = match clear {
+            Some(clear) => clear,
+            None => self.constant(1, 1)[0]
+        };
+        (first, none)
+    }
+
+    // returns a word to the given width holding the value paired with whichever of the variables is set, of
+    // which at most one may be, by setting each of the word's bits to whether any variable whose value has
+    // the bit set is; the bits above the largest value are left clear
+    fn encode(&mut self, choices:&[(usize, u64)], width:usize) -> Vec<usize> {
+        let most = choices.iter().map(|&(_, value)| value).max().unwrap_or(0);
+        let mut word = Vec::new();
+        for bit in 0..count_width(most as usize).min(width) {
+            let set:Vec<usize> = choices.iter().filter(|&&(_, value)| bit < 64 && value >> bit & 1 == 1).map(|&(variable, _)| variable).collect();
+            let variable = match self.any(&set) {
+                Some(variable) => variable,
+                None => self.constant(0, 1)[0]
+            };
+            word.push(variable);
+        }
+        self.extend(&word, word.len(), false, width)
+    }
+
+    // returns the word shifted left, or right filling in zeros or copies of its sign bit, by the amount another
+    // word holds, modulo the word's width, which is a power of two; a barrel of stages moves the word by each of
+    // the amount's bits in turn, and higher bits of the amount are ignored
+    pub fn shift(&mut self, a:&[usize], amount:&[usize], left:bool, signed:bool) -> Vec<usize> {
+        self.barrel(a, amount, left, if !left && signed { Fill::Sign } else { Fill::Zero })
+    }
+
+    // returns the word rotated left or right by the amount another word holds, modulo the word's width
+    pub fn rotate(&mut self, a:&[usize], amount:&[usize], left:bool) -> Vec<usize> {
+        self.barrel(a, amount, left, Fill::Wrap)
+    }
+
+    // returns the word shifted by a known amount, modulo its width, which only rewires its variables but for a
+    // constant to fill in zeros
+    pub fn shift_by(&mut self, a:&[usize], amount:u64, left:bool, signed:bool) -> Vec<usize> {
+        match (a.last(), (amount % a.len().max(1) as u64) as usize) {
+            (None, _) | (_, 0) => a.to_vec(),
+            (Some(sign), by) => {
+                let fill = if !left && signed { *sign } else { self.constant(0, 1)[0] };
+                sources(a.len(), by, left, false).iter().map(|source| source.map_or(fill, |source| a[source])).collect()
+            }
+        }
+    }
+
+    // returns the word rotated by a known amount, modulo its width, sharing its variables
+    pub fn rotate_by(&self, a:&[usize], amount:u64, left:bool) -> Vec<usize> {
+        let by = (amount % a.len().max(1) as u64) as usize;
+        sources(a.len(), by, left, true).iter().filter_map(|source| source.map(|source| a[source])).collect()
+    }
+
+    // each stage selects between the word and its copy moved by the weight of one of the amount's bits; bits
+    // moved in as zeros are cleared by the amount's bit instead, and bits that are the same either way are kept
+    fn barrel(&mut self, a:&[usize], amount:&[usize], left:bool, fill:Fill) -> Vec<usize> {
+        let mut word = a.to_vec();
+        for (stage, select) in amount.iter().take(count_width(a.len().saturating_sub(1))).enumerate() {
+            let sign = word[word.len() - 1];
+            let mut cleared = None;
+            word = sources(word.len(), 1 << stage, left, fill == Fill::Wrap).into_iter().enumerate().map(|(bit, source)| {
+                let kept = word[bit];
+                let moved = match (source, fill) {
+                    (Some(source), _) => Some(word[source]),
+                    (None, Fill::Sign) => Some(sign),
+                    _ => None
+                };
+                match moved {
+                    Some(moved) if moved == kept => kept,
+                    Some(moved) => self.select(*select, kept, moved),
+                    None => {
+                        let clear = *cleared.get_or_insert_with(|| self.not(*select));
+                        self.and(kept, clear)
+                    }
+                }
+            }).collect();
+        }
+        word
+    }
+
+    // returns the word's lowest bits sign or zero extended to the given width, sharing the word's variables
+    pub fn extend(&mut self, a:&[usize], bits:usize, signed:bool, width:usize) -> Vec<usize> {
+        let bits = bits.min(a.len()).min(width);
+        let mut word = a[..bits].to_vec();
+        if word.len() < width {
+            let fill = match (signed, word.last()) {
+                (true, Some(sign)) => *sign,
+                _ => self.constant(0, 1)[0]
+            };
+            word.resize(width, fill);
+        }
+        word
+    }
+
+    // returns a two's complement word saturated to the range of a signed or unsigned integer of the given bits,
+    // extended to the given width: a value out of range is replaced by the bound it crossed, which is told by
+    // the word's sign and whether its bits above the range differ from what the range allows
+    pub fn clamp(&mut self, a:&[usize], bits:usize, signed:bool, width:usize) -> Vec<usize> {
+        let (sign, bits) = match a.last() {
+            Some(sign) if bits > 0 => (*sign, bits.min(width)),
+            _ => return self.constant(0, width)
+        };
+        let word = if signed {
+
+            // the bits from the range's sign bit up must all equal the word's sign, and the range's sign bit is
+            // the word's either way
+            if a.len() <= bits {
+                return self.extend(a, a.len(), true, width);
+            }
+            let differences:Vec<usize> = a[bits - 1..a.len() - 1].iter().map(|bit| self.xor(*bit, sign)).collect();
+            let overflow = self.any(&differences).unwrap();
+            let bound = self.not(sign);
+            let mut word:Vec<usize> = a[..bits - 1].iter().map(|bit| self.select(overflow, *bit, bound)).collect();
+            word.push(sign);
+            word
+        } else {
+
+            // a negative word saturates to zero, and a positive one with any bit set above the range to all ones
+            let positive = self.not(sign);
+            let overflow = self.any(&a[bits.min(a.len() - 1)..a.len() - 1]);
+            a[..bits.min(a.len() - 1)].iter().map(|bit| {
+                let saturated = match overflow {
+                    Some(overflow) => self.or(*bit, overflow),
+                    None => *bit
+                };
+                self.and(saturated, positive)
+            }).collect()
+        };
+        self.extend(&word, bits, signed, width)
+    }
+
+    // pins a word to a value, holding each of its bits; a value the word is too narrow for can't be held, so no
+    // assignment escapes the penalty then
+    pub fn constrain(&mut self, word:&[usize], value:u64) {
+        for (bit, variable) in word.iter().enumerate() {
+            self.qubo.constrain_output(*variable, bit < 64 && value >> bit & 1 == 1);
+        }
+        if word.len() < 64 && value >> word.len() != 0 {
+            self.qubo.add_offset(1);
+        }
+    }
+
+    // returns how many variables have been allocated
+    pub fn variable_count(&self) -> usize {
+        self.variables
+    }
+
+    // borrows the summed penalties of the circuit's gadgets
+    pub fn qubo(&self) -> &Qubo {
+        &self.qubo
+    }
+
+    // returns the summed penalties of the circuit's gadgets
+    pub fn into_qubo(self) -> Qubo {
+        self.qubo
+    }
+}
+
+
+/// A fill tells what takes the places a barrel shifter's bits leave
+#[derive(Clone, Copy, PartialEq)]
+enum Fill {
+    Zero, // zeros, clearing the bits
+    Sign, // copies of the word's sign bit
+    Wrap // the bits moved out of the word's other end
+}
+
+
+// returns which bit of a word of the given number of bits each of its bits comes from once they are moved up or
+// down by fewer places than it has, which is none for the places left behind unless the bits moved out wrap around
+fn sources(bits:usize, by:usize, left:bool, wrap:bool) -> Vec<Option<usize>> {
+    (0..bits).map(|bit| match (left, wrap) {
+        (true, true) => Some((bit + bits - by) % bits),
+        (false, true) => Some((bit + by) % bits),
+        (true, false) => bit.checked_sub(by),
+        (false, false) => Some(bit + by).filter(|source| *source < bits)
+    }).collect()
+}
+
+
+// returns the value a word holds in an assignment of its variables, given as the set ones
+pub fn word_value(word:&[usize], state:&BTreeSet<usize>) -> u64 {
+    word.iter().enumerate().filter(|&(_, variable)| state.contains(variable)).map(|(bit, _)| 1 << bit).sum()
+}
+
+
+// returns how many bits it takes to count the given number of set bits
+fn count_width(bits:usize) -> usize {
+    64 - (bits as u64).leading_zeros() as usize
+}
+
+
+// returns how many variables a carry-save sum of the given number of words of the given width allocates
+pub fn carry_save_variables(operands:usize, width:usize) -> usize {
+    let mut columns = vec![operands; width];
+    let mut variables = 0;
+    for bit in 0..width {
+        if columns[bit] < 2 {
+            continue;
+        }
+        let count = count_width(columns[bit]);
+        variables += count;
+        for weight in 1..count.min(width - bit) {
+            columns[bit + weight] += 1;
+        }
+    }
+    variables
+}
+
+
+// returns how many variables a popcount of a word of the given number of bits to the given width allocates
+pub fn popcount_variables(bits:usize, width:usize) -> usize {
+    let mut counts = vec![1; bits];
+    let mut variables = 0;
+    while counts.len() > 1 {
+        counts = counts.chunks(2).map(|pair| match *pair {
+            [lhs, rhs] => {
+                variables += 2 * lhs.max(rhs);
+                lhs.max(rhs) + 1
+            }
+            _ => pair[0]
+        }).collect();
+    }
+
+    // a count narrower than the width is padded with a constant, as is a missing one
+    variables + if counts.first().map_or(0, |count| *count) < width { 1 } else { 0 }
+}
+
+
+// returns how many variables counting the leading or trailing zeros of a word of the given number of bits to
+// the given width allocates
+pub fn zero_count_variables(bits:usize, width:usize) -> usize {
+
+    // the priority chain takes two variables per bit but the first, or a constant if there are none, and each
+    // of the encoder's bits one less than the number of values having it set, or a constant if none has
+    let encoded = count_width(bits).min(width);
+    let gates:usize = (0..encoded).map(|bit| match (0..=bits).filter(|value| value >> bit & 1 == 1).count() {
+        0 => 1,
+        set => set - 1
+    }).sum();
+    2 * bits.max(1) - 1 + gates + if encoded < width { 1 } else { 0 }
+}
+
+
+// returns how many variables a barrel shifter of a word of the given number of bits, which is a power of two,
+// allocates: a multiplexer per bit moved in from the word, and for zeros filled in a gate per bit and another for
+// the stage
+pub fn shift_variables(bits:usize, signed:bool, rotate:bool) -> usize {
+    (0..count_width(bits.saturating_sub(1))).map(|stage| match (rotate, signed) {
+        (true, _) => 4 * bits,
+        (false, true) => 4 * (bits - 1),
+        (false, false) => 4 * (bits - (1 << stage)) + (1 << stage) + 1
+    }).sum()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // checks that a gadget's ground states are at zero and are exactly the given assignments of its variables,
+    // each given as whether the variables, in order, are set, and that every other assignment costs at least one
+    fn assert_ground_states(gadget:&Qubo, variables:&[usize], expected:&[&[bool]]) {
+        let (lowest, states) = gadget.ground_states();
+        assert_eq!(lowest, 0);
+        let mut expected:Vec<BTreeSet<usize>> = expected.iter()
+            .map(|values| variables.iter().zip(values.iter()).filter(|&(_, set)| *set).map(|(variable, _)| *variable).collect())
+            .collect();
+        expected.sort();
+        let mut states = states;
+        states.sort();
+        assert_eq!(states, expected);
+        for assignment in 0..1u64 << variables.len() {
+            let set:BTreeSet<usize> = variables.iter().enumerate().filter(|&(bit, _)| assignment >> bit & 1 == 1).map(|(_, variable)| *variable).collect();
+            assert!(states.contains(&set) || gadget.energy(&set) >= 1);
+        }
+    }
+
+    // enumerates the rows of a truth table over the given number of inputs, lowest input first
+    fn rows(inputs:usize) -> Vec<Vec<bool>> {
+        (0..1 << inputs).map(|row:usize| (0..inputs).map(|bit| row >> bit & 1 == 1).collect()).collect()
+    }
+
+    #[test]
+    fn gates() {
+        assert_ground_states(&fix(0, true), &[0], &[&[true]]);
+        assert_ground_states(&not(0, 1), &[0, 1], &[&[false, true], &[true, false]]);
+
+        // two-input gates, with the auxiliary variable of XOR and equality set when both inputs are
+        let table = |gate:&dyn Fn(bool, bool) -> bool, aux:bool| -> Vec<Vec<bool>> {
+            rows(2).into_iter().map(|row| {
+                let mut values = vec![row[0], row[1], gate(row[0], row[1])];
+                if aux {
+                    values.push(row[0] && row[1]);
+                }
+                values
+            }).collect()
+        };
+        let check = |gadget:Qubo, variables:&[usize], table:Vec<Vec<bool>>| {
+            let table:Vec<&[bool]> = table.iter().map(|row| &row[..]).collect();
+            assert_ground_states(&gadget, variables, &table);
+        };
+        check(and(0, 1, 2), &[0, 1, 2], table(&|a, b| a && b, false));
+        check(or(0, 1, 2), &[0, 1, 2], table(&|a, b| a || b, false));
+        check(xor(0, 1, 2, 3), &[0, 1, 2, 3], table(&|a, b| a != b, true));
+        check(equality(0, 1, 2, 3), &[0, 1, 2, 3], table(&|a, b| a == b, true));
+        check(half_adder(0, 1, 2, 3), &[0, 1, 2, 3], table(&|a, b| a != b, true));
+
+        // a full adder's sum and carry out are the bits of its inputs' count
+        let adds:Vec<Vec<bool>> = rows(3).into_iter().map(|row| {
+            let count = row.iter().filter(|set| **set).count();
+            vec![row[0], row[1], row[2], count & 1 == 1, count >> 1 == 1]
+        }).collect();
+        check(full_adder(0, 1, 2, 3, 4), &[0, 1, 2, 3, 4], adds);
+
+        // a multiplexer's auxiliary variables hold the negated selector and the selected inputs
+        let selections:Vec<Vec<bool>> = rows(3).into_iter().map(|row| {
+            let (select, a, b) = (row[0], row[1], row[2]);
+            vec![select, a, b, if select { b } else { a }, !select, !select && a, select && b]
+        }).collect();
+        check(multiplexer(0, 1, 2, 3, [4, 5, 6]), &[0, 1, 2, 3, 4, 5, 6], selections);
+
+        assert_eq!(not(0, 1).to_string(), "1 - x0 - x1 + 2 * x0 * x1");
+        let zero = 0;
+        assert!((and(0, 1, 2) * zero).is_empty());
+    }
+
+    #[test]
+    fn words() {
+        // the only ground state of constant operands holds their wrapping sum and product
+        for (a, b) in (0..16u64).map(|pair| (pair & 3, pair >> 2)) {
+            let mut circuit = Circuit::new();
+            let (lhs, rhs) = (circuit.constant(a, 2), circuit.constant(b, 2));
+            let sum = circuit.sum(&lhs, &rhs);
+            let product = circuit.product(&lhs, &rhs);
+            let extended = circuit.extend(&lhs, 1, true, 3);
+            let (lowest, states) = circuit.qubo().ground_states();
+            assert_eq!((lowest, states.len()), (0, 1));
+            let value = |word:&[usize]| word.iter().enumerate().filter(|&(_, variable)| states[0].contains(variable)).map(|(bit, _)| 1 << bit).sum::<u64>();
+            assert_eq!(value(&sum), (a + b) % 4);
+            assert_eq!(value(&product), a * b % 4);
+            assert_eq!(value(&extended), if a & 1 == 1 { 0b111 } else { 0 });
+
+            // substituting the operands' values leaves the same ground state without them
+            let mut qubo = circuit.qubo().clone();
+            qubo.substitute(&lhs.iter().chain(rhs.iter()).map(|variable| (*variable, states[0].contains(variable))).collect());
+            assert!(lhs.iter().chain(rhs.iter()).all(|variable| !qubo.variables().contains(variable)));
+            assert_eq!(qubo.ground_states(), (0, vec![states[0].iter().filter(|variable| !lhs.contains(variable) && !rhs.contains(variable)).cloned().collect()]));
+        }
+    }
+
+    #[test]
+    fn clamping() {
+        // four bit words saturate to the two bit ranges, -2 to 1 signed and 0 to 3 unsigned, extended to four bits
+        for value in 0..16u64 {
+            let signed = if value >= 8 { value as i64 - 16 } else { value as i64 };
+            for &(range_signed, expected) in [(true, signed.clamp(-2, 1) as u64 & 15), (false, signed.clamp(0, 3) as u64)].iter() {
+                let mut circuit = Circuit::new();
+                let word = circuit.constant(value, 4);
+                let clamped = circuit.clamp(&word, 2, range_signed, 4);
+                let (lowest, states) = circuit.qubo().ground_states();
+                assert_eq!((lowest, states.len()), (0, 1));
+                assert_eq!(word_value(&clamped, &states[0]), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn division() {
+        // constant operands leave a single ground state holding their quotient and remainder, which rules out a
+        // remainder as large as the divisor, or none at all when dividing by zero
+        for (a, b) in (0..4u64).map(|pair| (pair & 1, pair >> 1)) {
+            let mut circuit = Circuit::new();
+            let (dividend, divisor) = (circuit.constant(a, 1), circuit.constant(b, 1));
+            let (quotient, remainder) = circuit.divide(&dividend, &divisor, 1);
+            let mut qubo = circuit.qubo().clone();
+            qubo.substitute(&vec![(dividend[0], a == 1), (divisor[0], b == 1)].into_iter().collect());
+            let (lowest, states) = qubo.ground_states();
+            if b == 0 {
+                assert!(lowest > 0);
+                continue;
+            }
+            assert_eq!((lowest, states.len()), (0, 1));
+            assert_eq!((word_value(&quotient, &states[0]), word_value(&remainder, &states[0])), (a / b, a % b));
+        }
+
+        // a constant multiple adds up shifted copies of the word
+        for &(value, multiplier) in [(3u64, 5u64), (2, 4), (3, 0), (1, 6)].iter() {
+            let mut circuit = Circuit::new();
+            let word = circuit.constant(value, 2);
+            let product = circuit.scaled(&word, multiplier, 4);
+            let (lowest, states) = circuit.qubo().ground_states();
+            assert_eq!((lowest, states.len()), (0, 1));
+            assert_eq!(word_value(&product, &states[0]), value * multiplier % 16);
+        }
+    }
+
+    // a count of a word's bits to build, along with the value it should hold and how many variables it is
+    // estimated to take, if it is
+    type BitCount<'a> = (&'a dyn Fn(&mut Circuit, &[usize]) -> Vec<usize>, u64, Option<usize>);
+
+    #[test]
+    fn bit_counting() {
+        // each count of a four bit word has a single ground state holding it, and takes as many variables as are
+        // estimated for it; the word's leading zeros are also counted as the lowest bits of a six bit value
+        for value in 0..16u64 {
+            let counts:[BitCount; 4] = [
+                (&|circuit, word| circuit.popcount(word, 3), value.count_ones() as u64, Some(popcount_variables(4, 3))),
+                (&|circuit, word| circuit.trailing_zeros(word, 4, 3), (value | 16).trailing_zeros() as u64, Some(zero_count_variables(4, 3))),
+                (&|circuit, word| circuit.leading_zeros(word, 4, 3), (value as u8).leading_zeros() as u64 - 4, Some(zero_count_variables(4, 3))),
+                (&|circuit, word| circuit.leading_zeros(word, 6, 3), (value as u8).leading_zeros() as u64 - 2, None)
+            ];
+            for &(count, expected, variables) in counts.iter() {
+                let mut circuit = Circuit::new();
+                let word = circuit.constant(value, 4);
+                let result = count(&mut circuit, &word);
+                if let Some(variables) = variables {
+                    assert_eq!(circuit.variable_count() - word.len(), variables);
+                }
+                let mut qubo = circuit.qubo().clone();
+                qubo.substitute(&word.iter().enumerate().map(|(bit, variable)| (*variable, value >> bit & 1 == 1)).collect());
+                let (lowest, states) = qubo.ground_states();
+                assert_eq!((lowest, states.len()), (0, 1));
+                assert_eq!(word_value(&result, &states[0]), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_detection() {
+        // a four bit word is zero in exactly one ground state, and an empty word always is
+        let mut circuit = Circuit::new();
+        let word = circuit.word(4);
+        let zero = circuit.is_zero(&word);
+        assert_eq!(circuit.variable_count() - word.len(), 4);
+        let (lowest, states) = circuit.qubo().ground_states();
+        assert_eq!((lowest, states.len()), (0, 16));
+        for state in states.iter() {
+            assert_eq!(state.contains(&zero), word_value(&word, state) == 0);
+        }
+        let mut circuit = Circuit::new();
+        let zero = circuit.is_zero(&[]);
+        let (_, states) = circuit.qubo().ground_states();
+        assert!(states.iter().all(|state| state.contains(&zero)));
+    }
+
+    #[test]
+    fn shifts() {
+        // a two bit word is moved by the lowest bit of the amount, whose higher bit is ignored, each way a shift
+        // can move it; the expected values are the word's bits, lowest first, with zeros or its sign filled in
+        let shifted = |value:u64, by:u64, kind:usize| match (kind, by & 1) {
+            (_, 0) => value,
+            (0, _) => value << 1 & 3,
+            (1, _) => value >> 1 | value & 2,
+            (2, _) => value >> 1,
+            _ => (value << 1 | value >> 1) & 3
+        };
+        for (value, by) in (0..16u64).map(|pair| (pair & 3, pair >> 2)) {
+            for kind in 0..5 {
+                let mut circuit = Circuit::new();
+                let (word, amount) = (circuit.word(2), circuit.word(2));
+                let result = match kind {
+                    0 => circuit.shift(&word, &amount, true, false),
+                    1 => circuit.shift(&word, &amount, false, true),
+                    2 => circuit.shift(&word, &amount, false, false),
+                    3 => circuit.rotate(&word, &amount, true),
+                    _ => circuit.rotate(&word, &amount, false)
+                };
+                assert_eq!(circuit.variable_count() - 4, shift_variables(2, kind == 1, kind > 2));
+                let mut qubo = circuit.qubo().clone();
+                qubo.substitute(&word.iter().chain(amount.iter()).enumerate().map(|(bit, variable)| (*variable, (value | by << 2) >> bit & 1 == 1)).collect());
+                let (lowest, states) = qubo.ground_states();
+                assert_eq!((lowest, states.len()), (0, 1));
+                let set:BTreeSet<usize> = states[0].iter().cloned().chain(word.iter().enumerate().filter(|&(bit, _)| value >> bit & 1 == 1).map(|(_, variable)| *variable)).collect();
+                assert_eq!(word_value(&result, &set), shifted(value, by, kind), "{} by {} ({})", value, by, kind);
+            }
+        }
+
+        // a known amount rewires an eight bit word's variables, with a constant for the zeros filled in
+        for (value, by) in [(0xb4u64, 3u64), (0x81, 9), (0x7f, 15), (0x96, 0)].iter().cloned() {
+            let mut circuit = Circuit::new();
+            let word = circuit.word(8);
+            let set:BTreeSet<usize> = word.iter().enumerate().filter(|&(bit, _)| value >> bit & 1 == 1).map(|(_, variable)| *variable).collect();
+            let moved = (by % 8) as u32;
+            let signed = ((value as u8 as i8) >> moved) as u8 as u64;
+            assert_eq!(word_value(&circuit.shift_by(&word, by, true, false), &set), value << moved & 0xff);
+            assert_eq!(word_value(&circuit.shift_by(&word, by, false, true), &set), signed);
+            assert_eq!(word_value(&circuit.shift_by(&word, by, false, false), &set), value >> moved);
+            assert_eq!(word_value(&circuit.rotate_by(&word, by, true), &set), (value as u8).rotate_left(moved) as u64);
+            assert_eq!(word_value(&circuit.rotate_by(&word, by, false), &set), (value as u8).rotate_right(moved) as u64);
+            assert!(circuit.variable_count() <= 8 + 2);
+        }
+
+        // a wider barrel takes a stage per bit of the amount below its width's log, and as many variables as estimated
+        for &(bits, signed, rotate) in [(8, false, false), (8, true, false), (32, false, true)].iter() {
+            let mut circuit = Circuit::new();
+            let (word, amount) = (circuit.word(bits), circuit.word(bits));
+            match rotate {
+                true => circuit.rotate(&word, &amount, false),
+                false => circuit.shift(&word, &amount, !signed, signed)
+            };
+            assert_eq!(circuit.variable_count() - 2 * bits, shift_variables(bits, signed, rotate));
+        }
+    }
+
+    #[test]
+    fn float_comparisons() {
+        // two bit words are compared exactly, whichever is wider
+        for (a, b) in (0..32u64).map(|pair| (pair & 3, pair >> 2)) {
+            let mut circuit = Circuit::new();
+            let (lhs, rhs) = (circuit.word(2), circuit.word(3));
+            let less = circuit.less_than(&lhs, &rhs);
+            let mut qubo = circuit.qubo().clone();
+            qubo.substitute(&lhs.iter().chain(rhs.iter()).enumerate().map(|(bit, variable)| (*variable, (a | b << 2) >> bit & 1 == 1)).collect());
+            let (lowest, states) = qubo.ground_states();
+            assert_eq!((lowest, states.len()), (0, 1));
+            assert_eq!(states[0].contains(&less), a < b);
+        }
+
+        // a float of four bits has a sign, two bits of exponent and a bit of mantissa, so it is a NaN when all but
+        // its sign bit are set, whatever the sign bit, which isn't read
+        let mut circuit = Circuit::new();
+        let float = circuit.word(4);
+        let nan = circuit.is_nan(&float, 2);
+        let (lowest, states) = circuit.qubo().ground_states();
+        assert_eq!((lowest, states.len()), (0, 8));
+        for state in states.iter() {
+            assert_eq!(state.contains(&nan), word_value(&float, state) & 7 == 7);
+        }
+    }
+
+    #[test]
+    fn mixed_widths() {
+        // words of different widths are added and multiplied exactly, up to the width of the result
+        for (a, b) in (0..16u64).map(|pair| (pair & 7, pair >> 3)) {
+            let mut circuit = Circuit::new();
+            let (lhs, rhs) = (circuit.constant(a, 3), circuit.constant(b, 1));
+            let sum = circuit.sum_to(&lhs, &rhs, 4);
+            let product = circuit.product_to(&rhs, &lhs, 3);
+            let (lowest, states) = circuit.qubo().ground_states();
+            assert_eq!((lowest, states.len()), (0, 1));
+            let value = |word:&[usize]| word.iter().enumerate().filter(|&(_, variable)| states[0].contains(variable)).map(|(bit, _)| 1 << bit).sum::<u64>();
+            assert_eq!((value(&sum), value(&product)), (a + b, a * b));
+            assert_eq!((sum.len(), product.len()), (4, 3));
+        }
+    }
+
+    #[test]
+    fn karatsuba() {
+        // split down to single bits, the halves' products are recombined into the wrapping product
+        for (a, b) in (0..16u64).map(|pair| (pair & 3, pair >> 2)) {
+            let mut circuit = Circuit::new();
+            let (lhs, rhs) = (circuit.constant(a, 2), circuit.constant(b, 2));
+            let product = circuit.karatsuba_product(&lhs, &rhs, 1);
+            let (lowest, states) = circuit.qubo().ground_states();
+            assert_eq!((lowest, states.len()), (0, 1));
+            let value:u64 = product.iter().enumerate().filter(|&(_, variable)| states[0].contains(variable)).map(|(bit, _)| 1 << bit).sum();
+            assert_eq!(value, a * b % 4);
+        }
+
+        // subtracting a word counts its complement, so a column holding only negated bits still balances
+        let mut circuit = Circuit::new();
+        let (lhs, rhs) = (circuit.constant(1, 2), circuit.constant(2, 2));
+        let difference = circuit.carry_save_combination(&[(&lhs, 0, false), (&rhs, 0, true), (&lhs, 1, true)], 3);
+        let (lowest, states) = circuit.qubo().ground_states();
+        assert_eq!((lowest, states.len()), (0, 1));
+        let value:u64 = difference.iter().enumerate().filter(|&(_, variable)| states[0].contains(variable)).map(|(bit, _)| 1 << bit).sum();
+        // 1 - 2 - 2 wraps around to 5 in three bits
+        assert_eq!(value, 5);
+    }
+
+    #[test]
+    fn carry_save() {
+        for &values in [[1u64, 2, 3], [3, 3, 3], [0, 1, 0]].iter() {
+            let mut circuit = Circuit::new();
+            let words:Vec<Vec<usize>> = values.iter().map(|value| circuit.constant(*value, 2)).collect();
+            let allocated = circuit.variable_count();
+            let sum = circuit.carry_save_sum(&words);
+            assert_eq!(circuit.variable_count() - allocated, carry_save_variables(3, 2));
+            let (lowest, states) = circuit.qubo().ground_states();
+            assert_eq!((lowest, states.len()), (0, 1));
+            let value:u64 = sum.iter().enumerate().filter(|&(_, variable)| states[0].contains(variable)).map(|(bit, _)| 1 << bit).sum();
+            assert_eq!(value, values.iter().sum::<u64>() % 4);
+        }
+
+        // counting columns takes fewer variables than a chain of adders, more so the more operands there are
+        assert_eq!(carry_save_variables(2, 32), 2 * 32);
+        assert!(carry_save_variables(3, 32) < 2 * 2 * 32);
+        assert!(carry_save_variables(8, 32) < 2 * 32 * 7 / 2);
+    }
+}
@@ -0,0 +1,315 @@
+//! # Harness
+//! Test harnesses running the paths the analysis picks out of a module, e.g. its critical path, so that
+//! what the analysis predicts can be checked by timing the path on a conventional runtime. A harness is
+//! the module itself with a `_start` function appended, which calls the functions along the path in order
+//! with the arguments the user gives, dropping their results, as many times as asked and at least once.
+//! WASI runtimes run a module's `_start` export, so the harness runs as it is, e.g. with
+//! `time wasmtime harness.wasm`, and the functions on the path don't have to be exported
+
+use crate::{BinaryReader, BinaryReaderError, ExternalKind, FuncType, ImportSectionEntryType, Result, Type};
+use crate::{ExportSectionReader, FunctionSectionReader, ImportSectionReader, TypeSectionReader};
+use emit::{write_types, write_var_u32};
+
+
+// the ids of the sections that are extended; every other section is copied as is
+const TYPE_SECTION:u32 = 1;
+const IMPORT_SECTION:u32 = 2;
+const FUNCTION_SECTION:u32 = 3;
+const EXPORT_SECTION:u32 = 7;
+const CODE_SECTION:u32 = 10;
+
+// the name WASI runtimes run a module by
+pub const ENTRY_POINT:&str = "_start";
+
+
+/// A path call is a call the harness makes to a function on the path,
+/// with the bits of each of its arguments, the way the interpreter
+/// holds values
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathCall {
+    pub function: usize, // the index of the function, counting imported functions
+    pub args: Vec<u64> // the arguments, with floats given by their bits
+}
+
+
+impl PathCall {
+    pub fn new(function:usize, args:Vec<u64>) -> PathCall {
+        PathCall { function, args }
+    }
+}
+
+
+// returns where the sections with the given id go among the others, which custom sections don't have
+fn order(id:u32) -> Option<u32> {
+    match id {
+        1..=9 => Some(id),
+        12 => Some(10), // the data count section comes before the code
+        10 | 11 => Some(id + 1),
+        _ => None
+    }
+}
+
+
+// builds a harness calling the given functions of the module in order, running the calls the given number of
+// times; fails if a function doesn't exist, is given the wrong number of arguments or takes a vector, or if
+// the module already exports an entry point
+pub fn path_harness(buf:&[u8], calls:&[PathCall], repeat:usize) -> Result<Vec<u8>> {
+    let mut reader = BinaryReader::new(buf);
+    reader.read_file_header()?;
+
+    // sections are kept as their ids and payload ranges
+    let mut sections:Vec<(u32, usize, usize)> = Vec::new();
+    while !reader.eof() {
+        let id = reader.read_var_u7()?;
+        let len = reader.read_var_u32()? as usize;
+        let start = reader.original_position();
+        reader.read_bytes(len)?;
+        sections.push((id, start, start + len));
+    }
+
+    // the signature of every function, imported or not, is needed to pass its arguments
+    let mut types:Vec<FuncType> = Vec::new();
+    let mut functions:Vec<u32> = Vec::new();
+    let mut defined:Vec<u32> = Vec::new();
+    let mut exports = 0;
+    for &(id, start, end) in sections.iter() {
+        match id {
+            TYPE_SECTION => {
+                let mut section = TypeSectionReader::new(&buf[start..end], start)?;
+                for _ in 0..section.get_count() {
+                    types.push(section.read()?);
+                }
+            }
+            IMPORT_SECTION => {
+                let mut section = ImportSectionReader::new(&buf[start..end], start)?;
+                for _ in 0..section.get_count() {
+                    if let ImportSectionEntryType::Function(ty) = section.read()?.ty {
+                        functions.push(ty);
+                    }
+                }
+            }
+            FUNCTION_SECTION => {
+                let mut section = FunctionSectionReader::new(&buf[start..end], start)?;
+                for _ in 0..section.get_count() {
+                    defined.push(section.read()?);
+                }
+            }
+            EXPORT_SECTION => {
+                let mut section = ExportSectionReader::new(&buf[start..end], start)?;
+                exports = section.get_count();
+                for _ in 0..exports {
+                    let export = section.read()?;
+                    if export.field == ENTRY_POINT && matches!(export.kind, ExternalKind::Function) {
+                        return Err(BinaryReaderError { message: "Module already exports an entry point", offset: start });
+                    }
+                }
+            }
+            _ => ()
+        }
+    }
+    let entry_point = (functions.len() + defined.len()) as u32;
+    functions.extend(defined.iter().cloned());
+
+    // the calls run in a loop counting in the entry point's only local
+    let mut body = vec![0x01, 0x01, 0x7f, 0x03, 0x40];
+    for call in calls.iter() {
+        let ty = match functions.get(call.function).and_then(|ty| types.get(*ty as usize)) {
+            Some(ty) => ty,
+            None => return Err(BinaryReaderError { message: "Function index out of bounds", offset: call.function })
+        };
+        if ty.params.len() != call.args.len() {
+            return Err(BinaryReaderError { message: "Wrong number of arguments for the function", offset: call.function });
+        }
+        for (param, arg) in ty.params.iter().zip(call.args.iter()) {
+            write_const(&mut body, *param, *arg).map_err(|message| BinaryReaderError { message, offset: call.function })?;
+        }
+        body.push(0x10);
+        write_var_u32(&mut body, call.function as u32);
+        body.extend(ty.returns.iter().map(|_| 0x1a));
+    }
+    body.extend_from_slice(&[0x20, 0x00, 0x41, 0x01, 0x6a, 0x22, 0x00]);
+    write_const(&mut body, Type::I32, repeat as u64).unwrap();
+    body.extend_from_slice(&[0x49, 0x0d, 0x00, 0x0b, 0x0b]);
+
+    // the entry point takes and returns nothing, which the module may have a type for already
+    let entry_type = match types.iter().position(|ty| ty.params.is_empty() && ty.returns.is_empty()) {
+        Some(index) => index as u32,
+        None => {
+            types.push(FuncType { form: Type::Func, params: Box::new([]), returns: Box::new([]) });
+            (types.len() - 1) as u32
+        }
+    };
+
+    // sections the harness needs but the module lacks go where the ordering of sections puts them
+    for id in [TYPE_SECTION, FUNCTION_SECTION, EXPORT_SECTION, CODE_SECTION].iter() {
+        if !sections.iter().any(|section| section.0 == *id) {
+            let position = sections.iter().position(|section| order(section.0) > order(*id)).unwrap_or(sections.len());
+            sections.insert(position, (*id, 0, 0));
+        }
+    }
+
+    let mut bytes = buf[..8].to_vec();
+    for &(id, start, end) in sections.iter() {
+        let mut payload:Vec<u8> = Vec::new();
+        match id {
+            TYPE_SECTION => {
+                write_var_u32(&mut payload, types.len() as u32);
+                for ty in types.iter() {
+                    payload.push(0x60);
+                    write_types(&mut payload, &ty.params)?;
+                    write_types(&mut payload, &ty.returns)?;
+                }
+            }
+            FUNCTION_SECTION => {
+                write_var_u32(&mut payload, defined.len() as u32 + 1);
+                payload.extend_from_slice(skip_count(buf, start, end)?);
+                write_var_u32(&mut payload, entry_type);
+            }
+            EXPORT_SECTION => {
+                write_var_u32(&mut payload, exports + 1);
+                payload.extend_from_slice(skip_count(buf, start, end)?);
+                write_var_u32(&mut payload, ENTRY_POINT.len() as u32);
+                payload.extend_from_slice(ENTRY_POINT.as_bytes());
+                payload.push(0x00);
+                write_var_u32(&mut payload, entry_point);
+            }
+            CODE_SECTION => {
+                write_var_u32(&mut payload, defined.len() as u32 + 1);
+                payload.extend_from_slice(skip_count(buf, start, end)?);
+                write_var_u32(&mut payload, body.len() as u32);
+                payload.extend_from_slice(&body);
+            }
+            _ => payload.extend_from_slice(&buf[start..end])
+        }
+        write_var_u32(&mut bytes, id);
+        write_var_u32(&mut bytes, payload.len() as u32);
+        bytes.extend(payload);
+    }
+    Ok(bytes)
+}
+
+
+// returns the entries of a section's payload, after their count, which are empty for a section the module lacks
+fn skip_count(buf:&[u8], start:usize, end:usize) -> Result<&[u8]> {
+    if start == end {
+        return Ok(&[]);
+    }
+    let mut reader = BinaryReader::new_with_offset(&buf[start..end], start);
+    reader.read_var_u32()?;
+    Ok(&buf[reader.original_position()..end])
+}
+
+
+// writes the constant of the given type with the given bits
+fn write_const(bytes:&mut Vec<u8>, ty:Type, bits:u64) -> std::result::Result<(), &'static str> {
+    match ty {
+        Type::I32 => {
+            bytes.push(0x41);
+            write_var_i64(bytes, bits as u32 as i32 as i64);
+        }
+        Type::I64 => {
+            bytes.push(0x42);
+            write_var_i64(bytes, bits as i64);
+        }
+        Type::F32 => {
+            bytes.push(0x43);
+            bytes.extend_from_slice(&(bits as u32).to_le_bytes());
+        }
+        Type::F64 => {
+            bytes.push(0x44);
+            bytes.extend_from_slice(&bits.to_le_bytes());
+        }
+        _ => return Err("Function takes arguments a harness can't pass")
+    }
+    Ok(())
+}
+
+
+// writes a signed LEB128 integer
+fn write_var_i64(bytes:&mut Vec<u8>, mut value:i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate, CodeSectionReader, Operator};
+
+    // returns the operators of the module's last function and what it exports under the entry point
+    fn entry_point(buf:&[u8]) -> (Vec<String>, Option<u32>) {
+        let mut reader = BinaryReader::new(buf);
+        reader.read_file_header().unwrap();
+        let (mut operators, mut exported) = (Vec::new(), None);
+        while !reader.eof() {
+            let id = reader.read_var_u7().unwrap();
+            let len = reader.read_var_u32().unwrap() as usize;
+            let start = reader.original_position();
+            let payload = reader.read_bytes(len).unwrap();
+            if id == CODE_SECTION {
+                let mut section = CodeSectionReader::new(payload, start).unwrap();
+                for _ in 0..section.get_count() {
+                    let body = section.read().unwrap();
+                    operators = body.get_operators_reader().unwrap().into_iter().map(|op| format!("{:?}", op.unwrap())).collect();
+                }
+            }
+            if id == EXPORT_SECTION {
+                let mut section = ExportSectionReader::new(payload, start).unwrap();
+                for _ in 0..section.get_count() {
+                    let export = section.read().unwrap();
+                    if export.field == ENTRY_POINT {
+                        exported = Some(export.index);
+                    }
+                }
+            }
+        }
+        (operators, exported)
+    }
+
+    #[test]
+    fn path_harness() {
+
+        // $double and $scale are called in order, with their results dropped, ten times
+        let buf = include_bytes!("../tests/parallelization/inline.wasm");
+        let harness = super::path_harness(buf, &[PathCall::new(0, vec![u32::MAX as u64]), PathCall::new(1, vec![2, 5])], 10).unwrap();
+        assert!(validate(&harness, None));
+        let (operators, exported) = entry_point(&harness);
+        assert_eq!(exported, Some(3));
+        assert_eq!(&operators[1..8], &[
+            format!("{:?}", Operator::I32Const { value: -1 }), format!("{:?}", Operator::Call { function_index: 0 }), format!("{:?}", Operator::Drop),
+            format!("{:?}", Operator::I32Const { value: 2 }), format!("{:?}", Operator::I32Const { value: 5 }),
+            format!("{:?}", Operator::Call { function_index: 1 }), format!("{:?}", Operator::Drop)
+        ]);
+        assert!(operators.contains(&format!("{:?}", Operator::I32Const { value: 10 })));
+
+        // floats are passed by their bits, and the sections a module lacks are added
+        let buf = include_bytes!("../tests/parallelization/floats.wasm");
+        let harness = super::path_harness(buf, &[PathCall::new(1, vec![1.5f64.to_bits(), 2.0f64.to_bits()])], 1).unwrap();
+        assert!(validate(&harness, None));
+        let empty = super::path_harness(b"\0asm\x01\0\0\0", &[], 1).unwrap();
+        assert!(validate(&empty, None));
+        assert_eq!(entry_point(&empty).1, Some(0));
+
+        // calls have to fit the functions
+        let buf = include_bytes!("../tests/parallelization/inline.wasm");
+        assert!(super::path_harness(buf, &[PathCall::new(1, vec![2])], 1).is_err());
+        assert!(super::path_harness(buf, &[PathCall::new(3, vec![])], 1).is_err());
+        assert!(super::path_harness(&harness, &[], 1).is_err());
+    }
+
+    #[test]
+    fn write_var_i64() {
+        let mut bytes = Vec::new();
+        super::write_var_i64(&mut bytes, -123456);
+        super::write_var_i64(&mut bytes, 64);
+        assert_eq!(bytes, vec![0xc0, 0xbb, 0x78, 0xc0, 0x00]);
+    }
+}
@@ -0,0 +1,451 @@
+//! # Hazards
+//! Orders the memory accesses recorded for each node and classifies the hazards between them, so that
+//! it can be seen exactly which loads and stores keep two nodes from running in parallel. Accesses are
+//! ordered as they appear in the WASM file, and compared by the ranges of bytes they access: accesses to
+//! constant addresses, or relative to the same variable within a node, conflict if their ranges overlap,
+//! while any other accesses may alias, since dynamic addresses aren't known when mapping. Atomic operations also
+//! synchronize with other threads through the regions they access, which is recorded separately. When
+//! the memory is shared between threads, any other access to it can race with the code of another thread.
+//! Bulk memory operations move whole ranges of bytes, which are recorded as range couplings and, when
+//! their ranges are known when mapping, as accesses to the regions the ranges start at
+
+use std::fmt;
+
+
+/// The access kind enum represents whether a memory access reads or writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccessKind {
+    Read,
+    Write
+}
+
+
+/// A memory access is a load or store recorded while mapping a node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub offset: usize, // where the accessing instruction starts in the WASM file
+    pub access: MemAccess, // the bytes accessed, and whether they are read or written
+    pub atomic: bool // whether the access is made by an atomic operation
+}
+
+
+/// A mem access is what coupling a variable to memory records of the
+/// load or store it is read or written by, enough to tell apart accesses
+/// to the same static offset and to relate those stepping through memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MemAccess {
+    pub static_offset: usize, // the offset the instruction adds to the dynamic address
+    pub align: usize, // the alignment the instruction declares, in bytes
+    pub width: usize, // how many bytes are read or written
+    pub base_expr: Option<usize>, // the id of the variable the dynamic address is, if a variable holds it rather than a constant or an expression
+    pub address: Option<usize>, // the address accessed, if the dynamic address is a constant
+    pub kind: AccessKind
+}
+
+
+impl MemAccess {
+    // returns the region the access is identified by, which is the address accessed if it is known and the
+    // static offset of the address otherwise
+    pub fn region(&self) -> usize {
+        self.address.unwrap_or(self.static_offset)
+    }
+
+    // checks whether the access is to the same bytes as another as far as mapping can tell, whatever either does
+    // with them
+    pub fn same_location(&self, other:&MemAccess) -> bool {
+        (self.static_offset, self.width, self.base_expr, self.address) == (other.static_offset, other.width, other.base_expr, other.address)
+    }
+
+    // checks whether the access may touch any of the bytes another does; accesses to known addresses do if their
+    // ranges overlap, and so do those relative to the same variable, which is only the same within a node, while
+    // any other accesses may alias
+    pub fn may_alias(&self, other:&MemAccess, same_node:bool) -> bool {
+        let overlap = |start:usize, other_start:usize| start < other_start.saturating_add(other.width) && other_start < start.saturating_add(self.width);
+        match (self.address, other.address) {
+            (Some(address), Some(other_address)) => overlap(address, other_address),
+            _ if same_node && self.base_expr.is_some() && self.base_expr == other.base_expr => overlap(self.static_offset, other.static_offset),
+            _ => true
+        }
+    }
+
+    // checks whether the access is sure to touch every byte another does, which a write that does hides the
+    // other access behind
+    pub fn covers(&self, other:&MemAccess, same_node:bool) -> bool {
+        let contains = |start:usize, other_start:usize| start <= other_start && other_start.saturating_add(other.width) <= start.saturating_add(self.width);
+        match (self.address, other.address) {
+            (Some(address), Some(other_address)) => contains(address, other_address),
+            (None, None) if same_node && self.base_expr.is_some() && self.base_expr == other.base_expr => contains(self.static_offset, other.static_offset),
+            _ => false
+        }
+    }
+}
+
+
+/// The sync kind enum represents how an atomic operation synchronizes
+/// with other threads through a memory region
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncKind {
+    Load, // an atomic read
+    Store, // an atomic write
+    ReadModifyWrite, // an atomic arithmetic or bitwise update
+    Exchange, // an atomic swap
+    CompareExchange, // an atomic swap that only happens if the region holds an expected value
+    Wait, // blocks until another thread wakes it
+    Wake // wakes threads waiting on the region
+}
+
+
+impl SyncKind {
+    // checks whether the operation makes the code's progress depend on other threads, which keeps it
+    // from being combinational
+    pub fn is_blocking(&self) -> bool {
+        matches!(*self, SyncKind::Wait | SyncKind::Wake)
+    }
+
+    // returns the memory accesses the operation makes to its region, in order
+    pub fn access_kinds(&self) -> Vec<AccessKind> {
+        match *self {
+            SyncKind::Load | SyncKind::Wait => vec![AccessKind::Read],
+            SyncKind::Store => vec![AccessKind::Write],
+            SyncKind::Wake => Vec::new(),
+            _ => vec![AccessKind::Read, AccessKind::Write]
+        }
+    }
+}
+
+
+/// A sync coupling is an atomic operation synchronizing through a region
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyncCoupling {
+    pub location: usize, // the location of the operation within its node
+    pub offset: usize, // where the operation starts in the WASM file
+    pub kind: SyncKind
+}
+
+
+/// The range kind enum represents the bulk memory operation behind a
+/// range coupling
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeKind {
+    Copy, // copies a range of memory to another
+    Fill, // sets every byte of a range to the same value
+    Init { segment: usize } // copies a range of a passive data segment to memory
+}
+
+
+/// A range coupling is a bulk memory operation moving a range of bytes,
+/// whose bounds are only known when mapping if given by constants
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeCoupling {
+    pub location: usize, // the location of the operation within its node
+    pub offset: usize, // where the operation starts in the WASM file
+    pub kind: RangeKind,
+    pub destination: Option<usize>, // the start of the range written
+    pub source: Option<usize>, // the start of the range read from memory or from the data segment, unless filling
+    pub length: Option<usize> // the number of bytes moved
+}
+
+
+impl RangeCoupling {
+    // checks whether the ranges are known when mapping
+    pub fn is_static(&self) -> bool {
+        let source = match self.kind {
+            RangeKind::Fill => true,
+            _ => self.source.is_some()
+        };
+        source && self.destination.is_some() && self.length.is_some()
+    }
+
+    // checks whether the operation reads memory, rather than a value or a data segment
+    pub fn reads_memory(&self) -> bool {
+        self.kind == RangeKind::Copy
+    }
+}
+
+
+/// The hazard kind enum represents how a later access to a region
+/// depends on an earlier one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HazardKind {
+    ReadAfterWrite, // the later access reads what the earlier one wrote
+    WriteAfterRead, // the later access overwrites what the earlier one read
+    WriteAfterWrite // the later access overwrites what the earlier one wrote
+}
+
+
+impl HazardKind {
+    // classifies the hazard between two accesses to the same region; reads never conflict with each other
+    pub fn classify(earlier:AccessKind, later:AccessKind) -> Option<HazardKind> {
+        match (earlier, later) {
+            (AccessKind::Write, AccessKind::Read) => Some(HazardKind::ReadAfterWrite),
+            (AccessKind::Read, AccessKind::Write) => Some(HazardKind::WriteAfterRead),
+            (AccessKind::Write, AccessKind::Write) => Some(HazardKind::WriteAfterWrite),
+            (AccessKind::Read, AccessKind::Read) => None
+        }
+    }
+
+    // returns the usual abbreviation of the hazard, e.g. "RAW"
+    pub fn abbreviation(&self) -> &'static str {
+        match *self {
+            HazardKind::ReadAfterWrite => "RAW",
+            HazardKind::WriteAfterRead => "WAR",
+            HazardKind::WriteAfterWrite => "WAW"
+        }
+    }
+}
+
+
+/// An access site locates a memory access within a node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccessSite {
+    pub node: usize, // the id of the node making the access
+    pub offset: usize // where the accessing instruction starts in the WASM file
+}
+
+
+/// A hazard orders two accesses that may alias, at least one of which writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    pub region: usize, // the region of the later access
+    pub earlier: AccessSite, // the access that has to happen first
+    pub later: AccessSite // the access that has to wait for it
+}
+
+
+impl Hazard {
+    // checks whether both accesses are made by the same node
+    pub fn is_internal(&self) -> bool {
+        self.earlier.node == self.later.node
+    }
+}
+
+
+/// A hazard report holds the memory timeline of every node and the hazards
+/// found within and across them
+#[derive(Clone, Debug, Default)]
+pub struct HazardReport {
+    pub timelines: Vec<(usize, Vec<MemoryAccess>)>, // each node's accesses in order, with the nodes in order
+    pub hazards: Vec<Hazard> // the hazards within each node, followed by those across nodes
+}
+
+
+impl HazardReport {
+    // classifies the hazards between the accesses of the given timelines, which are expected to list
+    // the nodes and each node's accesses in program order
+    pub fn new(timelines:Vec<(usize, Vec<MemoryAccess>)>) -> HazardReport {
+        let mut hazards:Vec<Hazard> = Vec::new();
+
+        // within a node, each access depends on the writes before it that it may alias and a write also depends
+        // on the reads it may alias since then, until a later write is sure to overwrite them
+        for &(node, ref timeline) in timelines.iter() {
+            let mut writes:Vec<&MemoryAccess> = Vec::new();
+            let mut reads:Vec<&MemoryAccess> = Vec::new();
+            for memory in timeline.iter() {
+                let access = &memory.access;
+                let site = AccessSite { node, offset: memory.offset };
+                for write in writes.iter().filter(|write| write.access.may_alias(access, true)) {
+                    hazards.push(Hazard {
+                        kind: HazardKind::classify(AccessKind::Write, access.kind).unwrap(),
+                        region: access.region(),
+                        earlier: AccessSite { node, offset: write.offset },
+                        later: site
+                    });
+                }
+                match access.kind {
+                    AccessKind::Read => {
+                        reads.push(memory);
+                    }
+                    AccessKind::Write => {
+                        for read in reads.iter().filter(|read| read.access.may_alias(access, true)) {
+                            hazards.push(Hazard {
+                                kind: HazardKind::WriteAfterRead,
+                                region: access.region(),
+                                earlier: AccessSite { node, offset: read.offset },
+                                later: site
+                            });
+                        }
+                        reads.retain(|read| !access.covers(&read.access, true));
+                        writes.retain(|write| !access.covers(&write.access, true));
+                        writes.push(memory);
+                    }
+                }
+            }
+        }
+
+        // across nodes, the last access of each kind to each location in the earlier node is paired with the
+        // first access of each kind to each location in the later one it may alias
+        for (i, &(earlier_node, ref earlier)) in timelines.iter().enumerate() {
+            for &(later_node, ref later) in timelines.iter().skip(i + 1) {
+                let mut locations = distinct_locations(earlier);
+                locations.sort_by_key(|location| location.region());
+                let later_locations = distinct_locations(later);
+                for location in locations.iter() {
+                    for &(earlier_kind, later_kind) in [(AccessKind::Write, AccessKind::Read), (AccessKind::Read, AccessKind::Write), (AccessKind::Write, AccessKind::Write)].iter() {
+                        let first = match earlier.iter().rev().find(|memory| memory.access.same_location(location) && memory.access.kind == earlier_kind) {
+                            Some(first) => first,
+                            None => continue
+                        };
+                        for later_location in later_locations.iter() {
+                            let second = later.iter().find(|memory| memory.access.same_location(later_location) && memory.access.kind == later_kind);
+                            if let Some(second) = second.filter(|second| first.access.may_alias(&second.access, false)) {
+                                hazards.push(Hazard {
+                                    kind: HazardKind::classify(earlier_kind, later_kind).unwrap(),
+                                    region: second.access.region(),
+                                    earlier: AccessSite { node: earlier_node, offset: first.offset },
+                                    later: AccessSite { node: later_node, offset: second.offset }
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        HazardReport { timelines, hazards }
+    }
+
+    // returns the hazards between two different nodes, in either order
+    pub fn between(&self, a:usize, b:usize) -> Vec<Hazard> {
+        self.hazards.iter()
+            .filter(|hazard| (hazard.earlier.node == a && hazard.later.node == b) || (hazard.earlier.node == b && hazard.later.node == a))
+            .filter(|hazard| !hazard.is_internal())
+            .cloned()
+            .collect()
+    }
+
+    // returns the accesses that aren't made by atomic operations, in order, which can race with other
+    // threads when the memory is shared
+    pub fn races(&self) -> Vec<AccessSite> {
+        self.timelines.iter()
+            .flat_map(|&(node, ref timeline)| timeline.iter()
+                .filter(|access| !access.atomic)
+                .map(move |access| AccessSite { node, offset: access.offset }))
+            .collect()
+    }
+
+    // checks whether two nodes access memory independently of each other
+    pub fn can_run_in_parallel(&self, a:usize, b:usize) -> bool {
+        a == b || self.between(a, b).is_empty()
+    }
+}
+
+
+// returns the distinct locations the accesses of a timeline are to, in the order they are first accessed
+fn distinct_locations(timeline:&[MemoryAccess]) -> Vec<MemAccess> {
+    let mut locations:Vec<MemAccess> = Vec::new();
+    for memory in timeline.iter() {
+        if !locations.iter().any(|location| location.same_location(&memory.access)) {
+            locations.push(memory.access);
+        }
+    }
+    locations
+}
+
+
+impl fmt::Display for HazardReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} memory hazards", self.hazards.len())?;
+        for hazard in self.hazards.iter() {
+            write!(f, "\n{} on region {:#x}: node {} at {:#x} before node {} at {:#x}",
+                hazard.kind.abbreviation(), hazard.region,
+                hazard.earlier.node, hazard.earlier.offset,
+                hazard.later.node, hazard.later.offset)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a word accessed at a constant address
+    fn access(offset:usize, region:usize, kind:AccessKind) -> MemoryAccess {
+        let access = MemAccess { static_offset: region, align: 4, width: 4, base_expr: None, address: Some(region), kind };
+        MemoryAccess { offset, access, atomic: false }
+    }
+
+    #[test]
+    fn internal_hazards() {
+        let report = HazardReport::new(vec![(0, vec![
+            access(1, 16, AccessKind::Read),
+            access(2, 16, AccessKind::Read),
+            access(3, 16, AccessKind::Write),
+            access(4, 32, AccessKind::Read),
+            access(5, 16, AccessKind::Write),
+            access(6, 16, AccessKind::Read)
+        ])]);
+        let hazards:Vec<(HazardKind, usize, usize)> = report.hazards.iter()
+            .map(|hazard| (hazard.kind, hazard.earlier.offset, hazard.later.offset))
+            .collect();
+        assert_eq!(hazards, vec![
+            (HazardKind::WriteAfterRead, 1, 3),
+            (HazardKind::WriteAfterRead, 2, 3),
+            (HazardKind::WriteAfterWrite, 3, 5),
+            (HazardKind::ReadAfterWrite, 5, 6)
+        ]);
+        assert!(report.hazards.iter().all(|hazard| hazard.is_internal()));
+    }
+
+    #[test]
+    fn aliasing() {
+        let based = |offset:usize, base:Option<usize>, static_offset:usize, kind:AccessKind| {
+            let access = MemAccess { static_offset, align: 4, width: 4, base_expr: base, address: None, kind };
+            MemoryAccess { offset, access, atomic: false }
+        };
+        let byte = |offset:usize, address:usize| {
+            let access = MemAccess { static_offset: address, align: 1, width: 1, base_expr: None, address: Some(address), kind: AccessKind::Read };
+            MemoryAccess { offset, access, atomic: false }
+        };
+
+        // a byte read inside a word written to a constant address depends on it, while one past its end doesn't
+        let report = HazardReport::new(vec![(0, vec![access(1, 16, AccessKind::Write), byte(2, 19), byte(3, 20)])]);
+        let hazards:Vec<(usize, usize)> = report.hazards.iter().map(|hazard| (hazard.earlier.offset, hazard.later.offset)).collect();
+        assert_eq!(hazards, vec![(1, 2)]);
+
+        // words relative to the same variable only conflict if their ranges overlap, while those relative to another
+        // variable, or to an expression, may alias anything
+        let report = HazardReport::new(vec![(0, vec![
+            based(1, Some(3), 0, AccessKind::Write),
+            based(2, Some(3), 4, AccessKind::Read),
+            based(3, Some(4), 64, AccessKind::Read),
+            based(4, None, 64, AccessKind::Read)
+        ])]);
+        let hazards:Vec<(usize, usize)> = report.hazards.iter().map(|hazard| (hazard.earlier.offset, hazard.later.offset)).collect();
+        assert_eq!(hazards, vec![(1, 3), (1, 4)]);
+
+        // the same variable in another node is another variable
+        let report = HazardReport::new(vec![(0, vec![based(1, Some(3), 0, AccessKind::Write)]), (1, vec![based(2, Some(3), 4, AccessKind::Read)])]);
+        assert!(!report.can_run_in_parallel(0, 1));
+    }
+
+    #[test]
+    fn hazards_across_nodes() {
+        let report = HazardReport::new(vec![
+            (0, vec![access(1, 16, AccessKind::Write), access(2, 16, AccessKind::Write)]),
+            (1, vec![access(3, 16, AccessKind::Read), access(4, 32, AccessKind::Write)]),
+            (2, vec![access(5, 32, AccessKind::Read), access(6, 48, AccessKind::Read)])
+        ]);
+
+        // the last write in node 0 is read first thing by node 1
+        let hazards = report.between(1, 0);
+        assert_eq!(hazards.len(), 1);
+        assert_eq!(hazards[0].kind, HazardKind::ReadAfterWrite);
+        assert_eq!(hazards[0].earlier, AccessSite { node: 0, offset: 2 });
+        assert_eq!(hazards[0].later, AccessSite { node: 1, offset: 3 });
+
+        // nodes that touch disjoint regions, or only read the same ones, are independent
+        assert!(!report.can_run_in_parallel(1, 2));
+        assert!(report.can_run_in_parallel(0, 2));
+        assert_eq!(report.to_string().lines().count(), 4);
+
+        // atomic accesses can't race
+        let report = HazardReport::new(vec![
+            (0, vec![MemoryAccess { atomic: true, ..access(1, 16, AccessKind::Write) }]),
+            (1, vec![access(2, 16, AccessKind::Read)])
+        ]);
+        assert_eq!(report.races(), vec![AccessSite { node: 1, offset: 2 }]);
+    }
+}
@@ -0,0 +1,262 @@
+//! # Hints
+//! Mapping directives embedded in a module, so that a build can decide ahead of time what is parallelized
+//! and the mapper runs without asking anything. The directives are kept in a custom section named
+//! `pfc.hints`, as text with a line per function, e.g. `function 3 parallelize no unroll 2 width 8`:
+//! whether the function is expanded and lowered, how many calls and blocks deep its expansion goes, and
+//! how many bits its values fit in. Every directive is optional, and functions without a line are
+//! parallelized with the mapper's own settings. Build scripts add the section with `inject`
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use crate::{BinaryReader, Result};
+use emit::write_var_u32;
+use parallelize::{Decision, MapperObserver};
+
+
+// the name of the custom section holding the hints
+pub const SECTION_NAME:&str = "pfc.hints";
+
+
+/// Function hints are the directives given for a single function
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FunctionHints {
+    pub parallelize: Option<bool>, // whether the function is expanded and lowered
+    pub unroll: Option<usize>, // how many calls and blocks deep the function's expansion goes
+    pub bit_width: Option<usize> // how many bits the function's values fit in
+}
+
+
+/// Hints hold the directives of a module's functions
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Hints {
+    functions: HashMap<usize, FunctionHints> // the directives, by function index
+}
+
+
+impl Hints {
+    pub fn new() -> Hints {
+        Hints::default()
+    }
+
+    // sets the directives of a function, replacing any it had
+    pub fn set(&mut self, function:usize, hints:FunctionHints) {
+        self.functions.insert(function, hints);
+    }
+
+    // returns the directives of a function, which are all unset if it has none
+    pub fn get(&self, function:usize) -> FunctionHints {
+        self.functions.get(&function).cloned().unwrap_or_default()
+    }
+
+    // returns the indices of the functions with directives
+    pub fn functions(&self) -> Vec<usize> {
+        let mut functions:Vec<usize> = self.functions.keys().cloned().collect();
+        functions.sort();
+        functions
+    }
+
+    // answers a decision from the directives: functions are expanded and lowered unless they are hinted not to
+    // be, and nested nodes are lowered along with the nodes containing them
+    pub fn decide(&self, decision:&Decision) -> bool {
+        match *decision {
+            Decision::Parallelize { function } | Decision::Lower { node: function, .. } => self.get(function).parallelize.unwrap_or(true),
+            Decision::LowerNested { .. } => true
+        }
+    }
+
+    // describes the hints as the contents of their custom section
+    pub fn encode(&self) -> Vec<u8> {
+        let mut text = String::from(SECTION_NAME);
+        text.push('\n');
+        for function in self.functions() {
+            let hints = self.get(function);
+            write!(text, "function {}", function).unwrap();
+            if let Some(parallelize) = hints.parallelize {
+                text.push_str(if parallelize { " parallelize yes" } else { " parallelize no" });
+            }
+            if let Some(unroll) = hints.unroll {
+                write!(text, " unroll {}", unroll).unwrap();
+            }
+            if let Some(bit_width) = hints.bit_width {
+                write!(text, " width {}", bit_width).unwrap();
+            }
+            text.push('\n');
+        }
+        text.into_bytes()
+    }
+
+    // reads the contents of a hints section, unless they are malformed
+    pub fn decode(data:&[u8]) -> Option<Hints> {
+        let text = ::std::str::from_utf8(data).ok()?;
+        let mut lines = text.lines();
+        if lines.next()? != SECTION_NAME {
+            return None;
+        }
+        let mut hints = Hints::new();
+        for line in lines {
+            let words:Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            if words.len() < 2 || words[0] != "function" {
+                return None;
+            }
+            let function = words[1].parse().ok()?;
+            let mut directives = FunctionHints::default();
+            for pair in words[2..].chunks(2) {
+                match *pair {
+                    ["parallelize", "yes"] => directives.parallelize = Some(true),
+                    ["parallelize", "no"] => directives.parallelize = Some(false),
+                    ["unroll", bound] => directives.unroll = Some(bound.parse().ok()?),
+                    ["width", bits] => directives.bit_width = Some(bits.parse().ok()?),
+                    _ => return None
+                }
+            }
+            hints.set(function, directives);
+        }
+        Some(hints)
+    }
+}
+
+
+// returns a copy of a module carrying the given hints, in a custom section appended after its other sections
+// and replacing any hints section it already had
+pub fn inject(buf:&[u8], hints:&Hints) -> Result<Vec<u8>> {
+    let mut reader = BinaryReader::new(buf);
+    reader.read_file_header()?;
+    let mut bytes = buf[..8].to_vec();
+    while !reader.eof() {
+        let start = reader.original_position();
+        let id = reader.read_var_u7()?;
+        let len = reader.read_var_u32()? as usize;
+        let payload = reader.original_position();
+        reader.read_bytes(len)?;
+        if id == 0 && BinaryReader::new(&buf[payload..payload + len]).read_string()? == SECTION_NAME {
+            continue;
+        }
+        bytes.extend_from_slice(&buf[start..payload + len]);
+    }
+
+    let mut payload = Vec::new();
+    write_var_u32(&mut payload, SECTION_NAME.len() as u32);
+    payload.extend_from_slice(SECTION_NAME.as_bytes());
+    payload.extend(hints.encode());
+    bytes.push(0);
+    write_var_u32(&mut bytes, payload.len() as u32);
+    bytes.extend(payload);
+    Ok(bytes)
+}
+
+
+/// The hint observer answers every decision from a module's hints,
+/// passing the other events on to another observer, so that lowering
+/// can run without asking anything too
+pub struct HintObserver {
+    inner: Box<dyn MapperObserver>, // the observer events are passed on to
+    hints: Hints // the directives answering the decisions
+}
+
+
+impl HintObserver {
+    pub fn new(inner:Box<dyn MapperObserver>, hints:Hints) -> HintObserver {
+        HintObserver { inner, hints }
+    }
+}
+
+
+impl MapperObserver for HintObserver {
+    fn on_module(&mut self, module:&[u8]) {
+        self.inner.on_module(module);
+    }
+
+    fn on_function_start(&mut self, function:usize) {
+        self.inner.on_function_start(function);
+    }
+
+    fn on_block_found(&mut self, parent:usize, block:usize) {
+        self.inner.on_block_found(parent, block);
+    }
+
+    fn on_decision_needed(&mut self, decision:&Decision) -> bool {
+        self.hints.decide(decision)
+    }
+
+    fn on_warning(&mut self, message:&str) {
+        self.inner.on_warning(message);
+    }
+
+    fn on_progress(&mut self, message:&str) {
+        self.inner.on_progress(message);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use parallelize::{new_mapper, SilentObserver, WarningCategory};
+    use ids::NodeId;
+
+    #[derive(Clone, Default)]
+    struct CountingObserver {
+        asked: Arc<Mutex<usize>>
+    }
+
+    impl MapperObserver for CountingObserver {
+        fn on_decision_needed(&mut self, _decision:&Decision) -> bool {
+            *self.asked.lock().unwrap() += 1;
+            true
+        }
+    }
+
+    #[test]
+    fn hints() {
+        let mut hints = Hints::new();
+        hints.set(1, FunctionHints { parallelize: Some(false), unroll: None, bit_width: None });
+        hints.set(2, FunctionHints { parallelize: Some(true), unroll: Some(0), bit_width: Some(8) });
+        assert_eq!(String::from_utf8(hints.encode()).unwrap(), "pfc.hints\nfunction 1 parallelize no\nfunction 2 parallelize yes unroll 0 width 8\n");
+        assert_eq!(Hints::decode(&hints.encode()), Some(hints.clone()));
+        assert_eq!(Hints::decode(b"pfc.hints\nfunction 1 parallelize maybe"), None);
+
+        // injecting the hints twice leaves a single section
+        let module = include_bytes!("../tests/parallelization/inline.wasm");
+        let hinted = inject(&inject(module, &Hints::new()).unwrap(), &hints).unwrap();
+        assert_eq!(hinted.len(), module.len() + 2 + 10 + hints.encode().len());
+
+        // the hinted module is mapped without asking anything
+        let counting = CountingObserver::default();
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(counting.clone()));
+        let nodes = mapper.map(hinted);
+        assert_eq!(*counting.asked.lock().unwrap(), 0);
+        assert_eq!(mapper.get_hints(), Some(hints.clone()));
+
+        // main isn't unrolled into its callees, and its values are narrowed to a byte
+        assert!(nodes[&NodeId(2)].children().is_empty() && nodes[&NodeId(2)].is_truncated());
+        assert_eq!(nodes[&NodeId(2)].get_bit_width(), Some(8));
+        assert_eq!(nodes[&NodeId(2)].auxiliary_qubits(), 2 * 8);
+        assert!(nodes[&NodeId(0)].get_bit_width().is_none());
+
+        // lowering is answered by the hints as well
+        let mut observer = HintObserver::new(Box::new(SilentObserver), hints);
+        nodes[&NodeId(2)].clone().lower_with(&mut observer);
+        assert!(!observer.on_decision_needed(&Decision::Parallelize { function: 1 }));
+        assert_eq!(*counting.asked.lock().unwrap(), 0);
+
+        // a malformed section is ignored with a warning the mapper collects
+        let mut malformed = module.to_vec();
+        let contents = b"pfc.hints\nfunction 1 parallelize maybe";
+        malformed.push(0);
+        write_var_u32(&mut malformed, (1 + SECTION_NAME.len() + contents.len()) as u32);
+        write_var_u32(&mut malformed, SECTION_NAME.len() as u32);
+        malformed.extend_from_slice(SECTION_NAME.as_bytes());
+        malformed.extend_from_slice(contents);
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.map(malformed);
+        assert_eq!(mapper.get_hints(), None);
+        let warnings:Vec<WarningCategory> = mapper.get_warnings().iter().map(|warning| warning.category).collect();
+        assert_eq!(warnings, vec![WarningCategory::MalformedSection]);
+    }
+}
@@ -0,0 +1,72 @@
+//! # Ids
+//! The mapper keeps functions, the blocks read from their code and the nodes of the expanded tree under
+//! ids of their own. A function's id is its index in the module, a block's is where the mapper registered
+//! it while reading the code, and a node's is either the index of the function it is or an id expansion
+//! gave the block or unrolled copy it was made from. The three overlap as numbers, so they are kept apart
+//! as types, and only a function's id converts into a node's
+
+use std::fmt;
+
+
+/// A function id is the index of a function in the module, counting
+/// its imports first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FuncId(pub usize);
+
+
+/// A block id names a block, loop, if or else clause in the mapper's
+/// registry of the blocks read from the module's code
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(pub usize);
+
+
+/// A node id names a node of the expanded tree, which is a function
+/// under its index or a block or unrolled copy under an id expansion
+/// gave it past the functions'
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub usize);
+
+
+impl From<FuncId> for NodeId {
+    fn from(id:FuncId) -> NodeId {
+        NodeId(id.0)
+    }
+}
+
+
+impl fmt::Display for FuncId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn namespaces() {
+
+        // a function is the node of the same number, while blocks live in a map of their own
+        assert_eq!(NodeId::from(FuncId(3)), NodeId(3));
+        let mut blocks:HashMap<BlockId, &str> = HashMap::new();
+        blocks.insert(BlockId(3), "loop");
+        assert_eq!((blocks.get(&BlockId(3)), BlockId(3).to_string()), (Some(&"loop"), "3".to_string()));
+    }
+}
@@ -0,0 +1,257 @@
+//! # Interp
+//! An evaluator for the abstract expression IR, which runs a node's recorded operations on concrete
+//! input variable values so that the IR can be sanity checked before it is lowered. Child nodes, callees
+//! shared through the mapper's arena included, are run where they are called: functions with the
+//! arguments on the stack, nested blocks with the values of the locals they share, and the branches of
+//! an if depending on the value of its condition. Integer values are held as u64 and wrapped to the
+//! width of their type
+
+use std::collections::HashMap;
+use primitives::Type;
+use parallelize::{AbstractExpression, Node};
+
+
+/// An evaluation holds the values a node produced
+#[derive(Clone, Debug, Default)]
+pub struct Evaluation {
+    pub active: bool, // whether the node ran, which a branch of an if only does when its condition allows
+    pub values: HashMap<usize, u64>, // the value each operation produced by its location, when it is known
+    pub stack: Vec<Option<u64>>, // the values left on the stack once the node ended, which may be unknown
+    pub outputs: HashMap<usize, u64>, // the values written to the node's output variables by variable id, when they are known
+    pub children: HashMap<usize, Evaluation> // the evaluations of the child nodes by the location they ran at
+}
+
+
+impl Evaluation {
+    // returns the value left on top of the stack, which is the node's result if it has one
+    pub fn result(&self) -> Option<u64> {
+        self.stack.last().cloned().and_then(|value| value)
+    }
+}
+
+
+// runs a node's operations with the given values of its input variables, by variable id
+pub fn evaluate(node:&Node, inputs:&HashMap<usize, u64>) -> Evaluation {
+    evaluate_helper(node, inputs, true)
+}
+
+
+// runs a node's operations if it is active, and otherwise only its children, since the else clause of
+// an if is nested in the if's skipped branch
+fn evaluate_helper(node:&Node, inputs:&HashMap<usize, u64>, active:bool) -> Evaluation {
+    let operations = node.operations();
+    let calls = node.calls();
+    let blocks = node.blocks();
+    let internal_variables = node.internal_variables();
+    let input_variables = node.input_variables();
+    let result_couplings = node.get_result_couplings();
+
+    // the locations of the operations and of the calls to children are visited in order
+    let mut locations:Vec<usize> = operations.keys().cloned().collect();
+    locations.extend(calls.iter().filter(|&(_, child)| node.get_callee(*child).is_some()).map(|(location, _)| *location));
+    locations.sort();
+    locations.dedup();
+
+    let mut evaluation = Evaluation { active, ..Evaluation::default() };
+    let mut conditions:HashMap<usize, u64> = HashMap::new();
+    let mut previous:Option<usize> = None;
+    for i in locations {
+
+        // an unrecorded instruction leaves the stack in an unknown state
+        if !node.is_contiguous(previous, i) {
+            evaluation.stack.clear();
+        }
+        previous = Some(i);
+
+        if let (true, Some(op)) = (active, operations.get(&i)) {
+            let value = match *op {
+                AbstractExpression::Spin { id } if id == i && internal_variables.contains_key(&i) => {
+
+                    // an if consumes its condition, which its branches are coupled to
+                    if let Some(Some(condition)) = evaluation.stack.pop() {
+                        conditions.insert(id, condition);
+                    }
+                    None
+                }
+                AbstractExpression::Spin { id } => {
+                    let value = inputs.get(&id).cloned();
+                    evaluation.stack.push(value);
+                    value
+                }
+                AbstractExpression::Num { val } => {
+                    evaluation.stack.push(Some(val as u64));
+                    Some(val as u64)
+                }
+                AbstractExpression::Add { ty } | AbstractExpression::Mul { ty } | AbstractExpression::DivU { ty } | AbstractExpression::RemU { ty }
+                | AbstractExpression::Min { ty } | AbstractExpression::Max { ty } | AbstractExpression::Copysign { ty }
+                | AbstractExpression::Shift { ty, .. } => {
+                    let rhs = evaluation.stack.pop().and_then(|rhs| rhs);
+                    let lhs = evaluation.stack.pop().and_then(|lhs| lhs);
+                    let value = match (lhs, rhs) {
+                        (Some(lhs), Some(rhs)) => apply(op, ty, lhs, rhs),
+                        _ => None
+                    };
+                    evaluation.stack.push(value);
+                    value
+                }
+                AbstractExpression::Reuse { location } => {
+                    let value = evaluation.values.get(&location).cloned();
+                    evaluation.stack.push(value);
+                    value
+                }
+                AbstractExpression::Load { id, width } => {
+
+                    // the address is consumed, and the value read is extended from the bits the load read
+                    evaluation.stack.pop();
+                    let value = match (inputs.get(&id), input_variables.get(&id)) {
+                        (Some(value), Some(ty)) => Some(width.extend(*value, *ty)),
+                        _ => None
+                    };
+                    evaluation.stack.push(value);
+                    value
+                }
+                AbstractExpression::Extend { .. } | AbstractExpression::Truncate { .. } | AbstractExpression::Reinterpret { .. }
+                | AbstractExpression::ShrU { .. } | AbstractExpression::MulShr { .. }
+                | AbstractExpression::Clz { .. } | AbstractExpression::Ctz { .. } | AbstractExpression::Popcnt { .. }
+                | AbstractExpression::Abs { .. } | AbstractExpression::Neg { .. } | AbstractExpression::IsZero { .. } => {
+                    let value = evaluation.stack.pop().and_then(|operand| operand).and_then(|operand| op.unary(operand));
+                    evaluation.stack.push(value);
+                    value
+                }
+            };
+            if let Some(value) = value {
+                evaluation.values.insert(i, value);
+            }
+        }
+
+        let child = match calls.get(&i).and_then(|index| node.get_callee(*index)) {
+            Some(child) => child,
+            None => {
+
+                // a nested block that wasn't expanded into a child has an unknown effect
+                if blocks.contains_key(&i) {
+                    evaluation.stack.clear();
+                }
+                continue;
+            }
+        };
+
+        // functions take their parameters from the stack, while blocks share the locals of the node
+        let mut child_inputs:HashMap<usize, u64> = HashMap::new();
+        let child_locals = child.local_input_variables();
+        if child.is_function() {
+            let split = evaluation.stack.len().saturating_sub(child.get_param_count());
+            let arguments = evaluation.stack.split_off(split);
+            for (param, argument) in arguments.into_iter().enumerate() {
+                if let (Some(var_id), Some(argument)) = (child_locals.get(&param), argument) {
+                    child_inputs.insert(*var_id, argument);
+                }
+            }
+        } else {
+            for (local_index, var_id) in node.local_input_variables() {
+                if let (Some(child_var_id), Some(value)) = (child_locals.get(local_index), inputs.get(var_id)) {
+                    child_inputs.insert(*child_var_id, *value);
+                }
+            }
+        }
+
+        // the branches of an if run depending on the condition they are chained or anti-chained to, which
+        // is either the node's flow control variable or, for an else clause, the branch's own condition;
+        // a predicated branch always runs and only reads whether its condition holds
+        let mut child_active = active;
+        let chains = child.chains();
+        for (&outer_var_id, &inner_var_id) in child.flow_control_couplings() {
+            match conditions.get(&outer_var_id).or_else(|| inputs.get(&outer_var_id)) {
+                Some(condition) if child.is_predicated() => {
+                    child_inputs.insert(inner_var_id, (*condition != 0) as u64);
+                }
+                Some(condition) => {
+                    child_inputs.insert(inner_var_id, *condition);
+                    child_active = if chains.get(&outer_var_id).cloned().unwrap_or(true) { *condition != 0 } else { *condition == 0 };
+                }
+                None if child.is_predicated() => (),
+                None => child_active = false
+            }
+        }
+
+        // a function's result is the value it left on top of its stack, while a block's reaches the node
+        // through the input variable coupled to its result variable, which a skipped branch doesn't write
+        // unless its else clause did in its place; blocks without a result leave nothing
+        let child_evaluation = evaluate_helper(child, &child_inputs, child_active);
+        if child.is_function() || node.is_else_clause(i, child) {
+            if let Some(value) = child_evaluation.stack.last() {
+                evaluation.stack.push(*value);
+            }
+        } else if result_couplings.contains_key(&i) {
+            let value = child.get_result_variable().and_then(|var_id| child_evaluation.outputs.get(&var_id)).cloned();
+            if let Some(value) = value {
+                evaluation.values.insert(i, value);
+            }
+            evaluation.stack.push(value);
+        }
+        evaluation.children.insert(i, child_evaluation);
+    }
+
+    // a block writes the value it leaves on top of its stack to its result variable
+    if let (Some(var_id), Some(value)) = (node.get_result_variable(), evaluation.result()) {
+        evaluation.outputs.insert(var_id, value);
+    }
+    evaluation
+}
+
+
+// applies an addition or multiplication of the given type, wrapping the result to the type's width
+fn apply(op:&AbstractExpression, ty:Type, lhs:u64, rhs:u64) -> Option<u64> {
+    match (op, ty) {
+        (AbstractExpression::Add { .. }, Type::I32) => Some((lhs as u32).wrapping_add(rhs as u32) as u64),
+        (AbstractExpression::Mul { .. }, Type::I32) => Some((lhs as u32).wrapping_mul(rhs as u32) as u64),
+        (AbstractExpression::Add { .. }, Type::I64) => Some(lhs.wrapping_add(rhs)),
+        (AbstractExpression::Mul { .. }, Type::I64) => Some(lhs.wrapping_mul(rhs)),
+
+        // dividing by zero traps, which leaves the value unknown
+        (AbstractExpression::DivU { .. }, Type::I32) => (lhs as u32).checked_div(rhs as u32).map(u64::from),
+        (AbstractExpression::RemU { .. }, Type::I32) => (lhs as u32).checked_rem(rhs as u32).map(u64::from),
+        (AbstractExpression::DivU { .. }, Type::I64) => lhs.checked_div(rhs),
+        (AbstractExpression::RemU { .. }, Type::I64) => lhs.checked_rem(rhs),
+
+        // floats are held by their bits, and shifts take their amount modulo the type's width
+        _ => op.binary(lhs, rhs)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallelize::{new_mapper, SilentObserver};
+    use ids::NodeId;
+
+    #[test]
+    fn evaluate() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/branches.wasm").to_vec());
+
+        // pick adds one to its second parameter if its first is set, and doubles it otherwise
+        let pick = &nodes[&NodeId(0)];
+        let variables = pick.get_local_input_variables();
+        for &(condition, result) in [(1, 6), (7, 6), (0, 10)].iter() {
+            let mut inputs = HashMap::new();
+            inputs.insert(variables[&0], condition);
+            inputs.insert(variables[&1], 5);
+            assert_eq!(super::evaluate(pick, &inputs).result(), Some(result));
+        }
+
+        // pick_twice runs pick once with each condition
+        let pick_twice = &nodes[&NodeId(1)];
+        let mut inputs = HashMap::new();
+        inputs.insert(pick_twice.get_local_input_variables()[&0], 5);
+        let evaluation = super::evaluate(pick_twice, &inputs);
+        assert_eq!(evaluation.result(), Some(16));
+        assert_eq!(evaluation.children[&3].result(), Some(6));
+        assert_eq!(evaluation.children[&6].result(), Some(10));
+
+        // without its inputs nothing is known
+        assert_eq!(super::evaluate(pick_twice, &HashMap::new()).result(), None);
+    }
+}
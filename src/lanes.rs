@@ -0,0 +1,198 @@
+//! # Lanes
+//! Lane level modelling of 128 bit SIMD values, so that vectorized kernels can be lowered lane by lane.
+//! A vector is interpreted as a number of typed lanes, given by the shape of the operation using it or
+//! by the mapper's configured shape when the operation doesn't have one, as for loads and stores. Lanewise
+//! arithmetic becomes one abstract operation per lane, while splats, extractions, replacements and
+//! shuffles only move values between lanes and become re-wirings
+
+use primitives::Type;
+use parallelize::AbstractExpression;
+
+
+/// The lane shape enum represents how a 128 bit vector is divided into lanes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LaneShape {
+    I8x16,
+    I16x8,
+    #[default]
+    I32x4,
+    I64x2,
+    F32x4,
+    F64x2
+}
+
+
+impl LaneShape {
+    // returns the number of lanes in a vector
+    pub fn count(&self) -> usize {
+        128 / self.width()
+    }
+
+    // returns the number of bits in a lane
+    pub fn width(&self) -> usize {
+        match *self {
+            LaneShape::I8x16 => 8,
+            LaneShape::I16x8 => 16,
+            LaneShape::I32x4 | LaneShape::F32x4 => 32,
+            LaneShape::I64x2 | LaneShape::F64x2 => 64
+        }
+    }
+
+    // returns the type a lane has once extracted; narrow integer lanes are extended to 32 bits, as in WASM
+    pub fn lane_type(&self) -> Type {
+        match *self {
+            LaneShape::I8x16 | LaneShape::I16x8 | LaneShape::I32x4 => Type::I32,
+            LaneShape::I64x2 => Type::I64,
+            LaneShape::F32x4 => Type::F32,
+            LaneShape::F64x2 => Type::F64
+        }
+    }
+
+    // returns the types of a vector's lanes, from the lowest to the highest
+    pub fn decompose(&self) -> Vec<Type> {
+        vec![self.lane_type(); self.count()]
+    }
+
+    // splits the little endian bytes of a vector into the values of its lanes
+    pub fn split(&self, bytes:&[u8; 16]) -> Vec<usize> {
+        let lane_bytes = self.width() / 8;
+        bytes.chunks(lane_bytes)
+            .map(|chunk| chunk.iter().rev().fold(0, |value, byte| (value << 8) | *byte as usize))
+            .collect()
+    }
+}
+
+
+/// A lane source names where a lane's value comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LaneSource {
+    Lane { operand: usize, lane: usize }, // a lane of a vector operand, with operands numbered in the order they were pushed
+    Scalar { operand: usize }, // a scalar operand
+    Constant { value: usize } // a value known when mapping
+}
+
+
+/// A lane is how a single lane of a SIMD operation's result is produced
+#[derive(Clone, Debug, PartialEq)]
+pub enum Lane {
+    Wire(LaneSource), // copied from elsewhere without being changed
+    Compute { expression: AbstractExpression, operands: Vec<LaneSource> } // computed by an abstract operation
+}
+
+
+/// A lane operation is a SIMD operation broken down into the lanes of its
+/// result, which is a scalar when only one lane is extracted
+#[derive(Clone, Debug, PartialEq)]
+pub struct LaneOperation {
+    pub shape: LaneShape, // how the operands and the result are interpreted
+    pub operands: usize, // the number of values the operation pops
+    pub lanes: Vec<Lane>, // the result's lanes, from the lowest to the highest
+    pub scalar: bool // whether the result is the single lane of a scalar
+}
+
+
+impl LaneOperation {
+    // creates a vector constant, whose lanes are known when mapping
+    pub fn constant(shape:LaneShape, bytes:&[u8; 16]) -> LaneOperation {
+        let lanes = shape.split(bytes).into_iter()
+            .map(|value| Lane::Wire(LaneSource::Constant { value }))
+            .collect();
+        LaneOperation { shape, operands: 0, lanes, scalar: false }
+    }
+
+    // creates an operation applying the same abstract operation to the corresponding lanes of two vectors
+    pub fn lanewise(shape:LaneShape, expression:AbstractExpression) -> LaneOperation {
+        let lanes = (0..shape.count())
+            .map(|lane| Lane::Compute {
+                expression: expression.clone(),
+                operands: vec![LaneSource::Lane { operand: 0, lane }, LaneSource::Lane { operand: 1, lane }]
+            })
+            .collect();
+        LaneOperation { shape, operands: 2, lanes, scalar: false }
+    }
+
+    // creates an operation copying a scalar into every lane
+    pub fn splat(shape:LaneShape) -> LaneOperation {
+        let lanes = vec![Lane::Wire(LaneSource::Scalar { operand: 0 }); shape.count()];
+        LaneOperation { shape, operands: 1, lanes, scalar: false }
+    }
+
+    // creates an operation extracting a single lane as a scalar
+    pub fn extract(shape:LaneShape, lane:usize) -> LaneOperation {
+        let lanes = vec![Lane::Wire(LaneSource::Lane { operand: 0, lane })];
+        LaneOperation { shape, operands: 1, lanes, scalar: true }
+    }
+
+    // creates an operation replacing a single lane of a vector with a scalar
+    pub fn replace(shape:LaneShape, replaced:usize) -> LaneOperation {
+        let lanes = (0..shape.count())
+            .map(|lane| match lane == replaced {
+                true => Lane::Wire(LaneSource::Scalar { operand: 1 }),
+                false => Lane::Wire(LaneSource::Lane { operand: 0, lane })
+            })
+            .collect();
+        LaneOperation { shape, operands: 2, lanes, scalar: false }
+    }
+
+    // creates an operation picking each byte of the result from either of two vectors, whose bytes are
+    // numbered one after the other
+    pub fn shuffle(lines:&[u8; 16]) -> LaneOperation {
+        let lanes = lines.iter()
+            .map(|line| Lane::Wire(LaneSource::Lane { operand: *line as usize / 16, lane: *line as usize % 16 }))
+            .collect();
+        LaneOperation { shape: LaneShape::I8x16, operands: 2, lanes, scalar: false }
+    }
+
+    // checks whether the operation only moves values between lanes
+    pub fn is_rewiring(&self) -> bool {
+        self.lanes.iter().all(|lane| match *lane {
+            Lane::Wire(_) => true,
+            Lane::Compute { .. } => false
+        })
+    }
+
+    // returns the abstract operations computing the result's lanes, in lane order
+    pub fn expressions(&self) -> Vec<AbstractExpression> {
+        self.lanes.iter()
+            .filter_map(|lane| match *lane {
+                Lane::Compute { ref expression, .. } => Some(expression.clone()),
+                Lane::Wire(_) => None
+            })
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lane_operations() {
+        assert_eq!(LaneShape::I16x8.decompose(), vec![Type::I32; 8]);
+        assert_eq!(LaneShape::F64x2.decompose(), vec![Type::F64; 2]);
+
+        // constants are split little endian
+        let mut bytes = [0; 16];
+        bytes[0] = 1;
+        bytes[5] = 2;
+        let constant = LaneOperation::constant(LaneShape::I32x4, &bytes);
+        assert_eq!(constant.lanes[0], Lane::Wire(LaneSource::Constant { value: 1 }));
+        assert_eq!(constant.lanes[1], Lane::Wire(LaneSource::Constant { value: 0x200 }));
+
+        // lanewise arithmetic computes every lane, while the rest only moves them around
+        let add = LaneOperation::lanewise(LaneShape::I64x2, AbstractExpression::Add { ty: Type::I64 });
+        assert_eq!(add.expressions(), vec![AbstractExpression::Add { ty: Type::I64 }; 2]);
+        assert!(!add.is_rewiring());
+        let replace = LaneOperation::replace(LaneShape::I32x4, 2);
+        assert!(replace.is_rewiring() && replace.expressions().is_empty());
+        assert_eq!(replace.lanes[2], Lane::Wire(LaneSource::Scalar { operand: 1 }));
+        assert_eq!(replace.lanes[3], Lane::Wire(LaneSource::Lane { operand: 0, lane: 3 }));
+
+        // shuffles number the bytes of the second vector after those of the first
+        let mut lines = [0; 16];
+        lines[0] = 17;
+        let shuffle = LaneOperation::shuffle(&lines);
+        assert_eq!(shuffle.lanes[0], Lane::Wire(LaneSource::Lane { operand: 1, lane: 1 }));
+    }
+}
@@ -0,0 +1,352 @@
+//! # Leap
+//! A client for the D-Wave Solver API, closing the loop from a module to an annealer's answer. A lowered
+//! constraint's QUBO is submitted to a remote solver with the token the environment gives, the problem is
+//! polled until the solver answers, and the samples it returns are read back as WASM values through the
+//! decoder. Problems are sent in the solver's own qubit and coupler format, so the QUBO's variables must
+//! already be qubits of the solver, and its pairs couplers: finding such an embedding is left to the user,
+//! and a QUBO that isn't embedded is refused before anything is sent
+
+extern crate serde_json;
+extern crate ureq;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+use self::serde_json::Value;
+use decode;
+use decode::DecodedSample;
+use gadgets::Qubo;
+use parallelize::Constraint;
+
+// where the Solver API is reached unless the environment says otherwise
+const DEFAULT_ENDPOINT:&str = "https://na-west-1.cloud.dwavesys.com/sapi/v2";
+const DEFAULT_SOLVER:&str = "Advantage_system4.1";
+
+const BASE64:&[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+
+/// The Leap error enum represents the ways in which submitting a
+/// problem and getting its answer can fail
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LeapError {
+    MissingToken, // the environment doesn't give an API token
+    Http(String), // the request couldn't be made, or the API refused it
+    Response(String), // the API answered with something that couldn't be read
+    Embedding(String), // the QUBO's variables aren't qubits of the solver, or its pairs aren't couplers
+    Failed(String), // the solver failed or cancelled the problem
+    Timeout // the solver didn't answer in time
+}
+
+
+impl fmt::Display for LeapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LeapError::MissingToken => write!(f, "DWAVE_API_TOKEN isn't set"),
+            LeapError::Http(ref message) => write!(f, "request failed: {}", message),
+            LeapError::Response(ref message) => write!(f, "unreadable response: {}", message),
+            LeapError::Embedding(ref message) => write!(f, "the QUBO isn't embedded in the solver: {}", message),
+            LeapError::Failed(ref message) => write!(f, "the problem failed: {}", message),
+            LeapError::Timeout => write!(f, "the solver didn't answer in time")
+        }
+    }
+}
+
+
+/// A solver graph holds the qubits and couplers of a remote solver
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SolverGraph {
+    pub num_qubits: usize, // the number of qubits the solver's problems are indexed by, working or not
+    pub qubits: BTreeSet<usize>, // the working qubits
+    pub couplers: Vec<(usize, usize)> // the working couplers, in the order problems list their coefficients
+}
+
+
+/// A Leap sample is an assignment returned by the solver, as the set
+/// variables, along with how many of its reads found it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeapSample {
+    pub sample: BTreeSet<usize>,
+    pub occurrences: usize
+}
+
+
+/// A Leap client submits problems to a remote solver
+#[derive(Clone, Debug)]
+pub struct LeapClient {
+    endpoint: String, // the Solver API's URL, without a trailing slash
+    token: String, // the API token sent with every request
+    solver: String, // the id of the solver problems are submitted to
+    poll_interval: Duration, // how long to wait between polls
+    timeout: Duration // how long to poll before giving up
+}
+
+
+impl LeapClient {
+    pub fn new(endpoint:&str, token:&str, solver:&str) -> LeapClient {
+        LeapClient {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            solver: solver.to_string(),
+            poll_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(300)
+        }
+    }
+
+    // creates a client from DWAVE_API_TOKEN, along with DWAVE_API_ENDPOINT and DWAVE_API_SOLVER when they are
+    // set, which otherwise default to the North American endpoint and an Advantage system
+    pub fn from_env() -> Result<LeapClient, LeapError> {
+        let token = env::var("DWAVE_API_TOKEN").map_err(|_| LeapError::MissingToken)?;
+        let endpoint = env::var("DWAVE_API_ENDPOINT").unwrap_or_else(|_| DEFAULT_ENDPOINT.to_string());
+        let solver = env::var("DWAVE_API_SOLVER").unwrap_or_else(|_| DEFAULT_SOLVER.to_string());
+        Ok(LeapClient::new(&endpoint, &token, &solver))
+    }
+
+    // sets how long to wait between polls of a submitted problem
+    pub fn set_poll_interval(&mut self, poll_interval:Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    // sets how long to poll a submitted problem before giving up
+    pub fn set_timeout(&mut self, timeout:Duration) {
+        self.timeout = timeout;
+    }
+
+    // returns the id of the solver problems are submitted to
+    pub fn get_solver(&self) -> String {
+        self.solver.clone()
+    }
+
+    // fetches the qubits and couplers of the solver
+    pub fn solver_graph(&self) -> Result<SolverGraph, LeapError> {
+        let solver = self.request(ureq::get(&format!("{}/solvers/remote/{}/", self.endpoint, self.solver)), None)?;
+        parse_solver_graph(&solver)
+    }
+
+    // submits a QUBO to the solver for the given number of reads, returning the id of the problem
+    pub fn submit(&self, qubo:&Qubo, num_reads:usize) -> Result<String, LeapError> {
+        let graph = self.solver_graph()?;
+        let problem = encode_problem(qubo, &graph, &self.solver, num_reads)?;
+        let submitted = self.request(ureq::post(&format!("{}/problems/", self.endpoint)), Some(problem.to_string()))?;
+        let status = submitted.get(0).unwrap_or(&submitted);
+        match status.get("id").and_then(Value::as_str) {
+            Some(id) => Ok(id.to_string()),
+            None => Err(LeapError::Response(format!("no problem id in {}", submitted)))
+        }
+    }
+
+    // checks on a submitted problem, returning its samples once the solver has answered
+    pub fn poll(&self, id:&str) -> Result<Option<Vec<LeapSample>>, LeapError> {
+        let problem = self.request(ureq::get(&format!("{}/problems/{}/", self.endpoint, id)), None)?;
+        match problem.get("status").and_then(Value::as_str) {
+            Some("COMPLETED") => match problem.get("answer") {
+                Some(answer) => parse_answer(answer).map(Some),
+                None => Err(LeapError::Response(format!("problem {} completed without an answer", id)))
+            },
+            Some("FAILED") | Some("CANCELLED") => {
+                let message = problem.get("error_message").and_then(Value::as_str).unwrap_or("no reason given");
+                Err(LeapError::Failed(message.to_string()))
+            }
+            Some(_) => Ok(None),
+            None => Err(LeapError::Response(format!("no status for problem {}", id)))
+        }
+    }
+
+    // polls a submitted problem until the solver answers or the timeout passes
+    pub fn wait(&self, id:&str) -> Result<Vec<LeapSample>, LeapError> {
+        let started = Instant::now();
+        loop {
+            if let Some(samples) = self.poll(id)? {
+                return Ok(samples);
+            }
+            if started.elapsed() >= self.timeout {
+                return Err(LeapError::Timeout);
+            }
+            thread::sleep(self.poll_interval);
+        }
+    }
+
+    // submits a constraint's QUBO, waits for the answer and decodes every sample, in the order the solver
+    // returned them
+    pub fn solve(&self, constraint:&Constraint, num_reads:usize) -> Result<Vec<DecodedSample>, LeapError> {
+        let id = self.submit(constraint.qubo(), num_reads)?;
+        let samples:Vec<BTreeSet<usize>> = self.wait(&id)?.into_iter().map(|sample| sample.sample).collect();
+        Ok(decode::decode_all(constraint, &samples))
+    }
+
+    // sends a request with the client's token, and a JSON body if given, returning the JSON answered
+    fn request(&self, request:ureq::Request, body:Option<String>) -> Result<Value, LeapError> {
+        let request = request.set("X-Auth-Token", &self.token);
+        let response = match body {
+            Some(body) => request.set("Content-Type", "application/json").send_string(&body),
+            None => request.call()
+        }.map_err(|error| LeapError::Http(error.to_string()))?;
+        let text = response.into_string().map_err(|error| LeapError::Response(error.to_string()))?;
+        serde_json::from_str(&text).map_err(|error| LeapError::Response(error.to_string()))
+    }
+}
+
+
+// reads the qubits and couplers of a solver from its description
+pub fn parse_solver_graph(solver:&Value) -> Result<SolverGraph, LeapError> {
+    let properties = solver.get("properties").ok_or_else(|| LeapError::Response("no solver properties".to_string()))?;
+    let index = |value:&Value| value.as_u64().map(|index| index as usize).ok_or_else(|| LeapError::Response(format!("bad qubit {}", value)));
+    let list = |name:&str| properties.get(name).and_then(Value::as_array).ok_or_else(|| LeapError::Response(format!("no {} property", name)));
+    let qubits = list("qubits")?.iter().map(&index).collect::<Result<BTreeSet<usize>, LeapError>>()?;
+    let couplers = list("couplers")?.iter().map(|coupler| match coupler.as_array().map(|pair| &pair[..]) {
+        Some([u, v]) => Ok((index(u)?, index(v)?)),
+        _ => Err(LeapError::Response(format!("bad coupler {}", coupler)))
+    }).collect::<Result<Vec<(usize, usize)>, LeapError>>()?;
+    let num_qubits = match properties.get("num_qubits").and_then(Value::as_u64) {
+        Some(num_qubits) => num_qubits as usize,
+        None => qubits.iter().next_back().map_or(0, |qubit| qubit + 1)
+    };
+    Ok(SolverGraph { num_qubits, qubits, couplers })
+}
+
+
+// builds the submission of a QUBO to a solver in its qubit and coupler format, where every qubit has a linear
+// coefficient, which is NaN for those the problem leaves out, and every coupler between qubits in the problem
+// has a quadratic one; the QUBO's constant term isn't sent, since energies are taken from the QUBO itself
+pub fn encode_problem(qubo:&Qubo, graph:&SolverGraph, solver:&str, num_reads:usize) -> Result<Value, LeapError> {
+    let active:BTreeSet<usize> = qubo.variables().into_iter().collect();
+    if let Some(variable) = active.iter().find(|variable| !graph.qubits.contains(variable)) {
+        return Err(LeapError::Embedding(format!("variable {} isn't a working qubit", variable)));
+    }
+    let couplers:BTreeSet<(usize, usize)> = graph.couplers.iter().map(|&(u, v)| (u.min(v), u.max(v))).collect();
+    if let Some(&(u, v)) = qubo.quadratic().keys().find(|pair| !couplers.contains(pair)) {
+        return Err(LeapError::Embedding(format!("variables {} and {} aren't coupled", u, v)));
+    }
+
+    let lin:Vec<f64> = (0..graph.num_qubits)
+        .map(|qubit| if active.contains(&qubit) { qubo.linear().get(&qubit).cloned().unwrap_or(0) as f64 } else { f64::NAN })
+        .collect();
+    let quad:Vec<f64> = graph.couplers.iter()
+        .filter(|&&(u, v)| active.contains(&u) && active.contains(&v))
+        .map(|&(u, v)| qubo.quadratic().get(&(u.min(v), u.max(v))).cloned().unwrap_or(0) as f64)
+        .collect();
+    let bytes = |values:&[f64]| -> Vec<u8> { values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect() };
+    Ok(serde_json::json!([{
+        "solver": solver,
+        "type": "qubo",
+        "data": { "format": "qp", "lin": encode_base64(&bytes(&lin)), "quad": encode_base64(&bytes(&quad)) },
+        "params": { "num_reads": num_reads }
+    }]))
+}
+
+
+// reads the samples of an answer in the solver's format, where each solution packs the values of the active
+// variables into bytes, highest bit first
+pub fn parse_answer(answer:&Value) -> Result<Vec<LeapSample>, LeapError> {
+    let field = |name:&str| -> Result<Vec<u8>, LeapError> {
+        answer.get(name).and_then(Value::as_str)
+            .and_then(decode_base64)
+            .ok_or_else(|| LeapError::Response(format!("no {} in the answer", name)))
+    };
+    let active:Vec<usize> = field("active_variables")?.chunks(4)
+        .map(|chunk| chunk.iter().rev().fold(0, |index, byte| index << 8 | *byte as usize))
+        .collect();
+    let solutions = field("solutions")?;
+    let occurrences:Vec<usize> = match answer.get("num_occurrences") {
+        Some(_) => field("num_occurrences")?.chunks(4).map(|chunk| chunk.iter().rev().fold(0, |count, byte| count << 8 | *byte as usize)).collect(),
+        None => Vec::new()
+    };
+
+    let stride = active.len().div_ceil(8);
+    if stride == 0 {
+        return Ok(Vec::new());
+    }
+    Ok(solutions.chunks(stride).enumerate().map(|(solution, packed)| LeapSample {
+        sample: active.iter().enumerate()
+            .filter(|&(bit, _)| packed.get(bit / 8).is_some_and(|byte| byte >> (7 - bit % 8) & 1 == 1))
+            .map(|(_, variable)| *variable)
+            .collect(),
+        occurrences: occurrences.get(solution).cloned().unwrap_or(1)
+    }).collect())
+}
+
+
+// encodes bytes as standard, padded base64
+fn encode_base64(bytes:&[u8]) -> String {
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            text.push(if i <= chunk.len() { BASE64[(group >> (18 - 6 * i) & 63) as usize] as char } else { '=' });
+        }
+    }
+    text
+}
+
+
+// decodes standard base64, with or without padding, unless it holds anything else
+fn decode_base64(text:&str) -> Option<Vec<u8>> {
+    let digits:BTreeMap<u8, u32> = BASE64.iter().enumerate().map(|(value, digit)| (*digit, value as u32)).collect();
+    let mut bytes = Vec::new();
+    let (mut group, mut bits) = (0u32, 0);
+    for digit in text.trim_end_matches('=').bytes() {
+        group = group << 6 | digits.get(&digit)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+            group &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gadgets;
+
+    #[test]
+    fn base64() {
+        for bytes in [&b""[..], b"f", b"fo", b"foo", b"foob", &[0xff, 0x00, 0x80]].iter() {
+            assert_eq!(decode_base64(&encode_base64(bytes)), Some(bytes.to_vec()));
+        }
+        assert_eq!(encode_base64(b"foob"), "Zm9vYg==");
+        assert_eq!(decode_base64("Zm9v!"), None);
+    }
+
+    #[test]
+    fn problem() {
+        let solver = serde_json::json!({ "properties": { "num_qubits": 4, "qubits": [0, 1, 3], "couplers": [[0, 1], [1, 3]] } });
+        let graph = parse_solver_graph(&solver).unwrap();
+        assert_eq!((graph.num_qubits, graph.couplers.clone()), (4, vec![(0, 1), (1, 3)]));
+
+        // the inactive and broken qubits get NaN, and only the couplers between active qubits are listed
+        let qubo = gadgets::not(0, 1);
+        let problem = encode_problem(&qubo, &graph, "test", 10).unwrap();
+        let lin = decode_base64(problem[0]["data"]["lin"].as_str().unwrap()).unwrap();
+        let lin:Vec<f64> = lin.chunks(8).map(|chunk| { let mut bytes = [0; 8]; bytes.copy_from_slice(chunk); f64::from_le_bytes(bytes) }).collect();
+        assert_eq!((lin[0], lin[1]), (-1.0, -1.0));
+        assert!(lin[2].is_nan() && lin[3].is_nan());
+        assert_eq!(decode_base64(problem[0]["data"]["quad"].as_str().unwrap()).unwrap(), 2.0f64.to_le_bytes().to_vec());
+        assert_eq!(problem[0]["params"]["num_reads"], 10);
+
+        // a QUBO that isn't embedded is refused
+        assert!(matches!(encode_problem(&gadgets::not(0, 3), &graph, "test", 1), Err(LeapError::Embedding(_))));
+        assert!(matches!(encode_problem(&gadgets::not(0, 2), &graph, "test", 1), Err(LeapError::Embedding(_))));
+    }
+
+    #[test]
+    fn answer() {
+        // two solutions over qubits 1 and 3, the first setting qubit 3 and the second both
+        let answer = serde_json::json!({
+            "format": "qp",
+            "active_variables": encode_base64(&[1, 0, 0, 0, 3, 0, 0, 0]),
+            "solutions": encode_base64(&[0b0100_0000, 0b1100_0000]),
+            "num_occurrences": encode_base64(&[7, 0, 0, 0, 1, 0, 0, 0])
+        });
+        let samples = parse_answer(&answer).unwrap();
+        assert_eq!(samples, vec![
+            LeapSample { sample: vec![3].into_iter().collect(), occurrences: 7 },
+            LeapSample { sample: vec![1, 3].into_iter().collect(), occurrences: 1 }
+        ]);
+        assert_eq!(LeapError::MissingToken.to_string(), "DWAVE_API_TOKEN isn't set");
+    }
+}
@@ -28,10 +28,20 @@
 #[cfg(not(feature = "std"))]
 extern crate hashmap_core;
 
+// the pyo3 macros refer to ::core, which only resolves in 2015 edition
+// crates that declare it
+#[cfg(feature = "pyo3")]
+extern crate core;
+#[cfg(feature = "pyo3")]
+extern crate pyo3;
+
 #[cfg(not(feature = "std"))]
 #[macro_use]
 extern crate alloc;
 
+#[cfg(test)]
+extern crate proptest;
+
 pub use binary_reader::BinaryReader;
 pub use binary_reader::Range;
 use binary_reader::SectionHeader;
@@ -123,7 +133,80 @@ mod primitives;
 mod readers;
 mod tests;
 mod validator;
+#[cfg(feature = "pfc")]
+pub mod batch;
+#[cfg(feature = "pfc")]
+pub mod branches;
+#[cfg(feature = "pfc")]
+pub mod callgraph;
+#[cfg(feature = "pfc")]
+pub mod checkpoint;
+#[cfg(feature = "pfc")]
+pub mod console;
+#[cfg(feature = "pfc")]
+pub mod coverage;
+#[cfg(feature = "pfc")]
+pub mod decode;
+#[cfg(feature = "pfc")]
+pub mod emit;
+#[cfg(feature = "pfc")]
+pub mod gadgets;
+#[cfg(feature = "pfc")]
+pub mod harness;
+#[cfg(feature = "pfc")]
+pub mod hazards;
+#[cfg(feature = "pfc")]
+pub mod hints;
+#[cfg(feature = "pfc")]
+pub mod ids;
+#[cfg(feature = "pfc")]
+pub mod interp;
+#[cfg(feature = "pfc")]
+pub mod lanes;
+#[cfg(feature = "pfc")]
 pub mod parallelize;
+#[cfg(feature = "pfc")]
+pub mod partition;
+#[cfg(feature = "pfc")]
+pub mod passes;
+#[cfg(feature = "pfc")]
+pub mod pfc;
+#[cfg(feature = "pfc")]
+pub mod points;
+#[cfg(feature = "pfc")]
+pub mod precision;
+#[cfg(feature = "pfc")]
+pub mod profile;
+#[cfg(feature = "pfc")]
+pub mod provenance;
+#[cfg(feature = "pfc")]
+pub mod qaoa;
+#[cfg(feature = "pfc")]
+pub mod ranges;
+#[cfg(feature = "pfc")]
+pub mod simplify;
+#[cfg(feature = "pfc")]
+pub mod snapshot;
+#[cfg(feature = "pfc")]
+pub mod ssa;
+#[cfg(feature = "pfc")]
+pub mod structure;
+#[cfg(feature = "pfc")]
+pub mod support;
+#[cfg(feature = "pfc")]
+pub mod typecheck;
+#[cfg(feature = "pfc")]
+pub mod validation;
+#[cfg(feature = "pfc")]
+pub mod visit;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "leap")]
+pub mod leap;
+#[cfg(feature = "pyo3")]
+pub mod python;
+#[cfg(feature = "tui")]
+pub mod tui;
 
 #[cfg(not(feature = "std"))]
 mod std {
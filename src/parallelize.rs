@@ -2,24 +2,74 @@
 //! Data structures that represent the various transformations of WASM programs throughout parallelization, 
 //! dependency tree collapse and compilation to simulatable transfer functions for D-Wave
 
-extern crate termcolor;
 extern crate print_flat_tree;
+extern crate regex;
+#[cfg(feature = "wat")]
+extern crate wat;
 
-use std::env;
+#[cfg(feature = "std-io")]
 use std::fs::File;
+#[cfg(feature = "std-io")]
 use std::io;
+#[cfg(feature = "std-io")]
 use std::io::prelude::*;
-use std::process::Command;
 use std::str;
-use std::io::Write;
-use std::collections::HashMap;
-use primitives::Type;
+use std::ops;
+use std::fmt::{Display, Formatter};
+use std::collections::{btree_map, hash_map, BTreeMap, HashMap};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use primitives::{ImportSectionEntryType, MemoryImmediate, MemoryType, ResizableLimits, SectionCode, Type};
 use self::print_flat_tree::fmt;
-use self::termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
-use crate::Operator;
-use crate::{WasmDecoder, ParserState, ParserInput, ValidatingParser, ValidatingOperatorParser};
+use self::regex::Regex;
+use crate::{BinaryReader, BinaryReaderError, CustomSectionKind, ExternalKind, NameEntry, Operator, Range};
+use crate::{WasmDecoder, ParserState, ParserInput, ValidatingParser, ValidatingParserConfig, ValidatingOperatorParser};
+use crate::OperatorValidatorConfig;
 use crate::operators_validator::WasmModuleResources;
 use crate::readers::FunctionBody;
+#[cfg(feature = "std-io")]
+use batch;
+#[cfg(feature = "std-io")]
+use batch::{BatchReport, ModuleReport};
+use callgraph::CallGraph;
+use coverage;
+use coverage::CoverageReport;
+use checkpoint::Checkpoint;
+use provenance::{LineTable, SourceRange};
+use hints;
+use hints::Hints;
+use profile::{PassTiming, Profiler};
+use console;
+use console::{ConsolePrinter, Highlight, StandardStream, Theme, WriteColor};
+#[cfg(feature = "std-io")]
+use checkpoint::CheckpointObserver;
+use lanes::{LaneOperation, LaneShape};
+use hazards::{AccessKind, HazardReport, MemAccess, MemoryAccess, RangeCoupling, RangeKind, SyncCoupling, SyncKind};
+use ids::{BlockId, FuncId, NodeId};
+use simplify;
+use gadgets;
+use gadgets::{Circuit, Qubo};
+use ssa;
+use points::{relocate_keys, OffsetMap, ProgramPoint};
+use precision;
+use precision::PrecisionConfig;
+use ranges;
+use ranges::ValueRange;
+use snapshot::AnalysisSnapshot;
+use structure;
+use structure::StructureDiagnostic;
+use typecheck;
+use typecheck::TypeError;
+use validation;
+use validation::ValidationReport;
+use ssa::{Definition, ShiftKind, ValueId};
+use branches::{BranchCounts, BranchProfile};
+use passes::{PassError, PassManager};
+use support::{operator_name, OperatorCategory, OperatorHistogram, Support, SupportMatrix, UnsupportedOperator, UnsupportedOperatorsError};
+use visit;
+use visit::{Flow, OperatorVisitor};
 
 
 /// The physical expression enum represents the valid
@@ -34,24 +84,702 @@ pub enum PhysicalExpression {
 }
 
 
+impl PhysicalExpression {
+    // creates the sum of two expressions
+    pub fn sum(operand_one:PhysicalExpression, operand_two:PhysicalExpression) -> PhysicalExpression {
+        PhysicalExpression::Add{ operand_one: Box::new(operand_one), operand_two: Box::new(operand_two) }
+    }
+
+    // creates the product of two expressions
+    pub fn product(operand_one:PhysicalExpression, operand_two:PhysicalExpression) -> PhysicalExpression {
+        PhysicalExpression::Mul{ operand_one: Box::new(operand_one), operand_two: Box::new(operand_two) }
+    }
+
+    // creates a spin, which is +1 when set and -1 otherwise
+    pub fn spin(val:bool) -> PhysicalExpression {
+        PhysicalExpression::Spin{ val }
+    }
+
+    // creates a constant
+    pub fn num(val:usize) -> PhysicalExpression {
+        PhysicalExpression::Num{ val }
+    }
+
+    // creates a binary variable, which is 1 when set and 0 otherwise
+    pub fn binary(val:bool) -> PhysicalExpression {
+        PhysicalExpression::Binary{ val }
+    }
+
+    // returns the operands of a sum or product, which leaves have none of
+    pub fn operands(&self) -> Vec<&PhysicalExpression> {
+        match *self {
+            PhysicalExpression::Add { ref operand_one, ref operand_two }
+            | PhysicalExpression::Mul { ref operand_one, ref operand_two } => vec![&**operand_one, &**operand_two],
+            _ => Vec::new()
+        }
+    }
+
+    // simplifies the expression algebraically, returning none if it comes to zero
+    pub fn simplify(&self) -> Option<PhysicalExpression> {
+        simplify::simplify(self)
+    }
+
+    // walks the expression tree depth first, letting the visitor see each expression before and after
+    // its operands
+    pub fn walk(&self, visitor:&mut dyn ExpressionVisitor) {
+        visitor.enter(self);
+        for operand in self.operands() {
+            operand.walk(visitor);
+        }
+        visitor.leave(self);
+    }
+}
+
+
+impl Display for PhysicalExpression {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match *self {
+            PhysicalExpression::Add { ref operand_one, ref operand_two } => write!(f, "({} + {})", operand_one, operand_two),
+            PhysicalExpression::Mul { ref operand_one, ref operand_two } => write!(f, "{} * {}", Factor(operand_one), Factor(operand_two)),
+            PhysicalExpression::Spin { val } => write!(f, "s{}", if val { "+" } else { "-" }),
+            PhysicalExpression::Num { val } => write!(f, "{}", val),
+            PhysicalExpression::Binary { val } => write!(f, "b{}", val as u8)
+        }
+    }
+}
+
+
+// displays an operand of a product, which only needs parentheses if it is a product itself, since sums
+// already have them
+struct Factor<'a>(&'a PhysicalExpression);
+
+
+impl<'a> Display for Factor<'a> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match *self.0 {
+            PhysicalExpression::Mul { .. } => write!(f, "({})", self.0),
+            _ => write!(f, "{}", self.0)
+        }
+    }
+}
+
+
+/// An expression visitor is shown every expression of a tree as it is
+/// walked, before and after the expression's operands
+pub trait ExpressionVisitor {
+    // called before the expression's operands are walked
+    fn enter(&mut self, _expression:&PhysicalExpression) {}
+
+    // called once the expression's operands have been walked
+    fn leave(&mut self, _expression:&PhysicalExpression) {}
+}
+
+
 /// The abstract operation enum represents logical operations
 /// that can be compiled to simulatable transfer functions
 /// for quantum annealers.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum AbstractExpression {
     Spin { id: usize },
     Num { val: usize },
     Add { ty: Type },
-    Mul { ty: Type }
+    Mul { ty: Type },
+    DivU { ty: Type }, // the unsigned quotient of the two values on top of the stack
+    RemU { ty: Type }, // the unsigned remainder of the two values on top of the stack
+    ShrU { ty: Type, bits: usize }, // the value on top of the stack shifted right by a constant number of bits, filling in zeros
+    MulShr { ty: Type, multiplier: u64, shift: usize }, // the value on top of the stack times a constant, shifted right before wrapping
+    Clz { ty: Type }, // the number of zero bits above the highest set bit of the value on top of the stack
+    Ctz { ty: Type }, // the number of zero bits below the lowest set bit of the value on top of the stack
+    Popcnt { ty: Type }, // the number of set bits of the value on top of the stack
+    IsZero { ty: Type }, // an i32 holding one if the value on top of the stack is zero, and zero otherwise
+    Min { ty: Type }, // the lesser of the two floats on top of the stack
+    Max { ty: Type }, // the greater of the two floats on top of the stack
+    Copysign { ty: Type }, // the float below the top of the stack with the sign of the float on top of it
+    Abs { ty: Type }, // the float on top of the stack with its sign cleared
+    Neg { ty: Type }, // the float on top of the stack with its sign flipped
+    Shift { ty: Type, kind: ShiftKind }, // the value below the top of the stack shifted or rotated by the amount on top of it, modulo its width
+    Reuse { location: usize }, // the result of the operation at the given location, shared instead of recomputed
+    Load { id: usize, width: LoadWidth }, // an input variable read from memory at the address on top of the stack
+    Extend { from: Type, bits: usize, to: Type, signed: bool }, // the lowest bits of the value on top of the stack, sign or zero extended
+    Truncate { from: Type, to: Type, signed: bool }, // the value on top of the stack narrowed to an integer, wrapping integers and saturating floats
+    Reinterpret { from: Type, to: Type } // the bits of the value on top of the stack, taken as a value of another type of the same width
+}
+
+
+/// A load width is how many bits a load reads from memory and whether
+/// they are sign or zero extended to the type of the variable it reads;
+/// memory is little-endian, so the bits read are the value's lowest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LoadWidth {
+    pub bits: usize, // the bits read from memory
+    pub signed: bool // whether the bits are sign extended rather than zero extended
+}
+
+
+impl LoadWidth {
+    // returns the width of a load that reads a whole value of the given type
+    pub fn full(ty:Type) -> LoadWidth {
+        LoadWidth { bits: type_width(ty), signed: false }
+    }
+
+    // returns whether the load reads fewer bits than a value of the given type has
+    pub fn is_partial(&self, ty:Type) -> bool {
+        self.bits < type_width(ty)
+    }
+
+    // masks a value to the bits read and extends it to the given type, wrapping it to the type's width
+    pub fn extend(&self, value:u64, ty:Type) -> u64 {
+        let width = type_width(ty).min(64);
+        let mask = |bits:usize| if bits >= 64 { u64::MAX } else { (1 << bits) - 1 };
+        let bits = self.bits.min(width);
+        let mut value = value & mask(bits);
+        if self.signed && bits > 0 && value >> (bits - 1) & 1 == 1 {
+            value |= !mask(bits);
+        }
+        value & mask(width)
+    }
+}
+
+
+// shows an operation compactly, e.g. `spin 3`, `add I32`, `load 4 (8 bits, signed)` or `truncate F64 to I32 (unsigned)`
+impl Display for AbstractExpression {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match *self {
+            AbstractExpression::Spin { id } => write!(f, "spin {}", id),
+            AbstractExpression::Num { val } => write!(f, "num {}", val),
+            AbstractExpression::Add { ty } => write!(f, "add {:?}", ty),
+            AbstractExpression::Mul { ty } => write!(f, "mul {:?}", ty),
+            AbstractExpression::DivU { ty } => write!(f, "div_u {:?}", ty),
+            AbstractExpression::RemU { ty } => write!(f, "rem_u {:?}", ty),
+            AbstractExpression::ShrU { ty, bits } => write!(f, "shr_u {:?} by {}", ty, bits),
+            AbstractExpression::MulShr { ty, multiplier, shift } => write!(f, "mul_shr {:?} by {} >> {}", ty, multiplier, shift),
+            AbstractExpression::Clz { ty } => write!(f, "clz {:?}", ty),
+            AbstractExpression::Ctz { ty } => write!(f, "ctz {:?}", ty),
+            AbstractExpression::Popcnt { ty } => write!(f, "popcnt {:?}", ty),
+            AbstractExpression::IsZero { ty } => write!(f, "eqz {:?}", ty),
+            AbstractExpression::Min { ty } => write!(f, "min {:?}", ty),
+            AbstractExpression::Max { ty } => write!(f, "max {:?}", ty),
+            AbstractExpression::Copysign { ty } => write!(f, "copysign {:?}", ty),
+            AbstractExpression::Abs { ty } => write!(f, "abs {:?}", ty),
+            AbstractExpression::Neg { ty } => write!(f, "neg {:?}", ty),
+            AbstractExpression::Shift { ty, kind } => write!(f, "{} {:?}", kind, ty),
+            AbstractExpression::Reuse { location } => write!(f, "reuse @{}", location),
+            AbstractExpression::Load { id, width } => write!(f, "load {} ({} bits, {})", id, width.bits, if width.signed { "signed" } else { "unsigned" }),
+            AbstractExpression::Extend { from, bits, to, signed } => write!(f, "extend {:?} to {:?} ({} bits, {})", from, to, bits, if signed { "signed" } else { "unsigned" }),
+            AbstractExpression::Truncate { from, to, signed } => write!(f, "truncate {:?} to {:?} ({})", from, to, if signed { "signed" } else { "unsigned" }),
+            AbstractExpression::Reinterpret { from, to } => write!(f, "reinterpret {:?} as {:?}", from, to)
+        }
+    }
+}
+
+
+impl AbstractExpression {
+    // estimates the auxiliary qubits needed to lower the operation, assuming a binary encoding
+    // with a carry bit per result bit for additions and a partial product per bit pair for multiplications
+    pub fn auxiliary_qubits(&self) -> usize {
+        self.auxiliary_qubits_within(None)
+    }
+
+    // estimates the auxiliary qubits needed to lower the operation when its values are known to fit in the
+    // given number of bits, if fewer than its type has
+    pub fn auxiliary_qubits_within(&self, bit_width:Option<usize>) -> usize {
+        let width = |ty:Type| bit_width.map_or(type_width(ty), |bits| bits.min(type_width(ty)));
+        match *self {
+            AbstractExpression::Add { ty } => 2 * width(ty),
+            AbstractExpression::Mul { ty } => width(ty) * width(ty),
+
+            // a general division multiplies out its quotient, while a constant multiple adds up shifted copies
+            AbstractExpression::DivU { ty } | AbstractExpression::RemU { ty } => width(ty) * width(ty) + 4 * width(ty),
+            AbstractExpression::MulShr { ty, multiplier, .. } => 2 * multiplier.count_ones() as usize * width(ty),
+
+            // bits are counted by a tree of adders, and zeros by a priority encoder
+            AbstractExpression::Popcnt { ty } => gadgets::popcount_variables(width(ty), width(ty)),
+            AbstractExpression::Clz { ty } | AbstractExpression::Ctz { ty } => gadgets::zero_count_variables(width(ty), width(ty)),
+
+            // a zero test negates an OR of the operand's bits, and pads its result to an i32
+            AbstractExpression::IsZero { ty } => width(ty) + 1,
+
+            // a float minimum or maximum compares the magnitudes both ways, selects each bit by the comparison and
+            // checks either operand for NaN, while clearing or flipping a sign takes a variable for the new sign
+            AbstractExpression::Min { ty } | AbstractExpression::Max { ty } => 11 * type_width(ty) + 8,
+            AbstractExpression::Abs { .. } | AbstractExpression::Neg { .. } => 1,
+
+            // a shift by an amount that isn't known takes a barrel shifter over the operand padded to its type
+            AbstractExpression::Shift { ty, kind } => gadgets::shift_variables(type_width(ty), kind == ShiftKind::ShrS, kind == ShiftKind::Rotl || kind == ShiftKind::Rotr),
+
+            // a float's integer part takes a word of its own, which a multiplexer per bit saturates
+            AbstractExpression::Truncate { from, to, .. } if from == Type::F32 || from == Type::F64 => type_width(from) + 4 * width(to),
+            _ => 0
+        }
+    }
+
+    // applies an operation taking a single operand to a value: a conversion, taking floats by their bits, a
+    // shift by a constant or a count of bits; a float's integer part saturates to the integer type's range, with NaN converted to
+    // zero, which is what WASM's saturating truncations do and Rust's casts do too
+    pub fn unary(&self, value:u64) -> Option<u64> {
+        match *self {
+            AbstractExpression::ShrU { ty, bits } => Some(LoadWidth::full(ty).extend(value, ty).checked_shr(bits as u32).unwrap_or(0)),
+            AbstractExpression::MulShr { ty, multiplier, shift } => {
+                let product = LoadWidth::full(ty).extend(value, ty) as u128 * multiplier as u128;
+                Some(LoadWidth::full(ty).extend(product.checked_shr(shift as u32).unwrap_or(0) as u64, ty))
+            }
+            AbstractExpression::Popcnt { ty } => Some(LoadWidth::full(ty).extend(value, ty).count_ones() as u64),
+            AbstractExpression::IsZero { ty } => Some((LoadWidth::full(ty).extend(value, ty) == 0) as u64),
+            AbstractExpression::Abs { ty } => Some(LoadWidth::full(ty).extend(value, ty) & !float_sign(ty)),
+            AbstractExpression::Neg { ty } => Some(LoadWidth::full(ty).extend(value ^ float_sign(ty), ty)),
+            AbstractExpression::Ctz { ty } => Some((LoadWidth::full(ty).extend(value, ty).trailing_zeros() as u64).min(type_width(ty) as u64)),
+            AbstractExpression::Clz { ty } => Some(LoadWidth::full(ty).extend(value, ty).leading_zeros() as u64 - (64 - type_width(ty).min(64) as u64)),
+            AbstractExpression::Extend { bits, to, signed, .. } => Some(LoadWidth { bits, signed }.extend(value, to)),
+            AbstractExpression::Reinterpret { .. } => Some(value),
+            AbstractExpression::Truncate { from: Type::I32, to, .. }
+            | AbstractExpression::Truncate { from: Type::I64, to, .. } => Some(LoadWidth::full(to).extend(value, to)),
+            AbstractExpression::Truncate { from, to, signed } => {
+                let float = match from {
+                    Type::F32 => f32::from_bits(value as u32) as f64,
+                    Type::F64 => f64::from_bits(value),
+                    _ => return None
+                };
+                match (to, signed) {
+                    (Type::I32, true) => Some(float as i32 as u32 as u64),
+                    (Type::I32, false) => Some(float as u32 as u64),
+                    (Type::I64, true) => Some(float as i64 as u64),
+                    (Type::I64, false) => Some(float as u64),
+                    _ => None
+                }
+            }
+            _ => None
+        }
+    }
+
+    // applies a float operation taking two operands to their bits: the lesser or greater of them, the negative
+    // zero being the lesser zero and a NaN operand making the result the canonical NaN, or the first with the
+    // second's sign; or shifts an integer by the other
+    pub fn binary(&self, lhs:u64, rhs:u64) -> Option<u64> {
+        let (ty, maximum) = match *self {
+            AbstractExpression::Shift { ty, kind } => return Some(kind.apply(lhs, rhs, type_width(ty))),
+            AbstractExpression::Min { ty } => (ty, false),
+            AbstractExpression::Max { ty } => (ty, true),
+            AbstractExpression::Copysign { ty } => {
+                let sign = float_sign(ty);
+                return Some(LoadWidth::full(ty).extend(lhs, ty) & !sign | rhs & sign);
+            }
+            _ => return None
+        };
+        let sign = float_sign(ty);
+        let (lhs, rhs) = (LoadWidth::full(ty).extend(lhs, ty), LoadWidth::full(ty).extend(rhs, ty));
+        let mantissa = type_width(ty) - 1 - exponent_width(ty);
+        let infinity = (sign - 1) >> mantissa << mantissa;
+        if lhs & (sign - 1) > infinity || rhs & (sign - 1) > infinity {
+            return Some(infinity | 1 << (mantissa - 1));
+        }
+
+        // negative floats are ordered the other way around, below the positive ones
+        let key = |value:u64| if value & sign == sign { !value & (sign - 1) } else { value | sign };
+        Some(if (key(lhs) < key(rhs)) != maximum { lhs } else { rhs })
+    }
+}
+
+
+// returns the number of bits used to encode a value of the given type
+fn type_width(ty:Type) -> usize {
+    match ty {
+        Type::I64 | Type::F64 => 64,
+        Type::V128 => 128,
+        _ => 32
+    }
+}
+
+
+// returns the number of bits of a float type's exponent
+fn exponent_width(ty:Type) -> usize {
+    match ty {
+        Type::F64 => 11,
+        _ => 8
+    }
+}
+
+
+// returns the sign bit of a float type
+fn float_sign(ty:Type) -> u64 {
+    1 << (type_width(ty).min(64) - 1)
+}
+
+
+// returns how many bits an integer load reads and how it extends them
+fn load_width(op:&Operator) -> LoadWidth {
+    let (bits, signed) = match *op {
+        Operator::I32Load8S { .. } | Operator::I64Load8S { .. } => (8, true),
+        Operator::I32Load8U { .. } | Operator::I64Load8U { .. }
+        | Operator::I32AtomicLoad8U { .. } | Operator::I64AtomicLoad8U { .. } => (8, false),
+        Operator::I32Load16S { .. } | Operator::I64Load16S { .. } => (16, true),
+        Operator::I32Load16U { .. } | Operator::I64Load16U { .. }
+        | Operator::I32AtomicLoad16U { .. } | Operator::I64AtomicLoad16U { .. } => (16, false),
+        Operator::I64Load32S { .. } => (32, true),
+        Operator::I64Load32U { .. } | Operator::I64AtomicLoad32U { .. } => (32, false),
+        Operator::I64Load { .. } | Operator::I64AtomicLoad { .. } => (64, false),
+        _ => (32, false)
+    };
+    LoadWidth { bits, signed }
+}
+
+
+// returns how many bytes a load or store reads or writes
+fn access_width(op:&Operator) -> usize {
+    match *op {
+        Operator::I32Load8S { .. } | Operator::I32Load8U { .. } | Operator::I64Load8S { .. } | Operator::I64Load8U { .. }
+        | Operator::I32AtomicLoad8U { .. } | Operator::I64AtomicLoad8U { .. }
+        | Operator::I32Store8 { .. } | Operator::I64Store8 { .. } | Operator::I32AtomicStore8 { .. } | Operator::I64AtomicStore8 { .. } => 1,
+        Operator::I32Load16S { .. } | Operator::I32Load16U { .. } | Operator::I64Load16S { .. } | Operator::I64Load16U { .. }
+        | Operator::I32AtomicLoad16U { .. } | Operator::I64AtomicLoad16U { .. }
+        | Operator::I32Store16 { .. } | Operator::I64Store16 { .. } | Operator::I32AtomicStore16 { .. } | Operator::I64AtomicStore16 { .. } => 2,
+        Operator::I64Load { .. } | Operator::F64Load { .. } | Operator::I64AtomicLoad { .. }
+        | Operator::I64Store { .. } | Operator::F64Store { .. } | Operator::I64AtomicStore { .. } => 8,
+        Operator::V128Load { .. } | Operator::V128Store { .. } => 16,
+        Operator::I64Wait { .. } => 8,
+        _ => {
+            // atomic updates name the bytes they update unless they update the whole value
+            let name = operator_name(op);
+            match name.as_str() {
+                _ if name.contains("Rmw8") => 1,
+                _ if name.contains("Rmw16") => 2,
+                _ if name.starts_with("I64AtomicRmw") && !name.contains("Rmw32") => 8,
+                _ => 4
+            }
+        }
+    }
+}
+
+
+// describes the memory access made by the load or store at a location of a node, which takes its address, and a
+// store its value too, off the stack
+fn mem_access(node:&Node, i:usize, op:&Operator, memarg:&MemoryImmediate, kind:AccessKind) -> MemAccess {
+    let operands = if kind == AccessKind::Read { 1 } else { 2 };
+    MemAccess {
+        static_offset: memarg.offset as usize,
+        align: 1 << memarg.flags,
+        width: access_width(op),
+        base_expr: node.address_base(i, operands),
+        address: node.address_constant(i, operands).map(|constant| constant + memarg.offset as usize),
+        kind
+    }
+}
+
+// describes the address an atomic operation synchronizes through, which it takes off the stack below its other
+// operands; operations that write are described as writes, and the others as reads
+fn sync_access(node:&Node, i:usize, op:&Operator, memarg:&MemoryImmediate, kind:SyncKind) -> MemAccess {
+    let operands = match kind {
+        SyncKind::Load => 1,
+        SyncKind::CompareExchange | SyncKind::Wait => 3,
+        _ => 2
+    };
+    let access_kind = if kind.access_kinds().contains(&AccessKind::Write) { AccessKind::Write } else { AccessKind::Read };
+    MemAccess {
+        static_offset: memarg.offset as usize,
+        align: 1 << memarg.flags,
+        width: access_width(op),
+        base_expr: node.address_base(i, operands),
+        address: node.address_constant(i, operands).map(|constant| constant + memarg.offset as usize),
+        kind: access_kind
+    }
+}
+
+
+// returns a multiplier and shift that divide every dividend of the given bits by a divisor that isn't a power of
+// two, as the product of the dividend and the multiplier shifted right, unless the divisor takes a multiplier wider
+// than the dividend; the multiplier is the reciprocal rounded up at the precision of the divisor's highest bit,
+// which is exact when the rounding adds no more than that bit's worth (Granlund and Montgomery, 1994)
+fn division_magic(divisor:u64, bits:usize) -> Option<(u64, usize)> {
+    let log = 63 - divisor.leading_zeros() as usize;
+    let shift = bits + log;
+    let power = 1u128 << shift;
+    let multiplier = power.div_ceil(divisor as u128);
+    match multiplier * divisor as u128 - power <= 1 << log && multiplier < 1 << bits {
+        true => Some((multiplier as u64, shift)),
+        false => None
+    }
+}
+
+
+// groups the integer additions of a graph that are only read by an addition of the same type into trees, returning
+// the operands of each tree by the addition at its root along with the additions folded into the trees; a tree of
+// two operands is a plain addition, so only larger trees are returned
+fn addition_trees(graph:&ssa::Graph) -> (HashMap<ValueId, Vec<ValueId>>, HashSet<ValueId>) {
+    let uses = graph.use_map();
+    let addition = |value:ValueId| {
+        let instruction = graph.instruction(value);
+        matches!(instruction.definition, Definition::Add { .. }) && (instruction.ty == Type::I32 || instruction.ty == Type::I64)
+    };
+    let folds = |value:ValueId| addition(value) && match uses.get(&value) {
+        Some(readers) if readers.len() == 1 => addition(readers[0]) && graph.instruction(readers[0]).ty == graph.instruction(value).ty,
+        _ => false
+    };
+
+    // operands are defined before the additions reading them, so a folded addition's operands are known by then
+    let mut operands:HashMap<ValueId, Vec<ValueId>> = HashMap::new();
+    let mut folded:HashSet<ValueId> = HashSet::new();
+    for instruction in graph.instructions() {
+        if let Definition::Add { lhs, rhs } = instruction.definition {
+            if !addition(instruction.value) {
+                continue;
+            }
+            let mut leaves = Vec::new();
+            for operand in [lhs, rhs].iter() {
+                match folded.contains(operand) {
+                    true => leaves.extend(operands.remove(operand).unwrap_or_default()),
+                    false => leaves.push(*operand)
+                }
+            }
+            if folds(instruction.value) {
+                folded.insert(instruction.value);
+            }
+            operands.insert(instruction.value, leaves);
+        }
+    }
+    operands.retain(|root, leaves| !folded.contains(root) && leaves.len() > 2);
+    (operands, folded)
+}
+
+
+/// A lowering plan summarizes what lowering a node will cost
+/// before any variables are allocated
+#[derive(Clone, Debug)]
+pub struct LoweringPlan {
+    pub id: usize, // the planned node's id
+    pub operations: usize, // simulatable operations left to lower
+    pub auxiliary_qubits: usize, // estimated auxiliary qubits needed to lower the operations
+    pub shared_operations: usize, // repeated operations whose results are shared instead of recomputed
+    pub qubit_savings: usize, // estimated auxiliary qubits saved by sharing results
+    pub adder_trees: usize, // chains of additions synthesized as carry-save adder trees
+    pub adder_savings: usize, // estimated auxiliary qubits saved by the adder trees over pairwise adders
+    pub multipliers: MultiplierCost // what the multiplications cost with the strategy lowering will use
+}
+
+
+/// The adder synthesis enum represents how lowering encodes integer
+/// additions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdderSynthesis {
+    RippleCarry, // every addition is a chain of full adders over its two operands
+    CarrySave // additions only read by other additions are grouped into trees whose columns are counted at once
+}
+
+
+/// The multiplier synthesis enum represents how lowering encodes
+/// integer multiplications, which nodes can select for themselves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MultiplierSynthesis {
+    ShiftAdd, // the first operand's bits select shifted copies of the second, added up by chains of full adders
+    Karatsuba { cutoff: usize } // operands wider than the cutoff are split into halves multiplied three times, narrower ones have their partial products counted by column
+}
+
+
+impl Display for MultiplierSynthesis {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match *self {
+            MultiplierSynthesis::ShiftAdd => write!(f, "shift-add"),
+            MultiplierSynthesis::Karatsuba { cutoff } => write!(f, "karatsuba (cutoff {})", cutoff)
+        }
+    }
+}
+
+
+/// A multiplier cost is what a node's integer multiplications take to
+/// encode with a given strategy, so strategies can be compared against
+/// the qubits and couplers a device has
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiplierCost {
+    pub strategy: MultiplierSynthesis,
+    pub multiplications: usize, // integer multiplications in the node
+    pub qubits: usize, // auxiliary variables allocated for their products
+    pub couplers: usize // quadratic terms their gadgets add up to
+}
+
+
+// returns the auxiliary variables and the quadratic terms of multiplying two words of the given width with the
+// given strategy, which are counted off a circuit synthesizing the product
+fn multiplier_cost(strategy:MultiplierSynthesis, width:usize) -> (usize, usize) {
+    let mut circuit = Circuit::new();
+    let (a, b) = (circuit.word(width), circuit.word(width));
+    multiply(&mut circuit, strategy, &a, &b, width);
+    (circuit.variable_count() - 2 * width, circuit.qubo().quadratic().len())
+}
+
+
+// returns the product of two words to the given width, synthesized with the given strategy
+fn multiply(circuit:&mut Circuit, strategy:MultiplierSynthesis, a:&[usize], b:&[usize], width:usize) -> Vec<usize> {
+    match strategy {
+        MultiplierSynthesis::ShiftAdd => circuit.product_to(a, b, width),
+        MultiplierSynthesis::Karatsuba { cutoff } => circuit.karatsuba_product_to(a, b, width, cutoff)
+    }
+}
+
+
+/// The NaN policy enum represents what lowering makes of NaN operands
+/// of float minimums and maximums, which are encoded by their bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NanPolicy {
+    Propagate, // a NaN operand makes the result the canonical NaN, as WASM has it, which takes checking both operands for NaN
+    Exclude // operands are assumed never to be NaN, which leaves out the checks; a NaN is ordered above infinity instead
+}
+
+
+/// A lowering config selects how lowering synthesizes a node's
+/// operations from gadgets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoweringConfig {
+    pub adders: AdderSynthesis, // how integer additions are encoded
+    pub multipliers: MultiplierSynthesis, // how integer multiplications are encoded, unless a node selects otherwise
+    pub infer_widths: bool, // whether values only get as many bits as range analysis infers they take
+    pub nans: NanPolicy // what float minimums and maximums do with NaN operands
+}
+
+
+impl Default for LoweringConfig {
+    fn default() -> LoweringConfig {
+        LoweringConfig { adders: AdderSynthesis::RippleCarry, multipliers: MultiplierSynthesis::ShiftAdd, infer_widths: false, nans: NanPolicy::Propagate }
+    }
+}
+
+
+/// A node output names a value of a node that can be pinned, so that
+/// lowering only leaves the assignments of the inputs producing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeOutput {
+    Result, // the value the node leaves on top of the stack, which is what a function returns
+    Value(ValueId) // a value of the node's SSA form
+}
+
+
+/// An input marking says what lowering does with an input variable of a
+/// node: a free input is solved for, a fixed one is set to a value and
+/// propagated through the operations reading it, and a don't-care is
+/// left free but not reported among the inputs of the constraint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputMarking {
+    Free,
+    Fixed(u64),
+    DontCare
+}
+
+
+/// Operation weights assign a cost to each kind of SSA definition,
+/// which is a unit cost unless set otherwise
+#[derive(Clone, Debug, Default)]
+pub struct OperationWeights {
+    weights: HashMap<String, usize> // costs replacing the default ones, by definition name
+}
+
+
+impl OperationWeights {
+    // creates weights with a unit cost per operation
+    pub fn new() -> OperationWeights {
+        OperationWeights::default()
+    }
+
+    // sets the cost of the definitions with the given name, e.g. "Mul"
+    pub fn set(&mut self, name:&str, weight:usize) {
+        self.weights.insert(name.to_string(), weight);
+    }
+
+    // returns the cost of a definition; joins and values of unrecorded instructions aren't operations,
+    // so they are free by default
+    pub fn weight(&self, definition:&Definition) -> usize {
+        match self.weights.get(definition.name()) {
+            Some(weight) => *weight,
+            None => match *definition {
+                Definition::Phi { .. } | Definition::Opaque => 0,
+                _ => 1
+            }
+        }
+    }
+
+    // returns the total cost of a graph's instructions and the cost of its longest chain of dependent
+    // instructions; operands are always defined before the instructions reading them
+    fn costs(&self, graph:&ssa::Graph) -> (usize, usize) {
+        let mut work = 0;
+        let mut spans:Vec<usize> = Vec::new();
+        for instruction in graph.instructions() {
+            let weight = self.weight(&instruction.definition);
+            let longest = instruction.definition.operands().iter().map(|operand| spans[*operand]).max().unwrap_or(0);
+            work += weight;
+            spans.push(longest + weight);
+        }
+        (work, spans.into_iter().max().unwrap_or(0))
+    }
+}
+
+
+/// A speedup estimate compares the total work of a module with
+/// the length of its critical path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpeedupEstimate {
+    pub work: usize, // the summed cost of every operation
+    pub critical_path: usize // the summed cost of the longest chain of dependent operations
+}
+
+
+impl SpeedupEstimate {
+    // returns the upper bound on the speedup parallelization can give, which is reached with
+    // unlimited parallel resources
+    pub fn speedup(&self) -> f64 {
+        if self.critical_path == 0 {
+            1.0
+        } else {
+            self.work as f64 / self.critical_path as f64
+        }
+    }
+}
+
+
+/// Annealability summarizes how feasible it is to lower a function and
+/// anneal its QUBO, along with what goes into that
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annealability {
+    pub id: usize, // the function's node id
+    pub histogram: OperatorHistogram, // the function's operators by category
+    pub simulatable: f64, // the share of the function's operators that are simulated, rather than only recorded or not handled
+    pub auxiliary_qubits: usize, // the auxiliary qubits its operations are estimated to take before lowering
+    pub qubits: usize, // the variables of its lowered QUBO
+    pub couplers: usize, // the quadratic terms of its lowered QUBO
+    pub dynamic_range: f64, // how many times larger the largest coefficient is than the smallest
+    pub flagged: usize, // the terms beyond the annealer's dynamic range
+    pub score: f64 // the product of the shares simulated, of the device's qubits left free and of the terms kept, from 0 to 1
+}
+
+
+// summarizes a function on a line, e.g. `function 3: score 0.72, 90% simulatable, 120 qubits, 340 couplers, dynamic range 8`
+impl Display for Annealability {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "function {}: score {:.2}, {:.0}% simulatable, {} qubits, {} couplers, dynamic range {}",
+            self.id, self.score, 100.0 * self.simulatable, self.qubits, self.couplers, self.dynamic_range)
+    }
+}
+
+
+/// A binarization records which QUBO variables hold the bits of a
+/// node's values, and the types they are read back as
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Binarization {
+    pub inputs: BTreeMap<usize, Vec<usize>>, // free input variables mapped to the QUBO variables holding their bits, lowest first
+    pub input_types: BTreeMap<usize, Type>, // the types of the free input variables
+    pub conditions: BTreeMap<usize, Vec<usize>>, // internal flow control variables mapped to the QUBO variables holding their bits, lowest first
+    pub result: Vec<usize>, // the QUBO variables holding the bits of the node's result, lowest first
+    pub result_type: Option<Type> // the type of the node's result, if it leaves one
 }
 
 
 /// A Constraint represents a nestable quantum unconstrained
-/// boolean optimization problem expression.
+/// boolean optimization problem expression, along with the penalties
+/// of the gadgets a node's operations were lowered to. A constraint
+/// without an expression or penalties is empty: it contributes no
+/// energy, so it is the identity of addition and what any constraint
+/// scaled by zero becomes.
 #[derive(Clone, Debug)]
 pub struct Constraint {
     id: usize, // maps each Constraint to its node
-    expression: Option<PhysicalExpression> // low level boolean expressions
+    expression: Option<PhysicalExpression>, // low level boolean expressions
+    qubo: Qubo, // the summed penalties of the gadgets lowered from the node's operations, over the bits of its values
+    binarizations: BTreeMap<usize, Binarization> // the binarizations of the node and of every node whose constraint was added to it, by node id
 }
 
 
@@ -60,7 +788,160 @@ impl Constraint {
 
         Constraint {
             id: node_id,
-            expression: None
+            expression: None,
+            qubo: Qubo::new(),
+            binarizations: vec![(node_id, Binarization::default())].into_iter().collect()
+        }
+    }
+
+    // creates an empty constraint for the given node
+    pub fn new(node_id:usize) -> Constraint {
+        Constraint::default(node_id)
+    }
+
+    // creates a constraint for the given node from a boolean expression
+    pub fn from_expression(node_id:usize, expression:PhysicalExpression) -> Constraint {
+        Constraint {
+            expression: Some(expression),
+            ..Constraint::default(node_id)
+        }
+    }
+
+    // checks whether the constraint has no expression and no penalties
+    pub fn is_empty(&self) -> bool {
+        self.expression.is_none() && self.qubo.is_empty()
+    }
+
+    // simplifies the constraint's expression, which empties the constraint if it comes to zero
+    pub fn simplify(&mut self) {
+        self.expression = self.expression.as_ref().and_then(|expression| expression.simplify());
+    }
+
+    // returns the id of the node the constraint was lowered from
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    // returns the constraint's boolean expression, if one has been built
+    pub fn get_expression(&self) -> Option<PhysicalExpression> {
+        self.expression.clone()
+    }
+
+    // sets the penalties of the gadgets the node's operations were lowered to
+    pub fn set_qubo(&mut self, qubo:Qubo) {
+        self.qubo = qubo;
+    }
+
+    // returns the penalties of the gadgets the node's operations were lowered to
+    pub fn get_qubo(&self) -> Qubo {
+        self.qubo.clone()
+    }
+
+    // borrows the penalties of the gadgets the node's operations were lowered to
+    pub fn qubo(&self) -> &Qubo {
+        &self.qubo
+    }
+
+    // returns the QUBO variables holding the bits of an input variable, lowest first, if it was lowered
+    pub fn get_input_bits(&self, var_id:usize) -> Option<Vec<usize>> {
+        self.input_bits().get(&var_id).cloned()
+    }
+
+    // borrows the input variables mapped to the QUBO variables holding their bits
+    pub fn input_bits(&self) -> &BTreeMap<usize, Vec<usize>> {
+        &self.binarizations[&self.id].inputs
+    }
+
+    // returns the QUBO variables holding the bits of an internal flow control variable, lowest first, if it
+    // was lowered; they are the bits of the condition's producer if the variable was coupled to it
+    pub fn get_condition_bits(&self, var_id:usize) -> Option<Vec<usize>> {
+        self.binarizations[&self.id].conditions.get(&var_id).cloned()
+    }
+
+    // returns the QUBO variables holding the bits of the node's result, lowest first, which are none if the
+    // node leaves nothing on the stack
+    pub fn get_result_bits(&self) -> Vec<usize> {
+        self.binarizations[&self.id].result.clone()
+    }
+
+    // borrows the binarizations of the node and of the nodes whose constraints were added to it, by node id
+    pub fn binarizations(&self) -> &BTreeMap<usize, Binarization> {
+        &self.binarizations
+    }
+
+    // returns how many QUBO variables the constraint's penalties and binarizations take, counting up to the
+    // highest index
+    fn variable_count(&self) -> usize {
+        let bits = self.binarizations.values().flat_map(|binarization| {
+            binarization.inputs.values().chain(binarization.conditions.values()).flatten().chain(binarization.result.iter())
+        });
+        self.qubo.variables().into_iter().chain(bits.cloned()).max().map_or(0, |highest| highest + 1)
+    }
+
+    // adds the constraint of a nested node, whose variables are moved past the constraint's own so the two
+    // don't share any, along with the binarizations recording them
+    fn nest(&mut self, child:Constraint) {
+        let by = self.variable_count();
+        let shift = |bits:&Vec<usize>| bits.iter().map(|bit| bit + by).collect::<Vec<usize>>();
+        for (node_id, binarization) in child.binarizations {
+            let shifted = Binarization {
+                inputs: binarization.inputs.iter().map(|(var_id, bits)| (*var_id, shift(bits))).collect(),
+                conditions: binarization.conditions.iter().map(|(var_id, bits)| (*var_id, shift(bits))).collect(),
+                result: shift(&binarization.result),
+                ..binarization
+            };
+            self.binarizations.entry(node_id).or_insert(shifted);
+        }
+        self.qubo += child.qubo.shifted(by);
+        self.expression = match (self.expression.take(), child.expression) {
+            (Some(lhs), Some(rhs)) => Some(PhysicalExpression::sum(lhs, rhs)),
+            (lhs, rhs) => lhs.or(rhs)
+        };
+    }
+}
+
+
+// adds two constraints' expressions and penalties, keeping the id of the left one; empty constraints leave the
+// other as is, and the penalties are taken to be over the same variables, so the binarizations of both are kept,
+// the left one's winning for a node both have
+impl ops::Add for Constraint {
+    type Output = Constraint;
+
+    fn add(self, other:Constraint) -> Constraint {
+        let expression = match (self.expression, other.expression) {
+            (Some(lhs), Some(rhs)) => Some(PhysicalExpression::sum(lhs, rhs)),
+            (lhs, rhs) => lhs.or(rhs)
+        };
+        let mut binarizations = other.binarizations;
+        binarizations.extend(self.binarizations);
+        Constraint { id: self.id, expression, qubo: self.qubo + other.qubo, binarizations }
+    }
+}
+
+
+// scales a constraint's expression; scaling by one leaves it as is and scaling by zero empties it
+impl ops::Mul<usize> for Constraint {
+    type Output = Constraint;
+
+    fn mul(self, scalar:usize) -> Constraint {
+        let expression = match (self.expression, scalar) {
+            (_, 0) => None,
+            (expression, 1) => expression,
+            (expression, scalar) => expression.map(|expression| PhysicalExpression::product(expression, PhysicalExpression::num(scalar)))
+        };
+        Constraint { expression, qubo: self.qubo * scalar as i64, ..self }
+    }
+}
+
+
+// shows the constraint's expression and penalties in infix notation along with the node it was lowered from,
+// with an empty constraint contributing nothing, e.g. `node 2: (s+ + 3 * b1)`, `node 3: 1 - x0` or `node 4: 0`
+impl Display for Constraint {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match (self.expression.as_ref(), self.qubo.is_empty()) {
+            (Some(expression), true) => write!(f, "node {}: {}", self.id, expression),
+            (Some(expression), false) => write!(f, "node {}: {} + {}", self.id, expression, self.qubo),
+            (None, _) => write!(f, "node {}: {}", self.id, self.qubo)
         }
     }
 }
@@ -72,25 +953,61 @@ impl Constraint {
 /// of code after parallelization.
 #[derive(Clone, Debug)]
 pub struct Node {
-    id: usize, // each function and block has an id
+    id: NodeId, // each function and block has an id
     instrs: Vec<u8>, // hex instructions of the node
+    splits: OffsetMap, // the ranges of the file split out of the node's instructions, which its offsets, kept those of the file, skip over
     branches: HashMap<usize, usize>, // internal locations and targets of branches
-    calls: HashMap<usize, usize>, // calls to other functions
+    calls: HashMap<usize, NodeId>, // locations of calls mapped to the nodes they call, which are functions until blocks are expanded into calls too
+    indirect_calls: HashMap<usize, usize>, // locations of indirect calls mapped to the types of the functions they can call
     start: usize, // where the node's insturctions start in the WASM source file
     end: usize, // where the node's insturctions end in the WASM source file
-    children: HashMap<usize, Node>, // calls to other functions, or internal blocks of code
+    children: BTreeMap<NodeId, Node>, // calls to other functions, or internal blocks of code, in order of id
+    shared_children: BTreeMap<NodeId, Arc<Node>>, // called functions whose expanded subtrees are kept once in the mapper's arena, and which the node holds a link to
+    truncations: HashMap<usize, usize>, // locations of blocks and calls left unexpanded by the expansion limits, mapped to the registry ids of the blocks, which the node's blocks tell apart, or to the called functions
+    annotations: HashSet<String>, // labels given to the function by handlers of the module's custom sections
+    source_locations: Vec<SourceRange>, // the lines of original source the node's instructions were compiled from, if the module says
     constants: HashMap<usize, Type>, // constants instantiated within the scope of the node
-    chains: HashMap<usize, Type>, // whether the spins at indeces i are coupled via chaining or anti-chaining
+    chains: HashMap<usize, bool>, // whether the spins at indeces i are coupled via chaining or anti-chaining
+    conditions: BTreeMap<usize, usize>, // locations of ifs, conditional branches and selects mapped to the locations of the operations producing their conditions
+    branch_sites: BTreeMap<usize, ProgramPoint>, // locations of ifs, conditional branches and selects mapped to the program points they were read from, whether or not their conditions' producers are known
+    branch_counts: BTreeMap<usize, BranchCounts>, // locations of conditions mapped to how often they went each way in a profiled run, if the mapper was given a profile
     internal_variables: HashMap<usize, Type>, // internal variables that will be used to simulate flow control
     input_variables: HashMap<usize, Type>, // all input variables including parameters, memory references, global references are given ids
     output_variables: HashMap<usize, Type>, // all output varibles including writes to memory and returns
-    global_input_data_couplings: HashMap<usize, usize>, // map of global variable locations to the coupled node's input variable ids
-    global_output_data_couplings: HashMap<usize, usize>, // map of global variable locations to the coupled node's output variable ids
+    global_input_data_couplings: BTreeMap<usize, Vec<(ProgramPoint, usize)>>, // map of global indices to the node's reads of them, in order, as the program points of the reads and the input variable ids they are coupled to
+    global_output_data_couplings: BTreeMap<usize, Vec<(ProgramPoint, usize)>>, // map of global indices to the node's writes of them, in order, as the program points of the writes and the output variable ids they are coupled to
     flow_control_couplings: HashMap<usize, usize>, // map of instruction locations to coupled flow control variable ids
-    input_data_couplings: HashMap<usize, usize>, // map of memory locations to the coupled node's input variable ids
-    output_data_couplings: HashMap<usize, usize>, // map of memory locations to the coupled node's output variable ids
-    blocks: HashMap<usize, usize>, // internal blocks' locations mapped to their ids as maintained by the mapper
-    operations: HashMap<usize, AbstractExpression> // simulatable operations
+    input_data_couplings: Vec<(MemAccess, usize)>, // the loads coupled to the node's input variables, in the order they were read, as the accesses they make and the variable ids
+    input_widths: HashMap<usize, LoadWidth>, // memory input variables mapped to how much of memory their loads read
+    output_data_couplings: Vec<(MemAccess, usize)>, // the stores coupled to the node's output variables, in the order they were read, as the accesses they make and the variable ids
+    blocks: HashMap<usize, BlockId>, // internal blocks' locations mapped to their ids as maintained by the mapper
+    operations: BTreeMap<usize, AbstractExpression>, // simulatable operations, in the order they were read
+    terminator: Option<usize>, // location of the first unconditional unreachable or return, after which code is dead
+    eliminated: HashSet<usize>, // locations of operations whose values were folded into later operations by optimization passes
+    function: bool, // whether the node is a whole function, whose instructions start with its locals declarations
+    locals: Vec<Type>, // the enclosing function's local index space, its parameters followed by its declared locals
+    params: usize, // how many of the locals are parameters
+    local_input_variables: HashMap<usize, usize>, // local indices mapped to the input variables holding their values on entry
+    local_output_variables: HashMap<usize, usize>, // local indices mapped to the output variables holding the values written to them
+    local_values: HashMap<usize, Option<usize>>, // local indices mapped to the locations of the operations whose values were last written to them while the node's code was read, with none for values that aren't known
+    memory_accesses: Vec<MemoryAccess>, // loads and stores in the order they were read
+    predicated: bool, // whether the node is a branch of an if whose else clause was merged into it as arithmetic on the condition
+    result_variable: Option<usize>, // the output variable holding the value a block leaves for its enclosing frame, if its type has one
+    result_couplings: HashMap<usize, usize>, // nested blocks' locations mapped to the input variables receiving their results
+    sync_couplings: HashMap<MemAccess, Vec<SyncCoupling>>, // the accesses of atomic operations mapped to the operations synchronizing through them
+    lane_operations: HashMap<usize, LaneOperation>, // SIMD operations broken down lane by lane, by location
+    input_lanes: HashMap<usize, LaneShape>, // vector input variables mapped to how their lanes are interpreted
+    output_lanes: HashMap<usize, LaneShape>, // vector output variables mapped to how their lanes are interpreted
+    memory_size_reads: HashMap<usize, usize>, // locations whose value is the memory's size in pages, mapped to where they start in the WASM file
+    memory_size_variables: HashMap<usize, usize>, // locations reading a memory size only known when running, mapped to the input variables holding it
+    grows_memory: bool, // whether the node grows the memory
+    bit_width: Option<usize>, // how many bits the node's values fit in, if it was hinted
+    multiplier: Option<MultiplierSynthesis>, // how the node's multiplications are encoded, if not as the lowering config selects
+    input_ranges: HashMap<usize, ValueRange>, // input variables mapped to the ranges their values are known to fall in
+    output_constraints: Vec<(NodeOutput, u64)>, // the outputs pinned to values when the node is lowered, in order
+    input_markings: HashMap<usize, InputMarking>, // input variables mapped to how lowering treats them, unless free
+    range_couplings: Vec<RangeCoupling>, // the bulk memory operations that weren't unrolled, in order
+    function_index: FuncId // the index of the function the node's code was read from
 }
 
 
@@ -98,1689 +1015,8655 @@ impl Node {
     fn default () -> Node {
         let instrs:Vec<u8> = Vec::new();
         let branches:HashMap<usize, usize> = HashMap::new();
-        let calls:HashMap<usize, usize> = HashMap::new();
-        let children:HashMap<usize, Node> = HashMap::new();
-        let blocks:HashMap<usize, usize> = HashMap::new();
+        let calls:HashMap<usize, NodeId> = HashMap::new();
+        let children:BTreeMap<NodeId, Node> = BTreeMap::new();
+        let blocks:HashMap<usize, BlockId> = HashMap::new();
         let start = 0;
         let end = 0;
-        let id = 0;
+        let id = NodeId(0);
         let internal_variables = HashMap::new();
         let input_variables = HashMap::new();
         let output_variables = HashMap::new();
         let constants = HashMap::new();
         let chains = HashMap::new();
         let flow_control_couplings = HashMap::new();
-        let input_data_couplings = HashMap::new();
-        let output_data_couplings = HashMap::new();
-        let global_input_data_couplings = HashMap::new();
-        let global_output_data_couplings = HashMap::new();
-        let operations = HashMap::new();
+        let input_data_couplings = Vec::new();
+        let output_data_couplings = Vec::new();
+        let global_input_data_couplings = BTreeMap::new();
+        let global_output_data_couplings = BTreeMap::new();
+        let operations = BTreeMap::new();
 
         Node {
             id: id,
             instrs: instrs,
+            splits: OffsetMap::default(),
             branches: branches,
             calls: calls,
+            indirect_calls: HashMap::new(),
             start: start,
             end: end,
             children: children,
+            shared_children: BTreeMap::new(),
+            truncations: HashMap::new(),
+            annotations: HashSet::new(),
+            source_locations: Vec::new(),
             blocks: blocks,
             internal_variables: internal_variables,
             input_variables: input_variables,
             output_variables: output_variables,
             constants: constants,
             chains: chains,
+            conditions: BTreeMap::new(),
+            branch_sites: BTreeMap::new(),
+            branch_counts: BTreeMap::new(),
             flow_control_couplings: flow_control_couplings,
             input_data_couplings: input_data_couplings,
+            input_widths: HashMap::new(),
             output_data_couplings: output_data_couplings,
             global_input_data_couplings: global_input_data_couplings,
             global_output_data_couplings: global_output_data_couplings,
-            operations: operations
+            operations: operations,
+            terminator: None,
+            eliminated: HashSet::new(),
+            function: false,
+            locals: Vec::new(),
+            params: 0,
+            local_input_variables: HashMap::new(),
+            local_output_variables: HashMap::new(),
+            local_values: HashMap::new(),
+            memory_accesses: Vec::new(),
+            predicated: false,
+            result_variable: None,
+            result_couplings: HashMap::new(),
+            sync_couplings: HashMap::new(),
+            lane_operations: HashMap::new(),
+            input_lanes: HashMap::new(),
+            output_lanes: HashMap::new(),
+            memory_size_reads: HashMap::new(),
+            memory_size_variables: HashMap::new(),
+            grows_memory: false,
+            bit_width: None,
+            multiplier: None,
+            input_ranges: HashMap::new(),
+            output_constraints: Vec::new(),
+            input_markings: HashMap::new(),
+            range_couplings: Vec::new(),
+            function_index: FuncId(0)
         }
     }
 
-    // lowers the node's code to a representation compatible with PyQUBO
+    // lowers the node's code to a representation compatible with PyQUBO, along with every nested node, without
+    // asking anything; lower_with leaves the decisions to an observer instead
     pub fn lower(&mut self) -> Constraint {
+        self.lower_with(&mut SilentObserver)
+    }
 
-        let constraint = Constraint::default(self.id);
-
-        // couplings can be made between all the types of variables
-        let input_variables = self.get_input_variables(); 
-        let internal_variables = self.get_internal_variables();
-        let constants = self.get_constants();
-
-        // describe the node to the user
-        println!("Node {} has {} input variabes, {} internal variables coupled with other nodes, and {} constants.", self.id, input_variables.len(), internal_variables.len(), constants.len());
-
-        // ask the user if they would still like to lower the node
-        let mut stdin = io::stdin();
-        let mut input = String::new();
-        println!("Do you want to lower node {} (yes/no)?", self.id);
-        stdin.read_line(&mut input);
-        if !(input == "no\n" || input == "n\n") {
-
-            for (i, operation) in self.get_operations() {
-
-                match operation {
-                    AbstractExpression::Add{ ty: Type::I32 } => {
+    // lowers the node, leaving the decisions that come up to the given observer
+    pub fn lower_with(&mut self, observer:&mut dyn MapperObserver) -> Constraint {
+        self.lower_with_config(observer, LoweringConfig::default())
+    }
 
-                        let mut operand_one:AbstractExpression;
-                        let mut operand_two:AbstractExpression;
-                        let mut var_id:usize = 0;
+    // lowers the node as the given config selects, leaving the decisions that come up to the given observer
+    pub fn lower_with_config(&mut self, observer:&mut dyn MapperObserver, config:LoweringConfig) -> Constraint {
+
+        let mut constraint = Constraint::default(self.id.0);
+        let mut nested:Vec<Constraint> = Vec::new();
+
+        // couplings can be made between all the types of variables, so the observer is told how many of each there are
+        let decision = Decision::Lower {
+            node: self.id.0,
+            input_variables: self.input_variables().len(),
+            internal_variables: self.internal_variables().len(),
+            constants: self.constants().len()
+        };
+        if observer.on_decision_needed(&decision) {
+
+            // the SSA form links each operation to the operations defining its operands, and a node whose
+            // operations can't be lowered is skipped, telling the observer why
+            let graph = self.to_ssa();
+            if let Some((_, _, message)) = self.lowering_problem(&graph) {
+                observer.on_warning(&message);
+                return constraint;
+            }
 
-                        match self.operations[&(i - 1)] {
-                            AbstractExpression::Spin { id }=> {
-                                let ty = input_variables[&id];
-                                if !(ty == Type::I32) {
-                                    panic!("Invalid operand for I32 addition near line {}!", i - 1);
-                                } else {
-                                    var_id = id;
+            for instruction in graph.instructions() {
+
+                match instruction.definition {
+                    Definition::Internal { .. } if self.branches.contains_key(&instruction.location) => (),
+                    Definition::Internal { .. } => {
+
+                        // internal flow control variables are coupled to the nested node they guard, but for those
+                        // of conditional branches, which guard the rest of their own node
+                        let child = match self.calls.get(&instruction.location) {
+                            Some(block_id) => self.get_callee(*block_id).cloned(),
+                            None => None
+                        };
+                        match child {
+                            Some(mut child) => {
+                                let child_id = child.get_id();
+
+                                // ask the observer if they would like to lower the nested node
+                                if observer.on_decision_needed(&Decision::LowerNested { node: self.id.0, child: child_id.0 }) {
+                                    nested.push(child.lower_with_config(observer, config));
                                 }
                             }
-                            AbstractExpression::Add { ty: Type::I32 } => {
-                                // TODO
-                            }
-                            AbstractExpression::Mul { ty: Type::I32 } => {
-                                // TODO
-                            }
-                            _ => {
-                                panic!("Unsupported operation encountered!");
+                            None => {
+                                observer.on_warning(&format!("Node {} has not been expanded, so the nested node at {} can't be lowered.", self.id, instruction.location));
                             }
                         }
+                    }
+                    _ => {
+                        continue;
+                    }
+                }
+            }
 
-                        match self.operations[&(i - 2)] {
-                            AbstractExpression::Spin { id } => {
-                                let ty = input_variables[&id];
-                                if !(ty == Type::I32) {
-                                    panic!("Invalid operand for I32 addition near line {}!", i - 2);
-                                } else {
-                                    var_id = id;
-                                }
-                            }
-                            AbstractExpression::Add { ty: Type::I32 } => {
-                                // TODO
-                            }
-                            AbstractExpression::Mul { ty: Type::I32 } => {
-                                // TODO
-                            }
-                            _ => {
-                                panic!("Unsupported operation encountered!");
-                            }
-                        }
+            // the operations are encoded by the gadgets of the primitive gates computing them, and the nested
+            // nodes' penalties follow over variables of their own
+            self.lower_graph(&graph, config, &mut constraint, observer);
+            for child in nested {
+                constraint.nest(child);
+            }
+        }
+        constraint
+    }
 
-                        match internal_variables.get(&i) {
-                            Some(internal) => {
-                                if *internal == Type::I32 && self.has_child(i) {
-                                    match self.get_child(i) {
-                                        Some(mut child) => {
-                                            let child_id = child.get_id();
-                                            let child_variables = child.get_input_variables();
-                                            let coupled_var = self.get_flow_control_couplings()[&var_id];
-                                            let child_var = child_variables[&coupled_var];
-
-                                            // ask the user if they would like to lower the nested node
-                                            let mut stdin = io::stdin();
-                                            let mut input = String::new();
-                                            println!("Do you want to lower the nested node {} (yes/no)?", child_id);
-                                            stdin.read_line(&mut input);
-                                            if !(input == "no\n" || input == "n\n") {
-                                                let sub_expression = child.lower();
-                                            } else {
-                                                let sub_expression = Constraint::default(child_id);
-                                    }
-                                        }
-                                        _ => {
-                                            panic!("Incomplete flow control coupling encountered!");
-                                        }
-                                    }
-                                }
-                            }
-                            None => {
-                                panic!("Incomplete flow control coupling encountered!");
-                            }
+    // returns the first problem keeping the node's SSA form from being lowered, as its location, the category of
+    // the warning it raises and a message: an operand of another type than its operation's, or a nested node
+    // that isn't coupled to the internal flow control variable guarding it
+    pub(crate) fn lowering_problem(&self, graph:&ssa::Graph) -> Option<(usize, WarningCategory, String)> {
+        for instruction in graph.instructions() {
+            match instruction.definition {
+                Definition::Add { lhs, rhs }
+                | Definition::Mul { lhs, rhs }
+                | Definition::Div { lhs, rhs }
+                | Definition::Rem { lhs, rhs }
+                | Definition::Min { lhs, rhs }
+                | Definition::Max { lhs, rhs }
+                | Definition::Copysign { lhs, rhs }
+                | Definition::Shift { lhs, rhs, .. } => {
+
+                    // both operands must have the operation's type
+                    for operand in [lhs, rhs].iter() {
+                        let operand = graph.instruction(*operand);
+                        if operand.ty != instruction.ty {
+                            let message = format!("Node {} has an invalid {:?} operand for a {:?} operation at {}, so it can't be lowered.", self.id, operand.ty, instruction.ty, instruction.location);
+                            return Some((instruction.location, WarningCategory::IllTyped, message));
                         }
                     }
-                    AbstractExpression::Add{ ty: Type::I64 } => {
-                        
-                    }
-                    AbstractExpression::Add{ ty: Type::F32 } => {
-                        
-                    }
-                    AbstractExpression::Add{ ty: Type::F64 } => {
-                        
-                    }
-                    AbstractExpression::Mul{ ty: Type::I32 } => {
-                        
-                    }
-                    AbstractExpression::Mul{ ty: Type::I64 } => {
-                        
-                    }
-                    AbstractExpression::Mul{ ty: Type::F32 } => {
-                        
+                }
+                Definition::Internal { .. } if self.branches.contains_key(&instruction.location) => (),
+                Definition::Internal { var_id, .. } => {
+                    let child = self.calls.get(&instruction.location).and_then(|block_id| self.get_callee(*block_id));
+                    if let Some(child) = child.filter(|child| !child.get_flow_control_couplings().contains_key(&var_id)) {
+                        let message = format!("Node {} isn't coupled to the flow control variable {} of node {}, so node {} can't be lowered.", child.get_id(), var_id, self.id, self.id);
+                        return Some((instruction.location, WarningCategory::IncompleteCoupling, message));
                     }
-                    AbstractExpression::Mul{ ty: Type::F64 } => {
-                        
+                }
+                _ => ()
+            }
+        }
+        None
+    }
+
+    // builds the penalties of a graph's operations from gadgets over the bits of its values, which are as wide as
+    // their types, or as the node's values are hinted to be, or as their ranges are if the config infers them;
+    // integer additions and multiplications wrap like their WASM counterparts, while the values of joins,
+    // unmodelled instructions and other arithmetic are left free; the pinned outputs are held to their values,
+    // the values computed from fixed inputs alone are substituted out of the penalties, and the constraint is
+    // told which bits hold the free inputs and the result
+    fn lower_graph(&self, graph:&ssa::Graph, config:LoweringConfig, constraint:&mut Constraint, observer:&mut dyn MapperObserver) {
+        let mut circuit = Circuit::new();
+        let mut words:Vec<Vec<usize>> = Vec::new();
+
+        // additions folded into an adder tree are only summed at the tree's root
+        let (trees, folded) = match config.adders {
+            AdderSynthesis::CarrySave => addition_trees(graph),
+            AdderSynthesis::RippleCarry => (HashMap::new(), HashSet::new())
+        };
+
+        let multiplier = self.multiplier.unwrap_or(config.multipliers);
+        let ranges = if config.infer_widths { self.infer_ranges(graph) } else { Vec::new() };
+
+        // every read of a variable shares the variable's bits, and operands only have as many bits as their
+        // values take, which can be fewer than the operations reading them
+        let mut variables:HashMap<(bool, usize), Vec<usize>> = HashMap::new();
+
+        // the values known once the fixed inputs are, along with whether they depend on a fixed input, since
+        // only those are substituted
+        let mut known:Vec<Option<u64>> = Vec::new();
+        let mut propagated:Vec<bool> = Vec::new();
+        let mut substitutions:BTreeMap<usize, bool> = BTreeMap::new();
+        let mut read:HashSet<ValueId> = HashSet::new();
+        let mut live:HashSet<ValueId> = graph.results().iter().cloned().collect();
+        for instruction in graph.instructions() {
+            let width = match ranges.get(instruction.value) {
+                Some(range) => range.bits().min(self.value_width(instruction.ty)),
+                None => self.value_width(instruction.ty)
+            };
+            let integer = instruction.ty == Type::I32 || instruction.ty == Type::I64;
+            let float = instruction.ty == Type::F32 || instruction.ty == Type::F64;
+            let mask = ValueRange::full(width).max;
+            let operands = |lhs:ValueId, rhs:ValueId| match (known[lhs], known[rhs]) {
+                (Some(lhs_value), Some(rhs_value)) if propagated[lhs] || propagated[rhs] => Some((lhs_value, rhs_value)),
+                _ => None
+            };
+            let fixed = match instruction.definition {
+                Definition::Input { var_id } => self.fixed_input(var_id),
+                Definition::Add { lhs, rhs } if integer && !folded.contains(&instruction.value) => operands(lhs, rhs).map(|(lhs, rhs)| lhs.wrapping_add(rhs)),
+                Definition::Mul { lhs, rhs } if integer => operands(lhs, rhs).map(|(lhs, rhs)| lhs.wrapping_mul(rhs)),
+                Definition::Extend { operand, bits, signed } if propagated[operand] => known[operand].map(|value| {
+                    let kept = value & ValueRange::full(bits).max;
+                    if signed && bits > 0 && bits < 64 && kept >> (bits - 1) & 1 == 1 { kept | !ValueRange::full(bits).max } else { kept }
+                }),
+                Definition::Reinterpret { operand } if propagated[operand] => known[operand],
+                Definition::Div { lhs, rhs } if integer => operands(lhs, rhs).and_then(|(lhs, rhs)| lhs.checked_div(rhs)),
+                Definition::Rem { lhs, rhs } if integer => operands(lhs, rhs).and_then(|(lhs, rhs)| lhs.checked_rem(rhs)),
+                Definition::Shr { operand, bits } if propagated[operand] => known[operand].map(|value| value.checked_shr(bits as u32).unwrap_or(0)),
+                Definition::MulShr { operand, multiplier, shift } if propagated[operand] => known[operand]
+                    .map(|value| (value as u128 * multiplier as u128).checked_shr(shift as u32).unwrap_or(0) as u64),
+                Definition::Clz { operand } if propagated[operand] => known[operand].and_then(|value| AbstractExpression::Clz{ ty: instruction.ty }.unary(value)),
+                Definition::Ctz { operand } if propagated[operand] => known[operand].and_then(|value| AbstractExpression::Ctz{ ty: instruction.ty }.unary(value)),
+                Definition::Popcnt { operand } if propagated[operand] => known[operand].map(|value| value.count_ones() as u64),
+                Definition::IsZero { operand } if propagated[operand] => known[operand].map(|value| (value == 0) as u64),
+                Definition::Min { lhs, rhs } if float => operands(lhs, rhs).and_then(|(lhs, rhs)| AbstractExpression::Min{ ty: instruction.ty }.binary(lhs, rhs)),
+                Definition::Max { lhs, rhs } if float => operands(lhs, rhs).and_then(|(lhs, rhs)| AbstractExpression::Max{ ty: instruction.ty }.binary(lhs, rhs)),
+                Definition::Copysign { lhs, rhs } if float => operands(lhs, rhs).and_then(|(lhs, rhs)| AbstractExpression::Copysign{ ty: instruction.ty }.binary(lhs, rhs)),
+                Definition::Shift { lhs, rhs, kind } if integer => operands(lhs, rhs).map(|(lhs, rhs)| kind.apply(lhs, rhs, type_width(instruction.ty))),
+                Definition::Abs { operand } if float && propagated[operand] => known[operand].and_then(|value| AbstractExpression::Abs{ ty: instruction.ty }.unary(value)),
+                Definition::Neg { operand } if float && propagated[operand] => known[operand].and_then(|value| AbstractExpression::Neg{ ty: instruction.ty }.unary(value)),
+                _ => None
+            }.map(|value| value & mask);
+            known.push(fixed.or(match instruction.definition {
+                Definition::Const { val } => Some(val as u64 & mask),
+                _ => None
+            }));
+            propagated.push(fixed.is_some());
+            for operand in instruction.definition.operands() {
+                read.insert(operand);
+
+                // a shift by a known amount doesn't read the amount's bits
+                let rewired = match instruction.definition {
+                    Definition::Shift { rhs, .. } => rhs == operand && known[rhs].is_some(),
+                    _ => false
+                };
+                if fixed.is_none() && !rewired {
+                    live.insert(operand);
+                }
+            }
+            if let Some(value) = fixed {
+                let word = circuit.constant(value, width);
+                substitutions.extend(word.iter().enumerate().map(|(bit, variable)| (*variable, bit < 64 && value >> bit & 1 == 1)));
+                words.push(word);
+                continue;
+            }
+
+            let word = match instruction.definition {
+                Definition::Input { var_id } => variables.entry((false, var_id)).or_insert_with(|| circuit.word(width)).clone(),
+                Definition::Internal { var_id, condition: None } => variables.entry((true, var_id)).or_insert_with(|| circuit.word(width)).clone(),
+
+                // a flow control variable coupled to the producer of its condition shares the producer's bits
+                Definition::Internal { var_id, condition: Some(condition) } => {
+                    let word = circuit.extend(&words[condition], width, false, width);
+                    variables.entry((true, var_id)).or_insert(word).clone()
+                }
+                Definition::Const { val } => circuit.constant(val as u64, width),
+                Definition::Add { .. } if folded.contains(&instruction.value) => Vec::new(),
+                Definition::Add { .. } if trees.contains_key(&instruction.value) => {
+                    let operands:Vec<(&[usize], usize, bool)> = trees[&instruction.value].iter().map(|operand| (&words[*operand][..], 0, false)).collect();
+                    circuit.carry_save_combination(&operands, width)
+                }
+                Definition::Add { lhs, rhs } if integer => circuit.sum_to(&words[lhs], &words[rhs], width),
+                Definition::Mul { lhs, rhs } if integer => multiply(&mut circuit, multiplier, &words[lhs], &words[rhs], width),
+                Definition::Div { lhs, rhs } if integer => circuit.divide(&words[lhs], &words[rhs], width).0,
+                Definition::Rem { lhs, rhs } if integer => circuit.divide(&words[lhs], &words[rhs], width).1,
+
+                // shifting by a constant drops the low bits, and the product of a constant multiple is wide enough
+                // that none of the bits kept are wrapped away
+                Definition::Shr { operand, bits } => {
+                    let kept = &words[operand][bits.min(words[operand].len())..];
+                    circuit.extend(kept, kept.len(), false, width)
+                }
+                Definition::MulShr { operand, multiplier, shift } => {
+                    let product = circuit.scaled(&words[operand], multiplier, shift + width);
+                    circuit.extend(&product[shift..], width, false, width)
+                }
+
+                // a shift is modulo the width of the operand's type, to which a narrowed operand is padded; a known
+                // amount only rewires its bits, while any other takes a barrel shifter
+                Definition::Shift { lhs, rhs, kind } if integer => {
+                    let bits = type_width(instruction.ty);
+                    let value = circuit.extend(&words[lhs], bits, false, bits);
+                    let rotate = kind == ShiftKind::Rotl || kind == ShiftKind::Rotr;
+                    let shifted = match (known[rhs], rotate) {
+                        (Some(amount), false) => circuit.shift_by(&value, amount, kind.is_left(), kind == ShiftKind::ShrS),
+                        (Some(amount), true) => circuit.rotate_by(&value, amount, kind.is_left()),
+                        (None, false) => circuit.shift(&value, &words[rhs], kind.is_left(), kind == ShiftKind::ShrS),
+                        (None, true) => circuit.rotate(&value, &words[rhs], kind.is_left())
+                    };
+                    circuit.extend(&shifted, width, false, width)
+                }
+
+                // an operand narrower than its type has its higher bits clear, which leading zeros count too
+                Definition::Popcnt { operand } if integer => circuit.popcount(&words[operand], width),
+                Definition::IsZero { operand } => {
+                    let zero = circuit.is_zero(&words[operand]);
+                    circuit.extend(&[zero], 1, false, width)
+                }
+                Definition::Ctz { operand } if integer => circuit.trailing_zeros(&words[operand], type_width(instruction.ty), width),
+                Definition::Clz { operand } if integer => circuit.leading_zeros(&words[operand], type_width(instruction.ty), width),
+
+                // a float is encoded by its bits, which are padded back to its type's width if a range narrowed them
+                // so that its sign is the highest; a minimum or maximum selects the bits of either operand by their
+                // order, and the other operations only rewire them, but for a new sign
+                Definition::Min { lhs, rhs } | Definition::Max { lhs, rhs } if float => {
+                    let bits = type_width(instruction.ty);
+                    let (lhs, rhs) = (circuit.extend(&words[lhs], bits, false, bits), circuit.extend(&words[rhs], bits, false, bits));
+                    let exponent = match config.nans {
+                        NanPolicy::Propagate => Some(exponent_width(instruction.ty)),
+                        NanPolicy::Exclude => None
+                    };
+                    let extremum = circuit.float_extremum(&lhs, &rhs, matches!(instruction.definition, Definition::Max { .. }), exponent);
+                    circuit.extend(&extremum, width, false, width)
+                }
+                Definition::Copysign { lhs, rhs } if float => {
+                    let bits = type_width(instruction.ty);
+                    let mut word = circuit.extend(&words[lhs], bits - 1, false, bits - 1);
+                    word.push(circuit.extend(&words[rhs], bits, false, bits)[bits - 1]);
+                    circuit.extend(&word, width, false, width)
+                }
+                Definition::Abs { operand } | Definition::Neg { operand } if float => {
+                    let bits = type_width(instruction.ty);
+                    let mut word = circuit.extend(&words[operand], bits, false, bits);
+                    word[bits - 1] = match instruction.definition {
+                        Definition::Neg { .. } => circuit.not(word[bits - 1]),
+                        _ => circuit.constant(0, 1)[0]
+                    };
+                    circuit.extend(&word, width, false, width)
+                }
+
+                // an operand narrower than the bits kept is known not to have the sign bit set
+                Definition::Extend { operand, bits, signed } => circuit.extend(&words[operand], bits, signed && words[operand].len() >= bits, width),
+
+                // a reinterpretation aliases the operand's variables, which are only padded if its range narrowed them
+                Definition::Reinterpret { operand } => circuit.extend(&words[operand], width, false, width),
+
+                // floats aren't encoded, so a float's integer part is a word of its own that is only saturated
+                Definition::Clamp { operand, bits, signed } => {
+                    let integer_part = match graph.instruction(operand).ty {
+                        Type::F32 | Type::F64 => circuit.word(words[operand].len().max(bits + 1)),
+                        _ => words[operand].clone()
+                    };
+                    circuit.clamp(&integer_part, bits, signed, width)
+                }
+                _ => circuit.word(width)
+            };
+            words.push(word);
+        }
+
+        // a pinned value folded into an adder tree has no bits of its own to hold
+        for &(output, value) in self.output_constraints.iter() {
+            let pinned = match output {
+                NodeOutput::Result => graph.results().last().cloned(),
+                NodeOutput::Value(value) => Some(value).filter(|value| *value < words.len())
+            };
+            match pinned {
+                Some(pinned) if !words[pinned].is_empty() => {
+                    let width = self.value_width(graph.instruction(pinned).ty);
+                    circuit.constrain(&words[pinned], value & ValueRange::full(width).max);
+                }
+                Some(pinned) => observer.on_warning(&format!("Node {} can't pin value {}, which is folded into an adder tree.", self.id, pinned)),
+                None => observer.on_warning(&format!("Node {} has no {:?} output to pin.", self.id, output))
+            }
+        }
+
+        let binarization = constraint.binarizations.entry(self.id.0).or_default();
+        binarization.inputs = variables.iter()
+            .filter(|&(&(internal, var_id), _)| !internal && self.get_input_marking(var_id) == InputMarking::Free)
+            .map(|(&(_, var_id), word)| (var_id, word.clone()))
+            .collect();
+        binarization.conditions = variables.iter()
+            .filter(|&(&(internal, _), _)| internal)
+            .map(|(&(_, var_id), word)| (var_id, word.clone()))
+            .collect();
+        binarization.input_types = binarization.inputs.keys().filter_map(|var_id| self.input_variables.get(var_id).map(|ty| (*var_id, *ty))).collect();
+        binarization.result = graph.results().last().map_or_else(Vec::new, |result| words[*result].clone());
+        binarization.result_type = graph.results().last().map(|result| graph.instruction(*result).ty);
+        // constants only read where fixed values were propagated go along with them
+        for constant in read.difference(&live).filter(|value| !propagated[**value]) {
+            if let Some(value) = known[*constant] {
+                substitutions.extend(words[*constant].iter().enumerate().map(|(bit, variable)| (*variable, bit < 64 && value >> bit & 1 == 1)));
+            }
+        }
+        let mut qubo = circuit.into_qubo();
+        qubo.substitute(&substitutions);
+        constraint.set_qubo(qubo);
+    }
+
+    // returns the SSA form of the node's operations; nested blocks are not included, since the
+    // node only refers to them by id, so their results are read from the input variables coupled to them
+    pub fn to_ssa(&self) -> ssa::Graph {
+        let mut graph = ssa::Graph::new(self.id.0);
+        let (results, _) = self.emit_ssa_helper(&HashMap::new(), &mut graph, 0);
+        graph.set_results(results);
+        graph
+    }
+
+    // borrows whether the flow control variables at indices i are chained or anti-chained to their couplings
+    pub fn chains(&self) -> &HashMap<usize, bool> {
+        &self.chains
+    }
+
+    // checks whether the node is a branch that runs unconditionally, its condition only selecting its result
+    pub fn is_predicated(&self) -> bool {
+        self.predicated
+    }
+
+    // checks whether the block at the given location is the else clause of an if, which is coupled to the if's
+    // condition like a branch is, but isn't guarded by an internal variable of its own at the location
+    pub(crate) fn is_else_clause(&self, i:usize, block:&Node) -> bool {
+        !block.flow_control_couplings.is_empty() && !self.internal_variables.contains_key(&i)
+    }
+
+    // emits the node's operations into a region of an SSA graph, recursing into the nested blocks found
+    // among the given registered blocks; returns the values left on the stack, and the region and values
+    // of the node's else clause, if it has one, so they can be joined with the node's own values
+    fn emit_ssa_helper(&self, blocks:&HashMap<BlockId, Node>, graph:&mut ssa::Graph, region:usize) -> (Vec<ValueId>, Option<(usize, Vec<ValueId>)>) {
+        let mut locations:Vec<usize> = self.operations.keys().chain(self.blocks.keys()).cloned().collect();
+        locations.sort();
+        locations.dedup();
+
+        let mut stack:Vec<ValueId> = Vec::new();
+        let mut values:HashMap<usize, ValueId> = HashMap::new();
+        let mut else_clause:Option<(usize, Vec<ValueId>)> = None;
+        let mut previous:Option<usize> = None;
+
+        for i in locations {
+
+            // an unrecorded instruction leaves the stack in an unknown state
+            if !self.is_contiguous(previous, i) {
+                stack.clear();
+            }
+            previous = Some(i);
+
+            if let Some(op) = self.operations.get(&i) {
+                let value = match *op {
+                    AbstractExpression::Spin { id } if id == i && self.internal_variables.contains_key(&i) => {
+
+                        // an if consumes its condition, which the internal flow control variable stands in for and
+                        // is coupled to if the condition's producer was tracked
+                        stack.pop();
+                        let condition = self.conditions.get(&i).and_then(|producer| values.get(producer)).cloned();
+                        let value = graph.define(region, i, self.internal_variables[&i], Definition::Internal{ var_id: id, condition });
+                        values.insert(i, value);
+                        None
+                    }
+                    AbstractExpression::Spin { id } => {
+                        let ty = self.input_variables.get(&id).cloned().unwrap_or(Type::AnyRef);
+                        Some(graph.define(region, i, ty, Definition::Input{ var_id: id }))
+                    }
+                    AbstractExpression::Num { val } => {
+                        let ty = self.constants.get(&i).cloned().unwrap_or(Type::I32);
+                        Some(graph.define(region, i, ty, Definition::Const{ val }))
+                    }
+                    AbstractExpression::Add { ty }
+                    | AbstractExpression::Mul { ty }
+                    | AbstractExpression::DivU { ty }
+                    | AbstractExpression::RemU { ty }
+                    | AbstractExpression::Min { ty }
+                    | AbstractExpression::Max { ty }
+                    | AbstractExpression::Copysign { ty }
+                    | AbstractExpression::Shift { ty, .. } => {
+
+                        // operands produced before an unrecorded instruction are unknown
+                        let rhs = match stack.pop() {
+                            Some(rhs) => rhs,
+                            None => graph.define(region, i, ty, Definition::Opaque)
+                        };
+                        let lhs = match stack.pop() {
+                            Some(lhs) => lhs,
+                            None => graph.define(region, i, ty, Definition::Opaque)
+                        };
+                        let definition = match *op {
+                            AbstractExpression::Add { .. } => Definition::Add{ lhs, rhs },
+                            AbstractExpression::DivU { .. } => Definition::Div{ lhs, rhs },
+                            AbstractExpression::RemU { .. } => Definition::Rem{ lhs, rhs },
+                            AbstractExpression::Min { .. } => Definition::Min{ lhs, rhs },
+                            AbstractExpression::Max { .. } => Definition::Max{ lhs, rhs },
+                            AbstractExpression::Copysign { .. } => Definition::Copysign{ lhs, rhs },
+                            AbstractExpression::Shift { kind, .. } => Definition::Shift{ lhs, rhs, kind },
+                            _ => Definition::Mul{ lhs, rhs }
+                        };
+                        Some(graph.define(region, i, ty, definition))
+                    }
+                    AbstractExpression::Reuse { location } => match values.get(&location) {
+                        Some(value) => Some(*value),
+                        None => Some(graph.define(region, i, Type::AnyRef, Definition::Opaque))
+                    },
+                    AbstractExpression::Load { id, width } => {
+
+                        // a load consumes its address, and only keeps the bits it read of a narrower access
+                        stack.pop();
+                        let ty = self.input_variables.get(&id).cloned().unwrap_or(Type::AnyRef);
+                        let operand = graph.define(region, i, ty, Definition::Input{ var_id: id });
+                        if width.is_partial(ty) {
+                            Some(graph.define(region, i, ty, Definition::Extend{ operand, bits: width.bits, signed: width.signed }))
+                        } else {
+                            Some(operand)
+                        }
                     }
-                    _ => {
-                        continue;
+                    AbstractExpression::ShrU { ty, .. } | AbstractExpression::MulShr { ty, .. }
+                    | AbstractExpression::Clz { ty } | AbstractExpression::Ctz { ty } | AbstractExpression::Popcnt { ty }
+                    | AbstractExpression::Abs { ty } | AbstractExpression::Neg { ty } | AbstractExpression::IsZero { ty } => {
+                        let operand = match stack.pop() {
+                            Some(operand) => operand,
+                            None => graph.define(region, i, ty, Definition::Opaque)
+                        };
+
+                        // a zero test of either integer type is an i32
+                        let ty = match *op {
+                            AbstractExpression::IsZero { .. } => Type::I32,
+                            _ => ty
+                        };
+                        let definition = match *op {
+                            AbstractExpression::ShrU { bits, .. } => Definition::Shr{ operand, bits },
+                            AbstractExpression::MulShr { multiplier, shift, .. } => Definition::MulShr{ operand, multiplier, shift },
+                            AbstractExpression::Clz { .. } => Definition::Clz{ operand },
+                            AbstractExpression::Ctz { .. } => Definition::Ctz{ operand },
+                            AbstractExpression::Popcnt { .. } => Definition::Popcnt{ operand },
+                            AbstractExpression::IsZero { .. } => Definition::IsZero{ operand },
+                            AbstractExpression::Abs { .. } => Definition::Abs{ operand },
+                            AbstractExpression::Neg { .. } => Definition::Neg{ operand },
+                            _ => Definition::Opaque
+                        };
+                        Some(graph.define(region, i, ty, definition))
+                    }
+                    AbstractExpression::Extend { from, to, .. }
+                    | AbstractExpression::Truncate { from, to, .. }
+                    | AbstractExpression::Reinterpret { from, to } => {
+                        let operand = match stack.pop() {
+                            Some(operand) => operand,
+                            None => graph.define(region, i, from, Definition::Opaque)
+                        };
+
+                        // wrapping an integer keeps its lowest bits, just as extending them to the narrower type does
+                        let definition = match *op {
+                            AbstractExpression::Extend { bits, signed, .. } => Definition::Extend{ operand, bits, signed },
+                            _ if from == Type::I32 || from == Type::I64 => Definition::Extend{ operand, bits: type_width(to), signed: false },
+                            AbstractExpression::Truncate { signed, .. } => Definition::Clamp{ operand, bits: type_width(to), signed },
+                            _ => Definition::Reinterpret{ operand }
+                        };
+                        Some(graph.define(region, i, to, definition))
+                    }
+                };
+                if let Some(value) = value {
+                    values.insert(i, value);
+                    stack.push(value);
+                }
+            }
+
+            // a block the registry doesn't hold still leaves its result in the input variable coupled to it
+            let block = match self.blocks.get(&i).and_then(|index| blocks.get(index)) {
+                Some(block) => Some(block),
+                None => {
+                    if let Some(var_id) = self.result_couplings.get(&i) {
+                        let value = graph.define(region, i, self.input_variables[var_id], Definition::Input{ var_id: *var_id });
+                        values.insert(i, value);
+                        stack.push(value);
                     }
+                    None
+                }
+            };
+            if let Some(block) = block {
+                let block_region = graph.add_region(self.blocks[&i].0, region, i);
+                let (results, nested_else_clause) = block.emit_ssa_helper(blocks, graph, block_region);
+
+                // an else clause's values only join the if's values once control leaves the if
+                if self.is_else_clause(i, block) {
+                    else_clause = Some((block_region, results));
+                    continue;
                 }
+
+                // the block's result reaches the node through the input variable coupled to it, which holds the
+                // value the block left, joined with its else clause's if it has one; a block leaving no known
+                // value leaves the variable as it is read from outside
+                let var_id = match self.result_couplings.get(&i) {
+                    Some(var_id) => *var_id,
+                    None => continue
+                };
+                let value = match (results.last(), nested_else_clause) {
+                    (Some(then_value), Some((else_region, ref else_results))) if !else_results.is_empty() => {
+                        let else_value = else_results[else_results.len() - 1];
+                        let ty = graph.instruction(*then_value).ty;
+                        let incoming = vec![(block_region, *then_value), (else_region, else_value)];
+                        graph.define(region, i, ty, Definition::Phi{ incoming })
+                    }
+                    (Some(value), _) => *value,
+                    _ => graph.define(region, i, self.input_variables[&var_id], Definition::Input{ var_id })
+                };
+                values.insert(i, value);
+                stack.push(value);
             }
         }
-        constraint
+        (stack, else_clause)
     }
 
     // sets the node id
-    pub fn set_id(&mut self, id:usize) {
+    pub(crate) fn set_id(&mut self, id:NodeId) {
         self.id = id;
     }
 
     // returns the node id
-    pub fn get_id(&self) -> usize {
-        self.id.clone()
+    pub fn get_id(&self) -> NodeId {
+        self.id
     }
 
     // registers an internal variable of any kind
-    pub fn add_internal_variable(&mut self, i:usize, ty:Type) -> usize {
+    pub(crate) fn add_internal_variable(&mut self, i:usize, ty:Type) -> usize {
         self.internal_variables.insert(i, ty);
         i
     }
 
     // registers an input variable of any kind
-    pub fn add_input_variable(&mut self, ty:Type) -> usize {
+    pub(crate) fn add_input_variable(&mut self, ty:Type) -> usize {
         let var_id = self.input_variables.len();
         self.input_variables.insert(var_id, ty);
         var_id
     }
 
-    // registers an output variable of any kind
-    pub fn add_output_variable(&mut self, ty:Type) -> usize {
-        let var_id = self.output_variables.len();
-        self.output_variables.insert(var_id, ty);
+    // registers a function parameter as the next local and as the input variable holding its value
+    pub(crate) fn add_param(&mut self, ty:Type) -> usize {
+        let var_id = self.add_input_variable(ty);
+        let local_index = self.add_local(ty);
+        self.local_input_variables.insert(local_index, var_id);
+        self.params += 1;
         var_id
     }
 
-    // registers a locally scoped constant
-    pub fn add_constant(&mut self, ty:Type) -> usize {
-        let var_id = self.constants.len();
-        self.constants.insert(var_id, ty);
-        var_id
+    // registers the next local in the function's local index space, returning its index
+    pub(crate) fn add_local(&mut self, ty:Type) -> usize {
+        self.locals.push(ty);
+        self.locals.len() - 1
     }
 
-    // registers a simulatable operation
-     pub fn add_operation(&mut self, i:usize, op:AbstractExpression) {
-        self.operations.insert(i, op);
+    // returns the types in the local index space, indexed by local
+    pub fn get_locals(&self) -> Vec<Type> {
+        self.locals.clone()
     }
 
-    // returns the registered simulatable operations
-     pub fn get_operations(&self) -> HashMap<usize, AbstractExpression> {
-        self.operations.clone()
+    // returns how many of the locals are parameters
+    pub fn get_param_count(&self) -> usize {
+        self.params
     }
 
-    // registers an internal data coupling for flow control simulation
-    pub fn add_flow_control_coupling(&mut self, i:usize, var_id:usize, chain:bool) {
-        self.chains.insert(i, chain);
-        self.flow_control_couplings.insert(i, var_id);
+    // returns the input variable holding a local's value on entry to the node, registering one the first
+    // time the local is read; there is none if the local index is out of range
+    pub fn get_local_input_variable(&mut self, local_index:usize) -> Option<usize> {
+        if let Some(var_id) = self.local_input_variables.get(&local_index) {
+            return Some(*var_id);
+        }
+        let ty = *self.locals.get(local_index)?;
+        let var_id = self.add_input_variable(ty);
+        self.local_input_variables.insert(local_index, var_id);
+        Some(var_id)
     }
 
-    // registers a memory input data dependency
-    pub fn add_input_data_coupling(&mut self, memarg:usize, var_id:usize) {
-        self.input_data_couplings.insert(memarg as usize, var_id);
+    // returns the input variables holding the locals' values on entry to the node, by local index
+    pub(crate) fn get_local_input_variables(&self) -> HashMap<usize, usize> {
+        self.local_input_variables.clone()
     }
 
-    // registers a memory output data dependency
-    pub fn add_output_data_coupling(&mut self, memarg:usize, var_id:usize) {
-        self.output_data_couplings.insert(memarg as usize, var_id);
+    // borrows the input variables holding the locals' values on entry to the node, by local index
+    pub fn local_input_variables(&self) -> &HashMap<usize, usize> {
+        &self.local_input_variables
     }
 
-    // registers a global input data dependency
-    pub fn add_global_input_data_coupling(&mut self, memarg:usize, var_id:usize) {
-        self.global_input_data_couplings.insert(memarg as usize, var_id);
+    // returns the output variable holding the value written to a local, registering one the first time the
+    // local is written; there is none if the local index is out of range
+    pub fn get_local_output_variable(&mut self, local_index:usize) -> Option<usize> {
+        if let Some(var_id) = self.local_output_variables.get(&local_index) {
+            return Some(*var_id);
+        }
+        let ty = *self.locals.get(local_index)?;
+        let var_id = self.add_output_variable(ty);
+        self.local_output_variables.insert(local_index, var_id);
+        Some(var_id)
     }
 
-    // registers a global output data dependency
-    pub fn add_global_output_data_coupling(&mut self, memarg:usize, var_id:usize) {
-        self.global_output_data_couplings.insert(memarg as usize, var_id);
+    // returns the locals written within the node, including within its nested blocks
+    pub fn get_written_locals(&self) -> Vec<usize> {
+        self.local_output_variables.keys().cloned().collect()
     }
 
-    // registers a branch at a particular location with target depth
-    pub fn add_branch(&mut self, branch_index:usize, relative_depth:usize) {
-        self.branches.insert(branch_index, relative_depth);
+    // records the locals written by a nested block as written by this node too; what the block wrote to them
+    // isn't known to this node
+    fn add_written_locals(&mut self, block:&Node) {
+        for local_index in block.get_written_locals() {
+            self.get_local_output_variable(local_index);
+            self.local_values.insert(local_index, None);
+        }
     }
 
-    // checks if a branch has been registered at the given index
-    pub fn has_branch(&self, branch_index:usize) -> bool {
-        self.branches.contains_key(&branch_index)
+    // records a write to a local at a location as the value on top of the stack, which is known if it was
+    // pushed by a recorded operation
+    pub(crate) fn set_local_value(&mut self, i:usize, local_index:usize) {
+        let producer = self.stack_producers(i).last().cloned();
+        self.local_values.insert(local_index, producer);
     }
 
-    // registers the location of a block with the given id
-    pub fn add_block(&mut self, start_index:usize, block_index:usize) {
-        self.blocks.insert(start_index, block_index);
+    // returns the location of the operation whose value a local holds after being written within the node,
+    // with none inside if that value isn't known; there is none at all if the node hasn't written the local
+    pub fn get_local_value(&self, local_index:usize) -> Option<Option<usize>> {
+        self.local_values.get(&local_index).cloned()
     }
 
-    // returns the set of registered blocks
-    pub fn get_blocks(&self) -> HashMap<usize, usize> {
-        self.blocks.clone()
+    // checks whether a local still holds the zero it was initialized with; only the declared locals of a
+    // function start out as zero, and only until they are first written
+    pub fn is_zero_initialized(&self, local_index:usize) -> bool {
+        self.function
+            && local_index >= self.params
+            && local_index < self.locals.len()
+            && !self.local_output_variables.contains_key(&local_index)
     }
 
-    // registers the call to other functions found in this node
-    pub fn add_call(&mut self, call_index:usize, function_index:usize) {
-        self.calls.insert(call_index, function_index);
+    // returns an empty node for a block nested in this one, which shares its local index space
+    fn nested_block(&self) -> Node {
+        let mut block = Node::default();
+        block.locals = self.locals.clone();
+        block.params = self.params;
+        block.function_index = self.function_index;
+        block
     }
 
-    // checks if a call has been registered at the given index
-    pub fn has_call(&self, call_index:usize) -> bool {
-        self.calls.contains_key(&call_index)
+    // registers an output variable of any kind
+    pub(crate) fn add_output_variable(&mut self, ty:Type) -> usize {
+        let var_id = self.output_variables.len();
+        self.output_variables.insert(var_id, ty);
+        var_id
     }
 
-    // returns the set of registered calls
-    pub fn get_calls(&self) -> HashMap<usize, usize> {
-        self.calls.clone()
+    // registers the output variable holding the value the block leaves on the stack of its enclosing frame;
+    // blocks of the empty block type leave no value and get none
+    pub(crate) fn add_result_variable(&mut self, ty:Type) -> Option<usize> {
+        if ty == Type::EmptyBlockType {
+            return None;
+        }
+        let var_id = self.add_output_variable(ty);
+        self.result_variable = Some(var_id);
+        Some(var_id)
     }
 
-    // returns the set of registered constants
-    pub fn get_constants(&self) -> HashMap<usize, Type> {
-        self.constants.clone()
+    // returns the output variable holding the block's result, if it has one
+    pub fn get_result_variable(&self) -> Option<usize> {
+        self.result_variable
     }
 
-    // returns the set of registered internal variables
-    pub fn get_internal_variables(&self) -> HashMap<usize, Type> {
-        self.internal_variables.clone()
+    // registers the input variable receiving the result of the nested block at the given location, which
+    // stands for the stack slot the block's value ends up in: the node reads it at the block's location once
+    // the block has run, as the value the block wrote to its result variable. There is none for the empty
+    // block type
+    pub(crate) fn add_result_coupling(&mut self, i:usize, ty:Type) -> Option<usize> {
+        if ty == Type::EmptyBlockType {
+            return None;
+        }
+        let var_id = self.add_input_variable(ty);
+        self.result_couplings.insert(i, var_id);
+        Some(var_id)
     }
 
-    // returns the set of registered input variables
-    pub fn get_input_variables(&self) -> HashMap<usize, Type> {
-        self.input_variables.clone()
+    // returns the nested blocks' locations mapped to the input variables receiving their results
+    pub fn get_result_couplings(&self) -> HashMap<usize, usize> {
+        self.result_couplings.clone()
     }
 
-    // returns the node's least recently registered input variable
-    pub fn get_first_input_variable(&self) -> Type {
-        let mut ty = Type::AnyRef;
-        let index = self.input_variables.keys().min();
+    // registers a locally scoped constant at the location it is instantiated
+    pub(crate) fn add_constant(&mut self, i:usize, ty:Type) -> usize {
+        self.constants.insert(i, ty);
+        i
+    }
 
-        match index {
-            Some(index) => {
-                ty = self.input_variables[index]
-            }
-            _ => {
-                println!("Error: No input variables have been registered.")
-            }
-        }
-        ty
+    // registers a simulatable operation
+     pub fn add_operation(&mut self, i:usize, op:AbstractExpression) {
+        self.operations.insert(i, op);
     }
 
-    // returns the set of registered flow control couplings
-    pub fn get_flow_control_couplings(&self) -> HashMap<usize, usize> {
-        self.flow_control_couplings.clone()
+    // returns the registered simulatable operations, ordered by location
+     pub fn get_operations(&self) -> BTreeMap<usize, AbstractExpression> {
+        self.operations.clone()
     }
 
-    // returns the node's least recently registered flow control coupling
-    pub fn get_first_flow_control_coupling(&self) -> usize {
-        let mut coupling = 0;
-        let index = self.flow_control_couplings.keys().min();
+    // borrows the registered simulatable operations, ordered by location
+    pub fn operations(&self) -> &BTreeMap<usize, AbstractExpression> {
+        &self.operations
+    }
 
-        match index {
-            Some(index) => {
-                coupling = self.flow_control_couplings[index];
-            }
-            _ => {
-                println!("Error: No control flow couplings have been registered.");
-            }
-        }
-        coupling
+    // iterates over the registered simulatable operations in the order they were read
+    pub fn iter_operations(&self) -> btree_map::Iter<'_, usize, AbstractExpression> {
+        self.operations.iter()
     }
 
-    // checks if the variables with the given id is coupled to any global or memory dependency
-    pub fn input_variable_is_param(&self, var_id:usize) -> bool {
-        let mut param = true;
+    // returns the locations of the registered simulatable operations, in order
+    pub fn operation_locations(&self) -> Vec<usize> {
+        self.operations.keys().cloned().collect()
+    }
 
-        for (loc, var) in self.global_input_data_couplings.clone() {
-            if (var == var_id) {
-                param = false
-            }
-        }
-        for (loc, var) in self.input_data_couplings.clone() {
-            if (var == var_id) {
-                param = false
+    // removes an operation whose value has been folded into a later operation
+    fn eliminate_operation(&mut self, i:usize) {
+        self.operations.remove(&i);
+        self.constants.remove(&i);
+        self.eliminated.insert(i);
+    }
+
+    // drops an i32 zero test recorded right before a conditional at the given location, so that the conditional
+    // reads the tested value itself, and returns whether there was one; the conditional then holds when the
+    // value doesn't. Testing an i64 would change the condition's type, so those are left as they are
+    fn fold_zero_test(&mut self, i:usize) -> bool {
+        match i.checked_sub(1).and_then(|j| self.operations.get(&j)) {
+            Some(&AbstractExpression::IsZero { ty: Type::I32 }) => {
+                self.eliminate_operation(i - 1);
+                true
             }
+            _ => false
         }
-        param
     }
 
-    // removes all calls
-    fn remove_calls(&mut self, calls:Vec<usize>) {
-        for index in calls {
-            self.calls.remove(&index);
+    // records the operation producing the condition consumed at the given location, which is the last one
+    // pushing a value on the stack before it, and returns its location; conditions produced before an
+    // unrecorded instruction, a block or a call aren't tracked, but the branch site is, along with the
+    // program point of the instruction consuming the condition
+    pub(crate) fn add_condition(&mut self, i:usize, point:ProgramPoint) -> Option<usize> {
+        self.branch_sites.insert(i, point);
+        let producer = self.stack_producers(i).last().cloned();
+        if let Some(producer) = producer {
+            self.conditions.insert(i, producer);
         }
+        producer
     }
 
-    // registers the location of the node in the source WASM file
-    pub fn set_start(&mut self, start:usize) {
-        self.start = start;
+    // returns the locations of ifs, conditional branches and selects mapped to the locations of the operations
+    // producing their conditions
+    pub fn get_conditions(&self) -> BTreeMap<usize, usize> {
+        self.conditions.clone()
     }
 
-    // registers the end of the node in the source WASM file
-    pub fn set_end(&mut self, end:usize) {
-        self.end = end;
+    // borrows the locations of ifs, conditional branches and selects, including those whose conditions'
+    // producers aren't tracked, mapped to the program points they were read from
+    pub fn branch_sites(&self) -> &BTreeMap<usize, ProgramPoint> {
+        &self.branch_sites
     }
 
-    // returns the location of the node in the source WASM file
-    pub fn get_start(&self) -> usize {
-        self.start
+    // weighs the node's conditions with the counts the profile gives the instructions they were read from,
+    // returning how many were weighed
+    pub(crate) fn weigh_branches(&mut self, profile:&BranchProfile) -> usize {
+        self.branch_counts = self.branch_sites.iter()
+            .filter_map(|(i, point)| profile.get(*point).map(|counts| (*i, counts)))
+            .collect();
+        self.branch_counts.len()
     }
 
-    // returns the end of the node in the source WASM file
-    pub fn get_end(&self) -> usize {
-        self.end
+    // borrows the locations of conditions mapped to how often they went each way in a profiled run
+    pub fn branch_counts(&self) -> &BTreeMap<usize, BranchCounts> {
+        &self.branch_counts
     }
 
-    // sets this node's list of child nodes
-    pub fn set_children(&mut self, children:HashMap<usize, Node>) {
-        self.children = children;
+    // returns the probability of the condition at a location being true in a profiled run, if it ran
+    pub fn get_branch_probability(&self, i:usize) -> Option<f64> {
+        self.branch_counts.get(&i).and_then(BranchCounts::probability)
     }
 
-    // add multiple new children to this node's list of child nodes
-    pub fn add_children(&mut self, children:HashMap<usize, Node>) {
-        self.children.extend(children);
+    // checks that no unrecorded instruction, whose effect on the stack is unknown, was read between
+    // the operations at the given locations
+    pub fn is_contiguous(&self, previous:Option<usize>, i:usize) -> bool {
+        match previous {
+            Some(previous) => (previous + 1..i).all(|j| self.eliminated.contains(&j)),
+            None => false
+        }
     }
 
-    // inserts a child at a given index in this node's list of child nodes
-    pub fn add_child(&mut self, index:usize, child:Node) {
-        self.children.insert(index, child);
-    }
+    // folds integer additions, multiplications and conversions of known constants into a single constant,
+    // propagating the folded values into later operations; returns the number of operations folded away
+    pub(crate) fn fold_constants(&mut self) -> usize {
+        let locations = self.operation_locations();
 
-    // checks if this node's list of children contains a particular node
-    pub fn has_child(&self, key:usize) -> bool {
-        self.children.contains_key(&key)
-    }
+        // the abstract stack holds the location of each value and its value if it is a known constant
+        let mut stack:Vec<(usize, Option<usize>)> = Vec::new();
+        let mut previous:Option<usize> = None;
+        let mut folded = 0;
 
-    // returns a particular node if it is registered a child of this node
-    pub fn get_child(&self, key:usize) -> Option<Node> {
-        if self.children.contains_key(&key) {
-            Some(self.children[&key].clone())
-        } else {
-            None
-        }
-    }
+        for i in locations {
 
-    // clears this node's list of child nodes
-    fn remove_children(&mut self, children:Vec<usize>) {
-        for index in children {
-            self.children.remove(&index);
-        }
-    }
+            // an unrecorded instruction or a nested block leaves the stack in an unknown state
+            if !self.is_contiguous(previous, i) || self.blocks.contains_key(&i) {
+                stack.clear();
+            }
+            previous = Some(i);
 
-    // sets this node's list of hex instructions
-    pub fn set_instrs(&mut self, instrs:Vec<u8>) {
-        self.instrs = instrs;
-    }
+            let (ty, operands) = match self.operations[&i] {
+                AbstractExpression::Num { val } => {
+                    stack.push((i, Some(val)));
+                    continue;
+                }
 
-    // returns this node's list of hex instructions
-    pub fn get_instrs(&mut self) -> Vec<u8> {
-        self.instrs.clone()
+                // a conditional branch consumes its condition
+                AbstractExpression::Spin { id } if id == i && self.branches.contains_key(&i) => {
+                    stack.pop();
+                    continue;
+                }
+                AbstractExpression::Add { ty } => (ty, (stack.pop(), stack.pop())),
+                AbstractExpression::Mul { ty } => (ty, (stack.pop(), stack.pop())),
+                AbstractExpression::DivU { ty } => (ty, (stack.pop(), stack.pop())),
+                AbstractExpression::RemU { ty } => (ty, (stack.pop(), stack.pop())),
+                AbstractExpression::Min { ty } | AbstractExpression::Max { ty } | AbstractExpression::Copysign { ty } => (ty, (stack.pop(), stack.pop())),
+                AbstractExpression::Shift { ty, .. } => (ty, (stack.pop(), stack.pop())),
+
+                // a conversion, shift or count of a known constant is replaced by the constant it results in
+                AbstractExpression::Extend { to, .. } | AbstractExpression::Truncate { to, .. } | AbstractExpression::Reinterpret { to, .. }
+                | AbstractExpression::ShrU { ty: to, .. } | AbstractExpression::MulShr { ty: to, .. }
+                | AbstractExpression::Clz { ty: to } | AbstractExpression::Ctz { ty: to } | AbstractExpression::Popcnt { ty: to }
+                | AbstractExpression::Abs { ty: to } | AbstractExpression::Neg { ty: to } | AbstractExpression::IsZero { ty: to } => {
+                    let to = match self.operations[&i] {
+                        AbstractExpression::IsZero { .. } => Type::I32,
+                        _ => to
+                    };
+                    let converted = match stack.pop() {
+                        Some((j, Some(val))) => self.operations[&i].unary(val as u64).map(|val| (j, val as usize)),
+                        _ => None
+                    };
+                    if let Some((j, val)) = converted {
+                        self.eliminate_operation(j);
+                        self.add_constant(i, to);
+                        self.add_operation(i, AbstractExpression::Num{ val });
+                        folded += 1;
+                    }
+                    stack.push((i, converted.map(|(_, val)| val)));
+                    continue;
+                }
+                _ => {
+                    stack.push((i, None));
+                    continue;
+                }
+            };
+
+            let result = match operands {
+                (Some((j, Some(rhs))), Some((k, Some(lhs)))) => {
+                    let val = match (&self.operations[&i], ty) {
+                        (AbstractExpression::Add { .. }, Type::I32) => (lhs as u32).wrapping_add(rhs as u32) as usize,
+                        (AbstractExpression::Add { .. }, Type::I64) => (lhs as u64).wrapping_add(rhs as u64) as usize,
+                        (AbstractExpression::Mul { .. }, Type::I32) => (lhs as u32).wrapping_mul(rhs as u32) as usize,
+                        (AbstractExpression::Mul { .. }, Type::I64) => (lhs as u64).wrapping_mul(rhs as u64) as usize,
+
+                        // dividing by zero traps, so it is left to do so
+                        (AbstractExpression::DivU { .. }, Type::I32) if rhs as u32 != 0 => (lhs as u32 / rhs as u32) as usize,
+                        (AbstractExpression::DivU { .. }, Type::I64) if rhs != 0 => (lhs as u64 / rhs as u64) as usize,
+                        (AbstractExpression::RemU { .. }, Type::I32) if rhs as u32 != 0 => (lhs as u32 % rhs as u32) as usize,
+                        (AbstractExpression::RemU { .. }, Type::I64) if rhs != 0 => (lhs as u64 % rhs as u64) as usize,
+
+                        // float operations work on the floats' bits, and shifts wrap their amount
+                        (op, _) => match op.binary(lhs as u64, rhs as u64) {
+                            Some(val) => val as usize,
+                            None => {
+                                stack.push((i, None));
+                                continue;
+                            }
+                        }
+                    };
+
+                    // the operands are replaced by a single constant at the location of the operation
+                    self.eliminate_operation(j);
+                    self.eliminate_operation(k);
+                    self.add_constant(i, ty);
+                    self.add_operation(i, AbstractExpression::Num{ val });
+                    folded += 2;
+                    Some(val)
+                }
+                _ => None
+            };
+            stack.push((i, result));
+        }
+        folded
     }
 
-    // clears a segment of this node's list of hex instructions
-    pub fn remove_instrs(&mut self, start:usize, end:usize) {
-        let mut new_instrs:Vec<u8> = Vec::new();
-        let old_instrs = self.get_instrs();
-        let mut i = 0;
-        while i < start {
-            new_instrs.push(old_instrs[i]);
-            i += 1;
-        }
-        i = end;
-        while i < old_instrs.len() {
-            new_instrs.push(old_instrs[i]);
-            i += 1;
+    // rewrites unsigned divisions and remainders by known constants into operations that are cheap to lower:
+    // dividing by a power of two shifts the dividend right and the remainder keeps its low bits, while dividing
+    // by another constant multiplies by a rounded up reciprocal and shifts the product right, if the rounding is
+    // small enough to be exact for every dividend of the type; returns the number of operations rewritten
+    pub(crate) fn reduce_division_strength(&mut self) -> usize {
+        let mut reduced = 0;
+        for i in self.operation_locations() {
+            let (ty, remainder) = match self.operations[&i] {
+                AbstractExpression::DivU { ty } => (ty, false),
+                AbstractExpression::RemU { ty } => (ty, true),
+                _ => continue
+            };
+
+            // the divisor is on top of the stack, and dividing by zero is left to trap
+            let (j, divisor) = match self.stack_producers(i).last().map(|j| (*j, self.operations.get(j))) {
+                Some((j, Some(&AbstractExpression::Num { val }))) if val != 0 => (j, val as u64),
+                _ => continue
+            };
+            let reduction = match (divisor.is_power_of_two(), remainder) {
+                (true, false) => AbstractExpression::ShrU{ ty, bits: divisor.trailing_zeros() as usize },
+                (true, true) => AbstractExpression::Extend{ from: ty, bits: divisor.trailing_zeros() as usize, to: ty, signed: false },
+                (false, false) => match division_magic(divisor, type_width(ty)) {
+                    Some((multiplier, shift)) => AbstractExpression::MulShr{ ty, multiplier, shift },
+                    None => continue
+                },
+                (false, true) => continue
+            };
+            self.eliminate_operation(j);
+            self.add_operation(i, reduction);
+            reduced += 1;
         }
-        self.set_instrs(new_instrs);
+        reduced
     }
-}
 
+    // shares the result of repeated additions and multiplications of the same operands instead of
+    // recomputing them, returning the number of operations whose results are now shared
+    pub(crate) fn eliminate_common_subexpressions(&mut self) -> usize {
+        let locations = self.operation_locations();
+
+        // the abstract stack holds each value's location, a key identifying the expression that
+        // produced it, and the locations of every operation that contributed to it
+        let mut stack:Vec<(usize, String, Vec<usize>)> = Vec::new();
+        let mut expressions:HashMap<String, usize> = HashMap::new();
+        let mut keys:HashMap<usize, String> = HashMap::new();
+        let mut previous:Option<usize> = None;
+        let mut shared = 0;
+
+        for i in locations {
+
+            // values can only be shared while it is known that nothing in between changed them
+            if !self.is_contiguous(previous, i) || self.blocks.contains_key(&i) {
+                stack.clear();
+                expressions.clear();
+            }
+            previous = Some(i);
 
-/// The mapper is responsible for performing the mapping of arbitrary 
-/// input WASM to its parallel and simulatable form
-pub struct Mapper {
-    blocks:HashMap<usize, Node>, // registered code segments originally include ambiguous blocks,
-    nodes:HashMap<usize, Node>, // and eventually only uniquely adressed nodes
-}
+            let key = match self.operations[&i] {
+                AbstractExpression::Spin { id } if id == i && self.branches.contains_key(&i) => {
+                    stack.pop();
+                    continue;
+                }
+                AbstractExpression::Spin { id } => format!("spin {}", id),
+                AbstractExpression::Num { val } => format!("num {}", val),
+                AbstractExpression::Reuse { location } => match keys.get(&location) {
+                    Some(key) => key.clone(),
+                    None => format!("reuse {}", location)
+                },
+                AbstractExpression::Load { id, .. } => {
+                    stack.pop();
+                    format!("load {}", id)
+                }
 
+                // a conversion is keyed by its operand, so that the same conversion of different values isn't shared
+                AbstractExpression::Extend { .. } | AbstractExpression::Truncate { .. } | AbstractExpression::Reinterpret { .. }
+                | AbstractExpression::ShrU { .. } | AbstractExpression::MulShr { .. }
+                | AbstractExpression::Clz { .. } | AbstractExpression::Ctz { .. } | AbstractExpression::Popcnt { .. }
+                | AbstractExpression::Abs { .. } | AbstractExpression::Neg { .. } | AbstractExpression::IsZero { .. } => match stack.pop() {
+                    Some((_, operand, mut contributors)) => {
+                        let key = format!("{:?}({})", self.operations[&i], operand);
+                        contributors.push(i);
+                        keys.insert(i, key.clone());
+                        stack.push((i, key, contributors));
+                        continue;
+                    }
+                    None => format!("unknown {}", i)
+                },
+                AbstractExpression::Add { .. } | AbstractExpression::Mul { .. } | AbstractExpression::DivU { .. } | AbstractExpression::RemU { .. }
+                | AbstractExpression::Min { .. } | AbstractExpression::Max { .. } | AbstractExpression::Copysign { .. }
+                | AbstractExpression::Shift { .. } => {
+                    let (rhs, lhs) = match (stack.pop(), stack.pop()) {
+                        (Some(rhs), Some(lhs)) => (rhs, lhs),
+                        _ => {
+                            stack.clear();
+                            stack.push((i, format!("unknown {}", i), vec![i]));
+                            continue;
+                        }
+                    };
 
-impl Mapper {
-    fn default () -> Mapper {
-        let blocks:HashMap<usize, Node> = HashMap::new();
-        let nodes:HashMap<usize, Node> = HashMap::new();
+                    // additions, multiplications, minimums and maximums are commutative, so their operands' order doesn't matter
+                    let commutative = matches!(self.operations[&i], AbstractExpression::Add { .. } | AbstractExpression::Mul { .. }
+                        | AbstractExpression::Min { .. } | AbstractExpression::Max { .. });
+                    let (first, second) = if !commutative || lhs.1 <= rhs.1 { (&lhs.1, &rhs.1) } else { (&rhs.1, &lhs.1) };
+                    let key = format!("{:?}({}, {})", self.operations[&i], first, second);
 
-        Mapper{
-            blocks: blocks,
-            nodes: nodes,
-        }
-    }
+                    match expressions.get(&key) {
+                        Some(location) => {
 
-    // returns a unique id so that a block can be normalized and introduced uniquely into the list of functions
-    pub fn unique_block_id(&self) -> usize {
-        let nodes = self.get_nodes();
-        let max = nodes.keys().max();
-        let mut true_max = 0;
-        match max {
-            Some(max) => {
-                true_max = *max;
-           }
-           _ => ()
+                            // the operands only fed this operation, so they can be dropped along with it
+                            for j in lhs.2.iter().chain(rhs.2.iter()) {
+                                self.eliminate_operation(*j);
+                            }
+                            self.add_operation(i, AbstractExpression::Reuse{ location: *location });
+                            shared += 1;
+                            keys.insert(i, key.clone());
+                            stack.push((i, key, vec![i]));
+                        }
+                        None => {
+                            expressions.insert(key.clone(), i);
+                            keys.insert(i, key.clone());
+                            let mut contributors = lhs.2.clone();
+                            contributors.extend(rhs.2.iter());
+                            contributors.push(i);
+                            stack.push((i, key, contributors));
+                        }
+                    }
+                    continue;
+                }
+            };
+            keys.insert(i, key.clone());
+            stack.push((i, key, vec![i]));
         }
-        true_max + 1
+        shared
     }
 
-    // registers a block
-    fn add_block(&mut self, block:Node) -> usize {
-        let blocks = self.get_blocks();
-        let index = blocks.keys().max();
-        let mut insert_index = 0;
-        match index {
-            Some(index) => {
-                insert_index = *index + 1;
-           }
-           _ => ()
+    // returns the locations of the node's operations if they are straight-line arithmetic on its input
+    // variables and constants that leaves exactly one value on the stack, which is all a branch of an if
+    // may do to be predicated; nested blocks are left to the caller
+    fn straight_line_operations(&self) -> Option<Vec<usize>> {
+        if !self.branches.is_empty() || !self.calls.is_empty() || !self.memory_accesses.is_empty()
+            || !self.internal_variables.is_empty() || self.output_variables.len() > self.result_variable.iter().count()
+            || !self.input_data_couplings.is_empty() || !self.global_input_data_couplings.is_empty()
+            || self.terminator.is_some() {
+            return None;
         }
-        self.blocks.insert(insert_index, block);
-        insert_index
-    }
+        let locations = self.operation_locations();
 
-    // returns the set of registered nodes
-    fn get_nodes(&self) -> HashMap<usize, Node> {
-        self.nodes.clone()
+        let mut depth = 0;
+        let mut previous = Some(0);
+        for i in locations.iter() {
+            if !self.is_contiguous(previous, *i) {
+                return None;
+            }
+            previous = Some(*i);
+            match self.operations[i] {
+                AbstractExpression::Spin { id } if self.input_variables.contains_key(&id) => depth += 1,
+                AbstractExpression::Reuse { location } if location < *i && self.operations.contains_key(&location) => depth += 1,
+                AbstractExpression::Num { .. } => depth += 1,
+                AbstractExpression::Add { .. } | AbstractExpression::Mul { .. } if depth >= 2 => depth -= 1,
+                _ => return None
+            }
+        }
+        if depth == 1 { Some(locations) } else { None }
     }
 
-    // returns the set of registered nodes
-    fn get_blocks(&self) -> HashMap<usize, Node> {
-        self.blocks.clone()
+    // checks that the straight-line operations at the given locations all compute values of the given type: their
+    // arithmetic is of the type, and the variables and constants they push are too
+    fn operations_of_type(&self, locations:&[usize], ty:Type) -> bool {
+        locations.iter().all(|i| match self.operations[i] {
+            AbstractExpression::Spin { id } => self.input_variables.get(&id) == Some(&ty),
+            AbstractExpression::Num { .. } => self.constants.get(i) == Some(&ty),
+            AbstractExpression::Add { ty: op_ty } | AbstractExpression::Mul { ty: op_ty } => op_ty == ty,
+            _ => true
+        })
     }
 
-    // returns a specific registered block
-    fn get_block(&self, index:usize) -> Node {
-        self.blocks[&index].clone()
-    }
-
-    // removes a registered block
-    fn remove_block(&mut self, index:usize) {
-        self.blocks.remove(&index);
-    }
+    // merges the else clause of an if into the if's branch, turning the diamond into arithmetic that selects
+    // between the branches' values with the condition as then * c + else * (1 - c), computed as
+    // (then - else) * c + else; the branch then runs unconditionally and reads its condition as 0 or 1.
+    // Returns whether the node was predicated, which needs both branches to be straight-line integer arithmetic
+    // of the if's result type
+    pub(crate) fn predicate(&mut self, else_clause:&Node) -> bool {
+
+        // the branch is coupled to the if's condition and the else clause anti-chained to the branch's
+        let (outer_var_id, condition) = match self.flow_control_couplings.iter().next() {
+            Some((outer_var_id, condition)) if self.flow_control_couplings.len() == 1 => (*outer_var_id, *condition),
+            _ => return false
+        };
+        if self.predicated || self.chains.get(&outer_var_id) != Some(&true)
+            || !else_clause.flow_control_couplings.contains_key(&condition)
+            || else_clause.chains.get(&condition) != Some(&false) {
+            return false;
+        }
+        let else_condition = else_clause.flow_control_couplings[&condition];
+
+        // the arithmetic is of the if's result type, which both branches leave; subtracting is multiplying by
+        // the type's all-ones constant, which floats have no equivalent of
+        let result_type = |node:&Node| node.get_result_variable().and_then(|var_id| node.output_variables.get(&var_id).cloned());
+        let ty = match result_type(self) {
+            Some(ty) if result_type(else_clause) == Some(ty) => ty,
+            _ => return false
+        };
+        let minus_one = match ty {
+            Type::I32 => u32::MAX as usize,
+            Type::I64 => u64::MAX as usize,
+            _ => return false
+        };
+
+        // the else clause has to directly follow the branch's own operations, and both compute the result's type
+        let (then_locations, else_locations) = match (self.straight_line_operations(), else_clause.straight_line_operations()) {
+            (Some(then_locations), Some(else_locations)) => (then_locations, else_locations),
+            _ => return false
+        };
+        if !self.operations_of_type(&then_locations, ty) || !else_clause.operations_of_type(&else_locations, ty) {
+            return false;
+        }
+        let mut location = then_locations[then_locations.len() - 1] + 1;
+        if self.blocks.len() != 1 || !self.blocks.contains_key(&location) || !else_clause.blocks.is_empty() {
+            return false;
+        }
 
-    // reads a WASM file
-    pub fn read_wasm(&mut self, file: &str) -> io::Result<Vec<u8>> {
-        let mut data = Vec::new();
-        let mut f = File::open(file)?;
-        f.read_to_end(&mut data)?;
-        Ok(data)
-    }
+        // the else clause's variables are its condition and the locals it reads, which the branch shares
+        let else_locals = else_clause.get_local_input_variables();
+        if else_clause.input_variables.keys().any(|var_id| *var_id != else_condition && !else_locals.values().any(|local_var_id| local_var_id == var_id)) {
+            return false;
+        }
+        let mut variables:HashMap<usize, usize> = HashMap::new();
+        variables.insert(else_condition, condition);
+        for (local_index, var_id) in else_locals {
+            match self.get_local_input_variable(local_index) {
+                Some(local_var_id) => variables.insert(var_id, local_var_id),
+                None => return false
+            };
+        }
 
-    // extracts the node indeces from a flat tree of nodes
-    fn get_indices(&self, tree:HashMap<usize, Node>) -> Vec<usize> {
-        let mut indices:Vec<usize> = Vec::new();
-        for key in tree.keys() {
-            indices.push(*key);
+        // the else clause's operations are moved to where it was called from, and its result is the branch's
+        self.blocks.remove(&location);
+        let mut relocated:HashMap<usize, usize> = HashMap::new();
+        for j in else_locations {
+            let op = match else_clause.operations[&j] {
+                AbstractExpression::Spin { id } => AbstractExpression::Spin{ id: variables[&id] },
+                AbstractExpression::Reuse { location } => AbstractExpression::Reuse{ location: relocated[&location] },
+                ref op => op.clone()
+            };
+            if let Some(ty) = else_clause.constants.get(&j) {
+                self.add_constant(location, *ty);
+            }
+            self.add_operation(location, op);
+            relocated.insert(j, location);
+            location += 1;
+        }
+        let else_value = location - 1;
+
+        // both values are on the stack, the branch's below the else clause's
+        let select = vec![
+            AbstractExpression::Num{ val: minus_one },
+            AbstractExpression::Mul{ ty },
+            AbstractExpression::Add{ ty },
+            AbstractExpression::Spin{ id: condition },
+            AbstractExpression::Mul{ ty },
+            AbstractExpression::Reuse{ location: else_value },
+            AbstractExpression::Add{ ty }
+        ];
+        self.add_constant(location, ty);
+        for op in select {
+            self.add_operation(location, op);
+            location += 1;
         }
-        indices
-    }
 
-    // prints a flat tree of nodes
-    pub fn print_tree(&self, nodes:HashMap<usize, Node>) {
-        let indices = self.get_indices(nodes);
-        print!("{}", fmt(&indices));
+        self.chains.remove(&outer_var_id);
+        self.predicated = true;
+        true
     }
-    
 
-    // Associates a function's type signature with its corresponding node
-    fn attach_signature(&mut self, resources:&WasmModuleResources, mut node:Node, func_count:usize, func_types:Vec<u32>) -> Node {
+    // returns the locations of a function's operations if it can be inlined, which it can if it computes its
+    // single result with straight line arithmetic on its parameters and constants
+    fn inlinable_operations(&self) -> Option<Vec<usize>> {
+        if !self.function || self.output_variables.len() != 1 || !self.blocks.is_empty()
+            || !self.local_output_variables.is_empty() || !self.global_output_data_couplings.is_empty()
+            || !self.output_data_couplings.is_empty() || !self.lane_operations.is_empty() {
+            return None;
+        }
+        let params:HashSet<usize> = self.local_input_variables.iter()
+            .filter(|&(local_index, _)| *local_index < self.params)
+            .map(|(_, var_id)| *var_id)
+            .collect();
+        if self.input_variables.keys().any(|var_id| !params.contains(var_id)) {
+            return None;
+        }
 
-        // the function's type signature can be assigned after the node has been created
-        let func_signature = resources.types()[func_types[func_count - 1] as usize].clone();
-        let params = func_signature.params;
-        let rets = func_signature.returns;
-        let mut param = 0;
-        let mut ret = 0;
+        // the function's result is the only output variable, so the rest of the checks are a block's
+        let mut body = self.clone();
+        body.output_variables.clear();
+        body.straight_line_operations()
+    }
 
-        // the parser's resources object contains info about each function's params
-        while param < params.len() {
-            match params[param] {
-                Type => {
-                    let var_id = node.add_input_variable(params[param]);
+    // returns the locations of the operations that pushed the values on the stack right before a location,
+    // from the bottom up, where a block whose result is coupled to the node pushes it at its own location;
+    // values pushed before an unrecorded instruction, a block without a result or a call are unknown and
+    // aren't returned
+    fn stack_producers(&self, i:usize) -> Vec<usize> {
+        let mut locations:Vec<usize> = self.operations.range(..i).map(|(j, _)| *j).collect();
+        locations.extend(self.result_couplings.keys().filter(|j| **j < i));
+        locations.sort();
+        locations.dedup();
+
+        let mut stack:Vec<usize> = Vec::new();
+        let mut previous = Some(0);
+        for j in locations.into_iter().chain(Some(i)) {
+            let unknown = |k:&usize| (self.blocks.contains_key(k) && !self.result_couplings.contains_key(k)) || self.calls.contains_key(k) || self.indirect_calls.contains_key(k);
+            if !self.is_contiguous(previous, j) || (previous.unwrap_or(0)..j).any(|k| unknown(&k)) {
+                stack.clear();
+            }
+            previous = Some(j);
+            if j == i {
+                break;
+            }
+            match self.operations.get(&j) {
+                None => (),
+                Some(&AbstractExpression::Spin { id }) if id == j && self.internal_variables.contains_key(&j) => {
+                    stack.pop();
                 }
-                _ => {
-                    println!("Encountered unknown function parameter type.");
-                    break;
+                Some(AbstractExpression::Add { .. }) | Some(AbstractExpression::Mul { .. }) | Some(AbstractExpression::DivU { .. }) | Some(AbstractExpression::RemU { .. })
+                | Some(AbstractExpression::Min { .. }) | Some(AbstractExpression::Max { .. }) | Some(AbstractExpression::Copysign { .. })
+                | Some(AbstractExpression::Shift { .. }) => {
+                    stack.pop();
+                    stack.pop();
+                    stack.push(j);
                 }
+                Some(AbstractExpression::Load { .. }) | Some(AbstractExpression::Extend { .. }) | Some(AbstractExpression::Truncate { .. }) | Some(AbstractExpression::Reinterpret { .. })
+                | Some(AbstractExpression::ShrU { .. }) | Some(AbstractExpression::MulShr { .. })
+                | Some(AbstractExpression::Clz { .. }) | Some(AbstractExpression::Ctz { .. }) | Some(AbstractExpression::Popcnt { .. })
+                | Some(AbstractExpression::Abs { .. }) | Some(AbstractExpression::Neg { .. }) | Some(AbstractExpression::IsZero { .. }) => {
+                    stack.pop();
+                    stack.push(j);
+                }
+                Some(_) => stack.push(j)
             }
-            param += 1;
-        }
 
-        // the parser's resources object contains info about each function's outputs
-        while ret < rets.len() {
-            match rets[ret] {
-                Type => {
-                    let var_id = node.add_output_variable(rets[ret]);
-                }
-                _ => {
-                    println!("Encountered unknown function ret type.");
-                    break;
-                }
+            // a block's result is pushed once it has run, after an if has consumed its condition
+            if self.result_couplings.contains_key(&j) {
+                stack.push(j);
             }
-            ret += 1;
         }
-        node.clone()
+        stack
     }
 
+    // moves everything recorded at or after the given location the given number of locations further,
+    // making room for new operations
+    fn shift_locations(&mut self, from:usize, by:usize) {
+        self.relocate(&|location| Some(if location >= from { location + by } else { location }));
+    }
 
-    // entry point to the mapping functionality of the mapper
-    pub fn map(&mut self, buf:Vec<u8>) -> HashMap<usize, Node> {
-
-        // creates a new parser and colorful output stream
-        let mut parser = ValidatingParser::new(&buf, None);
-        let mut stdout = StandardStream::stdout(ColorChoice::Always);
-        let mut parser_input = None;
-        
-        // one top-level node at a time is processed recursively 
-        let mut nodes:HashMap<usize, Node> = HashMap::new();
-        let mut node:Node = Node::default();
-
-        // function parameters that can be determined before entering the function bodies themselves
-        let mut func_start = 0;
-        let mut func_end = 0;
-        let mut func_index = 0;
-        let mut func_types = Vec::new();
-
-        // number of encountered functions
-        let mut func_count = 0;
-
-        // loop until we reach the end of the input WASM code
-        loop {
-
-            node = Node::default();
-            node.set_id(func_index as usize);
-
-            // white is for non-significant printout that does not represent a simulatable 
-            // operation or control flow instruction
-            stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)));
-
-            // prepare the parser input
-            let next_input = parser_input.take().unwrap_or(ParserInput::Default);
-            parser_input = None;
-
-            // parse the input
-            match *parser.read_with_input(next_input) {
-                // print encountered errors
-                ParserState::Error(err) => println!("Error: {:?}", err),
-                // break out of the loop when the file has been processed
-                ParserState::EndWasm => break,
-                // extract the function section entry's reference to the function's type signature
-                ParserState::FunctionSectionEntry { 0: value } => { 
-                    func_types.push(value);
-                    continue;
-                },
-                // when we encounter the start of a function body extract what info we can and have the 
-                // parser skip the body itself
-                ParserState::BeginFunctionBody { range } => {
-                    parser_input = Some(ParserInput::SkipFunctionBody);
-                    func_start = range.start;
-                    func_end = range.end;
-                    node.set_end(func_end);
-                },
-                // print the parser's interpretation of everything else that is encountered
-                _ => {
-                    println!("{:?}", *parser.last_state());
-                    continue;
-                }
+    // moves everything recorded at a location to where the relocation takes it, and drops what it takes
+    // nowhere; this is how passes that split or remove recorded code keep every map keyed by location in step.
+    // Internal variables are ids the nested nodes are coupled to, so they stay as they are
+    pub(crate) fn relocate(&mut self, relocation:&dyn Fn(usize) -> Option<usize>) {
+        relocate_keys(&mut self.branches, relocation);
+        relocate_keys(&mut self.calls, relocation);
+        relocate_keys(&mut self.indirect_calls, relocation);
+        relocate_keys(&mut self.constants, relocation);
+        relocate_keys(&mut self.blocks, relocation);
+        relocate_keys(&mut self.truncations, relocation);
+        relocate_keys(&mut self.result_couplings, relocation);
+        relocate_keys(&mut self.lane_operations, relocation);
+        relocate_keys(&mut self.memory_size_reads, relocation);
+        relocate_keys(&mut self.memory_size_variables, relocation);
+        relocate_keys(&mut self.operations, relocation);
+        relocate_keys(&mut self.branch_sites, relocation);
+        relocate_keys(&mut self.branch_counts, relocation);
+        for op in self.operations.values_mut() {
+            if let AbstractExpression::Reuse { ref mut location } = *op {
+                *location = relocation(*location).unwrap_or(*location);
             }
+        }
+        self.eliminated = self.eliminated.iter().filter_map(|location| relocation(*location)).collect();
+        self.terminator = self.terminator.and_then(relocation);
+        for couplings in self.sync_couplings.values_mut() {
+            couplings.retain(|coupling| relocation(coupling.location).is_some());
+            for coupling in couplings.iter_mut() {
+                coupling.location = relocation(coupling.location).unwrap();
+            }
+        }
+        self.sync_couplings.retain(|_, couplings| !couplings.is_empty());
+        self.range_couplings.retain(|coupling| relocation(coupling.location).is_some());
+        for coupling in self.range_couplings.iter_mut() {
+            coupling.location = relocation(coupling.location).unwrap();
+        }
+        self.conditions = self.conditions.iter()
+            .filter_map(|(&i, &producer)| relocation(i).and_then(|i| relocation(producer).map(|producer| (i, producer))))
+            .collect();
+    }
 
-            stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)));
-            println!("{:?}", *parser.last_state());
+    // renumbers the functions the node calls and the blocks it contains, as when its module is linked into
+    // another; callees the renumbering doesn't name keep their ids
+    pub(crate) fn renumber(&mut self, callees:&HashMap<usize, usize>, block_offset:usize) {
+        for callee in self.calls.values_mut() {
+            *callee = callees.get(&callee.0).cloned().map(NodeId).unwrap_or(*callee);
+        }
+        for index in self.blocks.values_mut() {
+            index.0 += block_offset;
+        }
+    }
 
-            // the parser will have a reference to the most recent function its encountered
-            func_index = parser.current_func_index;
-            func_count += 1;
+    // returns the index of the function the node's code was read from
+    pub fn get_function_index(&self) -> FuncId {
+        self.function_index
+    }
 
-            // a new parser will handle the block
-            let mut reader = parser.create_validating_operator_parser();
+    // sets the index of the function the node's code was read from
+    pub(crate) fn set_function_index(&mut self, function_index:FuncId) {
+        self.function_index = function_index;
+    }
 
-            // the parser has information about globals and keeps track of each function's type signature
-            let resources = parser.get_resources();
+    // replaces the call at the given location with a copy of the callee's operations, reading the arguments
+    // where the callee read its parameters; only calls whose arguments are each pushed by a single operation
+    // are inlined, since those operations can be moved to where the parameters are read
+    pub(crate) fn inline_call(&mut self, location:usize, callee:&Node) -> bool {
+        let callee_locations = match (self.calls.get(&location), callee.inlinable_operations()) {
+            (Some(index), Some(callee_locations)) if *index == callee.id => callee_locations,
+            _ => return false
+        };
+
+        // the internal variables of ifs are shared with their branches by location, so they can't be moved
+        if self.internal_variables.keys().any(|i| *i > location) {
+            return false;
+        }
 
-            // find and attach the function signature before processing the body so we can access its parameter info
-            node = self.attach_signature(resources, node.clone(), func_count, func_types.clone());
+        // the arguments are the values on top of the stack, the first one lowest
+        let producers = self.stack_producers(location);
+        if producers.len() < callee.params {
+            return false;
+        }
+        let arguments = producers[producers.len() - callee.params..].to_vec();
+        if arguments.iter().any(|i| self.blocks.contains_key(i) || matches!(self.operations[i], AbstractExpression::Add { .. } | AbstractExpression::Mul { .. })) {
+            return false;
+        }
+        let mut parameters:HashMap<usize, usize> = HashMap::new();
+        for (local_index, var_id) in callee.local_input_variables.iter() {
+            if *local_index < callee.params {
+                parameters.insert(*var_id, arguments[*local_index]);
+            }
+        }
 
-            // the map helper will use the validating operator parser to recursively process the function
-            // body and create a corresponding node
-            node = self.map_helper(&mut reader, &buf, resources, func_start, func_index as usize, node.clone());
+        // the arguments are moved to where they are first read, and reused after that
+        let moved:Vec<(usize, AbstractExpression, Option<Type>)> = arguments.iter()
+            .map(|i| (*i, self.operations[i].clone(), self.constants.get(i).cloned()))
+            .collect();
+        for i in arguments.iter() {
+            self.eliminate_operation(*i);
+        }
+        self.calls.remove(&location);
+        self.shift_locations(location + 1, callee_locations.len() - 1);
+
+        let mut relocated:HashMap<usize, usize> = HashMap::new();
+        let mut read:HashMap<usize, usize> = HashMap::new();
+        for (k, j) in callee_locations.iter().enumerate() {
+            let i = location + k;
+            let op = match callee.operations[j] {
+                AbstractExpression::Spin { id } => {
+                    let argument = parameters[&id];
+                    match read.get(&argument) {
+                        Some(first) => AbstractExpression::Reuse{ location: *first },
+                        None => {
+                            read.insert(argument, i);
+                            let &(_, ref op, ty) = moved.iter().find(|&&(moved_location, _, _)| moved_location == argument).unwrap();
+                            if let Some(ty) = ty {
+                                self.add_constant(i, ty);
+                            }
+                            op.clone()
+                        }
+                    }
+                }
+                AbstractExpression::Reuse { location } => AbstractExpression::Reuse{ location: relocated[&location] },
+                ref op => {
+                    if let Some(ty) = callee.constants.get(j) {
+                        self.add_constant(i, *ty);
+                    }
+                    op.clone()
+                }
+            };
 
-            // register the encountered function and corresponding processed node
-            self.nodes.insert(func_index as usize, node.clone());
-            nodes.insert(func_index as usize, node.clone());
+            self.add_operation(i, op);
+            relocated.insert(*j, i);
         }
 
-        // print out some basic metrics
-        let indices = self.get_indices(nodes.clone());
-        println!("First pass found {} functions:", indices.len());
-        println!("{:?}", indices);
+        // the callee's copy is no longer needed unless it is still called elsewhere
+        if !self.calls.values().any(|index| *index == callee.id) {
+            self.children.remove(&callee.id);
+        }
+        true
+    }
 
-        // call the parallelizing function
-        nodes = self.expand_tree(nodes);
-        nodes.clone()
+    // counts the node's operators by category, including those of its nested blocks
+    pub fn operator_histogram(&self) -> OperatorHistogram {
+        self.operator_histogram_with(&SupportMatrix::new())
     }
 
-    // provides optional parallelization of each processed node in the provided node tree
-    fn expand_tree(&mut self, nodes:HashMap<usize, Node>) -> HashMap<usize, Node> {
-        let mut tree = nodes.clone();
-        
-        for (index, mut func) in nodes {
-
-            // ask the user if they would like to parallelize each top-level node
-            let mut stdin = io::stdin();
-            let mut input = String::new();
-            println!("Parallelize function {} (yes/no)?", index);
-            stdin.read_line(&mut input);
-            if input == "no\n" || input == "n\n" {
-                continue;
+    // counts the node's operators by category, with unsupported operators determined by the given matrix;
+    // counting stops at the first operator that can't be read
+    fn operator_histogram_with(&self, support:&SupportMatrix) -> OperatorHistogram {
+        let mut histogram = OperatorHistogram::default();
+        let mut reader = BinaryReader::new(&self.instrs);
+
+        // a function's instructions start with its locals declarations
+        if self.function {
+            let skipped = reader.read_var_u32().and_then(|count| {
+                for _ in 0..count {
+                    reader.read_var_u32()?;
+                    reader.read_type()?;
+                }
+                Ok(())
+            });
+            if skipped.is_err() {
+                return histogram;
             }
-            
-            println!("Analyzing function {}...", index);
-            
-            // this node will be replaced with an expanded version
-            tree.remove(&index);
+        }
+        while !reader.eof() {
+            match reader.read_operator() {
+                Ok(op) => histogram.add(support.categorize(&op)),
+                Err(_) => break
+            }
+        }
+        histogram
+    }
 
-            // this node will represent a possible execution path through the code
-            let mut path_nodes = HashMap::new();
+    // returns the estimated auxiliary qubits needed to lower the node's operations
+    pub fn auxiliary_qubits(&self) -> usize {
+        self.operations.values().map(|op| op.auxiliary_qubits_within(self.bit_width)).sum()
+    }
 
-            // a helper function recursively expands the node
-            let node = self.expand_func_tree_helper(func, index, tree.clone(), path_nodes);
-            tree.insert(index, node);
-        }
-        tree
+    // returns the number of operands and the width of each carry-save adder tree lowering would group the node's
+    // additions into, in order of the additions at their roots
+    pub fn adder_trees(&self) -> Vec<(usize, usize)> {
+        let graph = self.to_ssa();
+        let (trees, _) = addition_trees(&graph);
+        let mut roots:Vec<&ValueId> = trees.keys().collect();
+        roots.sort();
+        roots.into_iter().map(|root| {
+            let ty = graph.instruction(*root).ty;
+            (trees[root].len(), self.value_width(ty))
+        }).collect()
     }
 
-    // recursively discovers and normalizes structure in the given block
-    fn expand_block_tree_helper(&mut self, mut block:Node, node_id:usize, nodes:HashMap<usize, Node>, mut path_nodes:HashMap<usize, Node>) -> Node {
-        let mut tree = nodes;
+    // returns what the node's integer multiplications cost to encode with each of the given strategies
+    pub fn multiplier_costs(&self, strategies:&[MultiplierSynthesis]) -> Vec<MultiplierCost> {
+        let graph = self.to_ssa();
+        let widths:Vec<usize> = graph.instructions().iter()
+            .filter(|instruction| matches!(instruction.definition, Definition::Mul { .. }) && (instruction.ty == Type::I32 || instruction.ty == Type::I64))
+            .map(|instruction| self.value_width(instruction.ty))
+            .collect();
+        strategies.iter().map(|strategy| {
+            let mut costs:HashMap<usize, (usize, usize)> = HashMap::new();
+            let mut cost = MultiplierCost { strategy: *strategy, multiplications: widths.len(), qubits: 0, couplers: 0 };
+            for width in widths.iter() {
+                let (qubits, couplers) = *costs.entry(*width).or_insert_with(|| multiplier_cost(*strategy, *width));
+                cost.qubits += qubits;
+                cost.couplers += couplers;
+            }
+            cost
+        }).collect()
+    }
 
-        // normalizes block references to the node format for simplicity
-        let inner_blocks = block.get_blocks();
-        println!("Found {} blocks in block {}", inner_blocks.keys().len(), node_id);
-        for (start, index) in inner_blocks {
+    // selects how the node's integer multiplications are encoded, overriding the lowering config unless unset
+    pub fn set_multiplier_synthesis(&mut self, multiplier:Option<MultiplierSynthesis>) {
+        self.multiplier = multiplier;
+    }
 
-            // get the inner block by index
-            let mut inner_block = self.get_block(index);
-            println!("Breaking block {} out from block {}", index, node_id);
+    // returns how the node's integer multiplications are encoded, if the node selects it
+    pub fn get_multiplier_synthesis(&self) -> Option<MultiplierSynthesis> {
+        self.multiplier
+    }
 
-            // generate an id that won't collide with any other block or function's id
-            let block_id = self.unique_block_id();
+    // returns how many bits the node's values of the given type are encoded with, short of inferring their ranges
+    fn value_width(&self, ty:Type) -> usize {
+        self.bit_width.map_or(type_width(ty), |bits| bits.min(type_width(ty)))
+    }
 
-            // split the inner block's code out from the outer node's
-            let inner_block_end = inner_block.get_end();
-            block.remove_instrs(start, inner_block_end);
+    // infers the range of every value of the node's SSA form, indexed by value; fixed inputs only take their values
+    fn infer_ranges(&self, graph:&ssa::Graph) -> Vec<ValueRange> {
+        let mut inputs = self.input_ranges.clone();
+        inputs.extend(self.input_markings.keys().filter_map(|var_id| self.fixed_input(*var_id).map(|value| (*var_id, ValueRange::constant(value)))));
+        ranges::infer(graph, &inputs, &|ty| self.value_width(ty))
+    }
 
-            // register a call to the separated block
-            block.add_call(start, block_id);
-            
-            // recursively process the separated block 
-            block.add_child(block_id, self.expand_block_tree_helper(inner_block.clone(), index, tree.clone(), path_nodes.clone()));
+    // returns the range of every value of the node's SSA form, indexed by value, as lowering infers them
+    pub fn value_ranges(&self) -> Vec<ValueRange> {
+        self.infer_ranges(&self.to_ssa())
+    }
 
-            // register the separated block as a node
-            self.nodes.insert(block_id, inner_block.clone());
-        }
+    // sets the range an input variable's values are known to fall in, or forgets it
+    pub fn set_input_range(&mut self, var_id:usize, range:Option<ValueRange>) {
+        match range {
+            Some(range) => self.input_ranges.insert(var_id, range),
+            None => self.input_ranges.remove(&var_id)
+        };
+    }
 
-        // updates the node in the node tree with any transformations made so far
-        tree.remove(&node_id);
-        tree.insert(node_id, block.clone());
+    // returns the range an input variable's values are known to fall in, if any
+    pub fn get_input_range(&self, var_id:usize) -> Option<ValueRange> {
+        self.input_ranges.get(&var_id).cloned()
+    }
 
-        // traverses calls searching for feed-forward execution paths
-        let calls = block.get_calls();
-        println!("Found {} calls to other functions from block {}", calls.keys().len(), node_id);
-        for (call, index) in calls {
+    // pins an output of the node to a value when it is lowered, so the ground states of its constraint are the
+    // assignments of its inputs producing that value; values are taken modulo the output's width
+    pub fn constrain_output(&mut self, output:NodeOutput, value:u64) {
+        self.output_constraints.retain(|&(pinned, _)| pinned != output);
+        self.output_constraints.push((output, value));
+    }
 
-            // reference loops will expand infinitely and can't be unrolled at compile time,
-            // so these loops are not generally simulatable
-            if path_nodes.contains_key(&index) {
-                println!("Skipping reference loop in block {}", node_id);
-                continue;
-            }
+    // returns the outputs pinned to values, in the order they were pinned
+    pub fn get_output_constraints(&self) -> Vec<(NodeOutput, u64)> {
+        self.output_constraints.clone()
+    }
 
-            // skips functions already encountered; they don't need to be expanded again, just referenced again by location
-            if block.has_child(index){
-                println!("Skipping already registered call to function {} from block {}", index, node_id);
-                continue;
-            }
+    // forgets every value the node's outputs are pinned to
+    pub fn clear_output_constraints(&mut self) {
+        self.output_constraints.clear();
+    }
 
-            // updates the node in the execution path with any transformations made in this frame
-            path_nodes.insert(node_id, block.clone());
+    // marks what lowering does with an input variable, which is solved for unless marked otherwise
+    pub fn mark_input(&mut self, var_id:usize, marking:InputMarking) {
+        match marking {
+            InputMarking::Free => self.input_markings.remove(&var_id),
+            marking => self.input_markings.insert(var_id, marking)
+        };
+    }
 
-            println!("Registering call to function {} from block {}", index, node_id);
+    // returns what lowering does with an input variable
+    pub fn get_input_marking(&self, var_id:usize) -> InputMarking {
+        self.input_markings.get(&var_id).cloned().unwrap_or(InputMarking::Free)
+    }
 
-            // Any call that was not skipped is recursively analyzed
-            block.add_child(index, self.expand_func_tree_helper(tree[&index].clone(), index, tree.clone(), path_nodes.clone()));
+    // returns the value a fixed input variable is set to, taken modulo the width of its values
+    fn fixed_input(&self, var_id:usize) -> Option<u64> {
+        match self.input_markings.get(&var_id) {
+            Some(&InputMarking::Fixed(value)) => Some(match self.input_variables.get(&var_id) {
+                Some(ty) => value & ValueRange::full(self.value_width(*ty)).max,
+                None => value
+            }),
+            _ => None
         }
+    }
 
-        // updates the node in the node tree
-        tree.remove(&node_id);
-        tree.insert(node_id, block.clone());
-        block
+    // sets how many bits the node's values are known to fit in
+    pub fn set_bit_width(&mut self, bit_width:Option<usize>) {
+        self.bit_width = bit_width;
+    }
+
+    // returns how many bits the node's values are known to fit in, if fewer than their types have
+    pub fn get_bit_width(&self) -> Option<usize> {
+        self.bit_width
     }
 
-    // recursively discovers and normalizes structure in the given function
-    fn expand_func_tree_helper(&mut self, mut func:Node, node_id:usize, nodes:HashMap<usize, Node>, mut path_nodes:HashMap<usize, Node>) -> Node {
-        let mut tree = nodes;
+    // registers an internal data coupling for flow control simulation
+    pub(crate) fn add_flow_control_coupling(&mut self, i:usize, var_id:usize, chain:bool) {
+        self.chains.insert(i, chain);
+        self.flow_control_couplings.insert(i, var_id);
+    }
 
-        // normalizes block references to the node format for simplicity
-        let blocks = func.get_blocks();
-        println!("Found {} blocks in function {}", blocks.keys().len(), node_id);
-        for (start, index) in blocks {
+    // registers a memory input data dependency, coupling a load to an input variable after the node's earlier loads
+    pub(crate) fn add_input_data_coupling(&mut self, access:MemAccess, var_id:usize) {
+        self.input_data_couplings.push((access, var_id));
+    }
 
-            // get the block by index
-            let mut block = self.get_block(index);
-            println!("Breaking block {} out from function {}", index, node_id);
+    // returns the loads coupled to input variables, in order, as the accesses they make and the variables' ids
+    pub fn memory_reads(&self) -> &[(MemAccess, usize)] {
+        &self.input_data_couplings
+    }
 
-            // generate an id that won't collide with any other block or function's id
-            let block_id = self.unique_block_id();
+    // records how much of memory the load coupled to an input variable reads
+    pub(crate) fn set_input_width(&mut self, var_id:usize, width:LoadWidth) {
+        self.input_widths.insert(var_id, width);
+    }
 
-            // register a call to the block
-            func.add_call(start, block_id);
+    // returns how much of memory the load coupled to an input variable reads, if it is coupled to one
+    pub fn get_input_width(&self, var_id:usize) -> Option<LoadWidth> {
+        self.input_widths.get(&var_id).cloned()
+    }
 
-            // updates the node in the execution path with any transformations made so far
-            path_nodes.insert(node_id, func.clone());
+    // registers a memory output data dependency, coupling a store to an output variable after the node's earlier stores
+    pub(crate) fn add_output_data_coupling(&mut self, access:MemAccess, var_id:usize) {
+        self.output_data_couplings.push((access, var_id));
+    }
 
-            // recursively process the block 
-            func.add_child(block_id, self.expand_block_tree_helper(block.clone(), block_id, tree.clone(), path_nodes.clone()));
+    // returns the stores coupled to output variables, in order, as the accesses they make and the variables' ids
+    pub fn memory_writes(&self) -> &[(MemAccess, usize)] {
+        &self.output_data_couplings
+    }
 
-            // register the block as a node
-            self.nodes.insert(block_id, block.clone());
+    // returns the variable holding the dynamic address of the memory access at a location, which takes the given
+    // number of operands with the address first, if the operation pushing it reads a variable
+    pub(crate) fn address_base(&self, i:usize, operands:usize) -> Option<usize> {
+        let producers = self.stack_producers(i);
+        let address = producers.len().checked_sub(operands).map(|position| producers[position])?;
+        match *self.operations.get(&address)? {
+            AbstractExpression::Spin { id } if !(id == address && self.internal_variables.contains_key(&address)) => Some(id),
+            AbstractExpression::Load { id, .. } => Some(id),
+            _ => None
         }
+    }
 
-        // updates the node in the node tree with any transformations made so far
-        tree.remove(&node_id);
-        tree.insert(node_id, func.clone());
-
-        // traverses calls searching for feed-forward execution paths
-        let calls = func.get_calls();
-        println!("Found {} calls to other functions from function {}", calls.keys().len(), node_id);
-        for (call, index) in calls {
+    // returns the constant the dynamic address of the memory access at a location is, which takes the given number
+    // of operands with the address first, if a constant pushes it
+    pub(crate) fn address_constant(&self, i:usize, operands:usize) -> Option<usize> {
+        let producers = self.stack_producers(i);
+        let address = producers.len().checked_sub(operands).map(|position| producers[position])?;
+        match *self.operations.get(&address)? {
+            AbstractExpression::Num { val } => Some(val),
+            _ => None
+        }
+    }
 
-            // skips self references since these can't be unrolled at compile time,
-            // and aren't generally simulatable
-            if index == node_id {
-                println!("Skipping self referencing call in function {}", node_id);
-                continue;
+    // returns the values of the given number of operands on the stack right before a location, in the order they
+    // were pushed, with none for those that aren't pushed by constants or whose producers aren't known
+    pub fn constant_operands(&self, i:usize, count:usize) -> Vec<Option<usize>> {
+        let producers = self.stack_producers(i);
+        (0..count).map(|k| {
+            let producer = producers.len().checked_sub(count - k).map(|position| producers[position])?;
+            match *self.operations.get(&producer)? {
+                AbstractExpression::Num { val } if !self.eliminated.contains(&producer) => Some(val),
+                _ => None
             }
+        }).collect()
+    }
 
-            // reference loops will expand infinitely and can't be unrolled at compile time,
-            // so these loops are not generally simulatable
-            if path_nodes.contains_key(&index) {
-                println!("Skipping reference loop in function {}", node_id);
-                continue;
+    // registers a bulk memory operation; a static one moving at most the given number of bytes is unrolled
+    // into a coupling per byte instead, while a longer one is recorded as a range coupling along with the
+    // accesses to its ranges, and one whose ranges aren't known along with accesses that may touch any byte
+    pub(crate) fn add_range_coupling(&mut self, coupling:RangeCoupling, unroll_limit:usize) {
+        if !coupling.is_static() {
+            let anywhere = |kind:AccessKind| MemAccess { static_offset: 0, align: 1, width: usize::MAX, base_expr: None, address: None, kind };
+            if coupling.reads_memory() {
+                self.add_memory_access(coupling.offset, anywhere(AccessKind::Read));
             }
-
-            // skips functions already encountered; they don't need to be expanded again, just referenced again by location
-            if func.has_child(index) {
-                println!("Skipping already registered call to function {} from function {}", index, node_id);
-                continue;
+            self.add_memory_access(coupling.offset, anywhere(AccessKind::Write));
+            self.range_couplings.push(coupling);
+            return;
+        }
+        let destination = coupling.destination.unwrap();
+        let length = coupling.length.unwrap();
+        let range = |address:usize, width:usize, kind:AccessKind| MemAccess { static_offset: address, align: 1, width, base_expr: None, address: Some(address), kind };
+        if length > unroll_limit {
+            if coupling.reads_memory() {
+                self.add_memory_access(coupling.offset, range(coupling.source.unwrap(), length, AccessKind::Read));
             }
+            self.add_memory_access(coupling.offset, range(destination, length, AccessKind::Write));
+            self.range_couplings.push(coupling);
+            return;
+        }
+        for byte in 0..length {
+            if coupling.reads_memory() {
+                let source = range(coupling.source.unwrap() + byte, 1, AccessKind::Read);
+                let var_id = self.add_input_variable(Type::I32);
+                self.add_input_data_coupling(source, var_id);
+                self.set_input_width(var_id, LoadWidth { bits: 8, signed: false });
+                self.add_memory_access(coupling.offset, source);
+            }
+            let destination = range(destination + byte, 1, AccessKind::Write);
+            let var_id = self.add_output_variable(Type::I32);
+            self.add_output_data_coupling(destination, var_id);
+            self.add_memory_access(coupling.offset, destination);
+        }
+    }
 
-            // updates the node in the execution path with any transformations made in this frame
-            path_nodes.insert(node_id, func.clone());
+    // returns the bulk memory operations that weren't unrolled, in order
+    pub fn get_range_couplings(&self) -> Vec<RangeCoupling> {
+        self.range_couplings.clone()
+    }
 
-            println!("Registering call to function {} from function {}", index, node_id);
+    // records a load or store made by the instruction at the given offset in the WASM file
+    pub(crate) fn add_memory_access(&mut self, offset:usize, access:MemAccess) {
+        self.memory_accesses.push(MemoryAccess { offset, access, atomic: false });
+    }
 
-            // Any call that was not skipped is recursively analyzed
-            func.add_child(index, self.expand_func_tree_helper(tree[&index].clone(), index, tree.clone(), path_nodes.clone()));
+    // records an atomic operation making the given access, along with the memory accesses it makes to the
+    // accessed bytes
+    pub(crate) fn add_atomic_access(&mut self, i:usize, offset:usize, access:MemAccess, kind:SyncKind) {
+        for access_kind in kind.access_kinds() {
+            self.memory_accesses.push(MemoryAccess { offset, access: MemAccess { kind: access_kind, ..access }, atomic: true });
         }
+        self.sync_couplings.entry(access).or_default().push(SyncCoupling { location: i, offset, kind });
+    }
 
-        // updates the node in the node tree
-        tree.remove(&node_id);
-        tree.insert(node_id, func.clone());
-        func
+    // returns the accesses of atomic operations mapped to the operations synchronizing through them
+    pub fn get_sync_couplings(&self) -> HashMap<MemAccess, Vec<SyncCoupling>> {
+        self.sync_couplings.clone()
     }
 
-    // processes a function body using a validating operator parser
-    fn map_helper(&mut self, reader:&mut ValidatingOperatorParser, buf:&Vec<u8>, resources:&WasmModuleResources, start:usize, index:usize, mut node:Node) -> Node {
+    // checks that none of the node's atomic operations wait or wake, which would make its progress depend
+    // on other threads
+    pub fn is_combinational(&self) -> bool {
+        !self.sync_couplings.values().flatten().any(|coupling| coupling.kind.is_blocking())
+    }
 
-        // the number of reads made by the operator parser
-        let mut i = 0;
+    // registers an input variable holding a vector with the given lanes
+    pub(crate) fn add_vector_input_variable(&mut self, shape:LaneShape) -> usize {
+        let var_id = self.add_input_variable(Type::V128);
+        self.input_lanes.insert(var_id, shape);
+        var_id
+    }
 
-        // initiates a colorful output stream
-        let mut stdout = StandardStream::stdout(ColorChoice::Always);
+    // registers an output variable holding a vector with the given lanes
+    pub(crate) fn add_vector_output_variable(&mut self, shape:LaneShape) -> usize {
+        let var_id = self.add_output_variable(Type::V128);
+        self.output_lanes.insert(var_id, shape);
+        var_id
+    }
 
-        // sets initial pre-determined node properties
-        node.set_start(start);
-        node.set_id(index);
+    // returns the types of a vector input variable's lanes, or none if the variable isn't a vector
+    pub fn get_input_lanes(&self, var_id:usize) -> Option<Vec<Type>> {
+        self.input_lanes.get(&var_id).map(|shape| shape.decompose())
+    }
 
-        loop {
+    // returns the types of a vector output variable's lanes, or none if the variable isn't a vector
+    pub fn get_output_lanes(&self, var_id:usize) -> Option<Vec<Type>> {
+        self.output_lanes.get(&var_id).map(|shape| shape.decompose())
+    }
 
-            // green is for simulatable instructions
-            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)));
+    // registers a SIMD operation broken down lane by lane
+    pub(crate) fn add_lane_operation(&mut self, i:usize, op:LaneOperation) {
+        self.lane_operations.insert(i, op);
+    }
 
-            // read the next operator
-            let read = reader.next(resources);
+    // returns the registered SIMD operations broken down lane by lane
+    pub fn get_lane_operations(&self) -> HashMap<usize, LaneOperation> {
+        self.lane_operations.clone()
+    }
 
-            // update the cursor position
-            let position = reader.current_position();
+    // registers a read of the memory's size in pages, by memory.size or as the previous size returned by
+    // memory.grow
+    pub(crate) fn add_memory_size_read(&mut self, i:usize, offset:usize) {
+        self.memory_size_reads.insert(i, offset);
+    }
 
-            // update the read counter
-            i += 1;
+    // registers that the node grows the memory
+    pub(crate) fn set_grows_memory(&mut self) {
+        self.grows_memory = true;
+    }
 
-            if let Ok(ref op) = read {
+    // checks whether the node grows the memory
+    pub fn grows_memory(&self) -> bool {
+        self.grows_memory
+    }
 
-                // mapping of WASM instructions to node properties including data couplings and abstract 
-                // simulatable operations; a number of instructions are not yet supported
+    // models the node's reads of the memory size, as a constant for the given number of pages or, when the
+    // size is only known when running, as an input variable each
+    pub(crate) fn model_memory_size(&mut self, pages:Option<usize>) {
+        let mut locations:Vec<usize> = self.memory_size_reads.keys().cloned().collect();
+        locations.sort();
+        for i in locations {
+            match pages {
+                Some(pages) => {
+                    self.add_constant(i, Type::I32);
+                    self.add_operation(i, AbstractExpression::Num{ val: pages });
+                }
+                None => {
+                    let var_id = self.add_input_variable(Type::I32);
+                    self.add_operation(i, AbstractExpression::Spin{ id: var_id });
+                    self.memory_size_variables.insert(i, var_id);
+                }
+            }
+        }
+    }
 
-                // white is for non-critical code
-                // yellow is for control dependencies
-                // blue is for data dependencies
-                // purple is for function calls
-                // green is for simulatable operations
+    // returns the locations reading a memory size only known when running, mapped to the input variables
+    // holding it
+    pub fn get_memory_size_variables(&self) -> HashMap<usize, usize> {
+        self.memory_size_variables.clone()
+    }
 
-                match op {
-                    Operator::Unreachable => {
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)));
-                    }
-                    Operator::Nop => {
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)));
-                    }
-                    Operator::Block { ty } => {
+    // checks whether the node's address computations may depend on a memory size only known when running,
+    // which is assumed of every memory access following a read of the size
+    pub fn depends_on_memory_size(&self) -> bool {
+        let first = self.memory_size_variables.keys().map(|i| self.memory_size_reads[i]).min();
+        match first {
+            Some(first) => self.memory_accesses.iter().any(|access| access.offset > first),
+            None => false
+        }
+    }
 
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                        print!("==== New Block: ");
-                        println!("{}. {:?}", i, op);
+    // checks whether the node's or its nested blocks' address computations may depend on a memory size only
+    // known when running
+    fn depends_on_memory_size_within(&self, blocks:&HashMap<BlockId, Node>) -> bool {
+        self.depends_on_memory_size()
+            || self.blocks.values().any(|index| blocks.get(index).is_some_and(|block| block.depends_on_memory_size_within(blocks)))
+    }
 
-                        // blocks can simply be registered... they don't have parameters
-                        let block_node = self.map_helper(reader, buf, resources, position, i, Node::default());
-                        let block_id = self.add_block(block_node);
-                        node.add_block(i, block_id);
+    // checks whether the node itself writes to memory, with a store, an atomic operation other than a load or a
+    // bulk memory operation
+    pub fn writes_memory(&self) -> bool {
+        self.memory_accesses.iter().any(|memory| memory.access.kind == AccessKind::Write)
+            || self.sync_couplings.values().flatten().any(|coupling| coupling.kind != SyncKind::Load)
+            || !self.range_couplings.is_empty()
+    }
 
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                        print!("==== End of: ");
-                    }
-                    Operator::Loop { ty } => {
+    // returns the loads and stores made by the node itself, in the order they were read
+    pub fn get_memory_accesses(&self) -> Vec<MemoryAccess> {
+        self.memory_accesses.clone()
+    }
 
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                        print!("==== New Loop: ");
-                        println!("{}. {:?}", i, op);
+    // returns the loads and stores made by the node and its nested blocks in file order; the variables of nested
+    // blocks are their own, so their accesses are taken to have unknown bases rather than be compared with the node's
+    fn memory_timeline(&self, blocks:&HashMap<BlockId, Node>) -> Vec<MemoryAccess> {
+        let mut timeline = self.memory_accesses.clone();
+        for index in self.blocks.values() {
+            if let Some(block) = blocks.get(index) {
+                let nested = block.memory_timeline(blocks).into_iter()
+                    .map(|memory| MemoryAccess { access: MemAccess { base_expr: None, ..memory.access }, ..memory });
+                timeline.extend(nested);
+            }
+        }
+        timeline.sort_by_key(|access| access.offset);
+        timeline
+    }
 
-                        // loops don't have parameters so they can be registered as blocks
-                        let loop_node = self.map_helper(reader, buf, resources, position, i, Node::default());
-                        let loop_id = self.add_block(loop_node);
-                        node.add_block(i, loop_id);
+    // registers a read of a global at a program point, coupled to an input variable, after the global's earlier reads
+    pub(crate) fn add_global_input_data_coupling(&mut self, global:usize, point:ProgramPoint, var_id:usize) {
+        self.global_input_data_couplings.entry(global).or_default().push((point, var_id));
+    }
 
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                        print!("==== End of: ")
-                    }
-                    Operator::If { ty } => {
+    // registers a write of a global at a program point, coupled to an output variable, after the global's earlier writes
+    pub(crate) fn add_global_output_data_coupling(&mut self, global:usize, point:ProgramPoint, var_id:usize) {
+        self.global_output_data_couplings.entry(global).or_default().push((point, var_id));
+    }
+
+    // returns the node's own reads of the global with the given index, in order, as their program points and the
+    // input variables they are coupled to
+    pub fn global_reads(&self, global:usize) -> &[(ProgramPoint, usize)] {
+        self.global_input_data_couplings.get(&global).map_or(&[], Vec::as_slice)
+    }
+
+    // returns the node's own writes of the global with the given index, in order, as their program points and the
+    // output variables they are coupled to
+    pub fn global_writes(&self, global:usize) -> &[(ProgramPoint, usize)] {
+        self.global_output_data_couplings.get(&global).map_or(&[], Vec::as_slice)
+    }
+
+    // returns the node's own reads and writes of the global with the given index in the order they are made, each
+    // with the variable it is coupled to, which is what tells the hazards between them apart
+    pub fn global_accesses(&self, global:usize) -> Vec<(ProgramPoint, AccessKind, usize)> {
+        let reads = self.global_reads(global).iter().map(|(point, var_id)| (*point, AccessKind::Read, *var_id));
+        let writes = self.global_writes(global).iter().map(|(point, var_id)| (*point, AccessKind::Write, *var_id));
+        let mut accesses:Vec<(ProgramPoint, AccessKind, usize)> = reads.chain(writes).collect();
+        accesses.sort_by_key(|(point, _, _)| *point);
+        accesses
+    }
+
+    // checks if the node itself reads the global with the given index
+    pub fn reads_global(&self, global:usize) -> bool {
+        self.global_input_data_couplings.contains_key(&global)
+    }
+
+    // checks if the node itself may access memory in the given range of addresses; loads and stores are matched
+    // by the bytes they access from their region, and bulk operations whose ranges aren't known may touch any address
+    pub fn touches_memory(&self, range:Range) -> bool {
+        let overlaps = |start:Option<usize>, length:Option<usize>| match (start, length) {
+            (Some(start), Some(length)) => start < range.end && start.saturating_add(length) > range.start,
+            _ => true
+        };
+        self.memory_accesses.iter().any(|memory| overlaps(Some(memory.access.region()), Some(memory.access.width)))
+            || self.range_couplings.iter().any(|coupling| overlaps(coupling.destination, coupling.length)
+                || (coupling.reads_memory() && overlaps(coupling.source, coupling.length)))
+    }
+
+    // checks whether the node or its nested blocks use memory or globals, which belong to the module the
+    // node's code was read from
+    fn uses_module_state(&self, blocks:&HashMap<BlockId, Node>) -> bool {
+        !self.memory_accesses.is_empty() || !self.range_couplings.is_empty() || !self.sync_couplings.is_empty()
+            || !self.memory_size_reads.is_empty() || self.grows_memory
+            || !self.global_input_data_couplings.is_empty() || !self.global_output_data_couplings.is_empty()
+            || self.blocks.values().any(|index| blocks.get(index).is_some_and(|block| block.uses_module_state(blocks)))
+    }
+
+    // registers a branch at a particular location with target depth
+    pub(crate) fn add_branch(&mut self, branch_index:usize, relative_depth:usize) {
+        self.branches.insert(branch_index, relative_depth);
+    }
+
+    // checks if a branch has been registered at the given index
+    pub fn has_branch(&self, branch_index:usize) -> bool {
+        self.branches.contains_key(&branch_index)
+    }
+
+    // registers the location of a block with the given id
+    pub(crate) fn add_block(&mut self, start_index:usize, block_index:BlockId) {
+        self.blocks.insert(start_index, block_index);
+    }
+
+    // borrows the set of registered blocks
+    pub fn blocks(&self) -> &HashMap<usize, BlockId> {
+        &self.blocks
+    }
+
+    // registers the call to other functions found in this node
+    pub(crate) fn add_call(&mut self, call_index:usize, callee:NodeId) {
+        self.calls.insert(call_index, callee);
+    }
+
+    // registers an indirect call at a particular location through a table of functions of the given type
+    pub(crate) fn add_indirect_call(&mut self, call_index:usize, type_index:usize) {
+        self.indirect_calls.insert(call_index, type_index);
+    }
+
+    // returns the locations of the indirect calls mapped to the types of the functions they can call
+    pub fn get_indirect_calls(&self) -> HashMap<usize, usize> {
+        self.indirect_calls.clone()
+    }
+
+    // checks if a call has been registered at the given index
+    pub fn has_call(&self, call_index:usize) -> bool {
+        self.calls.contains_key(&call_index)
+    }
+
+    // borrows the set of registered calls
+    pub fn calls(&self) -> &HashMap<usize, NodeId> {
+        &self.calls
+    }
+
+    // iterates over the registered calls, by location, in no particular order
+    pub fn iter_calls(&self) -> hash_map::Iter<'_, usize, NodeId> {
+        self.calls.iter()
+    }
+
+    // borrows the set of registered constants
+    pub fn constants(&self) -> &HashMap<usize, Type> {
+        &self.constants
+    }
+
+    // borrows the set of registered internal variables
+    pub fn internal_variables(&self) -> &HashMap<usize, Type> {
+        &self.internal_variables
+    }
+
+    // borrows the set of registered input variables
+    pub fn input_variables(&self) -> &HashMap<usize, Type> {
+        &self.input_variables
+    }
+
+    // returns the node's least recently registered input variable, if it has any
+    pub(crate) fn get_first_input_variable(&self) -> Option<Type> {
+        self.input_variables.keys().min().map(|index| self.input_variables[index])
+    }
+
+    // returns the set of registered flow control couplings
+    pub(crate) fn get_flow_control_couplings(&self) -> HashMap<usize, usize> {
+        self.flow_control_couplings.clone()
+    }
+
+    // borrows the set of registered flow control couplings
+    pub fn flow_control_couplings(&self) -> &HashMap<usize, usize> {
+        &self.flow_control_couplings
+    }
+
+    // returns the node's least recently registered flow control coupling, if it has any
+    pub(crate) fn get_first_flow_control_coupling(&self) -> Option<usize> {
+        self.flow_control_couplings.keys().min().map(|index| self.flow_control_couplings[index])
+    }
+
+    // checks if the variables with the given id is coupled to any global or memory dependency
+    pub fn input_variable_is_param(&self, var_id:usize) -> bool {
+        let mut param = true;
+
+        if self.global_input_data_couplings.values().flatten().any(|(_, var)| *var == var_id) {
+            param = false
+        }
+        if self.input_data_couplings.iter().any(|(_, var)| *var == var_id) {
+            param = false
+        }
+        if self.result_couplings.values().any(|var| *var == var_id) {
+            param = false
+        }
+        param
+    }
+
+    // removes all calls
+    fn remove_calls(&mut self, calls:Vec<usize>) {
+        for index in calls {
+            self.calls.remove(&index);
+        }
+    }
+
+    // registers an unconditional unreachable or return; only the first one found bounds the live code
+    pub(crate) fn add_terminator(&mut self, i:usize) {
+        if self.terminator.is_none() {
+            self.terminator = Some(i);
+        }
+    }
+
+    // returns the location of the first unconditional unreachable or return, if any
+    pub fn get_terminator(&self) -> Option<usize> {
+        self.terminator
+    }
+
+    // removes everything registered after the terminator
+    pub(crate) fn remove_dead_code(&mut self) {
+        if let Some(terminator) = self.terminator {
+            self.relocate(&|i| if i <= terminator { Some(i) } else { None });
+        }
+    }
+
+    // registers the location of the node in the source WASM file
+    pub(crate) fn set_start(&mut self, start:usize) {
+        self.start = start;
+    }
+
+    // registers the end of the node in the source WASM file
+    pub(crate) fn set_end(&mut self, end:usize) {
+        self.end = end;
+    }
+
+    // marks whether the node is a whole function rather than a block
+    pub(crate) fn set_function(&mut self, function:bool) {
+        self.function = function;
+    }
+
+    // checks whether the node is a whole function rather than a block
+    pub fn is_function(&self) -> bool {
+        self.function
+    }
+
+    // returns the range of the source WASM file the node's instructions were taken from
+    pub fn source_span(&self) -> Range {
+        Range { start: self.start, end: self.end }
+    }
+
+    // returns the location of the node in the source WASM file
+    pub fn get_start(&self) -> usize {
+        self.start
+    }
+
+    // returns the end of the node in the source WASM file
+    pub fn get_end(&self) -> usize {
+        self.end
+    }
+
+    // checks whether an offset in the source WASM file falls within the node's instructions, including those
+    // of its nested blocks
+    pub fn spans(&self, offset:usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    // sets this node's list of child nodes
+    pub(crate) fn set_children(&mut self, children:BTreeMap<NodeId, Node>) {
+        self.children = children;
+    }
+
+    // inserts a child at a given index in this node's list of child nodes
+    pub(crate) fn add_child(&mut self, index:NodeId, child:Node) {
+        self.children.insert(index, child);
+    }
+
+    // borrows this node's list of child nodes
+    pub fn children(&self) -> &BTreeMap<NodeId, Node> {
+        &self.children
+    }
+
+    // counts this node and its descendants
+    pub(crate) fn subtree_size(&self) -> usize {
+        1 + self.children.values().map(Node::subtree_size).sum::<usize>()
+    }
+
+    // iterates over this node's child nodes, by id, in order of id
+    pub fn iter_children(&self) -> btree_map::Iter<'_, NodeId, Node> {
+        self.children.iter()
+    }
+
+    // walks this node and its nested nodes in pre-order, each node before its children, in order of id
+    pub fn walk(&self) -> Walk<'_> {
+        Walk::new(self, WalkOrder::PreOrder)
+    }
+
+    // walks this node and its nested nodes in post-order, each node after its children, in order of id
+    pub fn walk_post_order(&self) -> Walk<'_> {
+        Walk::new(self, WalkOrder::PostOrder)
+    }
+
+    // checks if this node's list of children contains a particular node
+    pub fn has_child(&self, key:NodeId) -> bool {
+        self.children.contains_key(&key)
+    }
+
+    // returns a particular node if it is registered a child of this node
+    pub fn get_child(&self, key:NodeId) -> Option<Node> {
+        if self.children.contains_key(&key) {
+            Some(self.children[&key].clone())
+        } else {
+            None
+        }
+    }
+
+    // borrows the node a call or block of this node runs, whether it is a child of its own or a callee shared
+    // through the mapper's arena, which is how everything running the node's children should find them
+    pub fn get_callee(&self, key:NodeId) -> Option<&Node> {
+        self.children.get(&key).or_else(|| self.shared_children.get(&key).map(|subtree| &**subtree))
+    }
+
+    // registers a called function whose expanded subtree is shared through the mapper's arena instead of copied;
+    // the subtree is linked in once expansion has finished it
+    pub(crate) fn add_shared_child(&mut self, index:NodeId) {
+        self.shared_children.insert(index, Arc::new(Node::default()));
+    }
+
+    // returns the called functions whose expanded subtrees are shared through the mapper's arena
+    pub(crate) fn get_shared_children(&self) -> HashSet<NodeId> {
+        self.shared_children.keys().cloned().collect()
+    }
+
+    // borrows the called functions whose expanded subtrees are shared through the mapper's arena, and the subtrees
+    pub fn shared_children(&self) -> &BTreeMap<NodeId, Arc<Node>> {
+        &self.shared_children
+    }
+
+    // checks if a called function's expanded subtree is shared through the mapper's arena
+    pub fn has_shared_child(&self, key:NodeId) -> bool {
+        self.shared_children.contains_key(&key)
+    }
+
+    // links the subtrees of the shared callees beneath the node, its own children's included, to the finished ones
+    // of the arena, linking those first so that every caller ends up with the same complete subtree; a callee
+    // whose subtree is still being linked, which only a reference loop would lead back to, is left as it is
+    fn link_shared_children(&mut self, arena:&HashMap<NodeId, Node>, linked:&mut HashMap<NodeId, Option<Arc<Node>>>) {
+        for index in self.get_shared_children() {
+            let subtree = match linked.get(&index) {
+                Some(subtree) => subtree.clone(),
+                None => match arena.get(&index) {
+                    Some(subtree) => {
+                        linked.insert(index, None);
+                        let mut subtree = subtree.clone();
+                        subtree.link_shared_children(arena, linked);
+                        let subtree = Arc::new(subtree);
+                        linked.insert(index, Some(subtree.clone()));
+                        Some(subtree)
+                    }
+                    None => None
+                }
+            };
+            if let Some(subtree) = subtree {
+                self.shared_children.insert(index, subtree);
+            }
+        }
+        for child in self.children.values_mut() {
+            child.link_shared_children(arena, linked);
+        }
+    }
+
+    // records that the block or call at a location was left unexpanded by the expansion limits
+    pub(crate) fn add_truncation(&mut self, location:usize, index:usize) {
+        self.truncations.insert(location, index);
+    }
+
+    // returns the locations of blocks and calls left unexpanded by the expansion limits, mapped to their ids
+    pub fn get_truncations(&self) -> HashMap<usize, usize> {
+        self.truncations.clone()
+    }
+
+    // checks whether expansion stopped short of any of the node's blocks or calls
+    pub fn is_truncated(&self) -> bool {
+        !self.truncations.is_empty()
+    }
+
+    // labels the node, e.g. with an annotation embedded in one of the module's custom sections
+    pub(crate) fn add_annotation(&mut self, label:&str) {
+        self.annotations.insert(label.to_string());
+    }
+
+    // returns the labels given to the node
+    pub fn get_annotations(&self) -> HashSet<String> {
+        self.annotations.clone()
+    }
+
+    // checks if the node was given a label
+    pub fn has_annotation(&self, label:&str) -> bool {
+        self.annotations.contains(label)
+    }
+
+    // sets the lines of original source the node's instructions were compiled from
+    pub(crate) fn set_source_locations(&mut self, source_locations:Vec<SourceRange>) {
+        self.source_locations = source_locations;
+    }
+
+    // returns the lines of original source the node's instructions were compiled from, a range per source
+    // file, or none if the module carries no DWARF line table and the mapper was given no source map
+    pub fn source_locations(&self) -> Vec<SourceRange> {
+        self.source_locations.clone()
+    }
+
+    // clears this node's list of child nodes
+    fn remove_children(&mut self, children:Vec<NodeId>) {
+        for index in children {
+            self.children.remove(&index);
+        }
+    }
+
+    // sets this node's list of hex instructions
+    pub(crate) fn set_instrs(&mut self, instrs:Vec<u8>) {
+        self.instrs = instrs;
+    }
+
+    // borrows this node's list of hex instructions
+    pub fn instrs(&self) -> &[u8] {
+        &self.instrs
+    }
+
+    // splits the code between two offsets in the file out of this node's list of hex instructions, as when a nested
+    // block becomes a node of its own, and returns the map of the split; the node's offsets, its children's and those
+    // of its memory accesses and couplings stay those of the file, and its branches, calls and blocks are keyed by
+    // locations, which a split doesn't move, so the splits made so far are what tell where an offset falls in the
+    // instructions left
+    pub(crate) fn remove_instrs(&mut self, start:usize, end:usize) -> OffsetMap {
+        let mut new_instrs:Vec<u8> = Vec::new();
+
+        // the offsets are taken past the earlier splits, and code already split out is left alone
+        let (start, end) = match (self.splits.map(start), self.splits.map(end)) {
+            (Some(start), Some(end)) => (start.saturating_sub(self.start), end.saturating_sub(self.start)),
+            _ => return OffsetMap::default()
+        };
+        let old_instrs = self.instrs();
+
+        // the segment is clamped to the instructions the node actually has
+        let end = end.min(old_instrs.len());
+        let start = start.min(end);
+        let mut i = 0;
+        while i < start {
+            new_instrs.push(old_instrs[i]);
+            i += 1;
+        }
+        i = end;
+        while i < old_instrs.len() {
+            new_instrs.push(old_instrs[i]);
+            i += 1;
+        }
+        self.set_instrs(new_instrs);
+        let split = OffsetMap::removing(self.start + start, self.start + end);
+        self.splits.extend(&split);
+        split
+    }
+
+    // returns where the byte at an offset in the file is in this node's list of hex instructions, unless it was split
+    // out of them or isn't the node's
+    pub fn code_offset(&self, offset:usize) -> Option<usize> {
+        self.splits.map(offset).filter(|offset| *offset >= self.start).map(|offset| offset - self.start).filter(|index| *index < self.instrs.len())
+    }
+
+    // returns the ranges of the file split out of this node's instructions
+    pub fn get_splits(&self) -> &OffsetMap {
+        &self.splits
+    }
+
+    // checks the node's bookkeeping, and that of the nodes expanded into it, against the invariants the mapper keeps:
+    // input and output variables are numbered in the order they were registered, whatever refers to a variable
+    // refers to one of the right kind, operations and conditions only refer back to operations recorded before
+    // them, and the node's code doesn't end before it starts
+    pub fn verify_invariants(&self) -> Vec<InvariantViolation> {
+        let mut violations = Vec::new();
+        self.verify_invariants_helper(&mut violations);
+        violations
+    }
+
+    fn verify_invariants_helper(&self, violations:&mut Vec<InvariantViolation>) {
+        let node = self.id.0;
+        let mut violate = |description:String| violations.push(InvariantViolation { node, description });
+
+        // variable ids are unique by construction, but are only ever handed out in order
+        for (kind, variables) in [("input", &self.input_variables), ("output", &self.output_variables)] {
+            let mut misnumbered:Vec<usize> = variables.keys().filter(|var_id| **var_id >= variables.len()).cloned().collect();
+            misnumbered.sort();
+            for var_id in misnumbered {
+                violate(format!("{} variable {} is numbered past the {} {} variables", kind, var_id, variables.len(), kind));
+            }
+        }
+
+        // couplings, and everything else kept by variable, refer to variables the node has
+        let inputs = [
+            ("memory input coupling", self.input_data_couplings.iter().map(|(_, var_id)| *var_id).collect::<Vec<usize>>()),
+            ("global input coupling", self.global_input_data_couplings.values().flatten().map(|(_, var_id)| *var_id).collect()),
+            ("flow control coupling", self.flow_control_couplings.values().cloned().collect()),
+            ("result coupling", self.result_couplings.values().cloned().collect()),
+            ("local input", self.local_input_variables.values().cloned().collect()),
+            ("memory size", self.memory_size_variables.values().cloned().collect()),
+            ("load width", self.input_widths.keys().cloned().collect()),
+            ("input range", self.input_ranges.keys().cloned().collect()),
+            ("input marking", self.input_markings.keys().cloned().collect()),
+            ("input lanes", self.input_lanes.keys().cloned().collect()),
+            ("load", self.operations.values().filter_map(|operation| match *operation {
+                AbstractExpression::Load { id, .. } => Some(id),
+                _ => None
+            }).collect())
+        ];
+        let outputs = [
+            ("memory output coupling", self.output_data_couplings.iter().map(|(_, var_id)| *var_id).collect::<Vec<usize>>()),
+            ("global output coupling", self.global_output_data_couplings.values().flatten().map(|(_, var_id)| *var_id).collect()),
+            ("local output", self.local_output_variables.values().cloned().collect()),
+            ("output lanes", self.output_lanes.keys().cloned().collect()),
+            ("result", self.result_variable.into_iter().collect())
+        ];
+        for (kind, references, variables) in inputs.iter().map(|(kind, references)| (kind, references, &self.input_variables))
+            .chain(outputs.iter().map(|(kind, references)| (kind, references, &self.output_variables))) {
+            let mut dangling:Vec<usize> = references.iter().filter(|var_id| !variables.contains_key(var_id)).cloned().collect();
+            dangling.sort();
+            for var_id in dangling {
+                violate(format!("a {} refers to variable {}, which the node doesn't have", kind, var_id));
+            }
+        }
+
+        // operations reuse, and ifs, conditional branches and selects are conditioned on, earlier operations or
+        // the results of earlier blocks
+        let reuses = self.operations.iter().filter_map(|(i, operation)| match *operation {
+            AbstractExpression::Reuse { location } => Some((*i, location, "reuses")),
+            _ => None
+        });
+        let conditions = self.conditions.iter().map(|(i, producer)| (*i, *producer, "is conditioned on"));
+        for (i, location, relation) in reuses.chain(conditions).collect::<Vec<(usize, usize, &str)>>() {
+            if location >= i || !(self.operations.contains_key(&location) || self.result_couplings.contains_key(&location)) {
+                violate(format!("location {} {} location {}, which isn't an operation recorded before it", i, relation, location));
+            }
+        }
+
+        if self.start > self.end {
+            violate(format!("the node's code starts at {:#x}, past its end at {:#x}", self.start, self.end));
+        }
+
+        for child in self.children.values() {
+            child.verify_invariants_helper(violations);
+        }
+    }
+
+    // describes the node and its nested nodes as a tree, a line per node indented by its depth, going at most the
+    // given number of levels below the node; deeper nodes, and those shared through the mapper's arena, are only
+    // counted
+    pub fn describe(&self, depth:usize) -> String {
+        let mut description = String::new();
+        self.describe_helper(0, depth, &mut description);
+        description
+    }
+
+    // appends the lines describing the node at the given level to the description
+    fn describe_helper(&self, level:usize, depth:usize, description:&mut String) {
+        let indent = "  ".repeat(level);
+        description.push_str(&format!("{}{}\n", indent, self));
+        if level < depth {
+            for child in self.children.values() {
+                child.describe_helper(level + 1, depth, description);
+            }
+        } else if !self.children.is_empty() {
+            description.push_str(&format!("{}  ... {} nested nodes\n", indent, self.children.len()));
+        }
+        if !self.shared_children.is_empty() {
+            description.push_str(&format!("{}  ... {} shared nodes\n", indent, self.shared_children.len()));
+        }
+    }
+}
+
+
+// summarizes the node on a line, e.g. `function 2 at 0x2f..0x45: 1 input, 1 internal, 0 constant variables;
+// 3 operations [spin 0, spin 1, add I32]`
+/// The order a walk visits a node in relative to its children
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WalkOrder {
+    PreOrder, // a node before its children
+    PostOrder // a node after its children
+}
+
+
+/// A visit is a step of a walk through a tree of nodes
+#[derive(Clone, Copy, Debug)]
+pub struct Visit<'a> {
+    pub depth: usize, // how many levels below the node the walk started from the visited node is
+    pub id: Option<NodeId>, // the id the visited node has among its parent's children, unless the walk started from it
+    pub node: &'a Node
+}
+
+
+/// A walk visits a node and its nested nodes, though not those shared
+/// through the mapper's arena, with the children of each node in
+/// order of id
+pub struct Walk<'a> {
+    order: WalkOrder,
+    pending: Vec<(Visit<'a>, bool)> // the nodes left to visit, last first, and whether their children are pending too
+}
+
+
+impl<'a> Walk<'a> {
+    fn new(node:&'a Node, order:WalkOrder) -> Walk<'a> {
+        Walk { order, pending: vec![(Visit { depth: 0, id: None, node }, false)] }
+    }
+
+    // queues the children of a visited node so that the one with the lowest id is visited first
+    fn push_children(&mut self, visit:Visit<'a>) {
+        for (id, child) in visit.node.children.iter().rev() {
+            self.pending.push((Visit { depth: visit.depth + 1, id: Some(*id), node: child }, false));
+        }
+    }
+}
+
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = Visit<'a>;
+
+    fn next(&mut self) -> Option<Visit<'a>> {
+        loop {
+            let (visit, expanded) = self.pending.pop()?;
+            match self.order {
+                WalkOrder::PreOrder => {
+                    self.push_children(visit);
+                    return Some(visit);
+                },
+                WalkOrder::PostOrder if expanded || visit.node.children.is_empty() => return Some(visit),
+                WalkOrder::PostOrder => {
+                    self.pending.push((visit, true));
+                    self.push_children(visit);
+                }
+            }
+        }
+    }
+}
+
+
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let kind = if self.is_function() { "function" } else { "block" };
+        write!(f, "{} {} at {:#x}..{:#x}: {} input, {} internal, {} constant variables; {} operations [",
+            kind, self.id, self.start, self.end, self.input_variables.len(), self.internal_variables.len(), self.constants.len(), self.operations.len())?;
+        for (index, op) in self.operations.values().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", op)?;
+        }
+        write!(f, "]")
+    }
+}
+
+
+/// A decision the mapper can't make on its own, which an
+/// observer answers with yes or no
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decision {
+    Parallelize { function: usize }, // whether to expand a top-level function
+    Lower { node: usize, input_variables: usize, internal_variables: usize, constants: usize }, // whether to lower a node of the given size
+    LowerNested { node: usize, child: usize } // whether to lower a nested node while lowering its parent
+}
+
+
+/// The warning category enum represents the kinds of constructs
+/// the mapper skips or can't fully map
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningCategory {
+    SelfReference, // a function calling itself, which can't be unrolled
+    ReferenceLoop, // a call back into a function already on the expansion path
+    ImportedCall, // a call to an imported function, which has no code to expand
+    UnknownType, // a parameter or result of a type the mapper doesn't know
+    UnreadableLocals, // a function body whose locals declarations can't be read
+    UndeclaredLocal, // an access to a local outside the function's local index space
+    RoundtripMismatch, // recorded operations that diverge from the original code
+    IllTyped, // an operation whose operands don't have its type
+    Synchronization, // a wait or wake, which makes a node's progress depend on other threads
+    Race, // a non-atomic access to shared memory, which other threads can interfere with
+    DynamicMemory, // memory accesses whose addresses may depend on the memory's size when running
+    Truncated, // a block or call left unexpanded because expansion reached its depth or size limit
+    Annotation, // an annotation from a custom section for a function the module doesn't define
+    Precision, // terms of a lowered QUBO too small for the annealer's dynamic range
+    RecursionBound, // a recursive call past the depth recursion is unrolled to, which is left unexpanded
+    UnresolvedImport, // an import naming a registered module that doesn't export the function
+    LinkedState, // a linked function using memory or globals, which are modelled as the importing module's
+    IncompleteCoupling, // a nested node that isn't coupled to the flow control variable guarding it, which keeps its parent from being lowered
+    MalformedSection // a custom section the mapper reads, such as its hints, that can't be decoded, raised on node 0 since it concerns the whole module
+}
+
+
+/// A section handler interprets the contents of a custom section,
+/// returning the functions it annotates along with their labels
+pub type SectionHandler = Box<dyn Fn(&[u8]) -> Vec<(usize, String)> + Send + Sync>;
+
+
+/// A partial result holds the nodes mapped from a module that may
+/// be invalid or use unsupported operators, along with the errors that
+/// stopped mapping short
+#[derive(Clone, Debug)]
+pub struct PartialResult {
+    pub nodes: HashMap<NodeId, Node>, // the nodes of the functions read before the error, or of every function, which are left unexpanded if a strict mapper met unsupported operators
+    pub error: Option<BinaryReaderError>, // the first error and where it is in the WASM file, if the module is invalid
+    pub unsupported: Option<UnsupportedOperatorsError> // the unsupported operators that stopped a strict mapper before expansion, if any
+}
+
+
+impl PartialResult {
+    // checks whether the whole module was mapped
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none() && self.unsupported.is_none()
+    }
+}
+
+
+/// A mapper warning records a construct that was skipped while mapping
+/// or expanding, so tooling can report it after the fact
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapperWarning {
+    pub node: usize, // the id of the node the construct was found in
+    pub location: Option<usize>, // the index of the instruction within the node, unless it concerns the node as a whole
+    pub category: WarningCategory,
+    pub message: String // the description passed on to the observer
+}
+
+
+/// An invariant violation is a piece of a node's bookkeeping that doesn't
+/// hold together, which is a bug in whatever recorded it
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvariantViolation {
+    pub node: usize, // the id of the node the bookkeeping belongs to
+    pub description: String // what doesn't hold together
+}
+
+
+/// A mapper observer is told about the progress of expansion and lowering,
+/// and makes the decisions that come up along the way. It has to be Send
+/// and Sync so that the mapper owning it stays shareable across threads
+pub trait MapperObserver: Send + Sync {
+    // called when a module starts being mapped, with its bytes
+    fn on_module(&mut self, _module:&[u8]) {}
+
+    // called when a top-level function starts being expanded
+    fn on_function_start(&mut self, _function:usize) {}
+
+    // called when a block is broken out of the node containing it, with the id it is registered under
+    fn on_block_found(&mut self, _parent:usize, _block:usize) {}
+
+    // called when a decision needs to be made; returning false declines
+    fn on_decision_needed(&mut self, _decision:&Decision) -> bool {
+        true
+    }
+
+    // called when part of the code can't be handled
+    fn on_warning(&mut self, _message:&str) {}
+
+    // called with a summary of what a pass or the expansion of a node did
+    fn on_progress(&mut self, _message:&str) {}
+}
+
+
+/// The console observer prints progress to the terminal and asks the
+/// user to make each decision, which is what the mapper does by default
+pub struct ConsoleObserver;
+
+
+impl MapperObserver for ConsoleObserver {
+    fn on_function_start(&mut self, function:usize) {
+        println!("Analyzing function {}...", function);
+    }
+
+    fn on_block_found(&mut self, parent:usize, block:usize) {
+        println!("Breaking block {} out from node {}", block, parent);
+    }
+
+    fn on_decision_needed(&mut self, decision:&Decision) -> bool {
+        match *decision {
+            Decision::Parallelize { function } => confirm(&format!("Parallelize function {}", function)),
+            Decision::Lower { node, input_variables, internal_variables, constants } => {
+                println!("Node {} has {} input variabes, {} internal variables coupled with other nodes, and {} constants.", node, input_variables, internal_variables, constants);
+                confirm(&format!("Do you want to lower node {}", node))
+            }
+            Decision::LowerNested { child, .. } => confirm(&format!("Do you want to lower the nested node {}", child))
+        }
+    }
+
+    fn on_warning(&mut self, message:&str) {
+        println!("{}", message);
+    }
+
+    fn on_progress(&mut self, message:&str) {
+        println!("{}", message);
+    }
+}
+
+
+/// The silent observer accepts every decision without printing anything,
+/// for running the mapper unattended
+pub struct SilentObserver;
+
+
+impl MapperObserver for SilentObserver {}
+
+
+/// The linking observer maps the modules linked into another without
+/// expanding them, since their functions are expanded as the other's
+struct LinkingObserver;
+
+
+impl MapperObserver for LinkingObserver {
+    fn on_decision_needed(&mut self, _decision:&Decision) -> bool {
+        false
+    }
+}
+
+
+// the mapper reads modules using the threads proposal, since its atomics express dependencies between threads,
+// the SIMD proposal, whose vectors are modelled lane by lane, and the bulk memory proposal, whose operations
+// are modelled as range couplings
+const PARSER_CONFIG: ValidatingParserConfig = ValidatingParserConfig {
+    operator_config: OperatorValidatorConfig {
+        enable_threads: true,
+        enable_reference_types: false,
+        enable_simd: true,
+        enable_bulk_memory: true
+    },
+    mutable_global_imports: false
+};
+
+
+// how many nodes expansion creates unless told otherwise, which keeps call graphs with many paths to the same
+// functions from exhausting memory when their subtrees aren't shared
+pub const DEFAULT_MAX_EXPANDED_NODES: usize = 10_000;
+
+
+/// The mapper is responsible for performing the mapping of arbitrary 
+/// input WASM to its parallel and simulatable form. It only owns plain
+/// data, so it is Send and Sync and its read-only analyses can be shared
+/// across threads once a module has been mapped
+pub struct Mapper {
+    blocks:HashMap<BlockId, Node>, // registered code segments originally include ambiguous blocks,
+    nodes:HashMap<NodeId, Node>, // and eventually only uniquely adressed nodes
+    exports:HashMap<usize, String>, // exported functions' indices mapped to their export names
+    function_names:HashMap<usize, String>, // the functions named by the last mapped module's name section, by index
+    include:Option<Regex>, // what the names of the functions mapped have to match, unless every function is mapped
+    exclude:Option<Regex>, // what the names of the functions left out of mapping match, if any are
+    imports:HashMap<usize, (String, String)>, // imported functions' ids mapped to the module and field they name, including the linked modules' unresolved imports
+    modules:Vec<(String, Vec<u8>)>, // the modules imports are resolved against, by name, in the order they were added
+    linked:HashMap<usize, (String, usize)>, // the ids of the functions linked in from those modules, mapped to their module and index there
+    start:Option<usize>, // the module's start function
+    shared_memory:bool, // whether the module's memory is shared between threads
+    external_memory:bool, // whether the module's memory is imported or exported, so that the host can grow it
+    memory_limits:Option<ResizableLimits>, // the declared limits of the module's memory, if it has one
+    bulk_unroll_limit:usize, // the longest static bulk memory operation unrolled into a coupling per byte
+    inline_threshold:usize, // the most operations a callee can have to be inlined when mapping
+    call_graph:CallGraph, // the calls between functions as discovered by the first pass of the last mapping
+    subtree_sharing:bool, // whether expansion keeps each callee's subtree once in the arena instead of copying it into every caller
+    arena:HashMap<NodeId, Arc<Node>>, // the expanded subtrees of called functions, shared by their callers, from the last expansion
+    max_expansion_depth:Option<usize>, // how many calls and blocks deep expansion goes, if limited
+    recursion_depth:usize, // how many times a function calling itself is unrolled, if at all
+    block_id_offset:Option<usize>, // where the ids of expanded blocks and unrolled copies start, if not right past the functions' ids
+    first_block_id:usize, // the first id given out by the last expansion
+    next_block_id:AtomicUsize, // the next id given to an expanded block or unrolled copy
+    max_expanded_nodes:Option<usize>, // how many nodes expansion creates across all functions, if limited
+    checkpoint:Option<Arc<Mutex<Checkpoint>>>, // the checkpoint of the session, if it is being recorded
+    theme:Theme, // the colors operators are printed in by the first pass, and the tree's critical path
+    critical_path_highlighting:bool, // whether printing the tree highlights the functions on the critical path
+    output:Option<Box<dyn WriteColor + Send + Sync>>, // where the first pass prints to instead of standard output, if anywhere
+    source_map:Option<String>, // the source map of the module, used when it carries no DWARF line table
+    branch_profile:Option<BranchProfile>, // the counts of the module's branches in a profiled run, if it was profiled
+    source_mapping_url:Option<String>, // where the last mapped module says its source map is
+    line_table:LineTable, // the source locations of the last mapped module's code
+    custom_sections:Vec<(String, Vec<u8>)>, // the last mapped module's custom sections, by name, in order
+    section_handlers:HashMap<String, SectionHandler>, // the handlers interpreting custom sections, by section name
+    hints:Option<Hints>, // the directives embedded in the last mapped module, which answer its decisions if present
+    profiler:Profiler, // the time spent on each node since the last mapping started
+    error:Option<BinaryReaderError>, // the first error in the last mapped module, past which it couldn't be read
+    lane_shape:LaneShape, // how vectors are divided into lanes when the operation using them doesn't say
+    common_subexpression_elimination:bool, // whether repeated operations share their results when lowering is planned
+    lowering:LoweringConfig, // how nodes are synthesized from gadgets when lowered and when lowering is planned
+    precision:Option<PrecisionConfig>, // the coefficients lowered QUBOs are checked against, unless unchecked
+    observer:Box<dyn MapperObserver>, // receives progress events and makes decisions during expansion
+    support:SupportMatrix, // how each operator is treated
+    strict:bool, // whether mapping fails when unsupported operators are encountered
+    unsupported:Vec<UnsupportedOperator>, // the unsupported operators encountered by the last mapping
+    weights:OperationWeights, // the cost of each operation when estimating the speedup
+    roundtrip_trials:usize, // how many random inputs each function is validated with after mapping, if any
+    predication:HashSet<usize>, // the functions whose simple if/else diamonds are turned into arithmetic on their conditions
+    input_ranges:HashMap<(usize, usize), ValueRange>, // the ranges given to input variables, by node id and variable id
+    data_segments:Option<Vec<(usize, Vec<u8>)>>, // where the last mapped module's data segments start and their bytes, unless its memory can be written from outside
+    warnings:Vec<MapperWarning>, // the constructs skipped by the last mapping and any expansion since
+    structure_diagnostics:Vec<StructureDiagnostic>, // the disagreements between the last mapping's spans and blocks and its code
+}
+
+
+/// An expansion is a node waiting to be, or already, expanded, along
+/// with where it goes once its own children are complete
+struct Expansion {
+    node: Node, // the node, updated once it has been expanded
+    id: NodeId, // the function index or block id of the node
+    function: bool, // whether the node is a function rather than a block
+    frame: NodeId, // the index of the function whose code the node, or the block's enclosing function, was read from
+    recursion: usize, // how many times that function has called itself on the way to the node
+    depth: usize, // how many calls and blocks the node is below the expanded function
+    path: HashSet<NodeId>, // the functions and blocks on the execution path leading to the node
+    parent: ExpansionParent // where the node goes once expanded
+}
+
+
+/// A shared expansion is a called function's subtree expanded once into
+/// the arena, along with what it was expanded along, which decides whether
+/// a later call can share it or would expand it differently
+struct SharedExpansion {
+    path: HashSet<NodeId>, // the functions and blocks on the execution path of the call that expanded it
+    depth: usize, // how many calls and blocks the function is below the function whose expansion reached it
+    max_depth: Option<usize>, // the depth expansion was limited to
+    reachable: HashSet<NodeId>, // the functions it can call, directly or not, which are those whose place on a path can change its subtree
+    node: Option<Node> // the expanded subtree, once it is finished
+}
+
+
+impl SharedExpansion {
+    // checks whether a call along the given path, at the given depth and under the given depth limit, would expand
+    // the function into the same subtree: a reference loop is only skipped when a function the subtree can reach
+    // is on the path, and a depth limit truncates it by how deep it starts
+    fn is_shared_by(&self, path:&HashSet<NodeId>, depth:usize, max_depth:Option<usize>) -> bool {
+        self.max_depth == max_depth
+            && (max_depth.is_none() || self.depth == depth)
+            && self.path.symmetric_difference(path).all(|index| !self.reachable.contains(index))
+    }
+}
+
+
+/// The expansion parent enum represents where an expanded node goes
+enum ExpansionParent {
+    Root, // it is the function being expanded
+    Child(usize, NodeId), // it is a child of the expansion at an index, under an id
+    Arena(NodeId) // it is shared through the arena, under a function index
+}
+
+
+/// A linked module is a registered module mapped to resolve the
+/// imports naming it, before its functions join the importing module's
+struct LinkedModule {
+    exports: HashMap<String, usize>, // the module's exported functions' indices, by export name
+    imports: HashMap<usize, (String, String)>, // the module's imported functions' indices mapped to the module and field they name
+    ids: HashMap<usize, usize>, // the ids given to the module's reachable functions, by index in the module
+    nodes: HashMap<NodeId, Node>, // the module's reachable functions, by index in the module
+    blocks: HashMap<BlockId, Node>, // the module's blocks, by id in the module
+    block_offset: usize // how far the module's block ids are moved to follow the importing module's
+}
+
+
+// returns the id of the linked function an import resolves to, following functions that modules export
+// without defining them to the modules they import them from
+fn resolve_import(linked:&BTreeMap<String, LinkedModule>, module:&str, field:&str) -> Option<usize> {
+    let (mut module, mut field) = (module, field);
+    for _ in 0..=linked.len() {
+        let source = linked.get(module)?;
+        let index = *source.exports.get(field)?;
+        if let Some(id) = source.ids.get(&index) {
+            return Some(*id);
+        }
+        let (next_module, next_field) = source.imports.get(&index)?;
+        module = next_module;
+        field = next_field;
+    }
+    None
+}
+
+
+/// The component search holds the bookkeeping of a strongly connected
+/// component search over the call graph
+#[derive(Default)]
+struct ComponentSearch {
+    counter: usize, // the next search index to assign
+    indices: HashMap<usize, usize>, // the search index each visited function was assigned
+    low_links: HashMap<usize, usize>, // the lowest search index reachable from each visited function
+    stack: Vec<usize>, // functions visited but not yet assigned to a component
+    components: Vec<Vec<usize>> // the strongly connected components found so far
+}
+
+
+impl Mapper {
+    fn default () -> Mapper {
+        let blocks:HashMap<BlockId, Node> = HashMap::new();
+        let nodes:HashMap<NodeId, Node> = HashMap::new();
+
+        Mapper{
+            blocks: blocks,
+            nodes: nodes,
+            exports: HashMap::new(),
+            function_names: HashMap::new(),
+            include: None,
+            exclude: None,
+            imports: HashMap::new(),
+            modules: Vec::new(),
+            linked: HashMap::new(),
+            start: None,
+            common_subexpression_elimination: true,
+            lowering: LoweringConfig::default(),
+            precision: None,
+            observer: Box::new(ConsoleObserver),
+            support: SupportMatrix::new(),
+            strict: false,
+            unsupported: Vec::new(),
+            weights: OperationWeights::new(),
+            roundtrip_trials: 0,
+            predication: HashSet::new(),
+            input_ranges: HashMap::new(),
+            data_segments: None,
+            warnings: Vec::new(),
+            structure_diagnostics: Vec::new(),
+            shared_memory: false,
+            external_memory: false,
+            memory_limits: None,
+            bulk_unroll_limit: 0,
+            inline_threshold: 0,
+            call_graph: CallGraph::default(),
+            subtree_sharing: false,
+            arena: HashMap::new(),
+            max_expansion_depth: None,
+            recursion_depth: 0,
+            block_id_offset: None,
+            first_block_id: 0,
+            next_block_id: AtomicUsize::new(0),
+            max_expanded_nodes: Some(DEFAULT_MAX_EXPANDED_NODES),
+            checkpoint: None,
+            theme: Theme::default(),
+            critical_path_highlighting: false,
+            output: None,
+            source_map: None,
+            branch_profile: None,
+            source_mapping_url: None,
+            line_table: LineTable::default(),
+            custom_sections: Vec::new(),
+            section_handlers: HashMap::new(),
+            hints: None,
+            profiler: Profiler::new(),
+            error: None,
+            lane_shape: LaneShape::default(),
+        }
+    }
+
+    // replaces the observer that receives progress events and makes decisions during expansion
+    pub fn set_observer(&mut self, observer:Box<dyn MapperObserver>) {
+        self.observer = observer;
+    }
+
+    // replaces the matrix describing how each operator is treated
+    pub fn set_support_matrix(&mut self, support:SupportMatrix) {
+        self.support = support;
+    }
+
+    // makes mapping fail instead of recording partial data when unsupported operators are encountered
+    pub fn set_strict(&mut self, strict:bool) {
+        self.strict = strict;
+    }
+
+    // replaces the cost of each operation used when estimating the speedup
+    pub fn set_operation_weights(&mut self, weights:OperationWeights) {
+        self.weights = weights;
+    }
+
+    // has every mapping validate its functions' recorded operations against their original code on the
+    // given number of random inputs, reporting divergences as warnings; zero turns validation off
+    pub fn set_roundtrip_validation(&mut self, trials:usize) {
+        self.roundtrip_trials = trials;
+    }
+
+    // selects whether the simple if/else diamonds of a function are predicated when it is mapped, which
+    // replaces their flow control couplings with arithmetic on the condition
+    pub fn set_predication(&mut self, function:usize, enabled:bool) {
+        if enabled {
+            self.predication.insert(function);
+        } else {
+            self.predication.remove(&function);
+        }
+    }
+
+    // gives an input variable of a node the range its values are known to fall in when modules are mapped,
+    // overriding any range the mapper infers for it, or forgets the range given to it
+    pub fn set_input_range(&mut self, node:usize, var_id:usize, range:Option<ValueRange>) {
+        match range {
+            Some(range) => self.input_ranges.insert((node, var_id), range),
+            None => self.input_ranges.remove(&(node, var_id))
+        };
+    }
+
+    // sets how the vectors of SIMD loads, stores and constants are divided into lanes, since the operations
+    // themselves don't say; vectors are divided into four 32 bit integers by default
+    pub fn set_lane_shape(&mut self, shape:LaneShape) {
+        self.lane_shape = shape;
+    }
+
+    // sets the number of bytes up to which bulk memory operations with constant operands are unrolled into
+    // a load and store coupling per byte when mapped, so that they can be lowered; none are by default
+    pub fn set_bulk_unroll_limit(&mut self, bytes:usize) {
+        self.bulk_unroll_limit = bytes;
+    }
+
+    // sets the most operations a function can have to be inlined into its callers when mapping, before its
+    // calls are expanded; none are by default
+    pub fn set_inline_threshold(&mut self, operations:usize) {
+        self.inline_threshold = operations;
+    }
+
+    // selects whether expansion keeps the subtree of each called function once, in an arena that its callers
+    // link to by id, rather than copying it into every caller's children; this keeps call graphs with many
+    // paths to the same functions from blowing up, and callers that need their own copy can materialize it.
+    // A call along a path that would expand the function differently, by its reference loops or by where the
+    // depth limit cuts it, gets its own copy instead
+    pub fn set_subtree_sharing(&mut self, enabled:bool) {
+        self.subtree_sharing = enabled;
+    }
+
+    // unrolls functions calling themselves up to the given depth, like loops, expanding each recursive call into
+    // a copy of the function with an id of its own; the recursive calls of the deepest copies are left unexpanded
+    // and recorded as truncations. Zero, the default, skips recursive calls instead
+    pub fn set_recursion_unrolling(&mut self, depth:usize) {
+        self.recursion_depth = depth;
+    }
+
+    // makes the ids expansion gives blocks and unrolled copies of recursive functions start at the given offset,
+    // so that they stay put when functions are added to the module; an offset below the ids of the functions
+    // and imports is raised past them. By default they start right past them
+    pub fn set_block_id_offset(&mut self, offset:Option<usize>) {
+        self.block_id_offset = offset;
+    }
+
+    // limits how many calls and nested blocks deep expansion goes below each function; the blocks and calls
+    // past the limit are left unexpanded and recorded as truncations on their nodes. There is no limit by default
+    pub fn set_max_expansion_depth(&mut self, depth:Option<usize>) {
+        self.max_expansion_depth = depth;
+    }
+
+    // limits how many nodes expansion creates, counting each function and the copies of its blocks and callees;
+    // once reached, the remaining blocks and calls are recorded as truncations. The default limit is
+    // DEFAULT_MAX_EXPANDED_NODES, and none lifts it
+    pub fn set_max_expanded_nodes(&mut self, nodes:Option<usize>) {
+        self.max_expanded_nodes = nodes;
+    }
+
+    // returns the expanded subtree of a called function from the arena, if it was shared by the last expansion
+    pub fn get_shared_node(&self, index:NodeId) -> Option<Node> {
+        self.arena.get(&index).map(|node| (**node).clone())
+    }
+
+    // copies a shared callee's subtree into a caller's children, so that the caller can specialize it without
+    // affecting the other callers; the copy still references its own callees through the arena
+    pub fn materialize(&self, caller:&mut Node, index:NodeId) -> bool {
+        match caller.shared_children.remove(&index) {
+            Some(subtree) => {
+                caller.add_child(index, (*subtree).clone());
+                true
+            }
+            None => false
+        }
+    }
+
+    // returns a copy of the given node with every shared subtree beneath it materialized, which is the tree
+    // expansion builds when subtrees aren't shared
+    pub fn materialize_tree(&self, node:&Node) -> Node {
+        let mut node = node.clone();
+        for index in node.get_shared_children() {
+            self.materialize(&mut node, index);
+        }
+        let children = node.children().iter()
+            .map(|(index, child)| (*index, self.materialize_tree(child)))
+            .collect();
+        node.set_children(children);
+        node
+    }
+
+    // returns the paths to the nodes of the given tree, nested ones included, that satisfy the predicate, in order;
+    // a path lists the ids from the top-level function down to the node, and subtrees shared through the arena
+    // are searched under every caller
+    pub fn find_nodes(&self, tree:&HashMap<NodeId, Node>, predicate:&dyn Fn(&Node) -> bool) -> Vec<Vec<NodeId>> {
+        let mut found = Vec::new();
+
+        // the tree is walked with a list of pending nodes, since call chains can be deep
+        let mut pending:Vec<(&Node, Vec<NodeId>)> = tree.iter().map(|(index, node)| (node, vec![*index])).collect();
+        while let Some((node, path)) = pending.pop() {
+            if predicate(node) {
+                found.push(path.clone());
+            }
+            let shared = node.shared_children().iter().map(|(index, child)| (index, &**child));
+            for (index, child) in node.iter_children().chain(shared) {
+                let mut child_path = path.clone();
+                child_path.push(*index);
+                pending.push((child, child_path));
+            }
+        }
+        found.sort();
+        found
+    }
+
+    // returns the paths to the nodes of the given tree that call the function with the given index
+    pub fn nodes_calling(&self, tree:&HashMap<NodeId, Node>, function:FuncId) -> Vec<Vec<NodeId>> {
+        self.find_nodes(tree, &|node| node.calls().values().any(|callee| *callee == NodeId::from(function)))
+    }
+
+    // returns the paths to the nodes of the given tree that read the global with the given index
+    pub fn nodes_reading_global(&self, tree:&HashMap<NodeId, Node>, global:usize) -> Vec<Vec<NodeId>> {
+        self.find_nodes(tree, &|node| node.reads_global(global))
+    }
+
+    // returns the paths to the nodes of the given tree that may access memory in the given range of addresses
+    pub fn nodes_touching_memory(&self, tree:&HashMap<NodeId, Node>, range:Range) -> Vec<Vec<NodeId>> {
+        self.find_nodes(tree, &|node| node.touches_memory(range))
+    }
+
+    // returns the paths to the nodes of the given tree that were read from unsupported operators by the last
+    // mapping, which their mapping leaves incomplete; an operator is read by the innermost node spanning it, so
+    // a node whose child spans it isn't included
+    pub fn nodes_with_unsupported_ops(&self, tree:&HashMap<NodeId, Node>) -> Vec<Vec<NodeId>> {
+        let offsets:Vec<usize> = self.unsupported.iter().map(|operator| operator.offset).collect();
+        self.find_nodes(tree, &|node| offsets.iter().any(|offset| node.spans(*offset) && !node.children().values().any(|child| child.spans(*offset))))
+    }
+
+    // replaces the colors the first pass prints operators in
+    pub fn set_theme(&mut self, theme:Theme) {
+        self.theme = theme;
+    }
+
+    // selects whether printing the tree highlights the functions on the critical path of the speedup estimate,
+    // the longest chain of functions waiting on each other, and prints its length
+    pub fn set_critical_path_highlighting(&mut self, enabled:bool) {
+        self.critical_path_highlighting = enabled;
+    }
+
+    // has the first pass print to the given sink instead of standard output, e.g. a file or a buffer that
+    // leaves out colors when the output is captured
+    pub fn set_output(&mut self, output:Box<dyn WriteColor + Send + Sync>) {
+        self.output = Some(output);
+    }
+
+    // gives the mapper the JSON source map of the modules it maps, which resolves their nodes' offsets to source
+    // locations when the modules carry no DWARF line table of their own
+    pub fn set_source_map(&mut self, json:&str) {
+        self.source_map = Some(json.to_string());
+    }
+
+    // gives the mapper the branch counts of a profiled run of the modules it maps, which weigh their nodes'
+    // conditions, or takes them away
+    pub fn set_branch_profile(&mut self, profile:Option<BranchProfile>) {
+        self.branch_profile = profile;
+    }
+
+    // returns the URL the last mapped module's sourceMappingURL section points its source map at, if it has one
+    pub fn get_source_mapping_url(&self) -> Option<String> {
+        self.source_mapping_url.clone()
+    }
+
+    // registers a handler for the custom sections with the given name, whose annotations label the nodes of the
+    // functions they name when a module is mapped; a later handler for the same name replaces the earlier one
+    pub fn set_section_handler(&mut self, name:&str, handler:SectionHandler) {
+        self.section_handlers.insert(name.to_string(), handler);
+    }
+
+    // registers a module that the functions imported by mapped modules are resolved against, under the name
+    // imports give it; a later module with the same name replaces the earlier one
+    pub fn add_module(&mut self, name:&str, buf:Vec<u8>) {
+        self.modules.retain(|(module, _)| module != name);
+        self.modules.push((name.to_string(), buf));
+    }
+
+    // returns the time spent mapping, expanding and lowering each node since the last mapping started
+    pub fn profile(&self) -> Profiler {
+        self.profiler.clone()
+    }
+
+    // records what a pass of the pass manager cost in the profile
+    pub(crate) fn record_pass(&mut self, timing:PassTiming) {
+        self.profiler.record_pass(timing);
+    }
+
+    // counts the nodes alive in the mapper and the given tree, including every node's descendants
+    pub(crate) fn live_nodes(&self, tree:&HashMap<NodeId, Node>) -> usize {
+        self.nodes.values().chain(self.blocks.values()).chain(self.arena.values().map(|node| &**node)).chain(tree.values())
+            .map(Node::subtree_size)
+            .sum()
+    }
+
+    // lowers a node of the mapped tree with the mapper's observer, recording the time it takes
+    pub fn lower(&mut self, node:&mut Node) -> Constraint {
+        let started = Instant::now();
+
+        // a node whose operations can't be lowered is skipped, leaving its constraint empty
+        if let Some((location, category, message)) = node.lowering_problem(&node.to_ssa()) {
+            self.warn(node.get_id().0, Some(location), category, message);
+            return Constraint::new(node.get_id().0);
+        }
+        let constraint = node.lower_with_config(self.observer.as_mut(), self.lowering);
+        self.profiler.record_lowering(node.get_id().0, started.elapsed());
+
+        // terms too small for the annealer's precision are lost once the largest sets the scale
+        if let Some(config) = self.precision {
+            let report = precision::analyze(constraint.qubo(), &config);
+            if !report.flagged.is_empty() {
+                let message = format!("Node {} has terms beyond the annealer's dynamic range of {}: {}.", node.get_id(), config.dynamic_range, report);
+                self.warn(node.get_id().0, None, WarningCategory::Precision, message);
+            }
+        }
+        constraint
+    }
+
+    // returns the directives embedded in the last mapped module's hints section, if it has one
+    pub fn get_hints(&self) -> Option<Hints> {
+        self.hints.clone()
+    }
+
+    // returns the names and contents of the last mapped module's custom sections, in the order they appear
+    pub fn custom_sections(&self) -> Vec<(String, Vec<u8>)> {
+        self.custom_sections.clone()
+    }
+
+    // has the mapper record a checkpoint of the session, written to the given file when a module starts being
+    // mapped and then whenever the given number of decisions have been asked since it last was, so that an
+    // interrupted session can be resumed
+    #[cfg(feature = "std-io")]
+    pub fn set_checkpointing(&mut self, path:&str, interval:usize) {
+        self.record_checkpoint(Checkpoint::default(), path, interval);
+    }
+
+    // resumes the session checkpointed to the given file by mapping its module again, with the recorded
+    // answers given to the decisions the session had reached, and keeps checkpointing to the same file;
+    // the mapper should be configured as it was for the interrupted session
+    #[cfg(feature = "std-io")]
+    pub fn resume(&mut self, path:&str, interval:usize) -> io::Result<HashMap<NodeId, Node>> {
+        let checkpoint = Checkpoint::load(path)?;
+        let module = checkpoint.module.clone();
+        self.record_checkpoint(checkpoint, path, interval);
+        Ok(self.map(module))
+    }
+
+    // wraps the observer so that it records the session's checkpoint, replaying an earlier session's answers
+    #[cfg(feature = "std-io")]
+    fn record_checkpoint(&mut self, earlier:Checkpoint, path:&str, interval:usize) {
+        let inner = std::mem::replace(&mut self.observer, Box::new(SilentObserver));
+        let mut observer = CheckpointObserver::new(inner, earlier);
+        observer.set_file(path, interval);
+        self.checkpoint = Some(observer.session());
+        self.observer = Box::new(observer);
+    }
+
+    // returns the checkpoint of the session as it stands, if one is being recorded
+    pub fn get_checkpoint(&self) -> Option<Checkpoint> {
+        self.checkpoint.as_ref().map(|session| session.lock().unwrap().clone())
+    }
+
+    // returns the warnings raised by the last mapping and any expansion since
+    pub fn get_warnings(&self) -> Vec<MapperWarning> {
+        self.warnings.clone()
+    }
+
+    // returns the disagreements found between the spans and blocks recorded by the last mapping and its code
+    pub fn get_structure_diagnostics(&self) -> Vec<StructureDiagnostic> {
+        self.structure_diagnostics.clone()
+    }
+
+    // records a warning and passes it on to the observer
+    fn warn(&mut self, node:usize, location:Option<usize>, category:WarningCategory, message:String) {
+        self.observer.on_warning(&message);
+        self.warnings.push(MapperWarning { node, location, category, message });
+    }
+
+    // reports what a pass or the expansion of a node did to the observer
+    pub(crate) fn progress(&mut self, message:&str) {
+        self.observer.on_progress(message);
+    }
+
+    // maps the registered modules the last mapped module imports functions from, and those they import from in
+    // turn, adding their reachable functions to the given nodes under ids following every other function's;
+    // calls to imports resolving to linked functions are redirected to them, so expansion goes through their code
+    fn link(&mut self, nodes:&mut HashMap<NodeId, Node>) {
+        let names:HashSet<String> = self.modules.iter().map(|(name, _)| name.clone()).collect();
+        let mut pending:Vec<String> = self.imports.values().map(|(module, _)| module.clone()).filter(|module| names.contains(module)).collect();
+        pending.sort();
+        pending.reverse();
+
+        // each module is mapped on its own, without being expanded, and its reachable functions and blocks are
+        // numbered after those of the modules before it
+        let mut next = nodes.keys().map(|id| id.0).chain(self.imports.keys().cloned()).max().map_or(0, |index| index + 1);
+        let mut block_offset = self.blocks.keys().max().map_or(0, |index| index.0 + 1);
+        let mut linked:BTreeMap<String, LinkedModule> = BTreeMap::new();
+        while let Some(name) = pending.pop() {
+            if linked.contains_key(&name) {
+                continue;
+            }
+            let buf = self.modules.iter().find(|(module, _)| *module == name).unwrap().1.clone();
+            let mut mapper = Mapper::default();
+            mapper.set_observer(Box::new(LinkingObserver));
+            mapper.support = self.support.clone();
+            mapper.lane_shape = self.lane_shape;
+            mapper.bulk_unroll_limit = self.bulk_unroll_limit;
+            mapper.map(buf);
+            self.unsupported.append(&mut mapper.unsupported);
+
+            let mut functions:Vec<usize> = mapper.nodes.keys().map(|id| id.0).collect();
+            functions.sort();
+            let ids:HashMap<usize, usize> = functions.iter().enumerate().map(|(offset, index)| (*index, next + offset)).collect();
+            next += functions.len();
+            pending.extend(mapper.imports.values().map(|(module, _)| module.clone()).filter(|module| names.contains(module)));
+            let module = LinkedModule {
+                exports: mapper.exports.iter().map(|(index, field)| (field.clone(), *index)).collect(),
+                imports: mapper.imports.clone(),
+                ids,
+                nodes: mapper.nodes.clone(),
+                blocks: mapper.blocks.clone(),
+                block_offset
+            };
+            block_offset += mapper.blocks.keys().max().map_or(0, |index| index.0 + 1);
+            linked.insert(name, module);
+        }
+        if linked.is_empty() {
+            return;
+        }
+
+        // the mapped module's calls to resolved imports go to the linked functions instead
+        let mut resolved:HashMap<usize, usize> = HashMap::new();
+        let mut imports:Vec<(usize, (String, String))> = self.imports.iter().map(|(index, import)| (*index, import.clone())).collect();
+        imports.sort();
+        for (index, (module, field)) in imports {
+            match resolve_import(&linked, &module, &field) {
+                Some(id) => {
+                    resolved.insert(index, id);
+                    self.imports.remove(&index);
+                }
+                None if names.contains(&module) => {
+                    let message = format!("Module {} doesn't export a function {}, imported as function {}", module, field, index);
+                    self.warn(index, None, WarningCategory::UnresolvedImport, message);
+                }
+                None => ()
+            }
+        }
+        for (index, node) in nodes.iter_mut() {
+            node.renumber(&resolved, 0);
+            if let Some(node) = self.nodes.get_mut(index) {
+                node.renumber(&resolved, 0);
+            }
+        }
+        for block in self.blocks.values_mut() {
+            block.renumber(&resolved, 0);
+        }
+
+        // the linked modules' own imports resolve the same way, and those that don't are given ids of their own
+        for (name, module) in linked.iter() {
+            let mut callees = module.ids.clone();
+            let mut imports:Vec<(&usize, &(String, String))> = module.imports.iter().collect();
+            imports.sort();
+            for (index, (source, field)) in imports {
+                let id = match resolve_import(&linked, source, field) {
+                    Some(id) => id,
+                    None => {
+                        if names.contains(source) {
+                            let message = format!("Module {} doesn't export a function {}, imported by module {} as function {}", source, field, name, index);
+                            self.warn(next, None, WarningCategory::UnresolvedImport, message);
+                        }
+                        self.imports.insert(next, (source.clone(), field.clone()));
+                        next += 1;
+                        next - 1
+                    }
+                };
+                callees.insert(*index, id);
+            }
+            for (index, block) in module.blocks.iter() {
+                let mut block = block.clone();
+                block.renumber(&callees, module.block_offset);
+                self.blocks.insert(BlockId(index.0 + module.block_offset), block);
+            }
+            for (index, node) in module.nodes.iter() {
+                let id = module.ids[&index.0];
+                let mut node = node.clone();
+                node.renumber(&callees, module.block_offset);
+                node.set_id(NodeId(id));
+                self.linked.insert(id, (name.clone(), index.0));
+                self.nodes.insert(NodeId(id), node.clone());
+                nodes.insert(NodeId(id), node);
+            }
+        }
+
+        // every linked function's memory and globals are modelled as the mapped module's, which only holds if
+        // they are the same ones
+        let mut stateful:Vec<usize> = self.linked.keys().filter(|id| self.nodes[&NodeId(**id)].uses_module_state(&self.blocks)).cloned().collect();
+        stateful.sort();
+        for id in stateful {
+            let (module, index) = self.linked[&id].clone();
+            let message = format!("Function {} of module {}, linked as function {}, uses its own module's memory or globals", index, module, id);
+            self.warn(id, None, WarningCategory::LinkedState, message);
+        }
+    }
+
+    // passes each custom section with a registered handler to it and labels the nodes of the functions it
+    // annotates, warning about annotations of functions the module doesn't define
+    fn annotate(&mut self, nodes:&mut HashMap<NodeId, Node>) {
+        let mut annotations = Vec::new();
+        for (name, data) in self.custom_sections.iter() {
+            if let Some(handler) = self.section_handlers.get(name) {
+                annotations.extend(handler(data).into_iter().map(|(function, label)| (name.clone(), function, label)));
+            }
+        }
+        for (name, function, label) in annotations {
+            match nodes.get_mut(&NodeId(function)) {
+                Some(node) => {
+                    node.add_annotation(&label);
+                    self.nodes.insert(NodeId(function), node.clone());
+                }
+                None => {
+                    let message = format!("Section {} annotates function {} as {}, which the module doesn't define", name, function, label);
+                    self.warn(function, None, WarningCategory::Annotation, message);
+                }
+            }
+        }
+    }
+
+    // reads the last mapped module's hints section, if it has one, and narrows the hinted functions' values
+    fn read_hints(&mut self, nodes:&mut HashMap<NodeId, Node>) {
+        self.hints = None;
+        let data = match self.custom_sections.iter().find(|(name, _)| name == hints::SECTION_NAME) {
+            Some((_, data)) => data.clone(),
+            None => return
+        };
+        let hints = match Hints::decode(&data) {
+            Some(hints) => hints,
+            None => {
+                self.warn(0, None, WarningCategory::MalformedSection, format!("Ignoring the malformed {} section", hints::SECTION_NAME));
+                return;
+            }
+        };
+        for function in hints.functions() {
+            let bit_width = hints.get(function).bit_width;
+            if let Some(node) = nodes.get_mut(&NodeId(function)) {
+                node.set_bit_width(bit_width);
+                self.nodes.insert(NodeId(function), node.clone());
+            }
+        }
+        self.hints = Some(hints);
+    }
+
+    // returns the operator counts of every registered function added together, by category
+    pub fn module_histogram(&self) -> OperatorHistogram {
+        let mut histogram = OperatorHistogram::default();
+        for node in self.nodes.values().filter(|node| node.is_function()) {
+            histogram.merge(&node.operator_histogram_with(&self.support));
+        }
+        histogram
+    }
+
+    // returns the unsupported operators encountered by the last mapping
+    pub fn get_unsupported_operators(&self) -> Vec<UnsupportedOperator> {
+        self.unsupported.clone()
+    }
+
+    // returns a unique id so that a block can be normalized and introduced uniquely into the list of functions;
+    // ids are handed out in order from a counter, so they don't depend on which nodes are registered already
+    pub(crate) fn unique_block_id(&self) -> NodeId {
+        NodeId(self.next_block_id.fetch_add(1, Ordering::SeqCst))
+    }
+
+    // restarts the block ids past the given functions, the imports and any other function still registered,
+    // or at the configured offset if it is past them, dropping the nodes registered under the ids the last
+    // expansion gave out; expanding the same functions again then gives every block the same id
+    fn reset_block_ids(&mut self, functions:&HashMap<NodeId, Node>) {
+        let previous = self.first_block_id..*self.next_block_id.get_mut();
+        self.nodes.retain(|id, _| !previous.contains(&id.0) || functions.contains_key(id));
+        let first = functions.keys().chain(self.nodes.keys()).map(|id| id.0).chain(self.imports.keys().cloned()).max().map_or(0, |id| id + 1);
+        self.first_block_id = self.block_id_offset.map_or(first, |offset| offset.max(first));
+        *self.next_block_id.get_mut() = self.first_block_id;
+    }
+
+    // registers a block
+    fn add_block(&mut self, block:Node) -> BlockId {
+        let index = self.blocks().keys().max();
+        let mut insert_index = BlockId(0);
+        match index {
+            Some(index) => {
+                insert_index = BlockId(index.0 + 1);
+           }
+           _ => ()
+        }
+        self.blocks.insert(insert_index, block);
+        insert_index
+    }
+
+    // returns the registered functions, by id, without copying them
+    pub fn nodes(&self) -> &HashMap<NodeId, Node> {
+        &self.nodes
+    }
+
+    // returns the registered blocks, by id, without copying them
+    pub(crate) fn blocks(&self) -> &HashMap<BlockId, Node> {
+        &self.blocks
+    }
+
+    // returns a specific registered block
+    fn get_block(&self, index:BlockId) -> Node {
+        self.blocks[&index].clone()
+    }
+
+    // removes a registered block
+    fn remove_block(&mut self, index:BlockId) {
+        self.blocks.remove(&index);
+    }
+
+    // reads a WASM file; with the wat feature, .wat and .wast files and any file without the binary format's
+    // magic bytes are assembled from the text format first, a .wast file having to hold a single module
+    #[cfg(feature = "std-io")]
+    pub fn read_wasm(&mut self, file: &str) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut f = File::open(file)?;
+        f.read_to_end(&mut data)?;
+        #[cfg(feature = "wat")]
+        {
+            if file.ends_with(".wat") || file.ends_with(".wast") || !data.starts_with(b"\0asm") {
+                return self::wat::parse_bytes(&data).map(|wasm| wasm.into_owned()).map_err(|mut err| {
+                    err.set_path(file);
+                    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+                });
+            }
+        }
+        Ok(data)
+    }
+
+    // maps every file in a directory whose name matches a glob pattern, e.g. `*.wasm`, one after another in
+    // the order of their names, and reports on each module and on how they compare; the mapper keeps the state
+    // of the last one. A file that can't be read or mapped is reported as failed rather than ending the batch
+    #[cfg(feature = "std-io")]
+    pub fn map_directory(&mut self, path:&str, pattern:&str) -> io::Result<BatchReport> {
+        let mut files:Vec<String> = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let file = entry?.path();
+            let matched = file.file_name().and_then(|name| name.to_str()).is_some_and(|name| batch::glob_match(pattern, name));
+            if matched && file.is_file() {
+                files.push(file.to_string_lossy().into_owned());
+            }
+        }
+        files.sort();
+
+        let mut report = BatchReport { device_qubits: batch::DEVICE_QUBITS, modules: Vec::new() };
+        for file in files {
+            let buf = match self.read_wasm(&file) {
+                Ok(buf) => buf,
+                Err(err) => {
+                    report.modules.push(ModuleReport::failed(&file, err.to_string()));
+                    continue;
+                }
+            };
+            let module = match self.try_map(buf) {
+                Ok(nodes) => ModuleReport {
+                    path: file.clone(),
+                    functions: nodes.len(),
+                    unsupported: self.unsupported.len(),
+                    warnings: self.warnings.len(),
+                    speedup: self.speedup_estimate(),
+                    annealability: self.annealability_report(report.device_qubits),
+                    error: self.error.map(|err| err.to_string())
+                },
+                Err(err) => ModuleReport { unsupported: err.operators.len(), ..ModuleReport::failed(&file, err.to_string()) }
+            };
+            report.modules.push(module);
+        }
+        Ok(report)
+    }
+
+    // extracts the node indeces from a flat tree of nodes
+    fn get_indices(&self, tree:&HashMap<NodeId, Node>) -> Vec<usize> {
+        let mut indices:Vec<usize> = Vec::new();
+        for key in tree.keys() {
+            indices.push(key.0);
+        }
+        indices
+    }
+
+    // prints a flat tree of nodes, highlighting the functions on the critical path if the mapper is configured to
+    pub fn print_tree(&self, nodes:HashMap<NodeId, Node>) {
+        if !self.critical_path_highlighting {
+            let indices = self.get_indices(&nodes);
+            print!("{}", fmt(&indices));
+            return;
+        }
+        let stream = StandardStream::stdout(console::color_choice());
+        let mut stdout = stream.lock();
+        self.write_tree(&nodes, &mut ConsolePrinter::new(&mut stdout, self.theme.clone()));
+    }
+
+    // writes a flat tree of nodes, highlighting the functions on the critical path and following the tree with its
+    // length and functions if the mapper is configured to
+    pub(crate) fn write_tree(&self, nodes:&HashMap<NodeId, Node>, printer:&mut ConsolePrinter) {
+        let indices = self.get_indices(nodes);
+        let tree = fmt(&indices);
+        if !self.critical_path_highlighting {
+            printer.print(&tree);
+            return;
+        }
+
+        // each line of the tree names at most one node, between the lines joining it to the others
+        let critical = self.critical_chain();
+        for line in tree.lines() {
+            let start = line.find(|c:char| c.is_ascii_digit()).unwrap_or(line.len());
+            let end = line[start..].find(|c:char| !c.is_ascii_digit()).map_or(line.len(), |length| start + length);
+            printer.highlight(Highlight::Plain);
+            printer.print(&line[..start]);
+            if line[start..end].parse().is_ok_and(|index:usize| critical.1.contains(&index)) {
+                printer.highlight(Highlight::Critical);
+            }
+            printer.print(&line[start..end]);
+            printer.highlight(Highlight::Plain);
+            printer.println(&line[end..]);
+        }
+        printer.println(&format!("Critical path of length {} through functions:", critical.0.critical_path));
+        printer.println(&format!("{:?}", critical.1));
+    }
+
+    // formats a registered node's instruction bytes next to the operators they decode to and their offsets
+    // in the source WASM file, so that a node's recorded span can be checked against what it contains
+    pub fn hexdump(&self, index:NodeId) -> Option<String> {
+        let node = self.nodes.get(&index)?;
+        let span = node.source_span();
+        let mut dump = format!("Node {} spans {:#010x}..{:#010x} ({} bytes)\n", index, span.start, span.end, node.instrs.len());
+        let mut reader = BinaryReader::new_with_offset(&node.instrs, span.start);
+
+        // a function's instructions are preceded by its locals declarations
+        if node.is_function() {
+            let locals = reader.read_var_u32().and_then(|count| {
+                for _ in 0..count {
+                    reader.read_var_u32()?;
+                    reader.read_type()?;
+                }
+                Ok(count)
+            });
+            let end = reader.original_position();
+            match locals {
+                Ok(count) => dump.push_str(&hexdump_line(span.start, &node.instrs[..end - span.start], &format!("{} locals declarations", count))),
+                Err(err) => dump.push_str(&format!("Error: {}\n", err.message))
+            }
+        }
+
+        while !reader.eof() {
+            let position = reader.original_position();
+            match reader.read_operator() {
+                Ok(op) => {
+                    let end = reader.original_position();
+                    dump.push_str(&hexdump_line(position, &node.instrs[position - span.start..end - span.start], &format!("{:?}", op)));
+                }
+                Err(err) => {
+
+                    // the remaining bytes can't be decoded, but are still shown for inspection
+                    dump.push_str(&format!("Error: {} at {:#010x}\n", err.message, err.offset));
+                    for (row, bytes) in node.instrs[position - span.start..].chunks(8).enumerate() {
+                        dump.push_str(&hexdump_line(position + row * 8, bytes, ""));
+                    }
+                    break;
+                }
+            }
+        }
+        Some(dump)
+    }
+
+    // returns the registered functions called from the given node, including calls made from its nested blocks
+    fn get_callees(&self, node:&Node) -> Vec<usize> {
+        let mut callees:Vec<usize> = Vec::new();
+        for &NodeId(index) in node.calls().values() {
+            if self.nodes.contains_key(&NodeId(index)) && !callees.contains(&index) {
+                callees.push(index);
+            }
+        }
+        for block_index in node.blocks().values() {
+            if let Some(block) = self.blocks.get(block_index) {
+                for index in self.get_callees(block) {
+                    if !callees.contains(&index) {
+                        callees.push(index);
+                    }
+                }
+            }
+        }
+        callees.sort();
+        callees
+    }
+
+    // returns the direct callees and the types of the indirect calls of a node, with one entry per call
+    // site, including those in its nested blocks
+    fn call_sites(&self, node:&Node, callees:&mut Vec<usize>, types:&mut Vec<usize>) {
+        callees.extend(node.calls.values().map(|id| id.0));
+        types.extend(node.indirect_calls.values());
+        for block_index in node.blocks.values() {
+            if let Some(block) = self.blocks.get(block_index) {
+                self.call_sites(block, callees, types);
+            }
+        }
+    }
+
+    // freezes the results of the last mapping for the tree it returned, so that they can be read, and shared across
+    // threads, whatever the mapper does next
+    pub fn finish(&self, tree:&HashMap<NodeId, Node>) -> Arc<AnalysisSnapshot> {
+        Arc::new(AnalysisSnapshot::new(self, tree))
+    }
+
+    // returns the calls between the functions of the last mapped module as discovered by its first pass,
+    // before any function was pruned, inlined or copied into its callers
+    pub fn call_graph(&self) -> CallGraph {
+        self.call_graph.clone()
+    }
+
+    // returns the groups of mutually recursive functions in the call graph; these reference loops
+    // can't be unrolled at compile time, so they block simulation of every function in the group
+    pub fn recursion_groups(&self) -> Vec<Vec<usize>> {
+        let mut indices:Vec<usize> = self.nodes.keys().map(|id| id.0).collect();
+        indices.sort();
+
+        // the call graph is built once so that the search doesn't revisit nested blocks
+        let mut graph:HashMap<usize, Vec<usize>> = HashMap::new();
+        for index in indices.iter() {
+            graph.insert(*index, self.get_callees(&self.nodes[&NodeId(*index)]));
+        }
+
+        // Tarjan's algorithm finds every strongly connected component of the call graph
+        let mut search = ComponentSearch::default();
+        for index in indices {
+            if !search.indices.contains_key(&index) {
+                self.strong_connect_helper(index, &graph, &mut search);
+            }
+        }
+
+        // only components with more than one function, or a single function calling itself, are recursive
+        let mut groups:Vec<Vec<usize>> = Vec::new();
+        for mut component in search.components {
+            let first = component[0];
+            if component.len() > 1 || graph[&first].contains(&first) {
+                component.sort();
+                groups.push(component);
+            }
+        }
+        groups.sort();
+        groups
+    }
+
+    // returns the exported functions' indices mapped to their export names
+    pub fn get_exports(&self) -> HashMap<usize, String> {
+        self.exports.clone()
+    }
+
+    // returns the name of a function, as the module's name section gives it or failing that as it is exported
+    pub fn get_function_name(&self, index:usize) -> Option<String> {
+        self.function_names.get(&index).or_else(|| self.exports.get(&index)).cloned()
+    }
+
+    // returns the names of the functions of the last mapped module, by index, as get_function_name gives them
+    pub fn get_function_names(&self) -> HashMap<usize, String> {
+        let mut names = self.exports.clone();
+        names.extend(self.function_names.iter().map(|(index, name)| (*index, name.clone())));
+        names
+    }
+
+    // maps only the functions whose names match the given regular expression, anywhere in the name unless it
+    // is anchored, along with the functions they call, which are expanded as their callees but not returned
+    // on their own; functions without a name don't match. None maps every function again
+    pub fn set_include_filter(&mut self, pattern:Option<&str>) -> Result<(), regex::Error> {
+        self.include = pattern.map(Regex::new).transpose()?;
+        Ok(())
+    }
+
+    // leaves the functions whose names match the given regular expression out of mapping, like the functions
+    // an include filter doesn't match; None leaves none out again
+    pub fn set_exclude_filter(&mut self, pattern:Option<&str>) -> Result<(), regex::Error> {
+        self.exclude = pattern.map(Regex::new).transpose()?;
+        Ok(())
+    }
+
+    // checks whether the function filters select the function with the given index
+    fn selects(&self, index:usize) -> bool {
+        let name = self.get_function_name(index);
+        let matches = |filter:&Option<Regex>| filter.as_ref().map(|regex| name.as_ref().is_some_and(|name| regex.is_match(name)));
+        matches(&self.include).unwrap_or(true) && !matches(&self.exclude).unwrap_or(false)
+    }
+
+    // returns the ids of the imported functions no registered module provides, mapped to the module and field
+    // they name
+    pub fn get_imports(&self) -> HashMap<usize, (String, String)> {
+        self.imports.clone()
+    }
+
+    // returns the ids of the functions linked in from registered modules, mapped to their module and their
+    // index there
+    pub fn get_linked_functions(&self) -> HashMap<usize, (String, usize)> {
+        self.linked.clone()
+    }
+
+    // returns the module's start function, if it has one
+    pub fn get_start(&self) -> Option<usize> {
+        self.start
+    }
+
+    // checks whether the last mapped module's memory, defined or imported, is shared between threads
+    pub fn has_shared_memory(&self) -> bool {
+        self.shared_memory
+    }
+
+    // checks whether the last mapped module's memory is imported or exported, so that the host can grow it
+    pub fn has_external_memory(&self) -> bool {
+        self.external_memory
+    }
+
+    // returns the declared limits of the last mapped module's memory, in pages, if it has one
+    pub fn get_memory_limits(&self) -> Option<ResizableLimits> {
+        self.memory_limits
+    }
+
+    // checks whether any registered node or block grows the memory, which makes its size only known when
+    // running
+    pub fn grows_memory(&self) -> bool {
+        self.nodes.values().chain(self.blocks.values()).any(|node| node.grows_memory())
+    }
+
+    // models the reads of the memory size in every registered node and block; a memory of the module's own that
+    // never grows keeps its declared initial size, which becomes a constant, while the size of one that does, or
+    // that is imported or exported and so can be grown by the host, becomes an input variable
+    pub fn model_memory_size(&mut self) {
+        let pages = match self.grows_memory() || self.external_memory {
+            true => None,
+            false => self.memory_limits.map(|limits| limits.initial as usize)
+        };
+        for node in self.nodes.values_mut().chain(self.blocks.values_mut()) {
+            node.model_memory_size(pages);
+        }
+    }
+
+    // bounds the loads of every registered node and block by the data segments, unless reachable code writes to
+    // the memory, and gives the input variables the user gave ranges to those ranges; the ranges are given by
+    // node id, so they don't apply to the registered blocks, which have ids of their own
+    pub fn bound_inputs(&mut self) {
+        let written = self.nodes.values().chain(self.blocks.values()).any(|node| node.writes_memory());
+        if let Some(segments) = self.data_segments.as_ref().filter(|_| !written) {
+            for node in self.nodes.values_mut().chain(self.blocks.values_mut()) {
+                for (var_id, width) in node.input_widths.clone() {
+                    node.set_input_range(var_id, Some(ranges::load_range(segments, width.bits / 8)));
+                }
+            }
+        }
+        for (&(id, var_id), range) in self.input_ranges.iter() {
+            if let Some(node) = self.nodes.get_mut(&NodeId(id)) {
+                node.set_input_range(var_id, Some(*range));
+            }
+        }
+    }
+
+    // returns the registered nodes whose memory accesses, or those of their nested blocks, may depend on the
+    // memory's size when running, in order
+    pub fn dynamic_memory_nodes(&self) -> Vec<usize> {
+        let mut dynamic:Vec<usize> = self.nodes.iter()
+            .filter(|&(_, node)| node.depends_on_memory_size_within(&self.blocks))
+            .map(|(index, _)| index.0)
+            .collect();
+        dynamic.sort();
+        dynamic
+    }
+
+    // returns the registered nodes whose non-atomic memory accesses, including those of their nested
+    // blocks, can race with other threads, mapped to the racing accesses in order; there are none unless
+    // the memory is shared
+    pub fn racing_nodes(&self) -> HashMap<usize, Vec<usize>> {
+        let mut racing:HashMap<usize, Vec<usize>> = HashMap::new();
+        if self.shared_memory {
+            for site in self.hazard_report().races() {
+                racing.entry(site.node).or_default().push(site.offset);
+            }
+        }
+        racing
+    }
+
+    // removes the registered nodes that can't be reached from the given root functions, or from the
+    // module's exports and start function when no roots are given, and strips the dead code after
+    // unconditional terminators from every remaining node; returns the ids of the removed nodes
+    pub fn prune_unreachable(&mut self, roots:Vec<usize>) -> Vec<usize> {
+        let mut roots = roots;
+        if roots.is_empty() {
+            roots = self.exports.keys().cloned().collect();
+            if let Some(start) = self.start {
+                roots.push(start);
+            }
+        }
+
+        // code after an unreachable or return never executes, so its calls don't keep callees alive
+        for node in self.nodes.values_mut() {
+            node.remove_dead_code();
+        }
+        for block in self.blocks.values_mut() {
+            block.remove_dead_code();
+        }
+
+        // without any roots every function has to be assumed live
+        let mut removed:Vec<usize> = Vec::new();
+        if !roots.is_empty() {
+
+            // walk the call graph outwards from the roots
+            let mut reachable:HashSet<usize> = HashSet::new();
+            while let Some(index) = roots.pop() {
+                if reachable.contains(&index) || !self.nodes.contains_key(&NodeId(index)) {
+                    continue;
+                }
+                reachable.insert(index);
+                roots.extend(self.get_callees(&self.nodes[&NodeId(index)]));
+            }
+
+            for index in self.nodes.keys() {
+                if !reachable.contains(&index.0) {
+                    removed.push(index.0);
+                }
+            }
+            for index in removed.iter() {
+                self.nodes.remove(&NodeId(*index));
+            }
+            removed.sort();
+        }
+
+        // blocks only referenced from removed nodes or dead code are removed as well
+        let mut live_blocks:HashSet<BlockId> = HashSet::new();
+        let mut pending:Vec<BlockId> = Vec::new();
+        for node in self.nodes.values() {
+            pending.extend(node.blocks.values());
+        }
+        while let Some(index) = pending.pop() {
+            if live_blocks.insert(index) {
+                if let Some(block) = self.blocks.get(&index) {
+                    pending.extend(block.blocks.values());
+                }
+            }
+        }
+        let dead_blocks:Vec<BlockId> = self.blocks.keys().filter(|index| !live_blocks.contains(index)).cloned().collect();
+        for index in dead_blocks {
+            self.remove_block(index);
+        }
+
+        removed
+    }
+
+    // enables or disables sharing the results of repeated operations when lowering is planned
+    pub fn set_common_subexpression_elimination(&mut self, enabled:bool) {
+        self.common_subexpression_elimination = enabled;
+    }
+
+    // selects how nodes are synthesized from gadgets when they are lowered and when lowering is planned
+    pub fn set_lowering_config(&mut self, config:LoweringConfig) {
+        self.lowering = config;
+    }
+
+    // checks the coefficients of every QUBO lowered against an annealer's precision, warning about the terms
+    // beyond its dynamic range, or stops checking them
+    pub fn set_precision_check(&mut self, config:Option<PrecisionConfig>) {
+        self.precision = config;
+    }
+
+    // returns how nodes are synthesized from gadgets when they are lowered
+    pub fn get_lowering_config(&self) -> LoweringConfig {
+        self.lowering
+    }
+
+    // selects how a registered node's integer multiplications are encoded, overriding the lowering config unless
+    // unset; returns whether the node is registered
+    pub fn set_multiplier_synthesis(&mut self, node:NodeId, multiplier:Option<MultiplierSynthesis>) -> bool {
+        match self.nodes.get_mut(&node) {
+            Some(node) => {
+                node.set_multiplier_synthesis(multiplier);
+                true
+            }
+            None => false
+        }
+    }
+
+    // marks what lowering does with an input variable of a registered node; returns whether the node exists
+    pub fn mark_input(&mut self, node:NodeId, var_id:usize, marking:InputMarking) -> bool {
+        match self.nodes.get_mut(&node) {
+            Some(node) => {
+                node.mark_input(var_id, marking);
+                true
+            }
+            None => false
+        }
+    }
+
+    // pins an output of a registered node to a value when it is lowered; returns whether the node exists
+    pub fn constrain_output(&mut self, node:NodeId, output:NodeOutput, value:u64) -> bool {
+        match self.nodes.get_mut(&node) {
+            Some(node) => {
+                node.constrain_output(output, value);
+                true
+            }
+            None => false
+        }
+    }
+
+    // plans the lowering of every registered node, sharing the results of repeated operations first
+    // unless that has been disabled, and grouping additions into adder trees if the lowering config does;
+    // multiplications are costed with the strategy each node selects, or else the lowering config
+    pub fn plan_lowering(&mut self) -> Vec<LoweringPlan> {
+        let mut indices:Vec<usize> = self.nodes.keys().map(|id| id.0).collect();
+        indices.sort();
+
+        let mut plans:Vec<LoweringPlan> = Vec::new();
+        for index in indices {
+            let enabled = self.common_subexpression_elimination;
+            let carry_save = self.lowering.adders == AdderSynthesis::CarrySave;
+            let multipliers = self.lowering.multipliers;
+            let node = self.nodes.get_mut(&NodeId(index)).unwrap();
+            let qubits = node.auxiliary_qubits();
+            let shared = if enabled { node.eliminate_common_subexpressions() } else { 0 };
+            let remaining = node.auxiliary_qubits();
+
+            // a tree of additions takes the variables of counting its columns instead of a pair of bits per
+            // addition and bit
+            let trees = if carry_save { node.adder_trees() } else { Vec::new() };
+            let adder_savings:usize = trees.iter()
+                .map(|&(operands, width)| (2 * width * (operands - 1)).saturating_sub(gadgets::carry_save_variables(operands, width)))
+                .sum();
+            let multiplier = node.multiplier.unwrap_or(multipliers);
+            plans.push(LoweringPlan {
+                id: index,
+                operations: node.operations.len(),
+                auxiliary_qubits: remaining - adder_savings.min(remaining),
+                shared_operations: shared,
+                qubit_savings: qubits - remaining,
+                adder_trees: trees.len(),
+                adder_savings,
+                multipliers: node.multiplier_costs(&[multiplier])[0]
+            });
+        }
+        plans
+    }
+
+    // reports which registered functions, blocks and branches the given trace of a run exercised
+    pub fn coverage(&self, trace:&[ProgramPoint]) -> CoverageReport {
+        let functions = self.nodes.iter().filter(|(_, node)| node.is_function()).map(|(index, node)| (index.0, node));
+        coverage::measure(functions, self.blocks.iter().map(|(id, node)| (*id, node)), trace)
+    }
+
+    // orders the memory accesses of every registered node, including those of its nested blocks, and
+    // reports the read-after-write, write-after-read and write-after-write hazards within and across
+    // them; nodes are ordered by where they start in the WASM file
+    pub fn hazard_report(&self) -> HazardReport {
+        let mut indices:Vec<usize> = self.nodes.keys().map(|id| id.0).collect();
+        indices.sort_by_key(|index| (self.nodes[&NodeId(*index)].start, *index));
+
+        let timelines = indices.iter()
+            .map(|index| (*index, self.nodes[&NodeId(*index)].memory_timeline(&self.blocks)))
+            .collect();
+        HazardReport::new(timelines)
+    }
+
+    // ranks the registered functions by how feasible annealing them is on a device with the given number of
+    // qubits, most feasible first; each function is lowered as the lowering config selects, leaving the nodes
+    // as they are, and its terms are checked against the precision config, or the default one, and its score
+    // weighs the share of its operators that are simulated by the share of the device its QUBO leaves free and
+    // the share of its terms within the dynamic range, so functions with nothing to lower score nothing
+    pub fn annealability_report(&self, device_qubits:usize) -> Vec<Annealability> {
+        let precision = self.precision.unwrap_or_default();
+        let mut report:Vec<Annealability> = self.nodes.values().filter(|node| node.is_function()).map(|node| {
+            let histogram = node.operator_histogram_with(&self.support);
+            let simulatable = if histogram.total() == 0 { 0.0 } else { histogram.arithmetic as f64 / histogram.total() as f64 };
+            let constraint = node.clone().lower_with_config(&mut SilentObserver, self.lowering);
+            let coefficients = precision::analyze(constraint.qubo(), &precision);
+            let qubits = constraint.qubo().variables().len();
+            let couplers = constraint.qubo().quadratic().len();
+            let terms = coefficients.linear.count + coefficients.quadratic.count;
+            let kept = if terms == 0 { 1.0 } else { 1.0 - coefficients.flagged.len() as f64 / terms as f64 };
+            let free = if qubits == 0 { 0.0 } else { device_qubits.saturating_sub(qubits) as f64 / device_qubits.max(1) as f64 };
+            Annealability {
+                id: node.get_id().0,
+                histogram,
+                simulatable,
+                auxiliary_qubits: node.auxiliary_qubits(),
+                qubits,
+                couplers,
+                dynamic_range: coefficients.dynamic_range,
+                flagged: coefficients.flagged.len(),
+                score: simulatable * free * kept
+            }
+        }).collect();
+        report.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal).then(a.id.cmp(&b.id)));
+        report
+    }
+
+    // estimates how much parallelization can speed the module up, by comparing the total work of its
+    // functions with the critical path through the dependency graph in which callers wait for their
+    // callees and later functions wait for the memory accesses of earlier ones they conflict with;
+    // groups of functions depending on each other are collapsed and run one after the other
+    pub fn speedup_estimate(&self) -> SpeedupEstimate {
+        self.critical_chain().0
+    }
+
+    // returns the functions on the critical path of the speedup estimate, in the order they run; the
+    // members of a group of functions depending on each other are listed together, by index
+    pub fn critical_path(&self) -> Vec<usize> {
+        self.critical_chain().1
+    }
+
+    // estimates the speedup, along with the functions on its critical path
+    fn critical_chain(&self) -> (SpeedupEstimate, Vec<usize>) {
+
+        // nested blocks are part of their function's SSA form, so separated blocks would count twice
+        let mut indices:Vec<usize> = self.nodes.iter().filter(|(_, node)| node.is_function()).map(|(id, _)| id.0).collect();
+        indices.sort();
+
+        let mut costs:HashMap<usize, (usize, usize)> = HashMap::new();
+        let mut graph:HashMap<usize, Vec<usize>> = HashMap::new();
+        for index in indices.iter() {
+            costs.insert(*index, self.weights.costs(&self.emit_ssa(NodeId(*index)).unwrap()));
+            let callees = self.get_callees(&self.nodes[&NodeId(*index)]).into_iter().filter(|callee| indices.contains(callee));
+            graph.insert(*index, callees.collect());
+        }
+        for hazard in self.hazard_report().hazards {
+            if !hazard.is_internal() && graph.contains_key(&hazard.earlier.node) {
+                if let Some(dependencies) = graph.get_mut(&hazard.later.node) {
+                    dependencies.push(hazard.earlier.node);
+                }
+            }
+        }
+
+        // Tarjan's algorithm finds each component only after every component it depends on
+        let mut search = ComponentSearch::default();
+        for index in indices.iter() {
+            if !search.indices.contains_key(index) {
+                self.strong_connect_helper(*index, &graph, &mut search);
+            }
+        }
+
+        // each component's path continues the longest of its dependencies', the one of the lowest index if several are
+        let mut work = 0;
+        let mut critical_path = 0;
+        let mut paths:HashMap<usize, usize> = HashMap::new();
+        let mut components:HashMap<usize, usize> = HashMap::new();
+        let mut previous:Vec<Option<usize>> = Vec::new();
+        let mut last = None;
+        for (position, component) in search.components.iter().enumerate() {
+            let span = if component.len() > 1 {
+                component.iter().map(|index| costs[index].0).sum()
+            } else {
+                costs[&component[0]].1
+            };
+            let longest = component.iter()
+                .flat_map(|index| graph[index].iter())
+                .filter(|dependency| !component.contains(dependency))
+                .map(|dependency| (paths[dependency], *dependency))
+                .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+            let path = longest.map_or(0, |(path, _)| path) + span;
+            previous.push(longest.map(|(_, dependency)| components[&dependency]));
+            for index in component.iter() {
+                work += costs[index].0;
+                paths.insert(*index, path);
+                components.insert(*index, position);
+            }
+            if last.is_none() || path > critical_path {
+                critical_path = path;
+                last = Some(position);
+            }
+        }
+
+        // the critical path is followed back from the component it ends in
+        let mut chain:Vec<usize> = Vec::new();
+        while let Some(position) = last {
+            let mut members = search.components[position].clone();
+            members.sort_by(|a, b| b.cmp(a));
+            chain.extend(members);
+            last = previous[position];
+        }
+        chain.reverse();
+        (SpeedupEstimate { work, critical_path }, chain)
+    }
+
+    // checks the operations of every registered node against the abstract stack, returning the type errors
+    // found ordered by node id; errors are attributed to the id each node is registered under
+    pub fn type_check(&self) -> Vec<TypeError> {
+        let mut indices:Vec<usize> = self.nodes.keys().map(|id| id.0).collect();
+        indices.sort();
+
+        let mut errors:Vec<TypeError> = Vec::new();
+        for index in indices {
+            for mut error in typecheck::check_node(&self.nodes[&NodeId(index)]) {
+                error.node = index;
+                errors.push(error);
+            }
+        }
+        errors
+    }
+
+    // runs the original code and the recorded operations of every registered function on the given number
+    // of random inputs, generated from the seed, and compares the values they produce
+    pub fn validate_roundtrip(&self, trials:usize, seed:u64) -> ValidationReport {
+        let mut indices:Vec<usize> = self.nodes.iter().filter(|(_, node)| node.is_function()).map(|(id, _)| id.0).collect();
+        indices.sort();
+
+        let mut report = ValidationReport::default();
+        for index in indices {
+            let (comparisons, mismatch) = validation::validate_node(&self.nodes[&NodeId(index)], trials, seed);
+            report.nodes.push(index);
+            report.comparisons += comparisons;
+            report.mismatches.extend(mismatch);
+        }
+        report
+    }
+
+    // returns the SSA form of a registered node, including its nested blocks with phi nodes where
+    // the branches of an if join
+    pub fn emit_ssa(&self, index:NodeId) -> Option<ssa::Graph> {
+        match self.nodes.get(&index) {
+            Some(node) => {
+                let mut graph = ssa::Graph::new(index.0);
+                let (results, _) = node.emit_ssa_helper(&self.blocks, &mut graph, 0);
+                graph.set_results(results);
+                Some(graph)
+            }
+            None => None
+        }
+    }
+
+    // predicates the simple if/else diamonds nested anywhere in a registered node, dropping the merged else
+    // clauses from the registered blocks; returns the number of diamonds predicated
+    pub fn predicate(&mut self, index:NodeId) -> usize {
+        let mut pending:Vec<BlockId> = match self.nodes.get(&index) {
+            Some(node) => node.blocks.values().cloned().collect(),
+            None => return 0
+        };
+        pending.sort();
+
+        let mut predicated = 0;
+        while let Some(block_id) = pending.pop() {
+            let mut block = match self.blocks.get(&block_id) {
+                Some(block) => block.clone(),
+                None => continue
+            };
+            pending.extend(block.blocks.values());
+
+            let else_id = match block.blocks.values().next() {
+                Some(else_id) if block.blocks.len() == 1 => *else_id,
+                _ => continue
+            };
+            let else_clause = match self.blocks.get(&else_id) {
+                Some(else_clause) => else_clause.clone(),
+                None => continue
+            };
+            if block.predicate(&else_clause) {
+                self.blocks.insert(block_id, block);
+                self.remove_block(else_id);
+                predicated += 1;
+            }
+        }
+        predicated
+    }
+
+    // replaces the calls in every registered node and block to functions with at most the given number of
+    // straight line operations with copies of those operations; returns the number of calls inlined
+    pub fn inline(&mut self, threshold:usize) -> usize {
+        let callees:HashMap<NodeId, Node> = self.nodes.iter()
+            .filter(|&(_, node)| matches!(node.inlinable_operations(), Some(ref operations) if operations.len() <= threshold))
+            .map(|(index, node)| (*index, node.clone()))
+            .collect();
+
+        let mut inlined = 0;
+        for node in self.nodes.values_mut().chain(self.blocks.values_mut()) {
+
+            // later calls go first, so that moving the code after a call doesn't move the ones still pending
+            let mut calls:Vec<(usize, NodeId)> = node.calls.iter().map(|(location, index)| (*location, *index)).collect();
+            calls.sort();
+            for (location, index) in calls.into_iter().rev() {
+                match callees.get(&index) {
+                    Some(callee) if index != node.id && node.inline_call(location, callee) => inlined += 1,
+                    _ => ()
+                }
+            }
+        }
+        inlined
+    }
+
+    // reduces the strength of the divisions by constants in every registered node and block, returning the
+    // number of operations rewritten
+    pub fn reduce_division_strength(&mut self) -> usize {
+        let mut reduced = 0;
+        for node in self.nodes.values_mut() {
+            reduced += node.reduce_division_strength();
+        }
+        for block in self.blocks.values_mut() {
+            reduced += block.reduce_division_strength();
+        }
+        reduced
+    }
+
+    // folds the integer arithmetic on known constants in every registered node and block, returning
+    // the number of operations folded away
+    pub fn fold_constants(&mut self) -> usize {
+        let mut folded = 0;
+        for node in self.nodes.values_mut() {
+            folded += node.fold_constants();
+        }
+        for block in self.blocks.values_mut() {
+            folded += block.fold_constants();
+        }
+        folded
+    }
+
+    // shares the results of repeated operations in every registered node and block, returning the
+    // number of operations whose results are now shared
+    pub fn eliminate_common_subexpressions(&mut self) -> usize {
+        let mut shared = 0;
+        for node in self.nodes.values_mut() {
+            shared += node.eliminate_common_subexpressions();
+        }
+        for block in self.blocks.values_mut() {
+            shared += block.eliminate_common_subexpressions();
+        }
+        shared
+    }
+
+    // recursively visits the callees of a function while tracking the lowest reachable search index
+    fn strong_connect_helper(&self, index:usize, graph:&HashMap<usize, Vec<usize>>, search:&mut ComponentSearch) {
+        let search_index = search.counter;
+        search.indices.insert(index, search_index);
+        search.low_links.insert(index, search_index);
+        search.counter += 1;
+        search.stack.push(index);
+
+        for callee in graph[&index].iter() {
+            if !search.indices.contains_key(callee) {
+                self.strong_connect_helper(*callee, graph, search);
+                let low_link = search.low_links[&index].min(search.low_links[callee]);
+                search.low_links.insert(index, low_link);
+            } else if search.stack.contains(callee) {
+                let low_link = search.low_links[&index].min(search.indices[callee]);
+                search.low_links.insert(index, low_link);
+            }
+        }
+
+        // the function is the root of a component, so the component can be popped off the stack
+        if search.low_links[&index] == search.indices[&index] {
+            let mut component:Vec<usize> = Vec::new();
+            while let Some(member) = search.stack.pop() {
+                component.push(member);
+                if member == index {
+                    break;
+                }
+            }
+            search.components.push(component);
+        }
+    }
+
+
+    // Associates a function's type signature with its corresponding node
+    fn attach_signature(&mut self, resources:&WasmModuleResources, mut node:Node, func_count:usize, func_types:Vec<u32>) -> Node {
+
+        // the function's type signature can be assigned after the node has been created
+        let func_signature = resources.types()[func_types[func_count - 1] as usize].clone();
+        let params = func_signature.params;
+        let rets = func_signature.returns;
+        let mut param = 0;
+        let mut ret = 0;
+
+        // the parser's resources object contains info about each function's params
+        while param < params.len() {
+            match params[param] {
+                Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::V128 => {
+                    node.add_param(params[param]);
+                }
+                ty => {
+                    self.warn(node.get_id().0, None, WarningCategory::UnknownType, format!("Encountered unknown parameter type {:?} in function {}", ty, node.get_id()));
+                    break;
+                }
+            }
+            param += 1;
+        }
+
+        // the parser's resources object contains info about each function's outputs
+        while ret < rets.len() {
+            match rets[ret] {
+                Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::V128 => {
+                    node.add_output_variable(rets[ret]);
+                }
+                ty => {
+                    self.warn(node.get_id().0, None, WarningCategory::UnknownType, format!("Encountered unknown result type {:?} in function {}", ty, node.get_id()));
+                    break;
+                }
+            }
+            ret += 1;
+        }
+        node.clone()
+    }
+
+
+    // Registers the locals a function body declares after its parameters
+    fn attach_locals(&mut self, body:FunctionBody, mut node:Node) -> Node {
+        let reader = match body.get_locals_reader() {
+            Ok(reader) => reader,
+            Err(err) => {
+                self.warn(node.get_id().0, None, WarningCategory::UnreadableLocals, format!("Can't read the locals of function {}: {:?}", node.get_id(), err));
+                return node;
+            }
+        };
+
+        // each declaration covers a run of locals of the same type
+        for declaration in reader {
+            match declaration {
+                Ok((count, ty)) => {
+                    for _ in 0..count {
+                        node.add_local(ty);
+                    }
+                }
+                Err(err) => {
+                    self.warn(node.get_id().0, None, WarningCategory::UnreadableLocals, format!("Can't read the locals of function {}: {:?}", node.get_id(), err));
+                    break;
+                }
+            }
+        }
+        node
+    }
+
+
+    // entry point to the mapping functionality of the mapper; a strict mapper panics with the unsupported
+    // operators it encountered, which try_map returns instead
+    pub fn map(&mut self, buf:Vec<u8>) -> HashMap<NodeId, Node> {
+        match self.try_map(buf) {
+            Ok(nodes) => nodes,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    // maps the given WASM as far as it is valid, returning the nodes mapped up to the first error along with
+    // the error; a strict mapper meeting unsupported operators returns the functions it read, unexpanded,
+    // along with the operators instead of panicking
+    pub fn map_partial(&mut self, buf:Vec<u8>) -> PartialResult {
+        match self.try_map(buf) {
+            Ok(nodes) => PartialResult { nodes, error: self.error, unsupported: None },
+            Err(err) => PartialResult { nodes: self.nodes.clone(), error: self.error, unsupported: Some(err) }
+        }
+    }
+
+    // returns the first error in the last mapped module, if it was invalid
+    pub fn get_error(&self) -> Option<BinaryReaderError> {
+        self.error
+    }
+
+    // maps the given WASM, failing before any expansion if the mapper is strict and unsupported operators
+    // were encountered; mapping runs the standard passes, see PassManager::standard
+    pub fn try_map(&mut self, buf:Vec<u8>) -> Result<HashMap<NodeId, Node>, UnsupportedOperatorsError> {
+        match PassManager::standard().run(self, buf) {
+            Ok(state) => Ok(state.into_tree()),
+            Err(PassError::Unsupported(err)) => Err(err),
+            Err(err) => unreachable!("the standard passes only fail on unsupported operators, not with {}", err)
+        }
+    }
+
+    // reads the given WASM in the first pass, registering a node for each of its functions, and joins the
+    // functions linked in from the registered modules; returns the functions' nodes, by index
+    pub(crate) fn read_module(&mut self, buf:&[u8]) -> HashMap<NodeId, Node> {
+        self.unsupported.clear();
+        self.warnings.clear();
+        self.structure_diagnostics.clear();
+
+        // the functions, blocks, exports and start function of the module mapped before are dropped, so that this
+        // one's tree, down to the numbering of its blocks, is the same whatever the mapper mapped before
+        self.nodes.clear();
+        self.blocks.clear();
+        self.exports.clear();
+        self.start = None;
+        self.imports.clear();
+        self.linked.clear();
+        self.function_names.clear();
+        self.shared_memory = false;
+        self.external_memory = false;
+        self.memory_limits = None;
+        self.custom_sections.clear();
+        self.profiler.clear();
+        self.error = None;
+        self.data_segments = ranges::data_segments(buf);
+        self.observer.on_module(&buf);
+
+        // offsets are resolved to source locations through the module's DWARF line table, or failing that the
+        // source map the mapper was given
+        self.source_mapping_url = LineTable::source_mapping_url(buf);
+        self.line_table = match LineTable::from_module(buf) {
+            Some(table) => table,
+            None => self.source_map.as_ref().and_then(|json| LineTable::from_source_map(json)).unwrap_or_default()
+        };
+
+        // creates a new parser and a printer for the first pass, writing to the configured output or to a colorful
+        // standard output stream, which stays locked for the pass so that mappers on other threads can't interleave
+        // their output with this one's; it is only locked when it is written to, so that mappers writing to outputs
+        // of their own don't hold each other up
+        let mut parser = ValidatingParser::new(buf, Some(PARSER_CONFIG));
+        let mut output = self.output.take();
+        let stream = StandardStream::stdout(console::color_choice());
+        let mut stdout;
+        let sink:&mut dyn WriteColor = match output {
+            Some(ref mut output) => output.as_mut(),
+            None => {
+                stdout = stream.lock();
+                &mut stdout
+            }
+        };
+        let mut printer = ConsolePrinter::new(sink, self.theme.clone());
+        let mut parser_input = None;
+        
+        // one top-level node at a time is processed recursively 
+        let mut nodes:HashMap<NodeId, Node> = HashMap::new();
+        let mut node:Node = Node::default();
+
+        // function parameters that can be determined before entering the function bodies themselves
+        let mut func_start;
+        let mut func_end;
+        let mut func_index = 0;
+        let mut func_types = Vec::new();
+
+        // number of encountered functions
+        let mut func_count = 0;
+
+        // loop until we reach the end of the input WASM code
+        loop {
+
+            node = Node::default();
+            node.set_id(NodeId(func_index as usize));
+            node.set_function(true);
+
+            // white is for non-significant printout that does not represent a simulatable 
+            // operation or control flow instruction
+            printer.highlight(Highlight::Plain);
+
+            // prepare the parser input
+            let next_input = parser_input.take().unwrap_or(ParserInput::Default);
+            parser_input = None;
+
+            // parse the input
+            match *parser.read_with_input(next_input) {
+                // print encountered errors
+                // the parser can't move past an error, so the module is mapped as far as it got
+                ParserState::Error(err) => {
+                    printer.highlight(Highlight::Error);
+                    printer.println(&format!("Error: {:?}", err));
+                    self.error.get_or_insert(err);
+                    break;
+                },
+                // break out of the loop when the file has been processed
+                ParserState::EndWasm => break,
+                // extract the function section entry's reference to the function's type signature
+                ParserState::FunctionSectionEntry { 0: value } => { 
+                    func_types.push(value);
+                    continue;
+                },
+                // exported functions and the start function are the roots of the live call graph
+                ParserState::ExportSectionEntry { field, kind: ExternalKind::Function, index } => {
+                    self.exports.insert(index as usize, field.to_string());
+                    printer.println(&format!("{:?}", *parser.last_state()));
+                    continue;
+                },
+                // the couplings assume a single thread, which only holds for memory that isn't shared
+                ParserState::MemorySectionEntry(MemoryType { limits, shared })
+                | ParserState::ImportSectionEntry { ty: ImportSectionEntryType::Memory(MemoryType { limits, shared }), .. } => {
+                    self.shared_memory |= shared;
+                    self.external_memory |= matches!(*parser.last_state(), ParserState::ImportSectionEntry { .. });
+                    self.memory_limits = Some(limits);
+                    printer.println(&format!("{:?}", *parser.last_state()));
+                    continue;
+                },
+                // the host can grow a memory it is given, so its declared size is only a lower bound
+                ParserState::ExportSectionEntry { kind: ExternalKind::Memory, .. } => {
+                    self.external_memory = true;
+                    printer.println(&format!("{:?}", *parser.last_state()));
+                    continue;
+                },
+                // imported functions come first in the function index space, and are resolved against the registered
+                // modules once the module's own functions have been mapped
+                ParserState::ImportSectionEntry { module, field, ty: ImportSectionEntryType::Function(_) } => {
+                    let index = self.imports.len();
+                    self.imports.insert(index, (module.to_string(), field.to_string()));
+                    printer.println(&format!("{:?}", *parser.last_state()));
+                    continue;
+                },
+                // the name section's function names are what the function filters match, ahead of export names
+                ParserState::NameSectionEntry(NameEntry::Function(ref namings)) => {
+                    for naming in namings.iter() {
+                        self.function_names.insert(naming.index as usize, naming.name.to_string());
+                    }
+                    printer.println(&format!("{:?}", *parser.last_state()));
+                    continue;
+                },
+                ParserState::StartSectionEntry { 0: index } => {
+                    self.start = Some(index as usize);
+                    printer.println(&format!("{:?}", *parser.last_state()));
+                    continue;
+                },
+                // custom sections are kept as they are, and the name section is read on for its function names
+                ParserState::BeginSection { code: SectionCode::Custom { name, kind }, ref range } => {
+                    self.custom_sections.push((name.to_string(), buf[range.start..range.end].to_vec()));
+                    if kind == CustomSectionKind::Name {
+                        parser_input = Some(ParserInput::ReadCustomSection);
+                    }
+                    printer.println(&format!("{:?}", *parser.last_state()));
+                    continue;
+                },
+                // when we encounter the start of a function body extract what info we can and have the 
+                // parser skip the body itself
+                ParserState::BeginFunctionBody { range } => {
+                    parser_input = Some(ParserInput::SkipFunctionBody);
+                    func_start = range.start;
+                    func_end = range.end;
+                    node.set_end(func_end);
+                },
+                // print the parser's interpretation of everything else that is encountered
+                _ => {
+                    printer.println(&format!("{:?}", *parser.last_state()));
+                    continue;
+                }
+            }
+
+            printer.highlight(Highlight::Plain);
+            printer.println(&format!("{:?}", *parser.last_state()));
+
+            // the parser will have a reference to the most recent function its encountered, which
+            // is offset by the imported functions in the function index space used by calls and exports
+            func_index = parser.current_func_index + parser.func_imports_count;
+            func_count += 1;
+
+            // a new parser will handle the block
+            let mut reader = parser.create_validating_operator_parser();
+
+            // the parser has information about globals and keeps track of each function's type signature
+            let resources = parser.get_resources();
+
+            // find and attach the function signature before processing the body so we can access its parameter info
+            node = self.attach_signature(resources, node.clone(), func_count, func_types.clone());
+
+            // the locals declared at the start of the body follow the parameters in the local index space
+            node = self.attach_locals(FunctionBody::new(func_start, &buf[func_start..func_end]), node);
+            node.set_function_index(FuncId(func_index as usize));
+
+            // the map helper will use the validating operator parser to recursively process the function
+            // body and create a corresponding node
+            let started = Instant::now();
+            let reads = self.profiler.reads();
+            node = self.map_helper(&mut reader, buf, resources, func_start, func_index as usize, node.clone(), &mut printer);
+            self.profiler.record_mapping(func_index as usize, started.elapsed(), self.profiler.reads() - reads);
+
+            // the recorded spans and blocks are checked against an independent replay of the function's code;
+            // the diagnostics are kept for tooling to inspect
+            let diagnostics = structure::check_function(&node, &self.blocks);
+            self.structure_diagnostics.extend(diagnostics);
+
+            // register the encountered function and corresponding processed node
+            self.nodes.insert(NodeId(func_index as usize), node.clone());
+            nodes.insert(NodeId(func_index as usize), node.clone());
+        }
+
+        // the output is kept for the next mapping
+        drop(printer);
+        self.output = output;
+
+        // the registered handlers label the functions annotated by the custom sections they interpret
+        self.annotate(&mut nodes);
+
+        // a module carrying hints is mapped as they direct, without asking the observer anything
+        self.read_hints(&mut nodes);
+
+        // functions imported from registered modules are mapped from those modules and join the tree
+        self.link(&mut nodes);
+
+        // print out some basic metrics
+        let indices = self.get_indices(&nodes);
+        self.progress(&format!("First pass found {} functions:\n{:?}", indices.len(), indices));
+
+        // the calls are recorded before any pass changes them
+        let mut sites:HashMap<usize, (Vec<usize>, Vec<usize>)> = HashMap::new();
+        for (index, node) in nodes.iter() {
+            let (mut callees, mut types) = (Vec::new(), Vec::new());
+            self.call_sites(node, &mut callees, &mut types);
+            sites.insert(index.0, (callees, types));
+        }
+        self.call_graph = CallGraph::new(sites);
+        nodes
+    }
+
+    // fails with the unsupported operators the last mapping encountered, if the mapper is strict
+    pub(crate) fn check_strict(&self) -> Result<(), UnsupportedOperatorsError> {
+        // a strict mapper doesn't go on with incomplete nodes
+        if self.strict && !self.unsupported.is_empty() {
+            return Err(UnsupportedOperatorsError { operators: self.unsupported.clone() });
+        }
+        Ok(())
+    }
+
+    // weighs the conditions of every registered node and block with the branch profile, if the mapper has one,
+    // returning how many were weighed
+    pub(crate) fn weigh_branches(&mut self) -> usize {
+        let profile = match self.branch_profile {
+            Some(ref profile) => profile,
+            None => return 0
+        };
+        self.nodes.values_mut().chain(self.blocks.values_mut()).map(|node| node.weigh_branches(profile)).sum()
+    }
+
+    // removes the functions that don't need to be expanded from the given ones
+    pub(crate) fn prune_unselected(&mut self, nodes:&HashMap<NodeId, Node>) {
+        // functions that are never called from the exports or start function don't need to be expanded, and with
+        // function filters, neither do those never called from the functions they select
+        let mut selected:Vec<usize> = Vec::new();
+        if self.include.is_some() || self.exclude.is_some() {
+            selected = nodes.keys().map(|index| index.0).filter(|index| self.selects(*index)).collect();
+            selected.sort();
+            self.progress(&format!("Selected {} functions by name:\n{:?}", selected.len(), selected));
+        }
+        let removed = self.prune_unreachable(selected);
+        if !removed.is_empty() {
+            self.progress(&format!("Pruned {} unreachable functions:\n{:?}", removed.len(), removed));
+        }
+    }
+
+    // models the memory's size, warning about the nodes that read it while it can grow
+    pub(crate) fn check_memory_size(&mut self) {
+        // the memory's size is only a constant if no reachable code grows it
+        self.model_memory_size();
+        for index in self.dynamic_memory_nodes() {
+            let message = format!("Node {} accesses memory after reading its size, which is only known when running", index);
+            self.warn(index, None, WarningCategory::DynamicMemory, message);
+        }
+    }
+
+    // predicates the diamonds of the functions selected for predication
+    pub(crate) fn predicate_selected(&mut self) {
+        // the selected functions' diamonds are predicated first, so their arithmetic can be folded too
+        let mut predicated = 0;
+        for index in self.predication.clone() {
+            predicated += self.predicate(NodeId(index));
+        }
+        if predicated > 0 {
+            self.progress(&format!("Predicated {} if/else diamonds", predicated));
+        }
+    }
+
+    // inlines the calls to functions under the inline threshold, if there is one
+    pub(crate) fn inline_small_functions(&mut self) {
+        // small helpers are inlined before folding, so that constant arguments fold into their arithmetic
+        if self.inline_threshold > 0 {
+            let inlined = self.inline(self.inline_threshold);
+            if inlined > 0 {
+                self.progress(&format!("Inlined {} calls", inlined));
+            }
+        }
+    }
+
+    // validates the recorded operations with the configured number of random inputs, if any
+    pub(crate) fn check_roundtrip(&mut self) {
+        // the recorded operations, as optimized so far, have to agree with the code they were mapped from;
+        // a fixed seed keeps the inputs the same from run to run
+        if self.roundtrip_trials > 0 {
+            let report = self.validate_roundtrip(self.roundtrip_trials, 0x5eed);
+            self.progress(&format!("Validated {} functions with {} comparisons", report.nodes.len(), report.comparisons));
+            for mismatch in report.mismatches {
+                self.warn(mismatch.node, Some(mismatch.location), WarningCategory::RoundtripMismatch, mismatch.to_string());
+            }
+        }
+    }
+
+    // expands the given functions' nodes as the passes before left them
+    pub(crate) fn expand(&mut self, mut nodes:HashMap<NodeId, Node>) -> HashMap<NodeId, Node> {
+        let indices:Vec<NodeId> = nodes.keys().cloned().collect();
+
+        // the node tree picks up the transformations made by the passes above
+        for index in indices {
+            match self.nodes.get(&index) {
+                Some(node) => {
+                    nodes.insert(index, node.clone());
+                }
+                None => {
+                    nodes.remove(&index);
+                }
+            }
+        }
+
+        // recursive functions can't be unrolled, so they are reported before expansion
+        let groups = self.recursion_groups();
+        if !groups.is_empty() {
+            self.progress(&format!("Found {} groups of recursive functions:\n{:?}", groups.len(), groups));
+        }
+
+        // call the parallelizing function
+        nodes = self.expand_tree(nodes);
+        nodes
+    }
+
+    // warns about the ill-typed operations of the registered nodes
+    pub(crate) fn check_types(&mut self) {
+        // ill-typed operations would only keep the nodes from being lowered later on, so they are reported now
+        for error in self.type_check() {
+            self.warn(error.node, Some(error.location), WarningCategory::IllTyped, error.to_string());
+        }
+    }
+
+    // warns about the nodes making non-atomic accesses to shared memory
+    pub(crate) fn check_races(&mut self) {
+        // plain loads and stores to shared memory can be interleaved with other threads' accesses
+        let mut racing:Vec<(usize, Vec<usize>)> = self.racing_nodes().into_iter().collect();
+        racing.sort();
+        for (index, offsets) in racing {
+            let message = format!("Node {} makes {} non-atomic accesses to shared memory, which can race with other threads", index, offsets.len());
+            self.warn(index, None, WarningCategory::Race, message);
+        }
+    }
+
+    // provides optional parallelization of each processed node in the provided node tree
+    fn expand_tree(&mut self, nodes:HashMap<NodeId, Node>) -> HashMap<NodeId, Node> {
+        let mut tree = nodes.clone();
+        self.arena.clear();
+        self.reset_block_ids(&nodes);
+
+        // callees are always expanded from their mapped form, whichever functions have been expanded already
+        let mapped = nodes.clone();
+
+        // the callees scheduled for the arena, and the nodes expanded so far across every function
+        let mut shared:HashMap<NodeId, SharedExpansion> = HashMap::new();
+        let mut expanded = 0;
+
+        // functions, blocks and calls are expanded in order, so that blocks get the same ids on every run
+        let mut functions:Vec<(NodeId, Node)> = nodes.into_iter().collect();
+        functions.sort_by_key(|(index, _)| *index);
+        for (index, func) in functions {
+
+            // functions the filters leave out are only expanded as the callees of those they select
+            if !self.selects(index.0) {
+                tree.remove(&index);
+                continue;
+            }
+
+            // ask the observer if they would like to parallelize each top-level node, unless the module's hints say
+            let decision = Decision::Parallelize { function: index.0 };
+            let parallelize = match self.hints {
+                Some(ref hints) => hints.decide(&decision),
+                None => self.observer.on_decision_needed(&decision)
+            };
+            if !parallelize {
+                continue;
+            }
+            
+            self.observer.on_function_start(index.0);
+            
+            let node = self.expand_function(func, index, &mapped, &mut shared, &mut expanded);
+            tree.insert(index, node);
+        }
+
+        // the callers of shared subtrees are linked to them once every subtree is finished, since a caller can be
+        // expanded before the subtree it shares
+        let arena:HashMap<NodeId, Node> = shared.into_iter().filter_map(|(index, expansion)| expansion.node.map(|node| (index, node))).collect();
+        let mut linked:HashMap<NodeId, Option<Arc<Node>>> = HashMap::new();
+        for node in tree.values_mut() {
+            node.link_shared_children(&arena, &mut linked);
+        }
+        for (index, node) in arena.iter() {
+            if !linked.contains_key(index) {
+                let mut node = node.clone();
+                node.link_shared_children(&arena, &mut linked);
+                linked.insert(*index, Some(Arc::new(node)));
+            }
+        }
+        self.arena = linked.into_iter().filter_map(|(index, node)| node.map(|node| (index, node))).collect();
+        tree
+    }
+
+    // returns the functions the given function can call, from its own code or its blocks, directly or through the
+    // functions it calls
+    fn reachable_functions(&self, index:NodeId, tree:&HashMap<NodeId, Node>) -> HashSet<NodeId> {
+        let mut reachable:HashSet<NodeId> = HashSet::new();
+        let mut pending:Vec<&Node> = tree.get(&index).into_iter().collect();
+        while let Some(node) = pending.pop() {
+            for callee in node.calls().values() {
+                if reachable.insert(*callee) {
+                    pending.extend(tree.get(callee));
+                }
+            }
+            pending.extend(node.blocks().values().filter_map(|block| self.blocks.get(block)));
+        }
+        reachable
+    }
+
+    // discovers and normalizes structure in the given function and everything it calls, working through a list
+    // of pending nodes rather than recursing so that deep call chains can't overflow the stack; each node is
+    // expanded once its parent has been, and the finished nodes are attached to their parents afterwards
+    fn expand_function(&mut self, func:Node, index:NodeId, tree:&HashMap<NodeId, Node>, shared:&mut HashMap<NodeId, SharedExpansion>, expanded:&mut usize) -> Node {
+        let mut expansions = vec![Expansion { node: func, id: index, function: true, frame: index, recursion: 0, depth: 0, path: HashSet::new(), parent: ExpansionParent::Root }];
+
+        // the function's hints can bound how deep its expansion goes
+        let max_depth = self.hints.as_ref().and_then(|hints| hints.get(index.0).unroll).or(self.max_expansion_depth);
+        let mut pending = vec![0];
+        *expanded += 1;
+
+        while let Some(slot) = pending.pop() {
+            let started = Instant::now();
+            let mut node = expansions[slot].node.clone();
+            let node_id = expansions[slot].id;
+            let function = expansions[slot].function;
+            let depth = expansions[slot].depth;
+            let (frame, recursion) = (expansions[slot].frame, expansions[slot].recursion);
+            let kind = if function { "function" } else { "block" };
+
+            // this node will represent a possible execution path through the code; a function's blocks are
+            // part of its frame, while a block's inner blocks are expanded alongside it
+            let mut path = expansions[slot].path.clone();
+            let block_path = if function { path.insert(node_id); path.clone() } else { path.clone() };
+            path.insert(node_id);
+
+            // the ids of the children expanded from this node so far, which calls don't need to expand again
+            let mut registered:HashSet<NodeId> = HashSet::new();
+
+            // normalizes block references to the node format for simplicity
+            let mut blocks:Vec<(usize, BlockId)> = node.blocks().iter().map(|(start, index)| (*start, *index)).collect();
+            blocks.sort();
+            self.progress(&format!("Found {} blocks in {} {}", blocks.len(), kind, node_id));
+            for (start, index) in blocks {
+                if self.truncate(&mut node, node_id.0, start, index.0, depth, max_depth, *expanded) {
+                    continue;
+                }
+
+                // get the block by index
+                let mut block = self.get_block(index);
+
+                // generate an id that won't collide with any other block or function's id; the block's node is
+                // known by it from here on, rather than by the block's id in the registry
+                let block_id = self.unique_block_id();
+                block.set_id(block_id);
+                self.observer.on_block_found(node_id.0, block_id.0);
+
+                // split an inner block's code out from the outer block's, by the offsets of its code rather than the
+                // location it starts at
+                if !function {
+                    node.remove_instrs(block.get_start(), block.get_end());
+                }
+
+                // register a call to the separated block, and the block as a node
+                node.add_call(start, block_id);
+                self.nodes.insert(block_id, block.clone());
+                registered.insert(block_id);
+
+                // the block is processed once this node is
+                expansions.push(Expansion { node: block, id: block_id, function: false, frame, recursion, depth: depth + 1, path: block_path.clone(), parent: ExpansionParent::Child(slot, block_id) });
+                pending.push(expansions.len() - 1);
+                *expanded += 1;
+            }
+
+            // traverses calls searching for feed-forward execution paths
+            let mut calls:Vec<(usize, NodeId)> = node.calls().iter().map(|(call, index)| (*call, *index)).collect();
+            calls.sort();
+            self.progress(&format!("Found {} calls to other functions from {} {}", calls.len(), kind, node_id));
+            for (call, index) in calls {
+
+                // with recursion unrolling, a function calling itself, from its body or its blocks, calls a copy of
+                // itself, which has an id of its own to tell its variables apart, until the copies reach the depth
+                if index == frame && self.recursion_depth > 0 && tree.contains_key(&index) {
+                    if recursion >= self.recursion_depth {
+                        node.add_truncation(call, index.0);
+                        let message = format!("Cutting off recursion of function {} at depth {} in {} {}, so deeper calls aren't modelled", index, recursion, kind, node_id);
+                        self.warn(node_id.0, Some(call), WarningCategory::RecursionBound, message);
+                        continue;
+                    }
+                    if self.truncate(&mut node, node_id.0, call, index.0, depth, max_depth, *expanded) {
+                        continue;
+                    }
+                    let copy_id = self.unique_block_id();
+                    let mut copy = tree[&index].clone();
+                    copy.set_id(copy_id);
+                    self.progress(&format!("Unrolling call to function {} from {} {} as function {}", index, kind, node_id, copy_id));
+                    node.add_call(call, copy_id);
+                    self.nodes.insert(copy_id, copy.clone());
+                    registered.insert(copy_id);
+                    expansions.push(Expansion { node: copy, id: copy_id, function: true, frame: index, recursion: recursion + 1, depth: depth + 1, path: path.clone(), parent: ExpansionParent::Child(slot, copy_id) });
+                    pending.push(expansions.len() - 1);
+                    *expanded += 1;
+                    continue;
+                }
+
+                // skips self references since these can't be unrolled at compile time,
+                // and aren't generally simulatable
+                if function && index == node_id {
+                    self.warn(node_id.0, Some(call), WarningCategory::SelfReference, format!("Skipping self referencing call in function {}", node_id));
+                    continue;
+                }
+
+                // reference loops will expand infinitely and can't be unrolled at compile time,
+                // so these loops are not generally simulatable
+                if path.contains(&index) {
+                    self.warn(node_id.0, Some(call), WarningCategory::ReferenceLoop, format!("Skipping reference loop in {} {}", kind, node_id));
+                    continue;
+                }
+
+                // skips functions already encountered; they don't need to be expanded again, just referenced again by location
+                if registered.contains(&index) || node.has_child(index) || node.has_shared_child(index) {
+                    self.progress(&format!("Skipping already registered call to function {} from {} {}", index, kind, node_id));
+                    continue;
+                }
+
+                // imported functions have no code to expand
+                if !tree.contains_key(&index) {
+                    self.warn(node_id.0, Some(call), WarningCategory::ImportedCall, format!("Skipping call to imported function {} from {} {}", index, kind, node_id));
+                    continue;
+                }
+
+                // a shared callee is only expanded by the first call to reach it, along that call's execution path,
+                // and is shared by the later calls it would expand the same for; the others get a copy of their own
+                let sharing = match shared.get(&index) {
+                    Some(expansion) if self.subtree_sharing => {
+                        if expansion.is_shared_by(&path, depth + 1, max_depth) {
+                            node.add_shared_child(index);
+                            registered.insert(index);
+                            continue;
+                        }
+                        false
+                    }
+                    _ => self.subtree_sharing
+                };
+
+                if self.truncate(&mut node, node_id.0, call, index.0, depth, max_depth, *expanded) {
+                    continue;
+                }
+
+                self.progress(&format!("Registering call to function {} from {} {}", index, kind, node_id));
+                registered.insert(index);
+
+                // any call that was not skipped is analyzed once this node is
+                let parent = match sharing {
+                    true => {
+                        node.add_shared_child(index);
+                        let reachable = self.reachable_functions(index, tree);
+                        shared.insert(index, SharedExpansion { path: path.clone(), depth: depth + 1, max_depth, reachable, node: None });
+                        ExpansionParent::Arena(index)
+                    }
+                    false => ExpansionParent::Child(slot, index)
+                };
+                expansions.push(Expansion { node: tree[&index].clone(), id: index, function: true, frame: index, recursion: 0, depth: depth + 1, path: path.clone(), parent });
+                pending.push(expansions.len() - 1);
+                *expanded += 1;
+            }
+
+            expansions[slot].node = node;
+            self.profiler.record_expansion(node_id.0, started.elapsed());
+        }
+
+        // children were always expanded after their parents, so attaching them in reverse leaves every subtree complete
+        while expansions.len() > 1 {
+            let expansion = expansions.pop().unwrap();
+            match expansion.parent {
+                ExpansionParent::Child(slot, key) => expansions[slot].node.add_child(key, expansion.node),
+                ExpansionParent::Arena(index) => {
+                    if let Some(shared) = shared.get_mut(&index) {
+                        shared.node = Some(expansion.node);
+                    }
+                }
+                ExpansionParent::Root => ()
+            }
+        }
+        expansions.pop().unwrap().node
+    }
+
+    // checks whether expanding a child at the given depth would go past the expansion limits, in which case the
+    // child's location is recorded as truncated on the node instead
+    #[allow(clippy::too_many_arguments)]
+    fn truncate(&mut self, node:&mut Node, node_id:usize, location:usize, index:usize, depth:usize, max_depth:Option<usize>, expanded:usize) -> bool {
+        let too_deep = matches!(max_depth, Some(max) if depth >= max);
+        let too_large = matches!(self.max_expanded_nodes, Some(max) if expanded >= max);
+        if !too_deep && !too_large {
+            return false;
+        }
+        node.add_truncation(location, index);
+        let reason = if too_deep { "the maximum depth" } else { "the maximum number of nodes" };
+        self.warn(node_id, Some(location), WarningCategory::Truncated, format!("Truncating expansion of node {} from node {}, which reached {}", index, node_id, reason));
+        true
+    }
+
+    // processes a function body using a validating operator parser
+    #[allow(clippy::too_many_arguments)]
+    fn map_helper(&mut self, reader:&mut ValidatingOperatorParser, buf:&[u8], resources:&WasmModuleResources, start:usize, index:usize, mut node:Node, printer:&mut ConsolePrinter) -> Node {
+
+        // the number of reads made by the operator parser
+        let mut i = 0;
+
+        // where the node's operators start in the WASM file, to find the source they were compiled from
+        let source_start = reader.original_position();
+
+        // sets initial pre-determined node properties
+        node.set_start(start);
+        node.set_id(NodeId(index));
+
+        loop {
+
+            // green is for simulatable instructions
+            printer.highlight(Highlight::Simulatable);
+
+            // read the next operator
+            let offset = reader.original_position();
+            let read = reader.next(resources);
+
+            // update the cursor position, which is where a block opened by the operator starts
+            let position = reader.original_position();
+
+            // update the read counter
+            i += 1;
+            self.profiler.count_read();
+
+            if let Ok(ref op) = read {
+
+                // operators the pipeline doesn't handle leave the node's mapping incomplete
+                if self.support.classify(op) == Support::Unsupported {
+                    self.unsupported.push(UnsupportedOperator { operator: operator_name(op), offset });
+                }
+
+                // mapping of WASM instructions to node properties including data couplings and abstract 
+                // simulatable operations, by the frame's visitor method for the kind of instruction; a number
+                // of instructions are not yet supported
+
+                // white is for non-critical code
+                // yellow is for control dependencies
+                // blue is for data dependencies
+                // purple is for function calls
+                // green is for simulatable operations
+
+                let mut frame = MapFrame { mapper: self, reader, buf, resources, printer, node: &mut node, index, offset, position };
+                if visit::dispatch(&mut frame, i, op) == Flow::Stop {
+                    break;
+                }
+
+                // print out each encountered operator
+                printer.println(&format!("{}. {:?}", i, op));
+            } else {
+
+                // red is for bad WASM
+                printer.highlight(Highlight::Error);
+                let err = read.err();
+                printer.println(&format!("Bad wasm code {:?}", err));
+
+                // the error is located at the start of the operator that couldn't be read, since the
+                // validator's own offsets aren't relative to the start of the file
+                if let (None, Some(err)) = (self.error, err) {
+                    self.error = Some(BinaryReaderError { message: err.message, offset });
+                }
+
+                // the reader can't move past an error, so reading on would report it forever; where
+                // the node would have ended is unknown, so a node without an end is left empty
+                if node.get_end() < start {
+                    node.set_end(start);
+                }
+                break;
+            }
+        }
+
+        // set the node's instruction list
+        let end = node.get_end();
+        node.set_instrs(buf[start..end].to_vec());
+        node.set_source_locations(self.line_table.ranges(source_start, reader.original_position()));
+
+        node
+    }
+}
+
+
+/// A map frame is the mapper reading an operator of a node's code
+/// in its first pass, with what it reads the operator into and from
+struct MapFrame<'m, 'b, 'p> {
+    mapper: &'m mut Mapper,
+    reader: &'m mut ValidatingOperatorParser<'b>, // reads the rest of the node's code, and the blocks it opens
+    buf: &'m [u8], // the WASM file
+    resources: &'m dyn WasmModuleResources, // the module's types, functions and globals
+    printer: &'m mut ConsolePrinter<'p>, // where the operators are printed to
+    node: &'m mut Node, // the node the operator is recorded in
+    index: usize, // the id of the node
+    offset: usize, // where the operator starts in the WASM file
+    position: usize // where the reader is past the operator in the WASM file
+}
+
+
+impl<'m, 'b, 'p> OperatorVisitor for MapFrame<'m, 'b, 'p> {
+    // records nops, drops and selects, where the node ends and what can't run past it, and reads the blocks, loops,
+    // ifs and elses it opens as nodes of their own; the end closing the node, or the else closing an if, stops reading it
+    fn visit_control(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let printer = &mut *self.printer;
+        let reader = &mut *self.reader;
+        let buf = self.buf;
+        let resources = self.resources;
+        let position = self.position;
+        match op {
+            Operator::Nop => {
+                printer.highlight(Highlight::Plain);
+            }
+            Operator::Block { ty } => {
+
+                printer.highlight(Highlight::Control);
+                printer.print("==== New Block: ");
+                printer.println(&format!("{}. {:?}", i, op));
+
+                // blocks can simply be registered... they don't have parameters, but their result is
+                // coupled to the stack slot it leaves in this node
+                let mut block_node = node.nested_block();
+                block_node.add_result_variable(*ty);
+                let block_node = self.mapper.map_helper(reader, buf, resources, position, i, block_node, printer);
+                node.add_written_locals(&block_node);
+                let block_id = self.mapper.add_block(block_node);
+                node.add_block(i, block_id);
+                node.add_result_coupling(i, *ty);
+
+                printer.highlight(Highlight::Control);
+                printer.print("==== End of: ");
+            }
+            Operator::Loop { ty } => {
+
+                printer.highlight(Highlight::Control);
+                printer.print("==== New Loop: ");
+                printer.println(&format!("{}. {:?}", i, op));
+
+                // loops don't have parameters so they can be registered as blocks
+                let mut loop_node = node.nested_block();
+                loop_node.add_result_variable(*ty);
+                let loop_node = self.mapper.map_helper(reader, buf, resources, position, i, loop_node, printer);
+                node.add_written_locals(&loop_node);
+                let loop_id = self.mapper.add_block(loop_node);
+                node.add_block(i, loop_id);
+                node.add_result_coupling(i, *ty);
+
+                printer.highlight(Highlight::Control);
+                printer.print("==== End of: ")
+            }
+            Operator::If { ty } => {
+
+                printer.highlight(Highlight::Control);
+                printer.print("==== New If Condition: ");
+                printer.println(&format!("{}. {:?}", i, op));
+
+                // if conditions imply a single data dependency
+                let mut conditional_node = node.nested_block();
+                        
+                // create variable to represent the condition
+                let outer_var_id = node.add_internal_variable(i, *ty);
+
+                // create data coupling to simulate flow control; a condition tested for zero right before
+                // the if is taken as it is instead, with the branch anti-chained to it
+                let chain = !node.fold_zero_test(i);
+                node.add_condition(i, ProgramPoint::new(node.get_function_index().0, self.offset));
+                let inner_var_id = conditional_node.add_input_variable(*ty);
+                conditional_node.add_flow_control_coupling(outer_var_id, inner_var_id, chain);
+                conditional_node.add_result_variable(*ty);
+                        
+                conditional_node = self.mapper.map_helper(reader, buf, resources, position, i, conditional_node, printer);
+
+                // register the conditional block, whose result is coupled to this node whichever of the branch
+                // and its else clause leaves it
+                node.add_written_locals(&conditional_node);
+                let conditional_id = self.mapper.add_block(conditional_node.clone());
+                node.add_block(i, conditional_id);
+                node.add_result_coupling(i, *ty);
+
+                // add a spin to each node
+                node.add_operation(i, AbstractExpression::Spin{ id: outer_var_id });
+                conditional_node.add_operation(i, AbstractExpression::Spin{ id: inner_var_id });
+
+                printer.highlight(Highlight::Control);
+                printer.print("==== End of: ")
+            }
+            Operator::Else => {
+
+                printer.highlight(Highlight::Control);
+
+                // else implies a single data anti-dependency
+                // it needs to be constructed from within the if so we can have easy access to its coupling parameters
+                // however, it will be lifted out during the collapse of its top-level parent function
+
+                // we should have most recently registered a conditional node with only one flow control coupling
+                let coupling_count = node.flow_control_couplings().len();
+
+                // if we aren't in a conditional already, don't process the else; the conditional's first
+                // input variable is its condition, though reading locals may have added more
+                if coupling_count == 1 {
+
+                    printer.print("==== New Else Clause: ");
+                    printer.println(&format!("{}. {:?}", i, op));
+
+                    // get coupling details from the if condition details, which the if registered as its first
+                    // coupling and input variable
+                    let (coupled_var_id, input_type) = match (node.get_first_flow_control_coupling(), node.get_first_input_variable()) {
+                        (Some(coupled_var_id), Some(input_type)) => (coupled_var_id, input_type),
+                        _ => {
+                            printer.highlight(Highlight::Error);
+                            printer.println("Error: The else clause's if registered no condition.");
+                            printer.highlight(Highlight::Plain);
+                            (0, Type::AnyRef)
+                        }
+                    };
+
+                    let mut else_node = node.nested_block();
+
+                    // create data anti-chain coupling to simulate flow control, which is a chain if the
+                    // branch's is an anti-chain
+                    let chained = node.flow_control_couplings().keys().min().and_then(|outer_var_id| node.chains().get(outer_var_id)) != Some(&false);
+                    let inner_var_id = else_node.add_input_variable(input_type);
+                    else_node.add_flow_control_coupling(coupled_var_id, inner_var_id, !chained);
+
+                    // the else clause leaves a value of the same type as the if
+                    let result_type = match node.get_result_variable() {
+                        Some(var_id) => node.output_variables[&var_id],
+                        None => Type::EmptyBlockType
+                    };
+                    else_node.add_result_variable(result_type);
+
+                    else_node = self.mapper.map_helper(reader, buf, resources, position, i, else_node, printer);
+
+                    // the else's end also terminates the if clause
+                    let if_end = else_node.get_end();
+                    node.set_end(if_end);
+
+                    // register the else block; its result goes to the enclosing node just like the branch's,
+                    // through the coupling the if is registered with there
+                    node.add_written_locals(&else_node);
+                    let else_id = self.mapper.add_block(else_node);
+                    node.add_block(i, else_id);
+                        
+                    printer.highlight(Highlight::Control);
+                    printer.print("==== End of: ");
+                    printer.println(&format!("{}. {:?}", i, op));
+                            
+                    // finish processing the if node
+                    return Flow::Stop;
+                }
+            }
+            Operator::Unreachable
+            | Operator::Return => {
+                printer.highlight(Highlight::Plain);
+
+                // nothing after these can execute, but the rest of the node must still be read up to its end
+                node.add_terminator(i);
+            }
+            Operator::End => {
+
+                printer.highlight(Highlight::Plain);
+
+                // if the node represetns a function, the function end was already extracted from the function metadata
+                if (node.get_end() == 0) {
+                    // otherwise, the block ends right past the end closing it
+                    node.set_end(position);
+                }
+                printer.println(&format!("{}. {:?}", i, op));
+
+                // finish processing the node
+                return Flow::Stop;
+            }
+            Operator::Drop => { 
+                // TODO 
+            }
+            Operator::Select => { 
+                // the selection itself isn't modelled yet, but its condition is still tracked
+                node.add_condition(i, ProgramPoint::new(node.get_function_index().0, self.offset));
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // records the branches out of the node and the conditions they consume
+    fn visit_branch(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let printer = &mut *self.printer;
+        match op {
+            Operator::Br { relative_depth } => {
+                node.add_branch(i, *relative_depth as usize);
+                printer.highlight(Highlight::Control);
+            }
+            Operator::BrIf { relative_depth } => {
+                node.add_branch(i, *relative_depth as usize);
+
+                // the branch consumes its condition, which an internal flow control variable stands in
+                // for; the rest of the node runs when it doesn't hold, or when a value tested for zero
+                // right before the branch doesn't, which is anti-chained to the variable instead
+                let chain = !node.fold_zero_test(i);
+                node.add_condition(i, ProgramPoint::new(node.get_function_index().0, self.offset));
+                let var_id = node.add_internal_variable(i, Type::I32);
+                node.chains.insert(var_id, chain);
+                node.add_operation(i, AbstractExpression::Spin{ id: var_id });
+                printer.highlight(Highlight::Control);
+            }
+            Operator::BrTable { ref table } => {
+                for relative_depth in table {
+                    node.add_branch(i, table.buffer[relative_depth as usize] as usize);
+                }
+                printer.highlight(Highlight::Control);
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // records the functions the node calls, directly or through a table
+    fn visit_call(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let printer = &mut *self.printer;
+        match op {
+            Operator::Call { function_index } => {
+                node.add_call(i, NodeId(*function_index as usize));
+                printer.highlight(Highlight::Call);
+            }
+            Operator::CallIndirect { index, .. } => {
+                node.add_indirect_call(i, *index as usize);
+                printer.highlight(Highlight::Call);
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // couples reads and writes of locals and globals to the node's variables
+    fn visit_variable(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let printer = &mut *self.printer;
+        let resources = self.resources;
+        let index = self.index;
+        match op {
+            Operator::GetLocal { local_index } => {
+                let local_index = *local_index as usize;
+                if let Some(value) = node.get_local_value(local_index) {
+
+                    // a local written within the node holds the value written to it, which is left unrecorded
+                    // when it isn't known rather than read from the local's value on entry
+                    if let Some(location) = value {
+                        node.add_operation(i, AbstractExpression::Reuse{ location });
+                    }
+                } else if node.is_zero_initialized(local_index) {
+
+                    // a declared local that hasn't been written yet is a known zero
+                    node.add_constant(i, node.get_locals()[local_index]);
+                    node.add_operation(i, AbstractExpression::Num{ val: 0 });
+                } else {
+                    match node.get_local_input_variable(local_index) {
+                        Some(var_id) => node.add_operation(i, AbstractExpression::Spin{ id: var_id }),
+                        None => self.mapper.warn(index, Some(i), WarningCategory::UndeclaredLocal, format!("Skipping read of undeclared local {} in node {}", local_index, index))
+                    }
+                }
+                printer.highlight(Highlight::Data);
+            }
+            Operator::SetLocal { local_index }
+            | Operator::TeeLocal { local_index } => {
+                match node.get_local_output_variable(*local_index as usize) {
+                    Some(_) => node.set_local_value(i, *local_index as usize),
+                    None => self.mapper.warn(index, Some(i), WarningCategory::UndeclaredLocal, format!("Skipping write to undeclared local {} in node {}", local_index, index))
+                }
+                printer.highlight(Highlight::Data);
+            }
+            Operator::GetGlobal { global_index } => {
+                let var_id = node.add_input_variable(resources.globals()[*global_index as usize].content_type);
+                let point = ProgramPoint::new(node.get_function_index().0, self.offset);
+                node.add_global_input_data_coupling(*global_index as usize, point, var_id);
+                printer.highlight(Highlight::Data);
+            }
+            Operator::SetGlobal { global_index } => {
+                let var_id = node.add_output_variable(resources.globals()[*global_index as usize].content_type);
+                let point = ProgramPoint::new(node.get_function_index().0, self.offset);
+                node.add_global_output_data_coupling(*global_index as usize, point, var_id);
+                printer.highlight(Highlight::Data);
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // records constants as known values
+    fn visit_const(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let printer = &mut *self.printer;
+        match op {
+            Operator::I32Const { value } => {
+                node.add_constant(i, Type::I32);
+                node.add_operation(i, AbstractExpression::Num{ val: *value as u32 as usize });
+                printer.highlight(Highlight::Data);
+            }
+            Operator::I64Const { value } => {
+                node.add_constant(i, Type::I64);
+                node.add_operation(i, AbstractExpression::Num{ val: *value as u64 as usize });
+                printer.highlight(Highlight::Data);
+            }
+            Operator::F32Const { .. } => {
+                node.add_constant(i, Type::F32);
+                printer.highlight(Highlight::Data);
+            }
+            Operator::F64Const { .. } => {
+                node.add_constant(i, Type::F64);
+                printer.highlight(Highlight::Data);
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // couples loads to input variables of the widths they read
+    fn visit_load(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let printer = &mut *self.printer;
+        let offset = self.offset;
+        match op {
+            Operator::F32Load { ref memarg } => {
+                let var_id = node.add_input_variable(Type::F32);
+                let access = mem_access(node, i, op, memarg, AccessKind::Read);
+                node.add_input_data_coupling(access, var_id);
+                node.add_memory_access(offset, access);
+                printer.highlight(Highlight::Data);
+            }
+            Operator::F64Load { ref memarg } => {
+                let var_id = node.add_input_variable(Type::F64);
+                let access = mem_access(node, i, op, memarg, AccessKind::Read);
+                node.add_input_data_coupling(access, var_id);
+                node.add_memory_access(offset, access);
+                printer.highlight(Highlight::Data);
+            }
+            Operator::I32Load8S { ref memarg }
+            | Operator::I32Load { ref memarg }
+            | Operator::I32Load8U { ref memarg }
+            | Operator::I32Load16S { ref memarg }
+            | Operator::I32Load16U { ref memarg }
+            | Operator::I32AtomicLoad { ref memarg }
+            | Operator::I32AtomicLoad16U { ref memarg }
+            | Operator::I32AtomicLoad8U { ref memarg } => {
+                let var_id = node.add_input_variable(Type::I32);
+                let width = load_width(op);
+                let access = mem_access(node, i, op, memarg, AccessKind::Read);
+                node.add_input_data_coupling(access, var_id);
+                node.set_input_width(var_id, width);
+                node.add_operation(i, AbstractExpression::Load{ id: var_id, width });
+                if self.mapper.support.categorize(op) == OperatorCategory::Atomic {
+                    node.add_atomic_access(i, offset, access, SyncKind::Load);
+                } else {
+                    node.add_memory_access(offset, access);
+                }
+                printer.highlight(Highlight::Data);
+            }
+            Operator::I64Load8S { ref memarg } 
+            | Operator::I64Load { ref memarg }
+            | Operator::I64Load8U { ref memarg } 
+            | Operator::I64Load16U { ref memarg }
+            | Operator::I64Load32S { ref memarg }
+            | Operator::I64Load32U { ref memarg }
+            | Operator::I64Load16S { ref memarg }
+            | Operator::I64AtomicLoad { ref memarg }
+            | Operator::I64AtomicLoad32U { ref memarg }
+            | Operator::I64AtomicLoad16U { ref memarg }
+            | Operator::I64AtomicLoad8U { ref memarg } => {
+                let var_id = node.add_input_variable(Type::I64);
+                let width = load_width(op);
+                let access = mem_access(node, i, op, memarg, AccessKind::Read);
+                node.add_input_data_coupling(access, var_id);
+                node.set_input_width(var_id, width);
+                node.add_operation(i, AbstractExpression::Load{ id: var_id, width });
+                if self.mapper.support.categorize(op) == OperatorCategory::Atomic {
+                    node.add_atomic_access(i, offset, access, SyncKind::Load);
+                } else {
+                    node.add_memory_access(offset, access);
+                }
+                printer.highlight(Highlight::Data);
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // couples stores to output variables
+    fn visit_store(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let printer = &mut *self.printer;
+        let offset = self.offset;
+        match op {
+            Operator::I32Store { ref memarg } 
+            | Operator::I32Store8 { ref memarg }
+            | Operator::I32Store16 { ref memarg }
+            | Operator::I32AtomicStore { ref memarg }
+            | Operator::I32AtomicStore8 { ref memarg }
+            | Operator::I32AtomicStore16 { ref memarg } => {
+                let var_id = node.add_output_variable(Type::I32);
+                let access = mem_access(node, i, op, memarg, AccessKind::Write);
+                node.add_output_data_coupling(access, var_id);
+                if self.mapper.support.categorize(op) == OperatorCategory::Atomic {
+                    node.add_atomic_access(i, offset, access, SyncKind::Store);
+                } else {
+                    node.add_memory_access(offset, access);
+                }
+                printer.highlight(Highlight::Data);
+            }
+            Operator::I64Store { ref memarg }
+            | Operator::I64Store8 { ref memarg }
+            | Operator::I64Store16 { ref memarg }
+            | Operator::I64Store32 { ref memarg }
+            | Operator::I64AtomicStore { ref memarg }
+            | Operator::I64AtomicStore32 { ref memarg }
+            | Operator::I64AtomicStore16 { ref memarg }
+            | Operator::I64AtomicStore8 { ref memarg } => {
+                let var_id = node.add_output_variable(Type::I64);
+                let access = mem_access(node, i, op, memarg, AccessKind::Write);
+                node.add_output_data_coupling(access, var_id);
+                if self.mapper.support.categorize(op) == OperatorCategory::Atomic {
+                    node.add_atomic_access(i, offset, access, SyncKind::Store);
+                } else {
+                    node.add_memory_access(offset, access);
+                }
+                printer.highlight(Highlight::Data);
+            }
+            Operator::F32Store { ref memarg } => {
+                let var_id = node.add_output_variable(Type::F32);
+                let access = mem_access(node, i, op, memarg, AccessKind::Write);
+                node.add_output_data_coupling(access, var_id);
+                node.add_memory_access(offset, access);
+                printer.highlight(Highlight::Data);
+            }
+            Operator::F64Store { ref memarg } => {
+                let var_id = node.add_output_variable(Type::F64);
+                let access = mem_access(node, i, op, memarg, AccessKind::Write);
+                node.add_output_data_coupling(access, var_id);
+                node.add_memory_access(offset, access);
+                printer.highlight(Highlight::Data);
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // records reads and growth of the memory's size and couples bulk memory operations to the ranges they touch
+    fn visit_memory(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let offset = self.offset;
+        match op {
+            Operator::MemorySize { .. } => { 
+                node.add_memory_size_read(i, offset);
+            }
+            Operator::MemoryGrow { .. } => { 
+                node.add_memory_size_read(i, offset);
+                node.set_grows_memory();
+            }
+            Operator::MemoryInit { segment } => { 
+                let operands = node.constant_operands(i, 3);
+                let coupling = RangeCoupling { location: i, offset, kind: RangeKind::Init { segment: *segment as usize }, destination: operands[0], source: operands[1], length: operands[2] };
+                node.add_range_coupling(coupling, self.mapper.bulk_unroll_limit);
+            }
+            Operator::DataDrop { segment } => { 
+                // TODO 
+            }
+            Operator::MemoryCopy => { 
+                let operands = node.constant_operands(i, 3);
+                let coupling = RangeCoupling { location: i, offset, kind: RangeKind::Copy, destination: operands[0], source: operands[1], length: operands[2] };
+                node.add_range_coupling(coupling, self.mapper.bulk_unroll_limit);
+            }
+            Operator::MemoryFill => { 
+                let operands = node.constant_operands(i, 3);
+                let coupling = RangeCoupling { location: i, offset, kind: RangeKind::Fill, destination: operands[0], source: None, length: operands[2] };
+                node.add_range_coupling(coupling, self.mapper.bulk_unroll_limit);
+            }
+            Operator::TableInit { segment } => { 
+                // TODO 
+            }
+            Operator::ElemDrop { segment } => { 
+                // TODO 
+            }
+            Operator::TableCopy => { 
+                // TODO 
+            }
+            Operator::TableGet { table } => { 
+                // TODO 
+            }
+            Operator::TableSet { table } => { 
+                // TODO 
+            }
+            Operator::TableGrow { table } => { 
+                // TODO 
+            }
+            Operator::TableSize { table } => { 
+                // TODO 
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // records comparisons, numeric operations and conversions as simulatable operations
+    fn visit_arith(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        match op {
+            Operator::I32Eqz => {
+                node.add_operation(i, AbstractExpression::IsZero{ty: Type::I32});
+            }
+            Operator::I32Eq
+            | Operator::I32Ne
+            | Operator::I32LtS
+            | Operator::I32LtU
+            | Operator::I32GtS
+            | Operator::I32GtU
+            | Operator::I32LeS
+            | Operator::I32LeU
+            | Operator::I32GeS
+            | Operator::I32GeU => {
+                // TODO
+            }
+            Operator::I64Eqz => {
+                node.add_operation(i, AbstractExpression::IsZero{ty: Type::I64});
+            }
+            Operator::I64Eq
+            | Operator::I64Ne
+            | Operator::I64LtS
+            | Operator::I64LtU
+            | Operator::I64GtS
+            | Operator::I64GtU
+            | Operator::I64LeS
+            | Operator::I64LeU
+            | Operator::I64GeS
+            | Operator::I64GeU => {
+                // TODO
+            }
+            Operator::F32Eq
+            | Operator::F32Ne
+            | Operator::F32Lt
+            | Operator::F32Gt
+            | Operator::F32Le
+            | Operator::F32Ge => {
+                // TODO
+            }
+            Operator::F64Eq
+            | Operator::F64Ne
+            | Operator::F64Lt
+            | Operator::F64Gt
+            | Operator::F64Le
+            | Operator::F64Ge => {
+                // TODO
+            }
+            Operator::I32Clz => {
+                node.add_operation(i, AbstractExpression::Clz{ty: Type::I32});
+            }
+            Operator::I32Ctz => {
+                node.add_operation(i, AbstractExpression::Ctz{ty: Type::I32});
+            }
+            Operator::I32Popcnt => {
+                node.add_operation(i, AbstractExpression::Popcnt{ty: Type::I32});
+            }
+            Operator::I32Add => {
+                node.add_operation(i, AbstractExpression::Add{ty: Type::I32});
+            }
+            Operator::I32Sub => {
+                // TODO
+            }
+            Operator::I32Mul => {
+                node.add_operation(i, AbstractExpression::Mul{ty: Type::I32});
+            }
+            Operator::I32DivU => {
+                node.add_operation(i, AbstractExpression::DivU{ty: Type::I32});
+            }
+            Operator::I32RemU => {
+                node.add_operation(i, AbstractExpression::RemU{ty: Type::I32});
+            }
+            Operator::I32DivS
+            | Operator::I32RemS
+            | Operator::I32And
+            | Operator::I32Or
+            | Operator::I32Xor => {
+                // TODO
+            }
+            Operator::I32Shl => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I32, kind: ShiftKind::Shl});
+            }
+            Operator::I32ShrS => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I32, kind: ShiftKind::ShrS});
+            }
+            Operator::I32ShrU => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I32, kind: ShiftKind::ShrU});
+            }
+            Operator::I32Rotl => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I32, kind: ShiftKind::Rotl});
+            }
+            Operator::I32Rotr => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I32, kind: ShiftKind::Rotr});
+            }
+            Operator::I64Clz => {
+                node.add_operation(i, AbstractExpression::Clz{ty: Type::I64});
+            }
+            Operator::I64Ctz => {
+                node.add_operation(i, AbstractExpression::Ctz{ty: Type::I64});
+            }
+            Operator::I64Popcnt => {
+                node.add_operation(i, AbstractExpression::Popcnt{ty: Type::I64});
+            }
+            Operator::I64Add => {
+                node.add_operation(i, AbstractExpression::Add{ty: Type::I64});
+            }
+            Operator::I64DivU => {
+                node.add_operation(i, AbstractExpression::DivU{ty: Type::I64});
+            }
+            Operator::I64RemU => {
+                node.add_operation(i, AbstractExpression::RemU{ty: Type::I64});
+            }
+            Operator::I64Sub
+            | Operator::I64Mul
+            | Operator::I64DivS
+            | Operator::I64RemS
+            | Operator::I64And
+            | Operator::I64Or
+            | Operator::I64Xor => {
+                // TODO
+            }
+            Operator::I64Shl => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I64, kind: ShiftKind::Shl});
+            }
+            Operator::I64ShrS => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I64, kind: ShiftKind::ShrS});
+            }
+            Operator::I64ShrU => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I64, kind: ShiftKind::ShrU});
+            }
+            Operator::I64Rotl => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I64, kind: ShiftKind::Rotl});
+            }
+            Operator::I64Rotr => {
+                node.add_operation(i, AbstractExpression::Shift{ty: Type::I64, kind: ShiftKind::Rotr});
+            }
+            Operator::F32Abs => {
+                node.add_operation(i, AbstractExpression::Abs{ty: Type::F32});
+            }
+            Operator::F32Neg => {
+                node.add_operation(i, AbstractExpression::Neg{ty: Type::F32});
+            }
+            Operator::F32Ceil
+            | Operator::F32Floor
+            | Operator::F32Trunc
+            | Operator::F32Nearest
+            | Operator::F32Sqrt => {
+                // TODO
+            }
+            Operator::F32Add => {
+                node.add_operation(i, AbstractExpression::Add{ty: Type::F32});
+            }
+            Operator::F32Sub => {
+                // TODO
+            }
+            Operator::F32Mul => {
+                node.add_operation(i, AbstractExpression::Mul{ty: Type::F32});
+            }
+            Operator::F32Min => {
+                node.add_operation(i, AbstractExpression::Min{ty: Type::F32});
+            }
+            Operator::F32Max => {
+                node.add_operation(i, AbstractExpression::Max{ty: Type::F32});
+            }
+            Operator::F32Copysign => {
+                node.add_operation(i, AbstractExpression::Copysign{ty: Type::F32});
+            }
+            | Operator::F32Div => {
+                // TODO
+            }
+            Operator::F64Abs => {
+                node.add_operation(i, AbstractExpression::Abs{ty: Type::F64});
+            }
+            Operator::F64Neg => {
+                node.add_operation(i, AbstractExpression::Neg{ty: Type::F64});
+            }
+            Operator::F64Ceil
+            | Operator::F64Floor
+            | Operator::F64Trunc
+            | Operator::F64Nearest
+            | Operator::F64Sqrt => {
+                // TODO
+            }
+            Operator::F64Add => {
+                node.add_operation(i, AbstractExpression::Add{ty: Type::F64});
+            }
+            Operator::F64Min => {
+                node.add_operation(i, AbstractExpression::Min{ty: Type::F64});
+            }
+            Operator::F64Max => {
+                node.add_operation(i, AbstractExpression::Max{ty: Type::F64});
+            }
+            Operator::F64Copysign => {
+                node.add_operation(i, AbstractExpression::Copysign{ty: Type::F64});
+            }
+            | Operator::F64Sub
+            | Operator::F64Mul
+            | Operator::F64Div => {
+                // TODO
+            }
+            Operator::I32WrapI64 => {
+                node.add_operation(i, AbstractExpression::Truncate{from: Type::I64, to: Type::I32, signed: false});
+            }
+            Operator::I32TruncSF32 | Operator::I32TruncUF32 => {
+                // TODO
+            }
+            Operator::I32TruncSF64 | Operator::I32TruncUF64 => {
+                // TODO
+            }
+            Operator::I64ExtendSI32 | Operator::I64ExtendUI32 => {
+                let signed = matches!(op, Operator::I64ExtendSI32);
+                node.add_operation(i, AbstractExpression::Extend{from: Type::I32, bits: 32, to: Type::I64, signed});
+            }
+            Operator::I64TruncSF32 | Operator::I64TruncUF32 => {
+                // TODO
+            }
+            Operator::I64TruncSF64 | Operator::I64TruncUF64 => {
+                // TODO
+            }
+            Operator::F32ConvertSI32 | Operator::F32ConvertUI32 => {
+                // TODO
+            }
+            Operator::F32ConvertSI64 | Operator::F32ConvertUI64 => {
+                // TODO
+            }
+            Operator::F32DemoteF64 => {
+                // TODO
+            }
+            Operator::F64ConvertSI32 | Operator::F64ConvertUI32 => {
+                // TODO
+            }
+            Operator::F64ConvertSI64 | Operator::F64ConvertUI64 => {
+                // TODO
+            }
+            Operator::F64PromoteF32 => {
+                // TODO
+            }
+            Operator::I32ReinterpretF32 => {
+                node.add_operation(i, AbstractExpression::Reinterpret{from: Type::F32, to: Type::I32});
+            }
+            Operator::I64ReinterpretF64 => {
+                node.add_operation(i, AbstractExpression::Reinterpret{from: Type::F64, to: Type::I64});
+            }
+            Operator::F32ReinterpretI32 => {
+                node.add_operation(i, AbstractExpression::Reinterpret{from: Type::I32, to: Type::F32});
+            }
+            Operator::F64ReinterpretI64 => {
+                node.add_operation(i, AbstractExpression::Reinterpret{from: Type::I64, to: Type::F64});
+            }
+            Operator::I32TruncSSatF32 | Operator::I32TruncUSatF32 => {
+                let signed = matches!(op, Operator::I32TruncSSatF32);
+                node.add_operation(i, AbstractExpression::Truncate{from: Type::F32, to: Type::I32, signed});
+            }
+            Operator::I32TruncSSatF64 | Operator::I32TruncUSatF64 => {
+                let signed = matches!(op, Operator::I32TruncSSatF64);
+                node.add_operation(i, AbstractExpression::Truncate{from: Type::F64, to: Type::I32, signed});
+            }
+            Operator::I64TruncSSatF32 | Operator::I64TruncUSatF32 => {
+                let signed = matches!(op, Operator::I64TruncSSatF32);
+                node.add_operation(i, AbstractExpression::Truncate{from: Type::F32, to: Type::I64, signed});
+            }
+            Operator::I64TruncSSatF64 | Operator::I64TruncUSatF64 => {
+                let signed = matches!(op, Operator::I64TruncSSatF64);
+                node.add_operation(i, AbstractExpression::Truncate{from: Type::F64, to: Type::I64, signed});
+            }
+            Operator::I32Extend16S | Operator::I32Extend8S => {
+                let bits = if matches!(op, Operator::I32Extend8S) { 8 } else { 16 };
+                node.add_operation(i, AbstractExpression::Extend{from: Type::I32, bits, to: Type::I32, signed: true});
+            }
+            Operator::I64Extend32S | Operator::I64Extend16S | Operator::I64Extend8S => {
+                let bits = match op { Operator::I64Extend8S => 8, Operator::I64Extend16S => 16, _ => 32 };
+                node.add_operation(i, AbstractExpression::Extend{from: Type::I64, bits, to: Type::I64, signed: true});
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // records atomic read-modify-writes, waits and wakes as synchronizing accesses
+    fn visit_atomic(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let index = self.index;
+        let offset = self.offset;
+
+        // atomic operations synchronize through the address they take
+        let synchronize = |node:&mut Node, memarg:&MemoryImmediate, kind:SyncKind| {
+            let access = sync_access(node, i, op, memarg, kind);
+            node.add_atomic_access(i, offset, access, kind);
+        };
+        match op {
+            Operator::I32AtomicRmwAdd { ref memarg }
+            | Operator::I32AtomicRmw16UAdd { ref memarg } 
+            | Operator::I32AtomicRmw8UAdd { ref memarg } => {
+                node.add_operation(i, AbstractExpression::Add{ty: Type::I32});
+                synchronize(node, memarg, SyncKind::ReadModifyWrite);
+            }
+            Operator::I64AtomicRmwAdd { ref memarg } 
+            | Operator::I64AtomicRmw32UAdd { ref memarg } 
+            | Operator::I64AtomicRmw8UAdd { ref memarg } => {
+                node.add_operation(i, AbstractExpression::Add{ty: Type::I64});
+                synchronize(node, memarg, SyncKind::ReadModifyWrite);
+            }
+            | Operator::I32AtomicRmwSub { ref memarg }
+            | Operator::I32AtomicRmwAnd { ref memarg }
+            | Operator::I32AtomicRmwOr { ref memarg }
+            | Operator::I32AtomicRmwXor { ref memarg }
+            | Operator::I32AtomicRmw16USub { ref memarg }
+            | Operator::I32AtomicRmw16UAnd { ref memarg }
+            | Operator::I32AtomicRmw16UOr { ref memarg }
+            | Operator::I32AtomicRmw16UXor { ref memarg }
+            | Operator::I32AtomicRmw8USub { ref memarg }
+            | Operator::I32AtomicRmw8UAnd { ref memarg }
+            | Operator::I32AtomicRmw8UOr { ref memarg }
+            | Operator::I32AtomicRmw8UXor { ref memarg } => {
+                synchronize(node, memarg, SyncKind::ReadModifyWrite);
+            }
+            Operator::I64AtomicRmw32UAdd { ref memarg }
+            | Operator::I64AtomicRmw16UAdd { ref memarg }
+            | Operator::I64AtomicRmw8UAdd { ref memarg }  => {
+                node.add_operation(i, AbstractExpression::Add{ty: Type::I64});
+                synchronize(node, memarg, SyncKind::ReadModifyWrite);
+            }
+            Operator::I64AtomicRmwSub { ref memarg }
+            | Operator::I64AtomicRmwAnd { ref memarg }
+            | Operator::I64AtomicRmwOr { ref memarg }
+            | Operator::I64AtomicRmwXor { ref memarg }
+            | Operator::I64AtomicRmw32USub { ref memarg }
+            | Operator::I64AtomicRmw32UAnd { ref memarg }
+            | Operator::I64AtomicRmw32UOr { ref memarg }
+            | Operator::I64AtomicRmw32UXor { ref memarg }
+            | Operator::I64AtomicRmw16USub { ref memarg }
+            | Operator::I64AtomicRmw16UAnd { ref memarg }
+            | Operator::I64AtomicRmw16UOr { ref memarg }
+            | Operator::I64AtomicRmw16UXor { ref memarg }
+            | Operator::I64AtomicRmw8USub { ref memarg }
+            | Operator::I64AtomicRmw8UAnd { ref memarg }
+            | Operator::I64AtomicRmw8UOr { ref memarg }
+            | Operator::I64AtomicRmw8UXor { ref memarg } => {
+                synchronize(node, memarg, SyncKind::ReadModifyWrite);
+            }
+            Operator::I32AtomicRmwXchg { ref memarg }
+            | Operator::I32AtomicRmw16UXchg { ref memarg }
+            | Operator::I32AtomicRmw8UXchg { ref memarg } => {
+                synchronize(node, memarg, SyncKind::Exchange);
+            }
+            Operator::I32AtomicRmwCmpxchg { ref memarg }
+            | Operator::I32AtomicRmw16UCmpxchg { ref memarg }
+            | Operator::I32AtomicRmw8UCmpxchg { ref memarg } => {
+                synchronize(node, memarg, SyncKind::CompareExchange);
+            }
+            Operator::I64AtomicRmwXchg { ref memarg }
+            | Operator::I64AtomicRmw32UXchg { ref memarg }
+            | Operator::I64AtomicRmw16UXchg { ref memarg }
+            | Operator::I64AtomicRmw8UXchg { ref memarg } => {
+                synchronize(node, memarg, SyncKind::Exchange);
+            }
+            Operator::I64AtomicRmwCmpxchg { ref memarg }
+            | Operator::I64AtomicRmw32UCmpxchg { ref memarg }
+            | Operator::I64AtomicRmw16UCmpxchg { ref memarg }
+            | Operator::I64AtomicRmw8UCmpxchg { ref memarg } => {
+                synchronize(node, memarg, SyncKind::CompareExchange);
+            }
+            Operator::Wake { ref memarg } => {
+                synchronize(node, memarg, SyncKind::Wake);
+                self.mapper.warn(index, Some(i), WarningCategory::Synchronization, format!("Node {} wakes threads waiting on region {:#x}, so it isn't combinational", index, memarg.offset));
+            }
+            Operator::I32Wait { ref memarg }
+            | Operator::I64Wait { ref memarg } => {
+                synchronize(node, memarg, SyncKind::Wait);
+                self.mapper.warn(index, Some(i), WarningCategory::Synchronization, format!("Node {} waits on region {:#x}, so it isn't combinational", index, memarg.offset));
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+
+    // couples vector loads and stores and records the lane operations of vectors
+    fn visit_simd(&mut self, i:usize, op:&Operator) -> Flow {
+        let node = &mut *self.node;
+        let printer = &mut *self.printer;
+        let offset = self.offset;
+        match op {
+            Operator::V128Load { ref memarg } => {
+                let var_id = node.add_vector_input_variable(self.mapper.lane_shape);
+                let access = mem_access(node, i, op, memarg, AccessKind::Read);
+                node.add_input_data_coupling(access, var_id);
+                node.add_memory_access(offset, access);
+                printer.highlight(Highlight::Data);
+            }
+            Operator::V128Store { ref memarg } => {
+                let var_id = node.add_vector_output_variable(self.mapper.lane_shape);
+                let access = mem_access(node, i, op, memarg, AccessKind::Write);
+                node.add_output_data_coupling(access, var_id);
+                node.add_memory_access(offset, access);
+                printer.highlight(Highlight::Data);
+            }
+            Operator::V128Const { ref value } => {
+                node.add_constant(i, Type::V128);
+                node.add_lane_operation(i, LaneOperation::constant(self.mapper.lane_shape, value.bytes()));
+            }
+            Operator::V8x16Shuffle { ref lines } => {
+                node.add_lane_operation(i, LaneOperation::shuffle(lines));
+            }
+            Operator::I8x16Splat => {
+                node.add_lane_operation(i, LaneOperation::splat(LaneShape::I8x16));
+            }
+            Operator::I16x8Splat => {
+                node.add_lane_operation(i, LaneOperation::splat(LaneShape::I16x8));
+            }
+            Operator::I32x4Splat => {
+                node.add_lane_operation(i, LaneOperation::splat(LaneShape::I32x4));
+            }
+            Operator::I64x2Splat => {
+                node.add_lane_operation(i, LaneOperation::splat(LaneShape::I64x2));
+            }
+            Operator::F32x4Splat => {
+                node.add_lane_operation(i, LaneOperation::splat(LaneShape::F32x4));
+            }
+            Operator::F64x2Splat => {
+                node.add_lane_operation(i, LaneOperation::splat(LaneShape::F64x2));
+            }
+            Operator::I8x16ExtractLaneS { line } | Operator::I8x16ExtractLaneU { line } => { 
+                node.add_lane_operation(i, LaneOperation::extract(LaneShape::I8x16, *line as usize));
+            }
+            Operator::I16x8ExtractLaneS { line } | Operator::I16x8ExtractLaneU { line } => { 
+                node.add_lane_operation(i, LaneOperation::extract(LaneShape::I16x8, *line as usize));
+            }
+            Operator::I32x4ExtractLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::extract(LaneShape::I32x4, *line as usize));
+            }
+            Operator::I8x16ReplaceLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::replace(LaneShape::I8x16, *line as usize));
+            }
+            Operator::I16x8ReplaceLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::replace(LaneShape::I16x8, *line as usize));
+            }
+            Operator::I32x4ReplaceLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::replace(LaneShape::I32x4, *line as usize));
+            }
+            Operator::I64x2ExtractLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::extract(LaneShape::I64x2, *line as usize));
+            }
+            Operator::I64x2ReplaceLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::replace(LaneShape::I64x2, *line as usize));
+            }
+            Operator::F32x4ExtractLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::extract(LaneShape::F32x4, *line as usize));
+            }
+            Operator::F32x4ReplaceLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::replace(LaneShape::F32x4, *line as usize));
+            }
+            Operator::F64x2ExtractLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::extract(LaneShape::F64x2, *line as usize));
+            }
+            Operator::F64x2ReplaceLane { line } => { 
+                node.add_lane_operation(i, LaneOperation::replace(LaneShape::F64x2, *line as usize));
+            }
+            Operator::I8x16Add => {
+                node.add_lane_operation(i, LaneOperation::lanewise(LaneShape::I8x16, AbstractExpression::Add{ty: Type::I32}));
+            }
+            Operator::I16x8Add => {
+                node.add_lane_operation(i, LaneOperation::lanewise(LaneShape::I16x8, AbstractExpression::Add{ty: Type::I32}));
+            }
+            Operator::I32x4Add => {
+                node.add_lane_operation(i, LaneOperation::lanewise(LaneShape::I32x4, AbstractExpression::Add{ty: Type::I32}));
+            }
+            Operator::I64x2Add => {
+                node.add_lane_operation(i, LaneOperation::lanewise(LaneShape::I64x2, AbstractExpression::Add{ty: Type::I64}));
+            }
+            Operator::I8x16Mul => {
+                node.add_lane_operation(i, LaneOperation::lanewise(LaneShape::I8x16, AbstractExpression::Mul{ty: Type::I32}));
+            }
+            Operator::I16x8Mul => {
+                node.add_lane_operation(i, LaneOperation::lanewise(LaneShape::I16x8, AbstractExpression::Mul{ty: Type::I32}));
+            }
+            Operator::I32x4Mul => {
+                node.add_lane_operation(i, LaneOperation::lanewise(LaneShape::I32x4, AbstractExpression::Mul{ty: Type::I32}));
+            }
+            Operator::I8x16Eq
+            | Operator::I8x16Ne
+            | Operator::I8x16LtS
+            | Operator::I8x16LtU
+            | Operator::I8x16GtS
+            | Operator::I8x16GtU
+            | Operator::I8x16LeS
+            | Operator::I8x16LeU
+            | Operator::I8x16GeS
+            | Operator::I8x16GeU
+            | Operator::I16x8Eq
+            | Operator::I16x8Ne
+            | Operator::I16x8LtS
+            | Operator::I16x8LtU
+            | Operator::I16x8GtS
+            | Operator::I16x8GtU
+            | Operator::I16x8LeS
+            | Operator::I16x8LeU
+            | Operator::I16x8GeS
+            | Operator::I16x8GeU
+            | Operator::I32x4Eq
+            | Operator::I32x4Ne
+            | Operator::I32x4LtS
+            | Operator::I32x4LtU
+            | Operator::I32x4GtS
+            | Operator::I32x4GtU
+            | Operator::I32x4LeS
+            | Operator::I32x4LeU
+            | Operator::I32x4GeS
+            | Operator::I32x4GeU
+            | Operator::F32x4Eq
+            | Operator::F32x4Ne
+            | Operator::F32x4Lt
+            | Operator::F32x4Gt
+            | Operator::F32x4Le
+            | Operator::F32x4Ge
+            | Operator::F64x2Eq
+            | Operator::F64x2Ne
+            | Operator::F64x2Lt
+            | Operator::F64x2Gt
+            | Operator::F64x2Le
+            | Operator::F64x2Ge
+            | Operator::V128And
+            | Operator::V128Or
+            | Operator::V128Xor
+            | Operator::I8x16AddSaturateS
+            | Operator::I8x16AddSaturateU
+            | Operator::I8x16Sub
+            | Operator::I8x16SubSaturateS
+            | Operator::I8x16SubSaturateU
+            | Operator::I16x8AddSaturateS
+            | Operator::I16x8AddSaturateU
+            | Operator::I16x8Sub
+            | Operator::I16x8SubSaturateS
+            | Operator::I16x8SubSaturateU
+            | Operator::I32x4Sub
+            | Operator::I64x2Sub
+            | Operator::F32x4Add
+            | Operator::F32x4Sub
+            | Operator::F32x4Mul
+            | Operator::F32x4Div
+            | Operator::F32x4Min
+            | Operator::F32x4Max
+            | Operator::F64x2Add
+            | Operator::F64x2Sub
+            | Operator::F64x2Mul
+            | Operator::F64x2Div
+            | Operator::F64x2Min
+            | Operator::F64x2Max => { 
+                // TODO 
+            }
+            Operator::V128Not
+            | Operator::I8x16Neg
+            | Operator::I16x8Neg
+            | Operator::I32x4Neg
+            | Operator::I64x2Neg
+            | Operator::F32x4Abs
+            | Operator::F32x4Neg
+            | Operator::F32x4Sqrt
+            | Operator::F64x2Abs
+            | Operator::F64x2Neg
+            | Operator::F64x2Sqrt
+            | Operator::I32x4TruncSF32x4Sat
+            | Operator::I32x4TruncUF32x4Sat
+            | Operator::I64x2TruncSF64x2Sat
+            | Operator::I64x2TruncUF64x2Sat
+            | Operator::F32x4ConvertSI32x4
+            | Operator::F32x4ConvertUI32x4
+            | Operator::F64x2ConvertSI64x2
+            | Operator::F64x2ConvertUI64x2 => { 
+                // TODO 
+            }
+            Operator::V128Bitselect => { 
+                // TODO 
+            }
+            Operator::I8x16AnyTrue
+            | Operator::I8x16AllTrue
+            | Operator::I16x8AnyTrue
+            | Operator::I16x8AllTrue
+            | Operator::I32x4AnyTrue
+            | Operator::I32x4AllTrue
+            | Operator::I64x2AnyTrue
+            | Operator::I64x2AllTrue => { 
+                // TODO 
+            }
+            Operator::I8x16Shl
+            | Operator::I8x16ShrS
+            | Operator::I8x16ShrU
+            | Operator::I16x8Shl
+            | Operator::I16x8ShrS
+            | Operator::I16x8ShrU
+            | Operator::I32x4Shl
+            | Operator::I32x4ShrS
+            | Operator::I32x4ShrU
+            | Operator::I64x2Shl
+            | Operator::I64x2ShrS
+            | Operator::I64x2ShrU => { 
+                // TODO 
+            }
+            _ => ()
+        }
+        Flow::Continue
+    }
+}
+
+
+// asks the user a yes/no question; anything but an explicit no counts as a yes
+#[cfg(feature = "std-io")]
+fn confirm(question:&str) -> bool {
+    let mut input = String::new();
+    println!("{} (yes/no)?", question);
+    let _ = io::stdin().read_line(&mut input);
+    !(input == "no\n" || input == "n\n")
+}
+
+// without standard input there is nobody to ask, so every question is answered with a yes
+#[cfg(not(feature = "std-io"))]
+fn confirm(question:&str) -> bool {
+    println!("{} (yes/no)? yes", question);
+    true
+}
+
+// formats a row of a hexdump with the offset of its first byte and a description of its contents
+fn hexdump_line(offset:usize, bytes:&[u8], description:&str) -> String {
+    let hex:Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let line = format!("{:#010x}: {:<24} {}", offset, hex.join(" "), description);
+    format!("{}\n", line.trim_end())
+}
+
+
+// Initializes a Node mapper
+pub fn new_mapper() -> Mapper {
+    Mapper::default()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lanes::{Lane, LaneSource};
+    use std::collections::BTreeSet;
+    use std::fmt::Write;
+    use proptest::prelude::*;
+    use std::sync::{Arc, Mutex};
+    use hazards::{Hazard, HazardKind};
+    use interp;
+
+    fn function(id:usize, callees:&[usize]) -> Node {
+        let mut node = Node::default();
+        node.set_id(NodeId(id));
+        for (i, callee) in callees.iter().enumerate() {
+            node.add_call(i, NodeId(*callee));
+        }
+        node
+    }
+
+    // lists paths of node ids given as plain numbers, as the tree queries return them
+    fn paths(paths:&[&[usize]]) -> Vec<Vec<NodeId>> {
+        paths.iter().map(|path| path.iter().cloned().map(NodeId).collect()).collect()
+    }
+
+    #[test]
+    fn recursion_groups() {
+        let mut mapper = new_mapper();
+
+        // 0 and 1 are mutually recursive, 2 calls itself, and 3 calls into both groups
+        mapper.nodes.insert(NodeId(0), function(0, &[1]));
+        mapper.nodes.insert(NodeId(1), function(1, &[0]));
+        mapper.nodes.insert(NodeId(2), function(2, &[2]));
+        mapper.nodes.insert(NodeId(3), function(3, &[0, 2, 4]));
+        mapper.nodes.insert(NodeId(4), function(4, &[]));
+
+        // calls made from within nested blocks belong to the enclosing function
+        let block_id = mapper.add_block(function(0, &[3]));
+        mapper.nodes.get_mut(&NodeId(4)).unwrap().add_block(0, block_id);
+
+        assert_eq!(mapper.recursion_groups(), vec![vec![0, 1], vec![2], vec![3, 4]]);
+    }
+
+    #[test]
+    fn prune_unreachable() {
+        let mut mapper = new_mapper();
+
+        // 0 is exported, 1 is called before 0 returns, and 2 is only called after 0 returns
+        let mut exported = function(0, &[1]);
+        exported.add_call(7, NodeId(2));
+        exported.add_operation(4, AbstractExpression::Num{ val: 1 });
+        exported.add_operation(6, AbstractExpression::Num{ val: 2 });
+        exported.add_terminator(5);
+        exported.add_terminator(6);
+        mapper.nodes.insert(NodeId(0), exported);
+        mapper.exports.insert(0, String::from("main"));
+
+        // 1 owns a block calling 3, while 4 is never called at all
+        let block_id = mapper.add_block(function(0, &[3]));
+        let mut called = function(1, &[]);
+        called.add_block(0, block_id);
+        mapper.nodes.insert(NodeId(1), called);
+        mapper.nodes.insert(NodeId(2), function(2, &[]));
+        mapper.nodes.insert(NodeId(3), function(3, &[]));
+        let orphan_id = mapper.add_block(function(0, &[]));
+        let mut unused = function(4, &[]);
+        unused.add_block(0, orphan_id);
+        mapper.nodes.insert(NodeId(4), unused);
+
+        assert_eq!(mapper.prune_unreachable(Vec::new()), vec![2, 4]);
+        assert_eq!(mapper.blocks().keys().cloned().collect::<Vec<BlockId>>(), vec![block_id]);
+        assert_eq!(mapper.nodes[&NodeId(0)].get_operations().len(), 1);
+
+        // explicit roots take precedence over the exports
+        assert_eq!(mapper.prune_unreachable(vec![3]), vec![0, 1]);
+    }
+
+    #[test]
+    fn fold_constants() {
+        let mut node = Node::default();
+
+        // (2 + 3) * 4 folds to 20, which then can't fold with the spin
+        node.add_constant(1, Type::I32);
+        node.add_operation(1, AbstractExpression::Num{ val: 2 });
+        node.add_constant(2, Type::I32);
+        node.add_operation(2, AbstractExpression::Num{ val: 3 });
+        node.add_operation(3, AbstractExpression::Add{ ty: Type::I32 });
+        node.add_constant(4, Type::I32);
+        node.add_operation(4, AbstractExpression::Num{ val: 4 });
+        node.add_operation(5, AbstractExpression::Mul{ ty: Type::I32 });
+        node.add_operation(6, AbstractExpression::Spin{ id: 0 });
+        node.add_operation(7, AbstractExpression::Add{ ty: Type::I32 });
+
+        // the unrecorded instruction at 10 hides what is below the constant on the stack
+        node.add_constant(8, Type::I32);
+        node.add_operation(8, AbstractExpression::Num{ val: 0xffff_ffff });
+        node.add_constant(9, Type::I32);
+        node.add_operation(9, AbstractExpression::Num{ val: 1 });
+        node.add_operation(11, AbstractExpression::Add{ ty: Type::I32 });
+
+        assert_eq!(node.fold_constants(), 4);
+        assert_eq!(node.operation_locations(), vec![5, 6, 7, 8, 9, 11]);
+        match node.get_operations()[&5] {
+            AbstractExpression::Num { val } => assert_eq!(val, 20),
+            ref op => panic!("expected a folded constant, found {:?}", op)
+        }
+        assert_eq!(node.constants().len(), 3);
+    }
+
+    #[test]
+    fn eliminate_common_subexpressions() {
+        let mut mapper = new_mapper();
+        let mut node = function(0, &[]);
+
+        // (a * b) + (b * a) shares the second multiplication's result with the first
+        node.add_operation(1, AbstractExpression::Spin{ id: 0 });
+        node.add_operation(2, AbstractExpression::Spin{ id: 1 });
+        node.add_operation(3, AbstractExpression::Mul{ ty: Type::I32 });
+        node.add_operation(4, AbstractExpression::Spin{ id: 1 });
+        node.add_operation(5, AbstractExpression::Spin{ id: 0 });
+        node.add_operation(6, AbstractExpression::Mul{ ty: Type::I32 });
+        node.add_operation(7, AbstractExpression::Add{ ty: Type::I32 });
+        mapper.nodes.insert(NodeId(0), node.clone());
+
+        let plans = mapper.plan_lowering();
+        assert_eq!(plans[0].shared_operations, 1);
+        assert_eq!(plans[0].qubit_savings, 32 * 32);
+        assert_eq!(plans[0].operations, 5);
+        match mapper.nodes[&NodeId(0)].get_operations()[&6] {
+            AbstractExpression::Reuse { location } => assert_eq!(location, 3),
+            ref op => panic!("expected a shared result, found {:?}", op)
+        }
+
+        // nothing is shared once the pass is disabled
+        mapper.nodes.insert(NodeId(0), node);
+        mapper.set_common_subexpression_elimination(false);
+        let plans = mapper.plan_lowering();
+        assert_eq!(plans[0].shared_operations, 0);
+        assert_eq!(plans[0].auxiliary_qubits, 2 * 32 * 32 + 2 * 32);
+    }
+
+    #[test]
+    fn emit_ssa() {
+        let mut mapper = new_mapper();
+
+        // the else clause is anti-chained to the if's condition and returns 7
+        let mut else_clause = function(0, &[]);
+        else_clause.add_input_variable(Type::I32);
+        else_clause.add_flow_control_coupling(2, 0, false);
+        else_clause.add_constant(1, Type::I32);
+        else_clause.add_operation(1, AbstractExpression::Num{ val: 7 });
+        let else_id = mapper.add_block(else_clause);
+
+        // the if clause returns 5
+        let mut if_clause = function(0, &[]);
+        if_clause.add_input_variable(Type::I32);
+        if_clause.add_flow_control_coupling(2, 0, true);
+        if_clause.add_constant(1, Type::I32);
+        if_clause.add_operation(1, AbstractExpression::Num{ val: 5 });
+        if_clause.add_block(2, else_id);
+        let if_id = mapper.add_block(if_clause);
+
+        // (if (a) (then 5) (else 7)) + b
+        let mut node = function(0, &[]);
+        node.add_input_variable(Type::I32);
+        node.add_input_variable(Type::I32);
+        node.add_operation(1, AbstractExpression::Spin{ id: 0 });
+        node.add_internal_variable(2, Type::I32);
+        node.add_operation(2, AbstractExpression::Spin{ id: 2 });
+        node.add_block(2, if_id);
+        node.add_result_coupling(2, Type::I32);
+        node.add_operation(3, AbstractExpression::Spin{ id: 1 });
+        node.add_operation(4, AbstractExpression::Add{ ty: Type::I32 });
+        mapper.nodes.insert(NodeId(0), node);
+
+        let graph = mapper.emit_ssa(NodeId(0)).unwrap();
+        assert_eq!(graph.regions().len(), 3);
+        assert_eq!(graph.phis().len(), 1);
+        let phi = graph.phis()[0].value;
+        let sum = graph.instructions().last().unwrap();
+        match sum.definition {
+            Definition::Add { lhs, rhs } => {
+                assert_eq!(lhs, phi);
+                assert_eq!(graph.instruction(rhs).definition, Definition::Input{ var_id: 1 });
+            }
+            ref definition => panic!("expected an addition, found {:?}", definition)
+        }
+        assert_eq!(graph.uses(phi), vec![sum.value]);
+
+        // without the registered blocks, the node's own SSA form can't see the branches' values
+        let graph = mapper.nodes[&NodeId(0)].to_ssa();
+        assert_eq!(graph.regions().len(), 1);
+        assert_eq!(graph.phis().len(), 0);
+    }
+
+    #[test]
+    fn hexdump() {
+        let mut mapper = new_mapper();
+
+        // a function without locals adding its two parameters
+        let mut node = function(0, &[]);
+        node.set_function(true);
+        node.set_start(0x40);
+        node.set_end(0x47);
+        node.set_instrs(vec![0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b]);
+        mapper.nodes.insert(NodeId(0), node);
+
+        let dump = mapper.hexdump(NodeId(0)).unwrap();
+        let lines:Vec<&str> = dump.lines().collect();
+        assert_eq!(lines[0], "Node 0 spans 0x00000040..0x00000047 (7 bytes)");
+        assert_eq!(lines[1], "0x00000040: 00                       0 locals declarations");
+        assert_eq!(lines[2], "0x00000041: 20 00                    GetLocal { local_index: 0 }");
+        assert_eq!(lines[4], "0x00000045: 6a                       I32Add");
+        assert_eq!(lines.len(), 6);
+        assert!(mapper.hexdump(NodeId(1)).is_none());
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingObserver {
+        events: Arc<Mutex<Vec<String>>>
+    }
+
+    impl MapperObserver for RecordingObserver {
+        fn on_function_start(&mut self, function:usize) {
+            self.events.lock().unwrap().push(format!("start {}", function));
+        }
+
+        fn on_block_found(&mut self, parent:usize, block:usize) {
+            self.events.lock().unwrap().push(format!("block {} in {}", block, parent));
+        }
+
+        // only function 0 is expanded
+        fn on_decision_needed(&mut self, decision:&Decision) -> bool {
+            self.events.lock().unwrap().push(format!("{:?}", decision));
+            *decision == Decision::Parallelize { function: 0 }
+        }
+
+        fn on_warning(&mut self, message:&str) {
+            self.events.lock().unwrap().push(message.to_string());
+        }
+    }
+
+    #[test]
+    fn observer() {
+        let observer = RecordingObserver::default();
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(observer.clone()));
+
+        // function 0 calls itself and contains a block, function 1 is declined
+        let block_id = mapper.add_block(function(2, &[]));
+        let mut node = function(0, &[0]);
+        node.add_block(5, block_id);
+        let mut nodes = HashMap::new();
+        nodes.insert(NodeId(0), node);
+        mapper.expand_tree(nodes.clone());
+        nodes.insert(NodeId(1), function(1, &[]));
+        let mut events = observer.events.lock().unwrap().clone();
+        assert_eq!(events, vec![
+            "Parallelize { function: 0 }".to_string(),
+            "start 0".to_string(),
+            "block 1 in 0".to_string(),
+            "Skipping self referencing call in function 0".to_string()
+        ]);
+
+        // declined functions are left as they are
+        observer.events.lock().unwrap().clear();
+        let tree = mapper.expand_tree(nodes);
+        events = observer.events.lock().unwrap().clone();
+        assert!(events.contains(&"Parallelize { function: 1 }".to_string()));
+        assert!(!events.contains(&"start 1".to_string()));
+        assert!(tree[&NodeId(1)].children().is_empty());
+
+        // lowering asks before doing anything
+        observer.events.lock().unwrap().clear();
+        let mut node = function(1, &[]);
+        node.lower_with(&mut observer.clone());
+        events = observer.events.lock().unwrap().clone();
+        assert_eq!(events, vec!["Lower { node: 1, input_variables: 0, internal_variables: 0, constants: 0 }".to_string()]);
+    }
+
+    #[test]
+    fn local_index_space() {
+        let mut func = Node::default();
+
+        // parameters come first in the local index space, followed by the declared locals
+        assert_eq!(func.add_param(Type::I32), 0);
+        func.add_input_variable(Type::F32);
+        assert_eq!(func.add_param(Type::I64), 2);
+        assert_eq!(func.add_local(Type::F64), 2);
+        assert_eq!(func.get_locals(), vec![Type::I32, Type::I64, Type::F64]);
+
+        // each local is read from the same typed variable every time
+        assert_eq!(func.get_local_input_variable(1), Some(2));
+        assert_eq!(func.get_local_input_variable(2), Some(3));
+        assert_eq!(func.get_local_input_variable(2), Some(3));
+        assert_eq!(func.input_variables()[&3], Type::F64);
+        assert_eq!(func.get_local_input_variable(3), None);
+
+        // nested blocks share the function's locals, but read them through their own variables
+        let mut block = func.nested_block();
+        assert_eq!(block.get_locals(), func.get_locals());
+        assert_eq!(block.get_local_input_variable(1), Some(0));
+        assert_eq!(block.input_variables()[&0], Type::I64);
+
+        // writes are recorded as outputs
+        assert_eq!(block.get_local_output_variable(2), Some(0));
+        assert_eq!(block.get_local_output_variable(2), Some(0));
+        assert_eq!(block.get_local_output_variable(3), None);
+    }
+
+    #[test]
+    fn declared_locals() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/locals.wasm").to_vec());
+
+        // the declared locals follow the parameter, and the first operator follows the declarations
+        let func = &nodes[&NodeId(0)];
+        assert_eq!(func.get_locals(), vec![Type::I32, Type::I64, Type::F32, Type::I32]);
+        let operations = func.get_operations();
+        assert_eq!(operations.len(), 3);
+
+        // the local is a known zero when it is first read, but once the block has written it what it holds
+        // isn't known to the function, so the read isn't recorded rather than taken from its value on entry
+        assert!(matches!(operations[&1], AbstractExpression::Num { val: 0 }));
+        assert_eq!(func.constants()[&1], Type::I32);
+        assert!(matches!(operations[&2], AbstractExpression::Spin { id: 0 }));
+        assert!(!operations.contains_key(&6));
+        assert_eq!(func.get_local_value(3), Some(None));
+    }
+
+    #[test]
+    fn local_values() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/reassign.wasm").to_vec());
+
+        // a read of a local written within the node reuses the value written instead of the parameter's
+        let reassign = &nodes[&NodeId(0)];
+        assert!(matches!(reassign.get_operations()[&3], AbstractExpression::Reuse { location: 1 }));
+        let inputs:HashMap<usize, u64> = vec![(reassign.get_local_input_variables()[&0], 9)].into_iter().collect();
+        assert_eq!(interp::evaluate(reassign, &inputs).result(), Some(5));
+
+        // a tee writes the local too, and a later write replaces the value read
+        let tee = &nodes[&NodeId(1)];
+        let inputs:HashMap<usize, u64> = vec![(tee.get_local_input_variables()[&0], 9)].into_iter().collect();
+        assert_eq!(interp::evaluate(tee, &inputs).result(), Some(14));
+    }
+
+    #[test]
+    fn strict_mode() {
+        let buf = include_bytes!("../tests/parallelization/locals.wasm").to_vec();
+        let drop = UnsupportedOperator { operator: "Drop".to_string(), offset: 52 };
+
+        // by default unsupported operators are only recorded
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        assert!(mapper.try_map(buf.clone()).is_ok());
+        assert_eq!(mapper.get_unsupported_operators(), vec![drop.clone()]);
+
+        // a strict mapper fails with them
+        mapper.set_strict(true);
+        let err = mapper.try_map(buf.clone()).unwrap_err();
+        assert_eq!(err.operators, vec![drop]);
+
+        // unless the support matrix tolerates them
+        let mut support = SupportMatrix::new();
+        support.set("Drop", Support::Coupling);
+        mapper.set_support_matrix(support);
+        assert!(mapper.try_map(buf).is_ok());
+        assert!(mapper.get_unsupported_operators().is_empty());
+    }
+
+    #[test]
+    fn hazard_report() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.map(include_bytes!("../tests/parallelization/memory.wasm").to_vec());
+        let report = mapper.hazard_report();
+
+        // produce reads the value it just stored
+        let internal:Vec<&Hazard> = report.hazards.iter().filter(|hazard| hazard.is_internal()).collect();
+        assert_eq!(internal.len(), 1);
+        assert_eq!(internal[0].kind, HazardKind::ReadAfterWrite);
+        assert_eq!(internal[0].region, 16);
+        assert_eq!(internal[0].earlier.node, 0);
+
+        // consume overwrites the region produce stores to and reads back, as well as the one it loads from
+        let across:Vec<(HazardKind, usize)> = report.between(0, 1).iter().map(|hazard| (hazard.kind, hazard.region)).collect();
+        assert_eq!(across, vec![
+            (HazardKind::WriteAfterRead, 16),
+            (HazardKind::WriteAfterWrite, 16),
+            (HazardKind::WriteAfterRead, 32)
+        ]);
+        assert!(!report.can_run_in_parallel(0, 1));
+    }
+
+    #[test]
+    fn speedup_estimate() {
+        let mut mapper = new_mapper();
+
+        // 0 calls 1, while 2 is independent of both
+        for (id, callees, product) in [(0, vec![1], false), (1, vec![], false), (2, vec![], true)].iter() {
+            let mut func = function(*id, callees);
+            func.set_function(true);
+            func.add_operation(10, AbstractExpression::Num{ val: 2 });
+            if *id != 0 {
+                func.add_operation(11, AbstractExpression::Num{ val: 3 });
+                func.add_operation(12, if *product { AbstractExpression::Mul{ ty: Type::I32 } } else { AbstractExpression::Add{ ty: Type::I32 } });
+            }
+            mapper.nodes.insert(NodeId(*id), func);
+        }
+
+        // 0 has to wait for 1, so the critical path runs through both
+        let estimate = mapper.speedup_estimate();
+        assert_eq!(estimate, SpeedupEstimate { work: 7, critical_path: 3 });
+        assert!((estimate.speedup() - 7.0 / 3.0).abs() < 1e-9);
+        assert_eq!(mapper.critical_path(), vec![1, 0]);
+
+        // an expensive multiplication moves the critical path to 2
+        let mut weights = OperationWeights::new();
+        weights.set("Mul", 4);
+        mapper.set_operation_weights(weights);
+        assert_eq!(mapper.speedup_estimate(), SpeedupEstimate { work: 10, critical_path: 5 });
+        assert_eq!(mapper.critical_path(), vec![2]);
+    }
+
+    #[test]
+    fn roundtrip_validation() {
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(RecordingObserver { events: warnings.clone() }));
+        mapper.set_roundtrip_validation(8);
+        mapper.map(include_bytes!("../tests/parallelization/locals.wasm").to_vec());
+
+        // the straight-line code before the block agrees with its recorded operations
+        let report = mapper.validate_roundtrip(8, 1);
+        assert_eq!(report.nodes, vec![0]);
+        assert_eq!(report.comparisons, 24);
+        assert!(report.mismatches.is_empty());
+        assert!(!warnings.lock().unwrap().iter().any(|event| event.contains("diverges")));
+    }
+
+    #[test]
+    fn predication() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.set_predication(0, true);
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/branches.wasm").to_vec());
+
+        // pick's diamond is now a single branch that always runs, with no else clause or phi left
+        let pick = &nodes[&NodeId(0)];
+        let branch = &pick.children()[&pick.calls()[&6]];
+        assert!(branch.is_predicated());
+        assert!(branch.children().is_empty());
+        let graph = mapper.emit_ssa(NodeId(0)).unwrap();
+        assert!(!graph.instructions().iter().any(|instruction| instruction.definition.name() == "Phi"));
+
+        // it still picks the same values
+        let variables = pick.get_local_input_variables();
+        for &(condition, result) in [(1, 6), (7, 6), (0, 10)].iter() {
+            let mut inputs = HashMap::new();
+            inputs.insert(variables[&0], condition);
+            inputs.insert(variables[&1], 5);
+            assert_eq!(interp::evaluate(pick, &inputs).result(), Some(result));
+        }
+
+        // pick_twice wasn't selected, but it calls pick's predicated form
+        let mut inputs = HashMap::new();
+        inputs.insert(nodes[&NodeId(1)].get_local_input_variables()[&0], 5);
+        assert_eq!(interp::evaluate(&nodes[&NodeId(1)], &inputs).result(), Some(16));
+    }
+
+    #[test]
+    fn predication_types() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.set_predication(0, true);
+        mapper.set_predication(1, true);
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/predicated.wasm").to_vec());
+
+        // pick_wide's diamond is predicated with arithmetic of its i64 result, so wide values are picked whole
+        let pick = &nodes[&NodeId(0)];
+        assert!(pick.children()[&pick.calls()[&2]].is_predicated());
+        let variables = pick.get_local_input_variables();
+        for &(condition, result) in [(1, (1 << 40) + 1), (0, (1 << 40) + 2)].iter() {
+            let mut inputs = HashMap::new();
+            inputs.insert(variables[&0], condition);
+            inputs.insert(variables[&1], 1 << 40);
+            assert_eq!(interp::evaluate(pick, &inputs).result(), Some(result));
+        }
+
+        // pick_float's branches are straight-line, but floats can't be selected by multiplying with the condition
+        let pick = &nodes[&NodeId(1)];
+        assert!(!pick.children()[&pick.calls()[&2]].is_predicated());
+    }
+
+    #[test]
+    fn operator_histogram() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/branches.wasm").to_vec());
+
+        // pick's nops and if/else/ends are control, while its local reads, constants and arithmetic aren't
+        let histogram = nodes[&NodeId(0)].operator_histogram();
+        assert_eq!((histogram.arithmetic, histogram.control, histogram.total()), (7, 8, 15));
+        assert_eq!(mapper.module_histogram(), OperatorHistogram { arithmetic: 12, control: 11, ..OperatorHistogram::default() });
+
+        // overriding the support of an operator moves it to the unsupported category
+        let mut support = SupportMatrix::new();
+        support.set("I32Mul", Support::Unsupported);
+        mapper.set_support_matrix(support);
+        assert_eq!(mapper.module_histogram().unsupported, 1);
+    }
+
+    #[test]
+    fn warnings() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // function 0 calls itself at 0 and the imported function 3 at 1
+        let mut nodes = HashMap::new();
+        nodes.insert(NodeId(0), function(0, &[0, 3]));
+        mapper.expand_tree(nodes);
+        let mut warnings = mapper.get_warnings();
+        warnings.sort_by_key(|warning| warning.location);
+        let warnings:Vec<(usize, Option<usize>, WarningCategory)> = warnings.iter()
+            .map(|warning| (warning.node, warning.location, warning.category))
+            .collect();
+        assert_eq!(warnings, vec![
+            (0, Some(0), WarningCategory::SelfReference),
+            (0, Some(1), WarningCategory::ImportedCall)
+        ]);
+
+        // each mapping starts over
+        mapper.map(include_bytes!("../tests/parallelization/branches.wasm").to_vec());
+        assert!(mapper.get_warnings().is_empty());
+    }
+
+    #[test]
+    fn constraint_composition() {
+        let spin = Constraint::from_expression(1, PhysicalExpression::Spin{ val: true });
+        let binary = Constraint::from_expression(2, PhysicalExpression::Binary{ val: false });
+
+        // the empty constraint is the identity of addition
+        let sum = Constraint::new(0) + spin.clone();
+        assert_eq!(sum.get_id(), 0);
+        assert_eq!(format!("{:?}", sum.get_expression()), format!("{:?}", spin.get_expression()));
+        assert!((Constraint::new(0) + Constraint::new(1)).is_empty());
+
+        // otherwise both expressions are kept
+        let sum = spin.clone() + binary;
+        match sum.get_expression() {
+            Some(PhysicalExpression::Add { .. }) => (),
+            expression => panic!("expected a sum, found {:?}", expression)
+        }
+
+        // scaling by zero empties a constraint and scaling by one keeps it
+        assert!((spin.clone() * 0).is_empty());
+        assert_eq!(format!("{:?}", (spin.clone() * 1).get_expression()), format!("{:?}", spin.get_expression()));
+        assert!((Constraint::new(0) * 3).is_empty());
+        match (spin * 3).get_expression() {
+            Some(PhysicalExpression::Mul { ref operand_two, .. }) => match **operand_two {
+                PhysicalExpression::Num { val } => assert_eq!(val, 3),
+                ref operand => panic!("expected the scalar, found {:?}", operand)
+            },
+            expression => panic!("expected a product, found {:?}", expression)
+        }
+    }
+
+    #[test]
+    fn physical_expression() {
+        let expression = PhysicalExpression::sum(
+            PhysicalExpression::product(PhysicalExpression::num(2), PhysicalExpression::product(PhysicalExpression::spin(true), PhysicalExpression::spin(false))),
+            PhysicalExpression::binary(true)
+        );
+        assert_eq!(expression.to_string(), "(2 * (s+ * s-) + b1)");
+
+        // a visitor sees every expression, parents before and after their operands
+        struct Depth { current: usize, deepest: usize, leaves: usize }
+        impl ExpressionVisitor for Depth {
+            fn enter(&mut self, expression:&PhysicalExpression) {
+                self.current += 1;
+                self.deepest = self.deepest.max(self.current);
+                if expression.operands().is_empty() {
+                    self.leaves += 1;
+                }
+            }
+            fn leave(&mut self, _expression:&PhysicalExpression) {
+                self.current -= 1;
+            }
+        }
+        let mut depth = Depth { current: 0, deepest: 0, leaves: 0 };
+        expression.walk(&mut depth);
+        assert_eq!((depth.current, depth.deepest, depth.leaves), (0, 4, 4));
+    }
+
+    #[test]
+    fn block_results() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/branches.wasm").to_vec());
+
+        // pick's if leaves an i32, which both of its branches produce; the else clause's result is coupled to
+        // pick through the if's coupling rather than to the branch it is nested in
+        let pick = &nodes[&NodeId(0)];
+        let branch = &pick.children()[&pick.calls()[&6]];
+        let else_clause = &branch.children()[&branch.calls()[&4]];
+        let var_id = pick.get_result_couplings()[&6];
+        assert_eq!(pick.input_variables()[&var_id], Type::I32);
+        assert!(!pick.input_variable_is_param(var_id));
+        assert!(branch.get_result_couplings().is_empty());
+        for node in [branch, else_clause].iter() {
+            let var_id = node.get_result_variable().unwrap();
+            assert_eq!(node.output_variables[&var_id], Type::I32);
+        }
+
+        // a block's result is pushed at its location, so a local written with it reuses it
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/results.wasm").to_vec());
+        let stash = &nodes[&NodeId(0)];
+        let location = *stash.get_result_couplings().keys().next().unwrap();
+        assert_eq!(stash.get_local_value(1), Some(Some(location)));
+        let mut inputs = HashMap::new();
+        inputs.insert(stash.get_local_input_variables()[&0], 4);
+        let evaluation = interp::evaluate(stash, &inputs);
+        assert_eq!((evaluation.values[&location], evaluation.result()), (7, Some(14)));
+        let block = &evaluation.children[&location];
+        assert_eq!(block.outputs[&stash.children()[&stash.calls()[&location]].get_result_variable().unwrap()], 7);
+        let graph = mapper.emit_ssa(NodeId(0)).unwrap();
+        match graph.instructions().last().unwrap().definition {
+            Definition::Mul { lhs, .. } => assert_eq!(graph.instruction(lhs).definition.name(), "Add"),
+            ref definition => panic!("expected a multiplication, found {:?}", definition)
+        }
+
+        // without the registry, the node reads the block's result from the variable coupled to it, so it lowers
+        let var_id = stash.get_result_couplings()[&location];
+        assert!(stash.to_ssa().instructions().iter().any(|instruction| instruction.definition == Definition::Input{ var_id } && instruction.ty == Type::I32));
+        let constraint = stash.clone().lower();
+        assert!(!constraint.qubo().is_empty() && !constraint.get_result_bits().is_empty());
+        assert!(mapper.annealability_report(1000)[0].qubits > 0);
+
+        // blocks without a result type have nothing to couple
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/locals.wasm").to_vec());
+        assert!(!nodes[&NodeId(0)].blocks().is_empty());
+        assert!(nodes[&NodeId(0)].get_result_couplings().is_empty());
+    }
+
+    #[test]
+    fn atomics() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/atomics.wasm").to_vec());
+
+        // updates read and write their region atomically, and keep a node combinational
+        let kinds = |node:&Node, region:usize| -> Vec<SyncKind> {
+            let couplings = node.get_sync_couplings();
+            couplings.iter().filter(|(access, _)| access.static_offset == region).flat_map(|(_, couplings)| couplings.iter().map(|coupling| coupling.kind)).collect()
+        };
+        assert_eq!(kinds(&nodes[&NodeId(0)], 16), vec![SyncKind::ReadModifyWrite]);
+        assert_eq!(kinds(&nodes[&NodeId(1)], 16), vec![SyncKind::CompareExchange]);
+
+        // they are keyed by the access they make, which for the exchange is a write of a word at a constant address
+        let swap = MemAccess { static_offset: 16, align: 4, width: 4, base_expr: None, address: Some(16), kind: AccessKind::Write };
+        assert_eq!(nodes[&NodeId(1)].get_sync_couplings().keys().cloned().collect::<Vec<MemAccess>>(), vec![swap]);
+        let accesses:Vec<AccessKind> = nodes[&NodeId(1)].get_memory_accesses().iter().map(|memory| memory.access.kind).collect();
+        assert_eq!(accesses, vec![AccessKind::Read, AccessKind::Write]);
+        assert!(nodes[&NodeId(0)].is_combinational() && nodes[&NodeId(1)].is_combinational());
+
+        // waits and wakes don't, and are reported
+        assert_eq!(kinds(&nodes[&NodeId(2)], 32), vec![SyncKind::Wait]);
+        assert_eq!(kinds(&nodes[&NodeId(3)], 32), vec![SyncKind::Wake]);
+        assert!(!nodes[&NodeId(2)].is_combinational() && !nodes[&NodeId(3)].is_combinational());
+        let warnings:Vec<(usize, WarningCategory)> = mapper.get_warnings().iter()
+            .filter(|warning| warning.category == WarningCategory::Synchronization)
+            .map(|warning| (warning.node, warning.category))
+            .collect();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.contains(&(2, WarningCategory::Synchronization)));
+        assert!(warnings.contains(&(3, WarningCategory::Synchronization)));
+    }
+
+    #[test]
+    fn simd_lanes() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/simd.wasm").to_vec());
+        assert!(mapper.get_warnings().is_empty());
+
+        // loaded and stored vectors are divided into the configured lanes, with the stored one following
+        // the function's result
+        let scale = &nodes[&NodeId(0)];
+        assert_eq!(scale.get_input_lanes(0), Some(vec![Type::I32; 4]));
+        assert_eq!(scale.get_output_lanes(1), Some(vec![Type::I32; 4]));
+
+        // the splatted factor multiplies every lane, and a single lane is extracted at the end
+        let lanes = scale.get_lane_operations();
+        assert_eq!(lanes[&5], LaneOperation::splat(LaneShape::I32x4));
+        assert_eq!(lanes[&6].expressions(), vec![AbstractExpression::Mul{ty: Type::I32}; 4]);
+        assert_eq!(lanes[&10], LaneOperation::extract(LaneShape::I32x4, 2));
+
+        // the multiplication is only recorded, so the node can't be simulated
+        let unsupported:Vec<String> = mapper.get_unsupported_operators().into_iter().map(|op| op.operator).collect();
+        assert_eq!(unsupported, vec!["I32x4Mul"]);
+
+        // shuffles and replacements are re-wirings only
+        let swizzle = &nodes[&NodeId(1)].get_lane_operations();
+        assert!(swizzle.values().all(|op| op.is_rewiring()));
+        assert_eq!(swizzle[&5].lanes[0], Lane::Wire(LaneSource::Lane { operand: 0, lane: 4 }));
+        assert_eq!(swizzle[&7], LaneOperation::replace(LaneShape::I32x4, 1));
+
+        // the configured lanes apply to the next mapping
+        mapper.set_lane_shape(LaneShape::I64x2);
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/simd.wasm").to_vec());
+        assert_eq!(nodes[&NodeId(0)].get_input_lanes(0), Some(vec![Type::I64; 2]));
+    }
+
+    #[test]
+    fn memory_size() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // a memory that never grows keeps its declared initial size, which is folded into the address
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/memory_size.wasm").to_vec());
+        let limits = mapper.get_memory_limits().unwrap();
+        assert_eq!((limits.initial, limits.maximum), (2, Some(4)));
+        assert!(!mapper.grows_memory());
+        match nodes[&NodeId(0)].get_operations()[&3] {
+            AbstractExpression::Num { val } => assert_eq!(val, 2 * 65532),
+            ref op => panic!("unexpected operation {:?}", op)
+        }
+        assert!(mapper.dynamic_memory_nodes().is_empty() && !mapper.has_external_memory());
+
+        // an exported or imported memory can be grown by the host, so its size is only known when running
+        for fixture in [&include_bytes!("../tests/parallelization/memory_exported.wasm")[..], &include_bytes!("../tests/parallelization/memory_imported.wasm")[..]] {
+            let nodes = mapper.map(fixture.to_vec());
+            assert!(mapper.has_external_memory() && !mapper.grows_memory());
+            assert_eq!(mapper.get_memory_limits().map(|limits| limits.initial), Some(2));
+            let var_id = nodes[&NodeId(0)].get_memory_size_variables()[&1];
+            assert_eq!(nodes[&NodeId(0)].get_operations()[&1], AbstractExpression::Spin { id: var_id });
+            assert_eq!(mapper.dynamic_memory_nodes(), vec![0]);
+        }
+
+        // once it grows, its size is only known when running and the load that depends on it is flagged
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/memory_grow.wasm").to_vec());
+        assert!(mapper.grows_memory() && nodes[&NodeId(1)].grows_memory());
+        let var_id = nodes[&NodeId(0)].get_memory_size_variables()[&1];
+        match nodes[&NodeId(0)].get_operations()[&1] {
+            AbstractExpression::Spin { id } => assert_eq!(id, var_id),
+            ref op => panic!("unexpected operation {:?}", op)
+        }
+        assert_eq!(mapper.dynamic_memory_nodes(), vec![0]);
+        let warnings:Vec<usize> = mapper.get_warnings().iter()
+            .filter(|warning| warning.category == WarningCategory::DynamicMemory)
+            .map(|warning| warning.node)
+            .collect();
+        assert_eq!(warnings, vec![0]);
+    }
+
+    #[test]
+    fn bulk_memory() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/bulk.wasm").to_vec());
+
+        // a copy with constant operands moves known ranges
+        let copies = nodes[&NodeId(0)].get_range_couplings();
+        assert_eq!(copies.len(), 1);
+        assert!(copies[0].is_static());
+        assert_eq!((copies[0].destination, copies[0].source, copies[0].length), (Some(64), Some(16), Some(4)));
+        let accesses:Vec<(usize, AccessKind)> = nodes[&NodeId(0)].get_memory_accesses().iter().map(|memory| (memory.access.region(), memory.access.kind)).collect();
+        assert_eq!(accesses, vec![(16, AccessKind::Read), (64, AccessKind::Write)]);
+
+        // a fill to a parameter's address doesn't
+        let fills = nodes[&NodeId(1)].get_range_couplings();
+        assert_eq!(fills[0].kind, RangeKind::Fill);
+        assert_eq!((fills[0].destination, fills[0].length), (None, Some(8)));
+        assert!(!fills[0].is_static());
+        let accesses:Vec<(usize, usize, AccessKind)> = nodes[&NodeId(1)].get_memory_accesses().iter().map(|memory| (memory.access.region(), memory.access.width, memory.access.kind)).collect();
+        assert_eq!(accesses, vec![(0, usize::MAX, AccessKind::Write)]);
+
+        // operands are found through the stack, so a computed source doesn't shift the others
+        let copies = nodes[&NodeId(2)].get_range_couplings();
+        assert_eq!((copies[0].destination, copies[0].source, copies[0].length), (Some(128), None, Some(4)));
+        let accesses:Vec<(usize, AccessKind)> = nodes[&NodeId(2)].get_memory_accesses().iter().map(|memory| (memory.access.width, memory.access.kind)).collect();
+        assert_eq!(accesses, vec![(usize::MAX, AccessKind::Read), (usize::MAX, AccessKind::Write)]);
+
+        // short static copies can be unrolled into a coupling per byte instead
+        mapper.set_bulk_unroll_limit(4);
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/bulk.wasm").to_vec());
+        assert!(nodes[&NodeId(0)].get_range_couplings().is_empty());
+        let accesses:Vec<(usize, AccessKind)> = nodes[&NodeId(0)].get_memory_accesses().iter().map(|memory| (memory.access.region(), memory.access.kind)).collect();
+        assert_eq!(accesses.len(), 8);
+        assert_eq!(accesses[0..2].to_vec(), vec![(16, AccessKind::Read), (64, AccessKind::Write)]);
+        assert_eq!(accesses[6..8].to_vec(), vec![(19, AccessKind::Read), (67, AccessKind::Write)]);
+        assert_eq!(nodes[&NodeId(1)].get_range_couplings().len(), 1);
+    }
+
+    #[test]
+    fn memory_couplings() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // loads from constant addresses keep their widths and declared alignments, with no base
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/subword.wasm").to_vec());
+        let reads:Vec<(usize, usize, usize, Option<usize>)> = nodes[&NodeId(0)].memory_reads().iter()
+            .map(|(access, _)| (access.static_offset, access.width, access.align, access.base_expr))
+            .collect();
+        assert_eq!(reads, vec![(0, 1, 1, None), (2, 2, 2, None)]);
+        assert_eq!(nodes[&NodeId(1)].memory_reads().iter().map(|(access, _)| access.width).collect::<Vec<usize>>(), vec![4, 8]);
+
+        // a load from a parameter's address is based on the parameter's variable
+        let mut nodes = mapper.map(include_bytes!("../tests/parallelization/ranges.wasm").to_vec());
+        let param = nodes.get_mut(&NodeId(0)).unwrap().get_local_input_variable(0);
+        let (access, var_id) = nodes[&NodeId(0)].memory_reads()[0];
+        assert_eq!((access.static_offset, access.base_expr, access.kind), (16, param, AccessKind::Read));
+        assert!(!nodes[&NodeId(0)].input_variable_is_param(var_id));
+
+        // stores to the same region at different offsets are told apart, in order
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/memory.wasm").to_vec());
+        let writes:Vec<(usize, AccessKind)> = nodes[&NodeId(1)].memory_writes().iter().map(|(access, _)| (access.static_offset, access.kind)).collect();
+        assert_eq!(writes, vec![(16, AccessKind::Write), (32, AccessKind::Write)]);
+        assert!(nodes[&NodeId(1)].memory_reads().is_empty());
+    }
+
+    #[test]
+    fn inline() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // only double is small enough to be inlined, and main reads its parameter where double did
+        mapper.set_inline_threshold(3);
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        let main = &nodes[&NodeId(2)];
+        assert_eq!(main.calls().values().cloned().collect::<Vec<NodeId>>(), vec![NodeId(1)]);
+        let operations = main.get_operations();
+        let param = main.get_local_input_variables()[&0];
+        match (&operations[&2], &operations[&3], &operations[&4]) {
+            (&AbstractExpression::Spin { id }, &AbstractExpression::Reuse { location: 2 }, &AbstractExpression::Add { .. }) => assert_eq!(id, param),
+            ops => panic!("unexpected operations {:?}", ops)
+        }
+        assert!(!operations.contains_key(&1));
+
+        // once both are, main is plain arithmetic on its parameter
+        mapper.set_inline_threshold(5);
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        let main = &nodes[&NodeId(2)];
+        assert!(main.calls().is_empty() && main.children().is_empty());
+        let mut inputs = HashMap::new();
+        inputs.insert(main.get_local_input_variables()[&0], 4);
+        assert_eq!(interp::evaluate(main, &inputs).result(), Some(4 + 4 + 2 * (4 + 3)));
+        assert!(mapper.type_check().is_empty());
+    }
+
+    #[test]
+    fn inlined_call_graph() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // inlining every callee leaves main without calls, but the call graph has them as they were discovered
+        mapper.set_inline_threshold(5);
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        assert!(nodes[&NodeId(2)].calls().is_empty());
+        let graph = mapper.call_graph();
+        assert_eq!(graph.functions, vec![0, 1, 2]);
+        assert_eq!(graph.callees(2), vec![0, 1]);
+        assert_eq!(graph.multiplicity(2, 0), 1);
+        assert!(!graph.calls_indirectly(2));
+    }
 
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                        print!("==== New If Condition: ");
-                        println!("{}. {:?}", i, op);
+    #[test]
+    fn program_points() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/conditions.wasm").to_vec());
+        let node = nodes[&NodeId(0)].clone();
+        let point = node.branch_sites()[&4];
+        assert_eq!(point.function, 0);
+        assert!(node.spans(point.offset));
+
+        // moving the node's code further moves its branch sites and conditions along with everything else, and
+        // the sites keep naming the same instructions
+        let mut moved = node.clone();
+        moved.relocate(&|location| Some(location + 2));
+        assert_eq!(moved.branch_sites().iter().map(|(i, point)| (*i - 2, *point)).collect::<BTreeMap<usize, ProgramPoint>>(), *node.branch_sites());
+        assert_eq!(moved.get_conditions().keys().map(|i| i - 2).collect::<Vec<usize>>(), node.get_conditions().keys().cloned().collect::<Vec<usize>>());
+
+        // removing code drops the branch sites along with everything else recorded there
+        let mut truncated = node.clone();
+        truncated.add_terminator(2);
+        truncated.remove_dead_code();
+        assert!(truncated.branch_sites().is_empty() && truncated.get_conditions().is_empty());
+    }
 
-                        // if conditions imply a single data dependency
-                        let mut conditional_node = Node::default();
-                        
-                        // create variable to represent the condition
-                        let outer_var_id = node.add_internal_variable(i, *ty);
+    #[test]
+    fn borrowing_accessors() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+
+        // the borrowed views of an expanded function agree with each other and with the function the mapper registered
+        let main = &nodes[&NodeId(2)];
+        let registered = &mapper.nodes()[&NodeId(2)];
+        assert_eq!((main.instrs(), main.input_variables()), (registered.instrs(), registered.input_variables()));
+        assert_eq!(main.iter_calls().count(), main.calls().len());
+        assert!(main.iter_calls().all(|(location, callee)| main.calls()[location] == *callee));
+        assert_eq!(mapper.nodes().len(), nodes.len());
+    }
 
-                        // create data coupling to simulate flow control
-                        let inner_var_id = conditional_node.add_input_variable(*ty);
-                        conditional_node.add_flow_control_coupling(outer_var_id, inner_var_id, true);
-                        
-                        conditional_node = self.map_helper(reader, buf, resources, position, i, conditional_node);
+    #[test]
+    fn display() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        let main = "function 2 at 0x3d..0x4a: 1 input, 0 internal, 1 constant variables; 4 operations [spin 0, num 2, spin 0, add I32]";
+        assert_eq!(nodes[&NodeId(2)].to_string(), main);
+
+        // the tree goes as deep as asked, counting the nodes below
+        assert_eq!(nodes[&NodeId(2)].describe(0), format!("{}\n  ... 2 nested nodes\n", main));
+        assert_eq!(nodes[&NodeId(2)].describe(1).lines().collect::<Vec<&str>>()[1..], [
+            "  function 0 at 0x2a..0x31: 1 input, 0 internal, 0 constant variables; 3 operations [spin 0, spin 0, add I32]",
+            "  function 1 at 0x32..0x3c: 2 input, 0 internal, 1 constant variables; 5 operations [spin 0, spin 1, num 3, add I32, mul I32]"
+        ]);
+
+        // constraints show their expressions in infix notation
+        let expression = PhysicalExpression::sum(PhysicalExpression::spin(true), PhysicalExpression::binary(true));
+        assert_eq!((Constraint::from_expression(2, expression) * 3).to_string(), "node 2: (s+ + b1) * 3");
+        assert_eq!(Constraint::new(4).to_string(), "node 4: 0");
+    }
 
-                        // register the conditional block
-                        let conditional_id = self.add_block(conditional_node.clone());
-                        node.add_block(i, conditional_id);
+    #[test]
+    fn walk() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/blocks.wasm").to_vec());
+        let steps = |walk:Walk| walk.map(|visit| (visit.depth, visit.id, visit.node.get_id())).collect::<Vec<(usize, Option<NodeId>, NodeId)>>();
+
+        // both orders visit every node once, children in order of id, with parents before or after them
+        for node in nodes.values() {
+            let pre_order = steps(node.walk());
+            let mut post_order = steps(node.walk_post_order());
+            assert_eq!(pre_order.len(), node.subtree_size());
+            assert_eq!(pre_order[0], (0, None, node.get_id()));
+            assert_eq!(*post_order.last().unwrap(), (0, None, node.get_id()));
+            let children:Vec<Option<NodeId>> = pre_order.iter().filter(|step| step.0 == 1).map(|step| step.1).collect();
+            assert_eq!(children, node.children().keys().map(|id| Some(*id)).collect::<Vec<Option<NodeId>>>());
+            post_order.sort();
+            let mut sorted = pre_order.clone();
+            sorted.sort();
+            assert_eq!(post_order, sorted);
+        }
+        let nested = nodes.values().find(|node| node.children().values().any(|child| !child.children().is_empty())).unwrap();
+        let pre_order = steps(nested.walk());
+        assert!(pre_order.windows(2).any(|pair| pair[1].0 == pair[0].0 + 1 && pair[0].0 > 0));
+        let post_order = steps(nested.walk_post_order());
+        assert!(post_order.windows(2).all(|pair| pair[1].0 + 1 >= pair[0].0));
+    }
 
-                        // add a spin to each node
-                        node.add_operation(i, AbstractExpression::Spin{ id: outer_var_id });
-                        conditional_node.add_operation(i, AbstractExpression::Spin{ id: inner_var_id });
+    #[test]
+    fn queries() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let mut nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+
+        // main calls both helpers, which are found nested under it too
+        assert_eq!(mapper.nodes_calling(&nodes, FuncId(0)), paths(&[&[2]]));
+        assert_eq!(mapper.find_nodes(&nodes, &|node| node.get_id() == NodeId(1)), paths(&[&[1], &[2, 1]]));
+        assert!(mapper.nodes_reading_global(&nodes, 0).is_empty());
+        nodes.get_mut(&NodeId(0)).unwrap().add_global_input_data_coupling(0, ProgramPoint::new(0, 0), 0);
+        assert_eq!(mapper.nodes_reading_global(&nodes, 0), paths(&[&[0]]));
+
+        // loads and stores are found by their static offsets, and bulk operations by their ranges
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/memory.wasm").to_vec());
+        assert_eq!(mapper.nodes_touching_memory(&nodes, Range::new(32, 36)), paths(&[&[0], &[1]]));
+        assert!(mapper.nodes_touching_memory(&nodes, Range::new(0, 16)).is_empty());
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/bulk.wasm").to_vec());
+        assert_eq!(mapper.nodes_touching_memory(&nodes, Range::new(18, 20)), paths(&[&[0], &[1], &[2]]));
+        assert_eq!(mapper.nodes_touching_memory(&nodes, Range::new(20, 64)), paths(&[&[1], &[2]]));
+
+        // the drop is read by the function, not by its block
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/locals.wasm").to_vec());
+        assert_eq!(mapper.nodes_with_unsupported_ops(&nodes), paths(&[&[0]]));
+    }
 
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                        print!("==== End of: ")
-                    }
-                    Operator::Else => {
+    #[test]
+    fn global_couplings() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/globals.wasm").to_vec());
+        let node = &nodes[&NodeId(0)];
+
+        // every read of the global is coupled to a variable of its own, in the order the reads are made
+        let reads = node.global_reads(0);
+        assert_eq!(reads.len(), 3);
+        assert!(reads.windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[0].1 != pair[1].1));
+        assert_eq!(node.global_writes(0).len(), 1);
+        assert!(node.global_reads(1).is_empty() && node.reads_global(0));
+
+        // reads and writes interleave as they are made, reading the global once before writing and twice after
+        let kinds:Vec<AccessKind> = node.global_accesses(0).iter().map(|(_, kind, _)| *kind).collect();
+        assert_eq!(kinds, vec![AccessKind::Read, AccessKind::Write, AccessKind::Read, AccessKind::Read]);
+        assert!(reads.iter().all(|(_, var_id)| !node.input_variable_is_param(*var_id)));
+        assert_eq!(node.verify_invariants(), Vec::new());
+    }
 
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+    #[test]
+    fn subtree_sharing() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // by default, leaf is copied beneath both of the functions calling it
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/diamond.wasm").to_vec());
+        let top = &nodes[&NodeId(3)];
+        assert!(top.get_child(NodeId(1)).unwrap().has_child(NodeId(0)) && top.get_child(NodeId(2)).unwrap().has_child(NodeId(0)));
+        assert!(mapper.get_shared_node(NodeId(0)).is_none());
+
+        // when shared, callers only reference their callees, which are expanded once into the arena
+        mapper.set_subtree_sharing(true);
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/diamond.wasm").to_vec());
+        let top = &nodes[&NodeId(3)];
+        assert!(top.children().is_empty());
+        assert!(top.has_shared_child(NodeId(1)) && top.has_shared_child(NodeId(2)));
+        let left = mapper.get_shared_node(NodeId(1)).unwrap();
+        assert!(left.children().is_empty() && left.has_shared_child(NodeId(0)));
+        assert!(mapper.get_shared_node(NodeId(0)).is_some());
+
+        // a caller can take its own copy of a callee, and the whole tree can be copied back out
+        let mut specialized = top.clone();
+        assert!(mapper.materialize(&mut specialized, NodeId(1)));
+        assert!(specialized.has_child(NodeId(1)) && !specialized.has_shared_child(NodeId(1)) && specialized.has_shared_child(NodeId(2)));
+        assert!(!mapper.materialize(&mut specialized, NodeId(1)));
+        let tree = mapper.materialize_tree(top);
+        assert!(tree.get_shared_children().is_empty());
+        assert!(tree.get_child(NodeId(1)).unwrap().has_child(NodeId(0)) && tree.get_child(NodeId(2)).unwrap().has_child(NodeId(0)));
+
+        // callers reach their shared callees through the links they hold, so running them needs no arena
+        let evaluate = |node:&Node| {
+            let mut inputs = HashMap::new();
+            inputs.insert(node.get_local_input_variables()[&0], 5);
+            interp::evaluate(node, &inputs).result()
+        };
+        assert!(top.get_callee(NodeId(1)).unwrap().get_callee(NodeId(0)).is_some());
+        assert_eq!(evaluate(top), evaluate(&tree));
+        assert!(evaluate(top).is_some());
+
+        // leaf is called from top, and one call deeper from left, so a depth limit truncates it differently on
+        // each path; left gets its own copy rather than the one expanded for top
+        let skew = include_bytes!("../tests/parallelization/skew.wasm").to_vec();
+        mapper.set_max_expansion_depth(Some(2));
+        let nodes = mapper.map(skew.clone());
+        let top = &nodes[&NodeId(3)];
+        assert!(top.has_shared_child(NodeId(1)) && top.has_shared_child(NodeId(2)));
+        assert!(top.get_callee(NodeId(1)).unwrap().get_callee(NodeId(0)).is_some());
+        let deeper = top.get_callee(NodeId(2)).unwrap().get_child(NodeId(1)).unwrap();
+        assert!(deeper.get_callee(NodeId(0)).is_none() && deeper.is_truncated());
+        let shared = mapper.materialize_tree(top).describe(8);
+        mapper.set_subtree_sharing(false);
+        assert_eq!(mapper.map(skew)[&NodeId(3)].describe(8), shared);
+
+        // without a depth limit, a call shares the subtree unless a function it can reach is on only one of the paths
+        let expansion = SharedExpansion { path: [NodeId(3)].iter().cloned().collect(), depth: 1, max_depth: None, reachable: [NodeId(0)].iter().cloned().collect(), node: None };
+        assert!(expansion.is_shared_by(&[NodeId(3), NodeId(2)].iter().cloned().collect(), 2, None));
+        assert!(!expansion.is_shared_by(&[NodeId(0), NodeId(3)].iter().cloned().collect(), 2, None));
+        assert!(!expansion.is_shared_by(&[NodeId(3)].iter().cloned().collect(), 1, Some(4)));
+    }
 
-                        // else implies a single data anti-dependency
-                        // it needs to be constructed from within the if so we can have easy access to its coupling parameters
-                        // however, it will be lifted out during the collapse of its top-level parent function
+    #[test]
+    fn expansion_limits() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // below top, left and right are expanded but the calls to leaf they make are cut off
+        mapper.set_max_expansion_depth(Some(1));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/diamond.wasm").to_vec());
+        let left = nodes[&NodeId(3)].get_child(NodeId(1)).unwrap();
+        assert!(left.children().is_empty());
+        assert_eq!(left.get_truncations().values().cloned().collect::<Vec<usize>>(), vec![0]);
+        assert!(nodes[&NodeId(1)].has_child(NodeId(0)) && !nodes[&NodeId(1)].is_truncated());
+        assert!(mapper.get_warnings().iter().any(|warning| warning.category == WarningCategory::Truncated && warning.node == 1));
+
+        // once the node budget is spent, only the functions themselves are left
+        mapper.set_max_expansion_depth(None);
+        mapper.set_max_expanded_nodes(Some(1));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/diamond.wasm").to_vec());
+        assert!(nodes.values().all(|node| node.children().is_empty()));
+        assert!(nodes[&NodeId(3)].is_truncated() && !nodes[&NodeId(0)].is_truncated());
+
+        // a default mapper expands the largest fixture within its default budget rather than running out of memory
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/chromatic.wasm").to_vec());
+        assert!(!nodes.is_empty() && nodes.values().any(Node::is_truncated));
+        assert!(mapper.get_warnings().iter().any(|warning| warning.category == WarningCategory::Truncated));
+    }
 
-                        // we should have most recently registered a conditional node with only one flow control coupling
-                        let couplings = node.get_flow_control_couplings();
-                        let coupling_count = couplings.keys().len();
+    #[test]
+    fn recursion_unrolling() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let recursion = include_bytes!("../tests/parallelization/recursion.wasm").to_vec();
+        let categories = |mapper:&Mapper| mapper.get_warnings().iter().map(|warning| (warning.node, warning.category)).collect::<Vec<(usize, WarningCategory)>>();
+
+        // without unrolling, the call countdown makes to itself and the one from nested's block are skipped
+        let nodes = mapper.map(recursion.clone());
+        assert!(nodes[&NodeId(0)].children().is_empty());
+        assert_eq!(categories(&mapper), vec![(0, WarningCategory::SelfReference), (2, WarningCategory::ReferenceLoop)]);
+
+        // countdown calls two copies of itself, one inside the other, and the second copy's call is cut off
+        mapper.set_recursion_unrolling(2);
+        let nodes = mapper.map(recursion);
+        let first = nodes[&NodeId(0)].children().clone().into_iter().next().unwrap();
+        let second = first.1.children().clone().into_iter().next().unwrap();
+        assert!(first.0 != second.0 && first.0 != NodeId(0) && second.1.children().is_empty());
+        assert_eq!(second.1.get_truncations().values().cloned().collect::<Vec<usize>>(), vec![0]);
+        assert_eq!(second.1.get_operations(), nodes[&NodeId(0)].get_operations());
+
+        // nested recurses from its block, so each copy goes through a block of its own
+        let (mut depth, mut id) = (0, NodeId(1));
+        let mut node = nodes[&NodeId(1)].clone();
+        while let Some((key, child)) = node.children().clone().into_iter().next() {
+            depth += 1;
+            id = key;
+            node = child;
+        }
+        assert!(depth == 5 && node.is_truncated());
+        let bounds:Vec<usize> = mapper.get_warnings().iter().filter(|warning| warning.category == WarningCategory::RecursionBound).map(|warning| warning.node).collect();
+        assert_eq!(bounds, vec![second.0.0, id.0]);
+    }
 
-                        // we should have most recently registered a conditional node with only one input variable
-                        let input_variables = node.get_input_variables();
-                        let input_variable_count = input_variables.keys().len();
+    #[test]
+    fn block_ids() {
+        let zeros = include_bytes!("../tests/parallelization/zeros.wasm").to_vec();
+        let ids = |nodes:&HashMap<NodeId, Node>| {
+            let mut ids:Vec<(usize, usize, usize)> = nodes.iter().flat_map(|(index, node)| node.calls().clone().into_iter().map(move |(i, id)| (index.0, i, id.0))).collect();
+            ids.sort();
+            ids
+        };
+
+        // blocks are numbered past the four functions in the order they are expanded, the else clause of
+        // guard's if taking 5, however often the module is mapped and by whichever mapper
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let first = ids(&mapper.map(zeros.clone()));
+        assert_eq!(first, vec![(2, 3, 4), (3, 1, 6)]);
+        assert_eq!(ids(&mapper.map(zeros.clone())), first);
+        let mut fresh = new_mapper();
+        fresh.set_observer(Box::new(SilentObserver));
+        assert_eq!(ids(&fresh.map(zeros.clone())), first);
+        assert!((4..7).all(|id| mapper.nodes().contains_key(&NodeId(id))) && !mapper.nodes().contains_key(&NodeId(7)));
+        // every expanded block carries the id it is registered under
+        assert!(mapper.nodes().iter().all(|(id, node)| node.get_id() == *id));
+
+        // an offset moves them, unless it would have them collide with the functions
+        mapper.set_block_id_offset(Some(100));
+        assert_eq!(ids(&mapper.map(zeros.clone())), vec![(2, 3, 100), (3, 1, 102)]);
+        assert!(!mapper.nodes().contains_key(&NodeId(4)));
+        mapper.set_block_id_offset(Some(1));
+        assert_eq!(ids(&mapper.map(zeros)), first);
+
+        // ids handed out from several threads at once are still unique
+        let shared = &mapper;
+        let mut handed:Vec<NodeId> = std::thread::scope(|scope| {
+            let handles:Vec<_> = (0..4).map(|_| scope.spawn(move || (0..100).map(|_| shared.unique_block_id()).collect::<Vec<NodeId>>())).collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        });
+        handed.sort();
+        handed.dedup();
+        assert_eq!((handed.len(), handed[0]), (400, NodeId(7)));
+    }
 
-                        // if we aren't in a conditional already, don't process the else
-                        if (coupling_count == 1 && input_variable_count == 1) {
+    #[test]
+    fn splits() {
+        let mut node = Node::default();
+        node.set_instrs((0..20).collect());
+        node.set_start(100);
+        node.set_end(120);
+
+        // blocks are split out by their offsets in the file, a later block's taken past the earlier one's code
+        assert_eq!(node.remove_instrs(105, 108), OffsetMap::removing(105, 108));
+        assert_eq!(node.remove_instrs(110, 115), OffsetMap::removing(107, 112));
+        assert_eq!(node.instrs().to_vec(), vec![0, 1, 2, 3, 4, 8, 9, 15, 16, 17, 18, 19]);
+
+        // the node keeps the offsets of the file, which the splits take to its instructions
+        assert_eq!((node.get_start(), node.get_end(), node.get_splits().removed()), (100, 120, 8));
+        assert_eq!((node.code_offset(109), node.code_offset(115), node.code_offset(106), node.code_offset(120)), (Some(6), Some(7), None, None));
+
+        // code already split out isn't split again
+        assert_eq!(node.remove_instrs(106, 107), OffsetMap::default());
+        assert_eq!(node.instrs().len(), 12);
+
+        // spin's loop keeps the operators opening its block and if, whose code is split out into their own nodes,
+        // and what is left of it still decodes
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/blocks.wasm").to_vec());
+        let spin = &nodes[&NodeId(1)];
+        let looped = &spin.children()[&spin.calls()[&1]];
+        let mut reader = BinaryReader::new_with_offset(looped.instrs(), looped.get_start());
+        let mut operators:Vec<String> = Vec::new();
+        while !reader.eof() {
+            let op = format!("{:?}", reader.read_operator().unwrap());
+            operators.push(op.split(|c:char| !c.is_alphanumeric()).next().unwrap().to_string());
+        }
+        assert_eq!(operators, vec!["Block", "GetLocal", "If", "Br", "End"]);
+        let removed:usize = looped.children().values().map(|child| child.get_end() - child.get_start()).sum();
+        assert_eq!(looped.get_splits().removed(), removed);
+        assert_eq!(looped.instrs().len() + removed, looped.get_end() - looped.get_start());
+    }
 
-                            print!("==== New Else Clause: ");
-                            println!("{}. {:?}", i, op);
+    #[test]
+    fn source_locations() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let range = |file:&str, line:usize| vec![SourceRange { file: file.to_string(), first_line: line, last_line: line }];
+
+        // without a line table or a source map, nodes can't be traced back to source
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/provenance.wasm").to_vec());
+        assert_eq!(mapper.get_source_mapping_url(), Some("provenance.wasm.map".to_string()));
+        assert!(nodes.values().all(|node| node.source_locations().is_empty()));
+
+        // the source map the module points at resolves each function to the line it starts on
+        mapper.set_source_map(include_str!("../tests/parallelization/provenance.wasm.map"));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/provenance.wasm").to_vec());
+        assert_eq!(nodes[&NodeId(0)].source_locations(), range("inline.c", 2));
+        assert_eq!(nodes[&NodeId(2)].source_locations(), range("inline.c", 17));
+
+        // and a DWARF line table takes precedence over it
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/dwarf.wasm").to_vec());
+        assert_eq!(mapper.get_source_mapping_url(), None);
+        assert_eq!(nodes[&NodeId(1)].source_locations(), range("src/dwarf.c", 9));
+    }
 
-                            // get coupling details from the if condition details
-                            let coupled_var_id = node.get_first_flow_control_coupling();
-                            let input_type = node.get_first_input_variable();
+    #[test]
+    fn custom_sections() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // the annotations section has a line per annotated function, with its label and index
+        mapper.set_section_handler("pfc.annotations", Box::new(|data:&[u8]| {
+            str::from_utf8(data).unwrap_or("").lines()
+                .filter_map(|line| {
+                    let mut words = line.split_whitespace();
+                    let label = words.next()?.to_string();
+                    Some((words.next()?.parse().ok()?, label))
+                })
+                .collect()
+        }));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/annotated.wasm").to_vec());
+        let names:Vec<String> = mapper.custom_sections().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["pfc.annotations".to_string(), "name".to_string()]);
+        assert_eq!(mapper.custom_sections()[0].1, b"anneal 2\nanneal 9\n".to_vec());
+        assert!(nodes[&NodeId(2)].has_annotation("anneal") && !nodes[&NodeId(1)].has_annotation("anneal"));
+
+        // annotations of functions the module doesn't define are reported
+        let warnings:Vec<usize> = mapper.get_warnings().iter()
+            .filter(|warning| warning.category == WarningCategory::Annotation)
+            .map(|warning| warning.node)
+            .collect();
+        assert_eq!(warnings, vec![9]);
+    }
 
-                            let mut else_node = Node::default();
+    #[test]
+    fn linked_modules() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.add_module("library", include_bytes!("../tests/parallelization/library.wasm").to_vec());
+        let linked = include_bytes!("../tests/parallelization/linked.wasm").to_vec();
+
+        // the library's scale and the increment it calls follow main, and main's block calls into them
+        let nodes = mapper.map(linked.clone());
+        let mut functions:Vec<(usize, (String, usize))> = mapper.get_linked_functions().into_iter().collect();
+        functions.sort();
+        assert_eq!(functions, vec![(3, ("library".to_string(), 0)), (4, ("library".to_string(), 1))]);
+        assert_eq!(mapper.nodes_calling(&nodes, FuncId(4)), paths(&[&[2, 5]]));
+        assert!(mapper.nodes_calling(&nodes, FuncId(3)).contains(&paths(&[&[2, 5, 4]])[0]));
+        assert_eq!(nodes[&NodeId(2)].children()[&NodeId(5)].children()[&NodeId(4)].children()[&NodeId(3)].children().len(), 1);
+
+        // only the import no module provides is left to warn about
+        assert_eq!(mapper.get_imports(), vec![(1, ("env".to_string(), "log".to_string()))].into_iter().collect());
+        let warnings:Vec<(usize, WarningCategory)> = mapper.get_warnings().iter().map(|warning| (warning.node, warning.category)).collect();
+        assert_eq!(warnings, vec![(2, WarningCategory::ImportedCall)]);
+
+        // a registered module that doesn't export an import leaves it unresolved
+        mapper.add_module("env", include_bytes!("../tests/parallelization/library.wasm").to_vec());
+        mapper.map(linked.clone());
+        let warnings:Vec<(usize, WarningCategory)> = mapper.get_warnings().iter().map(|warning| (warning.node, warning.category)).collect();
+        assert_eq!(warnings, vec![(1, WarningCategory::UnresolvedImport), (2, WarningCategory::ImportedCall)]);
+
+        // without the library, scale is an import as well
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(linked);
+        assert_eq!(nodes.len(), 1);
+        assert!(mapper.get_linked_functions().is_empty() && mapper.get_imports().len() == 2);
+    }
 
-                            // create data anti-chain coupling to simulate flow control
-                            let inner_var_id = else_node.add_input_variable(input_type);
-                            else_node.add_flow_control_coupling(coupled_var_id, inner_var_id, false);
+    #[test]
+    fn function_filters() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let kernels = include_bytes!("../tests/parallelization/kernels.wasm").to_vec();
+
+        // the name section names every function, and without it only exported functions have names
+        mapper.map(include_bytes!("../tests/parallelization/linked.wasm").to_vec());
+        assert_eq!((mapper.get_function_name(1), mapper.get_function_name(2)), (None, Some("main".to_string())));
+        mapper.map(kernels.clone());
+        assert_eq!(mapper.get_function_name(1), Some("kernel_scale".to_string()));
+        assert_eq!(mapper.get_function_names().len(), 3);
+
+        // only the kernels are mapped, and main, which nothing they call calls, is pruned
+        mapper.set_include_filter(Some("^kernel_")).unwrap();
+        let nodes = mapper.map(kernels.clone());
+        let mut indices:Vec<usize> = nodes.keys().map(|id| id.0).collect();
+        indices.sort();
+        assert_eq!(indices, vec![0, 1]);
+        assert!(!mapper.nodes().contains_key(&NodeId(2)));
+
+        // a function left out is still expanded as a callee of the functions mapped
+        mapper.set_include_filter(None).unwrap();
+        mapper.set_exclude_filter(Some("double")).unwrap();
+        let nodes = mapper.map(kernels.clone());
+        let mut indices:Vec<usize> = nodes.keys().map(|id| id.0).collect();
+        indices.sort();
+        assert_eq!(indices, vec![1, 2]);
+        assert!(nodes[&NodeId(2)].has_child(NodeId(0)) && mapper.nodes().contains_key(&NodeId(0)));
+
+        assert!(mapper.set_include_filter(Some("kernel_(")).is_err());
+        mapper.set_exclude_filter(None).unwrap();
+        assert_eq!(mapper.map(kernels).len(), 3);
+    }
 
-                            else_node = self.map_helper(reader, buf, resources, position, i, else_node);
+    #[cfg(feature = "wat")]
+    #[test]
+    fn text_format() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // the text format maps to the same operations as the binary assembled from it
+        let wasm = mapper.read_wasm("tests/parallelization/inline.wat").unwrap();
+        assert!(wasm.starts_with(b"\0asm"));
+        let text = mapper.map(wasm);
+        let binary = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        assert_eq!(text.len(), binary.len());
+        for (index, node) in binary.iter() {
+            assert_eq!(text[index].get_operations(), node.get_operations());
+        }
 
-                            // the else's end also terminates the if clause
-                            let if_end = else_node.get_end();
-                            node.set_end(if_end);
+        // text that doesn't assemble is invalid data, reported with the file it is in
+        let path = std::env::temp_dir().join("wasm-pfc-invalid.wat");
+        std::fs::write(&path, "(module (func (i32.frobnicate)))").unwrap();
+        let err = mapper.read_wasm(path.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("wasm-pfc-invalid.wat"));
+    }
 
-                            // register the else block
-                            let else_id = self.add_block(else_node);
-                            node.add_block(i, else_id);
-                        
-                            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                            print!("==== End of: ");
-                            println!("{}. {:?}", i, op);
-                            
-                            // finish processing the if node
-                            break;
-                        }
-                    }
-                    Operator::Return
-                    | Operator::End => {
+    #[test]
+    fn partial_results() {
+        let module = include_bytes!("../tests/parallelization/inline.wasm");
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // a valid module is mapped whole
+        let result = mapper.map_partial(module.to_vec());
+        assert!(result.is_complete() && result.nodes.len() == 3);
+
+        // a module cut off in its names section keeps the functions before it
+        let result = mapper.map_partial(module[..90].to_vec());
+        assert_eq!(result.nodes.len(), 3);
+        assert_eq!(result.error.unwrap().offset, 90);
+
+        // while one cut off in its code section has none, since the parser checks that sections are whole
+        let result = mapper.map_partial(module[..55].to_vec());
+        assert!(result.nodes.is_empty());
+        assert_eq!(result.error.unwrap().message, "Section body extends past end of file");
+
+        // an operator that doesn't validate stops its function short, where the operator starts
+        let mut invalid = module.to_vec();
+        invalid[54] = 0x7c;
+        let result = mapper.map_partial(invalid);
+        assert_eq!(result.error.unwrap().offset, 53);
+        assert_eq!(mapper.get_error().unwrap().message, "local index out of bounds");
+        assert_eq!(result.nodes.len(), 3);
+
+        // a strict mapper meeting an unsupported operator returns the functions it read along with the operator
+        mapper.set_strict(true);
+        let result = mapper.map_partial(include_bytes!("../tests/parallelization/locals.wasm").to_vec());
+        assert!(!result.is_complete() && result.error.is_none());
+        assert_eq!(result.unsupported.unwrap().operators, vec![UnsupportedOperator { operator: "Drop".to_string(), offset: 52 }]);
+        assert!(!result.nodes.is_empty() && result.nodes.values().all(|node| node.children().is_empty()));
+    }
 
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)));
+    // maps a module and lowers each of its functions without asking anything, describing the SSA form each
+    // function is lowered from and the constraint it is lowered to
+    fn lowering_snapshot(buf:&[u8]) -> String {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(buf.to_vec());
+        let mut ids:Vec<usize> = nodes.keys().map(|id| id.0).collect();
+        ids.sort();
+
+        let mut snapshot = String::new();
+        for id in ids {
+            let mut node = nodes[&NodeId(id)].clone();
+            let mut constraint = node.lower();
+            constraint.simplify();
+            describe_lowering(&node, &mut snapshot);
+            match constraint.get_expression() {
+                Some(expression) => snapshot.push_str(&format!("constraint {}\n", expression)),
+                None => snapshot.push_str("constraint empty\n")
+            }
+            snapshot.push_str(&format!("qubo {}\n", constraint.qubo()));
+        }
+        snapshot
+    }
 
-                        // if the node represetns a function, the function end was already extracted from the function metadata
-                        if (node.get_end() == 0) {
-                            // otherwise, deduce the end from the number of loops performed within this frame
-                            node.set_end(position + start);
-                        }
-                        println!("{}. {:?}", i, op);
+    // describes the SSA form of a node and then of its children, in order
+    fn describe_lowering(node:&Node, snapshot:&mut String) {
+        for visit in node.walk() {
+            let kind = if visit.node.is_function() { "function" } else { "block" };
+            snapshot.push_str(&format!("{} {}, {} auxiliary qubits\n", kind, visit.node.get_id(), visit.node.auxiliary_qubits()));
+            snapshot.push_str(&visit.node.to_ssa().to_string());
+        }
+    }
 
-                        // finish processing the node
-                        break;
-                    }
-                    Operator::Br { relative_depth } => {
-                        node.add_branch(i, *relative_depth as usize);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                    }
-                    Operator::BrIf { relative_depth } => {
-                        node.add_branch(i, *relative_depth as usize);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                    }
-                    Operator::BrTable { ref table } => {
-                        for relative_depth in table {
-                            node.add_branch(i, table.buffer[relative_depth as usize] as usize);
-                        }
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-                    }
-                    Operator::Call { function_index } => {
-                        node.add_call(i, *function_index as usize);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Magenta)));
-                    }
-                    Operator::CallIndirect { index, table_index } => {
-                        node.add_call(i, *table_index as usize);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Magenta)));
-                    }
-                    Operator::Drop => { 
-                        // TODO 
-                    }
-                    Operator::Select => { 
-                        // TODO 
-                    }
-                    Operator::GetLocal { local_index } => {
-                        let local_vars = node.get_input_variables();
-                        let var_id = *local_index as usize;
-                        let var_type = local_vars[&var_id];
-                        node.add_operation(i, AbstractExpression::Spin{ id: var_id });
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::SetLocal { local_index } => {
-                        // TODO
-                    }
-                    Operator::TeeLocal { local_index } => { 
-                        // TODO 
-                    }
-                    Operator::GetGlobal { global_index } => {
-                        let var_id = node.add_input_variable(resources.globals()[*global_index as usize].content_type);
-                        node.add_global_input_data_coupling(*global_index as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::SetGlobal { global_index } => {
-                        let var_id = node.add_output_variable(resources.globals()[*global_index as usize].content_type);
-                        node.add_global_output_data_coupling(*global_index as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::F32Load { ref memarg } => {
-                        let var_id = node.add_input_variable(Type::F32);
-                        node.add_input_data_coupling(memarg.offset as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::F64Load { ref memarg } => {
-                        let var_id = node.add_input_variable(Type::F64);
-                        node.add_input_data_coupling(memarg.offset as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::I32Load8S { ref memarg }
-                    | Operator::I32Load { ref memarg }
-                    | Operator::I32Load8U { ref memarg }
-                    | Operator::I32Load16S { ref memarg }
-                    | Operator::I32Load16U { ref memarg }
-                    | Operator::I32AtomicLoad { ref memarg }
-                    | Operator::I32AtomicLoad16U { ref memarg }
-                    | Operator::I32AtomicLoad8U { ref memarg } => {
-                        let var_id = node.add_input_variable(Type::I32);
-                        node.add_input_data_coupling(memarg.offset as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::I64Load8S { ref memarg } 
-                    | Operator::I64Load { ref memarg }
-                    | Operator::I64Load8U { ref memarg } 
-                    | Operator::I64Load16U { ref memarg }
-                    | Operator::I64Load32S { ref memarg }
-                    | Operator::I64Load32U { ref memarg }
-                    | Operator::I64Load16S { ref memarg }
-                    | Operator::I64AtomicLoad { ref memarg }
-                    | Operator::I64AtomicLoad32U { ref memarg }
-                    | Operator::I64AtomicLoad16U { ref memarg }
-                    | Operator::I64AtomicLoad8U { ref memarg } => {
-                        let var_id = node.add_input_variable(Type::I64);
-                        node.add_input_data_coupling(memarg.offset as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::I32Store { ref memarg } 
-                    | Operator::I32Store8 { ref memarg }
-                    | Operator::I32Store16 { ref memarg }
-                    | Operator::I32AtomicStore { ref memarg }
-                    | Operator::I32AtomicStore8 { ref memarg }
-                    | Operator::I32AtomicStore16 { ref memarg } => {
-                        let var_id = node.add_output_variable(Type::I32);
-                        node.add_output_data_coupling(memarg.offset as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::I64Store { ref memarg }
-                    | Operator::I64Store8 { ref memarg }
-                    | Operator::I64Store16 { ref memarg }
-                    | Operator::I64Store32 { ref memarg }
-                    | Operator::I64AtomicStore { ref memarg }
-                    | Operator::I64AtomicStore32 { ref memarg }
-                    | Operator::I64AtomicStore16 { ref memarg }
-                    | Operator::I64AtomicStore8 { ref memarg } => {
-                        let var_id = node.add_output_variable(Type::I64);
-                        node.add_output_data_coupling(memarg.offset as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::F32Store { ref memarg } => {
-                        let var_id = node.add_output_variable(Type::F32);
-                        node.add_output_data_coupling(memarg.offset as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::F64Store { ref memarg } => {
-                        let var_id = node.add_output_variable(Type::F64);
-                        node.add_output_data_coupling(memarg.offset as usize, var_id);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::MemorySize {
-                        reserved: memory_index,
-                    } => { 
-                        // TODO 
-                    }
-                    Operator::MemoryGrow {
-                        reserved: memory_index,
-                    } => { 
-                        // TODO 
-                    }
-                    Operator::I32Const { .. } => {
-                        node.add_constant(Type::I32);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::I64Const { .. } => {
-                        node.add_constant(Type::I64);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::F32Const { .. } => {
-                        node.add_constant(Type::F32);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::F64Const { .. } => {
-                        node.add_constant(Type::F64);
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::I32Eqz => {
-                        // TODO
-                    }
-                    Operator::I32Eq
-                    | Operator::I32Ne
-                    | Operator::I32LtS
-                    | Operator::I32LtU
-                    | Operator::I32GtS
-                    | Operator::I32GtU
-                    | Operator::I32LeS
-                    | Operator::I32LeU
-                    | Operator::I32GeS
-                    | Operator::I32GeU => {
-                        // TODO
-                    }
-                    Operator::I64Eqz => {
-                        // TODO
-                    }
-                    Operator::I64Eq
-                    | Operator::I64Ne
-                    | Operator::I64LtS
-                    | Operator::I64LtU
-                    | Operator::I64GtS
-                    | Operator::I64GtU
-                    | Operator::I64LeS
-                    | Operator::I64LeU
-                    | Operator::I64GeS
-                    | Operator::I64GeU => {
-                        // TODO
-                    }
-                    Operator::F32Eq
-                    | Operator::F32Ne
-                    | Operator::F32Lt
-                    | Operator::F32Gt
-                    | Operator::F32Le
-                    | Operator::F32Ge => {
-                        // TODO
-                    }
-                    Operator::F64Eq
-                    | Operator::F64Ne
-                    | Operator::F64Lt
-                    | Operator::F64Gt
-                    | Operator::F64Le
-                    | Operator::F64Ge => {
-                        // TODO
-                    }
-                    Operator::I32Clz | Operator::I32Ctz | Operator::I32Popcnt => { 
-                        // TODO 
-                    }
-                        // TODO
-                    Operator::I32Add => {
-                        node.add_operation(i, AbstractExpression::Add{ty: Type::I32});
-                    }
-                    Operator::I32Sub => {
-                        // TODO
-                    }
-                    Operator::I32Mul => {
-                        node.add_operation(i, AbstractExpression::Mul{ty: Type::I32});
-                    }
-                    Operator::I32DivS
-                    | Operator::I32DivU => {
-                        // TODO
-                    }
-                    | Operator::I32RemS
-                    | Operator::I32RemU
-                    | Operator::I32And
-                    | Operator::I32Or
-                    | Operator::I32Xor
-                    | Operator::I32Shl
-                    | Operator::I32ShrS
-                    | Operator::I32ShrU
-                    | Operator::I32Rotl
-                    | Operator::I32Rotr => {
-                        // TODO
-                    }
-                    Operator::I64Clz | Operator::I64Ctz | Operator::I64Popcnt => {
-                        // TODO
-                    }
-                    Operator::I64Add => {
-                        node.add_operation(i, AbstractExpression::Add{ty: Type::I64});
-                    }
-                    Operator::I64Sub
-                    | Operator::I64Mul
-                    | Operator::I64DivS
-                    | Operator::I64DivU
-                    | Operator::I64RemS
-                    | Operator::I64RemU
-                    | Operator::I64And
-                    | Operator::I64Or
-                    | Operator::I64Xor
-                    | Operator::I64Shl
-                    | Operator::I64ShrS
-                    | Operator::I64ShrU
-                    | Operator::I64Rotl
-                    | Operator::I64Rotr => {
-                        // TODO
-                    }
-                    Operator::F32Abs
-                    | Operator::F32Neg
-                    | Operator::F32Ceil
-                    | Operator::F32Floor
-                    | Operator::F32Trunc
-                    | Operator::F32Nearest
-                    | Operator::F32Sqrt => {
-                        // TODO
-                    }
-                    Operator::F32Add => {
-                        node.add_operation(i, AbstractExpression::Add{ty: Type::F32});
-                    }
-                    Operator::F32Sub => {
-                        // TODO
-                    }
-                    Operator::F32Mul => {
-                        node.add_operation(i, AbstractExpression::Mul{ty: Type::F32});
-                    }
-                    | Operator::F32Div
-                    | Operator::F32Min
-                    | Operator::F32Max
-                    | Operator::F32Copysign => {
-                        // TODO
-                    }
-                    Operator::F64Abs
-                    | Operator::F64Neg
-                    | Operator::F64Ceil
-                    | Operator::F64Floor
-                    | Operator::F64Trunc
-                    | Operator::F64Nearest
-                    | Operator::F64Sqrt => {
-                        // TODO
-                    }
-                    Operator::F64Add => {
-                        node.add_operation(i, AbstractExpression::Add{ty: Type::F64});
-                    }
-                    | Operator::F64Sub
-                    | Operator::F64Mul
-                    | Operator::F64Div
-                    | Operator::F64Min
-                    | Operator::F64Max
-                    | Operator::F64Copysign => {
-                        // TODO
-                    }
-                    Operator::I32WrapI64 => {
-                        // TODO
-                    }
-                    Operator::I32TruncSF32 | Operator::I32TruncUF32 => {
-                        // TODO
-                    }
-                    Operator::I32TruncSF64 | Operator::I32TruncUF64 => {
-                        // TODO
-                    }
-                    Operator::I64ExtendSI32 | Operator::I64ExtendUI32 => {
-                        // TODO
-                    }
-                    Operator::I64TruncSF32 | Operator::I64TruncUF32 => {
-                        // TODO
-                    }
-                    Operator::I64TruncSF64 | Operator::I64TruncUF64 => {
-                        // TODO
-                    }
-                    Operator::F32ConvertSI32 | Operator::F32ConvertUI32 => {
-                        // TODO
-                    }
-                    Operator::F32ConvertSI64 | Operator::F32ConvertUI64 => {
-                        // TODO
-                    }
-                    Operator::F32DemoteF64 => {
-                        // TODO
-                    }
-                    Operator::F64ConvertSI32 | Operator::F64ConvertUI32 => {
-                        // TODO
-                    }
-                    Operator::F64ConvertSI64 | Operator::F64ConvertUI64 => {
-                        // TODO
-                    }
-                    Operator::F64PromoteF32 => {
-                        // TODO
-                    }
-                    Operator::I32ReinterpretF32 => {
-                        // TODO
-                    }
-                    Operator::I64ReinterpretF64 => {
-                        // TODO
-                    }
-                    Operator::F32ReinterpretI32 => {
-                        // TODO
-                    }
-                    Operator::F64ReinterpretI64 => {
-                        // TODO
-                    }
-                    Operator::I32TruncSSatF32 | Operator::I32TruncUSatF32 => {
-                        // TODO
-                    }
-                    Operator::I32TruncSSatF64 | Operator::I32TruncUSatF64 => {
-                        // TODO
-                    }
-                    Operator::I64TruncSSatF32 | Operator::I64TruncUSatF32 => {
-                        // TODO
-                    }
-                    Operator::I64TruncSSatF64 | Operator::I64TruncUSatF64 => {
-                        // TODO
-                    }
-                    Operator::I32Extend16S | Operator::I32Extend8S => {
-                        // TODO
-                    }
+    // compares the lowering of a fixture with its golden file, which is rewritten instead when UPDATE_GOLDEN is set
+    fn assert_golden(name:&str, buf:&[u8]) {
+        let path = format!("{}/tests/parallelization/{}.golden", env!("CARGO_MANIFEST_DIR"), name);
+        let snapshot = lowering_snapshot(buf);
+        assert_eq!(snapshot, lowering_snapshot(buf), "lowering {} isn't deterministic", name);
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(&path, &snapshot).unwrap();
+        }
+        let golden = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(snapshot, golden, "lowering {} differs from {}", name, path);
+    }
 
-                    Operator::I64Extend32S | Operator::I64Extend16S | Operator::I64Extend8S => {
-                        // TODO
-                    }
-                    Operator::I32AtomicRmwAdd { ref memarg }
-                    | Operator::I32AtomicRmw16UAdd { ref memarg } 
-                    | Operator::I32AtomicRmw8UAdd { ref memarg } => {
-                        node.add_operation(i, AbstractExpression::Add{ty: Type::I32});
-                    }
-                    Operator::I64AtomicRmwAdd { ref memarg } 
-                    | Operator::I64AtomicRmw32UAdd { ref memarg } 
-                    | Operator::I64AtomicRmw8UAdd { ref memarg } => {
-                        node.add_operation(i, AbstractExpression::Add{ty: Type::I64});
-                    }
-                    | Operator::I32AtomicRmwSub { ref memarg }
-                    | Operator::I32AtomicRmwAnd { ref memarg }
-                    | Operator::I32AtomicRmwOr { ref memarg }
-                    | Operator::I32AtomicRmwXor { ref memarg }
-                    | Operator::I32AtomicRmw16USub { ref memarg }
-                    | Operator::I32AtomicRmw16UAnd { ref memarg }
-                    | Operator::I32AtomicRmw16UOr { ref memarg }
-                    | Operator::I32AtomicRmw16UXor { ref memarg }
-                    | Operator::I32AtomicRmw8USub { ref memarg }
-                    | Operator::I32AtomicRmw8UAnd { ref memarg }
-                    | Operator::I32AtomicRmw8UOr { ref memarg }
-                    | Operator::I32AtomicRmw8UXor { ref memarg } => {
-                        // TODO
-                    }
-                    Operator::I64AtomicRmw32UAdd { ref memarg }
-                    | Operator::I64AtomicRmw16UAdd { ref memarg }
-                    | Operator::I64AtomicRmw8UAdd { ref memarg }  => {
-                        node.add_operation(i, AbstractExpression::Add{ty: Type::I64});
-                    }
-                    Operator::I64AtomicRmwSub { ref memarg }
-                    | Operator::I64AtomicRmwAnd { ref memarg }
-                    | Operator::I64AtomicRmwOr { ref memarg }
-                    | Operator::I64AtomicRmwXor { ref memarg }
-                    | Operator::I64AtomicRmw32USub { ref memarg }
-                    | Operator::I64AtomicRmw32UAnd { ref memarg }
-                    | Operator::I64AtomicRmw32UOr { ref memarg }
-                    | Operator::I64AtomicRmw32UXor { ref memarg }
-                    | Operator::I64AtomicRmw16USub { ref memarg }
-                    | Operator::I64AtomicRmw16UAnd { ref memarg }
-                    | Operator::I64AtomicRmw16UOr { ref memarg }
-                    | Operator::I64AtomicRmw16UXor { ref memarg }
-                    | Operator::I64AtomicRmw8USub { ref memarg }
-                    | Operator::I64AtomicRmw8UAnd { ref memarg }
-                    | Operator::I64AtomicRmw8UOr { ref memarg }
-                    | Operator::I64AtomicRmw8UXor { ref memarg } => {
-                        // TODO
-                    }
-                    Operator::I32AtomicRmwXchg { ref memarg }
-                    | Operator::I32AtomicRmw16UXchg { ref memarg }
-                    | Operator::I32AtomicRmw8UXchg { ref memarg } => {
-                        // TODO
-                    }
-                    Operator::I32AtomicRmwCmpxchg { ref memarg }
-                    | Operator::I32AtomicRmw16UCmpxchg { ref memarg }
-                    | Operator::I32AtomicRmw8UCmpxchg { ref memarg } => {
-                        // TODO
-                    }
-                    Operator::I64AtomicRmwXchg { ref memarg }
-                    | Operator::I64AtomicRmw32UXchg { ref memarg }
-                    | Operator::I64AtomicRmw16UXchg { ref memarg }
-                    | Operator::I64AtomicRmw8UXchg { ref memarg } => {
-                         // TODO
-                    }
-                    Operator::I64AtomicRmwCmpxchg { ref memarg }
-                    | Operator::I64AtomicRmw32UCmpxchg { ref memarg }
-                    | Operator::I64AtomicRmw16UCmpxchg { ref memarg }
-                    | Operator::I64AtomicRmw8UCmpxchg { ref memarg } => {
-                         // TODO
-                    }
-                    Operator::Wake { ref memarg } => {
-                         // TODO
-                    }
-                    Operator::I32Wait { ref memarg } => {
-                         // TODO
-                    }
-                    Operator::I64Wait { ref memarg } => {
-                         // TODO
-                    }
-                    Operator::RefNull => {
-                         // TODO
-                    }
-                    Operator::RefIsNull => {
-                         // TODO
-                    }
-                    Operator::V128Load { ref memarg } => {
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::V128Store { ref memarg } => {
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
-                    }
-                    Operator::V128Const { .. } => {
-                        node.add_constant(Type::V128);
-                    }
-                    Operator::V8x16Shuffle { ref lines } => {
-                         // TODO
-                    }
-                    Operator::I8x16Splat | Operator::I16x8Splat | Operator::I32x4Splat => {
-                         // TODO
-                    }
-                    Operator::I64x2Splat => {
-                         // TODO
-                    }
-                    Operator::F32x4Splat => {
-                         // TODO
-                    }
-                    Operator::F64x2Splat => {
-                         // TODO
-                    }
-                    Operator::I8x16ExtractLaneS { line } | Operator::I8x16ExtractLaneU { line } => { 
-                        // TODO 
-                    }
-                    Operator::I16x8ExtractLaneS { line } | Operator::I16x8ExtractLaneU { line } => { 
-                        // TODO 
-                    }
-                    Operator::I32x4ExtractLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::I8x16ReplaceLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::I16x8ReplaceLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::I32x4ReplaceLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::I64x2ExtractLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::I64x2ReplaceLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::F32x4ExtractLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::F32x4ReplaceLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::F64x2ExtractLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::F64x2ReplaceLane { line } => { 
-                        // TODO 
-                    }
-                    Operator::I8x16Eq
-                    | Operator::I8x16Ne
-                    | Operator::I8x16LtS
-                    | Operator::I8x16LtU
-                    | Operator::I8x16GtS
-                    | Operator::I8x16GtU
-                    | Operator::I8x16LeS
-                    | Operator::I8x16LeU
-                    | Operator::I8x16GeS
-                    | Operator::I8x16GeU
-                    | Operator::I16x8Eq
-                    | Operator::I16x8Ne
-                    | Operator::I16x8LtS
-                    | Operator::I16x8LtU
-                    | Operator::I16x8GtS
-                    | Operator::I16x8GtU
-                    | Operator::I16x8LeS
-                    | Operator::I16x8LeU
-                    | Operator::I16x8GeS
-                    | Operator::I16x8GeU
-                    | Operator::I32x4Eq
-                    | Operator::I32x4Ne
-                    | Operator::I32x4LtS
-                    | Operator::I32x4LtU
-                    | Operator::I32x4GtS
-                    | Operator::I32x4GtU
-                    | Operator::I32x4LeS
-                    | Operator::I32x4LeU
-                    | Operator::I32x4GeS
-                    | Operator::I32x4GeU
-                    | Operator::F32x4Eq
-                    | Operator::F32x4Ne
-                    | Operator::F32x4Lt
-                    | Operator::F32x4Gt
-                    | Operator::F32x4Le
-                    | Operator::F32x4Ge
-                    | Operator::F64x2Eq
-                    | Operator::F64x2Ne
-                    | Operator::F64x2Lt
-                    | Operator::F64x2Gt
-                    | Operator::F64x2Le
-                    | Operator::F64x2Ge
-                    | Operator::V128And
-                    | Operator::V128Or
-                    | Operator::V128Xor
-                    | Operator::I8x16Add
-                    | Operator::I8x16AddSaturateS
-                    | Operator::I8x16AddSaturateU
-                    | Operator::I8x16Sub
-                    | Operator::I8x16SubSaturateS
-                    | Operator::I8x16SubSaturateU
-                    | Operator::I8x16Mul
-                    | Operator::I16x8Add
-                    | Operator::I16x8AddSaturateS
-                    | Operator::I16x8AddSaturateU
-                    | Operator::I16x8Sub
-                    | Operator::I16x8SubSaturateS
-                    | Operator::I16x8SubSaturateU
-                    | Operator::I16x8Mul
-                    | Operator::I32x4Add
-                    | Operator::I32x4Sub
-                    | Operator::I32x4Mul
-                    | Operator::I64x2Add
-                    | Operator::I64x2Sub
-                    | Operator::F32x4Add
-                    | Operator::F32x4Sub
-                    | Operator::F32x4Mul
-                    | Operator::F32x4Div
-                    | Operator::F32x4Min
-                    | Operator::F32x4Max
-                    | Operator::F64x2Add
-                    | Operator::F64x2Sub
-                    | Operator::F64x2Mul
-                    | Operator::F64x2Div
-                    | Operator::F64x2Min
-                    | Operator::F64x2Max => { 
-                        // TODO 
-                    }
-                    Operator::V128Not
-                    | Operator::I8x16Neg
-                    | Operator::I16x8Neg
-                    | Operator::I32x4Neg
-                    | Operator::I64x2Neg
-                    | Operator::F32x4Abs
-                    | Operator::F32x4Neg
-                    | Operator::F32x4Sqrt
-                    | Operator::F64x2Abs
-                    | Operator::F64x2Neg
-                    | Operator::F64x2Sqrt
-                    | Operator::I32x4TruncSF32x4Sat
-                    | Operator::I32x4TruncUF32x4Sat
-                    | Operator::I64x2TruncSF64x2Sat
-                    | Operator::I64x2TruncUF64x2Sat
-                    | Operator::F32x4ConvertSI32x4
-                    | Operator::F32x4ConvertUI32x4
-                    | Operator::F64x2ConvertSI64x2
-                    | Operator::F64x2ConvertUI64x2 => { 
-                        // TODO 
-                    }
-                    Operator::V128Bitselect => { 
-                        // TODO 
-                    }
-                    Operator::I8x16AnyTrue
-                    | Operator::I8x16AllTrue
-                    | Operator::I16x8AnyTrue
-                    | Operator::I16x8AllTrue
-                    | Operator::I32x4AnyTrue
-                    | Operator::I32x4AllTrue
-                    | Operator::I64x2AnyTrue
-                    | Operator::I64x2AllTrue => { 
-                        // TODO 
-                    }
-                    Operator::I8x16Shl
-                    | Operator::I8x16ShrS
-                    | Operator::I8x16ShrU
-                    | Operator::I16x8Shl
-                    | Operator::I16x8ShrS
-                    | Operator::I16x8ShrU
-                    | Operator::I32x4Shl
-                    | Operator::I32x4ShrS
-                    | Operator::I32x4ShrU
-                    | Operator::I64x2Shl
-                    | Operator::I64x2ShrS
-                    | Operator::I64x2ShrU => { 
-                        // TODO 
-                    }
+    // the fixtures built from committed WAT whose traversal is held to tests/parallelization/traversal.golden
+    const TRAVERSAL_FIXTURES:[&str; 35] = [
+        "add", "atomics", "bits", "blocks", "branches", "bulk", "chains", "conditions", "conversions", "diamond",
+        "division", "floats", "game_of_life", "globals", "inline", "kernels", "library", "linked", "locals", "math",
+        "memory", "memory_exported", "memory_grow", "memory_imported", "memory_size", "ranges", "recursion",
+        "reinterpret", "select", "shifts", "simd", "subword", "sum", "test", "zeros"
+    ];
+
+    // describes a node of an expanded tree and the blocks nested in it, a line per node giving its calls, by the
+    // locations they are at, and how many couplings of each kind it has
+    fn describe_traversal(id:usize, node:&Node, depth:usize, text:&mut String) {
+        for visit in node.walk() {
+            let node = visit.node;
+            let mut calls:Vec<(usize, NodeId)> = node.calls.iter().map(|(location, id)| (*location, *id)).collect();
+            calls.sort();
+            let calls:Vec<String> = calls.iter().map(|(location, id)| format!("{}@{}", id, location)).collect();
+            let mut shared:Vec<usize> = node.shared_children.keys().map(|id| id.0).collect();
+            shared.sort();
+            writeln!(text, "{}{} {}: calls [{}], shared {:?}, couplings {} in, {} out, {} global in, {} global out, {} sync, {} range",
+                "  ".repeat(depth + visit.depth), if node.is_function() { "function" } else { "block" }, visit.id.map_or(id, |id| id.0), calls.join(", "), shared,
+                node.input_data_couplings.len(), node.output_data_couplings.len(), node.global_input_data_couplings.values().map(Vec::len).sum::<usize>(),
+                node.global_output_data_couplings.values().map(Vec::len).sum::<usize>(), node.sync_couplings.values().map(Vec::len).sum::<usize>(), node.range_couplings.len()).unwrap();
+        }
+    }
 
-                    Operator::MemoryInit { segment } => { 
-                        // TODO 
-                    }
-                    Operator::DataDrop { segment } => { 
-                        // TODO 
-                    }
-                    Operator::MemoryCopy | Operator::MemoryFill => { 
-                        // TODO 
-                    }
-                    Operator::TableInit { segment } => { 
-                        // TODO 
-                    }
-                    Operator::ElemDrop { segment } => { 
-                        // TODO 
-                    }
-                    Operator::TableCopy => { 
-                        // TODO 
-                    }
-                    Operator::TableGet { table } => { 
-                        // TODO 
-                    }
-                    Operator::TableSet { table } => { 
-                        // TODO 
-                    }
-                    Operator::TableGrow { table } => { 
-                        // TODO 
-                    }
-                    Operator::TableSize { table } => { 
-                        // TODO 
-                    }
-                }
-                // print out each encountered operator
-                println!("{}. {:?}", i, op);
-            } else {
+    // maps a fixture without asking anything, describing how many functions and blocks its tree has, the call
+    // edges between them and the couplings of each node
+    fn traversal_snapshot(name:&str) -> String {
+        let buf = std::fs::read(format!("{}/tests/parallelization/{}.wasm", env!("CARGO_MANIFEST_DIR"), name)).unwrap();
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let tree = mapper.map(buf);
+        let mut functions:Vec<(&NodeId, &Node)> = tree.iter().collect();
+        functions.sort_by_key(|(index, _)| **index);
+        let mut nodes = String::new();
+        for (index, function) in functions {
+            describe_traversal(index.0, function, 1, &mut nodes);
+        }
+        let blocks = nodes.lines().filter(|line| line.trim_start().starts_with("block")).count();
+        let edges:usize = nodes.lines().map(|line| line.split("calls [").nth(1).map_or(0, |calls| calls.split(']').next().unwrap().split(", ").filter(|call| !call.is_empty()).count())).sum();
+        format!("fixture {}: {} functions, {} blocks, {} call edges\n{}", name, tree.len(), blocks, edges, nodes)
+    }
 
-                // red is for bad WASM
-                stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)));
-                println!("Bad wasm code {:?}", read.err());
+    #[test]
+    fn invariants() {
+
+        // every node of every fixture's tree keeps the invariants
+        for name in TRAVERSAL_FIXTURES.iter() {
+            let mut mapper = new_mapper();
+            mapper.set_observer(Box::new(SilentObserver));
+            let tree = mapper.map(std::fs::read(format!("{}/tests/parallelization/{}.wasm", env!("CARGO_MANIFEST_DIR"), name)).unwrap());
+            for node in tree.values().chain(mapper.blocks().values()) {
+                assert_eq!(node.verify_invariants(), Vec::new(), "{} breaks an invariant", name);
             }
         }
 
-        // set the node's instruction list
-        let end = node.get_end();
-        node.set_instrs(buf[start..end].to_vec());
+        // bookkeeping that doesn't hold together is reported by the node it belongs to
+        let mut node = Node::default();
+        node.add_input_variable(Type::I32);
+        node.input_variables.insert(3, Type::I32);
+        node.add_input_data_coupling(MemAccess { static_offset: 8, align: 4, width: 4, base_expr: None, address: None, kind: AccessKind::Read }, 5);
+        node.add_operation(4, AbstractExpression::Reuse { location: 6 });
+        node.set_start(0x20);
+        node.set_end(0x10);
+        let descriptions:Vec<String> = node.verify_invariants().into_iter().map(|violation| violation.description).collect();
+        assert_eq!(descriptions, vec![
+            "input variable 3 is numbered past the 2 input variables".to_string(),
+            "a memory input coupling refers to variable 5, which the node doesn't have".to_string(),
+            "location 4 reuses location 6, which isn't an operation recorded before it".to_string(),
+            "the node's code starts at 0x20, past its end at 0x10".to_string()
+        ]);
+    }
 
-        node
+    // encodes a module of a single function returning an i32, taking the given number of i32 parameters and
+    // declaring the given number of i32 locals, whose code follows the steps as far as the stack allows: each pushes
+    // a constant, reads a local, adds, subtracts or multiplies the two values on top of the stack, or writes one to a
+    // local, before what is left on the stack is added up into the result
+    fn straight_line_module(params:u8, locals:u8, steps:&[(u8, u8)]) -> Vec<u8> {
+        let leb = |mut value:usize, bytes:&mut Vec<u8>| loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                break;
+            }
+            bytes.push(byte | 0x80);
+        };
+        let count = params + locals;
+        let mut body = if locals > 0 { vec![1, locals, 0x7f] } else { vec![0] };
+        let mut depth = 0;
+        for &(kind, value) in steps {
+            match kind {
+                0 => body.extend(&[0x41, value & 0x3f]),
+                1 => body.extend(&[0x20, value % count]),
+                2..=4 if depth >= 2 => body.push([0x6a, 0x6b, 0x6c][kind as usize - 2]),
+                5 if depth >= 1 => body.extend(&[0x21, value % count]),
+                _ => continue
+            }
+            depth = match kind { 0 | 1 => depth + 1, _ => depth - 1 };
+        }
+        for _ in 1..depth {
+            body.push(0x6a);
+        }
+        if depth == 0 {
+            body.extend(&[0x41, 0]);
+        }
+        body.push(0x0b);
+
+        let mut types = vec![1, 0x60, params];
+        types.extend(std::iter::repeat(0x7f).take(params as usize));
+        types.extend(&[1, 0x7f]);
+        let mut code = vec![1];
+        leb(body.len(), &mut code);
+        code.extend(body);
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        for (id, section) in [(1, types), (3, vec![1, 0]), (10, code)] {
+            module.push(id);
+            leb(section.len(), &mut module);
+            module.extend(section);
+        }
+        module
     }
-}
 
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
 
-// Initializes a Node mapper
-pub fn new_mapper() -> Mapper {
-    Mapper::default()
+        #[test]
+        fn straight_line_invariants(params in 1u8..4, locals in 0u8..4, steps in proptest::collection::vec((0u8..6, 0u8..64), 0..48)) {
+            let mut mapper = new_mapper();
+            mapper.set_observer(Box::new(SilentObserver));
+            let tree = mapper.map(straight_line_module(params, locals, &steps));
+            prop_assert!(mapper.get_error().is_none());
+
+            // every parameter is an input variable, and nothing recorded along the way breaks an invariant
+            prop_assert!(tree[&NodeId(0)].input_variables().len() >= params as usize);
+            prop_assert_eq!(tree[&NodeId(0)].verify_invariants(), Vec::new());
+        }
+    }
+
+    #[test]
+    fn golden_traversal() {
+        let path = format!("{}/tests/parallelization/traversal.golden", env!("CARGO_MANIFEST_DIR"));
+        let snapshot:String = TRAVERSAL_FIXTURES.iter().map(|name| traversal_snapshot(name)).collect();
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(&path, &snapshot).unwrap();
+        }
+        let golden = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(snapshot, golden, "the traversal of the fixtures differs from {}", path);
+    }
+
+    #[test]
+    fn gadget_lowering() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/add.wasm").to_vec());
+
+        // narrowed to two bits, the ground states of the addition are every pair of parameters with their sum
+        let mut add = nodes[&NodeId(0)].clone();
+        add.set_bit_width(Some(2));
+        let constraint = add.lower();
+        let qubo = constraint.qubo();
+        assert_eq!(qubo.variables(), (0..8).collect::<Vec<usize>>());
+        let (lowest, states) = qubo.ground_states();
+        assert_eq!((lowest, states.len()), (0, 16));
+        let value = |state:&BTreeSet<usize>, word:&[usize]| word.iter().enumerate().filter(|&(_, variable)| state.contains(variable)).map(|(bit, _)| 1 << bit).sum::<usize>();
+        for state in states {
+            assert_eq!(value(&state, &[4, 6]), (value(&state, &[0, 1]) + value(&state, &[2, 3])) % 4);
+        }
+
+        // a node that isn't lowered has no penalties
+        let mut observer = RecordingObserver::default();
+        assert!(nodes[&NodeId(0)].clone().lower_with(&mut observer).is_empty());
+    }
+
+    #[test]
+    fn carry_save_lowering() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/sum.wasm").to_vec());
+        let carry_save = LoweringConfig { adders: AdderSynthesis::CarrySave, ..LoweringConfig::default() };
+
+        // the three additions are a single tree of the four parameters
+        let mut sum = nodes[&NodeId(0)].clone();
+        assert_eq!(sum.adder_trees(), vec![(4, 32)]);
+        let ripple = sum.lower().qubo().variables().len();
+        let tree = sum.lower_with_config(&mut SilentObserver, carry_save).qubo().variables().len();
+        assert_eq!(ripple - tree, 3 * 2 * 32 - gadgets::carry_save_variables(4, 32));
+
+        // narrowed to two bits, the ground states are every choice of parameters with their sum
+        sum.set_bit_width(Some(2));
+        let (lowest, states) = sum.lower_with_config(&mut SilentObserver, carry_save).qubo().ground_states();
+        assert_eq!((lowest, states.len()), (0, 256));
+        let value = |state:&BTreeSet<usize>, word:&[usize]| word.iter().enumerate().filter(|&(_, variable)| state.contains(variable)).map(|(bit, _)| 1 << bit).sum::<usize>();
+        for state in states {
+            let parameters:usize = (0..4).map(|parameter| value(&state, &[2 * parameter, 2 * parameter + 1])).sum();
+            assert_eq!(value(&state, &[8, 11]), parameters % 4);
+        }
+
+        // the plan reports the tree and what it saves
+        mapper.set_lowering_config(carry_save);
+        let plans = mapper.plan_lowering();
+        assert_eq!((plans[0].adder_trees, plans[0].adder_savings), (1, ripple - tree));
+        assert_eq!(plans[0].auxiliary_qubits, 3 * 2 * 32 - plans[0].adder_savings);
+    }
+
+    #[test]
+    fn annealability_report() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+
+        // double is small and mostly arithmetic, while scale's multiplication takes a third of the device
+        let report = mapper.annealability_report(5000);
+        assert_eq!(report.iter().map(|function| function.id).collect::<Vec<usize>>(), vec![0, 1, 2]);
+        assert_eq!((report[1].qubits, report[1].couplers, report[1].flagged), (1680, 6736, 0));
+        assert!(report[1].auxiliary_qubits > 0 && report[1].score < report[0].score);
+        assert_eq!(report[0].to_string(), "function 0: score 0.74, 75% simulatable, 96 qubits, 189 couplers, dynamic range 8");
+
+        // a function that doesn't fit on the device scores nothing
+        let report = mapper.annealability_report(1000);
+        assert_eq!((report[2].id, report[2].score), (1, 0.0));
+    }
+
+    #[test]
+    fn precision_check() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        let mut scale = nodes[&NodeId(1)].clone();
+
+        // the gadgets of a multiplication fit the default dynamic range, but not a much narrower one
+        mapper.set_precision_check(Some(PrecisionConfig::default()));
+        mapper.lower(&mut scale);
+        assert!(mapper.get_warnings().iter().all(|warning| warning.category != WarningCategory::Precision));
+        mapper.set_precision_check(Some(PrecisionConfig { dynamic_range: 2.0, ..PrecisionConfig::default() }));
+        let report = precision::analyze(mapper.lower(&mut scale).qubo(), &PrecisionConfig::default());
+        let warning = mapper.get_warnings().pop().unwrap();
+        assert_eq!((warning.node, warning.category), (1, WarningCategory::Precision));
+        assert!(warning.message.starts_with("Node 1 has terms beyond the annealer's dynamic range of 2: linear ["));
+        assert!(report.dynamic_range > 2.0 && report.flagged.is_empty());
+    }
+
+    #[test]
+    fn multiplier_synthesis() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        let karatsuba = MultiplierSynthesis::Karatsuba { cutoff: 8 };
+
+        // splitting 32-bit operands takes fewer qubits than adding up shifted copies, but more couplers
+        let mut scale = nodes[&NodeId(1)].clone();
+        let costs = scale.multiplier_costs(&[MultiplierSynthesis::ShiftAdd, karatsuba]);
+        assert_eq!((costs[0].multiplications, costs[1].multiplications), (1, 1));
+        assert!(costs[1].qubits < costs[0].qubits && costs[1].couplers > costs[0].couplers);
+
+        // the costs are what lowering the node's multiplication with either strategy allocates
+        let shift_add = scale.lower().qubo().variables().len();
+        scale.set_multiplier_synthesis(Some(karatsuba));
+        assert_eq!(shift_add - scale.lower().qubo().variables().len(), costs[0].qubits - costs[1].qubits);
+
+        // narrowed to the cutoff, the partial products are counted by column, with a ground state per choice of
+        // parameters
+        scale.set_bit_width(Some(2));
+        scale.set_multiplier_synthesis(Some(MultiplierSynthesis::Karatsuba { cutoff: 2 }));
+        let (lowest, states) = scale.lower().qubo().ground_states();
+        assert_eq!((lowest, states.len()), (0, 16));
+
+        // the plan costs multiplications with the strategy each node selects, or else the lowering config
+        mapper.set_lowering_config(LoweringConfig { multipliers: karatsuba, ..LoweringConfig::default() });
+        assert!(mapper.set_multiplier_synthesis(NodeId(1), Some(MultiplierSynthesis::ShiftAdd)) && !mapper.set_multiplier_synthesis(NodeId(7), None));
+        let plans = mapper.plan_lowering();
+        assert_eq!((plans[0].multipliers.multiplications, plans[1].multipliers), (0, costs[0]));
+        assert_eq!(plans[0].multipliers.strategy, karatsuba);
+        assert_eq!(karatsuba.to_string(), "karatsuba (cutoff 8)");
+    }
+
+    #[test]
+    fn width_inference() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.set_input_range(1, 0, Some(ValueRange::new(0, 7)));
+        mapper.set_input_range(1, 1, Some(ValueRange::new(0, 3)));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/ranges.wasm").to_vec());
+        let infer = LoweringConfig { infer_widths: true, ..LoweringConfig::default() };
+
+        // nothing writes to the memory, so the byte loaded is one of the data segment's
+        let tag = nodes[&NodeId(0)].clone();
+        assert_eq!(tag.get_input_range(1), Some(ValueRange::new(0, 7)));
+        assert_eq!(tag.value_ranges().last(), Some(&ValueRange::new(5, 12)));
+
+        // the byte takes three bits and its sum with the constant four, past the free address
+        let qubo = tag.clone().lower_with_config(&mut SilentObserver, infer).get_qubo();
+        assert_eq!(qubo.variables(), (32..44).collect::<Vec<usize>>());
+        assert!(qubo.variables().len() < tag.clone().lower().qubo().variables().len());
+        let (lowest, states) = qubo.ground_states();
+        assert_eq!((lowest, states.len()), (0, 8));
+        let value = |state:&BTreeSet<usize>, word:&[usize]| word.iter().enumerate().filter(|&(_, variable)| state.contains(variable)).map(|(bit, _)| 1 << bit).sum::<usize>();
+        for state in states {
+            assert_eq!(value(&state, &[38, 40, 42, 43]), value(&state, &[32, 33, 34]) + 5);
+        }
+
+        // the parameters were given ranges, which bound their product
+        let scale = nodes[&NodeId(1)].clone();
+        assert_eq!(scale.value_ranges().last(), Some(&ValueRange::new(0, 21)));
+        let (lowest, states) = scale.clone().lower_with_config(&mut SilentObserver, infer).qubo().ground_states();
+        assert_eq!((lowest, states.len()), (0, 32));
+
+        // loads from a memory that is written to span their types
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/memory.wasm").to_vec());
+        assert!(nodes[&NodeId(0)].get_input_width(0).is_some() && nodes[&NodeId(0)].get_input_range(0).is_none());
+    }
+
+    #[test]
+    fn output_constraints() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+
+        // pinning what scale returns leaves the parameters producing it as the ground states, here a * (b + 3) = 2
+        // modulo 4
+        let mut scale = nodes[&NodeId(1)].clone();
+        scale.set_bit_width(Some(2));
+        scale.constrain_output(NodeOutput::Result, 6);
+        let constraint = scale.lower();
+        let (lowest, states) = constraint.qubo().ground_states();
+        let (a, b) = (constraint.get_input_bits(0).unwrap(), constraint.get_input_bits(1).unwrap());
+        let solutions:BTreeSet<(u64, u64)> = states.iter().map(|state| (gadgets::word_value(&a, state), gadgets::word_value(&b, state))).collect();
+        assert_eq!((lowest, states.len()), (0, 4));
+        assert_eq!(solutions, vec![(1, 3), (2, 0), (2, 2), (3, 3)].into_iter().collect());
+        assert_eq!(constraint.get_result_bits().len(), 2);
+
+        // pinning an output again replaces its value, and an output the node doesn't have is left alone
+        scale.constrain_output(NodeOutput::Result, 1);
+        scale.constrain_output(NodeOutput::Value(99), 0);
+        assert_eq!(scale.get_output_constraints(), vec![(NodeOutput::Result, 1), (NodeOutput::Value(99), 0)]);
+        assert_eq!(scale.lower().qubo().ground_states().1.len(), 2);
+        scale.clear_output_constraints();
+        assert_eq!(scale.lower().qubo().ground_states().1.len(), 16);
+        assert!(mapper.constrain_output(NodeId(1), NodeOutput::Result, 0) && !mapper.constrain_output(NodeId(7), NodeOutput::Result, 0));
+    }
+
+    #[test]
+    fn input_markings() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        let mut scale = nodes[&NodeId(1)].clone();
+        scale.set_bit_width(Some(2));
+        scale.constrain_output(NodeOutput::Result, 2);
+        let free = scale.lower().qubo().variables().len();
+
+        // a fixed input is propagated out of the penalties, leaving the others to solve for, here 2 * (b + 3) = 2
+        // modulo 4
+        scale.mark_input(0, InputMarking::Fixed(6));
+        let constraint = scale.lower();
+        let (lowest, states) = constraint.qubo().ground_states();
+        let b = constraint.get_input_bits(1).unwrap();
+        let solutions:BTreeSet<u64> = states.iter().map(|state| gadgets::word_value(&b, state)).collect();
+        assert_eq!((lowest, solutions), (0, vec![0, 2].into_iter().collect()));
+        assert_eq!(constraint.input_bits().keys().cloned().collect::<Vec<usize>>(), vec![1]);
+        assert!(constraint.qubo().variables().len() < free);
+
+        // with every input fixed nothing is left to solve for, and a pin the inputs miss can't be met
+        scale.mark_input(1, InputMarking::Fixed(3));
+        assert!(scale.lower().qubo().variables().is_empty() && scale.lower().qubo().get_offset() > 0);
+        scale.mark_input(1, InputMarking::Fixed(2));
+        assert_eq!(scale.lower().qubo().get_offset(), 0);
+        assert_eq!(scale.value_ranges()[0], ValueRange::constant(2));
+
+        // a don't-care is left free but isn't reported
+        scale.mark_input(0, InputMarking::Free);
+        scale.mark_input(1, InputMarking::DontCare);
+        assert_eq!((scale.get_input_marking(0), scale.get_input_marking(1)), (InputMarking::Free, InputMarking::DontCare));
+        let constraint = scale.lower();
+        assert_eq!((constraint.qubo().variables().len(), constraint.input_bits().len()), (free, 1));
+        assert!(mapper.mark_input(NodeId(1), 0, InputMarking::DontCare) && !mapper.mark_input(NodeId(7), 0, InputMarking::Free));
+    }
+
+    #[test]
+    fn golden_lowering() {
+        assert_golden("add", include_bytes!("../tests/parallelization/add.wasm"));
+        assert_golden("select", include_bytes!("../tests/parallelization/select.wasm"));
+        assert_golden("branches", include_bytes!("../tests/parallelization/branches.wasm"));
+        assert_golden("subword", include_bytes!("../tests/parallelization/subword.wasm"));
+    }
+
+    #[test]
+    fn unlowerable_nodes() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/branches.wasm").to_vec());
+        let pick = &nodes[&NodeId(0)];
+
+        // an operand of the wrong type leaves the node's constraint empty rather than aborting
+        let mut branch = pick.children()[&pick.calls()[&6]].clone();
+        branch.add_constant(2, Type::I64);
+        assert!(branch.clone().lower().is_empty());
+        assert!(mapper.lower(&mut branch).is_empty());
+        let warning = mapper.get_warnings().pop().unwrap();
+        assert_eq!((warning.node, warning.location, warning.category), (branch.get_id().0, Some(3), WarningCategory::IllTyped));
+
+        // and so does a nested node that isn't coupled to the variable guarding it
+        let mut pick = pick.clone();
+        let branch_id = pick.calls()[&6];
+        pick.children.get_mut(&branch_id).unwrap().flow_control_couplings.clear();
+        assert!(pick.clone().lower().is_empty());
+        mapper.lower(&mut pick);
+        assert_eq!(mapper.get_warnings().pop().unwrap().category, WarningCategory::IncompleteCoupling);
+    }
+
+    #[test]
+    fn nested_lowering() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/select.wasm").to_vec());
+
+        // the if's then clause is lowered along with the function, over variables past the function's own
+        let constraint = nodes[&NodeId(0)].clone().lower();
+        let block = nodes[&NodeId(0)].get_callee(NodeId(1)).unwrap().clone().lower();
+        assert!(constraint.binarizations().contains_key(&1));
+        assert_eq!(constraint.qubo().variables().len(), block.qubo().variables().len());
+        let by = constraint.qubo().variables()[0];
+        assert_eq!(constraint.get_qubo(), block.qubo().shifted(by));
+        assert_eq!(constraint.binarizations()[&1].result, block.get_result_bits().iter().map(|bit| bit + by).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn subword_loads() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/subword.wasm").to_vec());
+
+        // each load records how much it read, by the variable it reads
+        let widths = |node:&Node| {
+            let mut widths:Vec<(Type, LoadWidth)> = node.input_variables().iter()
+                .map(|(var_id, ty)| (*ty, node.get_input_width(*var_id).unwrap()))
+                .collect();
+            widths.sort_by_key(|&(_, width)| width.bits);
+            widths
+        };
+        assert_eq!(widths(&nodes[&NodeId(0)]), vec![(Type::I32, LoadWidth { bits: 8, signed: true }), (Type::I32, LoadWidth { bits: 16, signed: false })]);
+        assert_eq!(widths(&nodes[&NodeId(1)]), vec![(Type::I64, LoadWidth { bits: 32, signed: true }), (Type::I64, LoadWidth::full(Type::I64))]);
+
+        // the bits a load didn't read are masked off and replaced by the extension of the bits it did
+        let evaluate = |node:&Node, values:[u64; 2]| {
+            let mut inputs = HashMap::new();
+            for (var_id, _) in node.input_variables().clone() {
+                let width = node.get_input_width(var_id).unwrap();
+                inputs.insert(var_id, if width.bits == 8 || width.bits == 32 { values[0] } else { values[1] });
+            }
+            interp::evaluate(node, &inputs).result()
+        };
+        assert_eq!(evaluate(&nodes[&NodeId(0)], [0x1ff, 0xf0003]), Some(2));
+        assert_eq!(evaluate(&nodes[&NodeId(0)], [0x17f, 0x8000]), Some(0x807f));
+        assert_eq!(evaluate(&nodes[&NodeId(1)], [0xffff_ffff, 3]), Some(2));
+        assert!(typecheck::check_node(&nodes[&NodeId(1)]).is_empty());
+
+        // only the narrower loads are extended once lowered to SSA form
+        let extensions = |index:usize| mapper.emit_ssa(NodeId(index)).unwrap().instructions().iter()
+            .filter(|instruction| instruction.definition.name() == "Extend")
+            .count();
+        assert_eq!((extensions(0), extensions(1)), (2, 1));
+    }
+
+    #[test]
+    fn conversions() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/conversions.wasm").to_vec());
+        let shown = |node:&Node| node.get_operations().values().map(|op| op.to_string()).collect::<Vec<String>>();
+        assert!(shown(&nodes[&NodeId(0)]).contains(&"extend I32 to I32 (8 bits, signed)".to_string()));
+        assert!(shown(&nodes[&NodeId(0)]).contains(&"extend I32 to I64 (32 bits, signed)".to_string()));
+        assert!(shown(&nodes[&NodeId(1)]).contains(&"truncate F32 to I32 (unsigned)".to_string()));
+        assert!((0..3).all(|index| typecheck::check_node(&nodes[&NodeId(index)]).is_empty()));
+
+        let evaluate = |node:&Node, values:&[u64]| {
+            let variables = node.get_local_input_variables();
+            let inputs:HashMap<usize, u64> = values.iter().enumerate().map(|(param, value)| (variables[&param], *value)).collect();
+            interp::evaluate(node, &inputs).result()
+        };
+
+        // narrow adds the sign extended low byte of its first parameter to the low half word of its second
+        assert_eq!(evaluate(&nodes[&NodeId(0)], &[0x80, 0x1_8001]), Some((-128i64 - 32767) as u64));
+        assert_eq!(evaluate(&nodes[&NodeId(0)], &[0x17f, 5]), Some(132));
+
+        // a float's integer part saturates to the unsigned range, and NaN converts to zero
+        for &(float, result) in [(7.9f32, 7), (-3.5, 0), (1e10, 0xffff_ffff), (f32::NAN, 0)].iter() {
+            assert_eq!(evaluate(&nodes[&NodeId(1)], &[float.to_bits() as u64]), Some(result));
+        }
+
+        // wrapping keeps the low word, and the constant's wrap was folded away while mapping
+        assert_eq!(evaluate(&nodes[&NodeId(2)], &[0x1234_5678_0000_0001]), Some(3));
+        assert_eq!(shown(&nodes[&NodeId(2)]).iter().filter(|op| op.starts_with("truncate")).count(), 1);
+        assert!(nodes[&NodeId(2)].get_operations().values().any(|op| *op == AbstractExpression::Num{ val: 2 }));
+
+        // integer conversions rewire bits, while the saturation is lowered to a clamp
+        let definitions = |index:usize| mapper.emit_ssa(NodeId(index)).unwrap().instructions().iter()
+            .map(|instruction| instruction.definition.name())
+            .filter(|name| *name == "Extend" || *name == "Clamp")
+            .collect::<Vec<&str>>();
+        assert_eq!((definitions(0), definitions(1), definitions(2)), (vec!["Extend"; 3], vec!["Clamp"], vec!["Extend"]));
+        assert!(!nodes[&NodeId(1)].clone().lower().qubo().variables().is_empty());
+    }
+
+    #[test]
+    fn conversion_widths() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/chains.wasm").to_vec());
+        let infer = LoweringConfig { infer_widths: true, ..LoweringConfig::default() };
+
+        // lowers a node whose parameters were given the ranges, checking that every ground state holds the native
+        // result of the parameters it assigns, and that there is one for each assignment
+        let check = |node:&Node, ranges:&[ValueRange], native:&dyn Fn(u64, u64) -> u64| {
+            let mut node = node.clone();
+            let variables = node.get_local_input_variables();
+            for (param, range) in ranges.iter().enumerate() {
+                node.set_input_range(variables[&param], Some(*range));
+            }
+            let constraint = node.lower_with_config(&mut SilentObserver, infer);
+            let words:Vec<Vec<usize>> = (0..ranges.len()).map(|param| constraint.get_input_bits(variables[&param]).unwrap()).collect();
+            let (lowest, states) = constraint.qubo().ground_states();
+            assert_eq!((lowest, states.len()), (0, ranges.iter().map(|range| range.max - range.min + 1).product::<u64>() as usize));
+            for state in states.iter() {
+                let values:Vec<u64> = words.iter().map(|word| gadgets::word_value(word, state)).collect();
+                let result = gadgets::word_value(&constraint.get_result_bits(), state);
+                assert_eq!(result, native(values[0], values.get(1).cloned().unwrap_or(0)), "{:?}", values);
+            }
+        };
+
+        // a word narrowed by its range has no sign bit to extend, so its highest bit is kept as it is
+        check(&nodes[&NodeId(1)], &[ValueRange::new(0, 7), ValueRange::new(0, 7)], &|a, x| a + x);
+        check(&nodes[&NodeId(2)], &[ValueRange::new(0, 7), ValueRange::new(0, 7)], &|x, a| x + a);
+
+        // wrapping and extending what is known to fit in the low bits only rewires them, so the result is the
+        // parameter's own variables
+        let mut roundtrip = nodes[&NodeId(0)].clone();
+        let variables = roundtrip.get_local_input_variables();
+        roundtrip.set_input_range(variables[&0], Some(ValueRange::new(0, 15)));
+        let constraint = roundtrip.lower_with_config(&mut SilentObserver, infer);
+        assert_eq!(constraint.get_result_bits(), constraint.get_input_bits(variables[&0]).unwrap());
+        assert_eq!(constraint.get_result_bits().len(), 4);
+    }
+
+    #[test]
+    fn division_strength() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/division.wasm").to_vec());
+        let shown = |index:usize| nodes[&NodeId(index)].get_operations().values().map(|op| op.to_string()).collect::<Vec<String>>();
+
+        // powers of two become shifts and masks, and other constants multiplications by a reciprocal if one is exact
+        assert_eq!(shown(0), vec!["spin 0", "shr_u I32 by 3"]);
+        assert!(shown(1).contains(&"extend I32 to I32 (4 bits, unsigned)".to_string()));
+        assert!(shown(2).contains(&format!("mul_shr I64 by {} >> 67", 0xcccc_cccc_cccc_cccdu64)));
+        assert!(shown(3).contains(&"mul_shr I32 by 2863311531 >> 33".to_string()));
+        assert!(shown(4).contains(&"div_u I32".to_string()) && shown(5).contains(&"rem_u I32".to_string()));
+
+        // the rewritten operations agree with the divisions they replace, dividends with the top bit set included
+        assert!(mapper.validate_roundtrip(256, 3).mismatches.is_empty());
+        let evaluate = |index:usize, value:u64| {
+            let inputs:HashMap<usize, u64> = vec![(nodes[&NodeId(index)].get_local_input_variables()[&0], value)].into_iter().collect();
+            interp::evaluate(&nodes[&NodeId(index)], &inputs).result()
+        };
+        assert_eq!(evaluate(3, u32::MAX as u64), Some(u32::MAX as u64 / 3));
+        assert_eq!(evaluate(2, u64::MAX), Some(u64::MAX / 10));
+        assert_eq!(evaluate(4, 0x8000_0000), Some(0x8000_0000 / 7));
+
+        // shifting rewires the dividend's bits, and a reciprocal's product takes fewer qubits than dividing does
+        let constraint = nodes[&NodeId(0)].clone().lower();
+        let dividend = constraint.input_bits().values().next().unwrap().clone();
+        assert_eq!(constraint.get_result_bits()[..29], dividend[3..]);
+        let qubits = |index:usize| nodes[&NodeId(index)].clone().lower().qubo().variables().len();
+        assert!(qubits(3) < qubits(4));
+
+        // a range on the dividend bounds the quotient
+        let mut third = nodes[&NodeId(3)].clone();
+        let variables = third.get_local_input_variables();
+        third.set_input_range(variables[&0], Some(ValueRange::new(0, 300)));
+        assert_eq!(third.value_ranges().last(), Some(&ValueRange::new(0, 100)));
+    }
+
+    #[test]
+    fn zero_tests() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/zeros.wasm").to_vec());
+        let shown = |node:&Node| node.get_operations().values().map(|op| op.to_string()).collect::<Vec<String>>();
+        assert_eq!(shown(&nodes[&NodeId(0)]), vec!["spin 0", "eqz I32"]);
+        assert_eq!(shown(&nodes[&NodeId(1)]), vec!["spin 0", "eqz I64"]);
+        assert!((0..4).all(|index| typecheck::check_node(&nodes[&NodeId(index)]).is_empty()));
+        assert!(mapper.validate_roundtrip(256, 3).mismatches.is_empty());
+
+        let evaluate = |index:usize, operands:&[u64]| {
+            let variables = nodes[&NodeId(index)].get_local_input_variables();
+            let inputs:HashMap<usize, u64> = operands.iter().enumerate().map(|(k, &value)| (variables[&k], value)).collect();
+            interp::evaluate(&nodes[&NodeId(index)], &inputs).result()
+        };
+        assert_eq!((evaluate(0, &[0]), evaluate(0, &[5]), evaluate(1, &[1 << 40]), evaluate(1, &[0])), (Some(1), Some(0), Some(0), Some(1)));
+
+        // a zero test right before an if is folded into the if, whose branch is anti-chained to the tested value
+        // and whose else clause is chained to it
+        let guard = &nodes[&NodeId(2)];
+        assert_eq!(shown(guard), vec!["spin 0", "spin 3"]);
+        let branch = &guard.children()[&guard.calls()[&3]];
+        let else_clause = &branch.children()[&branch.calls()[&2]];
+        assert_eq!((branch.chains()[&3], else_clause.chains().values().cloned().collect::<Vec<bool>>()), (false, vec![true]));
+        assert_eq!((evaluate(2, &[0, 9]), evaluate(2, &[4, 9])), (Some(9), Some(7)));
+
+        // a conditional branch consumes its condition through a flow control variable of its own, anti-chained
+        // to a value tested for zero right before it
+        let block = &nodes[&NodeId(3)].children()[&nodes[&NodeId(3)].calls()[&1]];
+        assert_eq!(shown(block), vec!["spin 0", "spin 3", "spin 1", "spin 5"]);
+        assert_eq!(block.chains(), &vec![(3, false), (5, true)].into_iter().collect());
+        assert!(typecheck::check_node(block).is_empty());
+
+        // the test negates an OR of the operand's bits, which holds in every ground state of a narrowed operand
+        let mut node = nodes[&NodeId(0)].clone();
+        let variable = node.get_local_input_variables()[&0];
+        node.set_input_range(variable, Some(ValueRange::new(0, 7)));
+        let constraint = node.lower_with_config(&mut SilentObserver, LoweringConfig { infer_widths: true, ..LoweringConfig::default() });
+        let parameter = constraint.get_input_bits(variable).unwrap();
+        let (lowest, states) = constraint.qubo().ground_states();
+        assert_eq!((lowest, states.len(), constraint.get_result_bits().len()), (0, 8, 1));
+        for state in states.iter() {
+            let zero = gadgets::word_value(&parameter, state) == 0;
+            assert_eq!(gadgets::word_value(&constraint.get_result_bits(), state), zero as u64);
+        }
+        assert_eq!(nodes[&NodeId(1)].clone().lower().qubo().variables().len(), 64 + nodes[&NodeId(1)].auxiliary_qubits());
+    }
+
+    #[test]
+    fn conditions() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/conditions.wasm").to_vec());
+        let producers = |node:&Node| node.get_conditions().into_iter().collect::<Vec<(usize, usize)>>();
+
+        // ifs, conditional branches and selects consume the value the operation right before them produced,
+        // which isn't known once a call left its result on the stack
+        let block = &nodes[&NodeId(1)].children()[&nodes[&NodeId(1)].calls()[&1]];
+        assert_eq!((producers(&nodes[&NodeId(0)]), producers(block), producers(&nodes[&NodeId(2)])), (vec![(4, 3)], vec![(4, 3)], vec![(4, 3)]));
+        assert!(producers(&nodes[&NodeId(3)]).is_empty());
+        assert!(mapper.validate_roundtrip(256, 3).mismatches.is_empty());
+
+        // the if's flow control variable is coupled to the sum it tests
+        let graph = nodes[&NodeId(0)].to_ssa();
+        let condition = graph.instructions().iter().find(|instruction| instruction.location == 4).map(|instruction| instruction.definition.clone());
+        let sum = graph.instructions().iter().find(|instruction| instruction.location == 3).unwrap().value;
+        assert_eq!(condition, Some(Definition::Internal{ var_id: 4, condition: Some(sum) }));
+        let graph = nodes[&NodeId(3)].to_ssa();
+        assert!(graph.instructions().iter().any(|instruction| instruction.definition == Definition::Internal{ var_id: 4, condition: None }));
+
+        // so it holds the sum in every ground state, rather than being free
+        let mut node = nodes[&NodeId(0)].clone();
+        let variables = node.get_local_input_variables();
+        for k in 0..2 {
+            node.set_input_range(variables[&k], Some(ValueRange::new(0, 3)));
+        }
+        let constraint = node.lower_with_config(&mut SilentObserver, LoweringConfig { infer_widths: true, ..LoweringConfig::default() });
+        let (x, y) = (constraint.get_input_bits(variables[&0]).unwrap(), constraint.get_input_bits(variables[&1]).unwrap());
+        let condition = constraint.get_condition_bits(4).unwrap();
+        let (lowest, states) = constraint.qubo().ground_states();
+        assert_eq!((lowest, states.len(), condition.len()), (0, 16, 3));
+        for state in states.iter() {
+            assert_eq!(gadgets::word_value(&condition, state), gadgets::word_value(&x, state) + gadgets::word_value(&y, state));
+        }
+    }
+
+    #[test]
+    fn branch_profile() {
+        let buf = include_bytes!("../tests/parallelization/conditions.wasm").to_vec();
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(buf.clone());
+
+        // the profile names branches by their program points, which a run without one already gives
+        let block_if = nodes[&NodeId(1)].children()[&nodes[&NodeId(1)].calls()[&1]].branch_sites()[&4];
+        let mut profile = BranchProfile::new();
+        profile.set(nodes[&NodeId(0)].branch_sites()[&4], BranchCounts::new(3, 1));
+        profile.set(block_if, BranchCounts::new(0, 8));
+        profile.set(ProgramPoint::new(0, 0), BranchCounts::new(5, 5));
+        mapper.set_branch_profile(Some(BranchProfile::from_json(&profile.to_json()).unwrap()));
+
+        // the conditions, including those of the expanded callees, are weighed with their counts
+        let nodes = mapper.map(buf);
+        let block = &nodes[&NodeId(1)].children()[&nodes[&NodeId(1)].calls()[&1]];
+        assert_eq!(nodes[&NodeId(0)].get_branch_probability(4), Some(0.75));
+        assert_eq!(block.get_branch_probability(4), Some(0.0));
+        assert_eq!(nodes[&NodeId(2)].branch_counts().len(), 0);
+        assert_eq!(nodes[&NodeId(0)].branch_counts().keys().cloned().collect::<Vec<usize>>(), vec![4]);
+    }
+
+    #[test]
+    fn coverage() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/conditions.wasm").to_vec());
+
+        // a run entering function 0 and reaching its if, but nothing else, covers the function and its branch
+        let condition = nodes[&NodeId(0)].branch_sites()[&4];
+        let report = mapper.coverage(&[ProgramPoint::new(0, nodes[&NodeId(0)].get_start()), condition]);
+        assert_eq!(report.functions.iter().filter(|(_, covered)| **covered).map(|(index, _)| *index).collect::<Vec<usize>>(), vec![0]);
+        assert_eq!(report.functions.len(), nodes.len());
+        assert_eq!(report.branches.len(), 4);
+        assert_eq!(report.branch_coverage(), 0.25);
+        assert!(!report.missed_branches().contains(&condition));
+
+        // blocks are covered once the trace reaches code inside them
+        let block = nodes[&NodeId(1)].children()[&nodes[&NodeId(1)].calls()[&1]].branch_sites()[&4];
+        let report = mapper.coverage(&[block]);
+        assert_eq!(report.blocks.values().filter(|covered| **covered).count(), 1);
+        assert!(report.function_coverage() > 0.0 && report.function_coverage() < 1.0);
+        assert_eq!(mapper.coverage(&[]).block_coverage(), 0.0);
+    }
+
+    #[test]
+    fn bit_counts() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/bits.wasm").to_vec());
+        let shown = |index:usize| nodes[&NodeId(index)].get_operations().values().map(|op| op.to_string()).collect::<Vec<String>>();
+        assert_eq!(shown(0), vec!["spin 0", "clz I32"]);
+        assert_eq!(shown(1), vec!["spin 0", "ctz I32"]);
+        assert_eq!(shown(2), vec!["spin 0", "popcnt I64"]);
+        assert_eq!(shown(3), vec!["num 8"]);
+        assert!((0..5).all(|index| typecheck::check_node(&nodes[&NodeId(index)]).is_empty()));
+
+        // a zero operand has as many leading and trailing zeros as its type has bits
+        assert!(mapper.validate_roundtrip(256, 3).mismatches.is_empty());
+        let evaluate = |index:usize, value:u64| {
+            let inputs:HashMap<usize, u64> = vec![(nodes[&NodeId(index)].get_local_input_variables()[&0], value)].into_iter().collect();
+            interp::evaluate(&nodes[&NodeId(index)], &inputs).result()
+        };
+        assert_eq!((evaluate(0, 0), evaluate(1, 0), evaluate(2, u64::MAX)), (Some(32), Some(32), Some(64)));
+        assert_eq!(evaluate(4, 0x0001_0001), Some(15 + 2));
+
+        // every ground state holds the count of the parameter it assigns, with a narrowed parameter's higher bits
+        // counted as leading zeros
+        let infer = LoweringConfig { infer_widths: true, ..LoweringConfig::default() };
+        let check = |index:usize, range:ValueRange, native:&dyn Fn(u64) -> u64| {
+            let mut node = nodes[&NodeId(index)].clone();
+            let variables = node.get_local_input_variables();
+            node.set_input_range(variables[&0], Some(range));
+            let constraint = node.lower_with_config(&mut SilentObserver, infer);
+            let parameter = constraint.get_input_bits(variables[&0]).unwrap();
+            let (lowest, states) = constraint.qubo().ground_states();
+            assert_eq!((lowest, states.len()), (0, (range.max - range.min + 1) as usize));
+            for state in states.iter() {
+                let value = gadgets::word_value(&parameter, state);
+                assert_eq!(gadgets::word_value(&constraint.get_result_bits(), state), native(value), "{}", value);
+            }
+        };
+        check(0, ValueRange::new(0, 3), &|x| (x as u32).leading_zeros() as u64);
+        check(1, ValueRange::new(0, 3), &|x| (x as u32).trailing_zeros() as u64);
+        check(2, ValueRange::new(0, 15), &|x| x.count_ones() as u64);
+
+        // the estimate is what lowering allocates besides the parameter's bits
+        for &(index, width) in [(0, 32), (2, 64)].iter() {
+            let estimate = nodes[&NodeId(index)].auxiliary_qubits();
+            assert_eq!(nodes[&NodeId(index)].clone().lower().qubo().variables().len(), width + estimate);
+        }
+        assert_eq!(nodes[&NodeId(2)].auxiliary_qubits(), gadgets::popcount_variables(64, 64));
+    }
+
+    #[test]
+    fn float_signs() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/floats.wasm").to_vec());
+        let shown = |index:usize| nodes[&NodeId(index)].get_operations().values().map(|op| op.to_string()).collect::<Vec<String>>();
+        assert_eq!(shown(0), vec!["spin 0", "spin 1", "min F32"]);
+        assert_eq!(shown(1), vec!["spin 0", "spin 1", "max F64"]);
+        assert_eq!(shown(2), vec!["spin 0", "abs F32"]);
+        assert_eq!(shown(3), vec!["spin 0", "neg F64"]);
+        assert_eq!(shown(4), vec!["spin 0", "spin 1", "copysign F32"]);
+        assert!((0..5).all(|index| typecheck::check_node(&nodes[&NodeId(index)]).is_empty()));
+        assert!(mapper.validate_roundtrip(256, 3).mismatches.is_empty());
+
+        // zeros order by sign and a NaN operand gives the canonical NaN
+        let evaluate = |index:usize, operands:&[u64]| {
+            let variables = nodes[&NodeId(index)].get_local_input_variables();
+            let inputs:HashMap<usize, u64> = operands.iter().enumerate().map(|(k, &value)| (variables[&k], value)).collect();
+            interp::evaluate(&nodes[&NodeId(index)], &inputs).result()
+        };
+        let single = |value:f32| value.to_bits() as u64;
+        let double = |value:f64| value.to_bits();
+        assert_eq!(evaluate(0, &[single(0.0), single(-0.0)]), Some(single(-0.0)));
+        assert_eq!(evaluate(0, &[single(-1.0), single(2.0)]), Some(single(-1.0)));
+        assert_eq!(evaluate(0, &[single(std::f32::NAN), single(1.0)]), Some(0x7fc0_0000));
+        assert_eq!(evaluate(1, &[double(-0.0), double(0.0)]), Some(double(0.0)));
+        assert_eq!(evaluate(1, &[double(1.5), double(std::f64::NAN)]), Some(0x7ff8_0000_0000_0000));
+        assert_eq!(evaluate(2, &[single(-2.5)]), Some(single(2.5)));
+        assert_eq!(evaluate(3, &[double(0.0)]), Some(double(-0.0)));
+        assert_eq!(evaluate(4, &[single(3.0), single(-0.0)]), Some(single(-3.0)));
+
+        // abs, neg and copysign only rewire the sign bit
+        let lowered = |index:usize| {
+            let variables = nodes[&NodeId(index)].get_local_input_variables();
+            let constraint = nodes[&NodeId(index)].clone().lower();
+            let parameters:Vec<Vec<usize>> = (0..variables.len()).map(|k| constraint.get_input_bits(variables[&k]).unwrap()).collect();
+            (parameters, constraint.get_result_bits(), constraint)
+        };
+        let (parameters, result, _) = lowered(2);
+        assert_eq!(&result[..31], &parameters[0][..31]);
+        assert!(!parameters[0].contains(&result[31]));
+        let (parameters, result, _) = lowered(3);
+        assert_eq!(&result[..63], &parameters[0][..63]);
+        assert_ne!(result[63], parameters[0][63]);
+        let (parameters, result, constraint) = lowered(4);
+        assert_eq!(&result[..31], &parameters[0][..31]);
+        assert_eq!(result[31], parameters[1][31]);
+        assert!(constraint.qubo().variables().len() <= 64);
+
+        // the estimate is what lowering allocates besides the operands' bits, and excluding NaNs needs fewer
+        let estimate = nodes[&NodeId(0)].auxiliary_qubits();
+        assert_eq!(estimate, 11 * 32 + 8);
+        assert_eq!(nodes[&NodeId(0)].clone().lower().qubo().variables().len(), 64 + estimate);
+        let exclude = LoweringConfig { nans: NanPolicy::Exclude, ..LoweringConfig::default() };
+        assert!(nodes[&NodeId(0)].clone().lower_with_config(&mut SilentObserver, exclude).qubo().variables().len() < 64 + estimate);
+    }
+
+    #[test]
+    fn shifts() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/shifts.wasm").to_vec());
+        let shown = |index:usize| nodes[&NodeId(index)].get_operations().values().map(|op| op.to_string()).collect::<Vec<String>>();
+        assert_eq!(shown(0), vec!["spin 0", "spin 1", "shl I32"]);
+        assert_eq!(shown(1), vec!["spin 0", "spin 1", "shr_s I64"]);
+        assert_eq!(shown(2), vec!["spin 0", "spin 1", "shr_u I32"]);
+        assert_eq!(shown(3), vec!["spin 0", "spin 1", "rotl I64"]);
+        assert_eq!(shown(4), vec!["spin 0", "num 8", "rotr I32"]);
+        assert!((0..6).all(|index| typecheck::check_node(&nodes[&NodeId(index)]).is_empty()));
+        assert!(mapper.validate_roundtrip(256, 3).mismatches.is_empty());
+
+        // amounts are taken modulo the width, and a signed shift fills in copies of the sign bit
+        let evaluate = |index:usize, operands:&[u64]| {
+            let variables = nodes[&NodeId(index)].get_local_input_variables();
+            let inputs:HashMap<usize, u64> = operands.iter().enumerate().map(|(k, &value)| (variables[&k], value)).collect();
+            interp::evaluate(&nodes[&NodeId(index)], &inputs).result()
+        };
+        assert_eq!(evaluate(0, &[1, 33]), Some(2));
+        assert_eq!(evaluate(1, &[-8i64 as u64, 1]), Some(-4i64 as u64));
+        assert_eq!(evaluate(2, &[0x8000_0000, 31]), Some(1));
+        assert_eq!(evaluate(3, &[0x8000_0000_0000_0001, 68]), Some(0x18));
+        assert_eq!(evaluate(4, &[0x1234_5678]), Some(0x7812_3456));
+        assert_eq!(evaluate(5, &[0x1234_5678]), Some(0x91a2_b3c0));
+
+        // a known amount only rewires the operand's bits, with a constant for the zeros filled in
+        let lowered = |index:usize| {
+            let variable = nodes[&NodeId(index)].get_local_input_variables()[&0];
+            let constraint = nodes[&NodeId(index)].clone().lower();
+            (constraint.get_input_bits(variable).unwrap(), constraint.get_result_bits(), constraint)
+        };
+        let (parameter, result, constraint) = lowered(4);
+        assert!((0..32).all(|bit| result[bit] == parameter[(bit + 8) % 32]));
+        assert!(constraint.qubo().is_empty());
+        let (parameter, result, constraint) = lowered(5);
+        assert_eq!(&result[3..], &parameter[..29]);
+        assert!(constraint.qubo().variables().len() <= 1);
+
+        // a variable amount takes a barrel shifter reading the amount's bits below the width's log, and the
+        // estimate is what it allocates besides the operands' bits
+        for &(index, width, log) in [(0, 32, 5), (1, 64, 6), (3, 64, 6)].iter() {
+            let estimate = nodes[&NodeId(index)].auxiliary_qubits();
+            assert_eq!(nodes[&NodeId(index)].clone().lower().qubo().variables().len(), width + log + estimate);
+        }
+    }
+
+    #[test]
+    fn reinterpretation() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/reinterpret.wasm").to_vec());
+        assert!(nodes[&NodeId(0)].get_operations().values().any(|op| op.to_string() == "reinterpret F32 as I32"));
+        assert!(typecheck::check_node(&nodes[&NodeId(0)]).is_empty() && typecheck::check_node(&nodes[&NodeId(1)]).is_empty());
+
+        // the float's bits are taken as they are
+        let variables = nodes[&NodeId(0)].get_local_input_variables();
+        let inputs:HashMap<usize, u64> = vec![(variables[&0], 1.5f32.to_bits() as u64)].into_iter().collect();
+        assert_eq!(interp::evaluate(&nodes[&NodeId(0)], &inputs).result(), Some(1.5f32.to_bits() as u64 + 1));
+        assert!(mapper.emit_ssa(NodeId(0)).unwrap().instructions().iter().any(|instruction| instruction.definition.name() == "Reinterpret"));
+
+        // once lowered, the reinterpreted value is the parameter's own variables rather than new ones
+        let constraint = nodes[&NodeId(1)].clone().lower();
+        let parameter = constraint.input_bits().values().next().unwrap().clone();
+        assert_eq!(parameter.len(), 64);
+        assert_eq!(constraint.get_result_bits(), parameter);
+    }
+
+    #[test]
+    fn shared_memory() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        mapper.map(include_bytes!("../tests/parallelization/atomics.wasm").to_vec());
+        assert!(mapper.has_shared_memory());
+
+        // only peek's plain load can race, since the other functions' accesses are atomic
+        let racing = mapper.racing_nodes();
+        assert_eq!(racing.keys().cloned().collect::<Vec<usize>>(), vec![4]);
+        assert_eq!(racing[&4].len(), 1);
+        let warnings:Vec<usize> = mapper.get_warnings().iter()
+            .filter(|warning| warning.category == WarningCategory::Race)
+            .map(|warning| warning.node)
+            .collect();
+        assert_eq!(warnings, vec![4]);
+
+        // the same accesses to memory that isn't shared are single threaded
+        mapper.map(include_bytes!("../tests/parallelization/memory.wasm").to_vec());
+        assert!(!mapper.has_shared_memory());
+        assert!(mapper.racing_nodes().is_empty());
+        assert!(mapper.get_warnings().iter().all(|warning| warning.category != WarningCategory::Race));
+    }
+
+    #[test]
+    fn mapper_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Mapper>();
+        assert_send_sync::<Node>();
+        assert_send_sync::<ssa::Graph>();
+    }
 }
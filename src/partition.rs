@@ -0,0 +1,250 @@
+//! # Partition
+//! Splitting QUBOs too large for a solver into subproblems. The variables of a QUBO and the pairs it couples
+//! form its interaction graph, which is bisected over and over, each bisection starting from a breadth first
+//! half and refined by swapping the pair of variables across the cut that lowers its weight the most, until
+//! every part has no more variables than the budget. Each part records its boundary, the variables coupled
+//! to other parts, and the outside variables coupled to it, and its subproblem is the QUBO conditioned on
+//! the values of everything outside it. Solving the subproblems in turn, each time keeping the values found,
+//! is the outer loop of iterative solvers like qbsolv, which also read the subproblems in its file format
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+use gadgets::Qubo;
+
+// how many times a bisection is refined before it is kept
+const REFINEMENT_PASSES:usize = 4;
+
+
+/// A partition is a part of a QUBO's variables along with the
+/// variables coupling it to the other parts
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Partition {
+    pub variables: BTreeSet<usize>, // the variables of the part
+    pub boundary: BTreeSet<usize>, // the variables of the part coupled to variables of other parts
+    pub neighbours: BTreeSet<usize> // the variables of other parts coupled to the part
+}
+
+
+impl Partition {
+    // returns the QUBO over the part's variables once every other variable takes its value in the given state,
+    // given as the set variables; its couplings to the part become coefficients of the part's variables
+    pub fn subproblem(&self, qubo:&Qubo, state:&BTreeSet<usize>) -> Qubo {
+        let outside:BTreeMap<usize, bool> = qubo.variables().into_iter()
+            .filter(|variable| !self.variables.contains(variable))
+            .map(|variable| (variable, state.contains(&variable)))
+            .collect();
+        let mut subproblem = qubo.clone();
+        subproblem.substitute(&outside);
+        subproblem
+    }
+}
+
+
+/// A partition plan splits a QUBO's variables into parts of at most a
+/// budget of variables each
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PartitionPlan {
+    pub budget: usize, // the most variables a part can have
+    pub parts: Vec<Partition>, // the parts, in the order they are solved
+    pub cut: BTreeMap<(usize, usize), i64> // the couplings between variables of different parts, by pair with the lower index first
+}
+
+
+impl PartitionPlan {
+    // returns the summed magnitude of the couplings between parts, which is what bisection keeps low
+    pub fn cut_weight(&self) -> i64 {
+        self.cut.values().map(|coefficient| coefficient.abs()).sum()
+    }
+
+    // solves each part's subproblem in turn with the given solver, which returns the set variables of its
+    // answer, keeping the values found for the parts that follow; returns the state the sweep ends with
+    pub fn sweep(&self, qubo:&Qubo, state:&BTreeSet<usize>, solve:&mut dyn FnMut(&Qubo) -> BTreeSet<usize>) -> BTreeSet<usize> {
+        let mut state = state.clone();
+        for part in self.parts.iter() {
+            let answer = solve(&part.subproblem(qubo, &state));
+            state.retain(|variable| !part.variables.contains(variable));
+            state.extend(answer.into_iter().filter(|variable| part.variables.contains(variable)));
+        }
+        state
+    }
+
+    // describes the plan as JSON, with the budget, the summed weight of the cut and each part's variables,
+    // boundary and neighbours
+    pub fn to_json(&self) -> String {
+        let list = |variables:&BTreeSet<usize>| variables.iter().map(|variable| variable.to_string()).collect::<Vec<String>>().join(",");
+        let parts:Vec<String> = self.parts.iter()
+            .map(|part| format!("{{\"variables\":[{}],\"boundary\":[{}],\"neighbours\":[{}]}}", list(&part.variables), list(&part.boundary), list(&part.neighbours)))
+            .collect();
+        format!("{{\"budget\":{},\"cut_weight\":{},\"parts\":[{}]}}", self.budget, self.cut_weight(), parts.join(","))
+    }
+}
+
+
+// splits a QUBO's variables into parts of at most the budget, which is a single part if the QUBO fits in it; a
+// budget of zero is taken to be one
+pub fn partition(qubo:&Qubo, budget:usize) -> PartitionPlan {
+    let budget = budget.max(1);
+    let mut adjacency:BTreeMap<usize, BTreeMap<usize, i64>> = qubo.variables().into_iter().map(|variable| (variable, BTreeMap::new())).collect();
+    for (&(u, v), coefficient) in qubo.quadratic() {
+        adjacency.get_mut(&u).unwrap().insert(v, coefficient.abs());
+        adjacency.get_mut(&v).unwrap().insert(u, coefficient.abs());
+    }
+
+    // parts too large are bisected until they fit, keeping the halves in order
+    let mut pending:Vec<Vec<usize>> = vec![adjacency.keys().cloned().collect()];
+    let mut parts:Vec<BTreeSet<usize>> = Vec::new();
+    while let Some(part) = pending.pop() {
+        if part.len() <= budget {
+            if !part.is_empty() {
+                parts.push(part.into_iter().collect());
+            }
+        } else {
+            let (first, second) = bisect(&part, &adjacency);
+            pending.push(second);
+            pending.push(first);
+        }
+    }
+
+    let owners:BTreeMap<usize, usize> = parts.iter().enumerate()
+        .flat_map(|(index, part)| part.iter().map(move |variable| (*variable, index)))
+        .collect();
+    let cut:BTreeMap<(usize, usize), i64> = qubo.quadratic().iter()
+        .filter(|&(&(u, v), _)| owners[&u] != owners[&v])
+        .map(|(pair, coefficient)| (*pair, *coefficient))
+        .collect();
+    let parts = parts.into_iter().map(|variables| {
+        let boundary = variables.iter().filter(|variable| adjacency[variable].keys().any(|other| !variables.contains(other))).cloned().collect();
+        let neighbours = variables.iter().flat_map(|variable| adjacency[variable].keys()).filter(|other| !variables.contains(other)).cloned().collect();
+        Partition { variables, boundary, neighbours }
+    }).collect();
+    PartitionPlan { budget, parts, cut }
+}
+
+
+// splits variables into two halves of the interaction graph with a light cut between them, the first half
+// growing breadth first from the lowest variable, moving on to the next one left whenever it runs out
+fn bisect(variables:&[usize], adjacency:&BTreeMap<usize, BTreeMap<usize, i64>>) -> (Vec<usize>, Vec<usize>) {
+    let members:BTreeSet<usize> = variables.iter().cloned().collect();
+    let mut first:BTreeSet<usize> = BTreeSet::new();
+    let mut queue:VecDeque<usize> = VecDeque::new();
+    let mut unvisited = members.iter();
+    while first.len() < variables.len() / 2 {
+        let variable = match queue.pop_front() {
+            Some(variable) => variable,
+            None => match unvisited.find(|variable| !first.contains(variable)) {
+                Some(variable) => *variable,
+                None => break
+            }
+        };
+        if first.insert(variable) {
+            queue.extend(adjacency[&variable].keys().filter(|other| members.contains(other) && !first.contains(other)));
+        }
+    }
+
+    // a variable's gain is how much lighter the cut gets by moving it to the other half, and swapping two
+    // variables gains both less twice their coupling, which stops counting as cut
+    for _ in 0..REFINEMENT_PASSES {
+        let gain = |variable:usize, first:&BTreeSet<usize>| -> i64 {
+            let side = first.contains(&variable);
+            adjacency[&variable].iter()
+                .filter(|&(other, _)| members.contains(other))
+                .map(|(other, weight)| if first.contains(other) == side { -weight } else { *weight })
+                .sum()
+        };
+        let mut locked:BTreeSet<usize> = BTreeSet::new();
+        let mut improved = false;
+        loop {
+            let best = |side:bool, first:&BTreeSet<usize>| members.iter()
+                .filter(|variable| first.contains(variable) == side && !locked.contains(variable))
+                .map(|variable| (gain(*variable, first), *variable))
+                .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+            let (a, b) = match (best(true, &first), best(false, &first)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => break
+            };
+            let coupling = adjacency[&a.1].get(&b.1).cloned().unwrap_or(0);
+            if a.0 + b.0 - 2 * coupling <= 0 {
+                break;
+            }
+            first.remove(&a.1);
+            first.insert(b.1);
+            locked.insert(a.1);
+            locked.insert(b.1);
+            improved = true;
+        }
+        if !improved {
+            break;
+        }
+    }
+    let second = variables.iter().filter(|variable| !first.contains(variable)).cloned().collect();
+    (first.into_iter().collect(), second)
+}
+
+
+// writes a QUBO in qbsolv's file format, with a line per variable's coefficient and then per coupling; the
+// constant term isn't part of the format, so it is given in a comment
+pub fn to_qbsolv(qubo:&Qubo) -> String {
+    let variables = qubo.variables();
+    let mut text = String::new();
+    writeln!(text, "c offset {}", qubo.get_offset()).unwrap();
+    writeln!(text, "p qubo 0 {} {} {}", variables.last().map_or(0, |variable| variable + 1), qubo.linear().len(), qubo.quadratic().len()).unwrap();
+    for (variable, coefficient) in qubo.linear() {
+        writeln!(text, "{} {} {}", variable, variable, coefficient).unwrap();
+    }
+    for (&(u, v), coefficient) in qubo.quadratic() {
+        writeln!(text, "{} {} {}", u, v, coefficient).unwrap();
+    }
+    text
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gadgets;
+    use parallelize::{new_mapper, SilentObserver};
+    use ids::NodeId;
+
+    #[test]
+    fn partition() {
+        // a chain of negations is cut between consecutive parts only
+        let mut chain = Qubo::new();
+        for variable in 0..12 {
+            chain += gadgets::not(variable, variable + 1);
+        }
+        let plan = super::partition(&chain, 4);
+        assert!(plan.parts.iter().all(|part| part.variables.len() <= 4));
+        assert_eq!(plan.parts.iter().map(|part| part.variables.len()).sum::<usize>(), 13);
+        assert_eq!(plan.cut.len(), plan.parts.len() - 1);
+        let first = &plan.parts[0];
+        assert_eq!((first.boundary.len(), first.neighbours.len()), (1, 1));
+        assert!(plan.to_json().starts_with(&format!("{{\"budget\":4,\"cut_weight\":{},\"parts\":[{{\"variables\":[0,", plan.cut_weight())));
+
+        // solving the parts in turn, each conditioned on the others, finds a ground state of the whole
+        let mut solve = |subproblem:&Qubo| subproblem.ground_states().1.remove(0);
+        let mut state = BTreeSet::new();
+        for _ in 0..3 {
+            state = plan.sweep(&chain, &state, &mut solve);
+        }
+        assert_eq!(chain.energy(&state), 0);
+
+        // a QUBO within the budget is a single part
+        assert_eq!(super::partition(&chain, 13).parts.len(), 1);
+        assert_eq!(to_qbsolv(&gadgets::not(0, 1)), "c offset 1\np qubo 0 2 2 1\n0 0 -1\n1 1 -1\n0 1 2\n");
+    }
+
+    #[test]
+    fn lowered() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        let qubo = nodes[&NodeId(1)].clone().lower().get_qubo();
+
+        // a 32 bit multiplication splits into parts within the budget, cutting fewer couplings than it keeps
+        let plan = super::partition(&qubo, 256);
+        let variables:BTreeSet<usize> = plan.parts.iter().flat_map(|part| part.variables.iter().cloned()).collect();
+        assert!(plan.parts.len() > 1 && plan.parts.iter().all(|part| part.variables.len() <= 256));
+        assert_eq!(variables.into_iter().collect::<Vec<usize>>(), qubo.variables());
+        assert!(plan.cut.len() * 2 < qubo.quadratic().len());
+    }
+}
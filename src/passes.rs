@@ -0,0 +1,633 @@
+//! # Passes
+//! Mapping a module is a chain of passes: the first pass reads the module into nodes, a few more prune,
+//! predicate, inline and simplify them, expansion builds the tree and the checks after it warn about what
+//! the tree can't do. A pass manager runs a list of passes in order over the mapper and the tree the passes
+//! so far left, so that passes can be left out, reordered or added. Mapping runs the standard list, and
+//! the passes estimating costs or counting simulatable operators can be added to it, as can passes of the
+//! user's own. Passes that only read the results share a snapshot of them, taken once a pass asks for it
+//! and kept until a pass changes the mapper or the tree. What each pass cost is recorded in the mapper's
+//! profile, so that users can see which pass dominates mapping their module
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+use parallelize::{LoweringPlan, Mapper, Node, SpeedupEstimate};
+use ids::NodeId;
+use profile;
+use profile::PassTiming;
+use snapshot::AnalysisSnapshot;
+use support::{OperatorHistogram, UnsupportedOperatorsError};
+
+
+/// A pass is a step of mapping, run by a pass manager under its
+/// name
+pub trait Pass {
+    // the name the pass is found under in the manager, e.g. "fold"
+    fn name(&self) -> &str;
+
+    // runs the pass over the mapper and the state the passes before it left
+    fn run(&mut self, mapper:&mut Mapper, state:&mut PassState) -> Result<(), PassError>;
+
+    // whether the pass can change the mapper or the tree, which retakes the snapshot for the passes after it
+    fn changes(&self) -> bool {
+        true
+    }
+}
+
+
+/// The pass error enum represents why a pass stopped the passes
+/// after it from running
+#[derive(Clone, Debug, PartialEq)]
+pub enum PassError {
+    Unsupported(UnsupportedOperatorsError), // a strict mapper met operators it can't record
+    Failed { pass: String, message: String } // a pass gave up, by name, with why
+}
+
+
+impl Error for PassError {}
+
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PassError::Unsupported(err) => write!(f, "{}", err),
+            PassError::Failed { pass, message } => write!(f, "pass {} failed: {}", pass, message)
+        }
+    }
+}
+
+
+/// The pass state holds what the passes read and leave for each
+/// other
+#[derive(Clone, Debug, Default)]
+pub struct PassState {
+    buf: Vec<u8>, // the module being mapped
+    tree: HashMap<NodeId, Node>, // the functions' nodes as the passes so far left them, by index
+    snapshot: Option<Arc<AnalysisSnapshot>>, // the results as of the last pass changing them, once a pass asked for them
+    plans: Vec<LoweringPlan>, // what lowering each registered node will cost, once estimated
+    speedup: Option<SpeedupEstimate>, // the work and critical path of the module, once estimated
+    histograms: BTreeMap<usize, OperatorHistogram> // the operator counts of the expanded functions, by index, once counted
+}
+
+
+impl PassState {
+    // creates the state for mapping the given module
+    pub fn new(buf:Vec<u8>) -> PassState {
+        PassState { buf, ..PassState::default() }
+    }
+
+    // returns the module being mapped
+    pub fn module(&self) -> &[u8] {
+        &self.buf
+    }
+
+    // returns the functions' nodes, by index
+    pub fn tree(&self) -> &HashMap<NodeId, Node> {
+        &self.tree
+    }
+
+    // returns the functions' nodes for a pass to change, by index
+    pub fn tree_mut(&mut self) -> &mut HashMap<NodeId, Node> {
+        &mut self.tree
+    }
+
+    // returns the functions' nodes, by index, once the passes are done
+    pub fn into_tree(self) -> HashMap<NodeId, Node> {
+        self.tree
+    }
+
+    // returns the snapshot of the mapper's results for the tree, taking it unless no pass changed them since
+    pub fn snapshot(&mut self, mapper:&Mapper) -> Arc<AnalysisSnapshot> {
+        let tree = &self.tree;
+        Arc::clone(self.snapshot.get_or_insert_with(|| mapper.finish(tree)))
+    }
+
+    // returns what lowering each registered node will cost, if estimated
+    pub fn plans(&self) -> &[LoweringPlan] {
+        &self.plans
+    }
+
+    // returns the work and critical path of the module, if estimated
+    pub fn speedup(&self) -> Option<SpeedupEstimate> {
+        self.speedup
+    }
+
+    // returns the operator counts of the expanded functions, by index, if counted
+    pub fn histograms(&self) -> &BTreeMap<usize, OperatorHistogram> {
+        &self.histograms
+    }
+}
+
+
+/// A pass manager runs its passes in the order they were added
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>> // the passes, in order
+}
+
+
+impl PassManager {
+    // creates a manager without any passes
+    pub fn new() -> PassManager {
+        PassManager::default()
+    }
+
+    // creates a manager with the passes mapping runs, in order
+    pub fn standard() -> PassManager {
+        let mut manager = PassManager::new();
+        manager.add(Box::new(MapModule));
+        manager.add(Box::new(WeighBranches));
+        manager.add(Box::new(Prune));
+        manager.add(Box::new(ModelMemory));
+        manager.add(Box::new(Predicate));
+        manager.add(Box::new(Inline));
+        manager.add(Box::new(FoldConstants));
+        manager.add(Box::new(ReduceDivisions));
+        manager.add(Box::new(ValidateRoundtrip));
+        manager.add(Box::new(BoundInputs));
+        manager.add(Box::new(ExpandCalls));
+        manager.add(Box::new(TypeCheck));
+        manager.add(Box::new(DetectRaces));
+        manager
+    }
+
+    // adds a pass after the others
+    pub fn add(&mut self, pass:Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    // adds a pass right before the pass with the given name, returning whether there is one
+    pub fn insert_before(&mut self, name:&str, pass:Box<dyn Pass>) -> bool {
+        match self.position(name) {
+            Some(position) => {
+                self.passes.insert(position, pass);
+                true
+            }
+            None => false
+        }
+    }
+
+    // adds a pass right after the pass with the given name, returning whether there is one
+    pub fn insert_after(&mut self, name:&str, pass:Box<dyn Pass>) -> bool {
+        match self.position(name) {
+            Some(position) => {
+                self.passes.insert(position + 1, pass);
+                true
+            }
+            None => false
+        }
+    }
+
+    // removes the pass with the given name, returning whether there was one
+    pub fn remove(&mut self, name:&str) -> bool {
+        match self.position(name) {
+            Some(position) => {
+                self.passes.remove(position);
+                true
+            }
+            None => false
+        }
+    }
+
+    // returns the names of the passes, in order
+    pub fn names(&self) -> Vec<&str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    // returns where the first pass with the given name is
+    fn position(&self, name:&str) -> Option<usize> {
+        self.passes.iter().position(|pass| pass.name() == name)
+    }
+
+    // runs the passes in order over the given module, stopping at the first that fails, and records what
+    // each pass that ran cost in the mapper's profile
+    pub fn run(&mut self, mapper:&mut Mapper, buf:Vec<u8>) -> Result<PassState, PassError> {
+        let mut state = PassState::new(buf);
+        for pass in self.passes.iter_mut() {
+            let nodes = mapper.live_nodes(&state.tree);
+            let (allocated, freed) = profile::allocations();
+            let started = Instant::now();
+            let result = pass.run(mapper, &mut state);
+            let time = started.elapsed();
+            let (now_allocated, now_freed) = profile::allocations();
+            mapper.record_pass(PassTiming {
+                name: pass.name().to_string(),
+                time,
+                boundary_nodes: nodes.max(mapper.live_nodes(&state.tree)),
+                allocated: now_allocated - allocated,
+                freed: now_freed - freed
+            });
+            result?;
+            if pass.changes() {
+                state.snapshot = None;
+            }
+        }
+        Ok(state)
+    }
+}
+
+
+/// The map module pass reads the module into a node per function,
+/// failing if a strict mapper met operators it can't record
+pub struct MapModule;
+
+
+impl Pass for MapModule {
+    fn name(&self) -> &str {
+        "map"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, state:&mut PassState) -> Result<(), PassError> {
+        state.tree = mapper.read_module(&state.buf);
+        mapper.check_strict().map_err(PassError::Unsupported)
+    }
+}
+
+
+/// The weigh branches pass weighs the conditions of every node with
+/// the mapper's branch profile, if it was given one
+pub struct WeighBranches;
+
+
+impl Pass for WeighBranches {
+    fn name(&self) -> &str {
+        "weights"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+        let weighed = mapper.weigh_branches();
+        if weighed > 0 {
+            mapper.progress(&format!("Weighed {} conditions with their profiled counts", weighed));
+        }
+        Ok(())
+    }
+}
+
+
+/// The prune pass removes the functions that are unreachable or left
+/// out by the function filters
+pub struct Prune;
+
+
+impl Pass for Prune {
+    fn name(&self) -> &str {
+        "prune"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, state:&mut PassState) -> Result<(), PassError> {
+        mapper.prune_unselected(&state.tree);
+        Ok(())
+    }
+}
+
+
+/// The model memory pass makes the memory's size a constant, unless
+/// reachable code grows it
+pub struct ModelMemory;
+
+
+impl Pass for ModelMemory {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+        mapper.check_memory_size();
+        Ok(())
+    }
+}
+
+
+/// The predicate pass turns the if/else diamonds of the functions
+/// selected for predication into arithmetic
+pub struct Predicate;
+
+
+impl Pass for Predicate {
+    fn name(&self) -> &str {
+        "predicate"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+        mapper.predicate_selected();
+        Ok(())
+    }
+}
+
+
+/// The inline pass inlines calls to functions under the mapper's
+/// inline threshold
+pub struct Inline;
+
+
+impl Pass for Inline {
+    fn name(&self) -> &str {
+        "inline"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+        mapper.inline_small_functions();
+        Ok(())
+    }
+}
+
+
+/// The fold constants pass folds arithmetic on known constants
+pub struct FoldConstants;
+
+
+impl Pass for FoldConstants {
+    fn name(&self) -> &str {
+        "fold"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+
+        // constant arithmetic is folded before it can multiply into free variables during expansion
+        let folded = mapper.fold_constants();
+        if folded > 0 {
+            mapper.progress(&format!("Folded {} constant operations", folded));
+        }
+        Ok(())
+    }
+}
+
+
+/// The reduce divisions pass rewrites divisions by constants into
+/// cheaper operations
+pub struct ReduceDivisions;
+
+
+impl Pass for ReduceDivisions {
+    fn name(&self) -> &str {
+        "reduce"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+
+        // divisions by the constants left are rewritten before they are lowered to general division gadgets
+        let reduced = mapper.reduce_division_strength();
+        if reduced > 0 {
+            mapper.progress(&format!("Reduced {} divisions by constants", reduced));
+        }
+        Ok(())
+    }
+}
+
+
+/// The eliminate common subexpressions pass shares the results of
+/// repeated operations in every registered node. Lowering plans do
+/// this themselves when the mapper is configured to, so the pass is
+/// only needed for the tree to share them
+pub struct EliminateCommonSubexpressions;
+
+
+impl Pass for EliminateCommonSubexpressions {
+    fn name(&self) -> &str {
+        "cse"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+        let shared = mapper.eliminate_common_subexpressions();
+        if shared > 0 {
+            mapper.progress(&format!("Shared the results of {} repeated operations", shared));
+        }
+        Ok(())
+    }
+}
+
+
+/// The validate roundtrip pass compares the recorded operations with
+/// the code they were mapped from, if the mapper is configured to
+pub struct ValidateRoundtrip;
+
+
+impl Pass for ValidateRoundtrip {
+    fn name(&self) -> &str {
+        "roundtrip"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+        mapper.check_roundtrip();
+        Ok(())
+    }
+}
+
+
+/// The bound inputs pass narrows the ranges of the values loaded
+/// from memory
+pub struct BoundInputs;
+
+
+impl Pass for BoundInputs {
+    fn name(&self) -> &str {
+        "bound"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+
+        // loads from memory only the data segments initialize are as small as the data, and the user's ranges
+        // override what is inferred
+        mapper.bound_inputs();
+        Ok(())
+    }
+}
+
+
+/// The expand calls pass expands the functions' nodes into the tree,
+/// splitting out their blocks and unrolling their calls
+pub struct ExpandCalls;
+
+
+impl Pass for ExpandCalls {
+    fn name(&self) -> &str {
+        "expand"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, state:&mut PassState) -> Result<(), PassError> {
+        let tree = std::mem::take(&mut state.tree);
+        state.tree = mapper.expand(tree);
+        Ok(())
+    }
+}
+
+
+/// The type check pass warns about ill-typed operations
+pub struct TypeCheck;
+
+
+impl Pass for TypeCheck {
+    fn name(&self) -> &str {
+        "typecheck"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+        mapper.check_types();
+        Ok(())
+    }
+}
+
+
+/// The detect races pass warns about non-atomic accesses to shared
+/// memory
+pub struct DetectRaces;
+
+
+impl Pass for DetectRaces {
+    fn name(&self) -> &str {
+        "races"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, _state:&mut PassState) -> Result<(), PassError> {
+        mapper.check_races();
+        Ok(())
+    }
+}
+
+
+/// The simulability pass counts the operators of each expanded
+/// function by category, from the snapshot, to show how much of it
+/// is simulated
+pub struct Simulability;
+
+
+impl Pass for Simulability {
+    fn name(&self) -> &str {
+        "simulability"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, state:&mut PassState) -> Result<(), PassError> {
+        let snapshot = state.snapshot(mapper);
+        state.histograms = snapshot.nodes().iter().map(|(index, node)| (index.0, node.operator_histogram())).collect();
+        Ok(())
+    }
+
+    fn changes(&self) -> bool {
+        false
+    }
+}
+
+
+/// The estimate cost pass plans the lowering of each registered node
+/// and estimates the module's speedup
+pub struct EstimateCost;
+
+
+impl Pass for EstimateCost {
+    fn name(&self) -> &str {
+        "cost"
+    }
+
+    fn run(&mut self, mapper:&mut Mapper, state:&mut PassState) -> Result<(), PassError> {
+        state.plans = mapper.plan_lowering();
+        state.speedup = Some(mapper.speedup_estimate());
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallelize::{new_mapper, MapperObserver, SilentObserver};
+
+    // remembers how many functions the snapshot it was handed had, failing if it had none
+    struct Count {
+        functions: Arc<std::sync::Mutex<Option<usize>>>
+    }
+
+    impl Pass for Count {
+        fn name(&self) -> &str {
+            "count"
+        }
+
+        fn run(&mut self, mapper:&mut Mapper, state:&mut PassState) -> Result<(), PassError> {
+            let functions = state.snapshot(mapper).nodes().len();
+            *self.functions.lock().unwrap() = Some(functions);
+            if functions == 0 {
+                return Err(PassError::Failed { pass: "count".to_string(), message: "no functions".to_string() });
+            }
+            Ok(())
+        }
+
+        fn changes(&self) -> bool {
+            false
+        }
+    }
+
+    // records the progress the passes report
+    struct Progress {
+        messages: Arc<std::sync::Mutex<Vec<String>>>
+    }
+
+    impl MapperObserver for Progress {
+        fn on_progress(&mut self, message:&str) {
+            self.messages.lock().unwrap().push(message.to_string());
+        }
+    }
+
+    fn describe(tree:&HashMap<NodeId, Node>) -> Vec<(usize, String)> {
+        let mut described:Vec<(usize, String)> = tree.iter().map(|(index, node)| (index.0, node.describe(4))).collect();
+        described.sort();
+        described
+    }
+
+    #[test]
+    fn standard() {
+        let buf = include_bytes!("../tests/parallelization/math.wasm").to_vec();
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let mapped = mapper.map(buf.clone());
+
+        // the standard passes are what mapping runs
+        let mut manager = PassManager::standard();
+        assert_eq!(manager.names(), vec!["map", "weights", "prune", "memory", "predicate", "inline", "fold", "reduce", "roundtrip", "bound", "expand", "typecheck", "races"]);
+        let state = manager.run(&mut mapper, buf.clone()).unwrap();
+        assert_eq!(describe(state.tree()), describe(&mapped));
+
+        // analyses are added where they are wanted, and can share a snapshot
+        assert!(manager.insert_after("races", Box::new(Simulability)));
+        assert!(manager.insert_after("simulability", Box::new(EstimateCost)));
+        assert!(!manager.insert_before("unroll", Box::new(EstimateCost)));
+        let mut state = manager.run(&mut mapper, buf).unwrap();
+        assert_eq!(state.histograms().keys().cloned().collect::<Vec<usize>>(), describe(&mapped).into_iter().map(|(index, _)| index).collect::<Vec<usize>>());
+        assert!(state.speedup().unwrap().work > 0 && !state.plans().is_empty());
+        assert_eq!(state.snapshot(&mapper).nodes().len(), mapped.len());
+    }
+
+    #[test]
+    fn progress() {
+        let messages = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(Progress { messages: Arc::clone(&messages) }));
+        mapper.map(include_bytes!("../tests/parallelization/math.wasm").to_vec());
+
+        // the passes report what they did to the observer rather than printing it
+        let messages = messages.lock().unwrap();
+        assert!(messages[0].starts_with("First pass found 2 functions:\n"));
+        assert!(messages.contains(&"Registering call to function 1 from function 0".to_string()));
+    }
+
+    #[test]
+    fn custom() {
+        let buf = include_bytes!("../tests/parallelization/add.wasm").to_vec();
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+
+        // a pass of the user's own sees the results of the passes before it
+        let functions = Arc::new(std::sync::Mutex::new(None));
+        let mut manager = PassManager::standard();
+        manager.add(Box::new(Count { functions: Arc::clone(&functions) }));
+        assert!(manager.run(&mut mapper, buf.clone()).is_ok());
+        assert_eq!(*functions.lock().unwrap(), Some(1));
+
+        // and stops the passes after it by failing
+        assert!(manager.remove("expand") && manager.remove("map"));
+        manager.insert_before("count", Box::new(EstimateCost));
+        let err = manager.run(&mut mapper, buf).err().unwrap();
+        assert_eq!(err.to_string(), "pass count failed: no functions");
+        assert_eq!(manager.names(), vec!["weights", "prune", "memory", "predicate", "inline", "fold", "reduce", "roundtrip", "bound", "typecheck", "races", "cost", "count"]);
+    }
+}
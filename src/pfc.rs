@@ -0,0 +1,67 @@
+//! # PFC
+//! The mapper's API in one place. The mapper, the nodes it expands, what they are lowered to and the reports
+//! and errors that come out of a mapping live in modules of their own, next to the parser they are built on,
+//! so using them takes imports from all over the crate. The prelude re-exports what a mapping session needs,
+//! so that downstream code only has to `use wasmparser::pfc::prelude::*;`, whichever module the types move to
+//!
+//! The prelude also marks out what the crate supports. Its types, the mapper's methods, which configure and
+//! run the pipeline and report on it, and the methods of nodes that read what a mapping recorded or set how a
+//! node is lowered only change in breaking releases. Nodes are built up by the mapper as it reads a module,
+//! so the methods doing that, along with the mapper's own bookkeeping, are kept to the crate and may change
+//! in any release. Nodes hand out what they recorded by reference, and callers copy what they need to keep
+
+
+/// The prelude holds the mapper and the types of what goes in and comes
+/// out of it
+pub mod prelude {
+    pub use parallelize::{new_mapper, Mapper, Node, PartialResult};
+    pub use parallelize::{Visit, Walk, WalkOrder};
+    pub use parallelize::{ConsoleObserver, Decision, MapperObserver, SilentObserver};
+    pub use parallelize::{AbstractExpression, Binarization, Constraint, LoweringConfig, NodeOutput};
+    pub use gadgets::{Circuit, Qubo};
+    pub use ids::{BlockId, FuncId, NodeId};
+    pub use points::ProgramPoint;
+    pub use branches::{BranchCounts, BranchProfile};
+    pub use harness::PathCall;
+    pub use coverage::CoverageReport;
+    pub use hints::Hints;
+    pub use checkpoint::Checkpoint;
+    pub use snapshot::AnalysisSnapshot;
+    pub use visit::{Flow, OperatorVisitor};
+    pub use passes::{Pass, PassError, PassManager, PassState};
+
+    // reports
+    pub use parallelize::{Annealability, SpeedupEstimate};
+    pub use callgraph::{CallGraph, PathEnumeration, PathLimits};
+    pub use hazards::{AccessKind, HazardReport, MemAccess};
+    pub use profile::Profiler;
+    pub use support::OperatorHistogram;
+    pub use validation::ValidationReport;
+
+    // errors and warnings
+    pub use parallelize::{InvariantViolation, MapperWarning, WarningCategory};
+    pub use structure::StructureDiagnostic;
+    pub use support::UnsupportedOperator;
+    pub use typecheck::TypeError;
+    pub use primitives::{BinaryReaderError, Type};
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::prelude::*;
+
+    #[test]
+    fn prelude() {
+
+        // a session needs nothing but the prelude
+        let mut mapper:Mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let tree = mapper.map(include_bytes!("../tests/parallelization/add.wasm").to_vec());
+        let snapshot = mapper.finish(&tree);
+        let warnings:&[MapperWarning] = snapshot.warnings();
+        let report:HazardReport = mapper.hazard_report();
+        let error:Option<BinaryReaderError> = mapper.get_error();
+        assert_eq!((snapshot.nodes().len(), warnings.len(), report.hazards.len(), error.is_none()), (1, 0, 0, true));
+    }
+}
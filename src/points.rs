@@ -0,0 +1,108 @@
+//! # Points
+//! Program points name instructions independently of where they are recorded in a node. Nodes record
+//! everything by location, counting the operators read for them, and the passes that split, move or remove
+//! recorded code relocate every map keyed by location in step; a program point instead holds the index of
+//! the function the instruction was read from and its offset in the file, so it keeps naming the same
+//! instruction through every pass. That is how the branch sites and global accesses a node records are
+//! named to profiles, traces and other modules
+
+use std::fmt;
+use std::mem;
+
+
+/// A program point is where an instruction was read: the function it
+/// belongs to and its offset in the WASM file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProgramPoint {
+    pub function: usize, // the index of the function the instruction was read from
+    pub offset: usize // where the instruction starts in the WASM file
+}
+
+
+impl ProgramPoint {
+    pub fn new(function:usize, offset:usize) -> ProgramPoint {
+        ProgramPoint { function, offset }
+    }
+}
+
+
+impl fmt::Display for ProgramPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "function {} at {:#x}", self.function, self.offset)
+    }
+}
+
+
+/// An offset map tells where offsets in the WASM file fall in a node's
+/// instructions once ranges of them were split out of the node, the way
+/// nested blocks are when they become nodes of their own
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OffsetMap {
+    removed: Vec<(usize, usize)> // the ranges split out, each in the offsets left by those before it, in order
+}
+
+
+impl OffsetMap {
+    // returns the map of splitting out the given range
+    pub fn removing(start:usize, end:usize) -> OffsetMap {
+        OffsetMap { removed: if start < end { vec![(start, end)] } else { Vec::new() } }
+    }
+
+    // appends the ranges split out by another map, which are in the offsets this one leaves
+    pub fn extend(&mut self, other:&OffsetMap) {
+        self.removed.extend(other.removed.iter().cloned());
+    }
+
+    // returns where an offset goes once the ranges are split out, unless it was split out with one of them;
+    // the end of a range that was split out goes to where the range started
+    pub fn map(&self, offset:usize) -> Option<usize> {
+        self.removed.iter().try_fold(offset, |offset, &(start, end)| match offset {
+            offset if offset < start => Some(offset),
+            offset if offset >= end => Some(offset - (end - start)),
+            _ => None
+        })
+    }
+
+    // returns how many bytes were split out
+    pub fn removed(&self) -> usize {
+        self.removed.iter().map(|(start, end)| end - start).sum()
+    }
+}
+
+
+// moves the entries of a map keyed by location, ordered or not, to where the relocation takes them, dropping
+// the entries it takes nowhere
+pub fn relocate_keys<M, T>(map:&mut M, relocation:&dyn Fn(usize) -> Option<usize>)
+    where M: Default + IntoIterator<Item = (usize, T)> + Extend<(usize, T)> {
+    let entries = mem::take(map);
+    map.extend(entries.into_iter().filter_map(|(location, value)| relocation(location).map(|location| (location, value))));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn relocation() {
+        let mut map:HashMap<usize, &str> = vec![(1, "a"), (2, "b"), (3, "c")].into_iter().collect();
+        relocate_keys(&mut map, &|location| if location == 2 { None } else { Some(location * 10) });
+        let mut entries:Vec<(usize, &str)> = map.clone().into_iter().collect();
+        entries.sort();
+        assert_eq!(entries, vec![(10, "a"), (30, "c")]);
+
+        assert_eq!(ProgramPoint::new(0, 0x2f).to_string(), "function 0 at 0x2f");
+    }
+
+    #[test]
+    fn offsets() {
+
+        // splitting out 0x10 to 0x14 and then, in the offsets that leaves, 0x18 to 0x20 takes out 0x1c to 0x24
+        let mut map = OffsetMap::removing(0x10, 0x14);
+        map.extend(&OffsetMap::removing(0x18, 0x20));
+        let mapped:Vec<Option<usize>> = [0x0f, 0x10, 0x14, 0x1b, 0x1c, 0x23, 0x24, 0x30].iter().map(|offset| map.map(*offset)).collect();
+        assert_eq!(mapped, vec![Some(0x0f), None, Some(0x10), Some(0x17), None, None, Some(0x18), Some(0x24)]);
+        assert_eq!((map.removed(), OffsetMap::removing(3, 3), OffsetMap::default().map(7)), (12, OffsetMap::default(), Some(7)));
+    }
+}
@@ -0,0 +1,200 @@
+//! # Precision
+//! Coefficient statistics of lowered QUBOs, since annealers only set their fields and couplings to a limited
+//! precision. Once a problem is scaled to the hardware's ranges, the largest coefficient sets the scale, and
+//! any term much smaller than it is lost among the noise of the analog controls, which changes the ground
+//! states the gadgets were built to have. The analysis reports the range of the linear and quadratic
+//! coefficients, and flags the terms whose magnitude falls further below the largest than the configured
+//! dynamic range allows. Two rescalings are offered: dividing a QUBO by the greatest common divisor of its
+//! coefficients, which is exact, and scaling the Ising form into the hardware's field and coupling ranges
+
+use std::fmt;
+use gadgets::Qubo;
+use qaoa::Ising;
+
+
+/// A precision config describes the coefficients an annealer can set
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrecisionConfig {
+    pub dynamic_range: f64, // how many times larger than the smallest term the largest can be before the smallest is lost
+    pub field_range: f64, // the largest magnitude of a field of the Ising form
+    pub coupling_range: f64 // the largest magnitude of a coupling of the Ising form
+}
+
+
+// an Advantage system's field and coupling ranges, with the hundredfold range its precision affords
+impl Default for PrecisionConfig {
+    fn default() -> PrecisionConfig {
+        PrecisionConfig { dynamic_range: 100.0, field_range: 4.0, coupling_range: 1.0 }
+    }
+}
+
+
+/// A term is a coefficient of a QUBO, on a single variable or a pair
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Term {
+    Linear(usize),
+    Quadratic(usize, usize)
+}
+
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Term::Linear(variable) => write!(f, "x{}", variable),
+            Term::Quadratic(u, v) => write!(f, "x{} * x{}", u, v)
+        }
+    }
+}
+
+
+/// Coefficient statistics summarize a kind of term's coefficients,
+/// which are all zero if there are none
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CoefficientStats {
+    pub count: usize, // how many terms there are
+    pub min: i64, // the lowest coefficient
+    pub max: i64, // the highest coefficient
+    pub smallest: u64, // the smallest magnitude of a coefficient
+    pub largest: u64 // the largest magnitude of a coefficient
+}
+
+
+impl CoefficientStats {
+    // summarizes the given coefficients, none of which is zero
+    fn of(coefficients:&[i64]) -> CoefficientStats {
+        CoefficientStats {
+            count: coefficients.len(),
+            min: coefficients.iter().cloned().min().unwrap_or(0),
+            max: coefficients.iter().cloned().max().unwrap_or(0),
+            smallest: coefficients.iter().map(|coefficient| coefficient.unsigned_abs()).min().unwrap_or(0),
+            largest: coefficients.iter().map(|coefficient| coefficient.unsigned_abs()).max().unwrap_or(0)
+        }
+    }
+
+    // returns how many times larger the largest magnitude is than the smallest, which is one without terms
+    pub fn ratio(&self) -> f64 {
+        if self.smallest == 0 { 1.0 } else { self.largest as f64 / self.smallest as f64 }
+    }
+}
+
+
+/// A precision report holds the coefficient statistics of a QUBO and
+/// the terms too small for the configured dynamic range
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrecisionReport {
+    pub linear: CoefficientStats,
+    pub quadratic: CoefficientStats,
+    pub dynamic_range: f64, // how many times larger the largest magnitude of any term is than the smallest
+    pub flagged: Vec<(Term, i64)> // the terms lost once the largest sets the scale, with their coefficients, in order
+}
+
+
+// summarizes the report on a line, e.g. `linear [-8, 3], quadratic [-2, 4], dynamic range 8, 2 terms flagged`
+impl fmt::Display for PrecisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "linear [{}, {}], quadratic [{}, {}], dynamic range {}, {} terms flagged",
+            self.linear.min, self.linear.max, self.quadratic.min, self.quadratic.max, self.dynamic_range, self.flagged.len())
+    }
+}
+
+
+// reports the coefficient statistics of a QUBO, flagging the terms whose magnitude times the configured dynamic
+// range is still below the largest magnitude of any term
+pub fn analyze(qubo:&Qubo, config:&PrecisionConfig) -> PrecisionReport {
+    let linear:Vec<i64> = qubo.linear().values().cloned().collect();
+    let quadratic:Vec<i64> = qubo.quadratic().values().cloned().collect();
+    let all = CoefficientStats::of(&[&linear[..], &quadratic[..]].concat());
+    let lost = |coefficient:&i64| (coefficient.unsigned_abs() as f64) * config.dynamic_range < all.largest as f64;
+    let mut flagged:Vec<(Term, i64)> = qubo.linear().iter()
+        .filter(|&(_, coefficient)| lost(coefficient))
+        .map(|(variable, coefficient)| (Term::Linear(*variable), *coefficient))
+        .collect();
+    flagged.extend(qubo.quadratic().iter()
+        .filter(|&(_, coefficient)| lost(coefficient))
+        .map(|(&(u, v), coefficient)| (Term::Quadratic(u, v), *coefficient)));
+    PrecisionReport {
+        linear: CoefficientStats::of(&linear),
+        quadratic: CoefficientStats::of(&quadratic),
+        dynamic_range: all.ratio(),
+        flagged
+    }
+}
+
+
+// divides every coefficient of a QUBO by their greatest common divisor, which keeps its ground states; returns
+// the reduced QUBO with the divisor, which is one if nothing divides them all
+pub fn reduce(qubo:&Qubo) -> (Qubo, i64) {
+    let divisor = qubo.linear().values()
+        .chain(qubo.quadratic().values())
+        .chain(Some(qubo.get_offset()).iter())
+        .fold(0, |divisor, coefficient| gcd(divisor, coefficient.abs()))
+        .max(1);
+    let mut reduced = Qubo::new();
+    reduced.add_offset(qubo.get_offset() / divisor);
+    for (variable, coefficient) in qubo.linear() {
+        reduced.add_linear(*variable, coefficient / divisor);
+    }
+    for (&(u, v), coefficient) in qubo.quadratic() {
+        reduced.add_quadratic(u, v, coefficient / divisor);
+    }
+    (reduced, divisor)
+}
+
+
+// scales an Ising form down into the configured field and coupling ranges, or up to fill them, which keeps its
+// ground states; returns the scaled form with the factor it was scaled by
+pub fn rescale(ising:&Ising, config:&PrecisionConfig) -> (Ising, f64) {
+    let largest = |values:&mut dyn Iterator<Item = &f64>| values.fold(0.0f64, |largest, value| largest.max(value.abs()));
+    let fields = largest(&mut ising.fields.values());
+    let couplings = largest(&mut ising.couplings.values());
+    let factor = match (fields > 0.0, couplings > 0.0) {
+        (true, true) => (config.field_range / fields).min(config.coupling_range / couplings),
+        (true, false) => config.field_range / fields,
+        (false, true) => config.coupling_range / couplings,
+        (false, false) => 1.0
+    };
+    let scaled = Ising {
+        offset: ising.offset * factor,
+        fields: ising.fields.iter().map(|(variable, field)| (*variable, field * factor)).collect(),
+        couplings: ising.couplings.iter().map(|(pair, coupling)| (*pair, coupling * factor)).collect()
+    };
+    (scaled, factor)
+}
+
+
+// returns the greatest common divisor of two magnitudes, which is the other for zero
+fn gcd(a:i64, b:i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gadgets;
+
+    #[test]
+    fn analyze() {
+        // a weighted full adder has coefficients from 1 to 4 times the weight in magnitude
+        let mut qubo = gadgets::full_adder(0, 1, 2, 3, 4) * 3;
+        qubo.add_linear(9, 1);
+        let report = super::analyze(&qubo, &PrecisionConfig { dynamic_range: 10.0, ..PrecisionConfig::default() });
+        assert_eq!((report.linear.count, report.linear.largest, report.quadratic.max), (6, 12, 12));
+        assert_eq!((report.dynamic_range, report.flagged.clone()), (12.0, vec![(Term::Linear(9), 1)]));
+        assert_eq!(report.to_string(), "linear [1, 12], quadratic [-12, 12], dynamic range 12, 1 terms flagged");
+        assert!(super::analyze(&qubo, &PrecisionConfig::default()).flagged.is_empty());
+        assert_eq!(CoefficientStats::default().ratio(), 1.0);
+
+        // reducing by the common divisor keeps the ground states
+        let (reduced, divisor) = reduce(&(gadgets::full_adder(0, 1, 2, 3, 4) * 3));
+        assert_eq!((reduced, divisor), (gadgets::full_adder(0, 1, 2, 3, 4), 3));
+    }
+
+    #[test]
+    fn rescale() {
+        let ising = Ising::from_qubo(&(gadgets::not(0, 1) * 4 + gadgets::fix(2, true) * 64));
+        let (scaled, factor) = super::rescale(&ising, &PrecisionConfig::default());
+        assert_eq!((factor, scaled.fields[&2], scaled.couplings[&(0, 1)]), (0.125, 4.0, 0.25));
+        assert_eq!(scaled.offset, ising.offset * 0.125);
+    }
+}
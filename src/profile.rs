@@ -0,0 +1,225 @@
+//! # Profile
+//! Timing of the mapping pipeline, so that performance work on large modules can target the nodes that
+//! are slow to process. The profiler records the wall time spent mapping each function in the first pass,
+//! expanding each node and lowering each node, along with how many operators the parser read. Times are
+//! inclusive of nested blocks for the first pass and lowering, which process them within their parents,
+//! while expansion processes every node on its own, so nodes expanded more than once add up their times
+//!
+//! The pass manager adds what each of its passes cost: the wall time, the nodes alive at its boundaries
+//! and the bytes allocated and freed while it ran. Nodes are only counted before and after a pass, not
+//! sampled during it, so a pass that builds and drops nodes doesn't show them. Allocations are only
+//! counted once the binary mapping modules installs the counting allocator as its global allocator, and
+//! are otherwise reported as zero. The counters are process-wide, so they are approximate: allocations
+//! made by other threads while a pass runs are counted against it as well
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+
+// the bytes allocated and freed through the counting allocator since the program started
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static FREED: AtomicUsize = AtomicUsize::new(0);
+
+
+/// The counting allocator hands allocations on to the system's,
+/// counting the bytes allocated and freed as it goes. Installing it
+/// with `#[global_allocator]` lets the profile report each pass's
+/// allocations, counted across the whole process
+pub struct CountingAllocator;
+
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout:Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr:*mut u8, layout:Layout) {
+        FREED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+
+// returns the bytes allocated and freed through the counting allocator so far, by every thread of the process
+pub fn allocations() -> (usize, usize) {
+    (ALLOCATED.load(Ordering::Relaxed), FREED.load(Ordering::Relaxed))
+}
+
+
+/// Node timings are the times spent on a single node in each stage
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NodeTiming {
+    pub mapping: Duration, // spent reading the node's operators in the first pass
+    pub expanding: Duration, // spent expanding the node
+    pub lowering: Duration, // spent lowering the node
+    pub reads: usize // operators read by the parser in the first pass
+}
+
+
+/// Pass timings are what a single pass of the pass manager cost
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PassTiming {
+    pub name: String, // the name of the pass
+    pub time: Duration, // spent running the pass
+    pub boundary_nodes: usize, // the more nodes alive before or after the pass, in the mapper and the tree
+    pub allocated: usize, // bytes allocated in the process while the pass ran, if the counting allocator is installed
+    pub freed: usize // bytes freed in the process while the pass ran, if the counting allocator is installed
+}
+
+
+impl PassTiming {
+    // returns how many more bytes were allocated than freed by the pass, which is negative if it freed more
+    pub fn retained(&self) -> isize {
+        self.allocated as isize - self.freed as isize
+    }
+}
+
+
+/// The profiler records the time spent on each node by the mapper
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profiler {
+    nodes: HashMap<usize, NodeTiming>, // the timings recorded so far, by node id
+    passes: Vec<PassTiming>, // the timings of the passes run so far, in order
+    reads: usize // the operators read by the parser across every node
+}
+
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    // forgets everything recorded so far
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.passes.clear();
+        self.reads = 0;
+    }
+
+    // counts an operator read by the parser
+    pub fn count_read(&mut self) {
+        self.reads += 1;
+    }
+
+    // returns how many operators the parser has read across every node
+    pub fn reads(&self) -> usize {
+        self.reads
+    }
+
+    // records the time spent mapping a node in the first pass and the operators read for it
+    pub fn record_mapping(&mut self, node:usize, time:Duration, reads:usize) {
+        let timing = self.nodes.entry(node).or_default();
+        timing.mapping += time;
+        timing.reads += reads;
+    }
+
+    // records the time spent expanding a node
+    pub fn record_expansion(&mut self, node:usize, time:Duration) {
+        self.nodes.entry(node).or_default().expanding += time;
+    }
+
+    // records the time spent lowering a node
+    pub fn record_lowering(&mut self, node:usize, time:Duration) {
+        self.nodes.entry(node).or_default().lowering += time;
+    }
+
+    // records what a pass cost
+    pub fn record_pass(&mut self, timing:PassTiming) {
+        self.passes.push(timing);
+    }
+
+    // returns the timings of the passes run so far, in order
+    pub fn passes(&self) -> &[PassTiming] {
+        &self.passes
+    }
+
+    // returns the pass that took the longest, if any ran
+    pub fn slowest_pass(&self) -> Option<&PassTiming> {
+        self.passes.iter().rev().max_by_key(|timing| timing.time)
+    }
+
+    // returns the timings of a node, which are all zero if nothing was recorded for it
+    pub fn get(&self, node:usize) -> NodeTiming {
+        self.nodes.get(&node).cloned().unwrap_or_default()
+    }
+
+    // returns the ids of the nodes with timings, in order
+    pub fn nodes(&self) -> Vec<usize> {
+        let mut nodes:Vec<usize> = self.nodes.keys().cloned().collect();
+        nodes.sort();
+        nodes
+    }
+
+    // returns the nodes that took the longest overall, slowest first
+    pub fn slowest(&self, count:usize) -> Vec<(usize, Duration)> {
+        let mut totals:Vec<(usize, Duration)> = self.nodes.iter()
+            .map(|(node, timing)| (*node, timing.mapping + timing.expanding + timing.lowering))
+            .collect();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        totals.truncate(count);
+        totals
+    }
+
+    // describes the profile as a JSON object with the total reads, each node's times in microseconds and
+    // what each pass cost
+    pub fn to_json(&self) -> String {
+        let nodes:Vec<String> = self.nodes().iter().map(|node| {
+            let timing = self.get(*node);
+            format!("{{\"id\":{},\"mapping_us\":{},\"expanding_us\":{},\"lowering_us\":{},\"reads\":{}}}",
+                node, timing.mapping.as_micros(), timing.expanding.as_micros(), timing.lowering.as_micros(), timing.reads)
+        }).collect();
+        let passes:Vec<String> = self.passes.iter().map(|timing| {
+            format!("{{\"name\":\"{}\",\"time_us\":{},\"boundary_nodes\":{},\"allocated\":{},\"freed\":{}}}",
+                timing.name, timing.time.as_micros(), timing.boundary_nodes, timing.allocated, timing.freed)
+        }).collect();
+        format!("{{\"reads\":{},\"nodes\":[{}],\"passes\":[{}]}}", self.reads, nodes.join(","), passes.join(","))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallelize::{new_mapper, SilentObserver};
+    use ids::NodeId;
+
+    #[test]
+    fn profiler() {
+        let mut profiler = Profiler::new();
+        profiler.record_mapping(1, Duration::from_micros(5), 3);
+        profiler.record_expansion(1, Duration::from_micros(2));
+        profiler.record_expansion(1, Duration::from_micros(2));
+        profiler.record_lowering(0, Duration::from_micros(10));
+        profiler.count_read();
+        profiler.record_pass(PassTiming { name: "map".to_string(), time: Duration::from_micros(7), boundary_nodes: 2, allocated: 64, freed: 96 });
+        assert_eq!(profiler.get(1).expanding, Duration::from_micros(4));
+        assert_eq!(profiler.slowest(1), vec![(0, Duration::from_micros(10))]);
+        assert_eq!(profiler.slowest_pass().unwrap().retained(), -32);
+        assert_eq!(profiler.to_json(), "{\"reads\":1,\"nodes\":[{\"id\":0,\"mapping_us\":0,\"expanding_us\":0,\"lowering_us\":10,\"reads\":0},{\"id\":1,\"mapping_us\":5,\"expanding_us\":4,\"lowering_us\":0,\"reads\":3}],\"passes\":[{\"name\":\"map\",\"time_us\":7,\"boundary_nodes\":2,\"allocated\":64,\"freed\":96}]}");
+    }
+
+    #[test]
+    fn mapper_profile() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let mut nodes = mapper.map(include_bytes!("../tests/parallelization/inline.wasm").to_vec());
+        mapper.lower(nodes.get_mut(&NodeId(2)).unwrap());
+
+        // every function was read and expanded, and the reads of the functions add up to the parser's
+        let profile = mapper.profile();
+        assert_eq!(profile.nodes(), vec![0, 1, 2]);
+        assert_eq!(profile.get(2).reads, 7);
+        assert_eq!((0..3).map(|node| profile.get(node).reads).sum::<usize>(), profile.reads());
+        assert!(profile.get(2).lowering > Duration::from_secs(0));
+        assert_eq!(profile.get(0).lowering, Duration::from_secs(0));
+
+        // every standard pass is timed, with the nodes alive once the module is expanded
+        let passes:Vec<&str> = profile.passes().iter().map(|timing| timing.name.as_str()).collect();
+        assert_eq!(passes, vec!["map", "weights", "prune", "memory", "predicate", "inline", "fold", "reduce", "roundtrip", "bound", "expand", "typecheck", "races"]);
+        let expand = &profile.passes()[10];
+        assert!(expand.boundary_nodes >= nodes.values().map(|node| node.children().len() + 1).sum::<usize>());
+    }
+}
@@ -0,0 +1,508 @@
+//! # Provenance
+//! Correlation of the mapper's nodes with the source code a module was compiled from, so that what to
+//! parallelize can be decided in terms of source files and lines rather than byte offsets. The correlation
+//! comes from a line table, which is read from the DWARF `.debug_line` custom section when the module has
+//! one, or from a source map, which modules name in their `sourceMappingURL` custom section but whose
+//! contents have to be fetched separately. DWARF addresses are offsets into the code section's contents,
+//! while source maps give offsets into the module as generated columns of their only line
+
+use std::collections::HashMap;
+use primitives::SectionCode;
+use readers::ModuleReader;
+
+
+/// A source location is a position in the original source code
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String, // the path of the source file
+    pub line: usize, // the line, counting from 1
+    pub column: usize // the column, counting from 1, or 0 if unknown
+}
+
+
+/// A source range covers the lines of a source file some code was
+/// compiled from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceRange {
+    pub file: String, // the path of the source file
+    pub first_line: usize, // the first line of the range
+    pub last_line: usize // the last line of the range, inclusive
+}
+
+
+/// A line table maps offsets in a module to the source locations the
+/// code at those offsets was compiled from
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineTable {
+    rows: Vec<(usize, Option<SourceLocation>)> // module offsets mapped to the code's location from there on, if any, in order
+}
+
+
+impl LineTable {
+    // reads the line table from a module's DWARF line programs, if it has any
+    pub fn from_module(buf:&[u8]) -> Option<LineTable> {
+        let mut sections:HashMap<&str, &[u8]> = HashMap::new();
+        let mut code_start = None;
+        let mut reader = ModuleReader::new(buf).ok()?;
+        while !reader.eof() {
+            let section = reader.read().ok()?;
+            let range = section.range();
+            match section.code {
+                SectionCode::Custom { name, .. } => { sections.insert(name, &buf[range.start..range.end]); }
+                SectionCode::Code => code_start = Some(range.start),
+                _ => ()
+            }
+        }
+        let debug_line = sections.get(".debug_line")?;
+        let strings = DebugStrings {
+            line: sections.get(".debug_line_str").cloned().unwrap_or(&[]),
+            other: sections.get(".debug_str").cloned().unwrap_or(&[])
+        };
+        let mut table = LineTable::default();
+        let mut cursor = Cursor { data: debug_line, position: 0 };
+        while cursor.position < debug_line.len() {
+            read_line_program(&mut cursor, &strings, code_start?, &mut table.rows)?;
+        }
+        table.rows.sort_by_key(|row| row.0);
+        Some(table)
+    }
+
+    // returns the URL of the source map named in a module's sourceMappingURL section, if it has one
+    pub fn source_mapping_url(buf:&[u8]) -> Option<String> {
+        let mut reader = ModuleReader::new(buf).ok()?;
+        while !reader.eof() {
+            let section = reader.read().ok()?;
+            if let SectionCode::Custom { name: "sourceMappingURL", .. } = section.code {
+                return section.get_sourcemappingurl_section_content().ok().map(|url| url.to_string());
+            }
+        }
+        None
+    }
+
+    // reads the line table from a version 3 source map, only looking at its sources, sourceRoot and mappings
+    pub fn from_source_map(json:&str) -> Option<LineTable> {
+        let root = json_value(json, "sourceRoot").and_then(|start| json_string(json, start)).map_or(String::new(), |(root, _)| root);
+        let sources = json_string_array(json, json_value(json, "sources")?)?;
+        let (mappings, _) = json_string(json, json_value(json, "mappings")?)?;
+
+        // each segment holds base 64 VLQ fields, relative to the same fields of the previous segment
+        let mut table = LineTable::default();
+        let mut fields = [0i64; 4];
+        for segment in mappings.split(&[',', ';'][..]).filter(|segment| !segment.is_empty()) {
+            let values = decode_vlq(segment)?;
+            for (field, value) in fields.iter_mut().zip(values.iter()) {
+                *field += *value;
+            }
+            let location = match values.len() {
+                1 => None,
+                _ => Some(SourceLocation {
+                    file: format!("{}{}", root, sources.get(fields[1] as usize)?),
+                    line: fields[2] as usize + 1,
+                    column: fields[3] as usize + 1
+                })
+            };
+            table.rows.push((fields[0] as usize, location));
+        }
+        table.rows.sort_by_key(|row| row.0);
+        Some(table)
+    }
+
+    // checks whether the table has no rows
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    // returns the source location the code at a module offset was compiled from, if known
+    pub fn lookup(&self, offset:usize) -> Option<SourceLocation> {
+        let index = self.rows.iter().rposition(|row| row.0 <= offset)?;
+        self.rows[index].1.clone()
+    }
+
+    // returns the lines of each source file the code between two module offsets was compiled from, in order of file
+    pub fn ranges(&self, start:usize, end:usize) -> Vec<SourceRange> {
+        let first = self.rows.iter().rposition(|row| row.0 <= start).unwrap_or(0);
+        let mut ranges:Vec<SourceRange> = Vec::new();
+        for (_, location) in self.rows.iter().skip(first).take_while(|row| row.0 < end.max(start + 1)) {
+            let location = match *location {
+                Some(ref location) => location,
+                None => continue
+            };
+            match ranges.iter_mut().find(|range| range.file == location.file) {
+                Some(range) => {
+                    range.first_line = range.first_line.min(location.line);
+                    range.last_line = range.last_line.max(location.line);
+                }
+                None => ranges.push(SourceRange { file: location.file.clone(), first_line: location.line, last_line: location.line })
+            }
+        }
+        ranges.sort_by(|a, b| a.file.cmp(&b.file));
+        ranges
+    }
+}
+
+
+/// The debug strings are the string sections file names can refer to
+struct DebugStrings<'a> {
+    line: &'a [u8], // the contents of .debug_line_str
+    other: &'a [u8] // the contents of .debug_str
+}
+
+
+/// A cursor reads the little endian values of DWARF sections
+struct Cursor<'a> {
+    data: &'a [u8], // the section
+    position: usize // the offset of the next byte to read
+}
+
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, count:usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.position..self.position + count)?;
+        self.position += count;
+        Some(bytes)
+    }
+
+    fn unsigned(&mut self, size:usize) -> Option<u64> {
+        Some(self.bytes(size)?.iter().rev().fold(0, |value, byte| (value << 8) | *byte as u64))
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn uleb(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Option<i64> {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Some(value);
+            }
+        }
+    }
+
+    // reads a null terminated string
+    fn string(&mut self) -> Option<String> {
+        let length = self.data.get(self.position..)?.iter().position(|byte| *byte == 0)?;
+        let string = String::from_utf8_lossy(self.bytes(length)?).into_owned();
+        self.position += 1;
+        Some(string)
+    }
+}
+
+
+// reads a null terminated string at an offset into a string section
+fn string_at(section:&[u8], offset:u64) -> Option<String> {
+    Cursor { data: section, position: offset as usize }.string()
+}
+
+
+// reads an attribute of a version 5 directory or file entry, returning it as a string or a number
+fn read_form(cursor:&mut Cursor, form:u64, offset_size:usize, strings:&DebugStrings) -> Option<(Option<String>, u64)> {
+    Some(match form {
+        0x08 => (Some(cursor.string()?), 0), // DW_FORM_string
+        0x1f => (Some(string_at(strings.line, cursor.unsigned(offset_size)?)?), 0), // DW_FORM_line_strp
+        0x0e => (Some(string_at(strings.other, cursor.unsigned(offset_size)?)?), 0), // DW_FORM_strp
+        0x0f => (None, cursor.uleb()?), // DW_FORM_udata
+        0x0b => (None, cursor.unsigned(1)?), // DW_FORM_data1
+        0x05 => (None, cursor.unsigned(2)?), // DW_FORM_data2
+        0x06 => (None, cursor.unsigned(4)?), // DW_FORM_data4
+        0x07 => (None, cursor.unsigned(8)?), // DW_FORM_data8
+        0x1e => { cursor.bytes(16)?; (None, 0) } // DW_FORM_data16
+        0x09 => { let length = cursor.uleb()? as usize; cursor.bytes(length)?; (None, 0) } // DW_FORM_block
+        _ => return None
+    })
+}
+
+
+// reads the directories or files of a version 5 line program header, each as a path and a directory index
+fn read_entries(cursor:&mut Cursor, offset_size:usize, strings:&DebugStrings) -> Option<Vec<(String, usize)>> {
+    let format_count = cursor.u8()?;
+    let mut formats = Vec::new();
+    for _ in 0..format_count {
+        formats.push((cursor.uleb()?, cursor.uleb()?));
+    }
+    let count = cursor.uleb()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let mut entry = (String::new(), 0);
+        for (content, form) in formats.iter() {
+            let (string, number) = read_form(cursor, *form, offset_size, strings)?;
+            match *content {
+                1 => entry.0 = string.unwrap_or_default(), // DW_LNCT_path
+                2 => entry.1 = number as usize, // DW_LNCT_directory_index
+                _ => ()
+            }
+        }
+        entries.push(entry);
+    }
+    Some(entries)
+}
+
+
+// joins a file name to the directory it is relative to
+fn join(directory:Option<&String>, name:&str) -> String {
+    match directory {
+        Some(directory) if !directory.is_empty() && !name.starts_with('/') => format!("{}/{}", directory.trim_end_matches('/'), name),
+        _ => name.to_string()
+    }
+}
+
+
+// reads a line program of a DWARF .debug_line section, adding the rows it describes as module offsets
+fn read_line_program(cursor:&mut Cursor, strings:&DebugStrings, code_start:usize, rows:&mut Vec<(usize, Option<SourceLocation>)>) -> Option<()> {
+    let (mut unit_length, mut offset_size) = (cursor.unsigned(4)?, 4);
+    if unit_length == 0xffff_ffff {
+        unit_length = cursor.unsigned(8)?;
+        offset_size = 8;
+    }
+    let unit_end = cursor.position + unit_length as usize;
+    let version = cursor.unsigned(2)?;
+    let mut address_size = 4;
+    if version >= 5 {
+        address_size = cursor.u8()? as usize;
+        cursor.u8()?;
+    }
+    let header_length = cursor.unsigned(offset_size)? as usize;
+    let program_start = cursor.position + header_length;
+    let minimum_instruction_length = cursor.u8()? as u64;
+    if version >= 4 {
+        cursor.u8()?;
+    }
+    let default_is_stmt = cursor.u8()? != 0;
+    let line_base = cursor.u8()? as i8 as i64;
+    let line_range = cursor.u8()? as u64;
+    let opcode_base = cursor.u8()?;
+    let mut standard_opcode_lengths = Vec::new();
+    for _ in 1..opcode_base {
+        standard_opcode_lengths.push(cursor.u8()?);
+    }
+
+    // files are numbered from 1 before version 5, where directory 0 is the compilation directory
+    let (directories, mut files) = if version >= 5 {
+        let directories = read_entries(cursor, offset_size, strings)?.into_iter().map(|(path, _)| path).collect::<Vec<String>>();
+        let files = read_entries(cursor, offset_size, strings)?;
+        (directories, files)
+    } else {
+        let mut directories = vec![String::new()];
+        loop {
+            let directory = cursor.string()?;
+            if directory.is_empty() {
+                break;
+            }
+            directories.push(directory);
+        }
+        let mut files = vec![(String::new(), 0)];
+        loop {
+            let name = cursor.string()?;
+            if name.is_empty() {
+                break;
+            }
+            let directory = cursor.uleb()? as usize;
+            cursor.uleb()?;
+            cursor.uleb()?;
+            files.push((name, directory));
+        }
+        (directories, files)
+    };
+    cursor.position = program_start;
+
+    let default_file = if version >= 5 { 0 } else { 1 };
+    let (mut address, mut file, mut line, mut column, mut is_stmt) = (0u64, default_file, 1i64, 0u64, default_is_stmt);
+    while cursor.position < unit_end {
+        let opcode = cursor.u8()?;
+        let mut emit = false;
+        let mut end_sequence = false;
+        if opcode >= opcode_base {
+            let adjusted = (opcode - opcode_base) as u64;
+            address += adjusted / line_range * minimum_instruction_length;
+            line += line_base + (adjusted % line_range) as i64;
+            emit = true;
+        } else {
+            match opcode {
+                0 => {
+                    let length = cursor.uleb()? as usize;
+                    let end = cursor.position + length;
+                    match cursor.u8()? {
+                        1 => { emit = true; end_sequence = true; }
+                        2 => address = cursor.unsigned(address_size)?,
+                        3 => {
+                            let name = cursor.string()?;
+                            let directory = cursor.uleb()? as usize;
+                            files.push((name, directory));
+                        }
+                        _ => ()
+                    }
+                    cursor.position = end;
+                }
+                1 => emit = true,
+                2 => address += cursor.uleb()? * minimum_instruction_length,
+                3 => line += cursor.sleb()?,
+                4 => file = cursor.uleb()? as usize,
+                5 => column = cursor.uleb()?,
+                6 => is_stmt = !is_stmt,
+                8 => address += (255 - opcode_base as u64) / line_range * minimum_instruction_length,
+                9 => address += cursor.unsigned(2)?,
+                _ => {
+                    for _ in 0..standard_opcode_lengths[opcode as usize - 1] {
+                        cursor.uleb()?;
+                    }
+                }
+            }
+        }
+        if emit {
+            let offset = code_start + address as usize;
+            if end_sequence {
+                rows.push((offset, None));
+                address = 0;
+                file = default_file;
+                line = 1;
+                column = 0;
+                is_stmt = default_is_stmt;
+            } else if let Some(&(ref name, directory)) = files.get(file) {
+                let location = SourceLocation { file: join(directories.get(directory), name), line: line.max(0) as usize, column: column as usize };
+                rows.push((offset, Some(location)));
+            }
+        }
+    }
+    cursor.position = unit_end;
+    Some(())
+}
+
+
+// decodes the base 64 VLQ values of a source map segment
+fn decode_vlq(segment:&str) -> Option<Vec<i64>> {
+    const ALPHABET:&[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0i64, 0);
+    for byte in segment.bytes() {
+        let digit = ALPHABET.iter().position(|c| *c == byte)? as i64;
+        value |= (digit & 0x1f) << shift;
+        shift += 5;
+        if digit & 0x20 == 0 {
+            values.push(if value & 1 == 1 { -(value >> 1) } else { value >> 1 });
+            value = 0;
+            shift = 0;
+        }
+    }
+    Some(values)
+}
+
+
+// returns the position of the value of a key in a JSON object, after its colon
+fn json_value(json:&str, key:&str) -> Option<usize> {
+    let quoted = format!("\"{}\"", key);
+    let start = json.find(&quoted)? + quoted.len();
+    let colon = json[start..].find(':')?;
+    Some(start + colon + 1)
+}
+
+// reads the JSON string starting at or after a position, returning it and the position after it
+fn json_string(json:&str, start:usize) -> Option<(String, usize)> {
+    let open = start + json[start..].find('"')?;
+    let mut string = String::new();
+    let mut chars = json[open + 1..].char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, open + index + 2)),
+            '\\' => match chars.next()?.1 {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                'u' => {
+                    let digits:String = (0..4).filter_map(|_| chars.next().map(|(_, c)| c)).collect();
+                    string.push(std::char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?);
+                }
+                escaped => string.push(escaped)
+            },
+            c => string.push(c)
+        }
+    }
+    None
+}
+
+// reads the JSON array of strings starting at or after a position
+fn json_string_array(json:&str, start:usize) -> Option<Vec<String>> {
+    let mut position = start + json[start..].find('[')? + 1;
+    let mut strings = Vec::new();
+    loop {
+        let rest = json[position..].trim_start();
+        if rest.starts_with(']') {
+            return Some(strings);
+        }
+        let (string, end) = json_string(json, position)?;
+        strings.push(string);
+        position = end;
+        let rest = json[position..].trim_start();
+        position = json.len() - rest.len();
+        if rest.starts_with(',') {
+            position += 1;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_map() {
+        // offset 10 comes from a.c line 1, offset 18 from b.c line 5 and offset 20 from a.c line 3
+        let json = "{\"version\":3,\"sourceRoot\":\"src/\",\"sources\":[\"a.c\", \"b.c\"],\"names\":[],\"mappings\":\"UAAA,QCIA,EDFA\"}";
+        let table = LineTable::from_source_map(json).unwrap();
+        assert_eq!(table.lookup(9), None);
+        assert_eq!(table.lookup(12), Some(SourceLocation { file: "src/a.c".to_string(), line: 1, column: 1 }));
+        assert_eq!(table.lookup(18).unwrap().line, 5);
+        assert_eq!(table.ranges(10, 21), vec![
+            SourceRange { file: "src/a.c".to_string(), first_line: 1, last_line: 3 },
+            SourceRange { file: "src/b.c".to_string(), first_line: 5, last_line: 5 }
+        ]);
+        assert_eq!(table.ranges(12, 14).len(), 1);
+        assert!(LineTable::from_source_map("{\"sources\":[]}").is_none());
+    }
+
+    #[test]
+    fn debug_line() {
+        // a version 4 line program for main.c in src, with rows at code offsets 2 and 5 and the end of its sequence at 9
+        let mut header = vec![1, 1, 1, 0xfb, 14, 13, 0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+        header.extend(b"src\0\0main.c\0\x01\0\0\0");
+        let program = [0, 5, 2, 2, 0, 0, 0, 1, 3, 4, 2, 3, 1, 2, 4, 0, 1, 1];
+        let mut unit = vec![4, 0];
+        unit.extend(&(header.len() as u32).to_le_bytes());
+        unit.extend(&header);
+        unit.extend(&program);
+        let mut section = (unit.len() as u32).to_le_bytes().to_vec();
+        section.extend(&unit);
+
+        let strings = DebugStrings { line: &[], other: &[] };
+        let mut rows = Vec::new();
+        read_line_program(&mut Cursor { data: &section, position: 0 }, &strings, 100, &mut rows).unwrap();
+        let table = LineTable { rows };
+        assert_eq!(table.lookup(103), Some(SourceLocation { file: "src/main.c".to_string(), line: 1, column: 0 }));
+        assert_eq!(table.lookup(106).unwrap().line, 5);
+        assert_eq!(table.lookup(110), None);
+        assert_eq!(table.ranges(100, 120), vec![SourceRange { file: "src/main.c".to_string(), first_line: 1, last_line: 5 }]);
+    }
+}
@@ -0,0 +1,193 @@
+//! # Python
+//! A Python module exposing the mapping pipeline, so that PyQUBO and Ocean workflows can map and
+//! lower WASM in process. The module is named `wasm_pfc`; build the crate with the `pyo3` feature
+//! and import the resulting shared library under that name:
+//!
+//! ```text
+//! import wasm_pfc
+//! nodes = wasm_pfc.Mapper().map(open("math.wasm", "rb").read())
+//! bqm = nodes[0].lower().to_bqm()
+//! ```
+
+// the wrappers pyo3 generates for functions returning PyResult convert errors to their own type
+#![allow(clippy::useless_conversion)]
+
+use std::collections::HashMap;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use parallelize::{new_mapper, Constraint, Mapper, Node, PhysicalExpression};
+use ids::NodeId;
+
+
+/// The Python mapper wraps a mapper, which keeps its registered blocks
+/// between calls just like it does in Rust
+#[pyclass(name = "Mapper")]
+pub struct PyMapper {
+    mapper: Mapper
+}
+
+
+#[pymethods]
+impl PyMapper {
+    #[new]
+    fn new() -> PyMapper {
+        PyMapper { mapper: new_mapper() }
+    }
+
+    // maps a WASM module given as bytes, returning its top-level nodes by id
+    fn map(&mut self, bytes:&[u8]) -> HashMap<usize, PyNode> {
+        self.mapper.map(bytes.to_vec()).into_iter()
+            .map(|(id, node)| (id.0, PyNode { node }))
+            .collect()
+    }
+
+    // returns the node ids grouped by mutual recursion
+    fn recursion_groups(&self) -> Vec<Vec<usize>> {
+        self.mapper.recursion_groups()
+    }
+
+    // returns the hexdump of a mapped node's instructions
+    fn hexdump(&self, index:usize) -> Option<String> {
+        self.mapper.hexdump(NodeId(index))
+    }
+}
+
+
+/// The Python node wraps a copy of a mapped node
+#[pyclass(name = "Node")]
+#[derive(Clone)]
+pub struct PyNode {
+    node: Node
+}
+
+
+#[pymethods]
+impl PyNode {
+    #[getter]
+    fn id(&self) -> usize {
+        self.node.get_id().0
+    }
+
+    #[getter]
+    fn function(&self) -> bool {
+        self.node.is_function()
+    }
+
+    #[getter]
+    fn start(&self) -> usize {
+        self.node.get_start()
+    }
+
+    #[getter]
+    fn end(&self) -> usize {
+        self.node.get_end()
+    }
+
+    // the called functions by the location of the call
+    #[getter]
+    fn calls(&self) -> HashMap<usize, usize> {
+        self.node.calls().iter().map(|(location, callee)| (*location, callee.0)).collect()
+    }
+
+    // the nested nodes by id
+    #[getter]
+    fn children(&self) -> HashMap<usize, PyNode> {
+        self.node.children().clone().into_iter()
+            .map(|(id, node)| (id.0, PyNode { node }))
+            .collect()
+    }
+
+    // the variables' type names by variable id
+    #[getter]
+    fn input_variables(&self) -> HashMap<usize, String> {
+        type_names(self.node.input_variables().clone())
+    }
+
+    #[getter]
+    fn internal_variables(&self) -> HashMap<usize, String> {
+        type_names(self.node.internal_variables().clone())
+    }
+
+    #[getter]
+    fn constants(&self) -> HashMap<usize, String> {
+        type_names(self.node.constants().clone())
+    }
+
+    // lowers the node to a QUBO
+    fn lower(&mut self) -> PyQubo {
+        PyQubo { constraint: self.node.lower() }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Node(id={}, start={}, end={})", self.node.get_id(), self.node.get_start(), self.node.get_end())
+    }
+}
+
+
+/// The Python QUBO wraps the constraint a node was lowered to
+#[pyclass(name = "QUBO")]
+pub struct PyQubo {
+    constraint: Constraint
+}
+
+
+#[pymethods]
+impl PyQubo {
+    #[getter]
+    fn id(&self) -> usize {
+        self.constraint.get_id()
+    }
+
+    // returns the QUBO as the keyword arguments of dimod's BinaryQuadraticModel constructor, once it
+    // has been simplified; the gadget penalties give the linear and quadratic terms, while physical
+    // expressions only combine known values so far, so they end up in the offset
+    fn to_bqm<'py>(&self, py:Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let qubo = self.constraint.qubo();
+        let offset = match self.constraint.get_expression().and_then(|expression| expression.simplify()) {
+            Some(expression) => evaluate(&expression),
+            None => 0.0
+        };
+        let linear = PyDict::new_bound(py);
+        for (variable, coefficient) in qubo.linear() {
+            linear.set_item(*variable, *coefficient as f64)?;
+        }
+        let quadratic = PyDict::new_bound(py);
+        for (&(u, v), coefficient) in qubo.quadratic() {
+            quadratic.set_item((u, v), *coefficient as f64)?;
+        }
+        let bqm = PyDict::new_bound(py);
+        bqm.set_item("linear", linear)?;
+        bqm.set_item("quadratic", quadratic)?;
+        bqm.set_item("offset", offset + qubo.get_offset() as f64)?;
+        bqm.set_item("vartype", "BINARY")?;
+        Ok(bqm)
+    }
+}
+
+
+// evaluates a physical expression whose values are all known
+fn evaluate(expression:&PhysicalExpression) -> f64 {
+    match *expression {
+        PhysicalExpression::Add { ref operand_one, ref operand_two } => evaluate(operand_one) + evaluate(operand_two),
+        PhysicalExpression::Mul { ref operand_one, ref operand_two } => evaluate(operand_one) * evaluate(operand_two),
+        PhysicalExpression::Spin { val } => if val { 1.0 } else { -1.0 },
+        PhysicalExpression::Num { val } => val as f64,
+        PhysicalExpression::Binary { val } => if val { 1.0 } else { 0.0 }
+    }
+}
+
+
+// names the types of a node's variables or constants
+fn type_names<T:std::fmt::Debug>(types:HashMap<usize, T>) -> HashMap<usize, String> {
+    types.into_iter().map(|(id, ty)| (id, format!("{:?}", ty))).collect()
+}
+
+
+/// The wasm_pfc Python module
+#[pymodule]
+fn wasm_pfc(module:&Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyMapper>()?;
+    module.add_class::<PyNode>()?;
+    module.add_class::<PyQubo>()?;
+    Ok(())
+}
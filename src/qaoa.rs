@@ -0,0 +1,148 @@
+//! # QAOA
+//! Export of lowered problems to gate-model devices. A QUBO over binary variables is the Ising form over
+//! spins once every variable is written `x = (1 - z) / 2`, which makes a variable set exactly when its qubit
+//! measures one, and that Ising form is the cost Hamiltonian of a QAOA circuit: alternating layers of the
+//! cost's phases, as Z rotations for the fields and CNOT conjugated Z rotations for the couplings, and X
+//! rotations mixing every qubit, starting from the uniform superposition. The circuit is written as
+//! OpenQASM 3 with the angles of each layer left as inputs, or as a JSON description of the cost operator
+//! that Qiskit's `SparsePauliOp.from_sparse_list` and `QAOAAnsatz` take. Qubits are numbered after the
+//! QUBO's variables in order, so a measured bit string reads back as the set variables
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use gadgets::Qubo;
+
+
+/// An Ising form is a function of spins, each being 1 or -1, with
+/// fields on single spins and couplings between pairs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ising {
+    pub offset: f64, // the constant term
+    pub fields: BTreeMap<usize, f64>, // the coefficients of single spins, by variable
+    pub couplings: BTreeMap<(usize, usize), f64> // the coefficients of pairs of spins, by pair with the lower index first
+}
+
+
+impl Ising {
+    // rewrites a QUBO over spins, a variable being set exactly when its spin is -1; coefficients are exact,
+    // since they are integers divided by two or four
+    pub fn from_qubo(qubo:&Qubo) -> Ising {
+        let mut ising = Ising { offset: qubo.get_offset() as f64, ..Ising::default() };
+        for variable in qubo.variables() {
+            ising.fields.insert(variable, 0.0);
+        }
+        for (variable, coefficient) in qubo.linear() {
+            ising.offset += *coefficient as f64 / 2.0;
+            *ising.fields.get_mut(variable).unwrap() -= *coefficient as f64 / 2.0;
+        }
+        for (&(u, v), coefficient) in qubo.quadratic() {
+            let quarter = *coefficient as f64 / 4.0;
+            ising.offset += quarter;
+            *ising.fields.get_mut(&u).unwrap() -= quarter;
+            *ising.fields.get_mut(&v).unwrap() -= quarter;
+            ising.couplings.insert((u, v), quarter);
+        }
+        ising
+    }
+
+    // returns the spins the function is over, in the order their qubits are numbered
+    pub fn variables(&self) -> Vec<usize> {
+        self.fields.keys().cloned().collect()
+    }
+
+    // returns the value of the function for an assignment of the variables, given as the set ones, whose spins
+    // are -1
+    pub fn energy(&self, set:&BTreeSet<usize>) -> f64 {
+        let spin = |variable:&usize| if set.contains(variable) { -1.0 } else { 1.0 };
+        self.offset
+            + self.fields.iter().map(|(variable, field)| field * spin(variable)).sum::<f64>()
+            + self.couplings.iter().map(|(&(u, v), coupling)| coupling * spin(&u) * spin(&v)).sum::<f64>()
+    }
+
+    // returns the variables set by a measured bit string, which holds the first qubit's bit first
+    pub fn read_bits(&self, bits:&str) -> BTreeSet<usize> {
+        self.variables().into_iter().zip(bits.chars()).filter(|&(_, bit)| bit == '1').map(|(variable, _)| variable).collect()
+    }
+}
+
+
+// writes a QAOA circuit of the given number of layers for an Ising form as OpenQASM 3, with a gamma and a beta
+// input angle per layer and every qubit measured at the end
+pub fn to_openqasm(ising:&Ising, layers:usize) -> String {
+    let qubits:BTreeMap<usize, usize> = ising.variables().into_iter().enumerate().map(|(qubit, variable)| (variable, qubit)).collect();
+    let mut qasm = String::from("OPENQASM 3.0;\ninclude \"stdgates.inc\";\n");
+    for (variable, qubit) in qubits.iter() {
+        writeln!(qasm, "// q[{}] holds x{}", qubit, variable).unwrap();
+    }
+    writeln!(qasm, "// the cost is offset by {}", ising.offset).unwrap();
+    for layer in 0..layers {
+        writeln!(qasm, "input float[64] gamma_{};\ninput float[64] beta_{};", layer, layer).unwrap();
+    }
+    writeln!(qasm, "qubit[{}] q;\nbit[{}] c;\nh q;", qubits.len(), qubits.len()).unwrap();
+
+    // each layer applies the cost's phases, exp(-i gamma H), then mixes with exp(-i beta X) on every qubit
+    for layer in 0..layers {
+        for (variable, field) in ising.fields.iter().filter(|&(_, field)| *field != 0.0) {
+            writeln!(qasm, "rz({} * gamma_{}) q[{}];", 2.0 * field, layer, qubits[variable]).unwrap();
+        }
+        for (&(u, v), coupling) in ising.couplings.iter() {
+            let (u, v) = (qubits[&u], qubits[&v]);
+            writeln!(qasm, "cx q[{}], q[{}];\nrz({} * gamma_{}) q[{}];\ncx q[{}], q[{}];", u, v, 2.0 * coupling, layer, v, u, v).unwrap();
+        }
+        writeln!(qasm, "rx(2 * beta_{}) q;", layer).unwrap();
+    }
+    qasm.push_str("c = measure q;\n");
+    qasm
+}
+
+
+// describes a QAOA problem for an Ising form as JSON: the number of qubits and layers, the variable each qubit
+// holds, the cost's offset, and its terms as `[paulis, qubits, coefficient]` triples, the sparse list Qiskit
+// builds a SparsePauliOp from
+pub fn to_json(ising:&Ising, layers:usize) -> String {
+    let variables = ising.variables();
+    let qubit = |variable:&usize| variables.binary_search(variable).unwrap();
+    let mut terms:Vec<String> = ising.fields.iter()
+        .filter(|&(_, field)| *field != 0.0)
+        .map(|(variable, field)| format!("[\"Z\",[{}],{}]", qubit(variable), field))
+        .collect();
+    terms.extend(ising.couplings.iter().map(|(&(u, v), coupling)| format!("[\"ZZ\",[{},{}],{}]", qubit(&u), qubit(&v), coupling)));
+    let variables:Vec<String> = variables.iter().map(|variable| variable.to_string()).collect();
+    format!("{{\"num_qubits\":{},\"layers\":{},\"variables\":[{}],\"offset\":{},\"sparse_pauli_list\":[{}]}}",
+        variables.len(), layers, variables.join(","), ising.offset, terms.join(","))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gadgets;
+
+    #[test]
+    fn ising() {
+        // the Ising form takes the QUBO's values on every assignment
+        let mut qubo = gadgets::full_adder(0, 1, 2, 3, 4);
+        qubo += gadgets::fix(7, true);
+        let ising = Ising::from_qubo(&qubo);
+        let variables = qubo.variables();
+        for assignment in 0..1u64 << variables.len() {
+            let set:BTreeSet<usize> = variables.iter().enumerate().filter(|&(bit, _)| assignment >> bit & 1 == 1).map(|(_, variable)| *variable).collect();
+            assert_eq!(ising.energy(&set), qubo.energy(&set) as f64);
+        }
+        assert_eq!(ising.read_bits("000001"), vec![7].into_iter().collect());
+
+        // NOT is a single antiferromagnetic coupling
+        let ising = Ising::from_qubo(&gadgets::not(2, 5));
+        assert_eq!((ising.offset, ising.fields[&2], ising.couplings[&(2, 5)]), (0.5, 0.0, 0.5));
+    }
+
+    #[test]
+    fn export() {
+        let ising = Ising::from_qubo(&(gadgets::not(2, 5) + gadgets::fix(5, true)));
+        let qasm = to_openqasm(&ising, 2);
+        assert!(qasm.starts_with("OPENQASM 3.0;\ninclude \"stdgates.inc\";\n// q[0] holds x2\n// q[1] holds x5\n"));
+        assert!(qasm.contains("input float[64] beta_1;\nqubit[2] q;\nbit[2] c;\nh q;\nrz(1 * gamma_0) q[1];\ncx q[0], q[1];\nrz(1 * gamma_0) q[1];\ncx q[0], q[1];\nrx(2 * beta_0) q;\n"));
+        assert!(qasm.ends_with("rx(2 * beta_1) q;\nc = measure q;\n"));
+        assert_eq!(to_json(&ising, 1), "{\"num_qubits\":2,\"layers\":1,\"variables\":[2,5],\"offset\":1,\"sparse_pauli_list\":[[\"Z\",[1],0.5],[\"ZZ\",[0,1],0.5]]}");
+    }
+}
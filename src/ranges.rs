@@ -0,0 +1,286 @@
+//! # Ranges
+//! Range analysis over the SSA form of a node, inferring how many bits each value actually takes so that
+//! lowering can allocate fewer variables for the many values that are known to be small, like flow control
+//! conditions, constants, and sums and products of small values. A range is an unsigned interval, and any
+//! value that could wrap around its width, or be negative, spans the whole width. Input variables span it
+//! too unless they are given a range, which the user can do for any of them, and which the mapper does for
+//! loads from a memory nothing writes to, whose values can only come from the module's data segments
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use primitives::{ExternalKind, ImportSectionEntryType, Operator, SectionCode, Type};
+use readers::{DataKind, ModuleReader};
+use ssa::{Definition, Graph, ShiftKind};
+
+
+/// A value range bounds the unsigned values an SSA value can take
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValueRange {
+    pub min: u64,
+    pub max: u64
+}
+
+
+impl ValueRange {
+    pub fn new(min:u64, max:u64) -> ValueRange {
+        ValueRange { min: min.min(max), max }
+    }
+
+    // returns the range of a single value
+    pub fn constant(value:u64) -> ValueRange {
+        ValueRange { min: value, max: value }
+    }
+
+    // returns the range of every value of the given number of bits
+    pub fn full(width:usize) -> ValueRange {
+        ValueRange { min: 0, max: mask(width) }
+    }
+
+    // returns how many bits the values of the range take, which is at least one
+    pub fn bits(&self) -> usize {
+        (64 - self.max.leading_zeros() as usize).max(1)
+    }
+
+    // returns the smallest range holding the values of both ranges
+    pub fn union(&self, other:ValueRange) -> ValueRange {
+        ValueRange { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    // returns the range, unless it doesn't fit in the given number of bits, in which case its values could be
+    // anything once wrapped to them
+    fn within(self, width:usize) -> ValueRange {
+        if self.max <= mask(width) { self } else { ValueRange::full(width) }
+    }
+}
+
+
+impl fmt::Display for ValueRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}, {}]", self.min, self.max)
+    }
+}
+
+
+// returns the largest value of the given number of bits
+fn mask(width:usize) -> u64 {
+    if width >= 64 { u64::MAX } else { (1 << width) - 1 }
+}
+
+
+// returns the number of bits of a value of the given type, whatever width it is lowered to
+fn type_width(ty:Type) -> u64 {
+    if ty == Type::I64 || ty == Type::F64 { 64 } else { 32 }
+}
+
+
+// infers the range of every value of a graph, indexed by value, from the ranges of the node's input variables;
+// values are as wide as the given width function makes their types
+pub fn infer(graph:&Graph, inputs:&HashMap<usize, ValueRange>, width:&dyn Fn(Type) -> usize) -> Vec<ValueRange> {
+    let mut ranges:Vec<ValueRange> = Vec::new();
+    for instruction in graph.instructions() {
+        let bits = width(instruction.ty);
+        let integer = instruction.ty == Type::I32 || instruction.ty == Type::I64;
+        let range = match instruction.definition {
+            Definition::Input { var_id } => match inputs.get(&var_id) {
+                Some(range) => ValueRange::new(range.min.min(mask(bits)), range.max.min(mask(bits))),
+                None => ValueRange::full(bits)
+            },
+
+            // an internal variable stands in for whether a nested node runs, and holds the condition it
+            // consumed if it is coupled to the condition's producer
+            Definition::Internal { condition: Some(condition), .. } => ranges[condition],
+            Definition::Internal { .. } => ValueRange::new(0, 1),
+            Definition::Const { val } => ValueRange::constant(val as u64 & mask(bits)),
+            Definition::Add { lhs, rhs } if integer => {
+                let (lhs, rhs) = (ranges[lhs], ranges[rhs]);
+                match (lhs.min.checked_add(rhs.min), lhs.max.checked_add(rhs.max)) {
+                    (Some(min), Some(max)) => ValueRange::new(min, max).within(bits),
+                    _ => ValueRange::full(bits)
+                }
+            }
+            Definition::Mul { lhs, rhs } if integer => {
+                let (lhs, rhs) = (ranges[lhs], ranges[rhs]);
+                match (lhs.min.checked_mul(rhs.min), lhs.max.checked_mul(rhs.max)) {
+                    (Some(min), Some(max)) => ValueRange::new(min, max).within(bits),
+                    _ => ValueRange::full(bits)
+                }
+            }
+
+            // extending keeps the operand's range as long as the bits kept hold it and the sign bit isn't set
+            Definition::Extend { operand, bits: kept, signed } => {
+                let operand = ranges[operand];
+                let positive = if signed { kept.saturating_sub(1) } else { kept };
+                match (operand.max <= mask(positive), signed) {
+                    (true, _) => operand.within(bits),
+                    (false, false) => ValueRange::full(kept.min(bits)),
+                    (false, true) => ValueRange::full(bits)
+                }
+            }
+
+            // dividing by zero traps, so a division that doesn't divides by one at least
+            Definition::Div { lhs, rhs } if integer => {
+                let (lhs, rhs) = (ranges[lhs], ranges[rhs]);
+                ValueRange::new(lhs.min / rhs.max.max(1), lhs.max / rhs.min.max(1))
+            }
+            Definition::Rem { lhs, rhs } if integer => ValueRange::new(0, ranges[lhs].max.min(ranges[rhs].max.saturating_sub(1))),
+            Definition::Shr { operand, bits: shift } => {
+                let operand = ranges[operand];
+                let shifted = |value:u64| value.checked_shr(shift as u32).unwrap_or(0);
+                ValueRange::new(shifted(operand.min), shifted(operand.max))
+            }
+            Definition::Shift { lhs, kind: ShiftKind::ShrU, .. } => ValueRange::new(0, ranges[lhs].max),
+            Definition::MulShr { operand, multiplier, shift } => {
+                let operand = ranges[operand];
+                let scale = |value:u64| (value as u128 * multiplier as u128).checked_shr(shift as u32).unwrap_or(0);
+                match (scale(operand.min), scale(operand.max)) {
+                    (min, max) if max <= u64::MAX as u128 => ValueRange::new(min as u64, max as u64).within(bits),
+                    _ => ValueRange::full(bits)
+                }
+            }
+
+            // a nonzero operand has a set bit at most as high as its largest value's, and counts of the bits of
+            // a zero operand run to the width of its type
+            Definition::Popcnt { operand } => {
+                let operand = ranges[operand];
+                ValueRange::new(operand.min.min(1), 64 - operand.max.leading_zeros() as u64).within(bits)
+            }
+            Definition::Ctz { operand } if ranges[operand].min > 0 => ValueRange::new(0, 63 - ranges[operand].max.leading_zeros() as u64).within(bits),
+            Definition::Ctz { .. } => ValueRange::new(0, type_width(instruction.ty)).within(bits),
+            Definition::Clz { operand } => {
+                let (operand, width) = (ranges[operand], type_width(instruction.ty));
+                let leading = |value:u64| width - (64 - value.leading_zeros() as u64).min(width);
+                ValueRange::new(leading(operand.max), leading(operand.min)).within(bits)
+            }
+
+            // a zero test is a boolean
+            Definition::IsZero { .. } => ValueRange::new(0, 1),
+
+            // a reinterpretation has the bits of its operand, and so its range
+            Definition::Reinterpret { operand } => ranges[operand].within(bits),
+
+            // a value saturated to an unsigned range stays within its bits, while a negative one wraps around
+            Definition::Clamp { bits: kept, signed: false, .. } => ValueRange::full(kept.min(bits)),
+            Definition::Phi { ref incoming } => incoming.iter()
+                .map(|&(_, value)| ranges[value])
+                .fold(None, |union:Option<ValueRange>, range| Some(union.map_or(range, |union| union.union(range))))
+                .unwrap_or_else(|| ValueRange::full(bits)),
+            _ => ValueRange::full(bits)
+        };
+        ranges.push(range);
+    }
+    ranges
+}
+
+
+// returns the active data segments initializing a module's memory, as where each starts and its bytes, unless
+// something besides the module's own code can write to the memory or initialize it, which is when the memory
+// is imported or exported, or the module has passive segments or segments placed by anything but a constant
+pub fn data_segments(buf:&[u8]) -> Option<Vec<(usize, Vec<u8>)>> {
+    let mut segments = Vec::new();
+    let mut reader = ModuleReader::new(buf).ok()?;
+    while !reader.eof() {
+        let section = reader.read().ok()?;
+        match section.code {
+            SectionCode::Import => {
+                let mut imports = section.get_import_section_reader().ok()?;
+                for _ in 0..imports.get_count() {
+                    if let ImportSectionEntryType::Memory(_) = imports.read().ok()?.ty {
+                        return None;
+                    }
+                }
+            }
+            SectionCode::Export => {
+                let mut exports = section.get_export_section_reader().ok()?;
+                for _ in 0..exports.get_count() {
+                    if let ExternalKind::Memory = exports.read().ok()?.kind {
+                        return None;
+                    }
+                }
+            }
+            SectionCode::Data => {
+                let mut data = section.get_data_section_reader().ok()?;
+                for _ in 0..data.get_count() {
+                    let segment = data.read().ok()?;
+                    let init_expr = match segment.kind {
+                        DataKind::Active { memory_index: 0, init_expr } => init_expr,
+                        _ => return None
+                    };
+                    let mut operators = init_expr.get_operators_reader();
+                    match (operators.read().ok()?, operators.read().ok()?) {
+                        (Operator::I32Const { value }, Operator::End) => segments.push((value as u32 as usize, segment.data.to_vec())),
+                        _ => return None
+                    }
+                }
+            }
+            _ => ()
+        }
+    }
+    Some(segments)
+}
+
+
+// returns the range of the values a load of the given number of bytes can read, little endian, from a memory
+// holding the given data segments and zeros everywhere else
+pub fn load_range(segments:&[(usize, Vec<u8>)], bytes:usize) -> ValueRange {
+    let mut image:BTreeMap<usize, u8> = BTreeMap::new();
+    for (start, data) in segments {
+        image.extend(data.iter().enumerate().map(|(byte, value)| (start + byte, *value)));
+    }
+
+    // only the loads overlapping a set byte can read anything but zero
+    let mut max = 0;
+    for address in image.iter().filter(|&(_, value)| *value != 0).map(|(address, _)| *address) {
+        for start in address.saturating_sub(bytes.max(1) - 1)..=address {
+            let value = (0..bytes.min(8)).fold(0, |value, byte| value | (image.get(&(start + byte)).cloned().unwrap_or(0) as u64) << (8 * byte));
+            max = max.max(value);
+        }
+    }
+    ValueRange::new(0, max)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infer() {
+        let mut graph = Graph::new(0);
+        let input = graph.define(0, 1, Type::I32, Definition::Input { var_id: 0 });
+        let three = graph.define(0, 2, Type::I32, Definition::Const { val: 3 });
+        let sum = graph.define(0, 3, Type::I32, Definition::Add { lhs: input, rhs: three });
+        let product = graph.define(0, 4, Type::I32, Definition::Mul { lhs: sum, rhs: sum });
+        let byte = graph.define(0, 5, Type::I32, Definition::Extend { operand: product, bits: 8, signed: false });
+        let signed = graph.define(0, 6, Type::I32, Definition::Extend { operand: product, bits: 8, signed: true });
+        let condition = graph.define(0, 7, Type::I32, Definition::Internal { var_id: 7, condition: None });
+        graph.define(0, 8, Type::I32, Definition::Phi { incoming: vec![(1, three), (2, condition)] });
+        let coupled = graph.define(0, 9, Type::I32, Definition::Internal { var_id: 9, condition: Some(byte) });
+
+        // a free input spans its type, so everything computed from it could wrap
+        let width = |ty:Type| if ty == Type::I64 { 64 } else { 32 };
+        let ranges = super::infer(&graph, &HashMap::new(), &width);
+        assert_eq!(ranges[sum], ValueRange::full(32));
+        assert_eq!((ranges[byte], ranges[signed], ranges[coupled]), (ValueRange::full(8), ValueRange::full(32), ValueRange::full(8)));
+        assert_eq!((ranges[three].bits(), ranges[condition].bits(), ranges[7].to_string()), (2, 1, "[0, 3]".to_string()));
+
+        // an input known to be small keeps the arithmetic on it small too
+        let mut inputs = HashMap::new();
+        inputs.insert(0, ValueRange::new(0, 5));
+        let ranges = super::infer(&graph, &inputs, &width);
+        assert_eq!((ranges[sum], ranges[product]), (ValueRange::new(3, 8), ValueRange::new(9, 64)));
+        assert_eq!((ranges[byte].bits(), ranges[signed].bits()), (7, 7));
+    }
+
+    #[test]
+    fn memory() {
+        let segments = vec![(16, vec![1, 2, 3, 7]), (18, vec![0])];
+        assert_eq!(load_range(&segments, 1), ValueRange::new(0, 7));
+        assert_eq!(load_range(&segments, 2), ValueRange::new(0, 0x0700));
+        assert_eq!(load_range(&[], 4), ValueRange::constant(0));
+
+        // a memory the host can write to isn't known from the segments
+        let module = include_bytes!("../tests/parallelization/ranges.wasm");
+        assert_eq!(data_segments(module), Some(vec![(16, vec![1, 2, 3, 7])]));
+        assert_eq!(data_segments(include_bytes!("../tests/parallelization/game_of_life.wasm")), None);
+    }
+}
@@ -0,0 +1,130 @@
+//! # Simplify
+//! Algebraic simplification of physical expressions before they are exported. An expression is
+//! expanded into a sum of terms, each a constant coefficient times a product of spins and binaries;
+//! nested sums are flattened along the way, constants are folded into the coefficients, terms over the
+//! same variables are collected and terms whose coefficient is zero are dropped. Spins and binaries are
+//! treated as variables, so identical ones are the same variable
+
+use std::collections::HashMap;
+use parallelize::PhysicalExpression;
+
+
+/// A term is a constant coefficient times a product of variables, which
+/// are kept sorted so that like terms compare equal
+#[derive(Clone, Debug)]
+struct Term {
+    coefficient: usize,
+    variables: Vec<PhysicalExpression> // the spins and binaries multiplied, sorted by how they display
+}
+
+
+// simplifies an expression, returning none if it turns out to be zero
+pub fn simplify(expression:&PhysicalExpression) -> Option<PhysicalExpression> {
+    let terms = collect_terms(expand(expression));
+
+    // each term is rebuilt with its coefficient first, unless the coefficient is one
+    let mut simplified:Option<PhysicalExpression> = None;
+    for term in terms {
+        let mut factors = term.variables.into_iter();
+        let product = match (term.coefficient, factors.next()) {
+            (coefficient, None) => PhysicalExpression::num(coefficient),
+            (1, Some(first)) => first,
+            (coefficient, Some(first)) => PhysicalExpression::product(PhysicalExpression::num(coefficient), first)
+        };
+        let product = factors.fold(product, PhysicalExpression::product);
+        simplified = match simplified {
+            Some(sum) => Some(PhysicalExpression::sum(sum, product)),
+            None => Some(product)
+        };
+    }
+    simplified
+}
+
+
+// expands an expression into a sum of terms, distributing products over sums
+fn expand(expression:&PhysicalExpression) -> Vec<Term> {
+    match *expression {
+        PhysicalExpression::Add { ref operand_one, ref operand_two } => {
+            let mut terms = expand(operand_one);
+            terms.extend(expand(operand_two));
+            terms
+        }
+        PhysicalExpression::Mul { ref operand_one, ref operand_two } => {
+            let rhs = expand(operand_two);
+            let mut terms:Vec<Term> = Vec::new();
+            for lhs in expand(operand_one) {
+                for rhs in rhs.iter() {
+                    let mut variables = lhs.variables.clone();
+                    variables.extend(rhs.variables.iter().cloned());
+                    terms.push(Term { coefficient: lhs.coefficient.wrapping_mul(rhs.coefficient), variables });
+                }
+            }
+            terms
+        }
+        PhysicalExpression::Num { val } => vec![Term { coefficient: val, variables: Vec::new() }],
+        ref variable => vec![Term { coefficient: 1, variables: vec![variable.clone()] }]
+    }
+}
+
+
+// adds up the coefficients of terms over the same variables and drops the terms that come to zero;
+// the constant term comes first, followed by the others from the lowest to the highest degree
+fn collect_terms(terms:Vec<Term>) -> Vec<Term> {
+    let mut collected:Vec<Term> = Vec::new();
+    let mut keys:HashMap<String, usize> = HashMap::new();
+    for mut term in terms {
+        term.variables.sort_by_key(|variable| variable.to_string());
+        let key:Vec<String> = term.variables.iter().map(|variable| variable.to_string()).collect();
+        let key = key.join(" * ");
+        match keys.get(&key) {
+            Some(index) => {
+                let coefficient = collected[*index].coefficient.wrapping_add(term.coefficient);
+                collected[*index].coefficient = coefficient;
+            }
+            None => {
+                keys.insert(key, collected.len());
+                collected.push(term);
+            }
+        }
+    }
+    collected.retain(|term| term.coefficient != 0);
+    collected.sort_by_key(|term| term.variables.len());
+    collected
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spin(val:bool) -> PhysicalExpression {
+        PhysicalExpression::spin(val)
+    }
+
+    fn num(val:usize) -> PhysicalExpression {
+        PhysicalExpression::num(val)
+    }
+
+    #[test]
+    fn simplify() {
+
+        // constants are folded and like terms collected across nested sums
+        let expression = PhysicalExpression::sum(
+            PhysicalExpression::sum(num(2), PhysicalExpression::product(num(3), spin(true))),
+            PhysicalExpression::sum(PhysicalExpression::product(spin(true), num(4)), num(5))
+        );
+        assert_eq!(super::simplify(&expression).unwrap().to_string(), "(7 + 7 * s+)");
+
+        // products distribute over sums, and variables commute
+        let expression = PhysicalExpression::product(
+            PhysicalExpression::sum(spin(true), PhysicalExpression::binary(true)),
+            PhysicalExpression::sum(spin(false), num(0))
+        );
+        let expression = PhysicalExpression::sum(expression, PhysicalExpression::product(spin(false), spin(true)));
+        assert_eq!(super::simplify(&expression).unwrap().to_string(), "((2 * s+) * s- + b1 * s-)");
+
+        // terms that come to zero are dropped
+        let expression = PhysicalExpression::product(num(0), PhysicalExpression::sum(spin(true), num(1)));
+        assert!(super::simplify(&expression).is_none());
+    }
+}
@@ -0,0 +1,196 @@
+//! # Snapshot
+//! A frozen record of what a mapping found, for the consumers that only read the results. The mapper keeps
+//! changing as modules are mapped, lowered and mapped again, and hands its results out piecemeal; a snapshot
+//! is taken once a mapping is done and holds the expanded tree, with the subtrees the mapper kept once in its
+//! arena copied back in, along with the call graph, the warnings and the metrics of the mapping. Nothing in it
+//! can be changed, so it is handed out behind an Arc and shared across threads as it is
+
+use std::collections::HashMap;
+use callgraph::CallGraph;
+use parallelize::{Mapper, MapperWarning, Node};
+use ids::NodeId;
+use profile::Profiler;
+use support::OperatorHistogram;
+
+
+/// An analysis snapshot holds the results of a mapping, read-only
+#[derive(Clone, Debug)]
+pub struct AnalysisSnapshot {
+    tree: HashMap<NodeId, Node>, // the expanded top-level nodes, by function index, with no subtree left in the arena
+    call_graph: CallGraph, // the calls between the module's functions
+    warnings: Vec<MapperWarning>, // the warnings raised by the mapping, in order
+    profile: Profiler, // the time spent on each node
+    histogram: OperatorHistogram // the operator counts of the module's functions, by category
+}
+
+
+impl AnalysisSnapshot {
+    // takes a snapshot of the mapper's results for the tree its last mapping returned
+    pub fn new(mapper:&Mapper, tree:&HashMap<NodeId, Node>) -> AnalysisSnapshot {
+        AnalysisSnapshot {
+            tree: tree.iter().map(|(index, node)| (*index, mapper.materialize_tree(node))).collect(),
+            call_graph: mapper.call_graph(),
+            warnings: mapper.get_warnings(),
+            profile: mapper.profile(),
+            histogram: mapper.module_histogram()
+        }
+    }
+
+    // returns the expanded top-level nodes, by function index
+    pub fn nodes(&self) -> &HashMap<NodeId, Node> {
+        &self.tree
+    }
+
+    // returns the expanded node of a function, if the mapping expanded it
+    pub fn node(&self, index:NodeId) -> Option<&Node> {
+        self.tree.get(&index)
+    }
+
+    // returns the calls between the module's functions
+    pub fn call_graph(&self) -> &CallGraph {
+        &self.call_graph
+    }
+
+    // returns the warnings raised by the mapping, in order
+    pub fn warnings(&self) -> &[MapperWarning] {
+        &self.warnings
+    }
+
+    // returns the time spent on each node
+    pub fn profile(&self) -> &Profiler {
+        &self.profile
+    }
+
+    // returns the operator counts of the module's functions, by category
+    pub fn histogram(&self) -> &OperatorHistogram {
+        &self.histogram
+    }
+
+    // describes the tree, the call graph and the warnings as a JSON object, which is the same for every mapping
+    // of a module, since consumers cache what they build from it by its text
+    pub fn to_json(&self) -> String {
+        let warnings:Vec<String> = self.warnings.iter().map(|warning| {
+            let location = warning.location.map_or("null".to_string(), |location| location.to_string());
+            format!("{{\"node\":{},\"location\":{},\"category\":{},\"message\":{}}}",
+                warning.node, location, json_string(&format!("{:?}", warning.category)), json_string(&warning.message))
+        }).collect();
+        format!("{{\"nodes\":{},\"call_graph\":{},\"warnings\":[{}]}}", nodes_json(&self.tree), self.call_graph.to_json(), warnings.join(","))
+    }
+}
+
+
+// describes a set of nodes as a JSON array ordered by id
+pub(crate) fn nodes_json(nodes:&HashMap<NodeId, Node>) -> String {
+    let mut ids:Vec<&NodeId> = nodes.keys().collect();
+    ids.sort();
+    let nodes:Vec<String> = ids.iter().map(|id| node_json(&nodes[*id])).collect();
+    format!("[{}]", nodes.join(","))
+}
+
+
+// describes a node and its nested children as a JSON object
+fn node_json(node:&Node) -> String {
+    // the walk describes children before their parent, which takes their descriptions off the end of the list
+    let mut described:Vec<String> = Vec::new();
+    for visit in node.walk_post_order() {
+        let node = visit.node;
+        let children = described.split_off(described.len() - node.children().len());
+        let mut calls:Vec<NodeId> = node.calls().values().cloned().collect();
+        calls.sort();
+        let calls:Vec<String> = calls.iter().map(|callee| callee.to_string()).collect();
+        described.push(format!("{{\"id\":{},\"function\":{},\"start\":{},\"end\":{},\"input_variables\":{},\"internal_variables\":{},\"constants\":{},\"operations\":{},\"calls\":[{}],\"children\":[{}]}}",
+            node.get_id(),
+            node.is_function(),
+            node.get_start(),
+            node.get_end(),
+            node.input_variables().len(),
+            node.internal_variables().len(),
+            node.constants().len(),
+            node.operations().len(),
+            calls.join(","),
+            children.join(",")));
+    }
+    described.pop().unwrap_or_default()
+}
+
+
+// quotes a string for JSON
+pub(crate) fn json_string(string:&str) -> String {
+    let mut quoted = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::thread;
+    use parallelize::{new_mapper, Mapper, SilentObserver};
+    use ids::NodeId;
+
+    #[test]
+    fn frozen() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let tree = mapper.map(include_bytes!("../tests/parallelization/zeros.wasm").to_vec());
+        let snapshot = mapper.finish(&tree);
+        let described:Vec<String> = (0..4).map(|index| snapshot.node(NodeId(index)).unwrap().describe(4)).collect();
+        let (graph, histogram, warnings) = (mapper.call_graph(), mapper.module_histogram(), mapper.get_warnings());
+
+        // mapping another module changes the mapper, but not the snapshot taken of the first
+        mapper.map(include_bytes!("../tests/parallelization/conditions.wasm").to_vec());
+        assert_ne!(mapper.call_graph(), graph);
+        assert_eq!((snapshot.nodes().len(), snapshot.call_graph(), snapshot.histogram(), snapshot.warnings()), (4, &graph, &histogram, &warnings[..]));
+        assert_eq!((0..4).map(|index| snapshot.node(NodeId(index)).unwrap().describe(4)).collect::<Vec<String>>(), described);
+
+        // threads read one snapshot without copying it
+        let readers:Vec<_> = (0..4).map(|index| {
+            let snapshot = Arc::clone(&snapshot);
+            thread::spawn(move || snapshot.node(NodeId(index)).map(|node| node.describe(4)))
+        }).collect();
+        let read:Vec<String> = readers.into_iter().map(|reader| reader.join().unwrap().unwrap()).collect();
+        assert_eq!((read, Arc::strong_count(&snapshot)), (described, 1));
+    }
+
+    #[test]
+    fn deterministic() {
+        let snapshot = |mapper:&mut Mapper, buf:&[u8]| {
+            let tree = mapper.map(buf.to_vec());
+            mapper.finish(&tree).to_json()
+        };
+
+        // every fixture maps to the same JSON on a fresh mapper and on one that mapped another module before, but
+        // for the compiled program, which is too large to map three times over
+        let mut reused = new_mapper();
+        reused.set_observer(Box::new(SilentObserver));
+        let mut fixtures:Vec<PathBuf> = fs::read_dir(format!("{}/tests/parallelization", env!("CARGO_MANIFEST_DIR"))).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "wasm"))
+            .filter(|path| !path.ends_with("chromatic.wasm"))
+            .collect();
+        fixtures.sort();
+        assert!(!fixtures.is_empty());
+        for path in fixtures {
+            let buf = fs::read(&path).unwrap();
+            let mut mapper = new_mapper();
+            mapper.set_observer(Box::new(SilentObserver));
+            let json = snapshot(&mut mapper, &buf);
+            let mut again = new_mapper();
+            again.set_observer(Box::new(SilentObserver));
+            assert_eq!(snapshot(&mut again, &buf), json, "mapping {} twice differs", path.display());
+            assert_eq!(snapshot(&mut reused, &buf), json, "mapping {} after another module differs", path.display());
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! # SSA
+//! Static single assignment form of the simulatable operations recorded for a node, which makes the
+//! data flow between operations explicit for lowering instead of relying on their positions
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use primitives::Type;
+
+
+/// Values are identified by their index in the graph
+pub type ValueId = usize;
+
+
+/// The definition enum represents how each SSA value is produced
+#[derive(Clone, Debug, PartialEq)]
+pub enum Definition {
+    Input { var_id: usize }, // one of the node's input variables
+    Internal { var_id: usize, condition: Option<ValueId> }, // one of the node's internal flow control variables, holding the condition it consumed if its producer is known
+    Const { val: usize }, // a constant known at compile time
+    Add { lhs: ValueId, rhs: ValueId },
+    Mul { lhs: ValueId, rhs: ValueId },
+    Div { lhs: ValueId, rhs: ValueId }, // the unsigned quotient
+    Rem { lhs: ValueId, rhs: ValueId }, // the unsigned remainder
+    Shr { operand: ValueId, bits: usize }, // the operand shifted right by a constant number of bits, filling in zeros
+    MulShr { operand: ValueId, multiplier: u64, shift: usize }, // the operand times a constant, shifted right before wrapping
+    Clz { operand: ValueId }, // the number of zero bits above the operand's highest set bit
+    Ctz { operand: ValueId }, // the number of zero bits below the operand's lowest set bit
+    Popcnt { operand: ValueId }, // the number of the operand's set bits
+    IsZero { operand: ValueId }, // one if the operand is zero, and zero otherwise
+    Min { lhs: ValueId, rhs: ValueId }, // the lesser float
+    Max { lhs: ValueId, rhs: ValueId }, // the greater float
+    Copysign { lhs: ValueId, rhs: ValueId }, // the first float with the second's sign
+    Abs { operand: ValueId }, // the float with its sign cleared
+    Neg { operand: ValueId }, // the float with its sign flipped
+    Shift { lhs: ValueId, rhs: ValueId, kind: ShiftKind }, // the first operand shifted or rotated by the second, modulo its width
+    Extend { operand: ValueId, bits: usize, signed: bool }, // the operand's lowest bits, sign or zero extended to its type
+    Clamp { operand: ValueId, bits: usize, signed: bool }, // the operand's integer part, saturated to a signed or unsigned range of bits
+    Reinterpret { operand: ValueId }, // the operand's bits, taken as a value of the instruction's type
+    Phi { incoming: Vec<(usize, ValueId)> }, // the value of whichever region ran, keyed by region index
+    Opaque // produced by an instruction whose semantics are not modelled
+}
+
+
+impl Definition {
+    // returns the values this definition reads
+    pub fn operands(&self) -> Vec<ValueId> {
+        match *self {
+            Definition::Add { lhs, rhs }
+            | Definition::Mul { lhs, rhs }
+            | Definition::Div { lhs, rhs }
+            | Definition::Rem { lhs, rhs }
+            | Definition::Min { lhs, rhs }
+            | Definition::Max { lhs, rhs }
+            | Definition::Copysign { lhs, rhs }
+            | Definition::Shift { lhs, rhs, .. } => vec![lhs, rhs],
+            Definition::Extend { operand, .. }
+            | Definition::Clamp { operand, .. }
+            | Definition::Reinterpret { operand }
+            | Definition::Shr { operand, .. }
+            | Definition::MulShr { operand, .. }
+            | Definition::Clz { operand }
+            | Definition::Ctz { operand }
+            | Definition::Popcnt { operand }
+            | Definition::IsZero { operand }
+            | Definition::Abs { operand }
+            | Definition::Neg { operand } => vec![operand],
+            Definition::Internal { condition: Some(condition), .. } => vec![condition],
+            Definition::Phi { ref incoming } => incoming.iter().map(|&(_, value)| value).collect(),
+            _ => Vec::new()
+        }
+    }
+
+    // returns the name of the definition's variant, e.g. "Add"
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Definition::Input { .. } => "Input",
+            Definition::Internal { .. } => "Internal",
+            Definition::Const { .. } => "Const",
+            Definition::Add { .. } => "Add",
+            Definition::Mul { .. } => "Mul",
+            Definition::Div { .. } => "Div",
+            Definition::Rem { .. } => "Rem",
+            Definition::Shr { .. } => "Shr",
+            Definition::MulShr { .. } => "MulShr",
+            Definition::Clz { .. } => "Clz",
+            Definition::Ctz { .. } => "Ctz",
+            Definition::Popcnt { .. } => "Popcnt",
+            Definition::IsZero { .. } => "IsZero",
+            Definition::Min { .. } => "Min",
+            Definition::Max { .. } => "Max",
+            Definition::Copysign { .. } => "Copysign",
+            Definition::Abs { .. } => "Abs",
+            Definition::Neg { .. } => "Neg",
+            Definition::Shift { .. } => "Shift",
+            Definition::Extend { .. } => "Extend",
+            Definition::Clamp { .. } => "Clamp",
+            Definition::Reinterpret { .. } => "Reinterpret",
+            Definition::Phi { .. } => "Phi",
+            Definition::Opaque => "Opaque"
+        }
+    }
+}
+
+
+/// A shift kind tells which way a shift moves its operand's bits and what takes the places they leave
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShiftKind {
+    Shl, // towards the high bits, filling in zeros
+    ShrS, // towards the low bits, filling in copies of the sign bit
+    ShrU, // towards the low bits, filling in zeros
+    Rotl, // towards the high bits, wrapping the bits moved out around
+    Rotr // towards the low bits, wrapping the bits moved out around
+}
+
+
+impl ShiftKind {
+    // returns whether the shift moves bits towards the high ones
+    pub fn is_left(&self) -> bool {
+        *self == ShiftKind::Shl || *self == ShiftKind::Rotl
+    }
+
+    // shifts or rotates a value of the given number of bits by an amount taken modulo that number, as WASM does
+    pub fn apply(&self, value:u64, amount:u64, bits:usize) -> u64 {
+        let mask = if bits >= 64 { u64::MAX } else { (1 << bits) - 1 };
+        let (value, by) = (value & mask, (amount % bits as u64) as u32);
+        let moved = match *self {
+            ShiftKind::Shl => value << by,
+            ShiftKind::ShrU => value >> by,
+            ShiftKind::ShrS => ((value << (64 - bits)) as i64 >> (64 - bits) >> by) as u64,
+            ShiftKind::Rotl => value << by | value.checked_shr(bits as u32 - by).unwrap_or(0),
+            ShiftKind::Rotr => value >> by | value.checked_shl(bits as u32 - by).unwrap_or(0)
+        };
+        moved & mask
+    }
+}
+
+
+// shows a shift kind by its WASM mnemonic, e.g. `shr_s`
+impl Display for ShiftKind {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let mnemonic = match *self {
+            ShiftKind::Shl => "shl",
+            ShiftKind::ShrS => "shr_s",
+            ShiftKind::ShrU => "shr_u",
+            ShiftKind::Rotl => "rotl",
+            ShiftKind::Rotr => "rotr"
+        };
+        write!(f, "{}", mnemonic)
+    }
+}
+
+
+/// An instruction defines a single typed value
+#[derive(Clone, Debug)]
+pub struct Instruction {
+    pub value: ValueId, // the value defined by this instruction
+    pub ty: Type, // the value's type
+    pub location: usize, // where the defining operation was recorded in its node
+    pub region: usize, // the index of the region the instruction belongs to
+    pub definition: Definition
+}
+
+
+/// A region holds the instructions of a node or of one of its nested blocks
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub id: usize, // the id of the node or block the region was emitted from
+    pub parent: Option<usize>, // the index of the enclosing region
+    pub location: usize, // where the region starts in the enclosing region
+    pub instructions: Vec<ValueId> // the region's instructions in execution order
+}
+
+
+/// A graph holds the SSA form of a node and all of its nested blocks
+#[derive(Clone, Debug)]
+pub struct Graph {
+    id: usize, // the id of the node the graph was emitted from
+    instructions: Vec<Instruction>, // every instruction, indexed by the value it defines
+    regions: Vec<Region>, // the first region is always the node itself
+    results: Vec<ValueId> // the values the node leaves on the stack, the last one on top
+}
+
+
+impl Graph {
+    // creates an empty graph whose root region represents the given node
+    pub fn new(id:usize) -> Graph {
+        Graph {
+            id,
+            instructions: Vec::new(),
+            regions: vec![Region { id, parent: None, location: 0, instructions: Vec::new() }],
+            results: Vec::new()
+        }
+    }
+
+    // returns the id of the node the graph was emitted from
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    // registers a nested region, returning its index
+    pub fn add_region(&mut self, id:usize, parent:usize, location:usize) -> usize {
+        self.regions.push(Region { id, parent: Some(parent), location, instructions: Vec::new() });
+        self.regions.len() - 1
+    }
+
+    // defines a new value in the given region, returning its id
+    pub fn define(&mut self, region:usize, location:usize, ty:Type, definition:Definition) -> ValueId {
+        let value = self.instructions.len();
+        self.instructions.push(Instruction { value, ty, location, region, definition });
+        self.regions[region].instructions.push(value);
+        value
+    }
+
+    // returns every instruction, indexed by the value it defines
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    // returns the instruction defining the given value
+    pub fn instruction(&self, value:ValueId) -> &Instruction {
+        &self.instructions[value]
+    }
+
+    // records the values the node leaves on the stack, the last one on top
+    pub fn set_results(&mut self, results:Vec<ValueId>) {
+        self.results = results;
+    }
+
+    // returns the values the node leaves on the stack, the last one on top
+    pub fn results(&self) -> &[ValueId] {
+        &self.results
+    }
+
+    // returns the graph's regions; the first is the node itself
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    // returns the values that read the given value
+    pub fn uses(&self, value:ValueId) -> Vec<ValueId> {
+        self.instructions.iter()
+            .filter(|instruction| instruction.definition.operands().contains(&value))
+            .map(|instruction| instruction.value)
+            .collect()
+    }
+
+    // returns every value's uses at once
+    pub fn use_map(&self) -> HashMap<ValueId, Vec<ValueId>> {
+        let mut uses:HashMap<ValueId, Vec<ValueId>> = HashMap::new();
+        for instruction in self.instructions.iter() {
+            for operand in instruction.definition.operands() {
+                uses.entry(operand).or_default().push(instruction.value);
+            }
+        }
+        uses
+    }
+
+    // returns the phi nodes placed where regions join
+    pub fn phis(&self) -> Vec<&Instruction> {
+        self.instructions.iter()
+            .filter(|instruction| matches!(instruction.definition, Definition::Phi { .. }))
+            .collect()
+    }
+}
+
+
+// lists the graph's regions in order, each with the instructions it defines, e.g. `v2: I32 = Add { lhs: 0, rhs: 1 } @2`
+impl Display for Graph {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        for (index, region) in self.regions.iter().enumerate() {
+            match region.parent {
+                Some(parent) => writeln!(f, "region {} of node {}, in region {} at {}", index, region.id, parent, region.location)?,
+                None => writeln!(f, "region {} of node {}", index, region.id)?
+            }
+            for value in region.instructions.iter() {
+                let instruction = &self.instructions[*value];
+                writeln!(f, "    v{}: {:?} = {:?} @{}", instruction.value, instruction.ty, instruction.definition, instruction.location)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+//! # Structure
+//! An independent cross-check of the spans and nesting the mapper records while reading function bodies.
+//! A function node's instruction bytes are replayed with the crate's operator reader, keeping track of how
+//! deeply blocks are nested, and where the function and each of its blocks end, as well as where each
+//! block starts and the locations blocks are registered at, are compared with what the replay finds. A
+//! block starts right past the operator opening it and ends right past the end closing it, in the file; a
+//! mismatch says when it is explained by the offset being taken relative to the function body instead,
+//! or by an end deduced by adding two such offsets, which is how nested blocks' spans used to go wrong
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::{BinaryReader, Operator, Result};
+use parallelize::Node;
+use ids::BlockId;
+
+
+/// The structure issue enum represents the ways in which a recorded span
+/// or block can disagree with the replay of a function's code
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StructureIssue {
+    Start, // a block's start isn't where its first operator is
+    End, // a node's end isn't right after the end closing it
+    MissingBlock, // a block, loop, if or else was read where the node doesn't register a block
+    ExtraBlock, // the node registers a block where none was read
+    Unterminated // the code can't be read up to the end closing the node
+}
+
+
+/// The cause enum represents the known mapping bugs that explain a
+/// recorded offset differing from the one the replay found
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Cause {
+    RelativeOffset, // the offset was taken relative to the start of the function body instead of the file
+    SummedOffsets, // the end was deduced by adding the end to the start, both relative to the function body
+    Unknown // nothing known explains the difference
+}
+
+
+/// A structure diagnostic is a disagreement between what the mapper
+/// recorded for a function or one of its blocks and the replay of the
+/// function's code
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructureDiagnostic {
+    pub function: usize, // the id of the function whose code was replayed
+    pub block: Option<BlockId>, // the id of the registered block the diagnostic is about, unless it is the function
+    pub depth: usize, // how deeply the node is nested in the function, which is at 0
+    pub location: usize, // the location in the node of the operator the diagnostic is about
+    pub issue: StructureIssue,
+    pub expected: Option<usize>, // the offset in the file the replay found, for starts and ends
+    pub found: Option<usize>, // the offset in the file the mapper recorded, for starts and ends
+    pub cause: Cause
+}
+
+
+impl fmt::Display for StructureDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.block {
+            Some(block) => write!(f, "block {} at depth {} of function {}", block, self.depth, self.function)?,
+            None => write!(f, "function {}", self.function)?
+        }
+        match self.issue {
+            StructureIssue::Start | StructureIssue::End => {
+                let what = if self.issue == StructureIssue::Start { "starts" } else { "ends" };
+                write!(f, " {} at {:#x} but is recorded at {:#x}", what, self.expected.unwrap_or(0), self.found.unwrap_or(0))?;
+                match self.cause {
+                    Cause::RelativeOffset => write!(f, ", relative to the function body"),
+                    Cause::SummedOffsets => write!(f, ", the sum of offsets relative to the function body"),
+                    Cause::Unknown => Ok(())
+                }
+            }
+            StructureIssue::MissingBlock => write!(f, " opens a block at operation {} that isn't registered", self.location),
+            StructureIssue::ExtraBlock => write!(f, " registers a block at operation {} that doesn't open one", self.location),
+            StructureIssue::Unterminated => write!(f, " can't be read past operation {}", self.location)
+        }
+    }
+}
+
+
+/// A replay walks a function's code, node by node
+struct Replay<'a> {
+    function: usize, // the id of the function being replayed
+    body: usize, // where the function body starts in the file
+    blocks: &'a HashMap<BlockId, Node>, // the blocks registered by the mapper, by id
+    diagnostics: Vec<StructureDiagnostic> // the disagreements found so far
+}
+
+
+// replays the code of a function node, comparing the recorded end of the function and the starts, ends and
+// locations of its blocks with what the replay finds; the diagnostics are in the order they were found
+pub fn check_function(function:&Node, blocks:&HashMap<BlockId, Node>) -> Vec<StructureDiagnostic> {
+    let body = function.get_start();
+    let mut replay = Replay { function: function.get_id().0, body, blocks, diagnostics: Vec::new() };
+
+    // the locals declarations come first
+    let mut reader = BinaryReader::new_with_offset(function.instrs(), body);
+    if skip_locals(&mut reader).is_err() {
+        replay.report(None, 0, 0, StructureIssue::Unterminated, None);
+        return replay.diagnostics;
+    }
+    replay.replay(function, None, 0, &mut reader);
+    replay.diagnostics
+}
+
+
+impl<'a> Replay<'a> {
+    // replays a node's operators up to the end closing it, returning where that end is in the file along with
+    // where the node that read it starts, which is the else clause for an if that has one
+    fn replay(&mut self, node:&Node, block:Option<BlockId>, depth:usize, reader:&mut BinaryReader) -> Option<(usize, usize)> {
+        let start = reader.original_position();
+        if block.is_some() && node.get_start() != start {
+            let cause = self.explain(node.get_start(), start, None);
+            self.report(block, depth, 0, StructureIssue::Start, Some((start, node.get_start(), cause)));
+        }
+
+        // every block, loop, if and else counts as a single location, its operators being read by its own node
+        let registered = node.blocks();
+        let mut opened:Vec<usize> = Vec::new();
+        let mut i = 0;
+        let closed = loop {
+            i += 1;
+            let op = match reader.read_operator() {
+                Ok(op) => op,
+                Err(_) => {
+                    self.report(block, depth, i, StructureIssue::Unterminated, None);
+                    break None;
+                }
+            };
+            match op {
+                Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } | Operator::Else => {
+                    opened.push(i);
+                    let nested = match registered.get(&i).and_then(|id| self.blocks.get(id).map(|nested| (*id, nested))) {
+                        Some((id, nested)) => self.replay(nested, Some(id), depth + 1, reader),
+                        None => {
+                            self.report(block, depth, i, StructureIssue::MissingBlock, None);
+                            skip_block(reader)
+                        }
+                    };
+
+                    // an else clause closes the if along with itself
+                    if nested.is_none() || matches!(op, Operator::Else) {
+                        break nested;
+                    }
+                }
+                Operator::End => break Some((reader.original_position(), start)),
+                _ => ()
+            }
+        };
+
+        let mut extra:Vec<usize> = registered.keys().filter(|location| !opened.contains(location)).cloned().collect();
+        extra.sort();
+        for location in extra {
+            self.report(block, depth, location, StructureIssue::ExtraBlock, None);
+        }
+        if let Some((end, closer)) = closed {
+            if node.get_end() != end {
+                let cause = self.explain(node.get_end(), end, Some(closer));
+                self.report(block, depth, i, StructureIssue::End, Some((end, node.get_end(), cause)));
+            }
+        }
+        closed
+    }
+
+    // explains a recorded offset that differs from the one found, given where the node that read the end
+    // starts for an end
+    fn explain(&self, found:usize, expected:usize, closer:Option<usize>) -> Cause {
+        if found + self.body == expected {
+            return Cause::RelativeOffset;
+        }
+        match closer {
+            Some(closer) if found + 2 * self.body == expected + closer => Cause::SummedOffsets,
+            _ => Cause::Unknown
+        }
+    }
+
+    // records a diagnostic, with the offset found, the offset recorded and their cause for a start or end
+    fn report(&mut self, block:Option<BlockId>, depth:usize, location:usize, issue:StructureIssue, offsets:Option<(usize, usize, Cause)>) {
+        self.diagnostics.push(StructureDiagnostic {
+            function: self.function,
+            block,
+            depth,
+            location,
+            issue,
+            expected: offsets.map(|(expected, _, _)| expected),
+            found: offsets.map(|(_, found, _)| found),
+            cause: offsets.map_or(Cause::Unknown, |(_, _, cause)| cause)
+        });
+    }
+}
+
+
+// reads past a function body's locals declarations
+fn skip_locals(reader:&mut BinaryReader) -> Result<()> {
+    let count = reader.read_var_u32()?;
+    for _ in 0..count {
+        reader.read_var_u32()?;
+        reader.read_type()?;
+    }
+    Ok(())
+}
+
+
+// reads past the rest of a block that has no registered node, up to the end closing it, returning where that
+// end is in the file along with where the block starts
+fn skip_block(reader:&mut BinaryReader) -> Option<(usize, usize)> {
+    let start = reader.original_position();
+    let mut depth = 0;
+    loop {
+        match reader.read_operator().ok()? {
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => depth += 1,
+            Operator::End if depth == 0 => return Some((reader.original_position(), start)),
+            Operator::End => depth -= 1,
+            _ => ()
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallelize::{new_mapper, SilentObserver};
+    use ids::NodeId;
+
+    // a diagnostic's block, issue, expected and found offsets and cause, which are what the tests compare
+    type Explained = (Option<BlockId>, StructureIssue, Option<usize>, Option<usize>, Cause);
+
+    #[test]
+    fn check_function() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/blocks.wasm").to_vec());
+
+        // functions and their blocks start and end where they are recorded to, and blocks are registered where
+        // they are opened
+        assert!(mapper.get_structure_diagnostics().is_empty());
+
+        // a span taken relative to the function body, or an end summed from two such offsets, is explained
+        let sum = &nodes[&NodeId(0)];
+        let mut blocks:HashMap<BlockId, Node> = sum.blocks().iter()
+            .map(|(location, block)| (*block, sum.children()[&sum.calls()[location]].clone()))
+            .collect();
+        let block = sum.blocks()[&1];
+        let (start, end) = (blocks[&block].get_start(), blocks[&block].get_end());
+        assert_eq!((start, end), (0x32, 0x37));
+        let nested = blocks.get_mut(&block).unwrap();
+        nested.set_start(start - sum.get_start());
+        nested.set_end(end + start - 2 * sum.get_start());
+        let diagnostics:Vec<Explained> = super::check_function(sum, &blocks).iter()
+            .map(|diagnostic| (diagnostic.block, diagnostic.issue, diagnostic.expected, diagnostic.found, diagnostic.cause))
+            .collect();
+        assert_eq!(diagnostics, vec![
+            (Some(block), StructureIssue::Start, Some(0x32), Some(0x5), Cause::RelativeOffset),
+            (Some(block), StructureIssue::End, Some(0x37), Some(0xf), Cause::SummedOffsets)
+        ]);
+        assert_eq!(super::check_function(sum, &blocks)[0].to_string(), format!("block {} at depth 1 of function 0 starts at 0x32 but is recorded at 0x5, relative to the function body", block));
+
+        // a block the node doesn't register is caught where it is opened, and the replay carries on past it
+        let mut function = nodes[&NodeId(0)].clone();
+        let diagnostics:Vec<(StructureIssue, usize)> = super::check_function(&function, &HashMap::new()).iter()
+            .map(|diagnostic| (diagnostic.issue, diagnostic.location))
+            .collect();
+        assert_eq!(diagnostics, vec![(StructureIssue::MissingBlock, 1), (StructureIssue::MissingBlock, 2)]);
+        function.set_end(function.get_end() + 1);
+        let diagnostics = super::check_function(&function, &HashMap::new());
+        assert_eq!((diagnostics[2].block, diagnostics[2].issue, diagnostics[2].cause), (None, StructureIssue::End, Cause::Unknown));
+    }
+}
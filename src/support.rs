@@ -0,0 +1,475 @@
+//! # Support
+//! Which operators the mapping pipeline simulates, which it only records as structure or couplings,
+//! and which it doesn't handle at all, so that incomplete mappings can be detected instead of
+//! silently lowered. Operators are also grouped into broad categories, whose counts help triage which
+//! functions are worth lowering
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use crate::Operator;
+
+
+/// The support enum represents how the pipeline treats an operator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Support {
+    Simulatable, // recorded as an abstract operation that can be lowered
+    Coupling, // recorded as control flow or as a coupling to data outside the node, but not simulated
+    Unsupported // not recorded at all or in a form that can be simulated, which leaves the mapping of its node incomplete
+}
+
+
+/// The operator category enum groups operators by what they do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OperatorCategory {
+    Arithmetic, // constants, variable accesses and numeric operations
+    Memory, // loads, stores and memory management
+    Control, // structure, branches, calls and stack manipulation
+    Simd, // operations on 128 bit vectors
+    Atomic, // atomic memory operations and waits
+    Unsupported // anything else the pipeline doesn't handle
+}
+
+
+/// A support matrix classifies operators by name. Operators without an
+/// override are classified by what the mapper records for them
+#[derive(Clone, Debug, Default)]
+pub struct SupportMatrix {
+    overrides: HashMap<String, Support> // classifications replacing the built in ones, by operator name
+}
+
+
+impl SupportMatrix {
+    // creates a matrix with the built in classification
+    pub fn new() -> SupportMatrix {
+        SupportMatrix::default()
+    }
+
+    // overrides the classification of the operator with the given name, e.g. "I32Sub"
+    pub fn set(&mut self, name:&str, support:Support) {
+        self.overrides.insert(name.to_string(), support);
+    }
+
+    // returns how the pipeline treats an operator
+    pub fn classify(&self, op:&Operator) -> Support {
+        match self.overrides.get(&operator_name(op)) {
+            Some(support) => *support,
+            None => builtin_support(op)
+        }
+    }
+
+    // returns the category of an operator; SIMD and atomic operators fall in their own categories whether
+    // or not they are supported, while any other operator the pipeline doesn't handle is unsupported
+    pub fn categorize(&self, op:&Operator) -> OperatorCategory {
+        let name = operator_name(op);
+        if name.starts_with("V128") || ["x16", "x8", "x4", "x2"].iter().any(|lanes| name.contains(lanes)) {
+            return OperatorCategory::Simd;
+        }
+        if name.contains("Atomic") || name == "Wake" || name.ends_with("Wait") {
+            return OperatorCategory::Atomic;
+        }
+        if self.classify(op) == Support::Unsupported {
+            return OperatorCategory::Unsupported;
+        }
+        match *op {
+            Operator::Unreachable
+            | Operator::Nop
+            | Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Else
+            | Operator::End
+            | Operator::Br { .. }
+            | Operator::BrIf { .. }
+            | Operator::BrTable { .. }
+            | Operator::Return
+            | Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::Drop
+            | Operator::Select => OperatorCategory::Control,
+            _ if name.contains("Load") || name.contains("Store") || name.starts_with("Memory") || name == "DataDrop" => OperatorCategory::Memory,
+            _ => OperatorCategory::Arithmetic
+        }
+    }
+}
+
+
+/// An operator histogram counts operators by category
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OperatorHistogram {
+    pub arithmetic: usize,
+    pub memory: usize,
+    pub control: usize,
+    pub simd: usize,
+    pub atomic: usize,
+    pub unsupported: usize
+}
+
+
+impl OperatorHistogram {
+    // counts an operator of the given category
+    pub fn add(&mut self, category:OperatorCategory) {
+        *self.count_mut(category) += 1;
+    }
+
+    // returns the number of operators of the given category
+    pub fn get(&self, category:OperatorCategory) -> usize {
+        match category {
+            OperatorCategory::Arithmetic => self.arithmetic,
+            OperatorCategory::Memory => self.memory,
+            OperatorCategory::Control => self.control,
+            OperatorCategory::Simd => self.simd,
+            OperatorCategory::Atomic => self.atomic,
+            OperatorCategory::Unsupported => self.unsupported
+        }
+    }
+
+    // adds the counts of another histogram to this one's
+    pub fn merge(&mut self, other:&OperatorHistogram) {
+        self.arithmetic += other.arithmetic;
+        self.memory += other.memory;
+        self.control += other.control;
+        self.simd += other.simd;
+        self.atomic += other.atomic;
+        self.unsupported += other.unsupported;
+    }
+
+    // returns the number of operators counted
+    pub fn total(&self) -> usize {
+        self.arithmetic + self.memory + self.control + self.simd + self.atomic + self.unsupported
+    }
+
+    // returns the count of the given category for updating
+    fn count_mut(&mut self, category:OperatorCategory) -> &mut usize {
+        match category {
+            OperatorCategory::Arithmetic => &mut self.arithmetic,
+            OperatorCategory::Memory => &mut self.memory,
+            OperatorCategory::Control => &mut self.control,
+            OperatorCategory::Simd => &mut self.simd,
+            OperatorCategory::Atomic => &mut self.atomic,
+            OperatorCategory::Unsupported => &mut self.unsupported
+        }
+    }
+}
+
+
+impl fmt::Display for OperatorHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} operators: {} arithmetic, {} memory, {} control, {} simd, {} atomic, {} unsupported",
+            self.total(), self.arithmetic, self.memory, self.control, self.simd, self.atomic, self.unsupported)
+    }
+}
+
+
+// returns how the mapper treats an operator unless told otherwise
+fn builtin_support(op:&Operator) -> Support {
+    match *op {
+        Operator::If { .. }
+        | Operator::GetLocal { .. }
+        | Operator::I32Const { .. }
+        | Operator::I64Const { .. }
+        | Operator::I32Add
+        | Operator::I32Mul
+        | Operator::I64Add
+        | Operator::I32DivU
+        | Operator::I32RemU
+        | Operator::I64DivU
+        | Operator::I64RemU
+        | Operator::I32Eqz
+        | Operator::I64Eqz
+        | Operator::I32Clz
+        | Operator::I32Ctz
+        | Operator::I32Popcnt
+        | Operator::I64Clz
+        | Operator::I64Ctz
+        | Operator::I64Popcnt
+        | Operator::I32Shl
+        | Operator::I32ShrS
+        | Operator::I32ShrU
+        | Operator::I32Rotl
+        | Operator::I32Rotr
+        | Operator::I64Shl
+        | Operator::I64ShrS
+        | Operator::I64ShrU
+        | Operator::I64Rotl
+        | Operator::I64Rotr
+        | Operator::F32Abs
+        | Operator::F32Neg
+        | Operator::F32Copysign
+        | Operator::F32Min
+        | Operator::F32Max
+        | Operator::F64Abs
+        | Operator::F64Neg
+        | Operator::F64Copysign
+        | Operator::F64Min
+        | Operator::F64Max
+        | Operator::I32WrapI64
+        | Operator::I64ExtendSI32
+        | Operator::I64ExtendUI32
+        | Operator::I32Extend8S
+        | Operator::I32Extend16S
+        | Operator::I64Extend8S
+        | Operator::I64Extend16S
+        | Operator::I64Extend32S
+        | Operator::I32TruncSSatF32
+        | Operator::I32TruncUSatF32
+        | Operator::I32TruncSSatF64
+        | Operator::I32TruncUSatF64
+        | Operator::I64TruncSSatF32
+        | Operator::I64TruncUSatF32
+        | Operator::I64TruncSSatF64
+        | Operator::I64TruncUSatF64
+        | Operator::I32ReinterpretF32
+        | Operator::I64ReinterpretF64
+        | Operator::F32ReinterpretI32
+        | Operator::F64ReinterpretI64 => Support::Simulatable,
+        Operator::Nop
+        | Operator::Block { .. }
+        | Operator::Loop { .. }
+        | Operator::Else
+        | Operator::End
+        | Operator::Unreachable
+        | Operator::Return
+        | Operator::Br { .. }
+        | Operator::BrIf { .. }
+        | Operator::BrTable { .. }
+        | Operator::Call { .. }
+        | Operator::CallIndirect { .. }
+        | Operator::SetLocal { .. }
+        | Operator::TeeLocal { .. }
+        | Operator::GetGlobal { .. }
+        | Operator::SetGlobal { .. }
+        | Operator::F32Const { .. }
+        | Operator::F64Const { .. }
+        | Operator::F32Load { .. }
+        | Operator::F64Load { .. }
+        | Operator::I32Load { .. }
+        | Operator::I32Load8S { .. }
+        | Operator::I32Load8U { .. }
+        | Operator::I32Load16S { .. }
+        | Operator::I32Load16U { .. }
+        | Operator::I32AtomicLoad { .. }
+        | Operator::I32AtomicLoad8U { .. }
+        | Operator::I32AtomicLoad16U { .. }
+        | Operator::I64Load { .. }
+        | Operator::I64Load8S { .. }
+        | Operator::I64Load8U { .. }
+        | Operator::I64Load16S { .. }
+        | Operator::I64Load16U { .. }
+        | Operator::I64Load32S { .. }
+        | Operator::I64Load32U { .. }
+        | Operator::I64AtomicLoad { .. }
+        | Operator::I64AtomicLoad8U { .. }
+        | Operator::I64AtomicLoad16U { .. }
+        | Operator::I64AtomicLoad32U { .. }
+        | Operator::F32Store { .. }
+        | Operator::F64Store { .. }
+        | Operator::I32Store { .. }
+        | Operator::I32Store8 { .. }
+        | Operator::I32Store16 { .. }
+        | Operator::I32AtomicStore { .. }
+        | Operator::I32AtomicStore8 { .. }
+        | Operator::I32AtomicStore16 { .. }
+        | Operator::I64Store { .. }
+        | Operator::I64Store8 { .. }
+        | Operator::I64Store16 { .. }
+        | Operator::I64Store32 { .. }
+        | Operator::I64AtomicStore { .. }
+        | Operator::I64AtomicStore8 { .. }
+        | Operator::I64AtomicStore16 { .. }
+        | Operator::I64AtomicStore32 { .. }
+        | Operator::I32AtomicRmwAdd { .. }
+        | Operator::I32AtomicRmwSub { .. }
+        | Operator::I32AtomicRmwAnd { .. }
+        | Operator::I32AtomicRmwOr { .. }
+        | Operator::I32AtomicRmwXor { .. }
+        | Operator::I32AtomicRmwXchg { .. }
+        | Operator::I32AtomicRmwCmpxchg { .. }
+        | Operator::I32AtomicRmw8UAdd { .. }
+        | Operator::I32AtomicRmw8USub { .. }
+        | Operator::I32AtomicRmw8UAnd { .. }
+        | Operator::I32AtomicRmw8UOr { .. }
+        | Operator::I32AtomicRmw8UXor { .. }
+        | Operator::I32AtomicRmw8UXchg { .. }
+        | Operator::I32AtomicRmw8UCmpxchg { .. }
+        | Operator::I32AtomicRmw16UAdd { .. }
+        | Operator::I32AtomicRmw16USub { .. }
+        | Operator::I32AtomicRmw16UAnd { .. }
+        | Operator::I32AtomicRmw16UOr { .. }
+        | Operator::I32AtomicRmw16UXor { .. }
+        | Operator::I32AtomicRmw16UXchg { .. }
+        | Operator::I32AtomicRmw16UCmpxchg { .. }
+        | Operator::I64AtomicRmwAdd { .. }
+        | Operator::I64AtomicRmwSub { .. }
+        | Operator::I64AtomicRmwAnd { .. }
+        | Operator::I64AtomicRmwOr { .. }
+        | Operator::I64AtomicRmwXor { .. }
+        | Operator::I64AtomicRmwXchg { .. }
+        | Operator::I64AtomicRmwCmpxchg { .. }
+        | Operator::I64AtomicRmw8UAdd { .. }
+        | Operator::I64AtomicRmw8USub { .. }
+        | Operator::I64AtomicRmw8UAnd { .. }
+        | Operator::I64AtomicRmw8UOr { .. }
+        | Operator::I64AtomicRmw8UXor { .. }
+        | Operator::I64AtomicRmw8UXchg { .. }
+        | Operator::I64AtomicRmw8UCmpxchg { .. }
+        | Operator::I64AtomicRmw16UAdd { .. }
+        | Operator::I64AtomicRmw16USub { .. }
+        | Operator::I64AtomicRmw16UAnd { .. }
+        | Operator::I64AtomicRmw16UOr { .. }
+        | Operator::I64AtomicRmw16UXor { .. }
+        | Operator::I64AtomicRmw16UXchg { .. }
+        | Operator::I64AtomicRmw16UCmpxchg { .. }
+        | Operator::I64AtomicRmw32UAdd { .. }
+        | Operator::I64AtomicRmw32USub { .. }
+        | Operator::I64AtomicRmw32UAnd { .. }
+        | Operator::I64AtomicRmw32UOr { .. }
+        | Operator::I64AtomicRmw32UXor { .. }
+        | Operator::I64AtomicRmw32UXchg { .. }
+        | Operator::I64AtomicRmw32UCmpxchg { .. }
+        | Operator::Wake { .. }
+        | Operator::I32Wait { .. }
+        | Operator::I64Wait { .. }
+        | Operator::MemorySize { .. }
+        | Operator::MemoryInit { .. }
+        | Operator::MemoryCopy
+        | Operator::MemoryFill
+        | Operator::MemoryGrow { .. }
+        | Operator::V128Load { .. }
+        | Operator::V128Store { .. }
+        | Operator::V128Const { .. }
+        | Operator::V8x16Shuffle { .. }
+        | Operator::I8x16Splat
+        | Operator::I16x8Splat
+        | Operator::I32x4Splat
+        | Operator::I64x2Splat
+        | Operator::F32x4Splat
+        | Operator::F64x2Splat
+        | Operator::I8x16ExtractLaneS { .. }
+        | Operator::I8x16ExtractLaneU { .. }
+        | Operator::I16x8ExtractLaneS { .. }
+        | Operator::I16x8ExtractLaneU { .. }
+        | Operator::I32x4ExtractLane { .. }
+        | Operator::I64x2ExtractLane { .. }
+        | Operator::F32x4ExtractLane { .. }
+        | Operator::F64x2ExtractLane { .. }
+        | Operator::I8x16ReplaceLane { .. }
+        | Operator::I16x8ReplaceLane { .. }
+        | Operator::I32x4ReplaceLane { .. }
+        | Operator::I64x2ReplaceLane { .. }
+        | Operator::F32x4ReplaceLane { .. }
+        | Operator::F64x2ReplaceLane { .. } => Support::Coupling,
+
+        // lane arithmetic is recorded lane by lane, but lanes are neither lowered nor interpreted, so the nodes
+        // using it can't be simulated
+        _ => Support::Unsupported
+    }
+}
+
+
+// returns the name of an operator without its immediates, e.g. "GetLocal" for local.get
+pub fn operator_name(op:&Operator) -> String {
+    let description = format!("{:?}", op);
+    match description.find([' ', '{', '(']) {
+        Some(end) => description[..end].to_string(),
+        None => description
+    }
+}
+
+
+/// An unsupported operator is an occurrence of an operator that
+/// the mapper could not record
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnsupportedOperator {
+    pub operator: String, // the operator's name
+    pub offset: usize // where the operator starts in the WASM file
+}
+
+
+/// The unsupported operators error is returned by a strict mapper
+/// when a module uses operators it can't record
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnsupportedOperatorsError {
+    pub operators: Vec<UnsupportedOperator> // every unsupported occurrence in file order
+}
+
+
+impl Error for UnsupportedOperatorsError {}
+
+
+impl fmt::Display for UnsupportedOperatorsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} unsupported operators:", self.operators.len())?;
+        for unsupported in self.operators.iter() {
+            write!(f, " {} (at offset {:#x})", unsupported.operator, unsupported.offset)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryImmediate;
+
+    #[test]
+    fn classify() {
+        let mut matrix = SupportMatrix::new();
+        assert_eq!(matrix.classify(&Operator::I32Add), Support::Simulatable);
+        assert_eq!(matrix.classify(&Operator::Call { function_index: 0 }), Support::Coupling);
+        assert_eq!(matrix.classify(&Operator::I32Sub), Support::Unsupported);
+        assert_eq!(matrix.classify(&Operator::I32x4Mul), Support::Unsupported);
+
+        // atomic updates, waits and wakes are recorded as couplings to the memory they synchronize through
+        let memarg = || MemoryImmediate { flags: 2, offset: 0 };
+        assert_eq!(matrix.classify(&Operator::I64AtomicRmw8UCmpxchg { memarg: memarg() }), Support::Coupling);
+        assert_eq!(matrix.classify(&Operator::I32Wait { memarg: memarg() }), Support::Coupling);
+        assert_eq!(matrix.classify(&Operator::Wake { memarg: memarg() }), Support::Coupling);
+
+        // overrides replace the built in classification
+        matrix.set("I32Sub", Support::Coupling);
+        matrix.set("Call", Support::Unsupported);
+        assert_eq!(matrix.classify(&Operator::I32Sub), Support::Coupling);
+        assert_eq!(matrix.classify(&Operator::Call { function_index: 0 }), Support::Unsupported);
+    }
+
+    #[test]
+    fn categorize() {
+        let matrix = SupportMatrix::new();
+        let memarg = || MemoryImmediate { flags: 2, offset: 0 };
+        let mut histogram = OperatorHistogram::default();
+        for op in [
+            Operator::I32Add,
+            Operator::GetLocal { local_index: 0 },
+            Operator::I32Load { memarg: memarg() },
+            Operator::BrIf { relative_depth: 0 },
+            Operator::I32x4Add,
+            Operator::V128Load { memarg: memarg() },
+            Operator::I32AtomicRmwAdd { memarg: memarg() },
+            Operator::I32Wait { memarg: memarg() },
+            Operator::I32Sub
+        ].iter() {
+            histogram.add(matrix.categorize(op));
+        }
+        assert_eq!(histogram, OperatorHistogram { arithmetic: 2, memory: 1, control: 1, simd: 2, atomic: 2, unsupported: 1 });
+        assert_eq!(histogram.to_string(), "9 operators: 2 arithmetic, 1 memory, 1 control, 2 simd, 2 atomic, 1 unsupported");
+    }
+
+    #[test]
+    fn operator_name() {
+        assert_eq!(super::operator_name(&Operator::Drop), "Drop");
+        assert_eq!(super::operator_name(&Operator::GetLocal { local_index: 3 }), "GetLocal");
+    }
+
+    #[test]
+    fn display() {
+        let error = UnsupportedOperatorsError {
+            operators: vec![
+                UnsupportedOperator { operator: "Drop".to_string(), offset: 0x34 },
+                UnsupportedOperator { operator: "I32Sub".to_string(), offset: 0x41 }
+            ]
+        };
+        assert_eq!(error.to_string(), "2 unsupported operators: Drop (at offset 0x34) I32Sub (at offset 0x41)");
+    }
+}
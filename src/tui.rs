@@ -0,0 +1,343 @@
+//! # TUI
+//! A terminal interface for exploring an expanded node tree, as an alternative to answering the mapper's
+//! prompts one by one. The tree is shown on the left, where nodes can be expanded and collapsed and marked
+//! for lowering, and the couplings and operations of the selected node on the right. Once the marked nodes
+//! are confirmed, they are lowered in a batch; nested nodes are only lowered when marked themselves
+
+extern crate ratatui;
+
+use std::io;
+use std::collections::{HashMap, HashSet};
+use self::ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use self::ratatui::layout::{Constraint as Size, Direction, Layout};
+use self::ratatui::style::{Modifier, Style};
+use self::ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use self::ratatui::{DefaultTerminal, Frame};
+use parallelize::{Constraint, Decision, MapperObserver, Node};
+use ids::NodeId;
+
+
+/// A row is a node as listed in the tree
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub path: Vec<NodeId>, // the ids of the node and its ancestors, from the top-level function down
+    pub function: bool, // whether the node is a function rather than a block
+    pub expandable: bool, // whether the node has children
+    pub expanded: bool, // whether the node's children are listed
+    pub marked: bool // whether the node is marked for lowering
+}
+
+
+impl Row {
+    // returns the id of the node the row lists
+    pub fn id(&self) -> NodeId {
+        self.path[self.path.len() - 1]
+    }
+
+    // describes the row as it is listed, indented by its depth
+    pub fn label(&self) -> String {
+        let toggle = match (self.expandable, self.expanded) {
+            (true, true) => "-",
+            (true, false) => "+",
+            (false, _) => " "
+        };
+        let mark = if self.marked { "[x]" } else { "[ ]" };
+        let kind = if self.function { "function" } else { "block" };
+        format!("{}{} {} {} {}", "  ".repeat(self.path.len() - 1), toggle, mark, kind, self.id())
+    }
+}
+
+
+/// The outcome enum represents how exploring the tree ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Lower, // the marked nodes should be lowered
+    Quit // nothing should be lowered
+}
+
+
+/// The explorer holds the state of the interface, which can be driven
+/// by key presses without a terminal
+pub struct Explorer {
+    tree: HashMap<NodeId, Node>, // the top-level functions of the expanded tree
+    expanded: HashSet<Vec<NodeId>>, // the paths of the nodes whose children are listed
+    marked: HashSet<Vec<NodeId>>, // the paths of the nodes marked for lowering
+    cursor: usize // the index of the selected row
+}
+
+
+impl Explorer {
+    // creates an explorer over an expanded node tree, with every function collapsed
+    pub fn new(tree:HashMap<NodeId, Node>) -> Explorer {
+        Explorer { tree, expanded: HashSet::new(), marked: HashSet::new(), cursor: 0 }
+    }
+
+    // returns the node at the end of a path of ids
+    pub fn node(&self, path:&[NodeId]) -> Option<Node> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.tree.get(first)?.clone();
+        for id in rest {
+            node = node.get_child(*id)?;
+        }
+        Some(node)
+    }
+
+    // returns the listed rows, with functions and children in order of id
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        let mut functions:Vec<&NodeId> = self.tree.keys().collect();
+        functions.sort();
+        for index in functions {
+            self.rows_helper(&self.tree[index], vec![*index], &mut rows);
+        }
+        rows
+    }
+
+    // lists a node's row and, if it is expanded, those of its children
+    fn rows_helper(&self, node:&Node, path:Vec<NodeId>, rows:&mut Vec<Row>) {
+        let children = node.children();
+        let expanded = self.expanded.contains(&path);
+        rows.push(Row {
+            path: path.clone(),
+            function: node.is_function(),
+            expandable: !children.is_empty(),
+            expanded,
+            marked: self.marked.contains(&path)
+        });
+        if expanded {
+            for (id, child) in children {
+                let mut child_path = path.clone();
+                child_path.push(*id);
+                self.rows_helper(child, child_path, rows);
+            }
+        }
+    }
+
+    // returns the selected row
+    pub fn selected(&self) -> Option<Row> {
+        self.rows().get(self.cursor).cloned()
+    }
+
+    // moves the selection by a number of rows, stopping at either end
+    pub fn move_cursor(&mut self, by:isize) {
+        let last = self.rows().len().saturating_sub(1) as isize;
+        self.cursor = (self.cursor as isize + by).max(0).min(last) as usize;
+    }
+
+    // lists the selected node's children
+    pub fn expand(&mut self) {
+        if let Some(row) = self.selected() {
+            if row.expandable {
+                self.expanded.insert(row.path);
+            }
+        }
+    }
+
+    // hides the selected node's children or, if they are already hidden, selects its parent
+    pub fn collapse(&mut self) {
+        let row = match self.selected() {
+            Some(row) => row,
+            None => return
+        };
+        if row.expanded {
+            self.expanded.remove(&row.path);
+        } else if row.path.len() > 1 {
+            let parent = &row.path[..row.path.len() - 1];
+            if let Some(position) = self.rows().iter().position(|row| row.path == parent) {
+                self.cursor = position;
+            }
+        }
+    }
+
+    // marks or unmarks the selected node for lowering
+    pub fn toggle_mark(&mut self) {
+        if let Some(row) = self.selected() {
+            if !self.marked.remove(&row.path) {
+                self.marked.insert(row.path);
+            }
+        }
+    }
+
+    // handles a key press, returning how exploring ended if it did
+    pub fn handle_key(&mut self, key:KeyCode) -> Option<Outcome> {
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-10),
+            KeyCode::PageDown => self.move_cursor(10),
+            KeyCode::Right | KeyCode::Char('l') => self.expand(),
+            KeyCode::Left | KeyCode::Char('h') => self.collapse(),
+            KeyCode::Char(' ') => self.toggle_mark(),
+            KeyCode::Enter => return Some(Outcome::Lower),
+            KeyCode::Esc | KeyCode::Char('q') => return Some(Outcome::Quit),
+            _ => ()
+        }
+        None
+    }
+
+    // describes the selected node's variables, couplings and operations
+    pub fn details(&self) -> Vec<String> {
+        let node = match self.selected().and_then(|row| self.node(&row.path)) {
+            Some(node) => node,
+            None => return Vec::new()
+        };
+        let mut lines = vec![format!("Node {}, {} to {}", node.get_id(), node.get_start(), node.get_end())];
+        lines.push(format!("Input variables: {}", sorted(node.input_variables())));
+        lines.push(format!("Internal variables: {}", sorted(node.internal_variables())));
+        lines.push(format!("Constants: {}", sorted(node.constants())));
+        lines.push(format!("Calls: {}", sorted(node.calls())));
+        lines.push(format!("Flow control couplings: {}", sorted(node.flow_control_couplings())));
+        lines.push(format!("Result couplings: {}", sorted(&node.get_result_couplings())));
+        lines.push(format!("Synchronizing accesses: {}", node.get_sync_couplings().len()));
+        lines.push(format!("Bulk memory operations: {}", node.get_range_couplings().len()));
+        lines.push(format!("Memory accesses: {}", node.get_memory_accesses().len()));
+        if node.is_truncated() {
+            lines.push(format!("Truncated: {}", sorted(&node.get_truncations())));
+        }
+        lines.push(String::from("Operations:"));
+        for (location, op) in node.iter_operations() {
+            lines.push(format!("  {}: {:?}", location, op));
+        }
+        lines
+    }
+
+    // returns the nodes marked for lowering, in the order they are listed
+    pub fn marked_nodes(&self) -> Vec<Node> {
+        let mut paths:Vec<&Vec<NodeId>> = self.marked.iter().collect();
+        paths.sort();
+        paths.into_iter().filter_map(|path| self.node(path)).collect()
+    }
+
+    // lowers the marked nodes, passing any warnings on to the given observer
+    pub fn lower_marked(&self, observer:&mut dyn MapperObserver) -> Vec<Constraint> {
+        let mut selection = SelectionObserver { inner: observer };
+        self.marked_nodes().iter_mut().map(|node| node.lower_with(&mut selection)).collect()
+    }
+
+    // shows the interface in the terminal until exploring ends
+    pub fn run(&mut self, terminal:&mut DefaultTerminal) -> io::Result<Outcome> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if let Some(outcome) = self.handle_key(key.code) {
+                    return Ok(outcome);
+                }
+            }
+        }
+    }
+
+    // draws the tree beside the selected node's details, above a line of help
+    fn draw(&self, frame:&mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Size::Min(1), Size::Length(1)])
+            .split(frame.area());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Size::Percentage(40), Size::Percentage(60)])
+            .split(rows[0]);
+
+        let items:Vec<ListItem> = self.rows().iter().map(|row| ListItem::new(row.label())).collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Nodes"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default();
+        state.select(Some(self.cursor));
+        frame.render_stateful_widget(list, columns[0], &mut state);
+
+        let details = Paragraph::new(self.details().join("\n"))
+            .block(Block::default().borders(Borders::ALL).title("Details"));
+        frame.render_widget(details, columns[1]);
+
+        let help = "up/down select, right/left expand/collapse, space mark, enter lower marked, q quit";
+        frame.render_widget(Paragraph::new(help), rows[1]);
+    }
+}
+
+
+/// The selection observer lowers the nodes it is given without asking,
+/// leaving their nested nodes to be marked separately
+struct SelectionObserver<'a> {
+    inner: &'a mut dyn MapperObserver // receives the warnings and progress raised while lowering
+}
+
+
+impl<'a> MapperObserver for SelectionObserver<'a> {
+    fn on_decision_needed(&mut self, decision:&Decision) -> bool {
+        !matches!(*decision, Decision::LowerNested { .. })
+    }
+
+    fn on_warning(&mut self, message:&str) {
+        self.inner.on_warning(message);
+    }
+
+    fn on_progress(&mut self, message:&str) {
+        self.inner.on_progress(message);
+    }
+}
+
+
+// formats a map's entries in order of key
+fn sorted<T:std::fmt::Debug>(map:&HashMap<usize, T>) -> String {
+    let mut entries:Vec<(&usize, &T)> = map.iter().collect();
+    entries.sort_by_key(|entry| entry.0);
+    let entries:Vec<String> = entries.iter().map(|(key, value)| format!("{}: {:?}", key, value)).collect();
+    format!("{{{}}}", entries.join(", "))
+}
+
+
+// explores an expanded node tree in the terminal and lowers the nodes marked there, if the user asks to;
+// warnings raised while lowering are passed on to the given observer
+pub fn explore(tree:HashMap<NodeId, Node>, observer:&mut dyn MapperObserver) -> io::Result<Vec<Constraint>> {
+    let mut explorer = Explorer::new(tree);
+    let mut terminal = ratatui::init();
+    let outcome = explorer.run(&mut terminal);
+    ratatui::restore();
+    match outcome? {
+        Outcome::Lower => Ok(explorer.lower_marked(observer)),
+        Outcome::Quit => Ok(Vec::new())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallelize::{new_mapper, SilentObserver};
+
+    #[test]
+    fn explorer() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/diamond.wasm").to_vec());
+        let mut explorer = Explorer::new(nodes);
+
+        // functions start collapsed, in order
+        let rows = explorer.rows();
+        assert_eq!(rows.iter().map(|row| row.id().0).collect::<Vec<usize>>(), vec![0, 1, 2, 3]);
+        assert!(!rows[0].expandable && rows[3].expandable);
+
+        // expanding top lists left and right beneath it, and collapsing from a child returns to it
+        explorer.move_cursor(10);
+        assert_eq!(explorer.selected().unwrap().id(), NodeId(3));
+        explorer.handle_key(KeyCode::Right);
+        let rows = explorer.rows();
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[4].path, vec![NodeId(3), NodeId(1)]);
+        assert_eq!(rows[4].label(), "  + [ ] function 1");
+        explorer.handle_key(KeyCode::Down);
+        explorer.handle_key(KeyCode::Char(' '));
+        assert!(explorer.details()[0].starts_with("Node 1"));
+        explorer.handle_key(KeyCode::Left);
+        assert_eq!(explorer.selected().unwrap().id(), NodeId(3));
+
+        // the marked node is lowered once confirmed
+        assert_eq!(explorer.handle_key(KeyCode::Enter), Some(Outcome::Lower));
+        let constraints = explorer.lower_marked(&mut SilentObserver);
+        assert_eq!(constraints.iter().map(|constraint| constraint.get_id()).collect::<Vec<usize>>(), vec![1]);
+        assert_eq!(explorer.handle_key(KeyCode::Char('q')), Some(Outcome::Quit));
+    }
+}
@@ -0,0 +1,149 @@
+//! # Typecheck
+//! Checks that a node's recorded operations are well typed against the abstract stack, so that a
+//! mapping bug shows up as an error naming the operation and the types involved instead of as a panic
+//! while lowering. Constants and input variables have the types they were registered with, and every
+//! arithmetic operation needs two operands of its own type. Operands produced before an unrecorded
+//! instruction or a nested block are unknown, so they are taken to be of whatever type is needed
+
+use std::fmt;
+use primitives::Type;
+use parallelize::{AbstractExpression, Node};
+
+
+/// A type error is an operation whose operand doesn't have the type the
+/// operation needs, or is missing altogether
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeError {
+    pub node: usize, // the id of the node the operation belongs to
+    pub location: usize, // the location of the operation
+    pub expected: Type, // the type the operation needs
+    pub found: Option<Type> // the operand's type, or none if the stack was empty
+}
+
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.found {
+            Some(found) => write!(f, "node {} at operation {}: expected a {:?} operand, found {:?}", self.node, self.location, self.expected, found),
+            None => write!(f, "node {} at operation {}: expected a {:?} operand, found none", self.node, self.location, self.expected)
+        }
+    }
+}
+
+
+// checks a node's operations against the abstract stack, returning every type error in order
+pub fn check_node(node:&Node) -> Vec<TypeError> {
+    let operations = node.operations();
+    let constants = node.constants();
+    let input_variables = node.input_variables();
+    let internal_variables = node.internal_variables();
+    let blocks = node.blocks();
+    let calls = node.calls();
+
+    let locations = node.operation_locations();
+
+    // the stack starts out empty, and a stack whose base is unknown yields unknown operands once emptied
+    let mut stack:Vec<Option<Type>> = Vec::new();
+    let mut unknown_base = false;
+    let mut types:Vec<(usize, Option<Type>)> = Vec::new();
+    let mut errors:Vec<TypeError> = Vec::new();
+    let mut previous = 0;
+
+    for i in locations {
+        if !node.is_contiguous(Some(previous), i) || (previous..i).any(|j| blocks.contains_key(&j) || calls.contains_key(&j)) {
+            stack.clear();
+            unknown_base = true;
+        }
+        previous = i;
+
+        let mut pop = |expected:Type, stack:&mut Vec<Option<Type>>| {
+            match stack.pop() {
+                Some(Some(found)) if found != expected => errors.push(TypeError { node: node.get_id().0, location: i, expected, found: Some(found) }),
+                None if !unknown_base => errors.push(TypeError { node: node.get_id().0, location: i, expected, found: None }),
+                _ => ()
+            }
+        };
+
+        let ty = match operations[&i] {
+            AbstractExpression::Spin { id } if id == i && internal_variables.contains_key(&i) => {
+
+                // an if consumes its condition
+                pop(Type::I32, &mut stack);
+                continue;
+            }
+            AbstractExpression::Spin { id } => input_variables.get(&id).cloned(),
+            AbstractExpression::Num { .. } => Some(constants.get(&i).cloned().unwrap_or(Type::I32)),
+            AbstractExpression::Add { ty } | AbstractExpression::Mul { ty } | AbstractExpression::DivU { ty } | AbstractExpression::RemU { ty }
+            | AbstractExpression::Min { ty } | AbstractExpression::Max { ty } | AbstractExpression::Copysign { ty }
+            | AbstractExpression::Shift { ty, .. } => {
+                pop(ty, &mut stack);
+                pop(ty, &mut stack);
+                Some(ty)
+            }
+            AbstractExpression::Reuse { location } => types.iter().find(|&&(j, _)| j == location).and_then(|&(_, ty)| ty),
+            AbstractExpression::Load { id, .. } => {
+
+                // a load consumes its address
+                pop(Type::I32, &mut stack);
+                input_variables.get(&id).cloned()
+            }
+            AbstractExpression::ShrU { ty, .. } | AbstractExpression::MulShr { ty, .. }
+            | AbstractExpression::Clz { ty } | AbstractExpression::Ctz { ty } | AbstractExpression::Popcnt { ty }
+            | AbstractExpression::Abs { ty } | AbstractExpression::Neg { ty } => {
+                pop(ty, &mut stack);
+                Some(ty)
+            }
+            AbstractExpression::IsZero { ty } => {
+                pop(ty, &mut stack);
+                Some(Type::I32)
+            }
+            AbstractExpression::Extend { from, to, .. }
+            | AbstractExpression::Truncate { from, to, .. }
+            | AbstractExpression::Reinterpret { from, to } => {
+                pop(from, &mut stack);
+                Some(to)
+            }
+        };
+        types.push((i, ty));
+        stack.push(ty);
+    }
+    errors
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallelize::{new_mapper, SilentObserver};
+    use ids::NodeId;
+
+    #[test]
+    fn check_node() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/branches.wasm").to_vec());
+        for node in nodes.values() {
+            assert!(super::check_node(node).is_empty());
+        }
+
+        // the branch of pick's if adds its parameter and a constant
+        let pick = &nodes[&NodeId(0)];
+        let branch = pick.children()[&pick.calls()[&6]].clone();
+        assert!(super::check_node(&branch).is_empty());
+
+        // a constant of the wrong type is caught where it is used
+        let mut node = branch.clone();
+        node.add_constant(2, Type::I64);
+        let errors = super::check_node(&node);
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].location, errors[0].expected, errors[0].found), (3, Type::I32, Some(Type::I64)));
+
+        // and so is a missing operand
+        let mut node = branch;
+        node.add_operation(2, AbstractExpression::Add{ ty: Type::I32 });
+        let errors = super::check_node(&node);
+        assert_eq!(errors[0].location, 2);
+        assert_eq!(errors[0].found, None);
+        assert_eq!(errors[0].to_string(), format!("node {} at operation 2: expected a I32 operand, found none", node.get_id()));
+    }
+}
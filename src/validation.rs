@@ -0,0 +1,358 @@
+//! # Validation
+//! Roundtrip validation of the mapping: the original code of a function and the operations recorded
+//! for its node are both run on random inputs, and the values they produce at each location are
+//! compared, so that mapping and optimization bugs show up at the first operation that diverges rather
+//! than somewhere in a lowered constraint. The operations are run by the IR interpreter, while only the
+//! original function's straight-line integer code, shifts and rotations included, along with the float
+//! operations on signs and the float minimums and maximums, is run; everything after the first instruction it doesn't model is left unchecked
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::{BinaryReader, Operator, Type};
+use interp;
+use parallelize::Node;
+
+
+/// A mismatch is the first location at which the recorded operations
+/// of a node disagree with its original code
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub node: usize, // the id of the diverging node
+    pub location: usize, // the location of the first diverging operation
+    pub expected: u64, // the value the original code produced there
+    pub found: u64, // the value the recorded operations produced there
+    pub inputs: Vec<u64> // the parameters both were run with
+}
+
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "node {} diverges at operation {}: expected {:#x}, found {:#x} with inputs {:x?}",
+            self.node, self.location, self.expected, self.found, self.inputs)
+    }
+}
+
+
+/// A validation report summarizes a roundtrip validation run
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub nodes: Vec<usize>, // the ids of the validated nodes
+    pub comparisons: usize, // the number of values compared over all trials
+    pub mismatches: Vec<Mismatch> // the first mismatch of each diverging node
+}
+
+
+// runs a function node's original code and its recorded operations on random parameters for the given
+// number of trials, returning the number of values compared and the first mismatch found, if any
+pub fn validate_node(node:&Node, trials:usize, seed:u64) -> (usize, Option<Mismatch>) {
+    if !node.is_function() {
+        return (0, None);
+    }
+    let mut random = seed | 1;
+    let mut comparisons = 0;
+
+    // parameters are read through the input variables of their locals
+    let mut probe = node.clone();
+    let locals = node.get_locals();
+    let param_variables:Vec<Option<usize>> = (0..node.get_param_count()).map(|p| probe.get_local_input_variable(p)).collect();
+    let instrs = node.instrs();
+
+    for _ in 0..trials {
+        let inputs:Vec<u64> = locals[..param_variables.len()].iter().map(|ty| wrap(*ty, next_random(&mut random))).collect();
+        let expected = run_original(instrs, &locals, &inputs);
+
+        let mut variables:HashMap<usize, u64> = HashMap::new();
+        for (p, var_id) in param_variables.iter().enumerate() {
+            if let Some(var_id) = var_id {
+                variables.insert(*var_id, inputs[p]);
+            }
+        }
+        let found = interp::evaluate(node, &variables).values;
+
+        let mut locations:Vec<usize> = expected.keys().cloned().collect();
+        locations.sort();
+        for location in locations {
+            if let Some(value) = found.get(&location) {
+                comparisons += 1;
+                if *value != expected[&location] {
+                    let mismatch = Mismatch { node: node.get_id().0, location, expected: expected[&location], found: *value, inputs };
+                    return (comparisons, Some(mismatch));
+                }
+            }
+        }
+    }
+    (comparisons, None)
+}
+
+
+// runs a function's straight-line code from the start of its body, returning the value each operator
+// pushed by its location; locations are counted the way the mapper counts them
+fn run_original(instrs:&[u8], locals:&[Type], inputs:&[u64]) -> HashMap<usize, u64> {
+    let mut values:HashMap<usize, u64> = HashMap::new();
+    let mut reader = BinaryReader::new(instrs);
+
+    // the locals declarations come first, and declared locals start out as zero
+    let skipped = reader.read_var_u32().and_then(|count| {
+        for _ in 0..count {
+            reader.read_var_u32()?;
+            reader.read_type()?;
+        }
+        Ok(())
+    });
+    if skipped.is_err() {
+        return values;
+    }
+    let mut state:Vec<u64> = inputs.to_vec();
+    state.resize(locals.len(), 0);
+
+    let mut stack:Vec<u64> = Vec::new();
+    let mut i = 0;
+    while let Ok(op) = reader.read_operator() {
+        i += 1;
+        let value = match op {
+            Operator::Nop => continue,
+            Operator::I32Const { value } => value as u32 as u64,
+            Operator::I64Const { value } => value as u64,
+            Operator::GetLocal { local_index } => match state.get(local_index as usize) {
+                Some(value) => *value,
+                None => break
+            },
+            Operator::SetLocal { local_index } | Operator::TeeLocal { local_index } => {
+                let value = match stack.pop() {
+                    Some(value) => value,
+                    None => break
+                };
+                match state.get_mut(local_index as usize) {
+                    Some(local) => *local = value,
+                    None => break
+                }
+                if let Operator::TeeLocal { .. } = op {
+                    stack.push(value);
+                }
+                continue;
+            }
+            Operator::Drop => {
+                stack.pop();
+                continue;
+            }
+            Operator::I32Add | Operator::I32Mul | Operator::I64Add | Operator::I64Mul => {
+                let (rhs, lhs) = match (stack.pop(), stack.pop()) {
+                    (Some(rhs), Some(lhs)) => (rhs, lhs),
+                    _ => break
+                };
+                match op {
+                    Operator::I32Add => (lhs as u32).wrapping_add(rhs as u32) as u64,
+                    Operator::I32Mul => (lhs as u32).wrapping_mul(rhs as u32) as u64,
+                    Operator::I64Add => lhs.wrapping_add(rhs),
+                    _ => lhs.wrapping_mul(rhs)
+                }
+            }
+            Operator::I32DivU | Operator::I32RemU | Operator::I64DivU | Operator::I64RemU => {
+                let (rhs, lhs) = match (stack.pop(), stack.pop()) {
+                    (Some(rhs), Some(lhs)) => (rhs, lhs),
+                    _ => break
+                };
+
+                // dividing by zero traps
+                let value = match op {
+                    Operator::I32DivU => (lhs as u32).checked_div(rhs as u32).map(u64::from),
+                    Operator::I32RemU => (lhs as u32).checked_rem(rhs as u32).map(u64::from),
+                    Operator::I64DivU => lhs.checked_div(rhs),
+                    _ => lhs.checked_rem(rhs)
+                };
+                match value {
+                    Some(value) => value,
+                    None => break
+                }
+            }
+            Operator::I32Shl | Operator::I32ShrS | Operator::I32ShrU | Operator::I32Rotl | Operator::I32Rotr
+            | Operator::I64Shl | Operator::I64ShrS | Operator::I64ShrU | Operator::I64Rotl | Operator::I64Rotr => {
+                let (rhs, lhs) = match (stack.pop(), stack.pop()) {
+                    (Some(rhs), Some(lhs)) => (rhs, lhs),
+                    _ => break
+                };
+
+                // the amount is taken modulo the width, which Rust's wrapping shifts and rotations do too
+                match op {
+                    Operator::I32Shl => (lhs as u32).wrapping_shl(rhs as u32) as u64,
+                    Operator::I32ShrS => (lhs as i32).wrapping_shr(rhs as u32) as u32 as u64,
+                    Operator::I32ShrU => (lhs as u32).wrapping_shr(rhs as u32) as u64,
+                    Operator::I32Rotl => (lhs as u32).rotate_left(rhs as u32 % 32) as u64,
+                    Operator::I32Rotr => (lhs as u32).rotate_right(rhs as u32 % 32) as u64,
+                    Operator::I64Shl => lhs.wrapping_shl(rhs as u32),
+                    Operator::I64ShrS => (lhs as i64).wrapping_shr(rhs as u32) as u64,
+                    Operator::I64ShrU => lhs.wrapping_shr(rhs as u32),
+                    Operator::I64Rotl => lhs.rotate_left((rhs % 64) as u32),
+                    _ => lhs.rotate_right((rhs % 64) as u32)
+                }
+            }
+            Operator::I32Eqz | Operator::I64Eqz => match stack.pop() {
+                Some(operand) if matches!(op, Operator::I32Eqz) => (operand as u32 == 0) as u64,
+                Some(operand) => (operand == 0) as u64,
+                None => break
+            },
+            Operator::I32Clz | Operator::I32Ctz | Operator::I32Popcnt | Operator::I64Clz | Operator::I64Ctz | Operator::I64Popcnt => {
+                let operand = match stack.pop() {
+                    Some(operand) => operand,
+                    None => break
+                };
+                match op {
+                    Operator::I32Clz => (operand as u32).leading_zeros() as u64,
+                    Operator::I32Ctz => (operand as u32).trailing_zeros() as u64,
+                    Operator::I32Popcnt => (operand as u32).count_ones() as u64,
+                    Operator::I64Clz => operand.leading_zeros() as u64,
+                    Operator::I64Ctz => operand.trailing_zeros() as u64,
+                    _ => operand.count_ones() as u64
+                }
+            }
+            Operator::F32Abs | Operator::F32Neg | Operator::F64Abs | Operator::F64Neg => {
+                let operand = match stack.pop() {
+                    Some(operand) => operand,
+                    None => break
+                };
+                match op {
+                    Operator::F32Abs => f32::from_bits(operand as u32).abs().to_bits() as u64,
+                    Operator::F32Neg => (-f32::from_bits(operand as u32)).to_bits() as u64,
+                    Operator::F64Abs => f64::from_bits(operand).abs().to_bits(),
+                    _ => (-f64::from_bits(operand)).to_bits()
+                }
+            }
+            Operator::F32Copysign | Operator::F32Min | Operator::F32Max | Operator::F64Copysign | Operator::F64Min | Operator::F64Max => {
+                let (rhs, lhs) = match (stack.pop(), stack.pop()) {
+                    (Some(rhs), Some(lhs)) => (rhs, lhs),
+                    _ => break
+                };
+
+                // a NaN operand makes the result the canonical NaN, and equal floats are zeros of either sign unless
+                // they have the same bits, where the negative zero is the lesser
+                let maximum = matches!(op, Operator::F32Max | Operator::F64Max);
+                let equal = if maximum { lhs & rhs } else { lhs | rhs };
+                match op {
+                    Operator::F32Copysign => f32::from_bits(lhs as u32).copysign(f32::from_bits(rhs as u32)).to_bits() as u64,
+                    Operator::F64Copysign => f64::from_bits(lhs).copysign(f64::from_bits(rhs)).to_bits(),
+                    Operator::F32Min | Operator::F32Max => {
+                        let (lhs, rhs) = (f32::from_bits(lhs as u32), f32::from_bits(rhs as u32));
+                        match (lhs.is_nan() || rhs.is_nan(), lhs == rhs, maximum) {
+                            (true, _, _) => 0x7fc0_0000,
+                            (false, true, _) => equal,
+                            (false, false, false) => lhs.min(rhs).to_bits() as u64,
+                            (false, false, true) => lhs.max(rhs).to_bits() as u64
+                        }
+                    }
+                    _ => {
+                        let (lhs, rhs) = (f64::from_bits(lhs), f64::from_bits(rhs));
+                        match (lhs.is_nan() || rhs.is_nan(), lhs == rhs, maximum) {
+                            (true, _, _) => 0x7ff8_0000_0000_0000,
+                            (false, true, _) => equal,
+                            (false, false, false) => lhs.min(rhs).to_bits(),
+                            (false, false, true) => lhs.max(rhs).to_bits()
+                        }
+                    }
+                }
+            }
+            Operator::I32WrapI64 | Operator::I64ExtendSI32 | Operator::I64ExtendUI32 | Operator::I32Extend8S
+            | Operator::I32Extend16S | Operator::I64Extend8S | Operator::I64Extend16S | Operator::I64Extend32S => {
+                let operand = match stack.pop() {
+                    Some(operand) => operand,
+                    None => break
+                };
+                match op {
+                    Operator::I32WrapI64 | Operator::I64ExtendUI32 => operand as u32 as u64,
+                    Operator::I64ExtendSI32 | Operator::I64Extend32S => operand as i32 as i64 as u64,
+                    Operator::I32Extend8S => operand as i8 as i32 as u32 as u64,
+                    Operator::I32Extend16S => operand as i16 as i32 as u32 as u64,
+                    Operator::I64Extend8S => operand as i8 as i64 as u64,
+                    _ => operand as i16 as i64 as u64
+                }
+            }
+
+            // control flow and everything else ends the straight-line code
+            _ => break
+        };
+        values.insert(i, value);
+        stack.push(value);
+    }
+    values
+}
+
+
+// wraps a random value to the range of the given type
+fn wrap(ty:Type, value:u64) -> u64 {
+    match ty {
+        Type::I32 | Type::F32 => value as u32 as u64,
+        _ => value
+    }
+}
+
+
+// returns the next value of a xorshift generator, which is plenty for picking test inputs
+pub(crate) fn next_random(state:&mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallelize::{new_mapper, AbstractExpression, SilentObserver};
+    use ids::NodeId;
+
+    #[test]
+    fn roundtrip() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/locals.wasm").to_vec());
+
+        // the zero local, the parameter and their sum are checked until the block is reached
+        let (comparisons, mismatch) = validate_node(&nodes[&NodeId(0)], 4, 7);
+        assert_eq!(comparisons, 12);
+        assert_eq!(mismatch, None);
+
+        // a wrongly recorded constant is caught where it is read
+        let mut node = nodes[&NodeId(0)].clone();
+        node.add_operation(1, AbstractExpression::Num{ val: 5 });
+        let (_, mismatch) = validate_node(&node, 4, 7);
+        let mismatch = mismatch.unwrap();
+        assert_eq!((mismatch.location, mismatch.expected, mismatch.found), (1, 0, 5));
+    }
+
+    #[test]
+    fn conversion_chains() {
+        let mut mapper = new_mapper();
+        mapper.set_observer(Box::new(SilentObserver));
+        let nodes = mapper.map(include_bytes!("../tests/parallelization/chains.wasm").to_vec());
+
+        // roundtrip sign extends the low word of its parameter, widen adds a zero extended word to a double word
+        // and narrow wraps the sum of a double word and a sign extended word
+        let natives:[fn(u64, u64) -> u64; 3] = [
+            |x, _| x as u32 as i32 as i64 as u64,
+            |a, x| (a as u32 as u64).wrapping_add(x),
+            |x, a| x.wrapping_add(a as u32 as i32 as i64 as u64) as u32 as u64
+        ];
+
+        // the values around both sign bits come first, then random ones
+        let edges = [0, 1, 0x7fff_ffff, 0x8000_0000, 0xffff_ffff, 0x1_0000_0000, 0x8000_0000_0000_0000, u64::MAX];
+        let mut random = 0x2545_f491_4f6c_dd1d;
+        let mut pairs:Vec<(u64, u64)> = edges.iter().flat_map(|first| edges.iter().map(move |second| (*first, *second))).collect();
+        pairs.extend((0..256).map(|_| (next_random(&mut random), next_random(&mut random))));
+
+        for (index, native) in natives.iter().enumerate() {
+            let node = &nodes[&NodeId(index)];
+            let locals = node.get_locals();
+            let variables = node.get_local_input_variables();
+            for &(first, second) in pairs.iter() {
+                let (first, second) = (wrap(locals[0], first), locals.get(1).map_or(0, |ty| wrap(*ty, second)));
+                let inputs:HashMap<usize, u64> = variables.iter()
+                    .map(|(param, var_id)| (*var_id, if *param == 0 { first } else { second }))
+                    .collect();
+                assert_eq!(interp::evaluate(node, &inputs).result(), Some(native(first, second)), "{} with {:#x}, {:#x}", index, first, second);
+            }
+
+            // the original code agrees at every location, not just on the result
+            assert_eq!(validate_node(node, 64, index as u64 + 1).1, None);
+        }
+    }
+}
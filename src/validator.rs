@@ -624,7 +624,14 @@ impl<'a> ValidatingParser<'a> {
             }
             _ => panic!("Invalid reader state"),
         };
-        let reader = self.create_binary_reader();
+        // the body starts with the locals declarations that were just read, so the
+        // operators follow them
+        let mut reader = self.create_binary_reader();
+        let local_count = reader.read_var_u32().expect("locals declarations");
+        for _ in 0..local_count {
+            reader.skip_var_32().expect("locals declarations");
+            reader.skip_type().expect("locals declarations");
+        }
         ValidatingOperatorParser::new(operator_validator, reader, func_body_offset)
     }
 }
@@ -708,6 +715,10 @@ impl<'b> ValidatingOperatorParser<'b> {
         self.reader.current_position()
     }
 
+    pub fn original_position(&self) -> usize {
+        self.reader.original_position()
+    }
+
     pub fn is_dead_code(&self) -> bool {
         self.operator_validator.is_dead_code()
     }
@@ -0,0 +1,246 @@
+//! # Visit
+//! Passes over a function's code mostly care about a few kinds of operators: the structure, the branches,
+//! the calls, the memory accesses or the arithmetic. An operator visitor has a method for each kind, which
+//! the operators are dispatched to, so that a pass only implements the kinds it records instead of matching
+//! on every operator again. The mapper's first pass is a visitor driven by the validating parser, while
+//! passes over code that was already read can walk it with a plain binary reader
+
+use crate::{BinaryReader, Operator, Result};
+use support::operator_name;
+
+
+/// The flow enum tells the driver whether to read on after an
+/// operator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Continue, // read the next operator
+    Stop // the visitor is done with the code it was reading, e.g. at the end closing it
+}
+
+
+/// An operator visitor is called for each operator read, by the
+/// location of the operator in the code, counting from 1. Every kind
+/// falls back to visit_operator, which ignores the operator unless
+/// overridden
+pub trait OperatorVisitor {
+    // called for any operator whose kind isn't overridden
+    fn visit_operator(&mut self, _i:usize, _op:&Operator) -> Flow {
+        Flow::Continue
+    }
+
+    // called for nops, blocks, loops, ifs, elses, ends, unreachables, returns, drops and selects
+    fn visit_control(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for unconditional, conditional and table branches
+    fn visit_branch(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for direct and indirect calls
+    fn visit_call(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for reads and writes of locals and globals
+    fn visit_variable(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for scalar constants
+    fn visit_const(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for scalar loads, including atomic ones
+    fn visit_load(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for scalar stores, including atomic ones
+    fn visit_store(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for the size and growth of memory, bulk memory operations and table operations
+    fn visit_memory(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for scalar comparisons, numeric operations and conversions
+    fn visit_arith(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for atomic read-modify-writes, waits and wakes
+    fn visit_atomic(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+
+    // called for operations on 128 bit vectors, including their loads and stores
+    fn visit_simd(&mut self, i:usize, op:&Operator) -> Flow {
+        self.visit_operator(i, op)
+    }
+}
+
+
+/// The operator kind enum names the visitor method an operator is
+/// dispatched to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OperatorKind {
+    Control,
+    Branch,
+    Call,
+    Variable,
+    Const,
+    Load,
+    Store,
+    Memory,
+    Arith,
+    Atomic,
+    Simd,
+    Other // references, which no pass records yet
+}
+
+
+// returns the kind of an operator
+pub fn kind(op:&Operator) -> OperatorKind {
+    match *op {
+        Operator::Unreachable
+        | Operator::Nop
+        | Operator::Block { .. }
+        | Operator::Loop { .. }
+        | Operator::If { .. }
+        | Operator::Else
+        | Operator::End
+        | Operator::Return
+        | Operator::Drop
+        | Operator::Select => OperatorKind::Control,
+        Operator::Br { .. }
+        | Operator::BrIf { .. }
+        | Operator::BrTable { .. } => OperatorKind::Branch,
+        Operator::Call { .. }
+        | Operator::CallIndirect { .. } => OperatorKind::Call,
+        Operator::GetLocal { .. }
+        | Operator::SetLocal { .. }
+        | Operator::TeeLocal { .. }
+        | Operator::GetGlobal { .. }
+        | Operator::SetGlobal { .. } => OperatorKind::Variable,
+        Operator::I32Const { .. }
+        | Operator::I64Const { .. }
+        | Operator::F32Const { .. }
+        | Operator::F64Const { .. } => OperatorKind::Const,
+        Operator::RefNull
+        | Operator::RefIsNull => OperatorKind::Other,
+        _ => {
+            let name = operator_name(op);
+            if name.starts_with("V128") || ["x16", "x8", "x4", "x2"].iter().any(|lanes| name.contains(lanes)) {
+                OperatorKind::Simd
+            } else if name.contains("Load") {
+                OperatorKind::Load
+            } else if name.contains("Store") {
+                OperatorKind::Store
+            } else if name.contains("Atomic") || name == "Wake" || name.ends_with("Wait") {
+                OperatorKind::Atomic
+            } else if name.starts_with("Memory") || name.starts_with("Table") || name.ends_with("Drop") {
+                OperatorKind::Memory
+            } else {
+                OperatorKind::Arith
+            }
+        }
+    }
+}
+
+
+// calls the visitor method for the kind of an operator
+pub fn dispatch<V:OperatorVisitor + ?Sized>(visitor:&mut V, i:usize, op:&Operator) -> Flow {
+    match kind(op) {
+        OperatorKind::Control => visitor.visit_control(i, op),
+        OperatorKind::Branch => visitor.visit_branch(i, op),
+        OperatorKind::Call => visitor.visit_call(i, op),
+        OperatorKind::Variable => visitor.visit_variable(i, op),
+        OperatorKind::Const => visitor.visit_const(i, op),
+        OperatorKind::Load => visitor.visit_load(i, op),
+        OperatorKind::Store => visitor.visit_store(i, op),
+        OperatorKind::Memory => visitor.visit_memory(i, op),
+        OperatorKind::Arith => visitor.visit_arith(i, op),
+        OperatorKind::Atomic => visitor.visit_atomic(i, op),
+        OperatorKind::Simd => visitor.visit_simd(i, op),
+        OperatorKind::Other => visitor.visit_operator(i, op)
+    }
+}
+
+
+// reads operators until the reader runs out or the visitor stops, dispatching each of them to the visitor;
+// returns how many were read, or the first error reading them
+pub fn walk<V:OperatorVisitor + ?Sized>(reader:&mut BinaryReader, visitor:&mut V) -> Result<usize> {
+    let mut i = 0;
+    while !reader.eof() {
+        let op = reader.read_operator()?;
+        i += 1;
+        if dispatch(visitor, i, &op) == Flow::Stop {
+            break;
+        }
+    }
+    Ok(i)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryImmediate;
+
+    // records the kind of every operator it is called for, up to the first end
+    #[derive(Default)]
+    struct Recorder {
+        kinds: Vec<(usize, OperatorKind)>
+    }
+
+    impl OperatorVisitor for Recorder {
+        fn visit_operator(&mut self, i:usize, op:&Operator) -> Flow {
+            self.kinds.push((i, kind(op)));
+            Flow::Continue
+        }
+
+        fn visit_control(&mut self, i:usize, op:&Operator) -> Flow {
+            self.visit_operator(i, op);
+            if let Operator::End = *op { Flow::Stop } else { Flow::Continue }
+        }
+    }
+
+    #[test]
+    fn kinds() {
+        let memarg = || MemoryImmediate { flags: 2, offset: 0 };
+        let kinds:Vec<OperatorKind> = [
+            Operator::BrIf { relative_depth: 0 },
+            Operator::GetGlobal { global_index: 0 },
+            Operator::I32Load8U { memarg: memarg() },
+            Operator::I64AtomicStore32 { memarg: memarg() },
+            Operator::I32AtomicRmwAdd { memarg: memarg() },
+            Operator::V128Load { memarg: memarg() },
+            Operator::MemoryCopy,
+            Operator::DataDrop { segment: 0 },
+            Operator::I64ExtendSI32,
+            Operator::RefNull
+        ].iter().map(kind).collect();
+        assert_eq!(kinds, vec![
+            OperatorKind::Branch, OperatorKind::Variable, OperatorKind::Load, OperatorKind::Store, OperatorKind::Atomic,
+            OperatorKind::Simd, OperatorKind::Memory, OperatorKind::Memory, OperatorKind::Arith, OperatorKind::Other
+        ]);
+    }
+
+    #[test]
+    fn walk() {
+
+        // i32.const 1, i32.const 2, i32.add, call 0, end, nop
+        let code = [0x41, 0x01, 0x41, 0x02, 0x6a, 0x10, 0x00, 0x0b, 0x01];
+        let mut recorder = Recorder::default();
+        let read = super::walk(&mut BinaryReader::new(&code), &mut recorder).unwrap();
+        assert_eq!(read, 5);
+        assert_eq!(recorder.kinds, vec![
+            (1, OperatorKind::Const), (2, OperatorKind::Const), (3, OperatorKind::Arith), (4, OperatorKind::Call), (5, OperatorKind::Control)
+        ]);
+    }
+}
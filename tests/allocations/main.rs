@@ -0,0 +1,25 @@
+//! The counting allocator is installed process-wide, so it is tested in a binary of its own rather than
+//! in the library's tests, where it would count every other test's allocations as well
+#![cfg(feature = "pfc")]
+
+extern crate wasmparser;
+
+use wasmparser::parallelize::{new_mapper, SilentObserver};
+use wasmparser::profile::{self, CountingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn pass_allocations() {
+    let (allocated, _) = profile::allocations();
+    let mut mapper = new_mapper();
+    mapper.set_observer(Box::new(SilentObserver));
+    mapper.map(include_bytes!("../parallelization/inline.wasm").to_vec());
+    assert!(profile::allocations().0 > allocated);
+
+    // reading the module and expanding it allocate the nodes
+    let profile = mapper.profile();
+    let expand = profile.passes().iter().find(|timing| timing.name == "expand").unwrap();
+    assert!(profile.passes()[0].allocated > 0 && expand.allocated > 0);
+}
@@ -0,0 +1,39 @@
+(module
+    (memory 1 1 shared)
+    (func $bump (result i32)
+        (i32.atomic.rmw.add offset=16
+            (i32.const 0)
+            (i32.const 1)
+        )
+    )
+    (func $swap (result i32)
+        (i32.atomic.rmw.cmpxchg offset=16
+            (i32.const 0)
+            (i32.const 1)
+            (i32.const 2)
+        )
+    )
+    (func $park (result i32)
+        (memory.atomic.wait32 offset=32
+            (i32.const 0)
+            (i32.const 0)
+            (i64.const -1)
+        )
+    )
+    (func $signal (result i32)
+        (memory.atomic.notify offset=32
+            (i32.const 0)
+            (i32.const 1)
+        )
+    )
+    (func $peek (result i32)
+        (i32.load offset=16
+            (i32.const 0)
+        )
+    )
+    (export "bump" (func $bump))
+    (export "swap" (func $swap))
+    (export "park" (func $park))
+    (export "signal" (func $signal))
+    (export "peek" (func $peek))
+)
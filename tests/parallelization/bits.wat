@@ -0,0 +1,22 @@
+(module
+    (func $bucket (param i32) (result i32)
+        (i32.clz (local.get 0))
+    )
+    (func $lowest (param i32) (result i32)
+        (i32.ctz (local.get 0))
+    )
+    (func $weight (param i64) (result i64)
+        (i64.popcnt (local.get 0))
+    )
+    (func $folded (result i32)
+        (i32.popcnt (i32.const 0xff00))
+    )
+    (func $mix (param i32) (result i32)
+        (i32.add (i32.clz (local.get 0)) (i32.popcnt (local.get 0)))
+    )
+    (export "bucket" (func $bucket))
+    (export "lowest" (func $lowest))
+    (export "weight" (func $weight))
+    (export "folded" (func $folded))
+    (export "mix" (func $mix))
+)
@@ -0,0 +1,31 @@
+(module
+    (func $sum (param $x i32) (result i32) (local $y i32)
+        (block
+            (local.set $y
+                (i32.const 3)
+            )
+        )
+        (block (result i32)
+            (i32.add
+                (local.get $x)
+                (local.get $y)
+            )
+        )
+    )
+    (func $spin (param $x i32)
+        (loop $again
+            (block
+                (nop)
+            )
+            (if
+                (local.get $x)
+                (then
+                    (return)
+                )
+            )
+            (br $again)
+        )
+    )
+    (export "sum" (func $sum))
+    (export "spin" (func $spin))
+)
@@ -0,0 +1,37 @@
+(module
+    (func $pick (param $c i32) (param $x i32) (result i32)
+        (nop)
+        (nop)
+        (nop)
+        (nop)
+        (if (result i32)
+            (local.get $c)
+            (then
+                (i32.add
+                    (local.get $x)
+                    (i32.const 1)
+                )
+            )
+            (else
+                (i32.mul
+                    (local.get $x)
+                    (i32.const 2)
+                )
+            )
+        )
+    )
+    (func $pick_twice (param $x i32) (result i32)
+        (i32.add
+            (call $pick
+                (i32.const 1)
+                (local.get $x)
+            )
+            (call $pick
+                (i32.const 0)
+                (local.get $x)
+            )
+        )
+    )
+    (export "pick_twice" (func $pick_twice))
+    (export "pick" (func $pick))
+)
@@ -0,0 +1,30 @@
+(module
+    (memory 1)
+    (func $copy
+        (memory.copy
+            (i32.const 64)
+            (i32.const 16)
+            (i32.const 4)
+        )
+    )
+    (func $fill (param i32)
+        (memory.fill
+            (local.get 0)
+            (i32.const 0)
+            (i32.const 8)
+        )
+    )
+    (func $copy_sum
+        (memory.copy
+            (i32.const 128)
+            (i32.add
+                (i32.const 8)
+                (i32.const 8)
+            )
+            (i32.const 4)
+        )
+    )
+    (export "copy" (func $copy))
+    (export "fill" (func $fill))
+    (export "copy_sum" (func $copy_sum))
+)
@@ -0,0 +1,22 @@
+(module
+    (func $roundtrip (param i64) (result i64)
+        (i64.extend_i32_s (i32.wrap_i64 (local.get 0)))
+    )
+    (func $widen (param i32 i64) (result i64)
+        (i64.add
+            (i64.extend_i32_u (local.get 0))
+            (local.get 1)
+        )
+    )
+    (func $narrow (param i64 i32) (result i32)
+        (i32.wrap_i64
+            (i64.add
+                (local.get 0)
+                (i64.extend_i32_s (local.get 1))
+            )
+        )
+    )
+    (export "roundtrip" (func $roundtrip))
+    (export "widen" (func $widen))
+    (export "narrow" (func $narrow))
+)
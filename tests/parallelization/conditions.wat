@@ -0,0 +1,29 @@
+(module
+    (func $picked (param i32 i32) (result i32)
+        (if (result i32)
+            (i32.add (local.get 0) (local.get 1))
+            (then (i32.const 1))
+            (else (i32.const 2))
+        )
+    )
+    (func $halted (param i32) (result i32)
+        (block
+            (br_if 0 (i32.mul (local.get 0) (i32.const 3)))
+        )
+        (local.get 0)
+    )
+    (func $chosen (param i32 i32 i32) (result i32)
+        (select (local.get 0) (local.get 1) (local.get 2))
+    )
+    (func $called (param i32) (result i32)
+        (if (result i32)
+            (call $picked (local.get 0) (local.get 0))
+            (then (i32.const 1))
+            (else (i32.const 0))
+        )
+    )
+    (export "picked" (func $picked))
+    (export "halted" (func $halted))
+    (export "chosen" (func $chosen))
+    (export "called" (func $called))
+)
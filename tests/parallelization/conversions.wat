@@ -0,0 +1,20 @@
+(module
+    (func $narrow (param i32 i64) (result i64)
+        (i64.add
+            (i64.extend_i32_s (i32.extend8_s (local.get 0)))
+            (i64.extend16_s (local.get 1))
+        )
+    )
+    (func $saturate (param f32) (result i32)
+        (i32.trunc_sat_f32_u (local.get 0))
+    )
+    (func $wrap (param i64) (result i32)
+        (i32.add
+            (i32.wrap_i64 (local.get 0))
+            (i32.wrap_i64 (i64.const 0x100000002))
+        )
+    )
+    (export "narrow" (func $narrow))
+    (export "saturate" (func $saturate))
+    (export "wrap" (func $wrap))
+)
@@ -0,0 +1,27 @@
+(module
+    (func $leaf (param i32) (result i32)
+        (i32.mul
+            (local.get 0)
+            (i32.const 3)
+        )
+    )
+    (func $left (param i32) (result i32)
+        (i32.add
+            (call $leaf (local.get 0))
+            (i32.const 1)
+        )
+    )
+    (func $right (param i32) (result i32)
+        (i32.sub
+            (call $leaf (local.get 0))
+            (i32.const 1)
+        )
+    )
+    (func $top (param i32) (result i32)
+        (i32.add
+            (call $left (local.get 0))
+            (call $right (local.get 0))
+        )
+    )
+    (export "top" (func $top))
+)
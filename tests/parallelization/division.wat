@@ -0,0 +1,26 @@
+(module
+    (func $eighth (param i32) (result i32)
+        (i32.div_u (local.get 0) (i32.const 8))
+    )
+    (func $low (param i32) (result i32)
+        (i32.rem_u (local.get 0) (i32.const 16))
+    )
+    (func $tenth (param i64) (result i64)
+        (i64.div_u (local.get 0) (i64.const 10))
+    )
+    (func $third (param i32) (result i32)
+        (i32.div_u (local.get 0) (i32.const 3))
+    )
+    (func $seventh (param i32) (result i32)
+        (i32.div_u (local.get 0) (i32.const 7))
+    )
+    (func $ratio (param i32 i32) (result i32)
+        (i32.rem_u (local.get 0) (local.get 1))
+    )
+    (export "eighth" (func $eighth))
+    (export "low" (func $low))
+    (export "tenth" (func $tenth))
+    (export "third" (func $third))
+    (export "seventh" (func $seventh))
+    (export "ratio" (func $ratio))
+)
@@ -0,0 +1,25 @@
+(module
+    (func $double (param i32) (result i32)
+        (i32.add
+            (local.get 0)
+            (local.get 0)
+        )
+    )
+    (func $scale (param i32 i32) (result i32)
+        (i32.mul
+            (local.get 0)
+            (i32.add
+                (local.get 1)
+                (i32.const 3)
+            )
+        )
+    )
+    (func $main (param i32) (result i32)
+        (i32.add
+            (call $double (local.get 0))
+            (call $scale (i32.const 2) (local.get 0))
+        )
+    )
+    (export "main" (func $main))
+    (@custom ".debug_line" (after last) "Q\00\00\00\04\00#\00\00\00\01\01\01\fb\0e\0d\00\01\01\01\01\00\00\00\01\00\00\01src\00\00dwarf.c\00\01\00\00\00\00\05\02\02\00\00\00\03\02\01\00\05\02\0a\00\00\00\03\06\01\00\05\02\15\00\00\00\03\0b\01\00\05\02\22\00\00\00\00\01\01")
+)
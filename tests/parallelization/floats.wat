@@ -0,0 +1,22 @@
+(module
+    (func $smaller (param f32 f32) (result f32)
+        (f32.min (local.get 0) (local.get 1))
+    )
+    (func $larger (param f64 f64) (result f64)
+        (f64.max (local.get 0) (local.get 1))
+    )
+    (func $magnitude (param f32) (result f32)
+        (f32.abs (local.get 0))
+    )
+    (func $flip (param f64) (result f64)
+        (f64.neg (local.get 0))
+    )
+    (func $signed (param f32 f32) (result f32)
+        (f32.copysign (local.get 0) (local.get 1))
+    )
+    (export "smaller" (func $smaller))
+    (export "larger" (func $larger))
+    (export "magnitude" (func $magnitude))
+    (export "flip" (func $flip))
+    (export "signed" (func $signed))
+)
@@ -0,0 +1,16 @@
+(module
+    (global $total (mut i32) (i32.const 0))
+    (func $accumulate (param $x i32) (result i32)
+        (global.set $total
+            (i32.add
+                (global.get $total)
+                (local.get $x)
+            )
+        )
+        (i32.mul
+            (global.get $total)
+            (global.get $total)
+        )
+    )
+    (export "accumulate" (func $accumulate))
+)
@@ -0,0 +1,24 @@
+(module
+    (func $double (param i32) (result i32)
+        (i32.add
+            (local.get 0)
+            (local.get 0)
+        )
+    )
+    (func $scale (param i32 i32) (result i32)
+        (i32.mul
+            (local.get 0)
+            (i32.add
+                (local.get 1)
+                (i32.const 3)
+            )
+        )
+    )
+    (func $main (param i32) (result i32)
+        (i32.add
+            (call $double (local.get 0))
+            (call $scale (i32.const 2) (local.get 0))
+        )
+    )
+    (export "main" (func $main))
+)
@@ -0,0 +1,17 @@
+(module
+    (func $increment (param i32) (result i32)
+        (block (result i32)
+            (i32.add
+                (local.get 0)
+                (i32.const 1)
+            )
+        )
+    )
+    (func $scale (param i32 i32) (result i32)
+        (i32.mul
+            (local.get 0)
+            (call $increment (local.get 1))
+        )
+    )
+    (export "scale" (func $scale))
+)
@@ -0,0 +1,14 @@
+(module
+    (import "library" "scale" (func $scale (param i32 i32) (result i32)))
+    (import "env" "log" (func $log (param i32)))
+    (func $main (param i32) (result i32)
+        (call $log (local.get 0))
+        (block (result i32)
+            (call $scale
+                (local.get 0)
+                (i32.const 2)
+            )
+        )
+    )
+    (export "main" (func $main))
+)
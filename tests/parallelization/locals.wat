@@ -0,0 +1,17 @@
+(module
+    (func $accumulate (param $x i32) (result i32) (local $wide i64) (local $ratio f32) (local $total i32)
+        (drop
+            (i32.add
+                (local.get $total)
+                (local.get $x)
+            )
+        )
+        (block
+            (local.set $total
+                (i32.const 1)
+            )
+        )
+        (local.get $total)
+    )
+    (export "accumulate" (func $accumulate))
+)
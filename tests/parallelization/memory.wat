@@ -0,0 +1,26 @@
+(module
+    (memory 1)
+    (func $produce (result i32)
+        (i32.store offset=16
+            (i32.const 0)
+            (i32.load offset=32
+                (i32.const 0)
+            )
+        )
+        (i32.load offset=16
+            (i32.const 0)
+        )
+    )
+    (func $consume
+        (i32.store offset=16
+            (i32.const 0)
+            (i32.const 1)
+        )
+        (i32.store offset=32
+            (i32.const 0)
+            (i32.const 2)
+        )
+    )
+    (export "produce" (func $produce))
+    (export "consume" (func $consume))
+)
@@ -0,0 +1,13 @@
+(module
+    (memory 2 4)
+    (func $top (result i32)
+        (i32.load offset=0
+            (i32.mul
+                (memory.size)
+                (i32.const 65532)
+            )
+        )
+    )
+    (export "top" (func $top))
+    (export "memory" (memory 0))
+)
@@ -0,0 +1,18 @@
+(module
+    (memory 2 4)
+    (func $top (result i32)
+        (i32.load offset=0
+            (i32.mul
+                (memory.size)
+                (i32.const 65532)
+            )
+        )
+    )
+    (func $grow (result i32)
+        (memory.grow
+            (i32.const 1)
+        )
+    )
+    (export "top" (func $top))
+    (export "grow" (func $grow))
+)
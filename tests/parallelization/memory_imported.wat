@@ -0,0 +1,12 @@
+(module
+    (import "env" "memory" (memory 2 4))
+    (func $top (result i32)
+        (i32.load offset=0
+            (i32.mul
+                (memory.size)
+                (i32.const 65532)
+            )
+        )
+    )
+    (export "top" (func $top))
+)
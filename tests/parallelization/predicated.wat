@@ -0,0 +1,32 @@
+(module
+    (func $pick_wide (param $c i32) (param $x i64) (result i64)
+        (if (result i64)
+            (local.get $c)
+            (then
+                (i64.add
+                    (local.get $x)
+                    (i64.const 1)
+                )
+            )
+            (else
+                (i64.add
+                    (local.get $x)
+                    (i64.const 2)
+                )
+            )
+        )
+    )
+    (func $pick_float (param $c i32) (param $x f32) (param $y f32) (result f32)
+        (if (result f32)
+            (local.get $c)
+            (then
+                (local.get $x)
+            )
+            (else
+                (local.get $y)
+            )
+        )
+    )
+    (export "pick_wide" (func $pick_wide))
+    (export "pick_float" (func $pick_float))
+)
@@ -0,0 +1,18 @@
+(module
+    (memory 1)
+    (data (i32.const 16) "\01\02\03\07")
+    (func $tag (param i32) (result i32)
+        (i32.add
+            (i32.load8_u offset=16 (local.get 0))
+            (i32.const 5)
+        )
+    )
+    (func $scale (param i32 i32) (result i32)
+        (i32.mul
+            (local.get 0)
+            (local.get 1)
+        )
+    )
+    (export "tag" (func $tag))
+    (export "scale" (func $scale))
+)
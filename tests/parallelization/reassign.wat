@@ -0,0 +1,24 @@
+(module
+    (func $reassign (param $x i32) (result i32)
+        (local.set $x
+            (i32.const 5)
+        )
+        (local.get $x)
+    )
+    (func $tee (param $x i32) (result i32)
+        (drop
+            (local.tee $x
+                (i32.const 2)
+            )
+        )
+        (local.set $x
+            (i32.const 7)
+        )
+        (i32.add
+            (local.get $x)
+            (local.get $x)
+        )
+    )
+    (export "reassign" (func $reassign))
+    (export "tee" (func $tee))
+)
@@ -0,0 +1,15 @@
+(module
+    (func $countdown (param i32) (result i32)
+        (i32.add
+            (local.get 0)
+            (call $countdown (local.get 0))
+        )
+    )
+    (func $nested (param i32) (result i32)
+        (block (result i32)
+            (call $nested (i32.const 1))
+        )
+    )
+    (export "countdown" (func $countdown))
+    (export "nested" (func $nested))
+)
@@ -0,0 +1,13 @@
+(module
+    (func $bits (param f32) (result i32)
+        (i32.add
+            (i32.reinterpret_f32 (local.get 0))
+            (i32.const 1)
+        )
+    )
+    (func $float (param i64) (result f64)
+        (f64.reinterpret_i64 (local.get 0))
+    )
+    (export "bits" (func $bits))
+    (export "float" (func $float))
+)
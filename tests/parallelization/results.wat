@@ -0,0 +1,18 @@
+(module
+    (func $stash (param $x i32) (result i32)
+        (local $y i32)
+        (local.set $y
+            (block (result i32)
+                (i32.add
+                    (local.get $x)
+                    (i32.const 3)
+                )
+            )
+        )
+        (i32.mul
+            (local.get $y)
+            (i32.const 2)
+        )
+    )
+    (export "stash" (func $stash))
+)
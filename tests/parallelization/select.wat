@@ -0,0 +1,17 @@
+(module
+    (func $select (param $c i32) (param $a i32) (param $b i32) (result i32)
+        (if (result i32)
+            (local.get $c)
+            (then
+                (i32.add
+                    (local.get $a)
+                    (i32.const 1)
+                )
+            )
+            (else
+                (local.get $b)
+            )
+        )
+    )
+    (export "select" (func $select))
+)
@@ -0,0 +1,26 @@
+(module
+    (func $scaled (param i32 i32) (result i32)
+        (i32.shl (local.get 0) (local.get 1))
+    )
+    (func $halved (param i64 i64) (result i64)
+        (i64.shr_s (local.get 0) (local.get 1))
+    )
+    (func $spread (param i32 i32) (result i32)
+        (i32.shr_u (local.get 0) (local.get 1))
+    )
+    (func $spun (param i64 i64) (result i64)
+        (i64.rotl (local.get 0) (local.get 1))
+    )
+    (func $turned (param i32) (result i32)
+        (i32.rotr (local.get 0) (i32.const 8))
+    )
+    (func $doubled (param i32) (result i32)
+        (i32.shl (local.get 0) (i32.const 35))
+    )
+    (export "scaled" (func $scaled))
+    (export "halved" (func $halved))
+    (export "spread" (func $spread))
+    (export "spun" (func $spun))
+    (export "turned" (func $turned))
+    (export "doubled" (func $doubled))
+)
@@ -0,0 +1,28 @@
+(module
+    (memory 1)
+    (func $scale (result i32)
+        (v128.store offset=32
+            (i32.const 0)
+            (i32x4.mul
+                (v128.load offset=16 (i32.const 0))
+                (i32x4.splat (i32.const 3))
+            )
+        )
+        (i32x4.extract_lane 2
+            (v128.load offset=32 (i32.const 0))
+        )
+    )
+    (func $swizzle (param i32) (result i32)
+        (i32x4.extract_lane 1
+            (i32x4.replace_lane 1
+                (i8x16.shuffle 4 5 6 7 0 1 2 3 12 13 14 15 8 9 10 11
+                    (v128.load offset=16 (i32.const 0))
+                    (v128.load offset=48 (i32.const 0))
+                )
+                (local.get 0)
+            )
+        )
+    )
+    (export "scale" (func $scale))
+    (export "swizzle" (func $swizzle))
+)
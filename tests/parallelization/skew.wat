@@ -0,0 +1,27 @@
+(module
+    (func $base (param i32) (result i32)
+        (i32.add
+            (local.get 0)
+            (i32.const 7)
+        )
+    )
+    (func $leaf (param i32) (result i32)
+        (i32.mul
+            (call $base (local.get 0))
+            (i32.const 3)
+        )
+    )
+    (func $left (param i32) (result i32)
+        (i32.add
+            (call $leaf (local.get 0))
+            (i32.const 1)
+        )
+    )
+    (func $top (param i32) (result i32)
+        (i32.add
+            (call $left (local.get 0))
+            (call $leaf (local.get 0))
+        )
+    )
+    (export "top" (func $top))
+)
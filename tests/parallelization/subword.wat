@@ -0,0 +1,17 @@
+(module
+    (memory 1)
+    (func $widen (result i32)
+        (i32.add
+            (i32.load8_s offset=0 (i32.const 0))
+            (i32.load16_u offset=2 (i32.const 0))
+        )
+    )
+    (func $widen64 (result i64)
+        (i64.add
+            (i64.load32_s offset=4 (i32.const 0))
+            (i64.load offset=8 (i32.const 0))
+        )
+    )
+    (export "widen" (func $widen))
+    (export "widen64" (func $widen64))
+)
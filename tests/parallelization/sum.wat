@@ -0,0 +1,15 @@
+(module
+    (func $sum (param i32 i32 i32 i32) (result i32)
+        (i32.add
+            (i32.add
+                (local.get 0)
+                (local.get 1)
+            )
+            (i32.add
+                (local.get 2)
+                (local.get 3)
+            )
+        )
+    )
+    (export "sum" (func $sum))
+)
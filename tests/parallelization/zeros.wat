@@ -0,0 +1,26 @@
+(module
+    (func $empty (param i32) (result i32)
+        (i32.eqz (local.get 0))
+    )
+    (func $vacant (param i64) (result i32)
+        (i64.eqz (local.get 0))
+    )
+    (func $guard (param i32 i32) (result i32)
+        (if (result i32)
+            (i32.eqz (local.get 0))
+            (then (local.get 1))
+            (else (i32.const 7))
+        )
+    )
+    (func $skip (param i32 i32) (result i32)
+        (block
+            (br_if 0 (i32.eqz (local.get 0)))
+            (br_if 0 (local.get 1))
+        )
+        (local.get 1)
+    )
+    (export "empty" (func $empty))
+    (export "vacant" (func $vacant))
+    (export "guard" (func $guard))
+    (export "skip" (func $skip))
+)